@@ -126,23 +126,33 @@ impl SceneLoader {
             // Draw something on the terrain.
 
             // Pull terrain.
-            terrain.draw(&Brush {
-                center: Vector3::new(x, 0.0, z),
-                shape: BrushShape::Circle { radius },
-                mode: BrushMode::ModifyHeightMap { amount: height },
-            });
+            terrain.draw(&Brush::new(
+                Vector3::new(x, 0.0, z),
+                BrushShape::Circle { radius },
+                BrushMode::ModifyHeightMap { amount: height },
+            ));
 
             // Draw rock texture on top.
-            terrain.draw(&Brush {
-                center: Vector3::new(x, 0.0, z),
-                shape: BrushShape::Circle { radius },
-                mode: BrushMode::DrawOnMask {
+            terrain.draw(&Brush::new(
+                Vector3::new(x, 0.0, z),
+                BrushShape::Circle { radius },
+                BrushMode::DrawOnMask {
                     layer: 1,
                     alpha: 1.0,
                 },
-            });
+            ));
         }
 
+        // Smooth out sharp edges of the hills.
+        terrain.draw(&Brush::new(
+            Vector3::new(32.0, 0.0, 32.0),
+            BrushShape::Rectangle {
+                width: 64.0,
+                length: 64.0,
+            },
+            BrushMode::Smooth { amount: 0.5 },
+        ));
+
         // Add some light.
         PointLightBuilder::new(BaseLightBuilder::new(
             BaseBuilder::new().with_local_transform(
@@ -1,10 +1,12 @@
 use rg3d_sound::{
     buffer::{DataSource, SoundBufferResource},
     context::SoundContext,
+    encoder::WavSampleFormat,
+    engine::SoundEngine,
     pool::Handle,
     source::{generic::GenericSourceBuilder, SoundSource, Status},
 };
-use rg3d_sound::engine::SoundEngine;
+use std::time::Duration;
 
 fn main() {
     // Initialize sound engine without output device.
@@ -36,29 +38,10 @@ fn main() {
     // and returns pool handle to it by which it can be accessed later on if needed.
     let _source_handle: Handle<SoundSource> = context.state().add_source(source);
 
-    // Create output wav file. The sample rate is currently fixed.
-    let wav_spec = hound::WavSpec {
-        channels: 2,
-        sample_rate: rg3d_sound::context::SAMPLE_RATE,
-        bits_per_sample: 32,
-        sample_format: hound::SampleFormat::Float,
-    };
-    let mut wav_writer = hound::WavWriter::create("output.wav", wav_spec).unwrap();
-
-    // Create an output buffer.
-    let buf_len = SoundEngine::render_buffer_len();
-    let mut buf = vec![(0.0f32, 0.0f32); buf_len];
-    let mut samples_written = 0;
-
-    // Wait until sound will play completely.
-    while samples_written < 3 * rg3d_sound::context::SAMPLE_RATE {
-        engine.lock().unwrap().render(&mut buf);
-        for &(l, r) in buf.iter() {
-            wav_writer.write_sample(l).unwrap();
-            wav_writer.write_sample(r).unwrap();
-        }
-        samples_written += buf_len as u32;
-    }
-
-    wav_writer.finalize().unwrap();
+    // Render three seconds of sound to output file. The sample rate is currently fixed.
+    engine
+        .lock()
+        .unwrap()
+        .render_offline_to_wav("output.wav", Duration::from_secs(3), WavSampleFormat::Float32)
+        .unwrap();
 }
@@ -20,7 +20,7 @@
 //! }
 //! ```
 
-use crate::{buffer::DataSource, decoder::Decoder};
+use crate::{buffer::DataSource, decoder::Decoder, error::SoundError};
 use rg3d_core::visitor::{Visit, VisitResult, Visitor};
 use std::path::Path;
 use std::{path::PathBuf, time::Duration};
@@ -65,19 +65,20 @@ impl GenericBuffer {
     /// # Notes
     ///
     /// Data source with raw samples must have sample count multiple of channel count, otherwise this
-    /// function will return `Err`.
-    pub fn new(source: DataSource) -> Result<Self, DataSource> {
+    /// function will return `Err`. Raw streaming data source can't be used with generic buffers, this
+    /// function will return [`SoundError::UnsupportedDataSource`] for it.
+    pub fn new(source: DataSource) -> Result<Self, SoundError> {
         match source {
+            DataSource::RawStreaming(_) => Err(SoundError::UnsupportedDataSource),
             DataSource::Raw {
                 sample_rate,
                 channel_count,
                 samples,
             } => {
                 if samples.len() % channel_count != 0 {
-                    Err(DataSource::Raw {
-                        sample_rate,
+                    Err(SoundError::InvalidSampleCount {
+                        sample_count: samples.len(),
                         channel_count,
-                        samples,
                     })
                 } else {
                     Ok(Self {
@@ -7,20 +7,107 @@
 //! this is why each instance wrapped into `Arc<Mutex<>>`. Why not just load a buffer per source? This
 //! is just inefficient memory-wise. Sound samples are very heavy: for example a mono sound that lasts
 //! just 1 second will take ~172 Kb of memory (with 44100 Hz sampling rate and float sample representation).
+//!
+//! # Errors
+//!
+//! Constructors of buffers return [`SoundError`] that describes why the buffer can't be created,
+//! for example [`SoundError::DecoderError`] for corrupted files. Previously they returned the data
+//! source back without a reason, code that used the returned data source must create a new one.
 
-use crate::buffer::{generic::GenericBuffer, streaming::StreamingBuffer};
+use crate::{
+    buffer::{generic::GenericBuffer, streaming::StreamingBuffer},
+    error::SoundError,
+};
 use rg3d_core::{io::FileLoadError, visitor::prelude::*};
 use rg3d_resource::{define_new_resource, Resource, ResourceData, ResourceState};
 use std::{
     borrow::Cow,
+    fmt::Debug,
     io::{Cursor, Read, Seek, SeekFrom},
     ops::{Deref, DerefMut},
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 pub mod generic;
 pub mod streaming;
 
+/// Source of samples that are produced on demand - by a synthesizer, a tone generator, a queue
+/// that is filled from network thread for voice chat, etc. Samples are fetched by
+/// [`Iterator::next`] in interleaved format (`LRLRLR...` for stereo). The source can be used only
+/// with streaming buffers, see [`DataSource::RawStreaming`].
+///
+/// # Notes
+///
+/// The source is called from sound thread, so it should not block. If samples are produced by
+/// some other thread and there is not enough of them, the source should return silence (zeros)
+/// instead of `None` - `None` means that the source has ended, it will be rewound and played
+/// from the beginning if playback is looping or stopped otherwise. Samples are fetched in blocks
+/// of [`StreamingBuffer::RAW_STREAM_SAMPLE_COUNT`] samples per channel, so the source should
+/// produce at least that much samples ahead to prevent gaps. Source must report at least one
+/// channel and non-zero sample rate, otherwise streaming buffer will not accept it.
+///
+/// # Examples
+///
+/// ```
+/// use rg3d_sound::buffer::{DataSource, RawStreamingDataSource, SoundBufferResource};
+///
+/// // Endless mono sine wave.
+/// #[derive(Debug)]
+/// struct Tone {
+///     frequency: f32,
+///     phase: f32,
+/// }
+///
+/// impl Iterator for Tone {
+///     type Item = f32;
+///
+///     fn next(&mut self) -> Option<Self::Item> {
+///         self.phase += self.frequency / 44100.0;
+///         Some((self.phase * 2.0 * std::f32::consts::PI).sin())
+///     }
+/// }
+///
+/// impl RawStreamingDataSource for Tone {
+///     fn sample_rate(&self) -> usize {
+///         44100
+///     }
+///
+///     fn channel_count(&self) -> usize {
+///         1
+///     }
+/// }
+///
+/// fn make_buffer() -> SoundBufferResource {
+///     let tone = Tone {
+///         frequency: 440.0,
+///         phase: 0.0,
+///     };
+///     SoundBufferResource::new_streaming(DataSource::RawStreaming(Box::new(tone))).unwrap()
+/// }
+/// ```
+pub trait RawStreamingDataSource: Iterator<Item = f32> + Send + Debug {
+    /// Returns sample rate of the source, it could differ from sample rate of the engine - the
+    /// source will be resampled.
+    fn sample_rate(&self) -> usize;
+
+    /// Returns amount of channels of the source.
+    fn channel_count(&self) -> usize;
+
+    /// Restarts the source from the beginning. Does nothing by default.
+    fn rewind(&mut self) -> Result<(), SoundError> {
+        Ok(())
+    }
+
+    /// Moves the source to given position. Does nothing by default.
+    fn time_seek(&mut self, _location: Duration) {}
+
+    /// Returns total duration of the source if it is known. Returns `None` by default.
+    fn duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
 /// Data source enumeration. Provides unified way of selecting data source for sound buffers. It can be either
 /// a file or memory block.
 #[derive(Debug)]
@@ -39,7 +126,7 @@ pub enum DataSource {
         data: Cursor<Vec<u8>>,
     },
 
-    /// Data source is a memory block. Memory block must be in valid format (wav or vorbis/ogg). This variant can
+    /// Data source is a memory block. Memory block must be in valid format (wav, vorbis/ogg, flac or mp3). This variant can
     /// be used together with virtual file system.
     Memory(Cursor<Vec<u8>>),
 
@@ -60,6 +147,14 @@ pub enum DataSource {
         /// get error at attempt to use such buffer.
         samples: Vec<f32>,
     },
+
+    /// Samples that are produced on demand by given source, see [`RawStreamingDataSource`].
+    ///
+    /// # Notes
+    ///
+    /// Can be used only with streaming buffers, the source could be endless so it can't be loaded
+    /// into memory.
+    RawStreaming(Box<dyn RawStreamingDataSource>),
 }
 
 impl DataSource {
@@ -95,6 +190,9 @@ impl Read for DataSource {
             DataSource::File { data, .. } => data.read(buf),
             DataSource::Memory(b) => b.read(buf),
             DataSource::Raw { .. } => unreachable!("Raw data source does not supports Read trait!"),
+            DataSource::RawStreaming(_) => {
+                unreachable!("Raw streaming data source does not supports Read trait!")
+            }
         }
     }
 }
@@ -105,6 +203,9 @@ impl Seek for DataSource {
             DataSource::File { data, .. } => data.seek(pos),
             DataSource::Memory(b) => b.seek(pos),
             DataSource::Raw { .. } => unreachable!("Raw data source does not supports Seek trait!"),
+            DataSource::RawStreaming(_) => {
+                unreachable!("Raw streaming data source does not supports Seek trait!")
+            }
         }
     }
 }
@@ -116,6 +217,8 @@ pub enum SoundBufferResourceLoadError {
     UnsupportedFormat,
     /// File load error.
     Io(FileLoadError),
+    /// Data source is corrupted or cannot be decoded, exact reason stored in inner value.
+    DataSourceError(SoundError),
 }
 
 define_new_resource!(
@@ -126,7 +229,7 @@ define_new_resource!(
 impl SoundBufferResource {
     /// Tries to create new streaming sound buffer from a given data source. Returns sound source
     /// wrapped into Arc<Mutex<>> that can be directly used with sound sources.
-    pub fn new_streaming(data_source: DataSource) -> Result<Self, DataSource> {
+    pub fn new_streaming(data_source: DataSource) -> Result<Self, SoundError> {
         Ok(Self(Resource::new(ResourceState::Ok(
             SoundBufferState::Streaming(StreamingBuffer::new(data_source)?),
         ))))
@@ -134,7 +237,7 @@ impl SoundBufferResource {
 
     /// Tries to create new generic sound buffer from a given data source. Returns sound source
     /// wrapped into Arc<Mutex<>> that can be directly used with sound sources.
-    pub fn new_generic(data_source: DataSource) -> Result<Self, DataSource> {
+    pub fn new_generic(data_source: DataSource) -> Result<Self, SoundError> {
         Ok(Self(Resource::new(ResourceState::Ok(
             SoundBufferState::Generic(GenericBuffer::new(data_source)?),
         ))))
@@ -158,13 +261,13 @@ pub enum SoundBufferState {
 impl SoundBufferState {
     /// Tries to create new streaming sound buffer from a given data source. It returns raw sound
     /// buffer that has to be wrapped into Arc<Mutex<>> for use with sound sources.
-    pub fn raw_streaming(data_source: DataSource) -> Result<Self, DataSource> {
+    pub fn raw_streaming(data_source: DataSource) -> Result<Self, SoundError> {
         Ok(Self::Streaming(StreamingBuffer::new(data_source)?))
     }
 
     /// Tries to create new generic sound buffer from a given data source. It returns raw sound
     /// buffer that has to be wrapped into Arc<Mutex<>> for use with sound sources.
-    pub fn raw_generic(data_source: DataSource) -> Result<Self, DataSource> {
+    pub fn raw_generic(data_source: DataSource) -> Result<Self, SoundError> {
         Ok(Self::Generic(GenericBuffer::new(data_source)?))
     }
 }
@@ -208,3 +311,257 @@ impl ResourceData for SoundBufferState {
         self.external_source_path = path;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        buffer::{
+            generic::GenericBuffer, streaming::StreamingBuffer, DataSource, RawStreamingDataSource,
+            SoundBufferResource,
+        },
+        error::SoundError,
+        source::{
+            generic::{GenericSource, GenericSourceBuilder},
+            Status,
+        },
+    };
+
+    // Mono source that produces 0, 1, 2, ... up to given length.
+    #[derive(Debug)]
+    struct Counter {
+        position: usize,
+        length: usize,
+    }
+
+    impl Iterator for Counter {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.position < self.length {
+                self.position += 1;
+                Some((self.position - 1) as f32)
+            } else {
+                None
+            }
+        }
+    }
+
+    impl RawStreamingDataSource for Counter {
+        fn sample_rate(&self) -> usize {
+            44100
+        }
+
+        fn channel_count(&self) -> usize {
+            1
+        }
+
+        fn rewind(&mut self) -> Result<(), SoundError> {
+            self.position = 0;
+            Ok(())
+        }
+    }
+
+    fn counter(length: usize) -> DataSource {
+        DataSource::RawStreaming(Box::new(Counter {
+            position: 0,
+            length,
+        }))
+    }
+
+    fn play(length: usize, pitch: f32, looping: bool, amount: usize) -> (GenericSource, Vec<f32>) {
+        let buffer = SoundBufferResource::new_streaming(counter(length)).unwrap();
+        let mut source = GenericSourceBuilder::new()
+            .with_buffer(buffer)
+            .with_status(Status::Playing)
+            .with_pitch(pitch)
+            .with_looping(looping)
+            .build()
+            .unwrap();
+        source.render(amount);
+        let samples = source.frame_samples().iter().map(|(l, _)| *l).collect();
+        (source, samples)
+    }
+
+    #[test]
+    fn test_raw_streaming() {
+        assert!(matches!(
+            GenericBuffer::new(counter(10)),
+            Err(SoundError::UnsupportedDataSource)
+        ));
+
+        let streaming = StreamingBuffer::new(counter(10000)).unwrap();
+        assert_eq!(
+            streaming.block_len(),
+            StreamingBuffer::RAW_STREAM_SAMPLE_COUNT
+        );
+        assert_eq!(streaming.channel_count(), 1);
+        assert_eq!(streaming.sample_rate(), 44100);
+
+        // Samples are continuous across blocks and the source stops at the end of data.
+        let (source, samples) = play(2500, 1.0, false, 3000);
+        assert_eq!(source.status(), Status::Stopped);
+        for pair in samples[..2498].windows(2) {
+            assert_eq!(pair[1] - pair[0], 1.0);
+        }
+        assert!(samples[2500..].iter().all(|s| *s == 0.0));
+
+        // Pitch is applied to produced samples as well.
+        let (_, samples) = play(10000, 2.0, false, 2000);
+        for pair in samples[..1999].windows(2) {
+            assert_eq!(pair[1] - pair[0], 2.0);
+        }
+    }
+
+    #[test]
+    fn test_raw_streaming_end_at_block_boundary() {
+        let length = 2 * StreamingBuffer::RAW_STREAM_SAMPLE_COUNT;
+
+        let (source, samples) = play(length, 1.0, false, length + 100);
+        assert_eq!(source.status(), Status::Stopped);
+        assert_eq!(samples[length - 2], (length - 1) as f32);
+        assert!(samples[length..].iter().all(|s| *s == 0.0));
+
+        // Looping source is rewound and starts over.
+        let (source, samples) = play(length, 1.0, true, length + 100);
+        assert_eq!(source.status(), Status::Playing);
+        assert_eq!(samples[length - 1], 0.0);
+        assert_eq!(samples[length + 99], 100.0);
+    }
+
+    #[derive(Debug)]
+    struct Silence {
+        sample_rate: usize,
+        channel_count: usize,
+    }
+
+    impl Iterator for Silence {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            Some(0.0)
+        }
+    }
+
+    impl RawStreamingDataSource for Silence {
+        fn sample_rate(&self) -> usize {
+            self.sample_rate
+        }
+
+        fn channel_count(&self) -> usize {
+            self.channel_count
+        }
+    }
+
+    #[test]
+    fn test_invalid_raw_data() {
+        let raw = || DataSource::Raw {
+            sample_rate: 44100,
+            channel_count: 2,
+            samples: vec![0.0; 3],
+        };
+        assert!(matches!(
+            SoundBufferResource::new_generic(raw()),
+            Err(SoundError::InvalidSampleCount {
+                sample_count: 3,
+                channel_count: 2
+            })
+        ));
+        assert!(matches!(
+            SoundBufferResource::new_streaming(raw()),
+            Err(SoundError::UnsupportedDataSource)
+        ));
+    }
+
+    #[test]
+    fn test_raw_streaming_invalid_format() {
+        let silence = |sample_rate, channel_count| {
+            DataSource::RawStreaming(Box::new(Silence {
+                sample_rate,
+                channel_count,
+            }))
+        };
+
+        assert!(matches!(
+            StreamingBuffer::new(silence(44100, 0)),
+            Err(SoundError::UnsupportedFormat)
+        ));
+        assert!(matches!(
+            StreamingBuffer::new(silence(0, 2)),
+            Err(SoundError::UnsupportedFormat)
+        ));
+        assert!(StreamingBuffer::new(silence(44100, 2)).is_ok());
+    }
+
+    // Receiver is Send, but not Sync - such sources must be accepted too. It can't be rewound.
+    #[derive(Debug)]
+    struct Channel {
+        receiver: std::sync::mpsc::Receiver<f32>,
+        channel_count: usize,
+    }
+
+    impl Iterator for Channel {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.receiver.try_recv().ok()
+        }
+    }
+
+    impl RawStreamingDataSource for Channel {
+        fn sample_rate(&self) -> usize {
+            44100
+        }
+
+        fn channel_count(&self) -> usize {
+            self.channel_count
+        }
+    }
+
+    #[test]
+    fn test_raw_streaming_not_sync() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        for i in 0..10 {
+            sender.send(i as f32).unwrap();
+        }
+
+        let streaming = StreamingBuffer::new(DataSource::RawStreaming(Box::new(Channel {
+            receiver,
+            channel_count: 1,
+        })))
+        .unwrap();
+        assert_eq!(streaming.channel_count(), 1);
+    }
+
+    #[test]
+    fn test_multichannel_raw_streaming_end() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        for _ in 0..10 {
+            for channel in 0..6 {
+                sender.send(channel as f32).unwrap();
+            }
+        }
+
+        let buffer =
+            SoundBufferResource::new_streaming(DataSource::RawStreaming(Box::new(Channel {
+                receiver,
+                channel_count: 6,
+            })))
+            .unwrap();
+        let mut source = GenericSourceBuilder::new()
+            .with_buffer(buffer)
+            .with_status(Status::Playing)
+            .build()
+            .unwrap();
+
+        // Every frame must have samples of every channel, even after the end of data.
+        source.render(100);
+        assert_eq!(source.status(), Status::Stopped);
+        assert_eq!(source.frame_samples().len(), 100);
+        assert_eq!(source.frame_channels.len(), 6 * 100);
+        assert_eq!(
+            &source.frame_channels[6..12],
+            &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]
+        );
+        assert!(source.frame_channels[(6 * 10)..].iter().all(|s| *s == 0.0));
+    }
+}
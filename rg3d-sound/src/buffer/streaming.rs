@@ -21,6 +21,9 @@
 //! }
 //! ```
 //!
+//! Streaming buffers could also play samples that are produced on demand by user code - see
+//! [`crate::buffer::RawStreamingDataSource`].
+//!
 //! # Notes
 //!
 //! Streaming buffer cannot be shared across multiple source. On attempt to create a source with a streaming
@@ -43,6 +46,8 @@ pub struct StreamingBuffer {
     /// user of streaming buffer, because streaming buffer does not allow random
     /// access.
     pub(in crate) use_count: usize,
+    // Amount of samples per channel that is read from the decoder at once.
+    block_len: usize,
     decoder: Decoder,
 }
 
@@ -52,6 +57,7 @@ impl Default for StreamingBuffer {
             generic: Default::default(),
             decoder: Decoder::Null,
             use_count: 0,
+            block_len: Self::STREAM_SAMPLE_COUNT,
         }
     }
 }
@@ -73,18 +79,31 @@ impl StreamingBuffer {
     /// Defines amount of samples `per channel` which each streaming buffer will use for internal buffer.
     pub const STREAM_SAMPLE_COUNT: usize = 44100;
 
+    /// Defines amount of samples `per channel` which streaming buffers with raw streaming data source
+    /// read at once. It is much less than [`Self::STREAM_SAMPLE_COUNT`], because such sources are
+    /// usually used for real-time sounds and samples that are read ahead add latency.
+    pub const RAW_STREAM_SAMPLE_COUNT: usize = 1024;
+
     /// Creates new streaming buffer using given data source. May fail if data source has unsupported format
     /// or it has corrupted data. Length of internal generic buffer cannot be changed but can be fetched from
-    /// `StreamingBuffer::STREAM_SAMPLE_COUNT`
+    /// `StreamingBuffer::STREAM_SAMPLE_COUNT` (or `StreamingBuffer::RAW_STREAM_SAMPLE_COUNT` for raw streaming
+    /// data sources)
     ///
     /// # Notes
     ///
     /// This function will return Err if data source is `Raw`. It makes no sense to stream raw data which
-    /// is already loaded into memory. Use Generic source instead!
-    pub fn new(source: DataSource) -> Result<Self, DataSource> {
-        if let DataSource::Raw { .. } = source {
-            return Err(source);
-        };
+    /// is already loaded into memory. Use Generic source instead! It will also return Err if data source
+    /// is `RawStreaming` and it reports zero channels or zero sample rate.
+    pub fn new(source: DataSource) -> Result<Self, SoundError> {
+        match source {
+            DataSource::Raw { .. } => return Err(SoundError::UnsupportedDataSource),
+            DataSource::RawStreaming(ref raw)
+                if raw.channel_count() == 0 || raw.sample_rate() == 0 =>
+            {
+                return Err(SoundError::UnsupportedFormat)
+            }
+            _ => (),
+        }
 
         let external_source_path = if let DataSource::File { path, .. } = &source {
             path.clone()
@@ -92,15 +111,17 @@ impl StreamingBuffer {
             Default::default()
         };
 
+        let block_len = if let DataSource::RawStreaming(_) = source {
+            Self::RAW_STREAM_SAMPLE_COUNT
+        } else {
+            Self::STREAM_SAMPLE_COUNT
+        };
+
         let mut decoder = Decoder::new(source)?;
 
         let mut samples = Vec::new();
         let channel_count = decoder.get_channel_count();
-        read_samples(
-            &mut samples,
-            &mut decoder,
-            Self::STREAM_SAMPLE_COUNT * channel_count,
-        );
+        read_samples(&mut samples, &mut decoder, block_len * channel_count);
         debug_assert_eq!(samples.len() % channel_count, 0);
 
         Ok(Self {
@@ -111,6 +132,7 @@ impl StreamingBuffer {
                 external_source_path,
             },
             use_count: 0,
+            block_len,
             decoder,
         })
     }
@@ -120,12 +142,17 @@ impl StreamingBuffer {
         self.decoder.duration()
     }
 
+    /// Returns amount of samples per channel that is read from data source at once.
+    pub fn block_len(&self) -> usize {
+        self.block_len
+    }
+
     #[inline]
     pub(in crate) fn read_next_block(&mut self) {
         read_samples(
             &mut self.generic.samples,
             &mut self.decoder,
-            self.generic.channel_count * Self::STREAM_SAMPLE_COUNT,
+            self.generic.channel_count * self.block_len,
         );
     }
 
@@ -0,0 +1,944 @@
+//! Bus module.
+//!
+//! # Overview
+//!
+//! Bus is a named mixing channel, every sound source is routed to some bus and every bus (except
+//! master one) is routed to its parent bus. Buses form a hierarchy, for example:
+//!
+//! ```text
+//! Master
+//! ├── Music
+//! ├── Sfx
+//! ├── Voice
+//! └── Ui
+//! ```
+//!
+//! Each bus has its own gain, can be muted or soloed and has a chain of effects that processes
+//! mixed samples of the bus before they're passed to the parent bus. This allows you to control
+//! loudness of whole groups of sounds at once - for example to have separate volume sliders for
+//! music and sound effects in game settings.
+//!
+//! Effects are stereo, so when output layout has more than two channels (see
+//! [`crate::layout`]) every group of surround channels of a bus is processed by its own copy of
+//! the effect chain: front center and low frequency channels are processed as mono sounds, back
+//! and side speakers are processed in left-right pairs. Copies have parameters of the effect chain
+//! at the moment they were made, they are made again when the chain is changed by
+//! [`AudioBus::add_effect`] or [`AudioBus::effects_mut`].
+//!
+//! # Ducking
+//!
+//! Bus could be automatically attenuated while some other bus (sidechain) is active - this is
+//! called ducking. Typical usage is to make music quieter while characters are talking, so the
+//! Music bus should be ducked by the Voice bus. Activity of the sidechain bus is detected by its
+//! output level (RMS), ducking gain is smoothly changed using attack and release times.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use rg3d_sound::{
+//!     buffer::SoundBufferResource,
+//!     bus::{self, AudioBusGraph},
+//!     context::SoundContext,
+//!     source::{generic::GenericSourceBuilder, Status},
+//! };
+//!
+//! fn setup(context: &SoundContext, speech: SoundBufferResource) {
+//!     let mut state = context.state();
+//!
+//!     // Master -> Music/Sfx/Voice/Ui, Music is ducked by Voice.
+//!     state.set_bus_graph(AudioBusGraph::standard());
+//!
+//!     let music = state.bus_graph().find_by_name(bus::MUSIC_BUS);
+//!     state.bus_graph_mut().bus_mut(music).set_gain(0.5);
+//!
+//!     let source = GenericSourceBuilder::new()
+//!         .with_buffer(speech)
+//!         .with_status(Status::Playing)
+//!         .with_bus(bus::VOICE_BUS)
+//!         .build_source()
+//!         .unwrap();
+//!     state.add_source(source);
+//! }
+//! ```
+
+use crate::{
+    context::SAMPLE_RATE,
+    effects::{Effect, EffectRenderTrait},
+    layout::ChannelLayout,
+};
+use rg3d_core::{
+    math,
+    pool::{Handle, Pool},
+    visitor::{Visit, VisitResult, Visitor},
+};
+use std::time::Duration;
+
+/// Name of the master bus. Master bus always exists and cannot be removed, sources that are
+/// routed to unknown buses are routed to the master bus.
+pub const MASTER_BUS: &str = "Master";
+
+/// Name of the music bus of the standard bus graph.
+pub const MUSIC_BUS: &str = "Music";
+
+/// Name of the sound effects bus of the standard bus graph.
+pub const SFX_BUS: &str = "Sfx";
+
+/// Name of the voice bus of the standard bus graph.
+pub const VOICE_BUS: &str = "Voice";
+
+/// Name of the user interface bus of the standard bus graph.
+pub const UI_BUS: &str = "Ui";
+
+/// Ducking settings of a bus. See module docs.
+#[derive(Debug, Clone)]
+pub struct Ducking {
+    sidechain: String,
+    threshold: f32,
+    ducked_gain: f32,
+    attack_time: f32,
+    release_time: f32,
+}
+
+impl Default for Ducking {
+    fn default() -> Self {
+        Self {
+            sidechain: Default::default(),
+            threshold: 0.01,
+            ducked_gain: 0.3,
+            attack_time: 0.05,
+            release_time: 0.5,
+        }
+    }
+}
+
+impl Ducking {
+    /// Creates new ducking settings with given name of the sidechain bus. Every other parameter
+    /// has default value: threshold is 0.01, ducked gain is 0.3, attack time is 50 ms and
+    /// release time is 500 ms.
+    pub fn new<N: AsRef<str>>(sidechain: N) -> Self {
+        Self {
+            sidechain: sidechain.as_ref().to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets output level (RMS) of the sidechain bus above which the sidechain is considered
+    /// active.
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold.max(0.0);
+        self
+    }
+
+    /// Sets gain that will be applied to the bus while the sidechain is active.
+    pub fn with_ducked_gain(mut self, gain: f32) -> Self {
+        self.ducked_gain = gain.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets time in which the bus will be attenuated after the sidechain became active.
+    pub fn with_attack_time(mut self, time: Duration) -> Self {
+        self.attack_time = time.as_secs_f32();
+        self
+    }
+
+    /// Sets time in which the bus will restore its gain after the sidechain became inactive.
+    pub fn with_release_time(mut self, time: Duration) -> Self {
+        self.release_time = time.as_secs_f32();
+        self
+    }
+
+    /// Returns name of the sidechain bus.
+    pub fn sidechain(&self) -> &str {
+        &self.sidechain
+    }
+
+    /// Returns activity threshold of the sidechain bus.
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Returns gain that is applied to the bus while the sidechain is active.
+    pub fn ducked_gain(&self) -> f32 {
+        self.ducked_gain
+    }
+
+    /// Returns attack time.
+    pub fn attack_time(&self) -> Duration {
+        Duration::from_secs_f32(self.attack_time)
+    }
+
+    /// Returns release time.
+    pub fn release_time(&self) -> Duration {
+        Duration::from_secs_f32(self.release_time)
+    }
+
+    fn smoothing(time: f32, block_time: f32) -> f32 {
+        if time > 0.0 {
+            1.0 - (-block_time / time).exp()
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Visit for Ducking {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.sidechain.visit("Sidechain", visitor)?;
+        self.threshold.visit("Threshold", visitor)?;
+        self.ducked_gain.visit("DuckedGain", visitor)?;
+        self.attack_time.visit("AttackTime", visitor)?;
+        self.release_time.visit("ReleaseTime", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// See module docs.
+#[derive(Debug, Clone)]
+pub struct AudioBus {
+    name: String,
+    gain: f32,
+    muted: bool,
+    solo: bool,
+    effects: Vec<Effect>,
+    ducking: Option<Ducking>,
+    parent: Handle<AudioBus>,
+    children: Vec<Handle<AudioBus>>,
+    // Mixed samples of the bus, it accumulates samples of sources and child buses.
+    buffer: Vec<(f32, f32)>,
+    // Interleaved samples of every channel except front left and right, it is empty when output
+    // layout is stereo.
+    surround: Vec<f32>,
+    // Copies of the effect chain for every group of surround channels, see module docs.
+    surround_effects: Vec<Vec<Effect>>,
+    // Samples of a group of surround channels that are processed by the effects.
+    surround_group: Vec<(f32, f32)>,
+    // Output level (RMS) from last render.
+    level: f32,
+    duck_gain: f32,
+    // Gain from last render, it is used to interpolate gain across the buffer to prevent
+    // clicks when gain changes.
+    last_gain: Option<f32>,
+}
+
+impl Default for AudioBus {
+    fn default() -> Self {
+        Self {
+            name: Default::default(),
+            gain: 1.0,
+            muted: false,
+            solo: false,
+            effects: Default::default(),
+            ducking: None,
+            parent: Handle::NONE,
+            children: Default::default(),
+            buffer: Default::default(),
+            surround: Default::default(),
+            surround_effects: Default::default(),
+            surround_group: Default::default(),
+            level: 0.0,
+            duck_gain: 1.0,
+            last_gain: None,
+        }
+    }
+}
+
+impl AudioBus {
+    /// Creates new bus with given name.
+    pub fn new<N: AsRef<str>>(name: N) -> Self {
+        Self {
+            name: name.as_ref().to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets desired gain of the bus and returns self.
+    pub fn with_gain(mut self, gain: f32) -> Self {
+        self.set_gain(gain);
+        self
+    }
+
+    /// Adds new effect to the effect chain of the bus and returns self.
+    pub fn with_effect(mut self, effect: Effect) -> Self {
+        self.add_effect(effect);
+        self
+    }
+
+    /// Sets ducking settings of the bus and returns self.
+    pub fn with_ducking(mut self, ducking: Ducking) -> Self {
+        self.set_ducking(Some(ducking));
+        self
+    }
+
+    /// Returns name of the bus.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sets new name of the bus. Sources are routed to buses by names, so sources that were
+    /// routed to this bus will be routed to the master bus after renaming.
+    pub fn set_name<N: AsRef<str>>(&mut self, name: N) {
+        self.name = name.as_ref().to_owned();
+    }
+
+    /// Returns gain of the bus.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Sets new gain of the bus.
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain.max(0.0);
+    }
+
+    /// Returns true if the bus is muted.
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Mutes or unmutes the bus. Muted bus produces no sound, as well as its child buses.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Returns true if the bus is soloed.
+    pub fn is_solo(&self) -> bool {
+        self.solo
+    }
+
+    /// Solos the bus. If there is at least one soloed bus in the graph, only soloed buses,
+    /// their ancestors and descendants will be heard.
+    pub fn set_solo(&mut self, solo: bool) {
+        self.solo = solo;
+    }
+
+    /// Adds new effect to the end of the effect chain. Effects in a chain process samples
+    /// of the bus, their inputs are ignored.
+    pub fn add_effect(&mut self, effect: Effect) {
+        self.effects.push(effect);
+        self.surround_effects.clear();
+    }
+
+    /// Returns shared reference to the effect chain.
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    /// Returns mutable reference to the effect chain.
+    pub fn effects_mut(&mut self) -> &mut Vec<Effect> {
+        self.surround_effects.clear();
+        &mut self.effects
+    }
+
+    /// Returns ducking settings of the bus.
+    pub fn ducking(&self) -> Option<&Ducking> {
+        self.ducking.as_ref()
+    }
+
+    /// Sets new ducking settings of the bus, `None` disables ducking.
+    pub fn set_ducking(&mut self, ducking: Option<Ducking>) {
+        self.ducking = ducking;
+        if self.ducking.is_none() {
+            self.duck_gain = 1.0;
+        }
+    }
+
+    /// Returns current gain of the ducking, it is 1.0 if the bus is not ducked.
+    pub fn duck_gain(&self) -> f32 {
+        self.duck_gain
+    }
+
+    /// Returns output level (RMS) of the bus from last render.
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Returns handle of the parent bus, it is `Handle::NONE` for the master bus.
+    pub fn parent(&self) -> Handle<AudioBus> {
+        self.parent
+    }
+
+    /// Returns handles of the child buses.
+    pub fn children(&self) -> &[Handle<AudioBus>] {
+        &self.children
+    }
+
+    fn prepare(&mut self, amount: usize, surround_channels: usize) {
+        self.buffer.clear();
+        self.buffer.resize(amount, (0.0, 0.0));
+        self.surround.clear();
+        self.surround.resize(amount * surround_channels, 0.0);
+    }
+
+    fn process_effects(&mut self, buffer: &mut [(f32, f32)], surround: &mut [f32]) {
+        let surround_channels = surround.len() / buffer.len().max(1);
+
+        // Surround channels of every layout start with front center and low frequency channels,
+        // they are followed by pairs of left and right speakers. Copies must be made before the
+        // chain processes front channels, otherwise they'd get its state.
+        let group_count = if surround_channels > 0 {
+            2 + (surround_channels - 2) / 2
+        } else {
+            0
+        };
+        if self.surround_effects.len() != group_count {
+            self.surround_effects = vec![self.effects.clone(); group_count];
+        }
+
+        for effect in self.effects.iter_mut() {
+            effect.process(buffer);
+        }
+
+        if self.effects.is_empty() || surround_channels == 0 {
+            return;
+        }
+
+        let mut group = std::mem::take(&mut self.surround_group);
+        group.clear();
+        group.resize(buffer.len(), (0.0, 0.0));
+        for (index, effects) in self.surround_effects.iter_mut().enumerate() {
+            let (left, right) = if index < 2 {
+                (index, index)
+            } else {
+                (2 * index - 2, 2 * index - 1)
+            };
+
+            for (pair, frame) in group
+                .iter_mut()
+                .zip(surround.chunks_exact(surround_channels))
+            {
+                *pair = (frame[left], frame[right]);
+            }
+
+            for effect in effects.iter_mut() {
+                effect.process(&mut group);
+            }
+
+            for (&(left_sample, right_sample), frame) in group
+                .iter()
+                .zip(surround.chunks_exact_mut(surround_channels))
+            {
+                if left == right {
+                    frame[left] = (left_sample + right_sample) * 0.5;
+                } else {
+                    frame[left] = left_sample;
+                    frame[right] = right_sample;
+                }
+            }
+        }
+        self.surround_group = group;
+    }
+}
+
+impl Visit for AudioBus {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.name.visit("Name", visitor)?;
+        self.gain.visit("Gain", visitor)?;
+        self.muted.visit("Muted", visitor)?;
+        self.solo.visit("Solo", visitor)?;
+        self.effects.visit("Effects", visitor)?;
+        if visitor.is_reading() {
+            self.surround_effects.clear();
+        }
+        self.ducking.visit("Ducking", visitor)?;
+        self.parent.visit("Parent", visitor)?;
+        self.children.visit("Children", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Hierarchy of buses of a sound context. See module docs.
+#[derive(Debug, Clone)]
+pub struct AudioBusGraph {
+    buses: Pool<AudioBus>,
+    root: Handle<AudioBus>,
+}
+
+impl Default for AudioBusGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioBusGraph {
+    /// Creates new graph with the master bus only.
+    pub fn new() -> Self {
+        let mut buses = Pool::new();
+        let root = buses.spawn(AudioBus::new(MASTER_BUS));
+        Self { buses, root }
+    }
+
+    /// Creates new graph with the master bus and Music, Sfx, Voice and Ui buses as its
+    /// children. The Music bus is ducked by the Voice bus.
+    pub fn standard() -> Self {
+        let mut graph = Self::new();
+        let root = graph.root;
+        graph.add_bus(
+            AudioBus::new(MUSIC_BUS).with_ducking(Ducking::new(VOICE_BUS)),
+            root,
+        );
+        graph.add_bus(AudioBus::new(SFX_BUS), root);
+        graph.add_bus(AudioBus::new(VOICE_BUS), root);
+        graph.add_bus(AudioBus::new(UI_BUS), root);
+        graph
+    }
+
+    /// Adds new bus as a child of given parent bus. If parent handle is invalid, the bus will
+    /// be added as a child of the master bus.
+    pub fn add_bus(&mut self, mut bus: AudioBus, parent: Handle<AudioBus>) -> Handle<AudioBus> {
+        let parent = if self.buses.is_valid_handle(parent) {
+            parent
+        } else {
+            self.root
+        };
+        bus.parent = parent;
+        bus.children.clear();
+        let handle = self.buses.spawn(bus);
+        self.buses[parent].children.push(handle);
+        handle
+    }
+
+    /// Removes bus from the graph, its children will be attached to its parent. The master
+    /// bus cannot be removed, `None` is returned in this case.
+    pub fn remove_bus(&mut self, handle: Handle<AudioBus>) -> Option<AudioBus> {
+        if handle == self.root || !self.buses.is_valid_handle(handle) {
+            return None;
+        }
+
+        let mut bus = self.buses.free(handle);
+        let parent = bus.parent;
+        self.buses[parent].children.retain(|&child| child != handle);
+        for child in bus.children.drain(..) {
+            self.buses[child].parent = parent;
+            self.buses[parent].children.push(child);
+        }
+        bus.parent = Handle::NONE;
+        Some(bus)
+    }
+
+    /// Returns handle of the master bus.
+    pub fn root(&self) -> Handle<AudioBus> {
+        self.root
+    }
+
+    /// Searches for a bus with given name, returns `Handle::NONE` if there is no such bus.
+    pub fn find_by_name(&self, name: &str) -> Handle<AudioBus> {
+        self.buses
+            .pair_iter()
+            .find(|(_, bus)| bus.name == name)
+            .map_or(Handle::NONE, |(handle, _)| handle)
+    }
+
+    /// Returns shared reference to the master bus.
+    pub fn master(&self) -> &AudioBus {
+        &self.buses[self.root]
+    }
+
+    /// Returns mutable reference to the master bus.
+    pub fn master_mut(&mut self) -> &mut AudioBus {
+        &mut self.buses[self.root]
+    }
+
+    /// Returns shared reference to a bus at given handle. If handle is invalid, this method
+    /// will panic.
+    pub fn bus(&self, handle: Handle<AudioBus>) -> &AudioBus {
+        &self.buses[handle]
+    }
+
+    /// Returns mutable reference to a bus at given handle. If handle is invalid, this method
+    /// will panic.
+    pub fn bus_mut(&mut self, handle: Handle<AudioBus>) -> &mut AudioBus {
+        &mut self.buses[handle]
+    }
+
+    /// Returns shared reference to a pool with all buses.
+    pub fn buses(&self) -> &Pool<AudioBus> {
+        &self.buses
+    }
+
+    /// Returns true if the bus could be heard, it checks mute and solo flags of the bus and
+    /// its ancestors.
+    pub fn is_audible(&self, handle: Handle<AudioBus>) -> bool {
+        let mut any_solo = false;
+        let mut soloed_path = false;
+        for bus in self.buses.iter() {
+            any_solo |= bus.solo;
+        }
+
+        let mut current = handle;
+        while let Some(bus) = self.buses.try_borrow(current) {
+            if bus.muted {
+                return false;
+            }
+            soloed_path |= bus.solo;
+            current = bus.parent;
+        }
+
+        !any_solo || soloed_path || self.has_soloed_descendant(handle)
+    }
+
+    fn has_soloed_descendant(&self, handle: Handle<AudioBus>) -> bool {
+        self.buses[handle]
+            .children
+            .iter()
+            .any(|&child| self.buses[child].solo || self.has_soloed_descendant(child))
+    }
+
+    // Returns handles of buses in order of processing: every bus is processed after its
+    // children and after its sidechain bus (unless there is a cycle).
+    fn processing_order(&self) -> Vec<Handle<AudioBus>> {
+        fn visit(
+            graph: &AudioBusGraph,
+            handle: Handle<AudioBus>,
+            visited: &mut Vec<Handle<AudioBus>>,
+            order: &mut Vec<Handle<AudioBus>>,
+        ) {
+            if visited.contains(&handle) {
+                return;
+            }
+            visited.push(handle);
+
+            let bus = &graph.buses[handle];
+            for &child in bus.children.iter() {
+                visit(graph, child, visited, order);
+            }
+            if let Some(ducking) = bus.ducking.as_ref() {
+                let sidechain = graph.find_by_name(&ducking.sidechain);
+                if sidechain.is_some() {
+                    visit(graph, sidechain, visited, order);
+                }
+            }
+
+            order.push(handle);
+        }
+
+        let mut visited = Vec::new();
+        let mut order = Vec::new();
+        visit(self, self.root, &mut visited, &mut order);
+        order
+    }
+
+    pub(in crate) fn begin_render(&mut self, amount: usize, layout: ChannelLayout) {
+        for bus in self.buses.iter_mut() {
+            bus.prepare(amount, layout.channel_count() - 2);
+        }
+    }
+
+    /// Returns buffer of a bus with given name or buffer of the master bus if there is no
+    /// such bus.
+    pub(in crate) fn input_buffer(&mut self, name: &str) -> &mut [(f32, f32)] {
+        self.input_buffers(name).0
+    }
+
+    /// Returns front and surround buffers of a bus with given name or buffers of the master
+    /// bus if there is no such bus.
+    pub(in crate) fn input_buffers(&mut self, name: &str) -> (&mut [(f32, f32)], &mut [f32]) {
+        let mut handle = self.find_by_name(name);
+        if handle.is_none() {
+            handle = self.root;
+        }
+        let bus = &mut self.buses[handle];
+        (&mut bus.buffer, &mut bus.surround)
+    }
+
+    /// Processes every bus and mixes the master bus into given buffers.
+    pub(in crate) fn end_render(&mut self, out: &mut [(f32, f32)], out_surround: &mut [f32]) {
+        let block_time = out.len() as f32 / SAMPLE_RATE as f32;
+
+        for handle in self.processing_order() {
+            let audible = self.is_audible(handle);
+
+            let sidechain_level = self.buses[handle]
+                .ducking
+                .as_ref()
+                .map(|ducking| self.find_by_name(&ducking.sidechain))
+                .and_then(|sidechain| self.buses.try_borrow(sidechain))
+                .map(|sidechain| sidechain.level);
+
+            let bus = &mut self.buses[handle];
+            let mut buffer = std::mem::take(&mut bus.buffer);
+            let mut surround = std::mem::take(&mut bus.surround);
+            let surround_channels = surround.len() / buffer.len().max(1);
+
+            bus.process_effects(&mut buffer, &mut surround);
+
+            if let (Some(ducking), Some(level)) = (bus.ducking.as_ref(), sidechain_level) {
+                let (target, time) = if level > ducking.threshold {
+                    (ducking.ducked_gain, ducking.attack_time)
+                } else {
+                    (1.0, ducking.release_time)
+                };
+                let k = Ducking::smoothing(time, block_time);
+                bus.duck_gain += (target - bus.duck_gain) * k;
+            }
+
+            let gain = if audible {
+                bus.gain * bus.duck_gain
+            } else {
+                0.0
+            };
+            let last_gain = bus.last_gain.unwrap_or(gain);
+            bus.last_gain = Some(gain);
+
+            let step = 1.0 / buffer.len().max(1) as f32;
+            let mut k = 0.0;
+            let mut sum = 0.0;
+            for (i, (left, right)) in buffer.iter_mut().enumerate() {
+                let g = math::lerpf(last_gain, gain, k);
+                *left *= g;
+                *right *= g;
+                sum += *left * *left + *right * *right;
+                for sample in
+                    surround[(i * surround_channels)..((i + 1) * surround_channels)].iter_mut()
+                {
+                    *sample *= g;
+                    sum += *sample * *sample;
+                }
+                k += step;
+            }
+            bus.level = if buffer.is_empty() {
+                0.0
+            } else {
+                (sum / ((2 + surround_channels) * buffer.len()) as f32).sqrt()
+            };
+
+            let parent = bus.parent;
+            let (destination, surround_destination) = if parent.is_some() {
+                let parent = &mut self.buses[parent];
+                (parent.buffer.as_mut_slice(), parent.surround.as_mut_slice())
+            } else {
+                (&mut *out, &mut *out_surround)
+            };
+            for ((out_left, out_right), &(left, right)) in destination.iter_mut().zip(&buffer) {
+                *out_left += left;
+                *out_right += right;
+            }
+            for (out_sample, sample) in surround_destination.iter_mut().zip(&surround) {
+                *out_sample += sample;
+            }
+
+            let bus = &mut self.buses[handle];
+            bus.buffer = buffer;
+            bus.surround = surround;
+        }
+    }
+}
+
+impl Visit for AudioBusGraph {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        if visitor.is_reading() {
+            self.buses.clear();
+        }
+
+        self.buses.visit("Buses", visitor)?;
+        self.root.visit("Root", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bus::{AudioBus, AudioBusGraph, Ducking, MASTER_BUS, MUSIC_BUS, VOICE_BUS},
+        context::SoundContext,
+        effects::{delay::Delay, BaseEffect, Effect},
+        layout::{
+            test::{constant_source, render_block},
+            ChannelLayout,
+        },
+        pool::Handle,
+        source::SoundSource,
+    };
+    use rg3d_core::{
+        futures::executor::block_on,
+        visitor::{Visit, Visitor},
+    };
+    use std::time::Duration;
+
+    fn add_source(context: &SoundContext, bus: &str) -> Handle<SoundSource> {
+        let source = constant_source(&[0.5])
+            .with_bus(bus)
+            .build_source()
+            .unwrap();
+        context.state().add_source(source)
+    }
+
+    fn render(context: &SoundContext) -> f32 {
+        render_block(context, ChannelLayout::Stereo)
+            .0
+            .last()
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn test_bus_routing_and_gain() {
+        let context = SoundContext::new();
+        context.state().set_bus_graph(AudioBusGraph::standard());
+        let source = add_source(&context, MUSIC_BUS);
+        let reference = render(&context);
+        assert!(reference > 0.0);
+
+        let music = context.state().bus_graph().find_by_name(MUSIC_BUS);
+        context.state().bus_graph_mut().bus_mut(music).set_gain(0.5);
+        render(&context);
+        assert!((render(&context) - reference * 0.5).abs() < 1.0e-5);
+
+        // Unknown bus means master bus.
+        context.state().source_mut(source).set_bus("Unknown");
+        assert!((render(&context) - reference).abs() < 1.0e-5);
+
+        context.state().bus_graph_mut().master_mut().set_gain(0.25);
+        render(&context);
+        assert!((render(&context) - reference * 0.25).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn test_mute_and_solo() {
+        let context = SoundContext::new();
+        let mut graph = AudioBusGraph::new();
+        let root = graph.root();
+        let music = graph.add_bus(AudioBus::new(MUSIC_BUS), root);
+        let voice = graph.add_bus(AudioBus::new(VOICE_BUS), root);
+        context.state().set_bus_graph(graph);
+
+        add_source(&context, MUSIC_BUS);
+        let reference = render(&context);
+
+        context
+            .state()
+            .bus_graph_mut()
+            .bus_mut(voice)
+            .set_solo(true);
+        assert!(!context.state().bus_graph().is_audible(music));
+        assert!(context.state().bus_graph().is_audible(voice));
+        assert!(context.state().bus_graph().is_audible(root));
+        render(&context);
+        assert_eq!(render(&context), 0.0);
+
+        context
+            .state()
+            .bus_graph_mut()
+            .bus_mut(voice)
+            .set_solo(false);
+        render(&context);
+        assert!((render(&context) - reference).abs() < 1.0e-5);
+
+        context.state().bus_graph_mut().master_mut().set_muted(true);
+        assert!(!context.state().bus_graph().is_audible(music));
+        render(&context);
+        assert_eq!(render(&context), 0.0);
+    }
+
+    #[test]
+    fn test_ducking() {
+        let context = SoundContext::new();
+        let mut graph = AudioBusGraph::new();
+        let root = graph.root();
+        let music = graph.add_bus(
+            AudioBus::new(MUSIC_BUS).with_ducking(
+                Ducking::new(VOICE_BUS)
+                    .with_ducked_gain(0.2)
+                    .with_attack_time(Duration::from_millis(10))
+                    .with_release_time(Duration::from_millis(10)),
+            ),
+            root,
+        );
+        graph.add_bus(AudioBus::new(VOICE_BUS), root);
+        context.state().set_bus_graph(graph);
+
+        add_source(&context, MUSIC_BUS);
+        let reference = render(&context);
+        assert_eq!(context.state().bus_graph().bus(music).duck_gain(), 1.0);
+
+        let voice_source = add_source(&context, VOICE_BUS);
+        for _ in 0..4 {
+            render(&context);
+        }
+        let duck_gain = context.state().bus_graph().bus(music).duck_gain();
+        assert!((duck_gain - 0.2).abs() < 1.0e-3);
+
+        context.state().source_mut(voice_source).stop().unwrap();
+        for _ in 0..4 {
+            render(&context);
+        }
+        assert!((context.state().bus_graph().bus(music).duck_gain() - 1.0).abs() < 1.0e-3);
+        assert!((render(&context) - reference).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn test_surround_effects() {
+        let context = SoundContext::new();
+        let mut delay = Delay::new(BaseEffect::default());
+        delay.set_left_time(Duration::from_millis(10));
+        delay.set_right_time(Duration::from_millis(20));
+        delay.set_feedback(0.0);
+        delay.set_dry(0.0);
+        delay.set_wet(1.0);
+        context
+            .state()
+            .bus_graph_mut()
+            .master_mut()
+            .add_effect(Effect::Delay(delay));
+
+        // FL FR FC LFE SL SR
+        let frame = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6];
+        context
+            .state()
+            .add_source(constant_source(&frame).build_source().unwrap());
+
+        let (front, surround) = render_block(&context, ChannelLayout::Surround51);
+        let assert_frame = |index: usize, expected: [f32; 6]| {
+            let (left, right) = front[index];
+            let actual = [left, right]
+                .iter()
+                .chain(&surround[(index * 4)..((index + 1) * 4)])
+                .cloned()
+                .collect::<Vec<_>>();
+            for (sample, expected) in actual.iter().zip(&expected) {
+                assert!((sample - expected).abs() < 1.0e-5, "{:?}", actual);
+            }
+        };
+
+        // Left speakers are delayed by 10 ms, right ones by 20 ms. Center and low frequency
+        // channels are processed as mono, so they get both delays.
+        assert_frame(100, [0.0; 6]);
+        assert_frame(600, [0.1, 0.0, 0.15, 0.2, 0.5, 0.0]);
+        assert_frame(front.len() - 1, frame);
+    }
+
+    #[test]
+    fn test_bus_graph_visit() {
+        let path = std::env::temp_dir().join("rg3d_sound_bus_graph.bin");
+
+        let mut graph = AudioBusGraph::standard();
+        let voice = graph.find_by_name(VOICE_BUS);
+        graph.bus_mut(voice).set_gain(0.75);
+        graph.add_bus(AudioBus::new("Footsteps").with_gain(0.5), Handle::NONE);
+
+        let mut visitor = Visitor::new();
+        graph.visit("Graph", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+
+        let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+        let mut loaded = AudioBusGraph::default();
+        loaded.visit("Graph", &mut visitor).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.master().name(), MASTER_BUS);
+        assert_eq!(loaded.master().children().len(), 5);
+        assert_eq!(loaded.bus(loaded.find_by_name(VOICE_BUS)).gain(), 0.75);
+        let music = loaded.bus(loaded.find_by_name(MUSIC_BUS));
+        assert_eq!(music.ducking().unwrap().sidechain(), VOICE_BUS);
+        assert_eq!(music.parent(), loaded.root());
+        assert!(loaded.find_by_name("Footsteps").is_some());
+    }
+}
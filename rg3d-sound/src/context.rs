@@ -12,18 +12,25 @@
 
 use crate::pool::Ticket;
 use crate::{
+    bus::{AudioBusGraph, MASTER_BUS},
+    cue::SoundCueResource,
     effects::{Effect, EffectRenderTrait},
+    layout::ChannelLayout,
     listener::Listener,
-    renderer::{render_source_default, Renderer},
+    occlusion::OcclusionProvider,
+    renderer::{render_source_default, render_source_surround, Renderer},
     source::{SoundSource, Status},
 };
 use rg3d_core::visitor::VisitError;
 use rg3d_core::{
+    algebra::Vector3,
     pool::{Handle, Pool},
     visitor::{Visit, VisitResult, Visitor},
 };
+use rg3d_resource::ResourceState;
 use std::sync::MutexGuard;
 use std::{
+    collections::HashMap,
     sync::{Arc, Mutex},
     time::Duration,
 };
@@ -106,6 +113,15 @@ pub struct State {
     effects: Pool<Effect>,
     distance_model: DistanceModel,
     paused: bool,
+    bus_graph: AudioBusGraph,
+    doppler_factor: f32,
+    speed_of_sound: f32,
+    occlusion_provider: Option<Arc<Mutex<dyn OcclusionProvider>>>,
+    occlusion_gain: f32,
+    occlusion_cutoff: f32,
+    occlusion_smoothing_time: f32,
+    // Instances of sound cues played in the context, the key is a key of cue resource.
+    cue_instances: HashMap<usize, Vec<Handle<SoundSource>>>,
 }
 
 impl State {
@@ -152,6 +168,76 @@ impl State {
         self.distance_model
     }
 
+    /// Sets Doppler factor - a multiplier of Doppler shift of spatial sources. 0.0 disables
+    /// Doppler effect, values larger than 1.0 exaggerate it. Negative values are clamped to zero.
+    pub fn set_doppler_factor(&mut self, doppler_factor: f32) {
+        self.doppler_factor = doppler_factor.max(0.0);
+    }
+
+    /// Returns Doppler factor.
+    pub fn doppler_factor(&self) -> f32 {
+        self.doppler_factor
+    }
+
+    /// Sets speed of sound in units per second that is used to calculate Doppler shift. Default
+    /// value is 343.3 which is speed of sound in air in meters per second. It is clamped to be at
+    /// least 1.0.
+    pub fn set_speed_of_sound(&mut self, speed_of_sound: f32) {
+        self.speed_of_sound = speed_of_sound.max(1.0);
+    }
+
+    /// Returns speed of sound.
+    pub fn speed_of_sound(&self) -> f32 {
+        self.speed_of_sound
+    }
+
+    /// Sets new occlusion provider, it will be asked about occlusion of every playing spatial
+    /// source on each render. `None` disables occlusion. See `occlusion` module docs for more info.
+    pub fn set_occlusion_provider(
+        &mut self,
+        provider: Option<Arc<Mutex<dyn OcclusionProvider>>>,
+    ) -> Option<Arc<Mutex<dyn OcclusionProvider>>> {
+        std::mem::replace(&mut self.occlusion_provider, provider)
+    }
+
+    /// Returns current occlusion provider.
+    pub fn occlusion_provider(&self) -> Option<Arc<Mutex<dyn OcclusionProvider>>> {
+        self.occlusion_provider.clone()
+    }
+
+    /// Sets gain of fully occluded source, gain of partially occluded source is interpolated
+    /// between 1.0 and this value. Default value is 0.3.
+    pub fn set_occlusion_gain(&mut self, gain: f32) {
+        self.occlusion_gain = gain.clamp(0.0, 1.0);
+    }
+
+    /// Returns gain of fully occluded source.
+    pub fn occlusion_gain(&self) -> f32 {
+        self.occlusion_gain
+    }
+
+    /// Sets cutoff frequency (in hertz) of low-pass filter of fully occluded source. Partially
+    /// occluded source is a mix of filtered and unfiltered signals. Default value is 1000 Hz.
+    pub fn set_occlusion_cutoff(&mut self, cutoff: f32) {
+        self.occlusion_cutoff = cutoff.max(0.0);
+    }
+
+    /// Returns cutoff frequency of low-pass filter of fully occluded source.
+    pub fn occlusion_cutoff(&self) -> f32 {
+        self.occlusion_cutoff
+    }
+
+    /// Sets time constant of occlusion smoothing - in this time occlusion factor of a source
+    /// passes ~63% of the way to a new value. Default value is 100 ms.
+    pub fn set_occlusion_smoothing_time(&mut self, time: Duration) {
+        self.occlusion_smoothing_time = time.as_secs_f32();
+    }
+
+    /// Returns time constant of occlusion smoothing.
+    pub fn occlusion_smoothing_time(&self) -> Duration {
+        Duration::from_secs_f32(self.occlusion_smoothing_time)
+    }
+
     /// Adds new effect to effects chain. Each sample from
     pub fn add_effect(&mut self, effect: Effect) -> Handle<Effect> {
         self.effects.spawn(effect)
@@ -204,6 +290,56 @@ impl State {
         self.sources.spawn(source)
     }
 
+    /// Plays given sound cue - creates new generic sound source using rules of the cue. Returns
+    /// handle of new source, or `None` if the cue is not loaded, is on cooldown, or has maximum
+    /// amount of instances and its stealing policy rejects new ones. The source is removed
+    /// automatically when it stops playing. See `cue` module docs for more info.
+    pub fn play_cue(&mut self, cue: &SoundCueResource) -> Option<Handle<SoundSource>> {
+        self.play_cue_internal(cue, None)
+    }
+
+    /// Same as `play_cue`, but creates spatial source at given position.
+    pub fn play_cue_at(
+        &mut self,
+        cue: &SoundCueResource,
+        position: Vector3<f32>,
+    ) -> Option<Handle<SoundSource>> {
+        self.play_cue_internal(cue, Some(position))
+    }
+
+    fn play_cue_internal(
+        &mut self,
+        cue: &SoundCueResource,
+        position: Option<Vector3<f32>>,
+    ) -> Option<Handle<SoundSource>> {
+        // Forget cues without playing instances, otherwise the map would grow forever.
+        let sources = &self.sources;
+        self.cue_instances.retain(|_, instances| {
+            instances.iter().any(|&handle| {
+                sources.is_valid_handle(handle)
+                    && sources.borrow(handle).status() != Status::Stopped
+            })
+        });
+
+        let instances = self.cue_instances.entry(cue.key()).or_default();
+        match *cue.state() {
+            ResourceState::Ok(ref mut cue) => {
+                cue.instantiate(&mut self.sources, instances, position)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns handles of sources that were created in the context by given cue. Some of them
+    /// could be already removed from the context, invalid handles are pruned on next play of
+    /// the cue.
+    pub fn cue_instances(&self, cue: &SoundCueResource) -> &[Handle<SoundSource>] {
+        self.cue_instances
+            .get(&cue.key())
+            .map(|instances| instances.as_slice())
+            .unwrap_or_default()
+    }
+
     /// Returns shared reference to a pool with all sound sources.
     pub fn sources(&self) -> &Pool<SoundSource> {
         &self.sources
@@ -249,7 +385,31 @@ impl State {
         self.effects.borrow_mut(handle)
     }
 
-    pub(crate) fn render(&mut self, master_gain: f32, buf: &mut [(f32, f32)]) {
+    /// Sets new graph of buses, returns old one. Sources are routed to buses by names, so
+    /// there is no need to reassign sources.
+    pub fn set_bus_graph(&mut self, bus_graph: AudioBusGraph) -> AudioBusGraph {
+        std::mem::replace(&mut self.bus_graph, bus_graph)
+    }
+
+    /// Returns shared reference to graph of buses.
+    pub fn bus_graph(&self) -> &AudioBusGraph {
+        &self.bus_graph
+    }
+
+    /// Returns mutable reference to graph of buses.
+    pub fn bus_graph_mut(&mut self) -> &mut AudioBusGraph {
+        &mut self.bus_graph
+    }
+
+    // Renders sound into front (left and right) and surround channels of given layout. Surround
+    // buffer contains interleaved samples of every channel of the layout except front ones.
+    pub(crate) fn render(
+        &mut self,
+        master_gain: f32,
+        layout: ChannelLayout,
+        buf: &mut [(f32, f32)],
+        surround_buf: &mut [f32],
+    ) {
         let last_time = rg3d_core::instant::Instant::now();
 
         if !self.paused {
@@ -261,6 +421,49 @@ impl State {
                 }
             }
 
+            self.bus_graph.begin_render(buf.len(), layout);
+
+            // Velocities are calculated from changes of positions between renders, unless they
+            // are set explicitly.
+            let dt = buf.len() as f32 / SAMPLE_RATE as f32;
+            self.listener.update_velocity(dt, self.speed_of_sound);
+            for source in self.sources.iter_mut() {
+                let doppler_pitch = match source {
+                    SoundSource::Generic(_) => 1.0,
+                    SoundSource::Spatial(spatial) => {
+                        spatial.update_velocity(dt, self.speed_of_sound);
+                        spatial.get_doppler_pitch(
+                            &self.listener,
+                            self.speed_of_sound,
+                            self.doppler_factor,
+                        ) as f64
+                    }
+                };
+                source.doppler_pitch = doppler_pitch;
+            }
+
+            // Sound thread must never wait for a game thread that updates the provider, so if
+            // the provider is locked (or poisoned) previous occlusion targets are used.
+            let mut occlusion_provider = self
+                .occlusion_provider
+                .as_ref()
+                .map(|provider| provider.try_lock().ok());
+            for (handle, source) in self.sources.pair_iter_mut() {
+                if let SoundSource::Spatial(spatial) = source {
+                    if spatial.status() == Status::Playing {
+                        let target = match occlusion_provider {
+                            Some(Some(ref mut provider)) => {
+                                provider.occlusion(handle, spatial, &self.listener)
+                            }
+                            Some(None) => spatial.occlusion_target(),
+                            None => 0.0,
+                        };
+                        spatial.update_occlusion(target, dt, self.occlusion_smoothing_time);
+                    }
+                }
+            }
+            drop(occlusion_provider);
+
             for source in self
                 .sources
                 .iter_mut()
@@ -268,26 +471,55 @@ impl State {
             {
                 source.render(buf.len());
 
+                if let SoundSource::Spatial(spatial) = source {
+                    spatial.apply_occlusion(
+                        self.occlusion_gain,
+                        self.occlusion_cutoff / SAMPLE_RATE as f32,
+                    );
+                }
+
+                let (bus_buf, bus_surround_buf) = self.bus_graph.input_buffers(source.bus());
+
                 match self.renderer {
+                    Renderer::Default if layout != ChannelLayout::Stereo => {
+                        render_source_surround(
+                            source,
+                            &self.listener,
+                            self.distance_model,
+                            layout,
+                            bus_buf,
+                            bus_surround_buf,
+                        );
+                    }
                     Renderer::Default => {
                         // Simple rendering path. Much faster (4-5 times) than HRTF path.
-                        render_source_default(source, &self.listener, self.distance_model, buf);
+                        render_source_default(source, &self.listener, self.distance_model, bus_buf);
                     }
+                    // HRTF is binaural, so it is played by front speakers of any layout.
                     Renderer::HrtfRenderer(ref mut hrtf_renderer) => {
                         hrtf_renderer.render_source(
                             source,
                             &self.listener,
                             self.distance_model,
-                            buf,
+                            bus_buf,
                         );
                     }
                 }
             }
 
+            // Effects are routed to the master bus, so they're affected by its gain and effects.
+            let master_buf = self.bus_graph.input_buffer(MASTER_BUS);
             for effect in self.effects.iter_mut() {
-                effect.render(&self.sources, &self.listener, self.distance_model, buf);
+                effect.render(
+                    &self.sources,
+                    &self.listener,
+                    self.distance_model,
+                    master_buf,
+                );
             }
 
+            self.bus_graph.end_render(buf, surround_buf);
+
             let global_gain = self.master_gain * master_gain;
 
             // Apply master gain to be able to control total sound volume.
@@ -295,6 +527,9 @@ impl State {
                 *left *= global_gain;
                 *right *= global_gain;
             }
+            for sample in surround_buf {
+                *sample *= global_gain;
+            }
         }
 
         self.render_duration = rg3d_core::instant::Instant::now() - last_time;
@@ -306,6 +541,9 @@ impl SoundContext {
     ///       HRTF length for faster FFT calculations. Find a better way of selecting this.
     pub const HRTF_BLOCK_LEN: usize = 513;
 
+    /// Speed of sound in air in meters per second, it is default speed of sound of a context.
+    pub const DEFAULT_SPEED_OF_SOUND: f32 = 343.3;
+
     pub(in crate) const HRTF_INTERPOLATION_STEPS: usize = 8;
 
     pub(in crate) const SAMPLES_PER_CHANNEL: usize =
@@ -325,6 +563,14 @@ impl SoundContext {
                 effects: Pool::new(),
                 distance_model: DistanceModel::InverseDistance,
                 paused: false,
+                bus_graph: AudioBusGraph::new(),
+                doppler_factor: 1.0,
+                speed_of_sound: SoundContext::DEFAULT_SPEED_OF_SOUND,
+                occlusion_provider: None,
+                occlusion_gain: 0.3,
+                occlusion_cutoff: 1000.0,
+                occlusion_smoothing_time: 0.1,
+                cue_instances: Default::default(),
             }))),
         }
     }
@@ -381,6 +627,7 @@ impl Visit for State {
             self.sources.clear();
             self.effects.clear();
             self.renderer = Renderer::Default;
+            self.cue_instances.clear();
         }
 
         self.master_gain.visit("MasterGain", visitor)?;
@@ -389,6 +636,17 @@ impl Visit for State {
         self.effects.visit("Effects", visitor)?;
         self.renderer.visit("Renderer", visitor)?;
         let _ = self.paused.visit("Paused", visitor);
+        let _ = self.doppler_factor.visit("DopplerFactor", visitor);
+        let _ = self.speed_of_sound.visit("SpeedOfSound", visitor);
+        let _ = self.occlusion_gain.visit("OcclusionGain", visitor);
+        let _ = self.occlusion_cutoff.visit("OcclusionCutoff", visitor);
+        let _ = self
+            .occlusion_smoothing_time
+            .visit("OcclusionSmoothingTime", visitor);
+        if visitor.is_reading() {
+            self.bus_graph = AudioBusGraph::new();
+        }
+        let _ = self.bus_graph.visit("BusGraph", visitor);
 
         let mut distance_model = self.distance_model as u32;
         distance_model.visit("DistanceModel", visitor)?;
@@ -0,0 +1,658 @@
+//! Sound cue module.
+//!
+//! # Overview
+//!
+//! Sound cue is a container of sound buffers with a set of rules of how to play them. Each time a
+//! cue is played, it selects one of its buffers and creates new sound source with slightly
+//! randomized pitch and gain. This is very useful for sounds that are played often - footsteps,
+//! gunshots, impacts, etc. - they won't sound monotonous.
+//!
+//! A cue also limits amount of its instances that could be played at the same time, when the
+//! limit is reached it either rejects new instance or "steals" one of existing instances, see
+//! [`StealingPolicy`]. Optional cooldown prevents a cue from being played too often.
+//!
+//! # Usage
+//!
+//! Cue can be created manually or loaded from a file by resource manager of the engine.
+//!
+//! ```no_run
+//! use rg3d_sound::buffer::SoundBufferResource;
+//! use rg3d_sound::context::SoundContext;
+//! use rg3d_sound::cue::{SelectionMode, SoundCue, SoundCueEntry, SoundCueResource};
+//! use rg3d_sound::numeric_range::NumericRange;
+//!
+//! fn footstep(context: &SoundContext, steps: Vec<SoundBufferResource>) -> SoundCueResource {
+//!     let mut cue = SoundCue::new(
+//!         steps
+//!             .into_iter()
+//!             .map(|buffer| SoundCueEntry::new(buffer, 1.0))
+//!             .collect(),
+//!     );
+//!     cue.set_selection_mode(SelectionMode::Shuffle)
+//!         .set_pitch(NumericRange::new(0.9, 1.1))
+//!         .set_gain(NumericRange::new(0.8, 1.0))
+//!         .set_max_instances(Some(4));
+//!
+//!     let cue = SoundCueResource::new(cue);
+//!     context.state().play_cue(&cue);
+//!     cue
+//! }
+//! ```
+
+use crate::{
+    buffer::SoundBufferResource,
+    source::{generic::GenericSourceBuilder, spatial::SpatialSourceBuilder, SoundSource, Status},
+};
+use rg3d_core::{
+    algebra::Vector3,
+    instant::Instant,
+    io::FileLoadError,
+    numeric_range::NumericRange,
+    pool::{Handle, Pool},
+    rand::{seq::SliceRandom, thread_rng, Rng},
+    visitor::prelude::*,
+};
+use rg3d_resource::{define_new_resource, Resource, ResourceData, ResourceState};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Defines how a cue selects next buffer to play.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub enum SelectionMode {
+    /// Random buffer is selected, probability of selection of a buffer is proportional to its
+    /// weight.
+    Random,
+
+    /// Buffers are played in random order, but every buffer is played once before any of them
+    /// will be played again. Weights are ignored.
+    Shuffle,
+
+    /// Buffers are played one after another in order of definition. Weights are ignored.
+    Sequential,
+}
+
+impl Default for SelectionMode {
+    fn default() -> Self {
+        Self::Random
+    }
+}
+
+impl SelectionMode {
+    fn id(self) -> u32 {
+        match self {
+            Self::Random => 0,
+            Self::Shuffle => 1,
+            Self::Sequential => 2,
+        }
+    }
+
+    fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::Random),
+            1 => Ok(Self::Shuffle),
+            2 => Ok(Self::Sequential),
+            _ => Err(format!("Invalid selection mode id {}!", id)),
+        }
+    }
+}
+
+/// Defines what to do when a cue is played, but it already has maximum amount of playing
+/// instances.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub enum StealingPolicy {
+    /// New instance won't be played.
+    Reject,
+
+    /// Oldest instance will be stopped and new instance will be played instead.
+    Oldest,
+
+    /// Instance with lowest gain will be stopped and new instance will be played instead.
+    Quietest,
+}
+
+impl Default for StealingPolicy {
+    fn default() -> Self {
+        Self::Oldest
+    }
+}
+
+impl StealingPolicy {
+    fn id(self) -> u32 {
+        match self {
+            Self::Reject => 0,
+            Self::Oldest => 1,
+            Self::Quietest => 2,
+        }
+    }
+
+    fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::Reject),
+            1 => Ok(Self::Oldest),
+            2 => Ok(Self::Quietest),
+            _ => Err(format!("Invalid stealing policy id {}!", id)),
+        }
+    }
+}
+
+/// A buffer of a cue with its weight.
+#[derive(Debug, Clone, Default)]
+pub struct SoundCueEntry {
+    buffer: Option<SoundBufferResource>,
+    weight: f32,
+}
+
+impl SoundCueEntry {
+    /// Creates new entry. Weight defines probability of selection of the buffer in
+    /// [`SelectionMode::Random`] mode, negative weights are clamped to zero.
+    pub fn new(buffer: SoundBufferResource, weight: f32) -> Self {
+        Self {
+            buffer: Some(buffer),
+            weight: weight.max(0.0),
+        }
+    }
+
+    /// Returns buffer of the entry.
+    pub fn buffer(&self) -> Option<SoundBufferResource> {
+        self.buffer.clone()
+    }
+
+    /// Returns weight of the entry.
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    // Buffers that are still loading or failed to load are skipped.
+    fn is_playable(&self) -> bool {
+        match self.buffer {
+            Some(ref buffer) => matches!(*buffer.state(), ResourceState::Ok(_)),
+            None => false,
+        }
+    }
+}
+
+impl Visit for SoundCueEntry {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.buffer.visit("Buffer", visitor)?;
+        self.weight.visit("Weight", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// See module docs.
+#[derive(Debug, Clone)]
+pub struct SoundCue {
+    path: PathBuf,
+    entries: Vec<SoundCueEntry>,
+    selection_mode: SelectionMode,
+    pitch: NumericRange,
+    gain: NumericRange,
+    max_instances: Option<u32>,
+    stealing_policy: StealingPolicy,
+    cooldown: f32,
+    // Runtime state of the cue.
+    next: usize,
+    shuffle_bag: Vec<usize>,
+    last_selected: Option<usize>,
+    last_play_time: Option<Instant>,
+}
+
+impl Default for SoundCue {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl SoundCue {
+    /// Creates new cue with given entries. By default the cue selects buffers randomly, does
+    /// not change pitch and gain, has unlimited amount of instances and no cooldown.
+    pub fn new(entries: Vec<SoundCueEntry>) -> Self {
+        Self {
+            path: Default::default(),
+            entries,
+            selection_mode: Default::default(),
+            pitch: NumericRange::new(1.0, 1.0),
+            gain: NumericRange::new(1.0, 1.0),
+            max_instances: None,
+            stealing_policy: Default::default(),
+            cooldown: 0.0,
+            next: 0,
+            shuffle_bag: Default::default(),
+            last_selected: None,
+            last_play_time: None,
+        }
+    }
+
+    /// Sets new entries of the cue.
+    pub fn set_entries(&mut self, entries: Vec<SoundCueEntry>) -> &mut Self {
+        self.entries = entries;
+        self.next = 0;
+        self.shuffle_bag.clear();
+        self.last_selected = None;
+        self
+    }
+
+    /// Returns entries of the cue.
+    pub fn entries(&self) -> &[SoundCueEntry] {
+        &self.entries
+    }
+
+    /// Sets the way of selection of next buffer.
+    pub fn set_selection_mode(&mut self, selection_mode: SelectionMode) -> &mut Self {
+        self.selection_mode = selection_mode;
+        self
+    }
+
+    /// Returns current selection mode.
+    pub fn selection_mode(&self) -> SelectionMode {
+        self.selection_mode
+    }
+
+    /// Sets range of pitch, every new instance will have random pitch from the range.
+    pub fn set_pitch(&mut self, pitch: NumericRange) -> &mut Self {
+        self.pitch = pitch;
+        self
+    }
+
+    /// Returns range of pitch.
+    pub fn pitch(&self) -> NumericRange {
+        self.pitch
+    }
+
+    /// Sets range of gain, every new instance will have random gain from the range.
+    pub fn set_gain(&mut self, gain: NumericRange) -> &mut Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Returns range of gain.
+    pub fn gain(&self) -> NumericRange {
+        self.gain
+    }
+
+    /// Sets maximum amount of instances of the cue that could be played at the same time.
+    /// `None` means that amount of instances is unlimited.
+    pub fn set_max_instances(&mut self, max_instances: Option<u32>) -> &mut Self {
+        self.max_instances = max_instances;
+        self
+    }
+
+    /// Returns maximum amount of instances of the cue.
+    pub fn max_instances(&self) -> Option<u32> {
+        self.max_instances
+    }
+
+    /// Sets what to do when maximum amount of instances is reached.
+    pub fn set_stealing_policy(&mut self, stealing_policy: StealingPolicy) -> &mut Self {
+        self.stealing_policy = stealing_policy;
+        self
+    }
+
+    /// Returns current stealing policy.
+    pub fn stealing_policy(&self) -> StealingPolicy {
+        self.stealing_policy
+    }
+
+    /// Sets minimal time between two consecutive plays of the cue. Attempts to play the cue
+    /// during cooldown are ignored.
+    pub fn set_cooldown(&mut self, cooldown: Duration) -> &mut Self {
+        self.cooldown = cooldown.as_secs_f32();
+        self
+    }
+
+    /// Returns cooldown of the cue.
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs_f32(self.cooldown)
+    }
+
+    fn select(&mut self) -> Option<usize> {
+        if !self.entries.iter().any(|entry| entry.is_playable()) {
+            return None;
+        }
+
+        let index = match self.selection_mode {
+            SelectionMode::Random => {
+                let total_weight = self
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.is_playable())
+                    .map(|entry| entry.weight)
+                    .sum::<f32>();
+                if total_weight <= 0.0 {
+                    return None;
+                }
+
+                let mut point = thread_rng().gen_range(0.0..total_weight);
+                let mut selected = None;
+                for (index, entry) in self.entries.iter().enumerate() {
+                    if entry.is_playable() && entry.weight > 0.0 {
+                        selected = Some(index);
+                        if point < entry.weight {
+                            break;
+                        }
+                        point -= entry.weight;
+                    }
+                }
+                selected?
+            }
+            SelectionMode::Shuffle => loop {
+                if self.shuffle_bag.is_empty() {
+                    self.shuffle_bag = (0..self.entries.len()).collect();
+                    self.shuffle_bag.shuffle(&mut thread_rng());
+                    // Bag is popped from the end, make sure that last buffer of previous round
+                    // won't be played twice in a row.
+                    if self.shuffle_bag.len() > 1
+                        && self.shuffle_bag.last() == self.last_selected.as_ref()
+                    {
+                        self.shuffle_bag.swap(0, self.entries.len() - 1);
+                    }
+                }
+                let index = self.shuffle_bag.pop()?;
+                if self.entries[index].is_playable() {
+                    break index;
+                }
+            },
+            SelectionMode::Sequential => {
+                let count = self.entries.len();
+                let index = (0..count)
+                    .map(|i| (self.next + i) % count)
+                    .find(|&index| self.entries[index].is_playable())?;
+                self.next = (index + 1) % count;
+                index
+            }
+        };
+
+        self.last_selected = Some(index);
+
+        Some(index)
+    }
+
+    // Makes room for a new instance, returns false if it is impossible.
+    fn steal(
+        &self,
+        sources: &mut Pool<SoundSource>,
+        instances: &mut Vec<Handle<SoundSource>>,
+    ) -> bool {
+        let max_instances = match self.max_instances {
+            Some(max_instances) => max_instances as usize,
+            None => return true,
+        };
+
+        while instances.len() >= max_instances {
+            let position = match self.stealing_policy {
+                StealingPolicy::Reject => None,
+                StealingPolicy::Oldest => {
+                    if instances.is_empty() {
+                        None
+                    } else {
+                        Some(0)
+                    }
+                }
+                StealingPolicy::Quietest => instances
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| {
+                        sources
+                            .borrow(**a)
+                            .gain()
+                            .partial_cmp(&sources.borrow(**b).gain())
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    })
+                    .map(|(position, _)| position),
+            };
+
+            match position {
+                Some(position) => {
+                    let handle = instances.remove(position);
+                    sources.free(handle);
+                }
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    // Instances are tracked by a context, because the cue could be played in multiple contexts
+    // and handles are valid only within a context that created them.
+    pub(in crate) fn instantiate(
+        &mut self,
+        sources: &mut Pool<SoundSource>,
+        instances: &mut Vec<Handle<SoundSource>>,
+        position: Option<Vector3<f32>>,
+    ) -> Option<Handle<SoundSource>> {
+        instances.retain(|&handle| {
+            sources.is_valid_handle(handle) && sources.borrow(handle).status() != Status::Stopped
+        });
+
+        let now = Instant::now();
+        if let Some(last_play_time) = self.last_play_time {
+            if (now - last_play_time).as_secs_f32() < self.cooldown {
+                return None;
+            }
+        }
+
+        let index = self.select()?;
+        let generic = GenericSourceBuilder::new()
+            .with_buffer(self.entries[index].buffer.clone()?)
+            .with_pitch(self.pitch.random())
+            .with_gain(self.gain.random())
+            .with_play_once(true)
+            .with_status(Status::Playing)
+            .build()
+            .ok()?;
+        let source = match position {
+            Some(position) => SpatialSourceBuilder::new(generic)
+                .with_position(position)
+                .build_source(),
+            None => SoundSource::Generic(generic),
+        };
+
+        if !self.steal(sources, instances) {
+            return None;
+        }
+
+        let handle = sources.spawn(source);
+        instances.push(handle);
+        self.last_play_time = Some(now);
+
+        Some(handle)
+    }
+}
+
+impl Visit for SoundCue {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.path.visit("Path", visitor)?;
+        self.entries.visit("Entries", visitor)?;
+        let mut selection_mode = self.selection_mode.id();
+        selection_mode.visit("SelectionMode", visitor)?;
+        self.pitch.visit("Pitch", visitor)?;
+        self.gain.visit("Gain", visitor)?;
+        self.max_instances.visit("MaxInstances", visitor)?;
+        let mut stealing_policy = self.stealing_policy.id();
+        stealing_policy.visit("StealingPolicy", visitor)?;
+        self.cooldown.visit("Cooldown", visitor)?;
+
+        if visitor.is_reading() {
+            self.selection_mode = SelectionMode::from_id(selection_mode)?;
+            self.stealing_policy = StealingPolicy::from_id(stealing_policy)?;
+            if !(self.cooldown.is_finite() && self.cooldown >= 0.0) {
+                return Err(format!("Invalid cooldown {} of sound cue!", self.cooldown).into());
+            }
+        }
+
+        visitor.leave_region()
+    }
+}
+
+impl ResourceData for SoundCue {
+    fn path(&self) -> Cow<Path> {
+        Cow::from(&self.path)
+    }
+
+    fn set_path(&mut self, path: PathBuf) {
+        self.path = path;
+    }
+}
+
+/// An error that can occur during loading of sound cue.
+#[derive(Debug)]
+pub enum SoundCueLoadError {
+    /// File load error.
+    Io(FileLoadError),
+    /// Definition of the cue is malformed.
+    Parse(String),
+}
+
+define_new_resource!(
+    /// A shared sound cue resource.
+    SoundCueResource<SoundCue, SoundCueLoadError>
+);
+
+impl SoundCueResource {
+    /// Creates new sound cue resource from given cue.
+    pub fn new(cue: SoundCue) -> Self {
+        Self(Resource::new(ResourceState::Ok(cue)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        buffer::{DataSource, SoundBufferResource},
+        context::SoundContext,
+        cue::{SelectionMode, SoundCue, SoundCueEntry, SoundCueResource, StealingPolicy},
+        source::SoundSource,
+    };
+    use rg3d_core::{numeric_range::NumericRange, pool::Pool};
+    use std::time::Duration;
+
+    fn make_cue(weights: &[f32]) -> SoundCue {
+        SoundCue::new(
+            weights
+                .iter()
+                .map(|&weight| {
+                    let buffer = SoundBufferResource::new_generic(DataSource::Raw {
+                        sample_rate: 44100,
+                        channel_count: 1,
+                        samples: vec![0.0; 100],
+                    })
+                    .unwrap();
+                    SoundCueEntry::new(buffer, weight)
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut cue = make_cue(&[1.0, 1.0, 1.0]);
+
+        cue.set_selection_mode(SelectionMode::Sequential);
+        let sequence = (0..5).map(|_| cue.select().unwrap()).collect::<Vec<_>>();
+        assert_eq!(sequence, vec![0, 1, 2, 0, 1]);
+
+        // Every buffer is played once per round, and there are no repeats between rounds.
+        cue.set_selection_mode(SelectionMode::Shuffle);
+        let mut prev = None;
+        for _ in 0..10 {
+            let mut round = (0..3).map(|_| cue.select().unwrap()).collect::<Vec<_>>();
+            assert_ne!(Some(round[0]), prev);
+            prev = round.last().cloned();
+            round.sort_unstable();
+            assert_eq!(round, vec![0, 1, 2]);
+        }
+
+        // Buffers with zero weight are never selected.
+        let mut cue = make_cue(&[0.0, 3.0, 0.0, 1.0]);
+        let mut counts = [0; 4];
+        for _ in 0..1000 {
+            counts[cue.select().unwrap()] += 1;
+        }
+        assert_eq!(counts[0] + counts[2], 0);
+        assert!(counts[1] > 2 * counts[3]);
+    }
+
+    #[test]
+    fn test_instances() {
+        let mut sources = Pool::<SoundSource>::new();
+        let mut instances = Vec::new();
+        let mut cue = make_cue(&[1.0]);
+        cue.set_gain(NumericRange::new(0.5, 1.0))
+            .set_pitch(NumericRange::new(0.9, 1.1))
+            .set_max_instances(Some(2))
+            .set_stealing_policy(StealingPolicy::Oldest);
+
+        let first = cue.instantiate(&mut sources, &mut instances, None).unwrap();
+        let gain = sources.borrow(first).gain();
+        let pitch = sources.borrow(first).pitch();
+        assert!((0.5..=1.0).contains(&gain));
+        assert!((0.9..=1.1).contains(&pitch));
+
+        // Oldest instance is replaced.
+        let second = cue.instantiate(&mut sources, &mut instances, None).unwrap();
+        let third = cue.instantiate(&mut sources, &mut instances, None).unwrap();
+        assert!(!sources.is_valid_handle(first));
+        assert_eq!(instances, [second, third]);
+
+        // Quietest instance is replaced.
+        cue.set_stealing_policy(StealingPolicy::Quietest);
+        sources.borrow_mut(second).set_gain(1.0);
+        sources.borrow_mut(third).set_gain(0.1);
+        let fourth = cue.instantiate(&mut sources, &mut instances, None).unwrap();
+        assert_eq!(instances, [second, fourth]);
+
+        // New instances are rejected.
+        cue.set_stealing_policy(StealingPolicy::Reject);
+        assert!(cue.instantiate(&mut sources, &mut instances, None).is_none());
+        assert_eq!(sources.alive_count(), 2);
+
+        // Nothing is stolen if there is nothing to play.
+        cue.set_stealing_policy(StealingPolicy::Oldest);
+        cue.set_entries(make_cue(&[0.0]).entries);
+        assert!(cue.instantiate(&mut sources, &mut instances, None).is_none());
+        assert_eq!(instances, [second, fourth]);
+        assert_eq!(sources.alive_count(), 2);
+    }
+
+    #[test]
+    fn test_instances_per_context() {
+        let mut cue = make_cue(&[1.0]);
+        cue.set_max_instances(Some(1))
+            .set_stealing_policy(StealingPolicy::Reject);
+        let cue = SoundCueResource::new(cue);
+
+        // Limit is applied to every context separately.
+        let first = SoundContext::new();
+        let second = SoundContext::new();
+        let handle = first.state().play_cue(&cue).unwrap();
+        assert!(first.state().play_cue(&cue).is_none());
+        assert!(second.state().play_cue(&cue).is_some());
+        assert_eq!(first.state().cue_instances(&cue), &[handle]);
+        assert_eq!(second.state().cue_instances(&cue).len(), 1);
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let mut sources = Pool::<SoundSource>::new();
+        let mut instances = Vec::new();
+        let mut cue = make_cue(&[1.0]);
+        cue.set_cooldown(Duration::from_secs(100));
+
+        assert!(cue.instantiate(&mut sources, &mut instances, None).is_some());
+        assert!(cue.instantiate(&mut sources, &mut instances, None).is_none());
+
+        cue.set_cooldown(Duration::from_secs(0));
+        assert!(cue.instantiate(&mut sources, &mut instances, None).is_some());
+    }
+}
@@ -1,10 +1,15 @@
 use crate::{
-    buffer::DataSource,
-    decoder::{vorbis::OggDecoder, wav::WavDecoder},
+    buffer::{DataSource, RawStreamingDataSource},
+    decoder::{
+        symphonia::{is_flac, is_mp3, SymphoniaDecoder},
+        vorbis::OggDecoder,
+        wav::WavDecoder,
+    },
     error::SoundError,
 };
 use std::time::Duration;
 
+mod symphonia;
 mod vorbis;
 mod wav;
 
@@ -13,6 +18,9 @@ pub(in crate) enum Decoder {
     Null,
     Wav(WavDecoder),
     Ogg(OggDecoder),
+    Flac(SymphoniaDecoder),
+    Mp3(SymphoniaDecoder),
+    Custom(Box<dyn RawStreamingDataSource>),
 }
 
 impl Iterator for Decoder {
@@ -22,30 +30,49 @@ impl Iterator for Decoder {
         match self {
             Decoder::Wav(wav) => wav.next(),
             Decoder::Ogg(ogg) => ogg.next(),
+            Decoder::Flac(flac) => flac.next(),
+            Decoder::Mp3(mp3) => mp3.next(),
+            Decoder::Custom(custom) => custom.next(),
             Decoder::Null => None,
         }
     }
 }
 
 impl Decoder {
-    pub fn new(source: DataSource) -> Result<Self, DataSource> {
+    pub fn new(source: DataSource) -> Result<Self, SoundError> {
+        // Samples are produced by the user, there is nothing to decode.
+        let source = match source {
+            DataSource::RawStreaming(custom) => return Ok(Decoder::Custom(custom)),
+            _ => source,
+        };
         // Try Wav
         let source = match WavDecoder::new(source) {
             Ok(wav_decoder) => return Ok(Decoder::Wav(wav_decoder)),
             Err(source) => source,
         };
         // Try Vorbis/Ogg
-        let source = match OggDecoder::new(source) {
+        let mut source = match OggDecoder::new(source) {
             Ok(ogg_decoder) => return Ok(Decoder::Ogg(ogg_decoder)),
             Err(source) => source,
         };
-        Err(source)
+        // Try FLAC and MP3. Source is consumed by the reader, so once the format is detected
+        // by magic bytes, an error of the decoder is returned as is.
+        if is_flac(&mut source) {
+            return Ok(Decoder::Flac(SymphoniaDecoder::new_flac(source)?));
+        }
+        if is_mp3(&mut source) {
+            return Ok(Decoder::Mp3(SymphoniaDecoder::new_mp3(source)?));
+        }
+        Err(SoundError::UnsupportedFormat)
     }
 
     pub fn rewind(&mut self) -> Result<(), SoundError> {
         match self {
             Decoder::Wav(wav) => wav.rewind(),
             Decoder::Ogg(ogg) => ogg.rewind(),
+            Decoder::Flac(flac) => flac.rewind(),
+            Decoder::Mp3(mp3) => mp3.rewind(),
+            Decoder::Custom(custom) => custom.rewind(),
             Decoder::Null => Ok(()),
         }
     }
@@ -54,6 +81,9 @@ impl Decoder {
         match self {
             Decoder::Wav(wav) => wav.time_seek(location),
             Decoder::Ogg(ogg) => ogg.time_seek(location),
+            Decoder::Flac(flac) => flac.time_seek(location),
+            Decoder::Mp3(mp3) => mp3.time_seek(location),
+            Decoder::Custom(custom) => custom.time_seek(location),
             Decoder::Null => (),
         }
     }
@@ -62,6 +92,9 @@ impl Decoder {
         match self {
             Decoder::Wav(wav) => wav.channel_count(),
             Decoder::Ogg(ogg) => ogg.channel_count,
+            Decoder::Flac(flac) => flac.channel_count(),
+            Decoder::Mp3(mp3) => mp3.channel_count(),
+            Decoder::Custom(custom) => custom.channel_count(),
             Decoder::Null => 0,
         }
     }
@@ -70,6 +103,9 @@ impl Decoder {
         match self {
             Decoder::Wav(wav) => wav.sample_rate(),
             Decoder::Ogg(ogg) => ogg.sample_rate,
+            Decoder::Flac(flac) => flac.sample_rate(),
+            Decoder::Mp3(mp3) => mp3.sample_rate(),
+            Decoder::Custom(custom) => custom.sample_rate(),
             Decoder::Null => 0,
         }
     }
@@ -82,7 +118,138 @@ impl Decoder {
         match self {
             Decoder::Wav(wav) => wav.duration(),
             Decoder::Ogg(ogg) => ogg.duration(),
+            Decoder::Flac(flac) => flac.duration(),
+            Decoder::Mp3(mp3) => mp3.duration(),
+            Decoder::Custom(custom) => custom.duration(),
             Decoder::Null => None,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        buffer::{generic::GenericBuffer, streaming::StreamingBuffer, DataSource},
+        decoder::Decoder,
+        error::SoundError,
+    };
+    use std::time::Duration;
+
+    // Stereo, 44100 Hz, 16-bit, 200 ms. Left channel is 440 Hz sine with amplitude of 0.5,
+    // right channel is 880 Hz sine with amplitude of 0.25.
+    const FLAC: &[u8] = include_bytes!("test_data/sine.flac");
+    // Mono, 44100 Hz, 64 kbps CBR, 20 frames. A tone near 440 Hz with peak amplitude of ~0.3.
+    const MP3: &[u8] = include_bytes!("test_data/sine.mp3");
+
+    fn decoder(data: &[u8]) -> Decoder {
+        Decoder::new(DataSource::from_memory(data.to_vec())).unwrap()
+    }
+
+    fn expected_flac_sample(index: usize) -> f32 {
+        // Samples are interleaved, even samples belong to the left channel.
+        let (frequency, amplitude) = match index % 2 {
+            0 => (440.0, 16384.0),
+            _ => (880.0, 8192.0),
+        };
+        let t = (index / 2) as f64 / 44100.0;
+        ((2.0 * std::f64::consts::PI * frequency * t).sin() * amplitude).round() as f32 / 32768.0
+    }
+
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count()
+    }
+
+    #[test]
+    fn test_flac_decoder() {
+        let decoder = decoder(FLAC);
+        assert!(matches!(decoder, Decoder::Flac(_)));
+        assert_eq!(decoder.get_channel_count(), 2);
+        assert_eq!(decoder.get_sample_rate(), 44100);
+        assert_eq!(decoder.duration(), Some(Duration::from_millis(200)));
+
+        // FLAC is lossless, so every sample must be restored.
+        let samples = decoder.into_samples();
+        assert_eq!(samples.len(), 8820 * 2);
+        for (i, sample) in samples.iter().enumerate() {
+            assert!((sample - expected_flac_sample(i)).abs() <= 1.0 / 32768.0);
+        }
+    }
+
+    #[test]
+    fn test_mp3_decoder() {
+        let decoder = decoder(MP3);
+        assert!(matches!(decoder, Decoder::Mp3(_)));
+        assert_eq!(decoder.get_channel_count(), 1);
+        assert_eq!(decoder.get_sample_rate(), 44100);
+        let duration = decoder.duration().unwrap();
+        assert!((duration.as_secs_f32() - 20.0 * 1152.0 / 44100.0).abs() < 0.001);
+
+        let samples = decoder.into_samples();
+        assert_eq!(samples.len(), 20 * 1152);
+        let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!(peak > 0.2 && peak < 0.4);
+        // ~44 periods of the tone in 100 ms.
+        let crossings = zero_crossings(&samples[4410..8820]);
+        assert!((80..=96).contains(&crossings));
+    }
+
+    #[test]
+    fn test_seek_and_rewind() {
+        for data in [FLAC, MP3] {
+            let reference = decoder(data).into_samples();
+
+            let mut decoder = decoder(data);
+            let channel_count = decoder.get_channel_count();
+            let head = decoder.by_ref().take(1000).collect::<Vec<_>>();
+            assert_eq!(head, reference[..1000]);
+
+            decoder.time_seek(Duration::from_millis(100));
+            let offset = 4410 * channel_count;
+            let tail = decoder.by_ref().take(1000).collect::<Vec<_>>();
+            assert_eq!(tail.len(), 1000);
+            for (a, b) in tail.iter().zip(&reference[offset..]) {
+                assert!((a - b).abs() < 1.0e-3);
+            }
+
+            decoder.rewind().unwrap();
+            assert_eq!(decoder.into_samples(), reference);
+        }
+    }
+
+    #[test]
+    fn test_buffers() {
+        for (data, channel_count, sample_count) in [(FLAC, 2, 8820 * 2), (MP3, 1, 20 * 1152)] {
+            let generic = GenericBuffer::new(DataSource::from_memory(data.to_vec())).unwrap();
+            assert_eq!(generic.channel_count(), channel_count);
+            assert_eq!(generic.sample_rate(), 44100);
+            assert_eq!(generic.samples().len(), sample_count);
+
+            let streaming = StreamingBuffer::new(DataSource::from_memory(data.to_vec())).unwrap();
+            assert_eq!(streaming.channel_count(), channel_count);
+            assert_eq!(streaming.samples(), generic.samples());
+            assert!(streaming.duration().is_some());
+        }
+    }
+
+    #[test]
+    fn test_corrupted_data() {
+        for data in [FLAC, MP3] {
+            // Keep magic bytes, but corrupt everything else.
+            let mut corrupted = data[..64].to_vec();
+            corrupted[4..].iter_mut().for_each(|b| *b = 0xAA);
+            match Decoder::new(DataSource::from_memory(corrupted)) {
+                Err(SoundError::UnsupportedFormat) => panic!("decoder error must not be hidden"),
+                Err(_) => (),
+                Ok(_) => panic!("corrupted data must not be decoded"),
+            }
+        }
+
+        assert!(matches!(
+            Decoder::new(DataSource::from_memory(vec![0; 64])),
+            Err(SoundError::UnsupportedFormat)
+        ));
+    }
+}
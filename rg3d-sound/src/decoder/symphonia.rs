@@ -0,0 +1,279 @@
+//! FLAC and MP3 decoders, both are backed by `symphonia` crate.
+
+use crate::{
+    buffer::DataSource,
+    error::{DecoderError, SoundError},
+};
+use std::{
+    fmt::{Debug, Formatter},
+    io::{Cursor, Read, Seek, SeekFrom},
+    time::Duration,
+};
+use symphonia::{
+    core::{
+        audio::SampleBuffer,
+        codecs::{Decoder as CodecDecoder, DecoderOptions},
+        errors::Error,
+        formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+        io::{MediaSource, MediaSourceStream},
+    },
+    default::{
+        codecs::{FlacDecoder, MpaDecoder},
+        formats::{FlacReader, MpaReader},
+    },
+};
+
+/// Byte stream of a data source for symphonia readers. Symphonia requires media sources to be
+/// `Sync`, raw streaming sources are not required to be `Sync`, so only the variants that have
+/// bytes are taken from the data source.
+enum MediaSourceAdapter {
+    #[cfg(not(target_arch = "wasm32"))]
+    File(std::io::BufReader<std::fs::File>),
+    Memory(Cursor<Vec<u8>>),
+}
+
+impl MediaSourceAdapter {
+    fn new(source: DataSource) -> Result<Self, DataSource> {
+        match source {
+            #[cfg(not(target_arch = "wasm32"))]
+            DataSource::File { data, .. } => Ok(Self::File(data)),
+            #[cfg(target_arch = "wasm32")]
+            DataSource::File { data, .. } => Ok(Self::Memory(data)),
+            DataSource::Memory(memory) => Ok(Self::Memory(memory)),
+            DataSource::Raw { .. } | DataSource::RawStreaming(_) => Err(source),
+        }
+    }
+}
+
+impl Read for MediaSourceAdapter {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(file) => file.read(buf),
+            Self::Memory(memory) => memory.read(buf),
+        }
+    }
+}
+
+impl Seek for MediaSourceAdapter {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(file) => file.seek(pos),
+            Self::Memory(memory) => memory.seek(pos),
+        }
+    }
+}
+
+impl MediaSource for MediaSourceAdapter {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(file) => file.get_ref().metadata().ok().map(|m| m.len()),
+            Self::Memory(memory) => Some(memory.get_ref().len() as u64),
+        }
+    }
+}
+
+impl From<Error> for SoundError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::IoError(io) => SoundError::Io(io),
+            _ => SoundError::DecoderError(DecoderError::Symphonia(e)),
+        }
+    }
+}
+
+/// Reads few first bytes of the source and rewinds it back.
+fn read_magic(source: &mut DataSource) -> Option<[u8; 4]> {
+    if let DataSource::Raw { .. } | DataSource::RawStreaming(_) = source {
+        return None;
+    }
+    let pos = source.stream_position().ok()?;
+    let mut magic = [0; 4];
+    let result = source.read_exact(&mut magic);
+    source.seek(SeekFrom::Start(pos)).ok()?;
+    result.ok().map(|_| magic)
+}
+
+pub(in crate) fn is_flac(source: &mut DataSource) -> bool {
+    matches!(read_magic(source), Some(magic) if &magic == b"fLaC")
+}
+
+pub(in crate) fn is_mp3(source: &mut DataSource) -> bool {
+    match read_magic(source) {
+        // ID3v2 tag is commonly placed in the beginning of MP3 files.
+        Some([b'I', b'D', b'3', _]) => true,
+        // Frame sync and MPEG Layer III.
+        Some([0xFF, b, _, _]) => b & 0xE0 == 0xE0 && (b >> 1) & 0x03 == 0x01,
+        _ => false,
+    }
+}
+
+pub(in crate) struct SymphoniaDecoder {
+    reader: Box<dyn FormatReader>,
+    decoder: Box<dyn CodecDecoder>,
+    track_id: u32,
+    buffer: Option<SampleBuffer<f32>>,
+    position: usize,
+    // Amount of samples that must be skipped after seeking, format readers seek to the
+    // beginning of a packet, not to an exact sample.
+    skip: usize,
+    channel_count: usize,
+    sample_rate: usize,
+    frame_count: Option<u64>,
+}
+
+impl Debug for SymphoniaDecoder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SymphoniaDecoder")
+    }
+}
+
+impl SymphoniaDecoder {
+    /// Creates FLAC decoder, the source must be checked with [`is_flac`] first.
+    pub fn new_flac(source: DataSource) -> Result<Self, SoundError> {
+        Self::new::<FlacReader, FlacDecoder>(source)
+    }
+
+    /// Creates MP3 decoder, the source must be checked with [`is_mp3`] first.
+    pub fn new_mp3(source: DataSource) -> Result<Self, SoundError> {
+        Self::new::<MpaReader, MpaDecoder>(source)
+    }
+
+    fn new<R, D>(source: DataSource) -> Result<Self, SoundError>
+    where
+        R: FormatReader + 'static,
+        D: CodecDecoder + 'static,
+    {
+        let source = MediaSourceAdapter::new(source).map_err(|_| SoundError::UnsupportedFormat)?;
+        let stream = MediaSourceStream::new(Box::new(source), Default::default());
+        let reader = R::try_new(
+            stream,
+            &FormatOptions {
+                enable_gapless: true,
+                ..Default::default()
+            },
+        )?;
+        let track = reader
+            .default_track()
+            .ok_or(SoundError::UnsupportedFormat)?;
+        let params = track.codec_params.clone();
+        let decoder = D::try_new(&params, &DecoderOptions::default())?;
+
+        Ok(Self {
+            track_id: track.id,
+            reader: Box::new(reader),
+            decoder: Box::new(decoder),
+            buffer: None,
+            position: 0,
+            skip: 0,
+            channel_count: params.channels.map_or(0, |c| c.count()),
+            sample_rate: params.sample_rate.unwrap_or_default() as usize,
+            frame_count: params.n_frames,
+        })
+    }
+
+    fn decode_next_packet(&mut self) -> bool {
+        loop {
+            let packet = match self.reader.next_packet() {
+                Ok(packet) => packet,
+                Err(_) => return false,
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let spec = *decoded.spec();
+                    let required_capacity = decoded.capacity() * spec.channels.count();
+                    let buffer = match self.buffer.as_mut() {
+                        Some(buffer) if buffer.capacity() >= required_capacity => buffer,
+                        _ => self
+                            .buffer
+                            .insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+                    };
+                    buffer.copy_interleaved_ref(decoded);
+                    self.position = 0;
+                    return true;
+                }
+                // Corrupted packet, skip it.
+                Err(Error::DecodeError(_)) => continue,
+                Err(_) => return false,
+            }
+        }
+    }
+
+    pub fn rewind(&mut self) -> Result<(), SoundError> {
+        self.seek(SeekTo::TimeStamp {
+            ts: 0,
+            track_id: self.track_id,
+        })
+    }
+
+    pub fn time_seek(&mut self, location: Duration) {
+        let _ = self.seek(SeekTo::Time {
+            time: location.into(),
+            track_id: Some(self.track_id),
+        });
+    }
+
+    fn seek(&mut self, to: SeekTo) -> Result<(), SoundError> {
+        let seeked_to = self.reader.seek(SeekMode::Accurate, to)?;
+        self.decoder.reset();
+        if let Some(buffer) = self.buffer.as_mut() {
+            buffer.clear();
+        }
+        self.position = 0;
+        self.skip =
+            seeked_to.required_ts.saturating_sub(seeked_to.actual_ts) as usize * self.channel_count;
+        Ok(())
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        match self.frame_count {
+            Some(frame_count) if self.sample_rate != 0 => Some(Duration::from_secs_f64(
+                frame_count as f64 / self.sample_rate as f64,
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.channel_count
+    }
+
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+}
+
+impl Iterator for SymphoniaDecoder {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(buffer) = self.buffer.as_ref() {
+                if let Some(&sample) = buffer.samples().get(self.position) {
+                    self.position += 1;
+                    if self.skip > 0 {
+                        self.skip -= 1;
+                        continue;
+                    }
+                    return Some(sample);
+                }
+            }
+
+            if !self.decode_next_packet() {
+                return None;
+            }
+        }
+    }
+}
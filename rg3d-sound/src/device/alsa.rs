@@ -1,7 +1,8 @@
 use crate::{
     context::SAMPLE_RATE,
-    device::{Device, FeedCallback, MixContext, NativeSample},
+    device::{Device, FeedCallback, MixContext},
     error::SoundError,
+    layout::MAX_CHANNELS,
 };
 use alsa_sys::*;
 use std::{
@@ -14,8 +15,8 @@ pub struct AlsaSoundDevice {
     frame_count: u32,
     playback_device: *mut snd_pcm_t,
     callback: Box<FeedCallback>,
-    out_data: Vec<NativeSample>,
-    mix_buffer: Vec<(f32, f32)>,
+    out_data: Vec<i16>,
+    mix_buffer: Vec<f32>,
 }
 
 unsafe impl Send for AlsaSoundDevice {}
@@ -39,15 +40,27 @@ pub fn check(err_code: c_int) -> Result<(), SoundError> {
     }
 }
 
+// Default ALSA channel maps put rear (side) speakers before center and low frequency channels:
+// FL FR RL RR FC LFE [SL SR]. For every channel of a device there is an index of the channel of
+// the engine layout.
+fn channel_map(channel_count: usize) -> &'static [usize] {
+    match channel_count {
+        6 => &[0, 1, 4, 5, 2, 3],
+        8 => &[0, 1, 4, 5, 2, 3, 6, 7],
+        _ => &[0, 1],
+    }
+}
+
 impl AlsaSoundDevice {
-    pub fn new<F: FnMut(&mut [(f32, f32)]) + Send + 'static>(
+    pub fn new<F: FnMut(&mut [f32]) + Send + 'static>(
         buffer_len_bytes: u32,
-        callback: F,
+        channel_count: usize,
+        mut callback: F,
     ) -> Result<Self, SoundError> {
         unsafe {
             let name = CString::new("default").unwrap();
-            // 16-bit stereo is 4 bytes, so frame count is bufferHalfSize / 4
-            let frame_count = buffer_len_bytes / 4;
+            // Every sample is 16-bit, so frame count is bufferHalfSize / (2 * channel_count)
+            let frame_count = buffer_len_bytes / (size_of::<i16>() * channel_count) as u32;
             let mut playback_device = std::ptr::null_mut();
             check(snd_pcm_open(
                 &mut playback_device,
@@ -79,7 +92,7 @@ impl AlsaSoundDevice {
             check(snd_pcm_hw_params_set_channels(
                 playback_device,
                 hw_params,
-                2,
+                channel_count as u32,
             ))?;
             check(snd_pcm_hw_params_set_period_size(
                 playback_device,
@@ -111,13 +124,25 @@ impl AlsaSoundDevice {
             check(snd_pcm_sw_params(playback_device, sw_params))?;
             check(snd_pcm_prepare(playback_device))?;
 
-            let samples_per_channel = buffer_len_bytes as usize / size_of::<NativeSample>();
+            let sample_count = buffer_len_bytes as usize / size_of::<i16>();
+            let map = channel_map(channel_count);
             Ok(Self {
                 playback_device,
                 frame_count,
-                callback: Box::new(callback),
-                out_data: vec![Default::default(); samples_per_channel],
-                mix_buffer: vec![(0.0, 0.0); samples_per_channel],
+                callback: Box::new(move |buf: &mut [f32]| {
+                    callback(buf);
+                    if channel_count > 2 {
+                        let mut frame = [0.0; MAX_CHANNELS];
+                        for samples in buf.chunks_exact_mut(channel_count) {
+                            frame[..channel_count].copy_from_slice(samples);
+                            for (sample, &channel) in samples.iter_mut().zip(map) {
+                                *sample = frame[channel];
+                            }
+                        }
+                    }
+                }),
+                out_data: vec![0; sample_count],
+                mix_buffer: vec![0.0; sample_count],
             })
         }
     }
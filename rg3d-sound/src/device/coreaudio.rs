@@ -1,5 +1,5 @@
 use crate::{
-    device::{Device, FeedCallback, MixContext, NativeSample},
+    device::{Device, FeedCallback, MixContext},
     error::SoundError,
 };
 use coreaudio_sys::*;
@@ -15,8 +15,8 @@ unsafe impl Send for CoreaudioSoundDevice {}
 struct Inner {
     // MixContext
     fill_callback: Box<FeedCallback>,
-    out_data: Vec<NativeSample>,
-    mix_buffer: Vec<(f32, f32)>,
+    out_data: Vec<i16>,
+    mix_buffer: Vec<f32>,
     // else
     queue: AudioQueueRef,
     bufs: [AudioQueueBufferRef; 2],
@@ -43,6 +43,24 @@ fn check(error: OSStatus, msg: &str) -> Result<(), SoundError> {
     }
 }
 
+/// Speakers of the engine layout with given amount of channels, channels are interleaved in
+/// order of the bits.
+fn channel_bitmap(channel_count: usize) -> u32 {
+    let front = kAudioChannelBit_Left as u32 | kAudioChannelBit_Right as u32;
+    let surround = front
+        | kAudioChannelBit_Center as u32
+        | kAudioChannelBit_LFEScreen as u32
+        | kAudioChannelBit_LeftSurroundDirect as u32
+        | kAudioChannelBit_RightSurroundDirect as u32;
+    match channel_count {
+        6 => surround,
+        8 => {
+            surround | kAudioChannelBit_LeftSurround as u32 | kAudioChannelBit_RightSurround as u32
+        }
+        _ => front,
+    }
+}
+
 /// Callback function set on `AudioQueueNewOutput`
 unsafe extern "C" fn audio_queue_callback(
     user_data: *mut c_void,
@@ -62,8 +80,9 @@ unsafe extern "C" fn audio_queue_callback(
 }
 
 impl CoreaudioSoundDevice {
-    pub fn new<F: FnMut(&mut [(f32, f32)]) + Send + 'static>(
+    pub fn new<F: FnMut(&mut [f32]) + Send + 'static>(
         buffer_len_bytes: u32,
+        channel_count: usize,
         fill_callback: F,
     ) -> Result<Self, SoundError> {
         // 16-bit linear PCM
@@ -73,18 +92,18 @@ impl CoreaudioSoundDevice {
             mFormatFlags: kLinearPCMFormatFlagIsSignedInteger | kLinearPCMFormatFlagIsPacked,
             mBitsPerChannel: 16,
             mFramesPerPacket: 1,
-            mChannelsPerFrame: 2,
-            mBytesPerFrame: 4,
-            mBytesPerPacket: 4,
+            mChannelsPerFrame: channel_count as u32,
+            mBytesPerFrame: (size_of::<i16>() * channel_count) as u32,
+            mBytesPerPacket: (size_of::<i16>() * channel_count) as u32,
             mReserved: 0,
         };
 
         // create data at fixed memory location
-        let samples_per_channel = buffer_len_bytes as usize / size_of::<NativeSample>();
+        let sample_count = buffer_len_bytes as usize / size_of::<i16>();
         let mut inner = Box::new(Inner {
             fill_callback: Box::new(fill_callback),
-            out_data: vec![Default::default(); samples_per_channel],
-            mix_buffer: vec![(0.0, 0.0); samples_per_channel],
+            out_data: vec![0; sample_count],
+            mix_buffer: vec![0.0; sample_count],
             queue: std::ptr::null_mut(),
             bufs: [std::ptr::null_mut(); 2],
             buffer_len_bytes: buffer_len_bytes as usize,
@@ -116,6 +135,22 @@ impl CoreaudioSoundDevice {
             queue
         };
 
+        // tell which speakers the channels belong to
+        if channel_count > 2 {
+            let res = unsafe {
+                let mut layout: AudioChannelLayout = std::mem::zeroed();
+                layout.mChannelLayoutTag = kAudioChannelLayoutTag_UseChannelBitmap as u32;
+                layout.mChannelBitmap = channel_bitmap(channel_count);
+                AudioQueueSetProperty(
+                    inner.queue,
+                    kAudioQueueProperty_ChannelLayout as u32,
+                    &layout as *const AudioChannelLayout as *const c_void,
+                    size_of::<AudioChannelLayout>() as u32,
+                )
+            };
+            check(res, "Failed to set channel layout of audio queue")?;
+        }
+
         // create two audio buffers
         for i in 0..2 {
             inner.bufs[i] = {
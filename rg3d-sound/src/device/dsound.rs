@@ -2,16 +2,16 @@
 
 use crate::{
     context::SAMPLE_RATE,
-    device::{Device, FeedCallback, MixContext, NativeSample},
+    device::{Device, FeedCallback, MixContext},
     error::SoundError,
 };
 use std::mem::size_of;
 use winapi::{
     ctypes::c_void,
     shared::{
-        guiddef::IID_NULL,
+        guiddef::{GUID, IID_NULL},
         minwindef::DWORD,
-        mmreg::{WAVEFORMATEX, WAVE_FORMAT_PCM},
+        mmreg::{WAVEFORMATEX, WAVEFORMATEXTENSIBLE, WAVE_FORMAT_EXTENSIBLE, WAVE_FORMAT_PCM},
         ntdef::{HANDLE, PVOID},
         winerror::HRESULT,
     },
@@ -38,13 +38,45 @@ interface IDirectSoundNotify(IDirectSoundNotifyVtbl): IUnknown(IUnknownVtbl) {
         ) -> HRESULT,
 }}
 
+// Speaker positions of WAVEFORMATEXTENSIBLE, channels are interleaved in order of the bits.
+const SPEAKER_FRONT_LEFT: DWORD = 0x1;
+const SPEAKER_FRONT_RIGHT: DWORD = 0x2;
+const SPEAKER_FRONT_CENTER: DWORD = 0x4;
+const SPEAKER_LOW_FREQUENCY: DWORD = 0x8;
+const SPEAKER_BACK_LEFT: DWORD = 0x10;
+const SPEAKER_BACK_RIGHT: DWORD = 0x20;
+const SPEAKER_SIDE_LEFT: DWORD = 0x200;
+const SPEAKER_SIDE_RIGHT: DWORD = 0x400;
+
+const KSDATAFORMAT_SUBTYPE_PCM: GUID = GUID {
+    Data1: 0x0000_0001,
+    Data2: 0x0000,
+    Data3: 0x0010,
+    Data4: [0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71],
+};
+
+// Speakers of the engine layout with given amount of channels.
+fn channel_mask(channel_count: usize) -> DWORD {
+    let front = SPEAKER_FRONT_LEFT | SPEAKER_FRONT_RIGHT;
+    let surround = front
+        | SPEAKER_FRONT_CENTER
+        | SPEAKER_LOW_FREQUENCY
+        | SPEAKER_SIDE_LEFT
+        | SPEAKER_SIDE_RIGHT;
+    match channel_count {
+        6 => surround,
+        8 => surround | SPEAKER_BACK_LEFT | SPEAKER_BACK_RIGHT,
+        _ => front,
+    }
+}
+
 pub struct DirectSoundDevice {
     direct_sound: *mut IDirectSound,
     buffer: *mut IDirectSoundBuffer,
     notify_points: [*mut c_void; 2],
     buffer_len_bytes: u32,
-    out_data: Vec<NativeSample>,
-    mix_buffer: Vec<(f32, f32)>,
+    out_data: Vec<i16>,
+    mix_buffer: Vec<f32>,
     callback: Box<FeedCallback>,
 }
 
@@ -59,8 +91,9 @@ fn check<S: Into<String>>(code: i32, message: S) -> Result<(), SoundError> {
 }
 
 impl DirectSoundDevice {
-    pub fn new<F: FnMut(&mut [(f32, f32)]) + Send + 'static>(
+    pub fn new<F: FnMut(&mut [f32]) + Send + 'static>(
         buffer_len_bytes: u32,
+        channel_count: usize,
         callback: F,
     ) -> Result<Self, SoundError> {
         unsafe {
@@ -75,18 +108,33 @@ impl DirectSoundDevice {
                 "Failed to set cooperative level",
             )?;
 
-            let channels_count = 2;
+            let channels_count = channel_count as u16;
             let byte_per_sample = size_of::<i16>() as u16;
             let block_align = byte_per_sample * channels_count;
 
-            let mut buffer_format = WAVEFORMATEX {
-                wFormatTag: WAVE_FORMAT_PCM,
-                nChannels: channels_count,
-                nSamplesPerSec: SAMPLE_RATE,
-                nAvgBytesPerSec: SAMPLE_RATE * u32::from(block_align),
-                nBlockAlign: block_align,
-                wBitsPerSample: 8 * byte_per_sample,
-                cbSize: size_of::<WAVEFORMATEX>() as u16,
+            // Layouts with more than two channels must be described with extensible format,
+            // it defines which speakers the channels belong to.
+            let mut buffer_format = WAVEFORMATEXTENSIBLE {
+                Format: WAVEFORMATEX {
+                    wFormatTag: if channel_count > 2 {
+                        WAVE_FORMAT_EXTENSIBLE
+                    } else {
+                        WAVE_FORMAT_PCM
+                    },
+                    nChannels: channels_count,
+                    nSamplesPerSec: SAMPLE_RATE,
+                    nAvgBytesPerSec: SAMPLE_RATE * u32::from(block_align),
+                    nBlockAlign: block_align,
+                    wBitsPerSample: 8 * byte_per_sample,
+                    cbSize: if channel_count > 2 {
+                        (size_of::<WAVEFORMATEXTENSIBLE>() - size_of::<WAVEFORMATEX>()) as u16
+                    } else {
+                        size_of::<WAVEFORMATEX>() as u16
+                    },
+                },
+                Samples: 8 * byte_per_sample,
+                dwChannelMask: channel_mask(channel_count),
+                SubFormat: KSDATAFORMAT_SUBTYPE_PCM,
             };
 
             let buffer_desc = DSBUFFERDESC {
@@ -94,7 +142,7 @@ impl DirectSoundDevice {
                 dwFlags: DSBCAPS_CTRLPOSITIONNOTIFY | DSBCAPS_GLOBALFOCUS,
                 dwBufferBytes: 2 * buffer_len_bytes,
                 dwReserved: 0,
-                lpwfxFormat: &mut buffer_format,
+                lpwfxFormat: &mut buffer_format as *mut WAVEFORMATEXTENSIBLE as *mut WAVEFORMATEX,
                 guid3DAlgorithm: IID_NULL,
             };
 
@@ -140,13 +188,13 @@ impl DirectSoundDevice {
                 "Failed to begin playing back buffer.",
             )?;
 
-            let samples_per_channel = buffer_len_bytes as usize / size_of::<NativeSample>();
+            let sample_count = buffer_len_bytes as usize / size_of::<i16>();
 
             Ok(Self {
                 direct_sound,
                 buffer,
-                out_data: vec![Default::default(); samples_per_channel],
-                mix_buffer: vec![(0.0, 0.0); samples_per_channel],
+                out_data: vec![0; sample_count],
+                mix_buffer: vec![0.0; sample_count],
                 notify_points,
                 buffer_len_bytes,
                 callback: Box::new(callback),
@@ -167,7 +215,7 @@ unsafe fn write(
     ds_buffer: *mut IDirectSoundBuffer,
     offset_bytes: u32,
     len_bytes: u32,
-    data: &[NativeSample],
+    data: &[i16],
 ) {
     let mut size = 0;
     let mut device_buffer = std::ptr::null_mut();
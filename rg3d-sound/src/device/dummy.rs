@@ -1,14 +1,13 @@
 use crate::{
-    device::{Device, FeedCallback, MixContext, NativeSample},
+    device::{Device, MixContext},
     error::SoundError,
 };
-use std::mem::size_of;
-
 pub struct DummySoundDevice;
 
 impl DummySoundDevice {
-    pub fn new<F: FnMut(&mut [(f32, f32)]) + Send + 'static>(
+    pub fn new<F: FnMut(&mut [f32]) + Send + 'static>(
         _buffer_len_bytes: u32,
+        _channel_count: usize,
         _callback: F,
     ) -> Result<Self, SoundError> {
         Ok(Self)
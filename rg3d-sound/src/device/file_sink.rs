@@ -0,0 +1,130 @@
+//! File sink is an output "device" that writes mixed samples to a WAV file instead of sound
+//! hardware. It consumes samples at the same pace as real device does, so the engine could be
+//! used as usual while everything that is heard is recorded. Unlike native devices, file sink
+//! is not limited to stereo - the file has every channel of the given layout.
+
+use crate::{
+    context::SAMPLE_RATE,
+    encoder::{WavEncoder, WavSampleFormat},
+    error::SoundError,
+    layout::ChannelLayout,
+};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+type FileSinkCallback = dyn FnMut(&mut [f32]) -> bool + Send;
+
+pub struct FileSinkDevice {
+    callback: Box<FileSinkCallback>,
+    // Interleaved samples of every channel of the layout.
+    mix_buffer: Vec<f32>,
+    frame_count: usize,
+    encoder: Option<WavEncoder<BufWriter<File>>>,
+}
+
+impl FileSinkDevice {
+    /// Creates new file sink that writes samples in given format, the callback gets interleaved
+    /// samples of every channel of the layout and must return false when there is nothing to
+    /// record anymore - the device stops and finalizes the file.
+    pub fn new<F: FnMut(&mut [f32]) -> bool + Send + 'static>(
+        path: &Path,
+        format: WavSampleFormat,
+        layout: ChannelLayout,
+        frame_count: usize,
+        callback: F,
+    ) -> Result<Self, SoundError> {
+        Ok(Self {
+            callback: Box::new(callback),
+            mix_buffer: vec![0.0; frame_count * layout.channel_count()],
+            frame_count,
+            encoder: Some(WavEncoder::create_with_layout(path, format, layout)?),
+        })
+    }
+
+    pub fn run(&mut self) {
+        let block_duration = Duration::from_secs_f64(self.frame_count as f64 / SAMPLE_RATE as f64);
+        let mut next_block_time = Instant::now();
+
+        loop {
+            self.mix_buffer.fill(0.0);
+
+            if !(self.callback)(&mut self.mix_buffer) {
+                break;
+            }
+
+            if let Some(encoder) = self.encoder.as_mut() {
+                if let Err(e) = encoder.write_interleaved(&self.mix_buffer) {
+                    eprintln!(
+                        "Failed to write samples to file sink, recording is stopped. Reason: {:?}",
+                        e
+                    );
+                    break;
+                }
+            }
+
+            // Consume samples in real time, like physical device does.
+            next_block_time += block_duration;
+            let now = Instant::now();
+            if next_block_time > now {
+                std::thread::sleep(next_block_time - now);
+            }
+        }
+
+        if let Some(encoder) = self.encoder.take() {
+            if let Err(e) = encoder.finalize() {
+                eprintln!("Failed to finalize file sink. Reason: {:?}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        device::file_sink::FileSinkDevice, encoder::WavSampleFormat, layout::ChannelLayout,
+    };
+
+    #[test]
+    fn test_multichannel_file_sink() {
+        let path = std::env::temp_dir().join("rg3d_sound_file_sink_51.wav");
+
+        let mut blocks = 0;
+        let mut device = FileSinkDevice::new(
+            &path,
+            WavSampleFormat::Pcm16,
+            ChannelLayout::Surround51,
+            441,
+            move |buf| {
+                blocks += 1;
+                for frame in buf.chunks_exact_mut(6) {
+                    for (channel, sample) in frame.iter_mut().enumerate() {
+                        *sample = channel as f32 * 0.1;
+                    }
+                }
+                blocks <= 3
+            },
+        )
+        .unwrap();
+        device.run();
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().channels, 6);
+        let samples = reader
+            .samples::<i16>()
+            .map(|s| s.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(samples.len(), 3 * 441 * 6);
+        for frame in samples.chunks_exact(6) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                let expected = (channel as f32 * 0.1 * i16::MAX as f32) as i16;
+                assert!((sample - expected).abs() <= 1);
+            }
+        }
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -15,34 +15,31 @@ mod alsa;
 mod coreaudio;
 
 // The dummy target works on all platforms
-#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos", target_arch = "wasm32")))]
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "macos",
+    target_arch = "wasm32"
+)))]
 mod dummy;
 
 #[cfg(target_arch = "wasm32")]
 mod web;
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct NativeSample {
-    pub left: i16,
-    pub right: i16,
-}
+// Works on all platforms except web, there is no file system.
+#[cfg(not(target_arch = "wasm32"))]
+mod file_sink;
 
-impl Default for NativeSample {
-    fn default() -> Self {
-        Self { left: 0, right: 0 }
-    }
-}
-
-pub type FeedCallback = dyn FnMut(&mut [(f32, f32)]) + Send;
+pub type FeedCallback = dyn FnMut(&mut [f32]) + Send;
 
 pub struct MixContext<'a> {
-    mix_buffer: &'a mut [(f32, f32)],
-    out_data: &'a mut [NativeSample],
+    // Interleaved samples of every channel of a device.
+    mix_buffer: &'a mut [f32],
+    out_data: &'a mut [i16],
     callback: &'a mut FeedCallback,
 }
 
-fn sample_to_i16(sample: f32) -> i16 {
+pub(in crate) fn sample_to_i16(sample: f32) -> i16 {
     const SCALE: f32 = i16::MAX as f32;
     let clamped = if sample > 1.0 {
         1.0
@@ -62,21 +59,15 @@ trait Device {
     fn mix(&mut self) {
         if let Some(context) = self.get_mix_context() {
             // Clear mixer buffer.
-            for (left, right) in context.mix_buffer.iter_mut() {
-                *left = 0.0;
-                *right = 0.0;
-            }
+            context.mix_buffer.fill(0.0);
 
             // Fill it.
             (context.callback)(context.mix_buffer);
 
             // Convert to i16 - device expects samples in this format.
             assert_eq!(context.mix_buffer.len(), context.out_data.len());
-            for ((left, right), ref mut out_sample) in
-                context.mix_buffer.iter().zip(context.out_data)
-            {
-                out_sample.left = sample_to_i16(*left);
-                out_sample.right = sample_to_i16(*right);
+            for (sample, out_sample) in context.mix_buffer.iter().zip(context.out_data) {
+                *out_sample = sample_to_i16(*sample);
             }
         }
     }
@@ -84,30 +75,52 @@ trait Device {
 
 /// Transfer ownership of device to separate mixer thread. It will
 /// call the callback with a specified rate to get data to send to a physical device.
-pub(in crate) fn run_device<F: FnMut(&mut [(f32, f32)]) + Send + 'static>(
+/// The device is opened with given amount of channels, the callback gets interleaved samples
+/// of every channel in order of [`crate::layout::ChannelLayout`] with given amount of channels.
+pub(in crate) fn run_device<F: FnMut(&mut [f32]) + Send + 'static>(
     #[allow(unused_variables)] buffer_len_bytes: u32,
+    #[allow(unused_variables)] channel_count: usize,
     #[allow(unused_variables)] callback: F,
 ) {
     #[cfg(not(target_arch = "wasm32"))]
     {
         std::thread::spawn(move || {
             #[cfg(target_os = "windows")]
-            let mut device = dsound::DirectSoundDevice::new(buffer_len_bytes, callback).unwrap();
+            let mut device =
+                dsound::DirectSoundDevice::new(buffer_len_bytes, channel_count, callback).unwrap();
             #[cfg(target_os = "linux")]
-            let mut device = alsa::AlsaSoundDevice::new(buffer_len_bytes, callback).unwrap();
+            let mut device =
+                alsa::AlsaSoundDevice::new(buffer_len_bytes, channel_count, callback).unwrap();
             #[cfg(target_os = "macos")]
             let mut device =
-                coreaudio::CoreaudioSoundDevice::new(buffer_len_bytes, callback).unwrap();
+                coreaudio::CoreaudioSoundDevice::new(buffer_len_bytes, channel_count, callback)
+                    .unwrap();
             #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
-            let mut device = dummy::DummySoundDevice::new(buffer_len_bytes, callback).unwrap();
+            let mut device =
+                dummy::DummySoundDevice::new(buffer_len_bytes, channel_count, callback).unwrap();
             device.run()
         });
     }
 
     #[cfg(target_arch = "wasm32")]
     {
-        let mut device = web::WebAudioDevice::new(buffer_len_bytes, callback);
+        let mut device = web::WebAudioDevice::new(buffer_len_bytes, channel_count, callback);
         device.run();
         std::mem::forget(device);
     }
 }
+
+/// Runs file sink device in separate thread. Mixed samples of every channel of given layout
+/// are written to a WAV file at given path in given format until the callback returns false.
+#[cfg(not(target_arch = "wasm32"))]
+pub(in crate) fn run_file_sink<F: FnMut(&mut [f32]) -> bool + Send + 'static>(
+    path: &std::path::Path,
+    format: crate::encoder::WavSampleFormat,
+    layout: crate::layout::ChannelLayout,
+    frame_count: usize,
+    callback: F,
+) -> Result<(), crate::error::SoundError> {
+    let mut device = file_sink::FileSinkDevice::new(path, format, layout, frame_count, callback)?;
+    std::thread::spawn(move || device.run());
+    Ok(())
+}
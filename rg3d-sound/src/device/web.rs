@@ -1,6 +1,6 @@
 use crate::{
     context::SAMPLE_RATE,
-    device::{Device, MixContext, NativeSample},
+    device::{Device, MixContext},
 };
 use rg3d_core::{
     wasm_bindgen::{self, prelude::*, JsCast},
@@ -18,8 +18,9 @@ pub struct WebAudioDevice {
 }
 
 impl WebAudioDevice {
-    pub fn new<F: FnMut(&mut [(f32, f32)]) + Send + 'static>(
+    pub fn new<F: FnMut(&mut [f32]) + Send + 'static>(
         buffer_len_bytes: u32,
+        channel_count: usize,
         callback: F,
     ) -> Self {
         let callback = Arc::new(Mutex::new(callback));
@@ -27,15 +28,24 @@ impl WebAudioDevice {
         let mut options = AudioContextOptions::new();
         options.sample_rate(SAMPLE_RATE as f32);
         let ctx = Arc::new(AudioContext::new_with_context_options(&options).unwrap());
-        let samples_per_channel = buffer_len_bytes as usize / size_of::<NativeSample>();
+        let samples_per_channel = buffer_len_bytes as usize / (size_of::<i16>() * channel_count);
         let buffer_duration_secs = samples_per_channel as f32 / (SAMPLE_RATE as f32);
+        // Destination is stereo by default, it downmixes anything with more channels.
+        let destination = ctx.destination();
+        if destination.max_channel_count() >= channel_count as u32 {
+            destination.set_channel_count(channel_count as u32);
+        }
         let mut onended: Vec<Arc<RwLock<Option<Closure<dyn FnMut()>>>>> = Vec::new();
 
         let time = Arc::new(RwLock::new(0.0f32));
 
         for _ in 0..2 {
             let buffer = ctx
-                .create_buffer(2, samples_per_channel as u32, SAMPLE_RATE as f32)
+                .create_buffer(
+                    channel_count as u32,
+                    samples_per_channel as u32,
+                    SAMPLE_RATE as f32,
+                )
                 .unwrap();
 
             let onended_closure: Arc<RwLock<Option<Closure<dyn FnMut()>>>> =
@@ -45,16 +55,13 @@ impl WebAudioDevice {
             let onended_closure_clone = onended_closure.clone();
             let time = time.clone();
             let callback = callback.clone();
-            let mut mix_buffer = vec![(0.0f32, 0.0f32); samples_per_channel];
+            let mut mix_buffer = vec![0.0f32; samples_per_channel * channel_count];
             let mut temp_samples = vec![0.0f32; samples_per_channel];
             onended_closure
                 .write()
                 .unwrap()
                 .replace(Closure::wrap(Box::new(move || {
-                    for (l, r) in mix_buffer.iter_mut() {
-                        *r = 0.0;
-                        *l = 0.0;
-                    }
+                    mix_buffer.fill(0.0);
 
                     let current_time = ctx_clone.current_time() as f32;
                     let raw_time = *time.read().unwrap();
@@ -66,17 +73,18 @@ impl WebAudioDevice {
 
                     callback.lock().unwrap()(&mut mix_buffer);
 
-                    // Fill left channel.
-                    for ((l, _), sample) in mix_buffer.iter().zip(temp_samples.iter_mut()) {
-                        *sample = *l;
-                    }
-                    buffer.copy_to_channel(&temp_samples, 0).unwrap();
-
-                    // Fill right channel.
-                    for ((_, r), sample) in mix_buffer.iter().zip(temp_samples.iter_mut()) {
-                        *sample = *r;
+                    // Fill every channel, Web Audio uses the same order of speakers.
+                    for channel in 0..channel_count {
+                        for (frame, sample) in mix_buffer
+                            .chunks_exact(channel_count)
+                            .zip(temp_samples.iter_mut())
+                        {
+                            *sample = frame[channel];
+                        }
+                        buffer
+                            .copy_to_channel(&temp_samples, channel as i32)
+                            .unwrap();
                     }
-                    buffer.copy_to_channel(&temp_samples, 1).unwrap();
 
                     // Create source.
                     let source = ctx_clone.create_buffer_source().unwrap();
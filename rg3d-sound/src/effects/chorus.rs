@@ -0,0 +1,334 @@
+//! Chorus module
+//!
+//! # Overview
+//!
+//! Chorus mixes input signal with its copy delayed by a time that is slowly modulated by a low
+//! frequency oscillator (LFO). Variations of the delay time give small variations of pitch, so
+//! one voice sounds like a few voices singing together. Left and right channels have LFOs with
+//! 90 degrees phase difference, this makes the sound wider.
+//!
+//! Flanger is the same effect with much shorter delay time and strong feedback, it gives
+//! distinctive "jet plane" sweeping sound. See [`Chorus::flanger`].
+//!
+//! # Usage
+//!
+//! ```
+//! use std::time::Duration;
+//! use rg3d_sound::context::SoundContext;
+//! use rg3d_sound::effects::chorus::Chorus;
+//! use rg3d_sound::effects::{BaseEffect, Effect};
+//!
+//! fn add_chorus(context: &mut SoundContext) {
+//!     let mut chorus = Chorus::new(BaseEffect::default());
+//!     chorus.set_rate(0.8);
+//!     chorus.set_depth(Duration::from_millis(4));
+//!     context.state().add_effect(Effect::Chorus(chorus));
+//! }
+//! ```
+
+use crate::{
+    context::SAMPLE_RATE,
+    effects::{BaseEffect, FrameEffect},
+};
+use rg3d_core::visitor::{Visit, VisitResult, Visitor};
+use std::{
+    f32::consts::PI,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
+/// Delay line with fractional delay time which could be changed on every sample.
+#[derive(Debug, Clone)]
+struct ModulatedDelayLine {
+    samples: Vec<f32>,
+    pos: usize,
+}
+
+impl ModulatedDelayLine {
+    fn new(len: usize) -> Self {
+        Self {
+            samples: vec![0.0; len],
+            pos: 0,
+        }
+    }
+
+    /// Returns sample that was written `delay` samples ago, delay must be at least one sample.
+    fn read(&self, delay: f32) -> f32 {
+        let len = self.samples.len();
+        let delay = delay.clamp(1.0, (len - 1) as f32);
+        let whole = delay as usize;
+        let fraction = delay - whole as f32;
+        let a = self.samples[(self.pos + len - whole) % len];
+        let b = self.samples[(self.pos + len - whole - 1) % len];
+        a + (b - a) * fraction
+    }
+
+    fn write(&mut self, sample: f32) {
+        self.samples[self.pos] = sample;
+        self.pos = (self.pos + 1) % self.samples.len();
+    }
+}
+
+impl Default for ModulatedDelayLine {
+    fn default() -> Self {
+        Self::new(Chorus::delay_line_len())
+    }
+}
+
+/// See module docs.
+#[derive(Debug, Clone)]
+pub struct Chorus {
+    base: BaseEffect,
+    delay: f32,
+    depth: f32,
+    rate: f32,
+    feedback: f32,
+    dry: f32,
+    wet: f32,
+    phase: f32,
+    left: ModulatedDelayLine,
+    right: ModulatedDelayLine,
+}
+
+impl Default for Chorus {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl Chorus {
+    /// Maximum total delay time (delay + depth).
+    pub const MAX_DELAY: Duration = Duration::from_millis(50);
+
+    fn delay_line_len() -> usize {
+        (Self::MAX_DELAY.as_secs_f32() * SAMPLE_RATE as f32).ceil() as usize + 2
+    }
+
+    /// Creates new chorus with 15 ms delay time, 3 ms depth, 0.5 Hz rate and no feedback.
+    pub fn new(base: BaseEffect) -> Self {
+        Self {
+            base,
+            delay: 0.015,
+            depth: 0.003,
+            rate: 0.5,
+            feedback: 0.0,
+            dry: 1.0,
+            wet: 0.5,
+            phase: 0.0,
+            left: Default::default(),
+            right: Default::default(),
+        }
+    }
+
+    /// Creates new flanger - chorus with 1 ms delay time, 2 ms depth, 0.25 Hz rate and 0.7
+    /// feedback.
+    pub fn flanger(base: BaseEffect) -> Self {
+        let mut flanger = Self::new(base);
+        flanger.set_delay(Duration::from_millis(1));
+        flanger.set_depth(Duration::from_millis(2));
+        flanger.set_rate(0.25);
+        flanger.set_feedback(0.7);
+        flanger
+    }
+
+    /// Sets minimal delay time. Sum of delay time and depth is clamped to
+    /// [`Self::MAX_DELAY`].
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay.min(Self::MAX_DELAY).as_secs_f32();
+        self.depth = self.depth.min(Self::MAX_DELAY.as_secs_f32() - self.delay);
+    }
+
+    /// Returns minimal delay time.
+    pub fn delay(&self) -> Duration {
+        Duration::from_secs_f32(self.delay)
+    }
+
+    /// Sets depth of modulation - delay time varies from `delay` to `delay + depth`. Sum of
+    /// delay time and depth is clamped to [`Self::MAX_DELAY`].
+    pub fn set_depth(&mut self, depth: Duration) {
+        self.depth = depth
+            .as_secs_f32()
+            .min(Self::MAX_DELAY.as_secs_f32() - self.delay);
+    }
+
+    /// Returns depth of modulation.
+    pub fn depth(&self) -> Duration {
+        Duration::from_secs_f32(self.depth)
+    }
+
+    /// Sets frequency of the LFO in hertz.
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.max(0.0);
+    }
+
+    /// Returns frequency of the LFO in hertz.
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Sets amount of delayed signal that is fed back to the delay line. It is clamped to
+    /// `[-0.95; 0.95]` range to keep the effect stable. Negative values invert phase of
+    /// feedback, it gives "hollow" sound.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-0.95, 0.95);
+    }
+
+    /// Returns feedback factor.
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    /// Sets how much of input signal should be passed to output without any processing.
+    /// Default value is 1.0.
+    pub fn set_dry(&mut self, dry: f32) {
+        self.dry = dry.clamp(0.0, 1.0);
+    }
+
+    /// Returns dry part.
+    pub fn dry(&self) -> f32 {
+        self.dry
+    }
+
+    /// Sets how much of delayed signal should be passed to output. Default value is 0.5.
+    pub fn set_wet(&mut self, wet: f32) {
+        self.wet = wet.clamp(0.0, 1.0);
+    }
+
+    /// Returns wet part.
+    pub fn wet(&self) -> f32 {
+        self.wet
+    }
+}
+
+impl Visit for Chorus {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.base.visit("Base", visitor)?;
+        self.delay.visit("Delay", visitor)?;
+        self.depth.visit("Depth", visitor)?;
+        self.rate.visit("Rate", visitor)?;
+        self.feedback.visit("Feedback", visitor)?;
+        self.dry.visit("Dry", visitor)?;
+        self.wet.visit("Wet", visitor)?;
+        self.phase.visit("Phase", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+impl FrameEffect for Chorus {
+    fn feed(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let sample_rate = SAMPLE_RATE as f32;
+        let modulation = |phase: f32| 0.5 * (1.0 + (2.0 * PI * phase).sin());
+
+        let left_delay = (self.delay + self.depth * modulation(self.phase)) * sample_rate;
+        let right_delay = (self.delay + self.depth * modulation(self.phase + 0.25)) * sample_rate;
+
+        self.phase += self.rate / sample_rate;
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+        }
+
+        let delayed_left = self.left.read(left_delay);
+        let delayed_right = self.right.read(right_delay);
+
+        self.left.write(left + self.feedback * delayed_left);
+        self.right.write(right + self.feedback * delayed_right);
+
+        (
+            self.gain * (self.dry * left + self.wet * delayed_left),
+            self.gain * (self.dry * right + self.wet * delayed_right),
+        )
+    }
+}
+
+impl Deref for Chorus {
+    type Target = BaseEffect;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl DerefMut for Chorus {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::effects::{
+        chorus::Chorus,
+        test::{amplitude, sine},
+        BaseEffect, EffectRenderTrait,
+    };
+    use std::time::Duration;
+
+    fn static_chorus(feedback: f32, dry: f32, wet: f32) -> Chorus {
+        // 10 ms (441 samples) delay without modulation.
+        let mut chorus = Chorus::new(BaseEffect::default());
+        chorus.set_delay(Duration::from_millis(10));
+        chorus.set_depth(Duration::from_secs(0));
+        chorus.set_feedback(feedback);
+        chorus.set_dry(dry);
+        chorus.set_wet(wet);
+        chorus
+    }
+
+    fn response(chorus: &mut Chorus, frequency: f32) -> f32 {
+        let mut samples = sine(frequency, 1.0, 44100);
+        chorus.process(&mut samples);
+        amplitude(&samples[22050..])
+    }
+
+    #[test]
+    fn test_comb_response() {
+        // Feed-forward comb: notches at odd multiples of 50 Hz, peaks at multiples of 100 Hz.
+        let mut chorus = static_chorus(0.0, 0.5, 0.5);
+        assert!(response(&mut chorus, 150.0) < 1.0e-3);
+        assert!((response(&mut chorus, 200.0) - 1.0).abs() < 1.0e-3);
+
+        // Feedback comb: 1 / (1 - feedback) at peaks and 1 / (1 + feedback) at notches.
+        let mut flanger = static_chorus(0.5, 0.0, 1.0);
+        assert!((response(&mut flanger, 200.0) - 2.0).abs() < 1.0e-2);
+        assert!((response(&mut flanger, 150.0) - 0.667).abs() < 1.0e-2);
+    }
+
+    #[test]
+    fn test_modulation() {
+        let mut chorus = Chorus::new(BaseEffect::default());
+        chorus.set_delay(Duration::from_millis(5));
+        chorus.set_depth(Duration::from_millis(5));
+        chorus.set_rate(5.0);
+        chorus.set_dry(0.0);
+        chorus.set_wet(1.0);
+
+        let mut samples = sine(1000.0, 1.0, 44100);
+        chorus.process(&mut samples);
+
+        // Modulated delay changes pitch, but keeps amplitude.
+        let amplitude = amplitude(&samples[4410..]);
+        assert!(amplitude > 0.95 && amplitude <= 1.0);
+
+        let crossings = samples[4410..]
+            .chunks(2205)
+            .map(|chunk| {
+                chunk
+                    .windows(2)
+                    .filter(|pair| (pair[0].0 < 0.0) != (pair[1].0 < 0.0))
+                    .count()
+            })
+            .collect::<Vec<_>>();
+        let min = *crossings.iter().min().unwrap();
+        let max = *crossings.iter().max().unwrap();
+        // 1 kHz gives 100 zero crossings per 50 ms, delay modulation gives up to ~8% shift.
+        assert!(min < 97 && max > 103);
+
+        // Channels are modulated with different phases.
+        assert!(samples
+            .iter()
+            .any(|(left, right)| (left - right).abs() > 0.1));
+    }
+}
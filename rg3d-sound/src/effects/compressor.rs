@@ -0,0 +1,293 @@
+//! Compressor module
+//!
+//! # Overview
+//!
+//! Feed-forward dynamic range compressor. It reduces gain of a signal when its level exceeds
+//! threshold, amount of reduction is defined by ratio: with ratio 4:1 every 4 decibels above the
+//! threshold become 1 decibel. Level of the signal is tracked by envelope follower with separate
+//! attack and release times, both channels share one envelope so stereo image is preserved.
+//!
+//! Compressor with infinite ratio and short attack time is a limiter - it does not let signal
+//! exceed the threshold. See [`Compressor::limiter`].
+//!
+//! # Usage
+//!
+//! ```
+//! use std::time::Duration;
+//! use rg3d_sound::context::SoundContext;
+//! use rg3d_sound::effects::compressor::Compressor;
+//! use rg3d_sound::effects::{BaseEffect, Effect};
+//!
+//! fn add_compressor(context: &mut SoundContext) {
+//!     let mut compressor = Compressor::new(BaseEffect::default());
+//!     compressor.set_threshold(-18.0);
+//!     compressor.set_ratio(3.0);
+//!     compressor.set_attack_time(Duration::from_millis(5));
+//!     compressor.set_release_time(Duration::from_millis(150));
+//!     compressor.set_makeup_gain(6.0);
+//!     context.state().add_effect(Effect::Compressor(compressor));
+//! }
+//! ```
+
+use crate::{
+    context::SAMPLE_RATE,
+    effects::{BaseEffect, FrameEffect},
+};
+use rg3d_core::visitor::{Visit, VisitResult, Visitor};
+use std::{
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
+fn db_to_linear(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+fn linear_to_db(linear: f32) -> f32 {
+    20.0 * linear.max(1.0e-6).log10()
+}
+
+fn time_coefficient(time: f32) -> f32 {
+    if time > 0.0 {
+        (-1.0 / (time * SAMPLE_RATE as f32)).exp()
+    } else {
+        0.0
+    }
+}
+
+/// See module docs.
+#[derive(Debug, Clone)]
+pub struct Compressor {
+    base: BaseEffect,
+    threshold: f32,
+    ratio: f32,
+    attack_time: f32,
+    release_time: f32,
+    makeup_gain: f32,
+    attack_coefficient: f32,
+    release_coefficient: f32,
+    envelope: f32,
+}
+
+impl Default for Compressor {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl Compressor {
+    /// Creates new compressor with -12 dB threshold, 4:1 ratio, 10 ms attack time, 100 ms
+    /// release time and no makeup gain.
+    pub fn new(base: BaseEffect) -> Self {
+        let mut compressor = Self {
+            base,
+            threshold: -12.0,
+            ratio: 4.0,
+            attack_time: 0.01,
+            release_time: 0.1,
+            makeup_gain: 0.0,
+            attack_coefficient: 0.0,
+            release_coefficient: 0.0,
+            envelope: 0.0,
+        };
+        compressor.update_coefficients();
+        compressor
+    }
+
+    /// Creates new limiter with given threshold in decibels. Limiter is a compressor with
+    /// infinite ratio, zero attack time and 50 ms release time.
+    pub fn limiter(base: BaseEffect, threshold: f32) -> Self {
+        let mut limiter = Self::new(base);
+        limiter.set_threshold(threshold);
+        limiter.set_ratio(f32::INFINITY);
+        limiter.set_attack_time(Duration::from_secs(0));
+        limiter.set_release_time(Duration::from_millis(50));
+        limiter
+    }
+
+    fn update_coefficients(&mut self) {
+        self.attack_coefficient = time_coefficient(self.attack_time);
+        self.release_coefficient = time_coefficient(self.release_time);
+    }
+
+    /// Sets level in decibels above which signal will be compressed. Should be less or equal
+    /// to zero.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// Returns threshold in decibels.
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Sets compression ratio, values less than 1.0 are clamped. Use `f32::INFINITY` to turn
+    /// the compressor into limiter.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.max(1.0);
+    }
+
+    /// Returns compression ratio.
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    /// Sets time in which the compressor reacts to increase of the level.
+    pub fn set_attack_time(&mut self, time: Duration) {
+        self.attack_time = time.as_secs_f32();
+        self.update_coefficients();
+    }
+
+    /// Returns attack time.
+    pub fn attack_time(&self) -> Duration {
+        Duration::from_secs_f32(self.attack_time)
+    }
+
+    /// Sets time in which the compressor reacts to decrease of the level.
+    pub fn set_release_time(&mut self, time: Duration) {
+        self.release_time = time.as_secs_f32();
+        self.update_coefficients();
+    }
+
+    /// Returns release time.
+    pub fn release_time(&self) -> Duration {
+        Duration::from_secs_f32(self.release_time)
+    }
+
+    /// Sets gain in decibels that is applied after compression to compensate loss of loudness.
+    pub fn set_makeup_gain(&mut self, gain: f32) {
+        self.makeup_gain = gain;
+    }
+
+    /// Returns makeup gain in decibels.
+    pub fn makeup_gain(&self) -> f32 {
+        self.makeup_gain
+    }
+
+    /// Returns current gain reduction in decibels, it is zero when signal is below threshold.
+    pub fn gain_reduction(&self) -> f32 {
+        let over = linear_to_db(self.envelope) - self.threshold;
+        if over > 0.0 {
+            over * (1.0 - 1.0 / self.ratio)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Visit for Compressor {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.base.visit("Base", visitor)?;
+        self.threshold.visit("Threshold", visitor)?;
+        self.ratio.visit("Ratio", visitor)?;
+        self.attack_time.visit("AttackTime", visitor)?;
+        self.release_time.visit("ReleaseTime", visitor)?;
+        self.makeup_gain.visit("MakeupGain", visitor)?;
+        self.envelope.visit("Envelope", visitor)?;
+
+        if visitor.is_reading() {
+            self.update_coefficients();
+        }
+
+        visitor.leave_region()
+    }
+}
+
+impl FrameEffect for Compressor {
+    fn feed(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let level = left.abs().max(right.abs());
+        let coefficient = if level > self.envelope {
+            self.attack_coefficient
+        } else {
+            self.release_coefficient
+        };
+        self.envelope = level + coefficient * (self.envelope - level);
+
+        let gain = self.gain * db_to_linear(self.makeup_gain - self.gain_reduction());
+        (left * gain, right * gain)
+    }
+}
+
+impl Deref for Compressor {
+    type Target = BaseEffect;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl DerefMut for Compressor {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::effects::{
+        compressor::Compressor,
+        test::{amplitude, sine},
+        BaseEffect, EffectRenderTrait,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_compression() {
+        let mut compressor = Compressor::new(BaseEffect::default());
+        compressor.set_threshold(-12.0);
+        compressor.set_ratio(4.0);
+        // Fast attack and slow release make the envelope follow peaks of the sine.
+        compressor.set_attack_time(Duration::from_millis(1));
+        compressor.set_release_time(Duration::from_millis(200));
+
+        // Signal below threshold is not affected.
+        let mut quiet = sine(440.0, 0.1, 44100);
+        compressor.process(&mut quiet);
+        assert!((amplitude(&quiet[22050..]) - 0.1).abs() < 1.0e-3);
+        assert_eq!(compressor.gain_reduction(), 0.0);
+
+        // 0 dB signal is 12 dB above threshold, with 4:1 ratio it becomes 3 dB above threshold,
+        // so output level is -9 dB.
+        let mut loud = sine(440.0, 1.0, 44100);
+        compressor.process(&mut loud);
+        assert!((amplitude(&loud[22050..]) - 0.355).abs() < 0.01);
+        assert!((compressor.gain_reduction() - 9.0).abs() < 0.2);
+
+        compressor.set_makeup_gain(9.0);
+        let mut loud = sine(440.0, 1.0, 44100);
+        compressor.process(&mut loud);
+        assert!((amplitude(&loud[22050..]) - 1.0).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_attack_and_release() {
+        let mut compressor = Compressor::new(BaseEffect::default());
+        compressor.set_attack_time(Duration::from_millis(50));
+        compressor.set_release_time(Duration::from_millis(100));
+
+        // Compressor reacts to a sudden loud signal smoothly, so first samples pass almost as
+        // is.
+        let mut loud = sine(440.0, 1.0, 44100);
+        compressor.process(&mut loud);
+        assert!(amplitude(&loud[..200]) > 0.9);
+        assert!(amplitude(&loud[22050..]) < 0.5);
+
+        // And restores gain in release time when signal becomes quiet.
+        let mut quiet = sine(440.0, 0.1, 44100);
+        compressor.process(&mut quiet);
+        assert!(amplitude(&quiet[..200]) < 0.05);
+        assert!((amplitude(&quiet[22050..]) - 0.1).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn test_limiter() {
+        let mut limiter = Compressor::limiter(BaseEffect::default(), -6.0);
+
+        let mut samples = sine(100.0, 1.0, 44100);
+        limiter.process(&mut samples);
+        assert!(amplitude(&samples) <= 0.502);
+        assert!(amplitude(&samples[22050..]) > 0.45);
+    }
+}
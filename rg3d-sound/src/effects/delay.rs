@@ -0,0 +1,264 @@
+//! Delay module
+//!
+//! # Overview
+//!
+//! Stereo delay (echo) with feedback. Each channel has its own delay line, so different delay
+//! times for left and right channels give wide stereo echo. Part of delayed signal is fed back to
+//! the delay line, this gives repeating echo that decays by `feedback` factor on every repeat.
+//!
+//! # Usage
+//!
+//! ```
+//! use std::time::Duration;
+//! use rg3d_sound::context::SoundContext;
+//! use rg3d_sound::effects::delay::Delay;
+//! use rg3d_sound::effects::{BaseEffect, Effect};
+//!
+//! fn add_echo(context: &mut SoundContext) {
+//!     let mut delay = Delay::new(BaseEffect::default());
+//!     delay.set_left_time(Duration::from_millis(300));
+//!     delay.set_right_time(Duration::from_millis(450));
+//!     delay.set_feedback(0.4);
+//!     context.state().add_effect(Effect::Delay(delay));
+//! }
+//! ```
+
+use crate::{
+    context::SAMPLE_RATE,
+    dsp::DelayLine,
+    effects::{BaseEffect, FrameEffect},
+};
+use rg3d_core::visitor::{Visit, VisitResult, Visitor};
+use std::{
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
+/// Delay line gives its input back on next `len` feed, and previous output is used in feedback
+/// loop, so the line should be one sample shorter than desired delay.
+fn make_delay_line(time: Duration) -> DelayLine {
+    let len = (time.as_secs_f32() * SAMPLE_RATE as f32).round() as usize;
+    DelayLine::new(len.saturating_sub(1).max(1))
+}
+
+/// See module docs.
+#[derive(Debug, Clone)]
+pub struct Delay {
+    base: BaseEffect,
+    left_time: f32,
+    right_time: f32,
+    feedback: f32,
+    dry: f32,
+    wet: f32,
+    left: DelayLine,
+    right: DelayLine,
+}
+
+impl Default for Delay {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl Delay {
+    /// Maximum delay time.
+    pub const MAX_TIME: Duration = Duration::from_secs(10);
+
+    /// Creates new delay with 250 ms delay time for both channels, 0.5 feedback and 0.5 wet
+    /// part.
+    pub fn new(base: BaseEffect) -> Self {
+        let time = Duration::from_millis(250);
+        Self {
+            base,
+            left_time: time.as_secs_f32(),
+            right_time: time.as_secs_f32(),
+            feedback: 0.5,
+            dry: 1.0,
+            wet: 0.5,
+            left: make_delay_line(time),
+            right: make_delay_line(time),
+        }
+    }
+
+    /// Sets delay time of left channel, it is clamped to [`Self::MAX_TIME`]. Delay line is
+    /// recreated, so every pending echo of the channel will be discarded.
+    pub fn set_left_time(&mut self, time: Duration) {
+        let time = time.min(Self::MAX_TIME);
+        self.left_time = time.as_secs_f32();
+        self.left = make_delay_line(time);
+    }
+
+    /// Returns delay time of left channel.
+    pub fn left_time(&self) -> Duration {
+        Duration::from_secs_f32(self.left_time)
+    }
+
+    /// Sets delay time of right channel, it is clamped to [`Self::MAX_TIME`]. Delay line is
+    /// recreated, so every pending echo of the channel will be discarded.
+    pub fn set_right_time(&mut self, time: Duration) {
+        let time = time.min(Self::MAX_TIME);
+        self.right_time = time.as_secs_f32();
+        self.right = make_delay_line(time);
+    }
+
+    /// Returns delay time of right channel.
+    pub fn right_time(&self) -> Duration {
+        Duration::from_secs_f32(self.right_time)
+    }
+
+    /// Sets amount of delayed signal that is fed back to the delay line. It is clamped to
+    /// `[0; 0.99]` range to keep the delay stable.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(0.0, 0.99);
+    }
+
+    /// Returns feedback factor.
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    /// Sets how much of input signal should be passed to output without any processing.
+    /// Default value is 1.0.
+    pub fn set_dry(&mut self, dry: f32) {
+        self.dry = dry.clamp(0.0, 1.0);
+    }
+
+    /// Returns dry part.
+    pub fn dry(&self) -> f32 {
+        self.dry
+    }
+
+    /// Sets how much of delayed signal should be passed to output. Default value is 0.5.
+    pub fn set_wet(&mut self, wet: f32) {
+        self.wet = wet.clamp(0.0, 1.0);
+    }
+
+    /// Returns wet part.
+    pub fn wet(&self) -> f32 {
+        self.wet
+    }
+}
+
+impl Visit for Delay {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.base.visit("Base", visitor)?;
+        self.left_time.visit("LeftTime", visitor)?;
+        self.right_time.visit("RightTime", visitor)?;
+        self.feedback.visit("Feedback", visitor)?;
+        self.dry.visit("Dry", visitor)?;
+        self.wet.visit("Wet", visitor)?;
+
+        // Delay lines are not saved, they could be large and pending echoes are not worth it.
+        if visitor.is_reading() {
+            self.left = make_delay_line(Duration::from_secs_f32(self.left_time));
+            self.right = make_delay_line(Duration::from_secs_f32(self.right_time));
+        }
+
+        visitor.leave_region()
+    }
+}
+
+impl FrameEffect for Delay {
+    fn feed(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let delayed_left = self.left.last();
+        let delayed_right = self.right.last();
+
+        self.left.feed(left + self.feedback * delayed_left);
+        self.right.feed(right + self.feedback * delayed_right);
+
+        (
+            self.gain * (self.dry * left + self.wet * delayed_left),
+            self.gain * (self.dry * right + self.wet * delayed_right),
+        )
+    }
+}
+
+impl Deref for Delay {
+    type Target = BaseEffect;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl DerefMut for Delay {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::effects::{
+        delay::Delay,
+        test::{amplitude, sine},
+        BaseEffect, EffectRenderTrait,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_echoes() {
+        let mut delay = Delay::new(BaseEffect::default());
+        // 441 and 882 samples.
+        delay.set_left_time(Duration::from_millis(10));
+        delay.set_right_time(Duration::from_millis(20));
+        delay.set_feedback(0.5);
+        delay.set_wet(0.8);
+
+        let mut samples = vec![(0.0, 0.0); 3000];
+        samples[0] = (1.0, 1.0);
+        delay.process(&mut samples);
+
+        let left_peaks = samples
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.0 != 0.0)
+            .map(|(i, s)| (i, s.0))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            left_peaks,
+            vec![
+                (0, 1.0),
+                (441, 0.8),
+                (882, 0.4),
+                (1323, 0.2),
+                (1764, 0.1),
+                (2205, 0.05),
+                (2646, 0.025)
+            ]
+        );
+
+        let right_peaks = samples
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.1 != 0.0)
+            .map(|(i, s)| (i, s.1))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            right_peaks,
+            vec![(0, 1.0), (882, 0.8), (1764, 0.4), (2646, 0.2)]
+        );
+    }
+
+    #[test]
+    fn test_comb_response() {
+        let mut delay = Delay::new(BaseEffect::default());
+        // 10 ms delay without feedback is a feed-forward comb filter with notches at odd
+        // multiples of 50 Hz and peaks at multiples of 100 Hz.
+        delay.set_left_time(Duration::from_millis(10));
+        delay.set_right_time(Duration::from_millis(10));
+        delay.set_feedback(0.0);
+        delay.set_dry(0.5);
+        delay.set_wet(0.5);
+
+        let mut notch = sine(150.0, 1.0, 44100);
+        delay.process(&mut notch);
+        assert!(amplitude(&notch[22050..]) < 1.0e-3);
+
+        let mut peak = sine(200.0, 1.0, 44100);
+        delay.process(&mut peak);
+        assert!((amplitude(&peak[22050..]) - 1.0).abs() < 1.0e-3);
+    }
+}
@@ -0,0 +1,292 @@
+//! Equalizer module
+//!
+//! # Overview
+//!
+//! Multi-band parametric equalizer, every band is a pair (left and right channels) of biquad
+//! filters. Bands are applied one after another, so their responses are combined. Typical usage
+//! is to cut low frequencies of sounds that are played through small speakers or to make a
+//! "telephone" voice.
+//!
+//! # Usage
+//!
+//! ```
+//! use rg3d_sound::context::SoundContext;
+//! use rg3d_sound::dsp::filters::BiquadKind;
+//! use rg3d_sound::effects::eq::{Equalizer, EqualizerBand};
+//! use rg3d_sound::effects::{BaseEffect, Effect};
+//!
+//! fn add_equalizer(context: &mut SoundContext) {
+//!     let mut equalizer = Equalizer::new(BaseEffect::default());
+//!     // Cut everything below 80 Hz.
+//!     equalizer.add_band(EqualizerBand::new(BiquadKind::HighPass, 80.0, 0.0, 0.707));
+//!     // Boost presence by 6 dB.
+//!     equalizer.add_band(EqualizerBand::new(BiquadKind::Peaking, 3000.0, 6.0, 1.0));
+//!     context.state().add_effect(Effect::Equalizer(equalizer));
+//! }
+//! ```
+
+use crate::{
+    context::SAMPLE_RATE,
+    dsp::filters::{Biquad, BiquadKind},
+    effects::{BaseEffect, FrameEffect},
+};
+use rg3d_core::visitor::{Visit, VisitResult, Visitor};
+use std::ops::{Deref, DerefMut};
+
+fn kind_to_id(kind: BiquadKind) -> u32 {
+    match kind {
+        BiquadKind::LowPass => 0,
+        BiquadKind::HighPass => 1,
+        BiquadKind::BandPass => 2,
+        BiquadKind::AllPass => 3,
+        BiquadKind::LowShelf => 4,
+        BiquadKind::HighShelf => 5,
+        BiquadKind::Peaking => 6,
+    }
+}
+
+fn kind_from_id(id: u32) -> Result<BiquadKind, String> {
+    match id {
+        0 => Ok(BiquadKind::LowPass),
+        1 => Ok(BiquadKind::HighPass),
+        2 => Ok(BiquadKind::BandPass),
+        3 => Ok(BiquadKind::AllPass),
+        4 => Ok(BiquadKind::LowShelf),
+        5 => Ok(BiquadKind::HighShelf),
+        6 => Ok(BiquadKind::Peaking),
+        _ => Err(format!("Unknown biquad kind {}", id)),
+    }
+}
+
+/// Single band of the equalizer.
+#[derive(Debug, Clone)]
+pub struct EqualizerBand {
+    kind: BiquadKind,
+    frequency: f32,
+    gain: f32,
+    quality: f32,
+    left: Biquad,
+    right: Biquad,
+}
+
+impl Default for EqualizerBand {
+    fn default() -> Self {
+        Self::new(BiquadKind::Peaking, 1000.0, 0.0, 1.0)
+    }
+}
+
+impl EqualizerBand {
+    /// Creates new band, where:
+    /// `kind` - kind of filter of the band.
+    /// `frequency` - center (or cutoff) frequency of the band in hertz.
+    /// `gain` - gain in decibels at `frequency`, it is used only by shelving and peaking bands.
+    /// `quality` - defines width of the band, see [`Biquad::new`] for more info.
+    pub fn new(kind: BiquadKind, frequency: f32, gain: f32, quality: f32) -> Self {
+        let mut band = Self {
+            kind,
+            frequency,
+            gain,
+            quality,
+            left: Default::default(),
+            right: Default::default(),
+        };
+        band.tune();
+        band
+    }
+
+    fn tune(&mut self) {
+        let fc = (self.frequency / SAMPLE_RATE as f32).clamp(0.0, 0.5);
+        // Biquad gain is an amplitude, and shelving and peaking filters give squared gain at fc.
+        let gain = 10.0f32.powf(self.gain / 40.0);
+        let quality = self.quality.max(f32::EPSILON);
+        self.left = Biquad::new(self.kind, fc, gain, quality);
+        self.right = self.left.clone();
+    }
+
+    /// Returns kind of filter of the band.
+    pub fn kind(&self) -> BiquadKind {
+        self.kind
+    }
+
+    /// Returns center (or cutoff) frequency of the band in hertz.
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    /// Returns gain of the band in decibels.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Returns quality of the band.
+    pub fn quality(&self) -> f32 {
+        self.quality
+    }
+
+    fn feed(&mut self, left: f32, right: f32) -> (f32, f32) {
+        (self.left.feed(left), self.right.feed(right))
+    }
+}
+
+impl Visit for EqualizerBand {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut kind = kind_to_id(self.kind);
+        kind.visit("Kind", visitor)?;
+        self.frequency.visit("Frequency", visitor)?;
+        self.gain.visit("Gain", visitor)?;
+        self.quality.visit("Quality", visitor)?;
+
+        if visitor.is_reading() {
+            self.kind = kind_from_id(kind)?;
+            self.tune();
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// See module docs.
+#[derive(Debug, Clone)]
+pub struct Equalizer {
+    base: BaseEffect,
+    bands: Vec<EqualizerBand>,
+}
+
+impl Default for Equalizer {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl Equalizer {
+    /// Creates new equalizer without bands, such equalizer passes signal as is.
+    pub fn new(base: BaseEffect) -> Self {
+        Self {
+            base,
+            bands: Default::default(),
+        }
+    }
+
+    /// Adds new band to the end of the equalizer.
+    pub fn add_band(&mut self, band: EqualizerBand) {
+        self.bands.push(band);
+    }
+
+    /// Replaces band at given index. Panics if index is out of bounds.
+    pub fn set_band(&mut self, index: usize, band: EqualizerBand) {
+        self.bands[index] = band;
+    }
+
+    /// Removes band at given index. Panics if index is out of bounds.
+    pub fn remove_band(&mut self, index: usize) -> EqualizerBand {
+        self.bands.remove(index)
+    }
+
+    /// Returns shared reference to the bands.
+    pub fn bands(&self) -> &[EqualizerBand] {
+        &self.bands
+    }
+}
+
+impl Visit for Equalizer {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.base.visit("Base", visitor)?;
+        self.bands.visit("Bands", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+impl FrameEffect for Equalizer {
+    fn feed(&mut self, mut left: f32, mut right: f32) -> (f32, f32) {
+        for band in self.bands.iter_mut() {
+            let (band_left, band_right) = band.feed(left, right);
+            left = band_left;
+            right = band_right;
+        }
+        (self.gain * left, self.gain * right)
+    }
+}
+
+impl Deref for Equalizer {
+    type Target = BaseEffect;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl DerefMut for Equalizer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        dsp::filters::BiquadKind,
+        effects::{
+            eq::{Equalizer, EqualizerBand},
+            test::{amplitude, sine},
+            BaseEffect, EffectRenderTrait,
+        },
+    };
+
+    fn response(equalizer: &mut Equalizer, frequency: f32) -> f32 {
+        let mut samples = sine(frequency, 1.0, 44100);
+        equalizer.process(&mut samples);
+        amplitude(&samples[22050..])
+    }
+
+    #[test]
+    fn test_peaking_band() {
+        let mut equalizer = Equalizer::new(BaseEffect::default());
+        equalizer.add_band(EqualizerBand::new(BiquadKind::Peaking, 1000.0, 12.0, 1.0));
+
+        // +12 dB is ~3.98 times louder.
+        assert!((response(&mut equalizer, 1000.0) - 3.98).abs() < 0.05);
+        assert!((response(&mut equalizer, 50.0) - 1.0).abs() < 0.05);
+        assert!((response(&mut equalizer, 15000.0) - 1.0).abs() < 0.1);
+
+        equalizer.set_band(
+            0,
+            EqualizerBand::new(BiquadKind::Peaking, 1000.0, -12.0, 1.0),
+        );
+        assert!((response(&mut equalizer, 1000.0) - 0.251).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_multiple_bands() {
+        let mut equalizer = Equalizer::new(BaseEffect::default());
+        equalizer.add_band(EqualizerBand::new(BiquadKind::HighPass, 500.0, 0.0, 0.707));
+        equalizer.add_band(EqualizerBand::new(BiquadKind::LowPass, 4000.0, 0.0, 0.707));
+
+        // -3 dB at cutoff frequencies.
+        assert!((response(&mut equalizer, 500.0) - 0.707).abs() < 0.03);
+        assert!((response(&mut equalizer, 4000.0) - 0.707).abs() < 0.03);
+        assert!((response(&mut equalizer, 1400.0) - 1.0).abs() < 0.05);
+        assert!(response(&mut equalizer, 50.0) < 0.02);
+        assert!(response(&mut equalizer, 16000.0) < 0.1);
+    }
+
+    #[test]
+    fn test_shelf_bands() {
+        let mut equalizer = Equalizer::new(BaseEffect::default());
+        equalizer.add_band(EqualizerBand::new(BiquadKind::LowShelf, 200.0, 6.0, 0.707));
+        equalizer.add_band(EqualizerBand::new(
+            BiquadKind::HighShelf,
+            5000.0,
+            -6.0,
+            0.707,
+        ));
+
+        assert!((response(&mut equalizer, 30.0) - 1.995).abs() < 0.05);
+        assert!((response(&mut equalizer, 1000.0) - 1.0).abs() < 0.1);
+        assert!((response(&mut equalizer, 18000.0) - 0.501).abs() < 0.03);
+    }
+}
@@ -7,7 +7,9 @@
 use crate::{
     context::DistanceModel,
     dsp::filters::Biquad,
-    effects::reverb::Reverb,
+    effects::{
+        chorus::Chorus, compressor::Compressor, delay::Delay, eq::Equalizer, reverb::Reverb,
+    },
     listener::Listener,
     source::{SoundSource, Status},
 };
@@ -18,6 +20,10 @@ use rg3d_core::{
 };
 use std::ops::{Deref, DerefMut};
 
+pub mod chorus;
+pub mod compressor;
+pub mod delay;
+pub mod eq;
 pub mod reverb;
 
 /// Stub effect that does nothing.
@@ -45,6 +51,8 @@ impl EffectRenderTrait for StubEffect {
         _mix_buf: &mut [(f32, f32)],
     ) {
     }
+
+    fn process(&mut self, _buf: &mut [(f32, f32)]) {}
 }
 
 impl Deref for StubEffect {
@@ -68,6 +76,14 @@ pub enum Effect {
     Stub(StubEffect),
     /// Reverberation effect. See corresponding module for more info.
     Reverb(Reverb),
+    /// Multi-band parametric equalizer. See corresponding module for more info.
+    Equalizer(Equalizer),
+    /// Dynamic range compressor or limiter. See corresponding module for more info.
+    Compressor(Compressor),
+    /// Stereo delay (echo) with feedback. See corresponding module for more info.
+    Delay(Delay),
+    /// Chorus or flanger. See corresponding module for more info.
+    Chorus(Chorus),
 }
 
 impl Default for Effect {
@@ -81,6 +97,10 @@ impl Effect {
         match self {
             Effect::Stub(_) => 0,
             Effect::Reverb(_) => 1,
+            Effect::Equalizer(_) => 2,
+            Effect::Compressor(_) => 3,
+            Effect::Delay(_) => 4,
+            Effect::Chorus(_) => 5,
         }
     }
 
@@ -88,6 +108,10 @@ impl Effect {
         match id {
             0 => Ok(Effect::Stub(Default::default())),
             1 => Ok(Effect::Reverb(Default::default())),
+            2 => Ok(Effect::Equalizer(Default::default())),
+            3 => Ok(Effect::Compressor(Default::default())),
+            4 => Ok(Effect::Delay(Default::default())),
+            5 => Ok(Effect::Chorus(Default::default())),
             _ => Err(format!("Unknown effect id {}", id)),
         }
     }
@@ -106,6 +130,10 @@ impl Visit for Effect {
         match self {
             Effect::Stub(v) => v.visit("Data", visitor)?,
             Effect::Reverb(v) => v.visit("Data", visitor)?,
+            Effect::Equalizer(v) => v.visit("Data", visitor)?,
+            Effect::Compressor(v) => v.visit("Data", visitor)?,
+            Effect::Delay(v) => v.visit("Data", visitor)?,
+            Effect::Chorus(v) => v.visit("Data", visitor)?,
         }
 
         visitor.leave_region()
@@ -120,6 +148,49 @@ pub(in crate) trait EffectRenderTrait {
         distance_model: DistanceModel,
         mix_buf: &mut [(f32, f32)],
     );
+
+    /// Processes samples in-place, it is used when the effect is inserted in an effect chain
+    /// of a bus. Inputs of the effect are ignored in this case.
+    fn process(&mut self, buf: &mut [(f32, f32)]);
+}
+
+/// Effect that transforms samples frame by frame, [`EffectRenderTrait`] is implemented for every
+/// such effect - samples of inputs or samples of a bus are just passed through [`Self::feed`].
+pub(in crate) trait FrameEffect: DerefMut<Target = BaseEffect> {
+    /// Processes single stereo frame and returns processed frame.
+    fn feed(&mut self, left: f32, right: f32) -> (f32, f32);
+}
+
+impl<T: FrameEffect> EffectRenderTrait for T {
+    fn render(
+        &mut self,
+        sources: &Pool<SoundSource>,
+        listener: &Listener,
+        distance_model: DistanceModel,
+        mix_buf: &mut [(f32, f32)],
+    ) {
+        self.deref_mut()
+            .render(sources, listener, distance_model, mix_buf.len());
+
+        let frame_samples = std::mem::take(&mut self.deref_mut().frame_samples);
+
+        for ((out_left, out_right), &(left, right)) in mix_buf.iter_mut().zip(frame_samples.iter())
+        {
+            let (processed_left, processed_right) = self.feed(left, right);
+            *out_left += processed_left;
+            *out_right += processed_right;
+        }
+
+        self.deref_mut().frame_samples = frame_samples;
+    }
+
+    fn process(&mut self, buf: &mut [(f32, f32)]) {
+        for (left, right) in buf.iter_mut() {
+            let (processed_left, processed_right) = self.feed(*left, *right);
+            *left = processed_left;
+            *right = processed_right;
+        }
+    }
 }
 
 /// Base effect for all other kinds of effects. It contains set of inputs (direct
@@ -354,6 +425,10 @@ macro_rules! static_dispatch {
         match $self {
             Effect::Stub(v) => v.$func($($args),*),
             Effect::Reverb(v) => v.$func($($args),*),
+            Effect::Equalizer(v) => v.$func($($args),*),
+            Effect::Compressor(v) => v.$func($($args),*),
+            Effect::Delay(v) => v.$func($($args),*),
+            Effect::Chorus(v) => v.$func($($args),*),
         }
     };
 }
@@ -368,6 +443,10 @@ impl EffectRenderTrait for Effect {
     ) {
         static_dispatch!(self, render, sources, listener, distance_model, mix_buf)
     }
+
+    fn process(&mut self, buf: &mut [(f32, f32)]) {
+        static_dispatch!(self, process, buf)
+    }
 }
 
 impl Deref for Effect {
@@ -377,6 +456,10 @@ impl Deref for Effect {
         match self {
             Effect::Stub(v) => v,
             Effect::Reverb(v) => v,
+            Effect::Equalizer(v) => v,
+            Effect::Compressor(v) => v,
+            Effect::Delay(v) => v,
+            Effect::Chorus(v) => v,
         }
     }
 }
@@ -386,6 +469,103 @@ impl DerefMut for Effect {
         match self {
             Effect::Stub(v) => v,
             Effect::Reverb(v) => v,
+            Effect::Equalizer(v) => v,
+            Effect::Compressor(v) => v,
+            Effect::Delay(v) => v,
+            Effect::Chorus(v) => v,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(in crate) mod test {
+    use crate::{
+        dsp::filters::BiquadKind,
+        effects::{
+            chorus::Chorus, compressor::Compressor, delay::Delay, eq::EqualizerBand,
+            reverb::Reverb, BaseEffect, Effect,
+        },
+    };
+    use rg3d_core::{
+        futures::executor::block_on,
+        visitor::{Visit, Visitor},
+    };
+    use std::time::Duration;
+
+    /// Generates stereo sine wave with given frequency, amplitude and length in samples.
+    pub fn sine(frequency: f32, amplitude: f32, len: usize) -> Vec<(f32, f32)> {
+        (0..len)
+            .map(|i| {
+                let sample =
+                    amplitude * (2.0 * std::f32::consts::PI * frequency * i as f32 / 44100.0).sin();
+                (sample, sample)
+            })
+            .collect()
+    }
+
+    /// Returns peak amplitude of left channel.
+    pub fn amplitude(samples: &[(f32, f32)]) -> f32 {
+        samples
+            .iter()
+            .fold(0.0, |amplitude: f32, (left, _)| amplitude.max(left.abs()))
+    }
+
+    #[test]
+    fn test_effects_visit() {
+        let path = std::env::temp_dir().join("rg3d_sound_effects.bin");
+
+        let mut equalizer = crate::effects::eq::Equalizer::new(BaseEffect::default());
+        equalizer.add_band(EqualizerBand::new(BiquadKind::Peaking, 500.0, 3.0, 2.0));
+        let mut compressor = Compressor::new(BaseEffect::default());
+        compressor.set_ratio(8.0);
+        let mut delay = Delay::new(BaseEffect::default());
+        delay.set_right_time(Duration::from_millis(400));
+        let mut chorus = Chorus::flanger(BaseEffect::default());
+        chorus.set_gain(0.5);
+
+        let mut effects = vec![
+            Effect::Reverb(Reverb::new(BaseEffect::default())),
+            Effect::Equalizer(equalizer),
+            Effect::Compressor(compressor),
+            Effect::Delay(delay),
+            Effect::Chorus(chorus),
+        ];
+
+        let mut visitor = Visitor::new();
+        effects.visit("Effects", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+
+        let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+        let mut loaded = Vec::<Effect>::new();
+        loaded.visit("Effects", &mut visitor).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.len(), 5);
+        assert!(matches!(loaded[0], Effect::Reverb(_)));
+        match &loaded[1] {
+            Effect::Equalizer(equalizer) => {
+                assert_eq!(equalizer.bands().len(), 1);
+                assert_eq!(equalizer.bands()[0].kind(), BiquadKind::Peaking);
+                assert_eq!(equalizer.bands()[0].frequency(), 500.0);
+            }
+            _ => panic!("expected equalizer"),
+        }
+        match &loaded[2] {
+            Effect::Compressor(compressor) => assert_eq!(compressor.ratio(), 8.0),
+            _ => panic!("expected compressor"),
+        }
+        match &loaded[3] {
+            Effect::Delay(delay) => {
+                assert_eq!(delay.right_time().as_millis(), 400)
+            }
+            _ => panic!("expected delay"),
+        }
+        match &loaded[4] {
+            Effect::Chorus(chorus) => {
+                assert_eq!(chorus.feedback(), 0.7);
+                assert_eq!(chorus.gain(), 0.5);
+            }
+            _ => panic!("expected chorus"),
         }
     }
 }
@@ -26,16 +26,10 @@
 //! is acceptable. To remove this effect, more complex reverberator should be implemented.
 
 use crate::{
-    context::DistanceModel,
     dsp::filters::{AllPass, LpfComb},
-    effects::{BaseEffect, EffectRenderTrait},
-    listener::Listener,
-    source::SoundSource,
-};
-use rg3d_core::{
-    pool::Pool,
-    visitor::{Visit, VisitResult, Visitor},
+    effects::{BaseEffect, FrameEffect},
 };
+use rg3d_core::visitor::{Visit, VisitResult, Visitor};
 use std::{
     ops::{Deref, DerefMut},
     time::Duration,
@@ -262,34 +256,21 @@ impl Visit for Reverb {
     }
 }
 
-impl EffectRenderTrait for Reverb {
-    fn render(
-        &mut self,
-        sources: &Pool<SoundSource>,
-        listener: &Listener,
-        distance_model: DistanceModel,
-        mix_buf: &mut [(f32, f32)],
-    ) {
-        self.base
-            .render(sources, listener, distance_model, mix_buf.len());
+impl FrameEffect for Reverb {
+    fn feed(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let mid = (left + right) * 0.5;
+        let input = mid * Self::GAIN;
+
+        let processed_left = self.left.feed(input);
+        let processed_right = self.right.feed(input);
 
         let wet1 = self.wet;
         let wet2 = 1.0 - self.wet;
 
-        for ((out_left, out_right), &(left, right)) in
-            mix_buf.iter_mut().zip(self.base.frame_samples.iter())
-        {
-            let mid = (left + right) * 0.5;
-            let input = mid * Self::GAIN;
-
-            let processed_left = self.left.feed(input);
-            let processed_right = self.right.feed(input);
-
-            *out_left +=
-                self.gain * (processed_left * wet1 + processed_right * wet2 + self.dry * left);
-            *out_right +=
-                self.gain * (processed_right * wet1 + processed_left * wet2 + self.dry * right);
-        }
+        (
+            self.gain * (processed_left * wet1 + processed_right * wet2 + self.dry * left),
+            self.gain * (processed_right * wet1 + processed_left * wet2 + self.dry * right),
+        )
     }
 }
 
@@ -0,0 +1,224 @@
+//! Encoder module.
+//!
+//! # Overview
+//!
+//! Encoder writes stereo samples produced by the engine to a WAV file. It is useful to save
+//! results of offline rendering (see [`crate::engine::SoundEngine::render_offline`]), for
+//! example to bounce audio of a cinematic or to compare output of the engine with reference
+//! files in tests.
+//!
+//! Multichannel files could be written by encoders created with a channel layout, channels are
+//! written in order described in [`crate::layout`] module docs. Speaker positions stored in the
+//! header of such files are generic, so some players may assign channels differently.
+
+use crate::{
+    context::SAMPLE_RATE, device::sample_to_i16, error::SoundError, layout::ChannelLayout,
+};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, Write},
+    path::Path,
+};
+
+/// Format of samples in a WAV file.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WavSampleFormat {
+    /// Signed 16-bit integer samples, values out of `[-1; 1]` range are clamped.
+    Pcm16,
+    /// 32-bit floating point samples, values are written as is.
+    Float32,
+}
+
+impl Default for WavSampleFormat {
+    fn default() -> Self {
+        Self::Pcm16
+    }
+}
+
+/// Incremental WAV encoder, samples could be written in chunks. The encoder must be finalized
+/// by [`WavEncoder::finalize`] to write correct header of the file, otherwise header will be
+/// written on drop and all errors will be ignored.
+pub struct WavEncoder<W: Write + Seek> {
+    writer: WavWriter<W>,
+    format: WavSampleFormat,
+    layout: ChannelLayout,
+    frames_written: usize,
+}
+
+impl WavEncoder<BufWriter<File>> {
+    /// Creates new WAV file at given path and returns encoder that writes to it.
+    pub fn create<P: AsRef<Path>>(path: P, format: WavSampleFormat) -> Result<Self, SoundError> {
+        Self::new(BufWriter::new(File::create(path)?), format)
+    }
+
+    /// Creates new WAV file at given path and returns encoder that writes samples of given
+    /// channel layout to it.
+    pub fn create_with_layout<P: AsRef<Path>>(
+        path: P,
+        format: WavSampleFormat,
+        layout: ChannelLayout,
+    ) -> Result<Self, SoundError> {
+        Self::with_layout(BufWriter::new(File::create(path)?), format, layout)
+    }
+}
+
+impl<W: Write + Seek> WavEncoder<W> {
+    /// Creates new encoder that writes stereo samples with sample rate of the engine to given
+    /// writer.
+    pub fn new(writer: W, format: WavSampleFormat) -> Result<Self, SoundError> {
+        Self::with_layout(writer, format, ChannelLayout::Stereo)
+    }
+
+    /// Creates new encoder that writes samples of given channel layout with sample rate of the
+    /// engine to given writer.
+    pub fn with_layout(
+        writer: W,
+        format: WavSampleFormat,
+        layout: ChannelLayout,
+    ) -> Result<Self, SoundError> {
+        let channels = layout.channel_count() as u16;
+        let spec = match format {
+            WavSampleFormat::Pcm16 => WavSpec {
+                channels,
+                sample_rate: SAMPLE_RATE,
+                bits_per_sample: 16,
+                sample_format: SampleFormat::Int,
+            },
+            WavSampleFormat::Float32 => WavSpec {
+                channels,
+                sample_rate: SAMPLE_RATE,
+                bits_per_sample: 32,
+                sample_format: SampleFormat::Float,
+            },
+        };
+
+        Ok(Self {
+            writer: WavWriter::new(writer, spec)?,
+            format,
+            layout,
+            frames_written: 0,
+        })
+    }
+
+    /// Writes interleaved stereo samples. If the encoder has multichannel layout, the samples
+    /// are written to front channels and other channels are silent.
+    pub fn write(&mut self, samples: &[(f32, f32)]) -> Result<(), SoundError> {
+        for &(left, right) in samples {
+            self.write_sample(left)?;
+            self.write_sample(right)?;
+            for _ in 2..self.layout.channel_count() {
+                self.write_sample(0.0)?;
+            }
+        }
+        self.frames_written += samples.len();
+        Ok(())
+    }
+
+    /// Writes interleaved samples of layout of the encoder. Amount of samples must be multiple
+    /// of channel count of the layout, otherwise the file will be corrupted.
+    pub fn write_interleaved(&mut self, samples: &[f32]) -> Result<(), SoundError> {
+        for &sample in samples {
+            self.write_sample(sample)?;
+        }
+        self.frames_written += samples.len() / self.layout.channel_count();
+        Ok(())
+    }
+
+    fn write_sample(&mut self, sample: f32) -> Result<(), SoundError> {
+        match self.format {
+            WavSampleFormat::Pcm16 => self.writer.write_sample(sample_to_i16(sample))?,
+            WavSampleFormat::Float32 => self.writer.write_sample(sample)?,
+        }
+        Ok(())
+    }
+
+    /// Returns total amount of written frames (samples of every channel at the same time).
+    pub fn frames_written(&self) -> usize {
+        self.frames_written
+    }
+
+    /// Returns format of samples.
+    pub fn format(&self) -> WavSampleFormat {
+        self.format
+    }
+
+    /// Returns channel layout of the encoder.
+    pub fn layout(&self) -> ChannelLayout {
+        self.layout
+    }
+
+    /// Writes header of the file and flushes all buffered data.
+    pub fn finalize(self) -> Result<(), SoundError> {
+        self.writer.finalize()?;
+        Ok(())
+    }
+}
+
+/// Writes given stereo samples to a new WAV file at given path.
+pub fn save_wav<P: AsRef<Path>>(
+    path: P,
+    samples: &[(f32, f32)],
+    format: WavSampleFormat,
+) -> Result<(), SoundError> {
+    let mut encoder = WavEncoder::create(path, format)?;
+    encoder.write(samples)?;
+    encoder.finalize()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        context::SAMPLE_RATE,
+        encoder::{WavEncoder, WavSampleFormat},
+    };
+    use std::io::Cursor;
+
+    fn encode(
+        format: WavSampleFormat,
+        samples: &[(f32, f32)],
+    ) -> hound::WavReader<Cursor<Vec<u8>>> {
+        let mut data = Cursor::new(Vec::new());
+        let mut encoder = WavEncoder::new(&mut data, format).unwrap();
+        encoder.write(samples).unwrap();
+        assert_eq!(encoder.frames_written(), samples.len());
+        encoder.finalize().unwrap();
+        data.set_position(0);
+        hound::WavReader::new(data).unwrap()
+    }
+
+    #[test]
+    fn test_pcm16_round_trip() {
+        let mut reader = encode(
+            WavSampleFormat::Pcm16,
+            &[(0.0, 1.0), (-1.0, 0.5), (2.0, -2.0)],
+        );
+        let spec = reader.spec();
+        assert_eq!(
+            (spec.channels, spec.sample_rate, spec.bits_per_sample),
+            (2, SAMPLE_RATE, 16)
+        );
+        let samples = reader
+            .samples::<i16>()
+            .map(|s| s.unwrap())
+            .collect::<Vec<_>>();
+        // Out of range samples are clamped.
+        assert_eq!(samples, vec![0, 32767, -32767, 16383, 32767, -32767]);
+    }
+
+    #[test]
+    fn test_float32_round_trip() {
+        let source = [(0.25, -0.75), (1.5, -3.0)];
+        let mut reader = encode(WavSampleFormat::Float32, &source);
+        let spec = reader.spec();
+        assert_eq!(
+            (spec.channels, spec.sample_rate, spec.bits_per_sample),
+            (2, SAMPLE_RATE, 32)
+        );
+        let samples = reader
+            .samples::<f32>()
+            .map(|s| s.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(samples, vec![0.25, -0.75, 1.5, -3.0]);
+    }
+}
@@ -3,16 +3,40 @@
 //! ## Overview
 //!
 //! Sound engine manages contexts, feeds output device with data.
+//!
+//! ## Channel layout
+//!
+//! Sound is mixed for output channel layout of the engine, see [`crate::layout`]. Output device
+//! (ALSA, DirectSound, CoreAudio or Web Audio) is opened with channel count of the layout that
+//! is passed to [`SoundEngine::with_output_layout`], [`SoundEngine::new`] opens a stereo device.
+//! If output layout of the engine is changed later, the sound is remixed to the layout of the
+//! device. Multichannel samples could also be obtained by [`SoundEngine::render_multichannel`]
+//! or recorded by the file sink, see [`SoundEngine::with_file_sink_and_layout`].
 
-use crate::{context::SoundContext, device};
+use crate::{
+    context::{SoundContext, SAMPLE_RATE},
+    device,
+    encoder::{WavEncoder, WavSampleFormat},
+    error::SoundError,
+    layout::{ChannelLayout, MAX_CHANNELS},
+};
 use rg3d_core::visitor::{Visit, VisitResult, Visitor};
-use std::sync::{Arc, Mutex};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 /// Internal state of sound engine.
 #[derive(Default)]
 pub struct SoundEngine {
     contexts: Vec<SoundContext>,
     master_gain: f32,
+    output_layout: ChannelLayout,
+    // Temporary buffers for rendering of multichannel layouts.
+    front_buffer: Vec<(f32, f32)>,
+    surround_buffer: Vec<f32>,
+    remix_buffer: Vec<f32>,
 }
 
 impl SoundEngine {
@@ -20,19 +44,30 @@ impl SoundEngine {
     /// the same time, but you shouldn't do this because you can create multiple contexts which
     /// should cover 99% of use cases.
     pub fn new() -> Arc<Mutex<Self>> {
+        Self::with_output_layout(ChannelLayout::Stereo)
+    }
+
+    /// Creates new instance of a sound engine with given output layout, the output device is
+    /// opened with channel count of the layout. See [`Self::new`] for more info.
+    pub fn with_output_layout(layout: ChannelLayout) -> Arc<Mutex<Self>> {
         let engine = Arc::new(Mutex::new(Self {
             contexts: Default::default(),
             master_gain: 1.0,
+            output_layout: layout,
+            ..Default::default()
         }));
 
         // Run the default output device. Internally it creates separate thread, so we have
         // to share sound engine instance with it, this is the only reason why it is wrapped
         // in Arc<Mutex<>>
-        device::run_device(4 * SoundContext::SAMPLES_PER_CHANNEL as u32, {
+        let channel_count = layout.channel_count();
+        let buffer_len_bytes =
+            channel_count * std::mem::size_of::<i16>() * SoundContext::SAMPLES_PER_CHANNEL;
+        device::run_device(buffer_len_bytes as u32, channel_count, {
             let state = engine.clone();
             move |buf| {
                 if let Ok(mut state) = state.lock() {
-                    state.render_inner(buf);
+                    state.render_to_layout(layout, buf);
                 }
             }
         });
@@ -46,9 +81,63 @@ impl SoundEngine {
         Arc::new(Mutex::new(Self {
             contexts: Default::default(),
             master_gain: 1.0,
+            ..Default::default()
         }))
     }
 
+    /// Creates new instance of a sound engine that writes everything it plays to a WAV file at
+    /// given path in given sample format instead of sending it to output device. Samples are
+    /// consumed in real time, the same as real device does, so it could be used to record sound
+    /// of a game without sound hardware. The file is finalized when the engine is destroyed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_file_sink<P: AsRef<Path>>(
+        path: P,
+        format: WavSampleFormat,
+    ) -> Result<Arc<Mutex<Self>>, SoundError> {
+        Self::with_file_sink_and_layout(path, format, ChannelLayout::Stereo)
+    }
+
+    /// Creates new instance of a sound engine with given output layout that records every
+    /// channel of the layout to a WAV file at given path, see [`Self::with_file_sink`] for more
+    /// info. If output layout of the engine is changed later, the sound is remixed to the layout
+    /// of the file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_file_sink_and_layout<P: AsRef<Path>>(
+        path: P,
+        format: WavSampleFormat,
+        layout: ChannelLayout,
+    ) -> Result<Arc<Mutex<Self>>, SoundError> {
+        let engine = Arc::new(Mutex::new(Self {
+            contexts: Default::default(),
+            master_gain: 1.0,
+            output_layout: layout,
+            ..Default::default()
+        }));
+
+        // Device thread must not keep the engine alive, otherwise the file will never be
+        // finalized.
+        device::run_file_sink(
+            path.as_ref(),
+            format,
+            layout,
+            SoundContext::SAMPLES_PER_CHANNEL,
+            {
+                let state = Arc::downgrade(&engine);
+                move |buf| match state.upgrade() {
+                    Some(state) => {
+                        if let Ok(mut state) = state.lock() {
+                            state.render_to_layout(layout, buf);
+                        }
+                        true
+                    }
+                    None => false,
+                }
+            },
+        )?;
+
+        Ok(engine)
+    }
+
     /// Adds new context to the engine. Each context must be added to the engine to emit
     /// sounds.
     pub fn add_context(&mut self, context: SoundContext) {
@@ -84,6 +173,22 @@ impl SoundEngine {
         self.master_gain
     }
 
+    /// Sets layout of output channels. See module docs for more info.
+    ///
+    /// # Limitations
+    ///
+    /// Output device is not reopened, it keeps channel count of the layout the engine was
+    /// created with and gets the sound remixed to it. Use [`Self::with_output_layout`] to get
+    /// a device with every channel of a layout.
+    pub fn set_output_layout(&mut self, layout: ChannelLayout) {
+        self.output_layout = layout;
+    }
+
+    /// Returns layout of output channels.
+    pub fn output_layout(&self) -> ChannelLayout {
+        self.output_layout
+    }
+
     /// Returns the length of buf to be passed to [`Self::render()`].
     pub fn render_buffer_len() -> usize {
         SoundContext::SAMPLES_PER_CHANNEL
@@ -102,10 +207,174 @@ impl SoundEngine {
         self.render_inner(buf);
     }
 
+    /// Renders the sound into buf as interleaved samples of every channel of output layout,
+    /// see [`crate::layout`] for order of the channels. The buf must have at least
+    /// [`Self::render_buffer_len()`] multiplied by channel count of the layout elements. The
+    /// same restrictions as for [`Self::render`] are applied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if length of the buf is not a multiple of channel count of the layout or if it is
+    /// less than required.
+    pub fn render_multichannel(&mut self, buf: &mut [f32]) {
+        let channel_count = self.output_layout.channel_count();
+        assert_eq!(
+            buf.len() % channel_count,
+            0,
+            "length of the buffer must be a multiple of channel count"
+        );
+        assert!(
+            buf.len() >= Self::render_buffer_len() * channel_count,
+            "the buffer must have at least {} samples",
+            Self::render_buffer_len() * channel_count
+        );
+        self.render_multichannel_inner(buf);
+    }
+
+    fn render_multichannel_inner(&mut self, buf: &mut [f32]) {
+        let channel_count = self.output_layout.channel_count();
+        let surround_channels = channel_count - 2;
+        let frame_count = buf.len() / channel_count;
+
+        let mut front = std::mem::take(&mut self.front_buffer);
+        front.clear();
+        front.resize(frame_count, (0.0, 0.0));
+        let mut surround = std::mem::take(&mut self.surround_buffer);
+        surround.clear();
+        surround.resize(frame_count * surround_channels, 0.0);
+
+        self.render_contexts(&mut front, &mut surround);
+
+        for (i, (frame, &(left, right))) in buf
+            .chunks_exact_mut(channel_count)
+            .zip(front.iter())
+            .enumerate()
+        {
+            frame[0] = left;
+            frame[1] = right;
+            frame[2..]
+                .copy_from_slice(&surround[(i * surround_channels)..((i + 1) * surround_channels)]);
+        }
+
+        self.front_buffer = front;
+        self.surround_buffer = surround;
+    }
+
+    /// Renders sound of every context for given duration, it advances playback of every source
+    /// the same as output device does. Sound is rendered in blocks of
+    /// [`Self::render_buffer_len()`] samples, so the duration is rounded up to whole amount of
+    /// blocks - consecutive calls produce continuous sound. Like [`Self::render`] this method
+    /// must be used only if the engine was created via [`Self::without_device`].
+    ///
+    /// Result of rendering is deterministic, so it could be used to compare output of the
+    /// engine with reference samples in tests.
+    pub fn render_offline(&mut self, duration: Duration) -> Vec<(f32, f32)> {
+        let block_len = Self::render_buffer_len();
+        let mut samples = vec![(0.0, 0.0); Self::block_count(duration) * block_len];
+        for block in samples.chunks_exact_mut(block_len) {
+            self.render(block);
+        }
+        samples
+    }
+
+    /// Renders sound of every context for given duration as interleaved samples of every
+    /// channel of output layout. See [`Self::render_offline`] and
+    /// [`Self::render_multichannel`] for more info.
+    pub fn render_offline_multichannel(&mut self, duration: Duration) -> Vec<f32> {
+        let block_len = Self::render_buffer_len() * self.output_layout.channel_count();
+        let mut samples = vec![0.0; Self::block_count(duration) * block_len];
+        for block in samples.chunks_exact_mut(block_len) {
+            self.render_multichannel(block);
+        }
+        samples
+    }
+
+    /// Renders sound of every context for given duration and writes it to a WAV file at given
+    /// path. The file has the same channel layout as output of the engine. See
+    /// [`Self::render_offline`] for more info.
+    pub fn render_offline_to_wav<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        duration: Duration,
+        format: WavSampleFormat,
+    ) -> Result<(), SoundError> {
+        let mut encoder = WavEncoder::create_with_layout(path, format, self.output_layout)?;
+        let mut block = vec![0.0; Self::render_buffer_len() * self.output_layout.channel_count()];
+        for _ in 0..Self::block_count(duration) {
+            self.render_multichannel(&mut block);
+            encoder.write_interleaved(&block)?;
+        }
+        encoder.finalize()
+    }
+
+    // Renders the sound for output layout of the engine and remixes it to given layout if they
+    // differ.
+    fn render_to_layout(&mut self, layout: ChannelLayout, buf: &mut [f32]) {
+        if layout == self.output_layout {
+            self.render_multichannel_inner(buf);
+        } else {
+            let frame_count = buf.len() / layout.channel_count();
+            let mut mixed = std::mem::take(&mut self.remix_buffer);
+            mixed.clear();
+            mixed.resize(frame_count * self.output_layout.channel_count(), 0.0);
+            self.render_multichannel_inner(&mut mixed);
+            buf.fill(0.0);
+            for (input, output) in mixed
+                .chunks_exact(self.output_layout.channel_count())
+                .zip(buf.chunks_exact_mut(layout.channel_count()))
+            {
+                ChannelLayout::remix(self.output_layout, layout, input, output);
+            }
+            self.remix_buffer = mixed;
+        }
+    }
+
+    fn block_count(duration: Duration) -> usize {
+        // Duration has nanosecond precision, so duration of a whole amount of frames may be up
+        // to a nanosecond longer and must not be rounded up to one more frame.
+        const NANOS_PER_SEC: u128 = 1_000_000_000;
+        let rate = SAMPLE_RATE as u128;
+        let frames = (duration.as_nanos() * rate).saturating_sub(rate);
+        let frames = ((frames + NANOS_PER_SEC - 1) / NANOS_PER_SEC) as usize;
+        let block_len = Self::render_buffer_len();
+        (frames + block_len - 1) / block_len
+    }
+
     fn render_inner(&mut self, buf: &mut [(f32, f32)]) {
+        let layout = self.output_layout;
+        let channel_count = layout.channel_count();
+        let surround_channels = channel_count - 2;
+
+        let mut surround = std::mem::take(&mut self.surround_buffer);
+        surround.clear();
+        surround.resize(buf.len() * surround_channels, 0.0);
+
+        self.render_contexts(buf, &mut surround);
+
+        // Downmix to stereo if the layout has more channels.
+        if surround_channels > 0 {
+            let mut frame = [0.0; MAX_CHANNELS];
+            for ((left, right), surround) in
+                buf.iter_mut().zip(surround.chunks_exact(surround_channels))
+            {
+                frame[0] = *left;
+                frame[1] = *right;
+                frame[2..channel_count].copy_from_slice(surround);
+                let (downmixed_left, downmixed_right) =
+                    layout.downmix_to_stereo(&frame[..channel_count]);
+                *left = downmixed_left;
+                *right = downmixed_right;
+            }
+        }
+
+        self.surround_buffer = surround;
+    }
+
+    fn render_contexts(&mut self, buf: &mut [(f32, f32)], surround: &mut [f32]) {
         let master_gain = self.master_gain;
+        let layout = self.output_layout;
         for context in self.contexts.iter_mut() {
-            context.state().render(master_gain, buf);
+            context.state().render(master_gain, layout, buf, surround);
         }
     }
 }
@@ -120,7 +389,63 @@ impl Visit for SoundEngine {
 
         self.master_gain.visit("MasterGain", visitor)?;
         self.contexts.visit("Contexts", visitor)?;
+        let _ = self.output_layout.visit("OutputLayout", visitor);
 
         visitor.leave_region()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        buffer::{DataSource, SoundBufferResource},
+        context::{SoundContext, SAMPLE_RATE},
+        engine::SoundEngine,
+        source::{generic::GenericSourceBuilder, Status},
+    };
+    use std::time::Duration;
+
+    fn render_sine(duration: Duration) -> Vec<(f32, f32)> {
+        let buffer = SoundBufferResource::new_generic(DataSource::Raw {
+            sample_rate: 44100,
+            channel_count: 1,
+            samples: (0..44100)
+                .map(|i| (i as f32 * 440.0 * std::f32::consts::TAU / 44100.0).sin())
+                .collect(),
+        })
+        .unwrap();
+        let context = SoundContext::new();
+        context.state().add_source(
+            GenericSourceBuilder::new()
+                .with_buffer(buffer)
+                .with_status(Status::Playing)
+                .build_source()
+                .unwrap(),
+        );
+
+        let engine = SoundEngine::without_device();
+        let mut engine = engine.lock().unwrap();
+        engine.add_context(context);
+        engine.render_offline(duration)
+    }
+
+    #[test]
+    fn test_render_offline_determinism() {
+        let duration = Duration::from_millis(100);
+        let samples = render_sine(duration);
+        assert!(samples.iter().any(|(left, _)| *left != 0.0));
+        assert_eq!(samples, render_sine(duration));
+    }
+
+    #[test]
+    fn test_block_count() {
+        let block_len = SoundEngine::render_buffer_len();
+        let frames = |count: usize| Duration::from_secs_f64(count as f64 / SAMPLE_RATE as f64);
+        assert_eq!(SoundEngine::block_count(Duration::default()), 0);
+        assert_eq!(SoundEngine::block_count(frames(1)), 1);
+        assert_eq!(SoundEngine::block_count(frames(block_len)), 1);
+        assert_eq!(SoundEngine::block_count(frames(block_len + 1)), 2);
+        assert_eq!(SoundEngine::block_count(frames(10 * block_len)), 10);
+        assert_eq!(render_sine(frames(block_len + 1)).len(), 2 * block_len);
+    }
+}
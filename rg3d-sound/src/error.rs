@@ -11,6 +11,9 @@ pub enum DecoderError {
 
     /// Ogg/vorbis (lewton) specific error.
     Ogg(lewton::VorbisError),
+
+    /// FLAC or MP3 (symphonia) specific error.
+    Symphonia(symphonia::core::errors::Error),
 }
 
 /// Generic error enumeration for each error in this engine.
@@ -53,6 +56,21 @@ pub enum SoundError {
 
     /// A buffer is not loaded yet, consider to `await` it before use.
     BufferIsNotLoaded,
+
+    /// Encoder specific error, exact reason stored in inner value.
+    EncoderError(String),
+
+    /// Raw data source has amount of samples that is not a multiple of its channel count.
+    InvalidSampleCount {
+        /// Total amount of samples.
+        sample_count: usize,
+        /// Amount of channels.
+        channel_count: usize,
+    },
+
+    /// Data source can't be used with a buffer of this kind, for example raw samples can't be
+    /// streamed.
+    UnsupportedDataSource,
 }
 
 impl From<std::io::Error> for SoundError {
@@ -73,6 +91,15 @@ impl From<lewton::VorbisError> for SoundError {
     }
 }
 
+impl From<hound::Error> for SoundError {
+    fn from(e: hound::Error) -> Self {
+        match e {
+            hound::Error::IoError(io) => SoundError::Io(io),
+            _ => SoundError::EncoderError(e.to_string()),
+        }
+    }
+}
+
 impl Display for SoundError {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         match self {
@@ -93,6 +120,18 @@ impl Display for SoundError {
             SoundError::DecoderError(de) => write!(f, "internal decoder error: {:?}", de),
             SoundError::BufferFailedToLoad => write!(f, "a buffer failed to load"),
             SoundError::BufferIsNotLoaded => write!(f, "a buffer is not loaded yet"),
+            SoundError::EncoderError(reason) => write!(f, "internal encoder error: {}", reason),
+            SoundError::InvalidSampleCount {
+                sample_count,
+                channel_count,
+            } => write!(
+                f,
+                "sample count {} is not a multiple of channel count {}",
+                sample_count, channel_count
+            ),
+            SoundError::UnsupportedDataSource => {
+                write!(f, "data source is not supported by the buffer")
+            }
         }
     }
 }
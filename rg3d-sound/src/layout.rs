@@ -0,0 +1,617 @@
+//! Channel layout module.
+//!
+//! # Overview
+//!
+//! Channel layout defines amount of output channels and positions of speakers that are connected
+//! to them. The engine supports stereo, 5.1 and 7.1 layouts, see [`ChannelLayout`]. Output device
+//! is opened with every channel of a layout by [`crate::engine::SoundEngine::with_output_layout`],
+//! surround sound is also available via [`crate::engine::SoundEngine::render_multichannel`] and
+//! the file sink. Samples of multichannel layouts are interleaved in the same order as in WAV
+//! files, native devices reorder them if they need to:
+//!
+//! | Layout | Channel order                  |
+//! |--------|--------------------------------|
+//! | Stereo | FL FR                          |
+//! | 5.1    | FL FR FC LFE SL SR             |
+//! | 7.1    | FL FR FC LFE BL BR SL SR       |
+//!
+//! # Panning
+//!
+//! Spatial sources are panned across speakers of 5.1 and 7.1 layouts using 2D vector base
+//! amplitude panning (VBAP) - a sound is distributed between two adjacent speakers that surround
+//! direction to the sound. Speakers are considered to be in horizontal plane of the listener, so
+//! elevation of a sound only makes it more diffuse - a sound that is right above the listener
+//! is played evenly by every speaker. Low frequency channel is never used for panning.
+//!
+//! # Up- and downmixing
+//!
+//! Multichannel sound buffers (6 channels for 5.1 and 8 channels for 7.1) keep their channels
+//! and are remixed to the output layout: missing speakers are folded into nearest ones with
+//! -3 dB gain, surplus speakers of output layout stay silent. Stereo output is the fallback for
+//! every layout, it uses ITU-R BS.775 downmix coefficients, low frequency channel is dropped.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use rg3d_sound::{engine::SoundEngine, layout::ChannelLayout};
+//!
+//! let engine = SoundEngine::without_device();
+//! engine
+//!     .lock()
+//!     .unwrap()
+//!     .set_output_layout(ChannelLayout::Surround51);
+//!
+//! // Interleaved FL FR FC LFE SL SR samples that should be sent to a device.
+//! let mut samples = vec![0.0; SoundEngine::render_buffer_len() * 6];
+//! engine.lock().unwrap().render_multichannel(&mut samples);
+//! ```
+
+use rg3d_core::{
+    algebra::Vector3,
+    visitor::{Visit, VisitResult, Visitor},
+};
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+/// Maximum amount of channels of any supported layout.
+pub const MAX_CHANNELS: usize = 8;
+
+/// A speaker (or a channel) of a layout.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Speaker {
+    /// Front left speaker.
+    FrontLeft,
+    /// Front right speaker.
+    FrontRight,
+    /// Front center speaker.
+    FrontCenter,
+    /// Low frequency effects channel (subwoofer).
+    LowFrequency,
+    /// Back left speaker.
+    BackLeft,
+    /// Back right speaker.
+    BackRight,
+    /// Side left speaker.
+    SideLeft,
+    /// Side right speaker.
+    SideRight,
+}
+
+impl Speaker {
+    // Defines how a speaker is folded into a layout that does not have it.
+    fn fold(self, layout: ChannelLayout) -> &'static [(Speaker, f32)] {
+        match (self, layout) {
+            (Speaker::FrontCenter, _) => &[
+                (Speaker::FrontLeft, FRAC_1_SQRT_2),
+                (Speaker::FrontRight, FRAC_1_SQRT_2),
+            ],
+            (Speaker::BackLeft, ChannelLayout::Surround51) => &[(Speaker::SideLeft, FRAC_1_SQRT_2)],
+            (Speaker::BackRight, ChannelLayout::Surround51) => {
+                &[(Speaker::SideRight, FRAC_1_SQRT_2)]
+            }
+            (Speaker::BackLeft, _) | (Speaker::SideLeft, _) => {
+                &[(Speaker::FrontLeft, FRAC_1_SQRT_2)]
+            }
+            (Speaker::BackRight, _) | (Speaker::SideRight, _) => {
+                &[(Speaker::FrontRight, FRAC_1_SQRT_2)]
+            }
+            // Front speakers exist in every layout, low frequency channel is dropped.
+            _ => &[],
+        }
+    }
+}
+
+/// Output channel layout. See module docs.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ChannelLayout {
+    /// Two channels: front left and front right.
+    Stereo,
+    /// Six channels: front left, front right, front center, low frequency, side left and side
+    /// right.
+    Surround51,
+    /// Eight channels: front left, front right, front center, low frequency, back left, back
+    /// right, side left and side right.
+    Surround71,
+}
+
+impl Default for ChannelLayout {
+    fn default() -> Self {
+        Self::Stereo
+    }
+}
+
+impl ChannelLayout {
+    /// Returns layout that matches given amount of channels of a sound buffer. There is no
+    /// layout for mono sounds, they are played as stereo.
+    pub fn from_channel_count(channel_count: usize) -> Option<Self> {
+        match channel_count {
+            2 => Some(Self::Stereo),
+            6 => Some(Self::Surround51),
+            8 => Some(Self::Surround71),
+            _ => None,
+        }
+    }
+
+    /// Returns speakers of the layout in order of interleaved channels.
+    pub fn speakers(self) -> &'static [Speaker] {
+        match self {
+            ChannelLayout::Stereo => &[Speaker::FrontLeft, Speaker::FrontRight],
+            ChannelLayout::Surround51 => &[
+                Speaker::FrontLeft,
+                Speaker::FrontRight,
+                Speaker::FrontCenter,
+                Speaker::LowFrequency,
+                Speaker::SideLeft,
+                Speaker::SideRight,
+            ],
+            ChannelLayout::Surround71 => &[
+                Speaker::FrontLeft,
+                Speaker::FrontRight,
+                Speaker::FrontCenter,
+                Speaker::LowFrequency,
+                Speaker::BackLeft,
+                Speaker::BackRight,
+                Speaker::SideLeft,
+                Speaker::SideRight,
+            ],
+        }
+    }
+
+    /// Returns amount of channels of the layout.
+    pub fn channel_count(self) -> usize {
+        self.speakers().len()
+    }
+
+    /// Returns index of channel of a speaker or `None` if there is no such speaker in the
+    /// layout.
+    pub fn channel_of(self, speaker: Speaker) -> Option<usize> {
+        self.speakers().iter().position(|s| *s == speaker)
+    }
+
+    /// Returns angle between look axis of the listener and direction to a speaker of the layout
+    /// in radians, positive angles are on the left side. Returns `None` for low frequency
+    /// channel, it has no position, and for speakers that are not in the layout.
+    pub fn azimuth(self, speaker: Speaker) -> Option<f32> {
+        let degrees = match (speaker, self) {
+            (Speaker::FrontLeft, _) => 30.0,
+            (Speaker::FrontRight, _) => -30.0,
+            (Speaker::FrontCenter, ChannelLayout::Stereo) => return None,
+            (Speaker::FrontCenter, _) => 0.0,
+            (Speaker::LowFrequency, _) => return None,
+            (Speaker::SideLeft, ChannelLayout::Surround51) => 110.0,
+            (Speaker::SideRight, ChannelLayout::Surround51) => -110.0,
+            (Speaker::SideLeft, ChannelLayout::Surround71) => 90.0,
+            (Speaker::SideRight, ChannelLayout::Surround71) => -90.0,
+            (Speaker::BackLeft, ChannelLayout::Surround71) => 150.0,
+            (Speaker::BackRight, ChannelLayout::Surround71) => -150.0,
+            _ => return None,
+        };
+        Some(f32::to_radians(degrees))
+    }
+
+    // Speakers that are used for panning sorted by azimuth.
+    fn panning_ring(self) -> &'static [Speaker] {
+        match self {
+            ChannelLayout::Stereo => &[Speaker::FrontRight, Speaker::FrontLeft],
+            ChannelLayout::Surround51 => &[
+                Speaker::SideRight,
+                Speaker::FrontRight,
+                Speaker::FrontCenter,
+                Speaker::FrontLeft,
+                Speaker::SideLeft,
+            ],
+            ChannelLayout::Surround71 => &[
+                Speaker::BackRight,
+                Speaker::SideRight,
+                Speaker::FrontRight,
+                Speaker::FrontCenter,
+                Speaker::FrontLeft,
+                Speaker::SideLeft,
+                Speaker::BackLeft,
+            ],
+        }
+    }
+
+    /// Calculates gains of every channel for a sound that comes from given direction. The
+    /// direction must be in coordinate system of the listener: `x` points to the left, `y` up
+    /// and `z` forward - [`crate::source::spatial::SpatialSource`] uses the same space for HRTF.
+    /// Zero direction means that a sound is everywhere and it is played evenly by all speakers.
+    ///
+    /// Gains are power-normalized - sum of their squares is always 1.0. Stereo layout can't
+    /// reproduce sounds from behind, so directions are mirrored to the front.
+    pub fn panning_gains(self, direction: Vector3<f32>) -> [f32; MAX_CHANNELS] {
+        let mut gains = [0.0; MAX_CHANNELS];
+
+        let ring = self.panning_ring();
+        let length = direction.norm();
+        // How much of the direction lies in horizontal plane, the rest is spread evenly.
+        let horizontal = if length > f32::EPSILON {
+            (direction.x * direction.x + direction.z * direction.z).sqrt() / length
+        } else {
+            0.0
+        };
+
+        if horizontal > f32::EPSILON {
+            let mut azimuth = direction.x.atan2(direction.z);
+            if self == ChannelLayout::Stereo {
+                // Mirror to the front and clamp to the arc between speakers.
+                if azimuth.abs() > PI / 2.0 {
+                    azimuth = azimuth.signum() * PI - azimuth;
+                }
+                let limit = self.azimuth(Speaker::FrontLeft).unwrap();
+                azimuth = azimuth.max(-limit).min(limit);
+            }
+
+            let (a, b) = self.find_pair(azimuth);
+            let (ga, gb) =
+                Self::pair_gains(self.azimuth(a).unwrap(), self.azimuth(b).unwrap(), azimuth);
+            gains[self.channel_of(a).unwrap()] += ga;
+            gains[self.channel_of(b).unwrap()] += gb;
+        }
+
+        let horizontal_sqr = horizontal * horizontal;
+        let diffuse_sqr = (1.0 - horizontal_sqr) / ring.len() as f32;
+        for speaker in ring {
+            let gain = &mut gains[self.channel_of(*speaker).unwrap()];
+            *gain = (horizontal_sqr * *gain * *gain + diffuse_sqr).sqrt();
+        }
+
+        gains
+    }
+
+    // Finds two adjacent speakers of the ring, that surround given azimuth.
+    fn find_pair(self, azimuth: f32) -> (Speaker, Speaker) {
+        let ring = self.panning_ring();
+        for pair in ring.windows(2) {
+            if azimuth >= self.azimuth(pair[0]).unwrap()
+                && azimuth <= self.azimuth(pair[1]).unwrap()
+            {
+                return (pair[0], pair[1]);
+            }
+        }
+        // Azimuth is in the arc behind the listener, between last and first speakers.
+        (ring[ring.len() - 1], ring[0])
+    }
+
+    // Solves 2D VBAP for a pair of speakers and normalizes gains by power.
+    fn pair_gains(a: f32, b: f32, azimuth: f32) -> (f32, f32) {
+        let (sin_a, cos_a) = a.sin_cos();
+        let (sin_b, cos_b) = b.sin_cos();
+        let (sin_p, cos_p) = azimuth.sin_cos();
+
+        // p = ga * la + gb * lb, where l is (sin, cos) of the speaker.
+        let det = sin_a * cos_b - sin_b * cos_a;
+        let ga = ((sin_p * cos_b - sin_b * cos_p) / det).max(0.0);
+        let gb = ((sin_a * cos_p - sin_p * cos_a) / det).max(0.0);
+
+        let norm = (ga * ga + gb * gb).sqrt();
+        if norm > f32::EPSILON {
+            (ga / norm, gb / norm)
+        } else {
+            (FRAC_1_SQRT_2, FRAC_1_SQRT_2)
+        }
+    }
+
+    /// Remixes one frame of interleaved samples from one layout to another and adds result to
+    /// the output frame. See module docs for more info.
+    pub fn remix(from: ChannelLayout, to: ChannelLayout, input: &[f32], output: &mut [f32]) {
+        for (speaker, sample) in from.speakers().iter().zip(input) {
+            if let Some(channel) = to.channel_of(*speaker) {
+                output[channel] += sample;
+            } else {
+                for (target, gain) in speaker.fold(to) {
+                    output[to.channel_of(*target).unwrap()] += gain * sample;
+                }
+            }
+        }
+    }
+
+    /// Downmixes one frame of interleaved samples of the layout to stereo.
+    pub fn downmix_to_stereo(self, input: &[f32]) -> (f32, f32) {
+        let mut output = [0.0; 2];
+        Self::remix(self, ChannelLayout::Stereo, input, &mut output);
+        (output[0], output[1])
+    }
+
+    fn id(self) -> u32 {
+        match self {
+            ChannelLayout::Stereo => 0,
+            ChannelLayout::Surround51 => 1,
+            ChannelLayout::Surround71 => 2,
+        }
+    }
+
+    fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::Stereo),
+            1 => Ok(Self::Surround51),
+            2 => Ok(Self::Surround71),
+            _ => Err(format!("Invalid channel layout id {}!", id)),
+        }
+    }
+}
+
+impl Visit for ChannelLayout {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut id = self.id();
+        id.visit(name, visitor)?;
+        if visitor.is_reading() {
+            *self = Self::from_id(id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(in crate) mod test {
+    use crate::{
+        buffer::{DataSource, SoundBufferResource},
+        context::{DistanceModel, SoundContext},
+        engine::SoundEngine,
+        layout::{ChannelLayout, Speaker},
+        source::{
+            generic::GenericSourceBuilder, spatial::SpatialSourceBuilder, SoundSource, Status,
+        },
+    };
+    use rg3d_core::algebra::Vector3;
+    use std::{f32::consts::FRAC_1_SQRT_2, time::Duration};
+
+    fn direction(azimuth: f32) -> Vector3<f32> {
+        let (sin, cos) = azimuth.to_radians().sin_cos();
+        Vector3::new(sin, 0.0, cos)
+    }
+
+    fn power(gains: &[f32]) -> f32 {
+        gains.iter().map(|g| g * g).sum()
+    }
+
+    #[test]
+    fn test_panning_gains() {
+        for &layout in &[
+            ChannelLayout::Stereo,
+            ChannelLayout::Surround51,
+            ChannelLayout::Surround71,
+        ] {
+            let lfe = layout.channel_of(Speaker::LowFrequency);
+
+            for i in 0..36 {
+                let gains = layout.panning_gains(direction(i as f32 * 10.0));
+                assert!((power(&gains) - 1.0).abs() < 1.0e-4);
+                if let Some(lfe) = lfe {
+                    assert_eq!(gains[lfe], 0.0);
+                }
+            }
+
+            // Sound that comes from exact position of a speaker is played only by the speaker.
+            for speaker in layout.speakers() {
+                if let Some(azimuth) = layout.azimuth(*speaker) {
+                    let gains = layout.panning_gains(direction(azimuth.to_degrees()));
+                    let channel = layout.channel_of(*speaker).unwrap();
+                    assert!((gains[channel] - 1.0).abs() < 1.0e-4);
+                }
+            }
+
+            // Sound without direction is played evenly.
+            let gains = layout.panning_gains(Vector3::new(0.0, 1.0, 0.0));
+            assert!((power(&gains) - 1.0).abs() < 1.0e-4);
+            let ring = layout
+                .speakers()
+                .iter()
+                .filter(|s| **s != Speaker::LowFrequency)
+                .count();
+            for speaker in layout.speakers() {
+                if *speaker != Speaker::LowFrequency {
+                    let channel = layout.channel_of(*speaker).unwrap();
+                    assert!((gains[channel] - (1.0 / ring as f32).sqrt()).abs() < 1.0e-4);
+                }
+            }
+        }
+
+        // Sound between two speakers is played by both of them.
+        let layout = ChannelLayout::Surround71;
+        let gains = layout.panning_gains(direction(-120.0));
+        let side = layout.channel_of(Speaker::SideRight).unwrap();
+        let back = layout.channel_of(Speaker::BackRight).unwrap();
+        assert!((gains[side] - FRAC_1_SQRT_2).abs() < 1.0e-4);
+        assert!((gains[back] - FRAC_1_SQRT_2).abs() < 1.0e-4);
+
+        // Sound from behind is mirrored to the front in stereo.
+        let behind = ChannelLayout::Stereo.panning_gains(direction(150.0));
+        assert!((behind[0] - 1.0).abs() < 1.0e-4);
+        assert!(behind[1].abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn test_remix() {
+        // FL FR FC LFE SL SR
+        let frame = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+
+        let (left, right) = ChannelLayout::Surround51.downmix_to_stereo(&frame);
+        assert!((left - (1.0 + (3.0 + 5.0) * FRAC_1_SQRT_2)).abs() < 1.0e-4);
+        assert!((right - (2.0 + (3.0 + 6.0) * FRAC_1_SQRT_2)).abs() < 1.0e-4);
+
+        let mut surround71 = [0.0; 8];
+        ChannelLayout::remix(
+            ChannelLayout::Surround51,
+            ChannelLayout::Surround71,
+            &frame,
+            &mut surround71,
+        );
+        assert_eq!(surround71, [1.0, 2.0, 3.0, 4.0, 0.0, 0.0, 5.0, 6.0]);
+
+        let mut surround51 = [0.0; 6];
+        ChannelLayout::remix(
+            ChannelLayout::Surround71,
+            ChannelLayout::Surround51,
+            &surround71,
+            &mut surround51,
+        );
+        assert_eq!(surround51, frame);
+
+        let mut upmixed = [0.0; 6];
+        ChannelLayout::remix(
+            ChannelLayout::Stereo,
+            ChannelLayout::Surround51,
+            &[1.0, 2.0],
+            &mut upmixed,
+        );
+        assert_eq!(upmixed, [1.0, 2.0, 0.0, 0.0, 0.0, 0.0]);
+    }
+
+    /// Creates builder of a looping source, that plays given frame of samples of every channel.
+    pub fn constant_source(channels: &[f32]) -> GenericSourceBuilder {
+        let buffer = SoundBufferResource::new_generic(DataSource::Raw {
+            sample_rate: 44100,
+            channel_count: channels.len(),
+            samples: channels.repeat(4410),
+        })
+        .unwrap();
+        GenericSourceBuilder::new()
+            .with_buffer(buffer)
+            .with_status(Status::Playing)
+            .with_looping(true)
+    }
+
+    /// Renders one block of the context for given layout, returns front and surround samples.
+    pub fn render_block(
+        context: &SoundContext,
+        layout: ChannelLayout,
+    ) -> (Vec<(f32, f32)>, Vec<f32>) {
+        let mut front = vec![(0.0, 0.0); SoundContext::SAMPLES_PER_CHANNEL];
+        let mut surround =
+            vec![0.0; SoundContext::SAMPLES_PER_CHANNEL * (layout.channel_count() - 2)];
+        context
+            .state()
+            .render(1.0, layout, &mut front, &mut surround);
+        (front, surround)
+    }
+
+    // Renders the source offline and returns last frame of output.
+    fn render(source: SoundSource, layout: ChannelLayout, stereo: bool) -> Vec<f32> {
+        let context = SoundContext::new();
+        context.state().set_distance_model(DistanceModel::None);
+        context.state().add_source(source);
+
+        let engine = SoundEngine::without_device();
+        let mut engine = engine.lock().unwrap();
+        engine.add_context(context);
+        engine.set_output_layout(layout);
+
+        let duration = Duration::from_millis(200);
+        if stereo {
+            let (left, right) = *engine.render_offline(duration).last().unwrap();
+            vec![left, right]
+        } else {
+            let samples = engine.render_offline_multichannel(duration);
+            samples[(samples.len() - layout.channel_count())..].to_vec()
+        }
+    }
+
+    fn render_spatial(position: Vector3<f32>, layout: ChannelLayout, stereo: bool) -> Vec<f32> {
+        let source = SpatialSourceBuilder::new(constant_source(&[0.5]).build().unwrap())
+            .with_position(position)
+            .build_source();
+        render(source, layout, stereo)
+    }
+
+    fn assert_frame(frame: &[f32], expected: &[f32]) {
+        assert_eq!(frame.len(), expected.len());
+        for (sample, expected) in frame.iter().zip(expected) {
+            assert!((sample - expected).abs() < 1.0e-4, "{:?}", frame);
+        }
+    }
+
+    #[test]
+    fn test_offline_panning() {
+        // Sound in front is played by center speaker and it has the same loudness in stereo
+        // downmix as in stereo output.
+        let front = Vector3::new(0.0, 0.0, 10.0);
+        let center = 0.5 * 2.0f32.sqrt();
+        assert_frame(
+            &render_spatial(front, ChannelLayout::Surround51, false),
+            &[0.0, 0.0, center, 0.0, 0.0, 0.0],
+        );
+        assert_frame(
+            &render_spatial(front, ChannelLayout::Surround51, true),
+            &[0.5, 0.5],
+        );
+        assert_frame(
+            &render_spatial(front, ChannelLayout::Stereo, false),
+            &[0.5, 0.5],
+        );
+
+        // Listener's ear axis points to the left, so this is exactly at the side left speaker
+        // of 7.1 layout.
+        assert_frame(
+            &render_spatial(
+                Vector3::new(10.0, 0.0, 0.0),
+                ChannelLayout::Surround71,
+                false,
+            ),
+            &[0.0, 0.0, 0.0, 0.0, 0.0, 0.0, center, 0.0],
+        );
+
+        // Right side is between front right and side right speakers of 5.1 layout.
+        let frame = render_spatial(
+            Vector3::new(-10.0, 0.0, 0.0),
+            ChannelLayout::Surround51,
+            false,
+        );
+        let layout = ChannelLayout::Surround51;
+        let gains = layout.panning_gains(Vector3::new(-1.0, 0.0, 0.0));
+        let front_right = layout.channel_of(Speaker::FrontRight).unwrap();
+        let side_right = layout.channel_of(Speaker::SideRight).unwrap();
+        assert!(gains[front_right] > 0.0 && gains[side_right] > 0.0);
+        let expected = gains
+            .iter()
+            .take(layout.channel_count())
+            .map(|g| g * center)
+            .collect::<Vec<_>>();
+        assert_frame(&frame, &expected);
+    }
+
+    #[test]
+    fn test_offline_multichannel_buffer() {
+        // FL FR FC LFE SL SR
+        let channels = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6];
+        let source = || SoundSource::Generic(constant_source(&channels).build().unwrap());
+
+        assert_frame(
+            &render(source(), ChannelLayout::Surround51, false),
+            &channels,
+        );
+        assert_frame(
+            &render(source(), ChannelLayout::Surround71, false),
+            &[0.1, 0.2, 0.3, 0.4, 0.0, 0.0, 0.5, 0.6],
+        );
+
+        let (left, right) = ChannelLayout::Surround51.downmix_to_stereo(&channels);
+        assert_frame(
+            &render(source(), ChannelLayout::Stereo, false),
+            &[left, right],
+        );
+        assert_frame(
+            &render(source(), ChannelLayout::Surround51, true),
+            &[left, right],
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_render_multichannel_partial_frame() {
+        let engine = SoundEngine::without_device();
+        let mut engine = engine.lock().unwrap();
+        engine.set_output_layout(ChannelLayout::Surround51);
+        let mut buf = vec![0.0; SoundEngine::render_buffer_len() * 6 + 1];
+        engine.render_multichannel(&mut buf);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_render_multichannel_short_buffer() {
+        let engine = SoundEngine::without_device();
+        let mut engine = engine.lock().unwrap();
+        engine.set_output_layout(ChannelLayout::Surround51);
+        let mut buf = vec![0.0; (SoundEngine::render_buffer_len() - 1) * 6];
+        engine.render_multichannel(&mut buf);
+    }
+}
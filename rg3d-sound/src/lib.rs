@@ -3,10 +3,16 @@
 //! ## Features
 //!
 //! - Generic and spatial sounds.
-//! - WAV and OGG/Vorbis formats support.
-//! - Streaming.
+//! - WAV, OGG/Vorbis, FLAC and MP3 formats support.
+//! - Streaming, including sounds that are generated on demand by user code.
 //! - Head-related transfer function support ([HRTF](https://en.wikipedia.org/wiki/Head-related_transfer_function)).
-//! - Reverb effect.
+//! - Reverb, equalizer, compressor/limiter, delay and chorus/flanger effects.
+//! - Mixer buses with gain, mute/solo, effect chains and ducking.
+//! - Doppler effect and directional sound cones.
+//! - Occlusion through user-defined ray queries.
+//! - Sound cues with randomized variations.
+//! - Stereo, 5.1 and 7.1 output with VBAP panning of spatial sounds.
+//! - Offline rendering and WAV encoding.
 //!
 //! ## Examples
 //!
@@ -67,13 +73,18 @@
 extern crate winapi;
 
 pub mod buffer;
+pub mod bus;
 pub mod context;
+pub mod cue;
 
 pub mod dsp;
 pub mod effects;
+pub mod encoder;
 pub mod engine;
 pub mod error;
+pub mod layout;
 pub mod listener;
+pub mod occlusion;
 pub mod renderer;
 pub mod source;
 
@@ -82,7 +93,9 @@ pub use hrtf;
 pub use rg3d_core::algebra;
 pub use rg3d_core::futures;
 pub use rg3d_core::math;
+pub use rg3d_core::numeric_range;
 pub use rg3d_core::pool;
 
 mod decoder;
 mod device;
+mod velocity;
@@ -5,6 +5,7 @@
 //! Engine has only one listener which can be positioned and oriented in space. Listener defined as coordinate
 //! system which is used to compute spatial properties of sound sources.
 
+use crate::velocity::VelocityEstimator;
 use rg3d_core::algebra::{Matrix3, Vector3};
 use rg3d_core::math::Matrix3Ext;
 use rg3d_core::visitor::{Visit, VisitResult, Visitor};
@@ -14,6 +15,10 @@ use rg3d_core::visitor::{Visit, VisitResult, Visitor};
 pub struct Listener {
     basis: Matrix3<f32>,
     position: Vector3<f32>,
+    velocity: Option<Vector3<f32>>,
+    // Velocity that is calculated from position deltas, it is used when velocity is not set
+    // explicitly.
+    velocity_estimator: VelocityEstimator,
 }
 
 impl Default for Listener {
@@ -27,6 +32,8 @@ impl Listener {
         Self {
             basis: Matrix3::identity(),
             position: Vector3::new(0.0, 0.0, 0.0),
+            velocity: None,
+            velocity_estimator: Default::default(),
         }
     }
 
@@ -83,6 +90,27 @@ impl Listener {
         self.position
     }
 
+    /// Sets velocity of listener in world space, it is used to calculate Doppler shift of spatial
+    /// sources. `None` means that velocity will be calculated automatically from changes of the
+    /// position. Such velocity is smoothed over time and changes of the position that are faster
+    /// than sound are considered as teleportation and ignored, set velocity explicitly if precise
+    /// Doppler shift is needed.
+    pub fn set_velocity(&mut self, velocity: Option<Vector3<f32>>) {
+        self.velocity = velocity;
+    }
+
+    /// Returns velocity of listener, it is either velocity set by `set_velocity` or velocity
+    /// calculated from changes of the position.
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.velocity
+            .unwrap_or_else(|| self.velocity_estimator.velocity())
+    }
+
+    pub(in crate) fn update_velocity(&mut self, dt: f32, speed_of_sound: f32) {
+        self.velocity_estimator
+            .update(self.position, dt, speed_of_sound);
+    }
+
     /// Returns up axis from basis.
     pub fn up_axis(&self) -> Vector3<f32> {
         self.basis.up()
@@ -105,6 +133,7 @@ impl Visit for Listener {
 
         self.basis.visit("Basis", visitor)?;
         self.position.visit("Position", visitor)?;
+        let _ = self.velocity.visit("Velocity", visitor);
 
         visitor.leave_region()
     }
@@ -0,0 +1,158 @@
+//! Occlusion module.
+//!
+//! # Overview
+//!
+//! The library knows nothing about geometry of a scene, so it can't tell whether there is a wall
+//! between a sound source and listener. Instead, a user can provide this information through
+//! [`OcclusionProvider`]. The provider is asked about every playing spatial source on each render
+//! and returns occlusion factor in `[0; 1]` range, where 0.0 means that path from the source to
+//! listener is clear and 1.0 means that the source is fully occluded. Occluded sources are quieter
+//! and muffled (low-pass filtered), see [`State::set_occlusion_gain`] and
+//! [`State::set_occlusion_cutoff`]. Occlusion factor is smoothed over time, so abrupt changes of
+//! the factor won't produce audible clicks.
+//!
+//! Typical provider casts one or more rays from listener to a source and counts obstacles between
+//! them. Keep in mind that the provider is called from sound thread while context is locked. The
+//! sound thread never waits for the provider - if it is locked by another thread at the moment
+//! (for example, while it is being updated with new geometry), or its mutex is poisoned, previous
+//! occlusion factors are used.
+//!
+//! # Usage
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//! use rg3d_sound::context::SoundContext;
+//! use rg3d_sound::listener::Listener;
+//! use rg3d_sound::occlusion::OcclusionProvider;
+//! use rg3d_sound::pool::Handle;
+//! use rg3d_sound::source::{spatial::SpatialSource, SoundSource};
+//!
+//! // Occludes everything that is below the floor.
+//! #[derive(Debug)]
+//! struct Floor {
+//!     height: f32,
+//! }
+//!
+//! impl OcclusionProvider for Floor {
+//!     fn occlusion(
+//!         &mut self,
+//!         _handle: Handle<SoundSource>,
+//!         source: &SpatialSource,
+//!         listener: &Listener,
+//!     ) -> f32 {
+//!         if (source.position().y < self.height) != (listener.position().y < self.height) {
+//!             1.0
+//!         } else {
+//!             0.0
+//!         }
+//!     }
+//! }
+//!
+//! fn use_occlusion(context: &mut SoundContext) {
+//!     context
+//!         .state()
+//!         .set_occlusion_provider(Some(Arc::new(Mutex::new(Floor { height: 0.0 }))));
+//! }
+//! ```
+//!
+//! [`State::set_occlusion_gain`]: crate::context::State::set_occlusion_gain
+//! [`State::set_occlusion_cutoff`]: crate::context::State::set_occlusion_cutoff
+
+use crate::{
+    listener::Listener,
+    source::{spatial::SpatialSource, SoundSource},
+};
+use rg3d_core::pool::Handle;
+use std::fmt::Debug;
+
+/// See module docs.
+pub trait OcclusionProvider: Debug + Send {
+    /// Returns occlusion factor of a source with given handle, it should be in `[0; 1]` range.
+    /// Values outside of the range are clamped.
+    fn occlusion(
+        &mut self,
+        handle: Handle<SoundSource>,
+        source: &SpatialSource,
+        listener: &Listener,
+    ) -> f32;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        context::SoundContext,
+        layout::{
+            test::{constant_source, render_block},
+            ChannelLayout,
+        },
+        listener::Listener,
+        occlusion::OcclusionProvider,
+        pool::Handle,
+        source::{
+            spatial::{SpatialSource, SpatialSourceBuilder},
+            SoundSource,
+        },
+    };
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    #[derive(Debug)]
+    struct Wall;
+
+    impl OcclusionProvider for Wall {
+        fn occlusion(
+            &mut self,
+            _handle: Handle<SoundSource>,
+            _source: &SpatialSource,
+            _listener: &Listener,
+        ) -> f32 {
+            1.0
+        }
+    }
+
+    fn render(context: &SoundContext) {
+        render_block(context, ChannelLayout::Stereo);
+    }
+
+    #[test]
+    fn test_busy_provider() {
+        let context = SoundContext::new();
+        let source = context.state().add_source(
+            SpatialSourceBuilder::new(constant_source(&[0.5]).build().unwrap()).build_source(),
+        );
+        let provider = Arc::new(Mutex::new(Wall));
+        context
+            .state()
+            .set_occlusion_provider(Some(provider.clone()));
+        context
+            .state()
+            .set_occlusion_smoothing_time(Duration::from_secs(0));
+
+        let occlusion = |context: &SoundContext| match context.state().source(source) {
+            SoundSource::Spatial(spatial) => spatial.occlusion(),
+            SoundSource::Generic(_) => unreachable!(),
+        };
+
+        render(&context);
+        assert_eq!(occlusion(&context), 1.0);
+
+        // Provider is locked by another thread, previous occlusion must be kept.
+        let guard = provider.lock().unwrap();
+        render(&context);
+        assert_eq!(occlusion(&context), 1.0);
+        drop(guard);
+
+        // Poisoned provider must not panic sound thread.
+        let poisoner = provider.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poison the provider");
+        })
+        .join();
+        assert!(provider.is_poisoned());
+        render(&context);
+        assert_eq!(occlusion(&context), 1.0);
+    }
+}
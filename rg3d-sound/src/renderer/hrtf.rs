@@ -119,7 +119,10 @@ impl HrtfRenderer {
                 render_source_default(source, listener, distance_model, out_buf)
             }
             SoundSource::Spatial(spatial) => {
-                let new_distance_gain = spatial.get_distance_gain(listener, distance_model);
+                spatial.apply_cone_filter(listener);
+                // Cone gain is merged with distance gain, so it will be interpolated the same way.
+                let new_distance_gain = spatial.get_distance_gain(listener, distance_model)
+                    * spatial.get_cone_gain(listener);
                 let new_sampling_vector = spatial.get_sampling_vector(listener);
 
                 self.processor
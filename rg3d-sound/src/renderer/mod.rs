@@ -7,12 +7,14 @@
 
 use crate::{
     context::DistanceModel,
+    layout::{ChannelLayout, MAX_CHANNELS},
     listener::Listener,
     math,
     renderer::hrtf::HrtfRenderer,
     source::{generic::GenericSource, SoundSource},
 };
 use rg3d_core::visitor::{Visit, VisitResult, Visitor};
+use std::f32::consts::SQRT_2;
 
 pub mod hrtf;
 
@@ -26,7 +28,8 @@ pub enum Renderer {
     Default,
 
     /// Can be used *only* with mono sounds, stereo sounds will be rendered through
-    /// default renderer.
+    /// default renderer. HRTF is meant for headphones, so with multichannel output layout
+    /// it is played by front speakers only.
     HrtfRenderer(HrtfRenderer),
 }
 
@@ -94,9 +97,11 @@ pub(in crate) fn render_source_default(
             generic.last_right_gain = Some(right_gain);
         }
         SoundSource::Spatial(spatial) => {
+            spatial.apply_cone_filter(listener);
             let distance_gain = spatial.get_distance_gain(listener, distance_model);
+            let cone_gain = spatial.get_cone_gain(listener);
             let panning = spatial.get_panning(listener);
-            let gain = distance_gain * spatial.generic().gain();
+            let gain = distance_gain * cone_gain * spatial.generic().gain();
             let left_gain = gain * (1.0 + panning);
             let right_gain = gain * (1.0 - panning);
             render_with_params(spatial.generic_mut(), left_gain, right_gain, mix_buffer);
@@ -106,6 +111,121 @@ pub(in crate) fn render_source_default(
     }
 }
 
+// Adds a sample to a channel of a frame that is split into front (left and right) and surround
+// buffers.
+fn add_to_channel(front: &mut (f32, f32), surround: &mut [f32], channel: usize, sample: f32) {
+    match channel {
+        0 => front.0 += sample,
+        1 => front.1 += sample,
+        _ => surround[channel - 2] += sample,
+    }
+}
+
+fn render_with_channel_gains(
+    source: &mut GenericSource,
+    layout: ChannelLayout,
+    gains: [f32; MAX_CHANNELS],
+    mix_buffer: &mut [(f32, f32)],
+    surround_buffer: &mut [f32],
+) {
+    let step = 1.0 / mix_buffer.len() as f32;
+    let mut t = 0.0;
+
+    let last_gains = *source.last_channel_gains.get_or_insert(gains);
+    let surround_channels = layout.channel_count() - 2;
+
+    for (i, (out, &(raw_left, raw_right))) in mix_buffer
+        .iter_mut()
+        .zip(source.frame_samples())
+        .enumerate()
+    {
+        // Sound is downmixed to mono and then panned across speakers.
+        let sample = (raw_left + raw_right) * 0.5;
+        let surround = &mut surround_buffer[(i * surround_channels)..];
+        for channel in 0..layout.channel_count() {
+            let gain = math::lerpf(last_gains[channel], gains[channel], t);
+            add_to_channel(out, surround, channel, gain * sample);
+        }
+
+        t += step;
+    }
+
+    source.last_channel_gains = Some(gains);
+}
+
+fn render_multichannel(
+    source: &mut GenericSource,
+    from: ChannelLayout,
+    to: ChannelLayout,
+    mix_buffer: &mut [(f32, f32)],
+    surround_buffer: &mut [f32],
+) {
+    let step = 1.0 / mix_buffer.len() as f32;
+    let mut t = 0.0;
+
+    let gain = source.gain();
+    let last_gain = *source.last_left_gain.get_or_insert(gain);
+    let surround_channels = to.channel_count() - 2;
+
+    for (i, (out, input)) in mix_buffer
+        .iter_mut()
+        .zip(source.frame_channels.chunks_exact(from.channel_count()))
+        .enumerate()
+    {
+        let mut frame = [0.0; MAX_CHANNELS];
+        ChannelLayout::remix(from, to, input, &mut frame);
+        let g = math::lerpf(last_gain, gain, t);
+        let surround = &mut surround_buffer[(i * surround_channels)..];
+        for (channel, sample) in frame.iter().take(to.channel_count()).enumerate() {
+            add_to_channel(out, surround, channel, g * sample);
+        }
+
+        t += step;
+    }
+
+    source.last_left_gain = Some(gain);
+    source.last_right_gain = Some(gain);
+}
+
+/// Renders a source into output of multichannel layout. Spatial sources are panned across
+/// speakers of the layout, see [`crate::layout`] module docs. Generic sources with multichannel
+/// buffers are remixed to the layout, other generic sources are played by front speakers.
+pub(in crate) fn render_source_surround(
+    source: &mut SoundSource,
+    listener: &Listener,
+    distance_model: DistanceModel,
+    layout: ChannelLayout,
+    mix_buffer: &mut [(f32, f32)],
+    surround_buffer: &mut [f32],
+) {
+    match source {
+        SoundSource::Generic(generic) => match generic.frame_layout {
+            Some(from) => render_multichannel(generic, from, layout, mix_buffer, surround_buffer),
+            None => render_source_default(source, listener, distance_model, mix_buffer),
+        },
+        SoundSource::Spatial(spatial) => {
+            spatial.apply_cone_filter(listener);
+            let distance_gain = spatial.get_distance_gain(listener, distance_model);
+            let cone_gain = spatial.get_cone_gain(listener);
+            let gain = distance_gain * cone_gain * spatial.generic().gain();
+            let mut gains = layout.panning_gains(spatial.get_listener_space_direction(listener));
+            for channel_gain in gains.iter_mut() {
+                // Panning gains are power-normalized, while stereo panning plays sound in front
+                // of the listener with full gain in both channels. Compensate the difference so
+                // loudness won't change when output layout changes.
+                *channel_gain *= gain * SQRT_2;
+            }
+            render_with_channel_gains(
+                spatial.generic_mut(),
+                layout,
+                gains,
+                mix_buffer,
+                surround_buffer,
+            );
+        }
+    }
+}
+
 impl Visit for Renderer {
     fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
         visitor.enter_region(name)?;
@@ -125,3 +245,58 @@ impl Visit for Renderer {
         visitor.leave_region()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        context::{DistanceModel, SoundContext, SAMPLE_RATE},
+        listener::Listener,
+        renderer::{hrtf::HrtfRenderer, render_source_default},
+        source::{spatial::SpatialSourceBuilder, SoundSource},
+    };
+    use hrtf::HrirSphere;
+    use rg3d_core::algebra::Vector3;
+    use std::f32::consts::PI;
+
+    // Renders constant signal from a directional source at (0, 0, 1) to listener at origin, and
+    // returns sum of magnitudes of output samples of left channel.
+    fn render(direction: Vector3<f32>, hrtf: bool) -> f32 {
+        let mut source = SoundSource::Spatial(
+            SpatialSourceBuilder::new(Default::default())
+                .with_position(Vector3::new(0.0, 0.0, 1.0))
+                .with_direction(direction)
+                .with_cone_inner_angle(PI / 2.0)
+                .with_cone_outer_angle(PI)
+                .with_cone_outer_gain(0.25)
+                .build(),
+        );
+        source.frame_samples = vec![(1.0, 1.0); SoundContext::SAMPLES_PER_CHANNEL];
+
+        let listener = Listener::new();
+        let mut out = vec![(0.0, 0.0); SoundContext::SAMPLES_PER_CHANNEL];
+        if hrtf {
+            let sphere =
+                HrirSphere::from_file("examples/data/IRC_1002_C.bin", SAMPLE_RATE).unwrap();
+            HrtfRenderer::new(sphere).render_source(
+                &mut source,
+                &listener,
+                DistanceModel::None,
+                &mut out,
+            );
+        } else {
+            render_source_default(&mut source, &listener, DistanceModel::None, &mut out);
+        }
+        out.iter().map(|(left, _)| left.abs()).sum()
+    }
+
+    #[test]
+    fn test_cones_are_honored_by_renderers() {
+        for &hrtf in [false, true].iter() {
+            // Source pointing at listener is heard as is, pointing away - with outer gain.
+            let towards = render(Vector3::new(0.0, 0.0, -1.0), hrtf);
+            let away = render(Vector3::new(0.0, 0.0, 1.0), hrtf);
+            assert!(towards > 0.0);
+            assert!((away / towards - 0.25).abs() < 1.0e-3);
+        }
+    }
+}
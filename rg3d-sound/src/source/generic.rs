@@ -30,8 +30,10 @@
 
 use crate::buffer::SoundBufferState;
 use crate::{
-    buffer::{streaming::StreamingBuffer, SoundBufferResource},
+    buffer::SoundBufferResource,
+    bus::MASTER_BUS,
     error::SoundError,
+    layout::{ChannelLayout, MAX_CHANNELS},
     source::{SoundSource, Status},
 };
 use rg3d_core::visitor::{Visit, VisitResult, Visitor};
@@ -64,6 +66,8 @@ pub struct GenericSource {
     resampling_multiplier: f64,
     status: Status,
     play_once: bool,
+    // Name of a bus to which the source is routed.
+    bus: String,
     // Here we use Option because when source is just created it has no info about it
     // previous left and right channel gains. We can't set it to 1.0 for example
     // because it would give incorrect results: a sound would just start as loud as it
@@ -73,6 +77,16 @@ pub struct GenericSource {
     pub(in crate) last_left_gain: Option<f32>,
     pub(in crate) last_right_gain: Option<f32>,
     pub(in crate) frame_samples: Vec<(f32, f32)>,
+    // Gains of every output channel from last render, they're used the same way as gains above
+    // when sound is panned across speakers of multichannel layout.
+    pub(in crate) last_channel_gains: Option<[f32; MAX_CHANNELS]>,
+    // Interleaved samples of a multichannel buffer, they're filled only if buffer has 5.1 or 7.1
+    // layout. `frame_samples` contains stereo downmix of them.
+    pub(in crate) frame_channels: Vec<f32>,
+    pub(in crate) frame_layout: Option<ChannelLayout>,
+    // Pitch multiplier caused by Doppler effect, it is calculated by the renderer for spatial
+    // sources.
+    pub(in crate) doppler_pitch: f64,
 }
 
 impl Default for GenericSource {
@@ -89,9 +103,14 @@ impl Default for GenericSource {
             resampling_multiplier: 1.0,
             status: Status::Stopped,
             play_once: false,
+            bus: MASTER_BUS.to_owned(),
             last_left_gain: None,
             last_right_gain: None,
             frame_samples: Default::default(),
+            last_channel_gains: None,
+            frame_channels: Default::default(),
+            frame_layout: None,
+            doppler_pitch: 1.0,
         }
     }
 }
@@ -136,6 +155,17 @@ impl GenericSource {
         self.name.to_owned()
     }
 
+    /// Sets name of a bus to which the source will be routed. If there is no bus with such
+    /// name in the context, the source will be routed to the master bus.
+    pub fn set_bus<N: AsRef<str>>(&mut self, bus: N) {
+        self.bus = bus.as_ref().to_owned();
+    }
+
+    /// Returns name of a bus to which the source is routed.
+    pub fn bus(&self) -> &str {
+        &self.bus
+    }
+
     /// Changes buffer of source. Returns old buffer. Source will continue playing from beginning, old
     /// position will be discarded.
     pub fn set_buffer(
@@ -326,7 +356,7 @@ impl GenericSource {
     }
 
     fn next_sample_pair(&mut self, buffer: &mut SoundBufferState) -> (f32, f32) {
-        let step = self.pitch * self.resampling_multiplier;
+        let step = self.pitch * self.doppler_pitch * self.resampling_multiplier;
 
         self.buf_read_pos += step;
         self.playback_pos += step;
@@ -338,13 +368,16 @@ impl GenericSource {
         if i > buffer.index_of_last_sample() {
             let mut end_reached = true;
             if let SoundBufferState::Streaming(streaming) = buffer {
-                // Means that this is the last available block.
-                if len != channel_count * StreamingBuffer::STREAM_SAMPLE_COUNT {
+                // Only the last available block is incomplete.
+                if len == channel_count * streaming.block_len() {
+                    streaming.read_next_block();
+                    // Data source could end exactly at the end of the block.
+                    end_reached = streaming.is_empty();
+                }
+                if end_reached {
                     let _ = streaming.rewind();
-                } else {
-                    end_reached = false;
+                    streaming.read_next_block();
                 }
-                streaming.read_next_block();
             }
             if end_reached {
                 if !self.looping {
@@ -354,13 +387,34 @@ impl GenericSource {
             }
             self.buf_read_pos = 0.0;
             i = 0;
+
+            // Data source can't be rewound.
+            if buffer.is_empty() {
+                self.status = Status::Stopped;
+                // Keep channels of every frame in sync with frame samples.
+                if let Some(layout) = self.frame_layout {
+                    self.frame_channels
+                        .extend((0..layout.channel_count()).map(|_| 0.0));
+                }
+                return (0.0, 0.0);
+            }
         }
 
         let samples = buffer.samples();
-        if channel_count == 2 {
-            (samples[i], samples[i + 1])
-        } else {
-            (samples[i], samples[i])
+        match channel_count {
+            1 => (samples[i], samples[i]),
+            2 => (samples[i], samples[i + 1]),
+            _ => {
+                let frame = &samples[i..(i + channel_count)];
+                match self.frame_layout {
+                    Some(layout) => {
+                        self.frame_channels.extend_from_slice(frame);
+                        layout.downmix_to_stereo(frame)
+                    }
+                    // Unknown layout, assume that first two channels are front left and right.
+                    None => (frame[0], frame[1]),
+                }
+            }
         }
     }
 
@@ -370,6 +424,8 @@ impl GenericSource {
         }
 
         self.frame_samples.clear();
+        self.frame_channels.clear();
+        self.frame_layout = None;
 
         if let Some(buffer) = self.buffer.clone() {
             let mut state = buffer.state();
@@ -380,12 +436,20 @@ impl GenericSource {
                             self.frame_samples.push((0.0, 0.0));
                         }
                     } else {
+                        if buffer.channel_count() > 2 {
+                            self.frame_layout =
+                                ChannelLayout::from_channel_count(buffer.channel_count());
+                        }
                         for _ in 0..amount {
                             if self.status == Status::Playing {
                                 let pair = self.next_sample_pair(buffer);
                                 self.frame_samples.push(pair);
                             } else {
                                 self.frame_samples.push((0.0, 0.0));
+                                if let Some(layout) = self.frame_layout {
+                                    self.frame_channels
+                                        .extend((0..layout.channel_count()).map(|_| 0.0));
+                                }
                             }
                         }
                     }
@@ -435,6 +499,7 @@ impl Visit for GenericSource {
             .visit("ResamplingMultiplier", visitor)?;
         self.status.visit("Status", visitor)?;
         self.play_once.visit("PlayOnce", visitor)?;
+        let _ = self.bus.visit("Bus", visitor);
 
         visitor.leave_region()
     }
@@ -481,6 +546,7 @@ pub struct GenericSourceBuilder {
     looping: bool,
     status: Status,
     play_once: bool,
+    bus: String,
 }
 
 impl Default for GenericSourceBuilder {
@@ -501,6 +567,7 @@ impl GenericSourceBuilder {
             looping: false,
             status: Status::Stopped,
             play_once: false,
+            bus: MASTER_BUS.to_owned(),
         }
     }
 
@@ -546,6 +613,12 @@ impl GenericSourceBuilder {
         self
     }
 
+    /// See `set_bus` of GenericSource
+    pub fn with_bus<N: AsRef<str>>(mut self, bus: N) -> Self {
+        self.bus = bus.as_ref().to_owned();
+        self
+    }
+
     /// Sets desired name of the source.
     pub fn with_name<N: AsRef<str>>(mut self, name: N) -> Self {
         self.name = name.as_ref().to_owned();
@@ -563,7 +636,9 @@ impl GenericSourceBuilder {
             status: self.status,
             looping: self.looping,
             name: self.name,
+            bus: self.bus,
             frame_samples: Default::default(),
+            frame_channels: Default::default(),
             ..Default::default()
         };
 
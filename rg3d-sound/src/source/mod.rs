@@ -29,6 +29,8 @@ pub enum Status {
 }
 
 /// See module docs.
+// Sources are stored in a pool, so boxing of spatial sources would only add an indirection.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum SoundSource {
     /// See `generic` module docs.
@@ -28,13 +28,19 @@
 //! ```
 
 use crate::{
-    context::DistanceModel,
+    context::{DistanceModel, SAMPLE_RATE},
+    dsp::filters::{Biquad, BiquadKind, OnePole},
     listener::Listener,
+    math,
     source::{generic::GenericSource, SoundSource},
+    velocity::VelocityEstimator,
 };
 use rg3d_core::algebra::Vector3;
 use rg3d_core::visitor::{Visit, VisitResult, Visitor};
-use std::ops::{Deref, DerefMut};
+use std::{
+    f32::consts::PI,
+    ops::{Deref, DerefMut},
+};
 
 /// See module docs.
 #[derive(Debug, Clone)]
@@ -44,6 +50,27 @@ pub struct SpatialSource {
     position: Vector3<f32>,
     max_distance: f32,
     rolloff_factor: f32,
+    velocity: Option<Vector3<f32>>,
+    // Velocity that is calculated from position deltas, it is used when velocity is not set
+    // explicitly.
+    velocity_estimator: VelocityEstimator,
+    direction: Vector3<f32>,
+    cone_inner_angle: f32,
+    cone_outer_angle: f32,
+    cone_outer_gain: f32,
+    cone_outer_cutoff: Option<f32>,
+    // Low-pass filters for left and right channels, they are used only if outer cutoff
+    // frequency is set.
+    cone_left_filter: Biquad,
+    cone_right_filter: Biquad,
+    prev_cone_factor: Option<f32>,
+    // Smoothed occlusion factor at the beginning and at the end of current frame.
+    prev_occlusion: f32,
+    occlusion: f32,
+    // Last occlusion factor returned by occlusion provider.
+    occlusion_target: f32,
+    occlusion_left_filter: OnePole,
+    occlusion_right_filter: OnePole,
     // Some data that needed for iterative overlap-save convolution.
     pub(in crate) prev_left_samples: Vec<f32>,
     pub(in crate) prev_right_samples: Vec<f32>,
@@ -52,6 +79,12 @@ pub struct SpatialSource {
 }
 
 impl SpatialSource {
+    /// Minimal pitch multiplier that could be given by Doppler effect.
+    pub const MIN_DOPPLER_PITCH: f32 = 0.5;
+
+    /// Maximal pitch multiplier that could be given by Doppler effect.
+    pub const MAX_DOPPLER_PITCH: f32 = 2.0;
+
     /// Sets position of source in world space.
     pub fn set_position(&mut self, position: Vector3<f32>) -> &mut Self {
         self.position = position;
@@ -63,6 +96,104 @@ impl SpatialSource {
         self.position
     }
 
+    /// Sets velocity of source in world space, it is used to calculate Doppler shift. `None`
+    /// means that velocity will be calculated automatically from changes of the position. Such
+    /// velocity is smoothed over time and changes of the position that are faster than sound are
+    /// considered as teleportation and ignored, set velocity explicitly if precise Doppler shift
+    /// is needed.
+    pub fn set_velocity(&mut self, velocity: Option<Vector3<f32>>) -> &mut Self {
+        self.velocity = velocity;
+        self
+    }
+
+    /// Returns velocity of source, it is either velocity set by `set_velocity` or velocity
+    /// calculated from changes of the position.
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.velocity
+            .unwrap_or_else(|| self.velocity_estimator.velocity())
+    }
+
+    /// Sets direction in which the source emits sound, it is used only by sound cone (see
+    /// `set_cone_inner_angle`). Direction is given in world space and will be normalized,
+    /// zero vector is ignored.
+    pub fn set_direction(&mut self, direction: Vector3<f32>) -> &mut Self {
+        if let Some(direction) = direction.try_normalize(f32::EPSILON) {
+            self.direction = direction;
+        }
+        self
+    }
+
+    /// Returns direction in which the source emits sound.
+    pub fn direction(&self) -> Vector3<f32> {
+        self.direction
+    }
+
+    /// Sets angle (in radians) of inner cone of the source. Listener inside inner cone hears
+    /// the source as is, between inner and outer cones gain (and cutoff frequency of low-pass
+    /// filter if any) is interpolated, and outside of outer cone `cone_outer_gain` is applied.
+    /// Angles are full angles of cones, not half angles, and they're clamped to `[0; 2π]` range.
+    /// Default value for both angles is 2π which means that the source emits sound uniformly
+    /// in all directions.
+    pub fn set_cone_inner_angle(&mut self, angle: f32) -> &mut Self {
+        self.cone_inner_angle = angle.clamp(0.0, 2.0 * PI);
+        self
+    }
+
+    /// Returns angle of inner cone in radians.
+    pub fn cone_inner_angle(&self) -> f32 {
+        self.cone_inner_angle
+    }
+
+    /// Sets angle (in radians) of outer cone of the source. See `set_cone_inner_angle` for more
+    /// info.
+    pub fn set_cone_outer_angle(&mut self, angle: f32) -> &mut Self {
+        self.cone_outer_angle = angle.clamp(0.0, 2.0 * PI);
+        self
+    }
+
+    /// Returns angle of outer cone in radians.
+    pub fn cone_outer_angle(&self) -> f32 {
+        self.cone_outer_angle
+    }
+
+    /// Sets gain that is applied when listener is outside of outer cone. Default value is 0.0.
+    pub fn set_cone_outer_gain(&mut self, gain: f32) -> &mut Self {
+        self.cone_outer_gain = gain.max(0.0);
+        self
+    }
+
+    /// Returns gain outside of outer cone.
+    pub fn cone_outer_gain(&self) -> f32 {
+        self.cone_outer_gain
+    }
+
+    /// Sets cutoff frequency (in hertz) of low-pass filter that is applied when listener is
+    /// outside of outer cone, it makes sound behind a loudspeaker muffled. `None` disables
+    /// filtering, this is default value.
+    pub fn set_cone_outer_cutoff(&mut self, cutoff: Option<f32>) -> &mut Self {
+        self.cone_outer_cutoff = cutoff;
+        self.tune_cone_filters();
+        self
+    }
+
+    /// Returns cutoff frequency of low-pass filter outside of outer cone.
+    pub fn cone_outer_cutoff(&self) -> Option<f32> {
+        self.cone_outer_cutoff
+    }
+
+    fn tune_cone_filters(&mut self) {
+        if let Some(cutoff) = self.cone_outer_cutoff {
+            let fc = (cutoff / SAMPLE_RATE as f32).clamp(0.0, 0.5);
+            self.cone_left_filter.tune(
+                BiquadKind::LowPass,
+                fc,
+                1.0,
+                std::f32::consts::FRAC_1_SQRT_2,
+            );
+            self.cone_right_filter = self.cone_left_filter.clone();
+        }
+    }
+
     /// Sets radius of imaginable sphere around source in which no distance attenuation is applied.
     pub fn set_radius(&mut self, radius: f32) -> &mut Self {
         self.radius = radius;
@@ -136,6 +267,133 @@ impl SpatialSource {
         }
     }
 
+    // Sound cones were taken from OpenAL Specification too. Returns 0.0 if listener is inside
+    // inner cone, 1.0 if listener is outside of outer cone, and linearly interpolated value
+    // between cones.
+    pub(in crate) fn get_cone_factor(&self, listener: &Listener) -> f32 {
+        let to_listener = match (listener.position() - self.position).try_normalize(f32::EPSILON) {
+            Some(direction) => direction,
+            None => return 0.0,
+        };
+
+        let angle = 2.0 * self.direction.dot(&to_listener).clamp(-1.0, 1.0).acos();
+        if angle <= self.cone_inner_angle {
+            0.0
+        } else if angle >= self.cone_outer_angle {
+            1.0
+        } else {
+            (angle - self.cone_inner_angle) / (self.cone_outer_angle - self.cone_inner_angle)
+        }
+    }
+
+    pub(in crate) fn get_cone_gain(&self, listener: &Listener) -> f32 {
+        math::lerpf(1.0, self.cone_outer_gain, self.get_cone_factor(listener))
+    }
+
+    // Mixes samples of current frame with their low-passed version according to position of
+    // listener relative to sound cone.
+    pub(in crate) fn apply_cone_filter(&mut self, listener: &Listener) {
+        if self.cone_outer_cutoff.is_none() {
+            return;
+        }
+
+        let cone_factor = self.get_cone_factor(listener);
+        let prev_cone_factor = self.prev_cone_factor.unwrap_or(cone_factor);
+        self.prev_cone_factor = Some(cone_factor);
+
+        let step = 1.0 / self.generic.frame_samples.len().max(1) as f32;
+        let mut t = 0.0;
+        for (left, right) in self.generic.frame_samples.iter_mut() {
+            // Filters are always fed to keep their state continuous.
+            let filtered_left = self.cone_left_filter.feed(*left);
+            let filtered_right = self.cone_right_filter.feed(*right);
+            let k = math::lerpf(prev_cone_factor, cone_factor, t);
+            *left = math::lerpf(*left, filtered_left, k);
+            *right = math::lerpf(*right, filtered_right, k);
+            t += step;
+        }
+    }
+
+    /// Returns current occlusion factor of the source, see `occlusion` module docs for more
+    /// info.
+    pub fn occlusion(&self) -> f32 {
+        self.occlusion
+    }
+
+    // Moves occlusion factor towards given target, `dt` is duration of a frame and
+    // `smoothing_time` is a time constant of smoothing.
+    pub(in crate) fn update_occlusion(&mut self, target: f32, dt: f32, smoothing_time: f32) {
+        let k = if smoothing_time > 0.0 {
+            1.0 - (-dt / smoothing_time).exp()
+        } else {
+            1.0
+        };
+        self.occlusion_target = target.clamp(0.0, 1.0);
+        self.prev_occlusion = self.occlusion;
+        self.occlusion += (self.occlusion_target - self.occlusion) * k;
+    }
+
+    // Returns occlusion factor that was used as a target in last update.
+    pub(in crate) fn occlusion_target(&self) -> f32 {
+        self.occlusion_target
+    }
+
+    // Attenuates and muffles samples of current frame according to occlusion factor. `gain` and
+    // `cutoff` are gain and normalized cutoff frequency of low-pass filter of fully occluded
+    // source.
+    pub(in crate) fn apply_occlusion(&mut self, gain: f32, cutoff: f32) {
+        if self.prev_occlusion == 0.0 && self.occlusion == 0.0 {
+            return;
+        }
+
+        self.occlusion_left_filter.set_fc(cutoff);
+        self.occlusion_right_filter.set_fc(cutoff);
+
+        let step = 1.0 / self.generic.frame_samples.len().max(1) as f32;
+        let mut t = 0.0;
+        for (left, right) in self.generic.frame_samples.iter_mut() {
+            let filtered_left = self.occlusion_left_filter.feed(*left);
+            let filtered_right = self.occlusion_right_filter.feed(*right);
+            let k = math::lerpf(self.prev_occlusion, self.occlusion, t);
+            let g = math::lerpf(1.0, gain, k);
+            *left = g * math::lerpf(*left, filtered_left, k);
+            *right = g * math::lerpf(*right, filtered_right, k);
+            t += step;
+        }
+    }
+
+    pub(in crate) fn update_velocity(&mut self, dt: f32, speed_of_sound: f32) {
+        self.velocity_estimator
+            .update(self.position, dt, speed_of_sound);
+    }
+
+    // Doppler shift formula was taken from OpenAL Specification as well.
+    pub(in crate) fn get_doppler_pitch(
+        &self,
+        listener: &Listener,
+        speed_of_sound: f32,
+        doppler_factor: f32,
+    ) -> f32 {
+        if doppler_factor <= 0.0 {
+            return 1.0;
+        }
+
+        let to_listener = match (listener.position() - self.position).try_normalize(f32::EPSILON) {
+            Some(direction) => direction,
+            None => return 1.0,
+        };
+
+        // Velocities are clamped, so neither source nor listener could move faster than sound.
+        let limit = speed_of_sound / doppler_factor;
+        let listener_speed = listener.velocity().dot(&to_listener).clamp(-limit, limit);
+        let source_speed = self.velocity().dot(&to_listener).clamp(-limit, limit);
+
+        let numerator = speed_of_sound - doppler_factor * listener_speed;
+        let denominator = (speed_of_sound - doppler_factor * source_speed).max(f32::EPSILON);
+
+        (numerator / denominator).clamp(Self::MIN_DOPPLER_PITCH, Self::MAX_DOPPLER_PITCH)
+    }
+
     pub(in crate) fn get_panning(&self, listener: &Listener) -> f32 {
         (self.position - listener.position())
             .try_normalize(f32::EPSILON)
@@ -145,6 +403,17 @@ impl SpatialSource {
             .dot(&listener.ear_axis())
     }
 
+    // Returns direction to the source in coordinate system of the listener, it is not
+    // normalized and it is zero if the source is at the position of the listener.
+    pub(in crate) fn get_listener_space_direction(&self, listener: &Listener) -> Vector3<f32> {
+        let to_self = self.position - listener.position();
+        Vector3::new(
+            to_self.dot(&listener.ear_axis()),
+            to_self.dot(&listener.up_axis()),
+            to_self.dot(&listener.look_axis()),
+        )
+    }
+
     pub(in crate) fn get_sampling_vector(&self, listener: &Listener) -> Vector3<f32> {
         let to_self = self.position - listener.position();
 
@@ -176,6 +445,16 @@ impl Visit for SpatialSource {
 
         self.radius.visit("Radius", visitor)?;
         self.position.visit("Position", visitor)?;
+        let _ = self.velocity.visit("Velocity", visitor);
+        let _ = self.direction.visit("Direction", visitor);
+        let _ = self.cone_inner_angle.visit("ConeInnerAngle", visitor);
+        let _ = self.cone_outer_angle.visit("ConeOuterAngle", visitor);
+        let _ = self.cone_outer_gain.visit("ConeOuterGain", visitor);
+        let _ = self.cone_outer_cutoff.visit("ConeOuterCutoff", visitor);
+
+        if visitor.is_reading() {
+            self.tune_cone_filters();
+        }
 
         visitor.leave_region()
     }
@@ -189,6 +468,21 @@ impl Default for SpatialSource {
             position: Vector3::new(0.0, 0.0, 0.0),
             max_distance: f32::MAX,
             rolloff_factor: 1.0,
+            velocity: None,
+            velocity_estimator: Default::default(),
+            direction: Vector3::new(0.0, 0.0, 1.0),
+            cone_inner_angle: 2.0 * PI,
+            cone_outer_angle: 2.0 * PI,
+            cone_outer_gain: 0.0,
+            cone_outer_cutoff: None,
+            cone_left_filter: Default::default(),
+            cone_right_filter: Default::default(),
+            prev_cone_factor: None,
+            prev_occlusion: 0.0,
+            occlusion: 0.0,
+            occlusion_target: 0.0,
+            occlusion_left_filter: Default::default(),
+            occlusion_right_filter: Default::default(),
             prev_left_samples: Default::default(),
             prev_right_samples: Default::default(),
             prev_sampling_vector: Vector3::new(0.0, 0.0, 1.0),
@@ -204,6 +498,12 @@ pub struct SpatialSourceBuilder {
     position: Vector3<f32>,
     max_distance: f32,
     rolloff_factor: f32,
+    velocity: Option<Vector3<f32>>,
+    direction: Vector3<f32>,
+    cone_inner_angle: f32,
+    cone_outer_angle: f32,
+    cone_outer_gain: f32,
+    cone_outer_cutoff: Option<f32>,
 }
 
 impl SpatialSourceBuilder {
@@ -216,6 +516,12 @@ impl SpatialSourceBuilder {
             position: Vector3::new(0.0, 0.0, 0.0),
             max_distance: f32::MAX,
             rolloff_factor: 1.0,
+            velocity: None,
+            direction: Vector3::new(0.0, 0.0, 1.0),
+            cone_inner_angle: 2.0 * PI,
+            cone_outer_angle: 2.0 * PI,
+            cone_outer_gain: 0.0,
+            cone_outer_cutoff: None,
         }
     }
 
@@ -225,6 +531,42 @@ impl SpatialSourceBuilder {
         self
     }
 
+    /// See `set_velocity` of SpatialSource.
+    pub fn with_velocity(mut self, velocity: Vector3<f32>) -> Self {
+        self.velocity = Some(velocity);
+        self
+    }
+
+    /// See `set_direction` of SpatialSource.
+    pub fn with_direction(mut self, direction: Vector3<f32>) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// See `set_cone_inner_angle` of SpatialSource.
+    pub fn with_cone_inner_angle(mut self, angle: f32) -> Self {
+        self.cone_inner_angle = angle;
+        self
+    }
+
+    /// See `set_cone_outer_angle` of SpatialSource.
+    pub fn with_cone_outer_angle(mut self, angle: f32) -> Self {
+        self.cone_outer_angle = angle;
+        self
+    }
+
+    /// See `set_cone_outer_gain` of SpatialSource.
+    pub fn with_cone_outer_gain(mut self, gain: f32) -> Self {
+        self.cone_outer_gain = gain;
+        self
+    }
+
+    /// See `set_cone_outer_cutoff` of SpatialSource.
+    pub fn with_cone_outer_cutoff(mut self, cutoff: f32) -> Self {
+        self.cone_outer_cutoff = Some(cutoff);
+        self
+    }
+
     /// See `set_radius` of SpatialSource.
     pub fn with_radius(mut self, radius: f32) -> Self {
         self.radius = radius;
@@ -245,16 +587,24 @@ impl SpatialSourceBuilder {
 
     /// Creates new instance of spatial sound source.
     pub fn build(self) -> SpatialSource {
-        SpatialSource {
+        let mut source = SpatialSource {
             generic: self.generic,
             radius: self.radius,
             position: self.position,
             max_distance: self.max_distance,
             rolloff_factor: self.rolloff_factor,
+            velocity: self.velocity,
             prev_left_samples: Default::default(),
             prev_right_samples: Default::default(),
             ..Default::default()
-        }
+        };
+        source
+            .set_direction(self.direction)
+            .set_cone_inner_angle(self.cone_inner_angle)
+            .set_cone_outer_angle(self.cone_outer_angle)
+            .set_cone_outer_gain(self.cone_outer_gain)
+            .set_cone_outer_cutoff(self.cone_outer_cutoff);
+        source
     }
 
     /// Creates new instance of sound source of `Spatial` variant.
@@ -262,3 +612,133 @@ impl SpatialSourceBuilder {
         SoundSource::Spatial(self.build())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        effects::test::{amplitude, sine},
+        listener::Listener,
+        source::spatial::SpatialSourceBuilder,
+    };
+    use rg3d_core::algebra::Vector3;
+    use std::f32::consts::PI;
+
+    fn listener_at(position: Vector3<f32>) -> Listener {
+        let mut listener = Listener::new();
+        listener.set_position(position);
+        listener
+    }
+
+    #[test]
+    fn test_doppler_pitch() {
+        let listener = Listener::new();
+        let mut source = SpatialSourceBuilder::new(Default::default())
+            .with_position(Vector3::new(0.0, 0.0, 10.0))
+            .build();
+
+        assert_eq!(source.get_doppler_pitch(&listener, 343.3, 1.0), 1.0);
+
+        // Approaching source has higher pitch, receding - lower.
+        source.set_velocity(Some(Vector3::new(0.0, 0.0, -34.33)));
+        assert!((source.get_doppler_pitch(&listener, 343.3, 1.0) - 1.0 / 0.9).abs() < 1.0e-4);
+        source.set_velocity(Some(Vector3::new(0.0, 0.0, 34.33)));
+        assert!((source.get_doppler_pitch(&listener, 343.3, 1.0) - 1.0 / 1.1).abs() < 1.0e-4);
+
+        // Zero Doppler factor disables the effect and extreme velocities are clamped.
+        assert_eq!(source.get_doppler_pitch(&listener, 343.3, 0.0), 1.0);
+        source.set_velocity(Some(Vector3::new(0.0, 0.0, -1000.0)));
+        assert_eq!(source.get_doppler_pitch(&listener, 343.3, 1.0), 2.0);
+    }
+
+    #[test]
+    fn test_estimated_velocity() {
+        let mut source = SpatialSourceBuilder::new(Default::default()).build();
+        source.update_velocity(0.1, 343.3);
+        for i in 1..50 {
+            source.set_position(Vector3::new(i as f32, 0.0, 0.0));
+            source.update_velocity(0.1, 343.3);
+        }
+        assert!((source.velocity() - Vector3::new(10.0, 0.0, 0.0)).norm() < 1.0e-3);
+
+        // Explicit velocity overrides estimated one.
+        source.set_velocity(Some(Vector3::new(0.0, 1.0, 0.0)));
+        assert_eq!(source.velocity(), Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_cone_gain() {
+        // 90 degrees inner cone and 180 degrees outer cone, directed along Z axis.
+        let source = SpatialSourceBuilder::new(Default::default())
+            .with_direction(Vector3::new(0.0, 0.0, 2.0))
+            .with_cone_inner_angle(PI / 2.0)
+            .with_cone_outer_angle(PI)
+            .with_cone_outer_gain(0.2)
+            .build();
+
+        let gain_at = |position: Vector3<f32>| source.get_cone_gain(&listener_at(position));
+
+        // In front of the source and on the edge of inner cone.
+        assert_eq!(gain_at(Vector3::new(0.0, 0.0, 10.0)), 1.0);
+        assert!((gain_at(Vector3::new(10.0, 0.0, 10.0)) - 1.0).abs() < 1.0e-4);
+        // Half-way between cones - 67.5 degrees off the axis.
+        let angle = 3.0 * PI / 8.0;
+        assert!((gain_at(Vector3::new(angle.sin(), 0.0, angle.cos())) - 0.6).abs() < 1.0e-4);
+        // On the edge of outer cone, and behind the source.
+        assert!((gain_at(Vector3::new(0.0, 10.0, 0.0)) - 0.2).abs() < 1.0e-4);
+        assert!((gain_at(Vector3::new(0.0, 0.0, -10.0)) - 0.2).abs() < 1.0e-4);
+
+        // Default source is omnidirectional.
+        let source = SpatialSourceBuilder::new(Default::default()).build();
+        assert_eq!(
+            source.get_cone_gain(&listener_at(Vector3::new(0.0, 0.0, -10.0))),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_cone_filter() {
+        let mut source = SpatialSourceBuilder::new(Default::default())
+            .with_cone_inner_angle(PI / 2.0)
+            .with_cone_outer_angle(PI)
+            .with_cone_outer_gain(1.0)
+            .with_cone_outer_cutoff(500.0)
+            .build();
+
+        let mut response = |listener: &Listener, frequency: f32| {
+            source.generic.frame_samples = sine(frequency, 1.0, 44100);
+            source.apply_cone_filter(listener);
+            amplitude(&source.generic.frame_samples[22050..])
+        };
+
+        // Inside inner cone signal is not filtered.
+        let front = listener_at(Vector3::new(0.0, 0.0, 10.0));
+        assert!((response(&front, 5000.0) - 1.0).abs() < 1.0e-3);
+
+        // Outside of outer cone high frequencies are cut.
+        let behind = listener_at(Vector3::new(0.0, 0.0, -10.0));
+        assert!((response(&behind, 100.0) - 1.0).abs() < 0.02);
+        assert!(response(&behind, 5000.0) < 0.02);
+    }
+
+    #[test]
+    fn test_occlusion() {
+        let mut source = SpatialSourceBuilder::new(Default::default()).build();
+
+        // Occlusion is smoothed with given time constant.
+        source.update_occlusion(1.0, 0.1, 0.1);
+        assert!((source.occlusion() - 0.632).abs() < 1.0e-3);
+        source.update_occlusion(1.0, 0.1, 0.0);
+        assert_eq!(source.occlusion(), 1.0);
+
+        let mut response = |frequency: f32| {
+            source.update_occlusion(1.0, 0.1, 0.0);
+            source.generic.frame_samples = sine(frequency, 1.0, 44100);
+            source.apply_occlusion(0.5, 500.0 / 44100.0);
+            amplitude(&source.generic.frame_samples[22050..])
+        };
+
+        // Fully occluded source is quieter and muffled.
+        assert!((response(100.0) - 0.5).abs() < 0.03);
+        assert!(response(5000.0) < 0.06);
+    }
+}
@@ -0,0 +1,73 @@
+//! Estimation of velocities of spatial sources and listener from changes of their positions.
+//!
+//! Positions are sampled once per render block, but blocks are not synchronized with game frames,
+//! so a block may see either N or N + 1 frames of movement. Raw position deltas are therefore
+//! jittery and the estimation is exponentially smoothed. Deltas that imply movement faster than
+//! sound are treated as teleports and ignored, otherwise they would produce a pitch spike.
+
+use rg3d_core::algebra::Vector3;
+
+#[derive(Debug, Clone)]
+pub(in crate) struct VelocityEstimator {
+    velocity: Vector3<f32>,
+    prev_position: Option<Vector3<f32>>,
+}
+
+impl Default for VelocityEstimator {
+    fn default() -> Self {
+        Self {
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            prev_position: None,
+        }
+    }
+}
+
+impl VelocityEstimator {
+    /// Time constant of exponential smoothing in seconds.
+    pub const SMOOTHING_TIME: f32 = 0.25;
+
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.velocity
+    }
+
+    pub fn update(&mut self, position: Vector3<f32>, dt: f32, speed_of_sound: f32) {
+        if let Some(prev_position) = self.prev_position {
+            if dt > 0.0 {
+                let raw_velocity = (position - prev_position).scale(1.0 / dt);
+                if raw_velocity.norm() <= speed_of_sound {
+                    let k = 1.0 - (-dt / Self::SMOOTHING_TIME).exp();
+                    self.velocity += (raw_velocity - self.velocity).scale(k);
+                }
+            }
+        }
+        self.prev_position = Some(position);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::velocity::VelocityEstimator;
+    use rg3d_core::algebra::Vector3;
+
+    #[test]
+    fn test_velocity_estimation() {
+        let dt = 0.1;
+        let mut estimator = VelocityEstimator::default();
+        let mut position = Vector3::new(0.0, 0.0, 0.0);
+        estimator.update(position, dt, 343.3);
+
+        // Movement at 10 units per second with frames of 1/60 s, blocks see 5 or 6 frames.
+        for i in 0..100 {
+            let frames = if i % 2 == 0 { 5.0 } else { 6.0 };
+            position.x += frames * 10.0 / 60.0;
+            estimator.update(position, dt, 343.3);
+        }
+        let velocity = estimator.velocity();
+        assert!((velocity.x - 9.2).abs() < 0.5, "{:?}", velocity);
+
+        // Teleport does not change estimated velocity.
+        position.x += 1000.0;
+        estimator.update(position, dt, 343.3);
+        assert_eq!(estimator.velocity(), velocity);
+    }
+}
@@ -7,13 +7,15 @@ use crate::{
     renderer::TextureUploadSender,
     resource::{
         model::{Model, ModelData},
+        sound_cue::load_sound_cue,
         texture::{
             CompressionOptions, Texture, TextureData, TextureError, TextureMagnificationFilter,
             TextureMinificationFilter, TexturePixelKind, TextureState, TextureWrapMode,
         },
     },
-    sound::buffer::{
-        DataSource, SoundBufferResource, SoundBufferResourceLoadError, SoundBufferState,
+    sound::{
+        buffer::{DataSource, SoundBufferResource, SoundBufferResourceLoadError, SoundBufferState},
+        cue::SoundCueResource,
     },
     utils::log::{Log, MessageKind},
 };
@@ -238,6 +240,7 @@ pub struct ResourceManagerState {
     models: ResourceContainer<Model>,
     sound_buffers: ResourceContainer<SoundBufferResource>,
     shaders: ResourceContainer<Shader>,
+    sound_cues: ResourceContainer<SoundCueResource>,
     textures_import_options: TextureImportOptions,
     #[cfg(not(target_arch = "wasm32"))]
     thread_pool: ThreadPool,
@@ -251,6 +254,7 @@ impl Default for ResourceManagerState {
             models: Default::default(),
             sound_buffers: Default::default(),
             shaders: Default::default(),
+            sound_cues: Default::default(),
             textures_import_options: Default::default(),
             #[cfg(not(target_arch = "wasm32"))]
             thread_pool: ThreadPool::new().unwrap(),
@@ -450,6 +454,33 @@ async fn load_shader(shader: Shader, path: PathBuf) {
     }
 }
 
+async fn load_cue(cue: SoundCueResource, path: PathBuf, resource_manager: ResourceManager) {
+    match load_sound_cue(&path, resource_manager).await {
+        Ok(sound_cue) => {
+            Log::writeln(
+                MessageKind::Information,
+                format!("Sound cue {:?} is loaded!", path),
+            );
+
+            cue.state().commit(ResourceState::Ok(sound_cue));
+        }
+        Err(error) => {
+            Log::writeln(
+                MessageKind::Error,
+                format!(
+                    "Unable to load sound cue from {:?}! Reason {:?}",
+                    path, error
+                ),
+            );
+
+            cue.state().commit(ResourceState::LoadError {
+                path,
+                error: Some(Arc::new(error)),
+            });
+        }
+    }
+}
+
 async fn load_sound_buffer(resource: SoundBufferResource, path: PathBuf, stream: bool) {
     match DataSource::from_file(&path).await {
         Ok(source) => {
@@ -467,15 +498,15 @@ async fn load_sound_buffer(resource: SoundBufferResource, path: PathBuf, stream:
 
                     resource.state().commit(ResourceState::Ok(sound_buffer));
                 }
-                Err(_) => {
+                Err(e) => {
                     Log::writeln(
                         MessageKind::Error,
-                        format!("Unable to load sound buffer from {:?}!", path),
+                        format!("Unable to load sound buffer from {:?}! Reason {:?}", path, e),
                     );
 
                     resource.state().commit(ResourceState::LoadError {
                         path: path.clone(),
-                        error: Some(Arc::new(SoundBufferResourceLoadError::UnsupportedFormat)),
+                        error: Some(Arc::new(SoundBufferResourceLoadError::DataSourceError(e))),
                     })
                 }
             }
@@ -533,15 +564,15 @@ async fn reload_sound_buffer(resource: SoundBufferResource, path: PathBuf, strea
 
                 resource.state().commit(ResourceState::Ok(new_sound_buffer));
             }
-            Err(_) => {
+            Err(e) => {
                 Log::writeln(
                     MessageKind::Error,
-                    format!("Unable to reload {:?} sound buffer!", path),
+                    format!("Unable to reload {:?} sound buffer! Reason {:?}", path, e),
                 );
 
                 resource.state().commit(ResourceState::LoadError {
                     path,
-                    error: Some(Arc::new(SoundBufferResourceLoadError::UnsupportedFormat)),
+                    error: Some(Arc::new(SoundBufferResourceLoadError::DataSourceError(e))),
                 })
             }
         }
@@ -770,6 +801,44 @@ impl ResourceManager {
         result
     }
 
+    /// Tries to load a new sound cue resource from given path or get instance of existing, if any.
+    /// This method is asynchronous, it immediately returns a cue which can be shared across
+    /// multiple places, the loading may fail, but it is internal state of the cue. Sound buffers
+    /// of the cue are requested from the resource manager as well. See
+    /// [`crate::resource::sound_cue`] module docs for the format of cue files.
+    ///
+    /// # Async/.await
+    ///
+    /// Each sound cue implements Future trait and can be used in async contexts.
+    pub fn request_sound_cue<P: AsRef<Path>>(&self, path: P) -> SoundCueResource {
+        let mut state = self.state();
+
+        if let Some(cue) = state.sound_cues.find(path.as_ref()) {
+            return cue.clone();
+        }
+
+        let cue = SoundCueResource(Resource::new(ResourceState::new_pending(
+            path.as_ref().to_owned(),
+        )));
+        state.sound_cues.push(cue.clone());
+
+        let result = cue.clone();
+        let path = path.as_ref().to_owned();
+        let resource_manager = self.clone();
+
+        #[cfg(target_arch = "wasm32")]
+        crate::core::wasm_bindgen_futures::spawn_local(async move {
+            load_cue(cue, path, resource_manager).await;
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        state.thread_pool.spawn_ok(async move {
+            load_cue(cue, path, resource_manager).await;
+        });
+
+        result
+    }
+
     /// Reloads every loaded texture. This method is asynchronous, internally it uses thread pool
     /// to run reload on separate thread per texture.
     pub async fn reload_textures(&self) {
@@ -924,6 +993,41 @@ impl ResourceManager {
         crate::core::futures::future::join_all(buffers).await;
     }
 
+    /// Reloads every loaded sound cue. This method is asynchronous, internally it uses thread pool
+    /// to run reload on separate thread per sound cue.
+    pub async fn reload_sound_cues(&self) {
+        let cues = {
+            let state = self.state();
+
+            let cues = state.sound_cues.iter().cloned().collect::<Vec<_>>();
+
+            for cue in cues.iter().cloned() {
+                let path = cue.state().path().to_path_buf();
+                *cue.state() = ResourceState::new_pending(path.clone());
+                let resource_manager = self.clone();
+
+                #[cfg(target_arch = "wasm32")]
+                crate::core::wasm_bindgen_futures::spawn_local(async move {
+                    load_cue(cue, path, resource_manager).await;
+                });
+
+                #[cfg(not(target_arch = "wasm32"))]
+                state.thread_pool.spawn_ok(async move {
+                    load_cue(cue, path, resource_manager).await;
+                })
+            }
+
+            cues
+        };
+
+        crate::core::futures::future::join_all(cues).await;
+
+        Log::writeln(
+            MessageKind::Information,
+            "All sound cue resources reloaded!".to_owned(),
+        );
+    }
+
     /// Reloads all loaded resources. Normally it should never be called, because it is **very** heavy
     /// method! This method is asynchronous, it uses all available CPU power to reload resources as
     /// fast as possible.
@@ -932,7 +1036,8 @@ impl ResourceManager {
             self.reload_textures(),
             self.reload_models(),
             self.reload_sound_buffers(),
-            self.reload_shaders()
+            self.reload_shaders(),
+            self.reload_sound_cues()
         );
     }
 }
@@ -995,6 +1100,7 @@ impl ResourceManagerState {
             models: Default::default(),
             sound_buffers: Default::default(),
             shaders: Default::default(),
+            sound_cues: Default::default(),
             textures_import_options: Default::default(),
             #[cfg(not(target_arch = "wasm32"))]
             thread_pool: ThreadPool::new().unwrap(),
@@ -1032,12 +1138,19 @@ impl ResourceManagerState {
         &self.shaders
     }
 
+    /// Returns a reference to sound cues container.
+    #[inline]
+    pub fn sound_cues(&self) -> &ResourceContainer<SoundCueResource> {
+        &self.sound_cues
+    }
+
     /// Returns total amount of resources in pending state.
     pub fn count_pending_resources(&self) -> usize {
         self.textures.count_pending_resources()
             + self.sound_buffers.count_pending_resources()
             + self.models.count_pending_resources()
             + self.shaders.count_pending_resources()
+            + self.sound_cues.count_pending_resources()
     }
 
     /// Returns total amount of loaded resources.
@@ -1046,11 +1159,16 @@ impl ResourceManagerState {
             + self.sound_buffers.count_loaded_resources()
             + self.models.count_loaded_resources()
             + self.shaders.count_loaded_resources()
+            + self.sound_cues.count_loaded_resources()
     }
 
     /// Returns total amount of registered resources.
     pub fn count_registered_resources(&self) -> usize {
-        self.textures.len() + self.sound_buffers.len() + self.models.len() + self.shaders.len()
+        self.textures.len()
+            + self.sound_buffers.len()
+            + self.models.len()
+            + self.shaders.len()
+            + self.sound_cues.len()
     }
 
     /// Returns percentage of loading progress. This method is useful to show progress on
@@ -1072,6 +1190,7 @@ impl ResourceManagerState {
         self.models.destroy_unused();
         self.textures.destroy_unused();
         self.shaders.destroy_unused();
+        self.sound_cues.destroy_unused();
     }
 
     pub(in crate) fn update(&mut self, dt: f32) {
@@ -1079,6 +1198,7 @@ impl ResourceManagerState {
         self.models.update(dt);
         self.sound_buffers.update(dt);
         self.shaders.update(dt);
+        self.sound_cues.update(dt);
     }
 }
 
@@ -1090,11 +1210,13 @@ impl Visit for ResourceManagerState {
         self.models.wait();
         self.sound_buffers.wait();
         self.shaders.wait();
+        self.sound_cues.wait();
 
         self.textures.visit("Textures", visitor)?;
         self.models.visit("Models", visitor)?;
         self.sound_buffers.visit("SoundBuffers", visitor)?;
         self.shaders.visit("Shaders", visitor)?;
+        let _ = self.sound_cues.visit("SoundCues", visitor);
 
         visitor.leave_region()
     }
@@ -19,6 +19,7 @@ pub mod scene2d;
 pub mod utils;
 
 pub use crate::core::rand;
+pub use glow;
 #[cfg(not(target_arch = "wasm32"))]
 pub use glutin::*;
 pub use lazy_static;
@@ -433,6 +433,8 @@ define_new_resource!(
     /// | rg3d_cameraPosition       | `Vector3`       | Position of the camera.
     /// | rg3d_usePOM               | `bool`          | Whether to use parallax mapping or not.
     /// | rg3d_lightPosition        | `Vector3`       | Light position.
+    /// | rg3d_viewProjectionMatrix | `Matrix4`       | World-to-clip-space transform.
+    /// | rg3d_useInstancing        | `bool`          | Whether instanced rendering is used or not.
     ///
     /// To use any of the variables, just define a uniform with appropriate name:
     ///
@@ -443,6 +445,33 @@ define_new_resource!(
     ///
     /// This list will be extended in future releases.
     ///
+    /// # Instancing
+    ///
+    /// Non-skinned surfaces that share same data and material are rendered using single instanced
+    /// draw call, if a program of a pass defines `rg3d_useInstancing` uniform. In this case world
+    /// matrix of each instance is passed as per-instance vertex attribute at location 7 and
+    /// `rg3d_worldMatrix` with `rg3d_worldViewProjection` must not be used:
+    ///
+    /// ```glsl
+    /// layout(location = 7) in mat4 instanceWorldMatrix;
+    ///
+    /// uniform mat4 rg3d_worldViewProjection;
+    /// uniform mat4 rg3d_viewProjectionMatrix;
+    /// uniform bool rg3d_useInstancing;
+    ///
+    /// void main()
+    /// {
+    ///     if (rg3d_useInstancing) {
+    ///         gl_Position = rg3d_viewProjectionMatrix * instanceWorldMatrix * vec4(vertexPosition, 1.0);
+    ///     } else {
+    ///         gl_Position = rg3d_worldViewProjection * vec4(vertexPosition, 1.0);
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// Shaders that does not define `rg3d_useInstancing` uniform will be rendered using a draw
+    /// call per instance.
+    ///
     /// # Drawing parameters
     ///
     /// Drawing parameters defines which GPU functions to use and at which state. For example, to render
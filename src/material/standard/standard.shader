@@ -84,13 +84,16 @@
                 layout(location = 4) in vec4 boneWeights;
                 layout(location = 5) in vec4 boneIndices;
                 layout(location = 6) in vec2 vertexSecondTexCoord;
+                layout(location = 7) in mat4 instanceWorldMatrix;
 
                 // Define uniforms with reserved names. rg3d will automatically provide
                 // required data to these uniforms.
                 uniform mat4 rg3d_worldMatrix;
                 uniform mat4 rg3d_worldViewProjection;
+                uniform mat4 rg3d_viewProjectionMatrix;
                 uniform mat4 rg3d_boneMatrices[60];
                 uniform bool rg3d_useSkeletalAnimation;
+                uniform bool rg3d_useInstancing;
 
                 out vec3 position;
                 out vec3 normal;
@@ -141,15 +144,28 @@
                         localTangent = vertexTangent.xyz;
                     }
 
-                    mat3 nm = mat3(rg3d_worldMatrix);
+                    mat4 worldMatrix;
+                    mat4 worldViewProjection;
+                    if (rg3d_useInstancing)
+                    {
+                        worldMatrix = instanceWorldMatrix;
+                        worldViewProjection = rg3d_viewProjectionMatrix * instanceWorldMatrix;
+                    }
+                    else
+                    {
+                        worldMatrix = rg3d_worldMatrix;
+                        worldViewProjection = rg3d_worldViewProjection;
+                    }
+
+                    mat3 nm = mat3(worldMatrix);
                     normal = normalize(nm * localNormal);
                     tangent = normalize(nm * localTangent);
                     binormal = normalize(vertexTangent.w * cross(tangent, normal));
                     texCoord = vertexTexCoord;
-                    position = vec3(rg3d_worldMatrix * localPosition);
+                    position = vec3(worldMatrix * localPosition);
                     secondTexCoord = vertexSecondTexCoord;
 
-                    gl_Position = rg3d_worldViewProjection * localPosition;
+                    gl_Position = worldViewProjection * localPosition;
                 }
                 "#,
             fragment_shader:
@@ -252,9 +268,12 @@
                 layout(location = 1) in vec2 vertexTexCoord;
                 layout(location = 5) in vec4 boneWeights;
                 layout(location = 6) in vec4 boneIndices;
+                layout(location = 7) in mat4 instanceWorldMatrix;
 
                 uniform mat4 rg3d_worldViewProjection;
+                uniform mat4 rg3d_viewProjectionMatrix;
                 uniform bool rg3d_useSkeletalAnimation;
+                uniform bool rg3d_useInstancing;
                 uniform mat4 rg3d_boneMatrices[60];
 
                 out vec3 position;
@@ -281,7 +300,14 @@
                     {
                         localPosition = vec4(vertexPosition, 1.0);
                     }
-                    gl_Position = rg3d_worldViewProjection * localPosition;
+                    if (rg3d_useInstancing)
+                    {
+                        gl_Position = rg3d_viewProjectionMatrix * instanceWorldMatrix * localPosition;
+                    }
+                    else
+                    {
+                        gl_Position = rg3d_worldViewProjection * localPosition;
+                    }
                     texCoord = vertexTexCoord;
                 }
                "#,
@@ -330,9 +356,12 @@
                 layout(location = 1) in vec2 vertexTexCoord;
                 layout(location = 4) in vec4 boneWeights;
                 layout(location = 5) in vec4 boneIndices;
+                layout(location = 7) in mat4 instanceWorldMatrix;
 
                 uniform mat4 rg3d_worldViewProjection;
+                uniform mat4 rg3d_viewProjectionMatrix;
                 uniform bool rg3d_useSkeletalAnimation;
+                uniform bool rg3d_useInstancing;
                 uniform mat4 rg3d_boneMatrices[60];
 
                 out vec2 texCoord;
@@ -355,7 +384,14 @@
                         localPosition = vec4(vertexPosition, 1.0);
                     }
 
-                    gl_Position = rg3d_worldViewProjection * localPosition;
+                    if (rg3d_useInstancing)
+                    {
+                        gl_Position = rg3d_viewProjectionMatrix * instanceWorldMatrix * localPosition;
+                    }
+                    else
+                    {
+                        gl_Position = rg3d_worldViewProjection * localPosition;
+                    }
                     texCoord = vertexTexCoord;
                 }
                 "#,
@@ -401,10 +437,13 @@
                 layout(location = 1) in vec2 vertexTexCoord;
                 layout(location = 4) in vec4 boneWeights;
                 layout(location = 5) in vec4 boneIndices;
+                layout(location = 7) in mat4 instanceWorldMatrix;
 
                 uniform mat4 rg3d_worldMatrix;
                 uniform mat4 rg3d_worldViewProjection;
+                uniform mat4 rg3d_viewProjectionMatrix;
                 uniform bool rg3d_useSkeletalAnimation;
+                uniform bool rg3d_useInstancing;
                 uniform mat4 rg3d_boneMatrices[60];
 
                 out vec2 texCoord;
@@ -428,8 +467,16 @@
                         localPosition = vec4(vertexPosition, 1.0);
                     }
 
-                    gl_Position = rg3d_worldViewProjection * localPosition;
-                    worldPosition = (rg3d_worldMatrix * localPosition).xyz;
+                    if (rg3d_useInstancing)
+                    {
+                        gl_Position = rg3d_viewProjectionMatrix * instanceWorldMatrix * localPosition;
+                        worldPosition = (instanceWorldMatrix * localPosition).xyz;
+                    }
+                    else
+                    {
+                        gl_Position = rg3d_worldViewProjection * localPosition;
+                        worldPosition = (rg3d_worldMatrix * localPosition).xyz;
+                    }
                     texCoord = vertexTexCoord;
                 }
                 "#,
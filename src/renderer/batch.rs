@@ -1,7 +1,12 @@
 use crate::material::PropertyValue;
 use crate::utils::log::{Log, MessageKind};
 use crate::{
-    core::{algebra::Matrix4, arrayvec::ArrayVec, pool::Handle, scope_profile},
+    core::{
+        algebra::{Matrix4, Vector3},
+        arrayvec::ArrayVec,
+        pool::Handle,
+        scope_profile,
+    },
     material::Material,
     scene::{
         graph::Graph,
@@ -36,6 +41,15 @@ pub struct Batch {
     sort_index: u64,
 }
 
+impl Batch {
+    /// Returns true if instances of the batch can be rendered using single instanced draw call.
+    /// Skinned surfaces cannot be instanced, because every instance has its own set of bone
+    /// matrices.
+    pub fn can_be_instanced(&self) -> bool {
+        !self.is_skinned
+    }
+}
+
 impl Debug for Batch {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -56,7 +70,9 @@ pub struct BatchStorage {
 }
 
 impl BatchStorage {
-    pub(in crate) fn generate_batches(&mut self, graph: &Graph) {
+    /// Generates batches for every surface in the graph. Observer position is used to select
+    /// levels of detail of terrain chunks.
+    pub(in crate) fn generate_batches(&mut self, graph: &Graph, observer_position: Vector3<f32>) {
         scope_profile!();
 
         for batch in self.batches.iter_mut() {
@@ -67,6 +83,8 @@ impl BatchStorage {
         self.batches.clear();
         self.batch_map.clear();
 
+        let mut transforms = Vec::new();
+
         for (handle, node) in graph.pair_iter() {
             match node {
                 Node::Mesh(mesh) => {
@@ -88,9 +106,9 @@ impl BatchStorage {
                             self.batch_map.insert(key, self.batches.len());
                             self.batches.push(Batch {
                                 data,
-                                // Batches from meshes will be sorted using diffuse textures.
+                                // Batches from meshes will be sorted using materials.
                                 // This will significantly reduce pipeline state changes.
-                                sort_index: surface.batch_id(),
+                                sort_index: surface.material_id(),
                                 instances: self.buffers.pop().unwrap_or_default(),
                                 material: surface.material().clone(),
                                 is_skinned: !surface.bones.is_empty(),
@@ -100,7 +118,7 @@ impl BatchStorage {
                             self.batches.last_mut().unwrap()
                         };
 
-                        batch.sort_index = surface.batch_id();
+                        batch.sort_index = surface.material_id();
                         batch.material = surface.material().clone();
 
                         batch.instances.push(SurfaceInstance {
@@ -120,9 +138,10 @@ impl BatchStorage {
                     }
                 }
                 Node::Terrain(terrain) => {
+                    let lods = terrain.select_lods(observer_position);
                     for (layer_index, layer) in terrain.layers().iter().enumerate() {
                         for (chunk_index, chunk) in terrain.chunks_ref().iter().enumerate() {
-                            let data = chunk.data();
+                            let data = chunk.lod_data(lods[chunk_index]);
                             let data_key = &*data as *const _ as u64;
 
                             let mut material = (*layer.material.lock().unwrap()).clone();
@@ -181,6 +200,43 @@ impl BatchStorage {
                             }
                         }
                     }
+
+                    for (layer_index, layer) in terrain.foliage_layers().iter().enumerate() {
+                        transforms.clear();
+                        terrain.visible_foliage(layer_index, observer_position, &mut transforms);
+                        if transforms.is_empty() {
+                            continue;
+                        }
+
+                        for surface in layer.surfaces() {
+                            let key = surface.batch_id();
+
+                            let batch = if let Some(&batch_index) = self.batch_map.get(&key) {
+                                self.batches.get_mut(batch_index).unwrap()
+                            } else {
+                                self.batch_map.insert(key, self.batches.len());
+                                self.batches.push(Batch {
+                                    data: surface.data(),
+                                    sort_index: surface.material_id(),
+                                    instances: self.buffers.pop().unwrap_or_default(),
+                                    material: surface.material().clone(),
+                                    is_skinned: false,
+                                    render_path: RenderPath::Deferred,
+                                    decal_layer_index: terrain.decal_layer_index(),
+                                });
+                                self.batches.last_mut().unwrap()
+                            };
+
+                            batch.instances.extend(transforms.iter().map(|transform| {
+                                SurfaceInstance {
+                                    world_transform: *transform,
+                                    bone_matrices: Default::default(),
+                                    owner: handle,
+                                    depth_offset: terrain.depth_offset_factor(),
+                                }
+                            }));
+                        }
+                    }
                 }
                 _ => (),
             }
@@ -189,3 +245,129 @@ impl BatchStorage {
         self.batches.sort_unstable_by_key(|b| b.sort_index);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Matrix4, Vector3},
+            pool::Handle,
+        },
+        renderer::batch::BatchStorage,
+        scene::{
+            base::BaseBuilder,
+            graph::Graph,
+            mesh::{
+                surface::{SurfaceBuilder, SurfaceData},
+                MeshBuilder,
+            },
+            terrain::{
+                foliage::{FoliageLayer, FoliageScatter},
+                TerrainBuilder,
+            },
+        },
+    };
+    use std::sync::{Arc, RwLock};
+
+    #[test]
+    fn test_batching_of_shared_surfaces() {
+        let mut graph = Graph::new();
+
+        let shared_data = Arc::new(RwLock::new(SurfaceData::make_cube(Matrix4::identity())));
+        let shared_surface = SurfaceBuilder::new(shared_data).build();
+
+        for _ in 0..3 {
+            MeshBuilder::new(BaseBuilder::new())
+                .with_surfaces(vec![shared_surface.clone()])
+                .build(&mut graph);
+        }
+
+        // Same material, but different data - must be in separate batch.
+        let unique_surface = SurfaceBuilder::new(Arc::new(RwLock::new(SurfaceData::make_cube(
+            Matrix4::identity(),
+        ))))
+        .with_material(shared_surface.material().clone())
+        .build();
+        MeshBuilder::new(BaseBuilder::new())
+            .with_surfaces(vec![unique_surface])
+            .build(&mut graph);
+
+        // Skinned surfaces cannot be instanced.
+        let mut skinned_surface = SurfaceBuilder::new(Arc::new(RwLock::new(
+            SurfaceData::make_cube(Matrix4::identity()),
+        )))
+        .build();
+        skinned_surface.bones.push(graph.get_root());
+        MeshBuilder::new(BaseBuilder::new())
+            .with_surfaces(vec![skinned_surface])
+            .build(&mut graph);
+
+        let mut storage = BatchStorage::default();
+        storage.generate_batches(&graph, Default::default());
+
+        assert_eq!(storage.batches.len(), 3);
+
+        let mut instance_counts = storage
+            .batches
+            .iter()
+            .map(|b| b.instances.len())
+            .collect::<Vec<_>>();
+        instance_counts.sort_unstable();
+        assert_eq!(instance_counts, vec![1, 1, 3]);
+
+        let shared_batch = storage
+            .batches
+            .iter()
+            .find(|b| b.instances.len() == 3)
+            .unwrap();
+        assert!(shared_batch.can_be_instanced());
+        assert!(shared_batch
+            .instances
+            .iter()
+            .all(|i| i.owner != Handle::NONE));
+
+        assert_eq!(
+            storage
+                .batches
+                .iter()
+                .filter(|b| !b.can_be_instanced())
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_batching_of_foliage() {
+        let mut graph = Graph::new();
+
+        let terrain = TerrainBuilder::new(BaseBuilder::new()).build(&mut graph);
+        let grass = Arc::new(RwLock::new(SurfaceData::make_quad(&Matrix4::identity())));
+        let terrain_ref = graph[terrain].as_terrain_mut();
+        let layer = terrain_ref
+            .add_foliage_layer(FoliageLayer::new(vec![
+                SurfaceBuilder::new(grass.clone()).build()
+            ]));
+        terrain_ref.scatter_foliage(layer, &FoliageScatter::default());
+        terrain_ref.foliage_layers_mut()[layer].set_draw_distance(20.0);
+        terrain_ref.update();
+        let instance_count = terrain_ref.foliage_layers()[layer].instance_count();
+
+        let mut storage = BatchStorage::default();
+        storage.generate_batches(&graph, Vector3::new(32.0, 0.0, 32.0));
+        let batch = storage
+            .batches
+            .iter()
+            .find(|b| Arc::ptr_eq(&b.data, &grass))
+            .unwrap();
+        assert!(batch.can_be_instanced());
+        assert!(!batch.instances.is_empty() && batch.instances.len() < instance_count);
+        assert!(batch.instances.iter().all(|i| i.owner == terrain));
+
+        // Foliage is culled by distance.
+        storage.generate_batches(&graph, Vector3::new(1000.0, 0.0, 32.0));
+        assert!(storage
+            .batches
+            .iter()
+            .all(|b| !Arc::ptr_eq(&b.data, &grass)));
+    }
+}
@@ -1,12 +1,12 @@
 use crate::{
-    core::scope_profile,
+    core::{algebra::Matrix4, scope_profile},
     engine::resource_manager::DEFAULT_RESOURCE_LIFETIME,
     renderer::{
         cache::CacheEntry,
         framework::{
             geometry_buffer::{
-                BufferBuilder, ElementKind, GeometryBuffer, GeometryBufferBuilder,
-                GeometryBufferKind,
+                AttributeDefinition, AttributeKind, BufferBuilder, ElementKind, GeometryBuffer,
+                GeometryBufferBuilder, GeometryBufferKind,
             },
             state::PipelineState,
         },
@@ -15,6 +15,14 @@ use crate::{
 };
 use std::collections::HashMap;
 
+/// Index of a buffer with per-instance world matrices. Every geometry buffer produced by the
+/// cache has such buffer, its contents must be set before instanced draw call.
+pub const INSTANCE_BUFFER_INDEX: usize = 1;
+
+/// First shader location of per-instance world matrix. Matrix occupies four sequential locations
+/// (one per column), so in shaders it must be defined as `layout(location = 7) in mat4 instanceWorldMatrix;`
+pub const INSTANCE_MATRIX_LOCATION: u32 = 7;
+
 #[derive(Default)]
 pub struct GeometryCache {
     map: HashMap<usize, CacheEntry<GeometryBuffer>>,
@@ -28,14 +36,19 @@ impl GeometryCache {
         let data_hash = data.content_hash();
 
         let geometry_buffer = self.map.entry(key).or_insert_with(|| {
-            let geometry_buffer = GeometryBufferBuilder::new(ElementKind::Triangle)
+            let mut geometry_buffer = GeometryBufferBuilder::new(ElementKind::Triangle)
                 .with_buffer_builder(BufferBuilder::from_vertex_buffer(
                     &data.vertex_buffer,
                     GeometryBufferKind::StaticDraw,
                 ))
+                .with_buffer_builder(make_instance_buffer_builder())
                 .build(state)
                 .unwrap();
 
+            // Put single identity matrix in the instance buffer, so non-instanced draw calls
+            // will never read out of bounds of the buffer.
+            Self::set_instances(state, &mut geometry_buffer, &[Matrix4::identity()]);
+
             geometry_buffer
                 .bind(state)
                 .set_triangles(data.geometry_buffer.triangles_ref());
@@ -61,6 +74,16 @@ impl GeometryCache {
         geometry_buffer
     }
 
+    /// Uploads given world matrices to the instance buffer of the geometry buffer. Call this
+    /// before instanced draw call.
+    pub fn set_instances(
+        state: &mut PipelineState,
+        geometry_buffer: &mut GeometryBuffer,
+        world_matrices: &[Matrix4<f32>],
+    ) {
+        geometry_buffer.set_buffer_data(state, INSTANCE_BUFFER_INDEX, world_matrices);
+    }
+
     pub fn update(&mut self, dt: f32) {
         scope_profile!();
 
@@ -74,3 +97,16 @@ impl GeometryCache {
         self.map.clear();
     }
 }
+
+fn make_instance_buffer_builder() -> BufferBuilder {
+    let mut builder = BufferBuilder::new::<Matrix4<f32>>(GeometryBufferKind::DynamicDraw, None);
+    for column in 0..4 {
+        builder = builder.with_attribute(AttributeDefinition {
+            location: INSTANCE_MATRIX_LOCATION + column,
+            kind: AttributeKind::Float4,
+            normalized: false,
+            divisor: 1,
+        });
+    }
+    builder
+}
@@ -18,6 +18,9 @@ use std::{
 pub struct RenderPassData {
     pub program: GpuProgram,
     pub draw_params: DrawParameters,
+    /// True if program of the pass uses `rg3d_useInstancing` built-in variable, which means
+    /// that it can fetch world matrices from per-instance attributes.
+    pub supports_instancing: bool,
 }
 
 pub struct ShaderSet {
@@ -36,11 +39,16 @@ impl ShaderSet {
                 &render_pass.fragment_shader,
             ) {
                 Ok(gpu_program) => {
+                    let supports_instancing = gpu_program
+                        .uniform_location(state, "rg3d_useInstancing")
+                        .is_ok();
+
                     map.insert(
                         render_pass.name.clone(),
                         RenderPassData {
                             program: gpu_program,
                             draw_params: render_pass.draw_parameters.clone(),
+                            supports_instancing,
                         },
                     );
                 }
@@ -7,7 +7,7 @@
 //! path).
 
 use crate::{
-    core::{math::Rect, scope_profile},
+    core::{algebra::Matrix4, math::Rect, scope_profile},
     renderer::{
         apply_material,
         batch::BatchStorage,
@@ -63,6 +63,8 @@ impl ForwardRenderer {
 
         let initial_view_projection = camera.view_projection_matrix();
 
+        let mut instance_matrices = Vec::new();
+
         for batch in batch_storage
             .batches
             .iter()
@@ -76,7 +78,59 @@ impl ForwardRenderer {
                 .get(state, material.shader())
                 .and_then(|shader_set| shader_set.render_passes.get("Forward"))
             {
+                let use_instancing = batch.can_be_instanced() && render_pass.supports_instancing;
+
+                if use_instancing {
+                    instance_matrices.clear();
+                    instance_matrices.extend(
+                        batch
+                            .instances
+                            .iter()
+                            .filter(|i| {
+                                i.depth_offset == 0.0 && camera.visibility_cache.is_visible(i.owner)
+                            })
+                            .map(|i| i.world_transform),
+                    );
+
+                    if !instance_matrices.is_empty() {
+                        GeometryCache::set_instances(state, geometry, &instance_matrices);
+
+                        statistics += framebuffer.draw_instances(
+                            instance_matrices.len(),
+                            geometry,
+                            state,
+                            viewport,
+                            &render_pass.program,
+                            &render_pass.draw_params,
+                            |mut program_binding| {
+                                apply_material(MaterialContext {
+                                    material: &material,
+                                    program_binding: &mut program_binding,
+                                    texture_cache,
+                                    world_matrix: &Matrix4::identity(),
+                                    wvp_matrix: &initial_view_projection,
+                                    view_projection_matrix: &initial_view_projection,
+                                    use_instancing: true,
+                                    bone_matrices: &[],
+                                    use_skeletal_animation: false,
+                                    camera_position: &camera.global_position(),
+                                    use_pom: quality_settings.use_parallax_mapping,
+                                    light_position: &Default::default(),
+                                    normal_dummy: normal_dummy.clone(),
+                                    white_dummy: white_dummy.clone(),
+                                    black_dummy: black_dummy.clone(),
+                                });
+                            },
+                        );
+                    }
+                }
+
                 for instance in batch.instances.iter() {
+                    if use_instancing && instance.depth_offset == 0.0 {
+                        // Already rendered.
+                        continue;
+                    }
+
                     if camera.visibility_cache.is_visible(instance.owner) {
                         let view_projection = if instance.depth_offset != 0.0 {
                             let mut projection = camera.projection_matrix();
@@ -94,11 +148,13 @@ impl ForwardRenderer {
                             &render_pass.draw_params,
                             |mut program_binding| {
                                 apply_material(MaterialContext {
-                                    material: &*material,
+                                    material: &material,
                                     program_binding: &mut program_binding,
                                     texture_cache,
                                     world_matrix: &instance.world_transform,
                                     wvp_matrix: &(view_projection * instance.world_transform),
+                                    view_projection_matrix: &view_projection,
+                                    use_instancing: false,
                                     bone_matrices: &instance.bone_matrices,
                                     use_skeletal_animation: batch.is_skinned,
                                     camera_position: &camera.global_position(),
@@ -0,0 +1,1077 @@
+//! Graphics backend abstraction.
+//!
+//! Every call the framework makes to the graphics API goes through the [`GraphicsBackend`]
+//! trait. The default implementation is [`GlowBackend`], which forwards calls to OpenGL
+//! (or WebGL on wasm) via `glow`. Other implementations (for example the recording backend
+//! in [`super::recording`]) allow the renderer to run without a GPU.
+
+use glow::HasContext;
+use std::{cell::RefCell, collections::HashMap, num::NonZeroU32};
+
+macro_rules! define_handle {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(pub(in crate::renderer::framework) NonZeroU32);
+    };
+}
+
+define_handle!(
+    /// Opaque handle of a shader object.
+    ShaderId
+);
+define_handle!(
+    /// Opaque handle of a linked GPU program.
+    ProgramId
+);
+define_handle!(
+    /// Opaque handle of a vertex or index buffer.
+    BufferId
+);
+define_handle!(
+    /// Opaque handle of a texture.
+    TextureId
+);
+define_handle!(
+    /// Opaque handle of a framebuffer object.
+    FramebufferId
+);
+define_handle!(
+    /// Opaque handle of a vertex array object.
+    VertexArrayId
+);
+define_handle!(
+    /// Opaque handle of a uniform location inside a program.
+    UniformLocationId
+);
+
+/// Set of graphics API calls used by the renderer framework. Methods mirror OpenGL
+/// (`glow`) calls, but all objects are referenced by backend-agnostic handles.
+///
+/// # Safety
+///
+/// Methods are `unsafe` for the same reasons as OpenGL calls are - the caller must
+/// guarantee that handles are alive and that passed data matches the current state.
+#[allow(clippy::too_many_arguments, clippy::missing_safety_doc)]
+pub trait GraphicsBackend {
+    unsafe fn active_texture(&self, unit: u32);
+    unsafe fn attach_shader(&self, program: ProgramId, shader: ShaderId);
+    unsafe fn bind_buffer(&self, target: u32, buffer: Option<BufferId>);
+    unsafe fn bind_framebuffer(&self, target: u32, framebuffer: Option<FramebufferId>);
+    unsafe fn bind_texture(&self, target: u32, texture: Option<TextureId>);
+    unsafe fn bind_vertex_array(&self, vertex_array: Option<VertexArrayId>);
+    unsafe fn blend_func(&self, src: u32, dst: u32);
+    unsafe fn blit_framebuffer(
+        &self,
+        src_x0: i32,
+        src_y0: i32,
+        src_x1: i32,
+        src_y1: i32,
+        dst_x0: i32,
+        dst_y0: i32,
+        dst_x1: i32,
+        dst_y1: i32,
+        mask: u32,
+        filter: u32,
+    );
+    unsafe fn buffer_data_u8_slice(&self, target: u32, data: &[u8], usage: u32);
+    unsafe fn buffer_sub_data_u8_slice(&self, target: u32, offset: i32, src_data: &[u8]);
+    unsafe fn check_framebuffer_status(&self, target: u32) -> u32;
+    unsafe fn clear(&self, mask: u32);
+    unsafe fn clear_buffer_depth_stencil(
+        &self,
+        target: u32,
+        draw_buffer: u32,
+        depth: f32,
+        stencil: i32,
+    );
+    unsafe fn clear_buffer_f32_slice(&self, target: u32, draw_buffer: u32, values: &[f32]);
+    unsafe fn clear_buffer_i32_slice(&self, target: u32, draw_buffer: u32, values: &[i32]);
+    unsafe fn clear_buffer_u32_slice(&self, target: u32, draw_buffer: u32, values: &[u32]);
+    unsafe fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32);
+    unsafe fn clear_depth_f32(&self, depth: f32);
+    unsafe fn clear_stencil(&self, stencil: i32);
+    unsafe fn color_mask(&self, red: bool, green: bool, blue: bool, alpha: bool);
+    unsafe fn compile_shader(&self, shader: ShaderId);
+    unsafe fn compressed_tex_image_1d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        border: i32,
+        image_size: i32,
+        pixels: &[u8],
+    );
+    unsafe fn compressed_tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        border: i32,
+        image_size: i32,
+        pixels: &[u8],
+    );
+    unsafe fn compressed_tex_image_3d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        depth: i32,
+        border: i32,
+        image_size: i32,
+        pixels: &[u8],
+    );
+    unsafe fn create_buffer(&self) -> Result<BufferId, String>;
+    unsafe fn create_framebuffer(&self) -> Result<FramebufferId, String>;
+    unsafe fn create_program(&self) -> Result<ProgramId, String>;
+    unsafe fn create_shader(&self, shader_type: u32) -> Result<ShaderId, String>;
+    unsafe fn create_texture(&self) -> Result<TextureId, String>;
+    unsafe fn create_vertex_array(&self) -> Result<VertexArrayId, String>;
+    unsafe fn cull_face(&self, value: u32);
+    unsafe fn delete_buffer(&self, buffer: BufferId);
+    unsafe fn delete_framebuffer(&self, framebuffer: FramebufferId);
+    unsafe fn delete_program(&self, program: ProgramId);
+    unsafe fn delete_shader(&self, shader: ShaderId);
+    unsafe fn delete_texture(&self, texture: TextureId);
+    unsafe fn delete_vertex_array(&self, vertex_array: VertexArrayId);
+    unsafe fn depth_func(&self, func: u32);
+    unsafe fn depth_mask(&self, value: bool);
+    unsafe fn disable(&self, parameter: u32);
+    unsafe fn draw_buffer(&self, buffer: u32);
+    unsafe fn draw_buffers(&self, buffers: &[u32]);
+    unsafe fn draw_elements(&self, mode: u32, count: i32, element_type: u32, offset: i32);
+    unsafe fn draw_elements_instanced(
+        &self,
+        mode: u32,
+        count: i32,
+        element_type: u32,
+        offset: i32,
+        instance_count: i32,
+    );
+    unsafe fn enable(&self, parameter: u32);
+    unsafe fn enable_vertex_attrib_array(&self, index: u32);
+    unsafe fn framebuffer_texture(
+        &self,
+        target: u32,
+        attachment: u32,
+        texture: Option<TextureId>,
+        level: i32,
+    );
+    unsafe fn framebuffer_texture_2d(
+        &self,
+        target: u32,
+        attachment: u32,
+        texture_target: u32,
+        texture: Option<TextureId>,
+        level: i32,
+    );
+    unsafe fn framebuffer_texture_3d(
+        &self,
+        target: u32,
+        attachment: u32,
+        texture_target: u32,
+        texture: Option<TextureId>,
+        level: i32,
+        layer: i32,
+    );
+    unsafe fn generate_mipmap(&self, target: u32);
+    unsafe fn get_debug_message_log(&self, count: u32) -> Vec<glow::DebugMessageLogEntry>;
+    unsafe fn get_error(&self) -> u32;
+    unsafe fn get_parameter_f32(&self, parameter: u32) -> f32;
+    unsafe fn get_program_info_log(&self, program: ProgramId) -> String;
+    unsafe fn get_program_link_status(&self, program: ProgramId) -> bool;
+    unsafe fn get_shader_compile_status(&self, shader: ShaderId) -> bool;
+    unsafe fn get_shader_info_log(&self, shader: ShaderId) -> String;
+    unsafe fn get_uniform_location(
+        &self,
+        program: ProgramId,
+        name: &str,
+    ) -> Option<UniformLocationId>;
+    unsafe fn link_program(&self, program: ProgramId);
+    unsafe fn pixel_store_i32(&self, parameter: u32, value: i32);
+    unsafe fn read_pixels(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        ty: u32,
+        pixels: &mut [u8],
+    );
+    unsafe fn scissor(&self, x: i32, y: i32, width: i32, height: i32);
+    unsafe fn shader_source(&self, shader: ShaderId, source: &str);
+    unsafe fn stencil_func(&self, func: u32, reference: i32, mask: u32);
+    unsafe fn stencil_mask(&self, mask: u32);
+    unsafe fn stencil_op(&self, stencil_fail: u32, depth_fail: u32, pass: u32);
+    unsafe fn tex_image_1d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        border: i32,
+        format: u32,
+        ty: u32,
+        pixels: Option<&[u8]>,
+    );
+    unsafe fn tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        border: i32,
+        format: u32,
+        ty: u32,
+        pixels: Option<&[u8]>,
+    );
+    unsafe fn tex_image_3d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        depth: i32,
+        border: i32,
+        format: u32,
+        ty: u32,
+        pixels: Option<&[u8]>,
+    );
+    unsafe fn tex_parameter_f32(&self, target: u32, parameter: u32, value: f32);
+    unsafe fn tex_parameter_f32_slice(&self, target: u32, parameter: u32, values: &[f32]);
+    unsafe fn tex_parameter_i32(&self, target: u32, parameter: u32, value: i32);
+    unsafe fn uniform_1_f32(&self, location: Option<&UniformLocationId>, x: f32);
+    unsafe fn uniform_1_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]);
+    unsafe fn uniform_1_i32(&self, location: Option<&UniformLocationId>, x: i32);
+    unsafe fn uniform_1_i32_slice(&self, location: Option<&UniformLocationId>, v: &[i32]);
+    unsafe fn uniform_1_u32(&self, location: Option<&UniformLocationId>, x: u32);
+    unsafe fn uniform_1_u32_slice(&self, location: Option<&UniformLocationId>, v: &[u32]);
+    unsafe fn uniform_2_f32(&self, location: Option<&UniformLocationId>, x: f32, y: f32);
+    unsafe fn uniform_2_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]);
+    unsafe fn uniform_3_f32(&self, location: Option<&UniformLocationId>, x: f32, y: f32, z: f32);
+    unsafe fn uniform_3_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]);
+    unsafe fn uniform_4_f32(
+        &self,
+        location: Option<&UniformLocationId>,
+        x: f32,
+        y: f32,
+        z: f32,
+        w: f32,
+    );
+    unsafe fn uniform_4_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]);
+    unsafe fn uniform_matrix_2_f32_slice(
+        &self,
+        location: Option<&UniformLocationId>,
+        transpose: bool,
+        v: &[f32],
+    );
+    unsafe fn uniform_matrix_3_f32_slice(
+        &self,
+        location: Option<&UniformLocationId>,
+        transpose: bool,
+        v: &[f32],
+    );
+    unsafe fn uniform_matrix_4_f32_slice(
+        &self,
+        location: Option<&UniformLocationId>,
+        transpose: bool,
+        v: &[f32],
+    );
+    unsafe fn use_program(&self, program: Option<ProgramId>);
+    unsafe fn vertex_attrib_divisor(&self, index: u32, divisor: u32);
+    unsafe fn vertex_attrib_pointer_f32(
+        &self,
+        index: u32,
+        size: i32,
+        data_type: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    );
+    unsafe fn viewport(&self, x: i32, y: i32, width: i32, height: i32);
+}
+
+/// Dense storage that maps backend-agnostic handles to native objects. Freed slots are
+/// reused, as OpenGL does with object names.
+pub(in crate::renderer::framework) struct HandleTable<T> {
+    entries: Vec<Option<T>>,
+    free: Vec<usize>,
+}
+
+impl<T> Default for HandleTable<T> {
+    fn default() -> Self {
+        Self {
+            entries: Default::default(),
+            free: Default::default(),
+        }
+    }
+}
+
+impl<T> HandleTable<T> {
+    pub fn insert(&mut self, value: T) -> NonZeroU32 {
+        let index = if let Some(index) = self.free.pop() {
+            self.entries[index] = Some(value);
+            index
+        } else {
+            self.entries.push(Some(value));
+            self.entries.len() - 1
+        };
+        NonZeroU32::new(index as u32 + 1).unwrap()
+    }
+
+    pub fn get_ref(&self, handle: NonZeroU32) -> &T {
+        self.entries[handle.get() as usize - 1]
+            .as_ref()
+            .expect("Attempt to use deleted graphics object!")
+    }
+
+    pub fn remove(&mut self, handle: NonZeroU32) -> Option<T> {
+        let index = handle.get() as usize - 1;
+        let value = self.entries.get_mut(index)?.take();
+        if value.is_some() {
+            self.free.push(index);
+        }
+        value
+    }
+}
+
+impl<T: Clone> HandleTable<T> {
+    pub fn get(&self, handle: NonZeroU32) -> T {
+        self.get_ref(handle).clone()
+    }
+}
+
+/// Graphics backend that forwards every call to an OpenGL (or WebGL) context.
+pub struct GlowBackend {
+    gl: glow::Context,
+    shaders: RefCell<HandleTable<glow::Shader>>,
+    programs: RefCell<HandleTable<glow::Program>>,
+    buffers: RefCell<HandleTable<glow::Buffer>>,
+    textures: RefCell<HandleTable<glow::Texture>>,
+    framebuffers: RefCell<HandleTable<glow::Framebuffer>>,
+    vertex_arrays: RefCell<HandleTable<glow::VertexArray>>,
+    uniform_locations: RefCell<HandleTable<glow::UniformLocation>>,
+    // Uniform locations are immutable after linking, so they're looked up only once per
+    // program and then reused instead of creating new handles on every request.
+    uniform_location_cache: RefCell<HashMap<ProgramId, HashMap<String, Option<UniformLocationId>>>>,
+}
+
+impl GlowBackend {
+    /// Creates new backend that uses given OpenGL context.
+    pub fn new(gl: glow::Context) -> Self {
+        Self {
+            gl,
+            shaders: Default::default(),
+            programs: Default::default(),
+            buffers: Default::default(),
+            textures: Default::default(),
+            framebuffers: Default::default(),
+            vertex_arrays: Default::default(),
+            uniform_locations: Default::default(),
+            uniform_location_cache: Default::default(),
+        }
+    }
+
+    fn forget_uniform_locations(&self, program: ProgramId) {
+        if let Some(locations) = self.uniform_location_cache.borrow_mut().remove(&program) {
+            let mut table = self.uniform_locations.borrow_mut();
+            for location in locations.values().flatten() {
+                table.remove(location.0);
+            }
+        }
+    }
+}
+
+impl GraphicsBackend for GlowBackend {
+    unsafe fn active_texture(&self, unit: u32) {
+        self.gl.active_texture(unit)
+    }
+
+    unsafe fn attach_shader(&self, program: ProgramId, shader: ShaderId) {
+        self.gl.attach_shader(
+            self.programs.borrow().get(program.0),
+            self.shaders.borrow().get(shader.0),
+        )
+    }
+
+    unsafe fn bind_buffer(&self, target: u32, buffer: Option<BufferId>) {
+        self.gl
+            .bind_buffer(target, buffer.map(|h| self.buffers.borrow().get(h.0)))
+    }
+
+    unsafe fn bind_framebuffer(&self, target: u32, framebuffer: Option<FramebufferId>) {
+        self.gl.bind_framebuffer(
+            target,
+            framebuffer.map(|h| self.framebuffers.borrow().get(h.0)),
+        )
+    }
+
+    unsafe fn bind_texture(&self, target: u32, texture: Option<TextureId>) {
+        self.gl
+            .bind_texture(target, texture.map(|h| self.textures.borrow().get(h.0)))
+    }
+
+    unsafe fn bind_vertex_array(&self, vertex_array: Option<VertexArrayId>) {
+        self.gl
+            .bind_vertex_array(vertex_array.map(|h| self.vertex_arrays.borrow().get(h.0)))
+    }
+
+    unsafe fn blend_func(&self, src: u32, dst: u32) {
+        self.gl.blend_func(src, dst)
+    }
+
+    unsafe fn blit_framebuffer(
+        &self,
+        src_x0: i32,
+        src_y0: i32,
+        src_x1: i32,
+        src_y1: i32,
+        dst_x0: i32,
+        dst_y0: i32,
+        dst_x1: i32,
+        dst_y1: i32,
+        mask: u32,
+        filter: u32,
+    ) {
+        self.gl.blit_framebuffer(
+            src_x0, src_y0, src_x1, src_y1, dst_x0, dst_y0, dst_x1, dst_y1, mask, filter,
+        )
+    }
+
+    unsafe fn buffer_data_u8_slice(&self, target: u32, data: &[u8], usage: u32) {
+        self.gl.buffer_data_u8_slice(target, data, usage)
+    }
+
+    unsafe fn buffer_sub_data_u8_slice(&self, target: u32, offset: i32, src_data: &[u8]) {
+        self.gl.buffer_sub_data_u8_slice(target, offset, src_data)
+    }
+
+    unsafe fn check_framebuffer_status(&self, target: u32) -> u32 {
+        self.gl.check_framebuffer_status(target)
+    }
+
+    unsafe fn clear(&self, mask: u32) {
+        self.gl.clear(mask)
+    }
+
+    unsafe fn clear_buffer_depth_stencil(
+        &self,
+        target: u32,
+        draw_buffer: u32,
+        depth: f32,
+        stencil: i32,
+    ) {
+        self.gl
+            .clear_buffer_depth_stencil(target, draw_buffer, depth, stencil)
+    }
+
+    unsafe fn clear_buffer_f32_slice(&self, target: u32, draw_buffer: u32, values: &[f32]) {
+        self.gl.clear_buffer_f32_slice(target, draw_buffer, values)
+    }
+
+    unsafe fn clear_buffer_i32_slice(&self, target: u32, draw_buffer: u32, values: &[i32]) {
+        self.gl.clear_buffer_i32_slice(target, draw_buffer, values)
+    }
+
+    unsafe fn clear_buffer_u32_slice(&self, target: u32, draw_buffer: u32, values: &[u32]) {
+        self.gl.clear_buffer_u32_slice(target, draw_buffer, values)
+    }
+
+    unsafe fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32) {
+        self.gl.clear_color(red, green, blue, alpha)
+    }
+
+    unsafe fn clear_depth_f32(&self, depth: f32) {
+        self.gl.clear_depth_f32(depth)
+    }
+
+    unsafe fn clear_stencil(&self, stencil: i32) {
+        self.gl.clear_stencil(stencil)
+    }
+
+    unsafe fn color_mask(&self, red: bool, green: bool, blue: bool, alpha: bool) {
+        self.gl.color_mask(red, green, blue, alpha)
+    }
+
+    unsafe fn compile_shader(&self, shader: ShaderId) {
+        self.gl.compile_shader(self.shaders.borrow().get(shader.0))
+    }
+
+    unsafe fn compressed_tex_image_1d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        border: i32,
+        image_size: i32,
+        pixels: &[u8],
+    ) {
+        self.gl.compressed_tex_image_1d(
+            target,
+            level,
+            internal_format,
+            width,
+            border,
+            image_size,
+            pixels,
+        )
+    }
+
+    unsafe fn compressed_tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        border: i32,
+        image_size: i32,
+        pixels: &[u8],
+    ) {
+        self.gl.compressed_tex_image_2d(
+            target,
+            level,
+            internal_format,
+            width,
+            height,
+            border,
+            image_size,
+            pixels,
+        )
+    }
+
+    unsafe fn compressed_tex_image_3d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        depth: i32,
+        border: i32,
+        image_size: i32,
+        pixels: &[u8],
+    ) {
+        self.gl.compressed_tex_image_3d(
+            target,
+            level,
+            internal_format,
+            width,
+            height,
+            depth,
+            border,
+            image_size,
+            pixels,
+        )
+    }
+
+    unsafe fn create_buffer(&self) -> Result<BufferId, String> {
+        self.gl
+            .create_buffer()
+            .map(|h| BufferId(self.buffers.borrow_mut().insert(h)))
+    }
+
+    unsafe fn create_framebuffer(&self) -> Result<FramebufferId, String> {
+        self.gl
+            .create_framebuffer()
+            .map(|h| FramebufferId(self.framebuffers.borrow_mut().insert(h)))
+    }
+
+    unsafe fn create_program(&self) -> Result<ProgramId, String> {
+        self.gl
+            .create_program()
+            .map(|h| ProgramId(self.programs.borrow_mut().insert(h)))
+    }
+
+    unsafe fn create_shader(&self, shader_type: u32) -> Result<ShaderId, String> {
+        self.gl
+            .create_shader(shader_type)
+            .map(|h| ShaderId(self.shaders.borrow_mut().insert(h)))
+    }
+
+    unsafe fn create_texture(&self) -> Result<TextureId, String> {
+        self.gl
+            .create_texture()
+            .map(|h| TextureId(self.textures.borrow_mut().insert(h)))
+    }
+
+    unsafe fn create_vertex_array(&self) -> Result<VertexArrayId, String> {
+        self.gl
+            .create_vertex_array()
+            .map(|h| VertexArrayId(self.vertex_arrays.borrow_mut().insert(h)))
+    }
+
+    unsafe fn cull_face(&self, value: u32) {
+        self.gl.cull_face(value)
+    }
+
+    unsafe fn delete_buffer(&self, buffer: BufferId) {
+        if let Some(handle) = self.buffers.borrow_mut().remove(buffer.0) {
+            self.gl.delete_buffer(handle)
+        }
+    }
+
+    unsafe fn delete_framebuffer(&self, framebuffer: FramebufferId) {
+        if let Some(handle) = self.framebuffers.borrow_mut().remove(framebuffer.0) {
+            self.gl.delete_framebuffer(handle)
+        }
+    }
+
+    unsafe fn delete_program(&self, program: ProgramId) {
+        self.forget_uniform_locations(program);
+        if let Some(handle) = self.programs.borrow_mut().remove(program.0) {
+            self.gl.delete_program(handle)
+        }
+    }
+
+    unsafe fn delete_shader(&self, shader: ShaderId) {
+        if let Some(handle) = self.shaders.borrow_mut().remove(shader.0) {
+            self.gl.delete_shader(handle)
+        }
+    }
+
+    unsafe fn delete_texture(&self, texture: TextureId) {
+        if let Some(handle) = self.textures.borrow_mut().remove(texture.0) {
+            self.gl.delete_texture(handle)
+        }
+    }
+
+    unsafe fn delete_vertex_array(&self, vertex_array: VertexArrayId) {
+        if let Some(handle) = self.vertex_arrays.borrow_mut().remove(vertex_array.0) {
+            self.gl.delete_vertex_array(handle)
+        }
+    }
+
+    unsafe fn depth_func(&self, func: u32) {
+        self.gl.depth_func(func)
+    }
+
+    unsafe fn depth_mask(&self, value: bool) {
+        self.gl.depth_mask(value)
+    }
+
+    unsafe fn disable(&self, parameter: u32) {
+        self.gl.disable(parameter)
+    }
+
+    unsafe fn draw_buffer(&self, buffer: u32) {
+        self.gl.draw_buffer(buffer)
+    }
+
+    unsafe fn draw_buffers(&self, buffers: &[u32]) {
+        self.gl.draw_buffers(buffers)
+    }
+
+    unsafe fn draw_elements(&self, mode: u32, count: i32, element_type: u32, offset: i32) {
+        self.gl.draw_elements(mode, count, element_type, offset)
+    }
+
+    unsafe fn draw_elements_instanced(
+        &self,
+        mode: u32,
+        count: i32,
+        element_type: u32,
+        offset: i32,
+        instance_count: i32,
+    ) {
+        self.gl
+            .draw_elements_instanced(mode, count, element_type, offset, instance_count)
+    }
+
+    unsafe fn enable(&self, parameter: u32) {
+        self.gl.enable(parameter)
+    }
+
+    unsafe fn enable_vertex_attrib_array(&self, index: u32) {
+        self.gl.enable_vertex_attrib_array(index)
+    }
+
+    unsafe fn framebuffer_texture(
+        &self,
+        target: u32,
+        attachment: u32,
+        texture: Option<TextureId>,
+        level: i32,
+    ) {
+        self.gl.framebuffer_texture(
+            target,
+            attachment,
+            texture.map(|h| self.textures.borrow().get(h.0)),
+            level,
+        )
+    }
+
+    unsafe fn framebuffer_texture_2d(
+        &self,
+        target: u32,
+        attachment: u32,
+        texture_target: u32,
+        texture: Option<TextureId>,
+        level: i32,
+    ) {
+        self.gl.framebuffer_texture_2d(
+            target,
+            attachment,
+            texture_target,
+            texture.map(|h| self.textures.borrow().get(h.0)),
+            level,
+        )
+    }
+
+    unsafe fn framebuffer_texture_3d(
+        &self,
+        target: u32,
+        attachment: u32,
+        texture_target: u32,
+        texture: Option<TextureId>,
+        level: i32,
+        layer: i32,
+    ) {
+        self.gl.framebuffer_texture_3d(
+            target,
+            attachment,
+            texture_target,
+            texture.map(|h| self.textures.borrow().get(h.0)),
+            level,
+            layer,
+        )
+    }
+
+    unsafe fn generate_mipmap(&self, target: u32) {
+        self.gl.generate_mipmap(target)
+    }
+
+    unsafe fn get_debug_message_log(&self, count: u32) -> Vec<glow::DebugMessageLogEntry> {
+        self.gl.get_debug_message_log(count)
+    }
+
+    unsafe fn get_error(&self) -> u32 {
+        self.gl.get_error()
+    }
+
+    unsafe fn get_parameter_f32(&self, parameter: u32) -> f32 {
+        self.gl.get_parameter_f32(parameter)
+    }
+
+    unsafe fn get_program_info_log(&self, program: ProgramId) -> String {
+        self.gl
+            .get_program_info_log(self.programs.borrow().get(program.0))
+    }
+
+    unsafe fn get_program_link_status(&self, program: ProgramId) -> bool {
+        self.gl
+            .get_program_link_status(self.programs.borrow().get(program.0))
+    }
+
+    unsafe fn get_shader_compile_status(&self, shader: ShaderId) -> bool {
+        self.gl
+            .get_shader_compile_status(self.shaders.borrow().get(shader.0))
+    }
+
+    unsafe fn get_shader_info_log(&self, shader: ShaderId) -> String {
+        self.gl
+            .get_shader_info_log(self.shaders.borrow().get(shader.0))
+    }
+
+    unsafe fn link_program(&self, program: ProgramId) {
+        self.gl.link_program(self.programs.borrow().get(program.0))
+    }
+
+    unsafe fn pixel_store_i32(&self, parameter: u32, value: i32) {
+        self.gl.pixel_store_i32(parameter, value)
+    }
+
+    unsafe fn read_pixels(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        ty: u32,
+        pixels: &mut [u8],
+    ) {
+        self.gl.read_pixels(
+            x,
+            y,
+            width,
+            height,
+            format,
+            ty,
+            glow::PixelPackData::Slice(pixels),
+        )
+    }
+
+    unsafe fn scissor(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.gl.scissor(x, y, width, height)
+    }
+
+    unsafe fn shader_source(&self, shader: ShaderId, source: &str) {
+        self.gl
+            .shader_source(self.shaders.borrow().get(shader.0), source)
+    }
+
+    unsafe fn stencil_func(&self, func: u32, reference: i32, mask: u32) {
+        self.gl.stencil_func(func, reference, mask)
+    }
+
+    unsafe fn stencil_mask(&self, mask: u32) {
+        self.gl.stencil_mask(mask)
+    }
+
+    unsafe fn stencil_op(&self, stencil_fail: u32, depth_fail: u32, pass: u32) {
+        self.gl.stencil_op(stencil_fail, depth_fail, pass)
+    }
+
+    unsafe fn tex_image_1d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        border: i32,
+        format: u32,
+        ty: u32,
+        pixels: Option<&[u8]>,
+    ) {
+        self.gl.tex_image_1d(
+            target,
+            level,
+            internal_format,
+            width,
+            border,
+            format,
+            ty,
+            pixels,
+        )
+    }
+
+    unsafe fn tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        border: i32,
+        format: u32,
+        ty: u32,
+        pixels: Option<&[u8]>,
+    ) {
+        self.gl.tex_image_2d(
+            target,
+            level,
+            internal_format,
+            width,
+            height,
+            border,
+            format,
+            ty,
+            pixels,
+        )
+    }
+
+    unsafe fn tex_image_3d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        depth: i32,
+        border: i32,
+        format: u32,
+        ty: u32,
+        pixels: Option<&[u8]>,
+    ) {
+        self.gl.tex_image_3d(
+            target,
+            level,
+            internal_format,
+            width,
+            height,
+            depth,
+            border,
+            format,
+            ty,
+            pixels,
+        )
+    }
+
+    unsafe fn tex_parameter_f32(&self, target: u32, parameter: u32, value: f32) {
+        self.gl.tex_parameter_f32(target, parameter, value)
+    }
+
+    unsafe fn tex_parameter_f32_slice(&self, target: u32, parameter: u32, values: &[f32]) {
+        self.gl.tex_parameter_f32_slice(target, parameter, values)
+    }
+
+    unsafe fn tex_parameter_i32(&self, target: u32, parameter: u32, value: i32) {
+        self.gl.tex_parameter_i32(target, parameter, value)
+    }
+
+    unsafe fn uniform_1_f32(&self, location: Option<&UniformLocationId>, x: f32) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_1_f32(location.map(|l| locations.get_ref(l.0)), x)
+    }
+
+    unsafe fn uniform_1_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_1_f32_slice(location.map(|l| locations.get_ref(l.0)), v)
+    }
+
+    unsafe fn uniform_1_i32(&self, location: Option<&UniformLocationId>, x: i32) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_1_i32(location.map(|l| locations.get_ref(l.0)), x)
+    }
+
+    unsafe fn uniform_1_i32_slice(&self, location: Option<&UniformLocationId>, v: &[i32]) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_1_i32_slice(location.map(|l| locations.get_ref(l.0)), v)
+    }
+
+    unsafe fn uniform_1_u32(&self, location: Option<&UniformLocationId>, x: u32) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_1_u32(location.map(|l| locations.get_ref(l.0)), x)
+    }
+
+    unsafe fn uniform_1_u32_slice(&self, location: Option<&UniformLocationId>, v: &[u32]) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_1_u32_slice(location.map(|l| locations.get_ref(l.0)), v)
+    }
+
+    unsafe fn uniform_2_f32(&self, location: Option<&UniformLocationId>, x: f32, y: f32) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_2_f32(location.map(|l| locations.get_ref(l.0)), x, y)
+    }
+
+    unsafe fn uniform_2_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_2_f32_slice(location.map(|l| locations.get_ref(l.0)), v)
+    }
+
+    unsafe fn uniform_3_f32(&self, location: Option<&UniformLocationId>, x: f32, y: f32, z: f32) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_3_f32(location.map(|l| locations.get_ref(l.0)), x, y, z)
+    }
+
+    unsafe fn uniform_3_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_3_f32_slice(location.map(|l| locations.get_ref(l.0)), v)
+    }
+
+    unsafe fn uniform_4_f32(
+        &self,
+        location: Option<&UniformLocationId>,
+        x: f32,
+        y: f32,
+        z: f32,
+        w: f32,
+    ) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_4_f32(location.map(|l| locations.get_ref(l.0)), x, y, z, w)
+    }
+
+    unsafe fn uniform_4_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_4_f32_slice(location.map(|l| locations.get_ref(l.0)), v)
+    }
+
+    unsafe fn uniform_matrix_2_f32_slice(
+        &self,
+        location: Option<&UniformLocationId>,
+        transpose: bool,
+        v: &[f32],
+    ) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_matrix_2_f32_slice(location.map(|l| locations.get_ref(l.0)), transpose, v)
+    }
+
+    unsafe fn uniform_matrix_3_f32_slice(
+        &self,
+        location: Option<&UniformLocationId>,
+        transpose: bool,
+        v: &[f32],
+    ) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_matrix_3_f32_slice(location.map(|l| locations.get_ref(l.0)), transpose, v)
+    }
+
+    unsafe fn uniform_matrix_4_f32_slice(
+        &self,
+        location: Option<&UniformLocationId>,
+        transpose: bool,
+        v: &[f32],
+    ) {
+        let locations = self.uniform_locations.borrow();
+        self.gl
+            .uniform_matrix_4_f32_slice(location.map(|l| locations.get_ref(l.0)), transpose, v)
+    }
+
+    unsafe fn use_program(&self, program: Option<ProgramId>) {
+        self.gl
+            .use_program(program.map(|h| self.programs.borrow().get(h.0)))
+    }
+
+    unsafe fn vertex_attrib_divisor(&self, index: u32, divisor: u32) {
+        self.gl.vertex_attrib_divisor(index, divisor)
+    }
+
+    unsafe fn vertex_attrib_pointer_f32(
+        &self,
+        index: u32,
+        size: i32,
+        data_type: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    ) {
+        self.gl
+            .vertex_attrib_pointer_f32(index, size, data_type, normalized, stride, offset)
+    }
+
+    unsafe fn viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.gl.viewport(x, y, width, height)
+    }
+
+    unsafe fn get_uniform_location(
+        &self,
+        program: ProgramId,
+        name: &str,
+    ) -> Option<UniformLocationId> {
+        let mut cache = self.uniform_location_cache.borrow_mut();
+        let locations = cache.entry(program).or_default();
+        if let Some(location) = locations.get(name) {
+            return *location;
+        }
+        let location = self
+            .gl
+            .get_uniform_location(self.programs.borrow().get(program.0), name)
+            .map(|l| UniformLocationId(self.uniform_locations.borrow_mut().insert(l)));
+        locations.insert(name.to_owned(), location);
+        location
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     core::{color::Color, math::Rect, scope_profile, visitor::prelude::*},
     renderer::framework::{
+        backend::FramebufferId,
         error::FrameworkError,
         geometry_buffer::{DrawCallStatistics, GeometryBuffer},
         gpu_program::{GpuProgram, GpuProgramBinding},
@@ -8,7 +9,6 @@ use crate::{
         state::{BlendFunc, ColorMask, PipelineState, StencilFunc, StencilOp},
     },
 };
-use glow::HasContext;
 use serde::Deserialize;
 use std::{cell::RefCell, rc::Rc};
 
@@ -26,7 +26,7 @@ pub struct Attachment {
 
 pub struct FrameBuffer {
     state: *mut PipelineState,
-    fbo: Option<glow::Framebuffer>,
+    fbo: Option<FramebufferId>,
     depth_attachment: Option<Attachment>,
     color_attachments: Vec<Attachment>,
 }
@@ -208,8 +208,31 @@ impl FrameBuffer {
         self
     }
 
+    /// Reads RGBA8 pixels of the first color attachment (or of the back buffer) in the given
+    /// rectangle. Rows are returned as OpenGL stores them - in bottom-to-top order.
+    pub fn read_pixels(&self, state: &mut PipelineState, rect: Rect<i32>) -> Vec<u8> {
+        let row_size = rect.w() as usize * 4;
+        let mut pixels = vec![0; row_size * rect.h() as usize];
+
+        unsafe {
+            state.set_framebuffer(self.fbo);
+            state.gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+            state.gl.read_pixels(
+                rect.x(),
+                rect.y(),
+                rect.w(),
+                rect.h(),
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                &mut pixels,
+            );
+        }
+
+        pixels
+    }
+
     /// None is possible only for back buffer.
-    pub fn id(&self) -> Option<glow::Framebuffer> {
+    pub fn id(&self) -> Option<FramebufferId> {
         self.fbo
     }
 
@@ -378,7 +401,7 @@ impl FrameBuffer {
 }
 
 fn pre_draw<F: FnOnce(GpuProgramBinding<'_>)>(
-    fbo: Option<glow::Framebuffer>,
+    fbo: Option<FramebufferId>,
     state: &mut PipelineState,
     viewport: Rect<i32>,
     program: &GpuProgram,
@@ -1,15 +1,18 @@
 use crate::{
     core::{math::TriangleDefinition, scope_profile},
-    renderer::framework::{error::FrameworkError, state::PipelineState},
+    renderer::framework::{
+        backend::{BufferId, VertexArrayId},
+        error::FrameworkError,
+        state::PipelineState,
+    },
     scene::mesh::buffer::{VertexAttributeDataType, VertexBuffer},
     utils::array_as_u8_slice,
 };
-use glow::HasContext;
 use std::{cell::Cell, marker::PhantomData, mem::size_of};
 
 struct NativeBuffer {
     state: *mut PipelineState,
-    id: glow::Buffer,
+    id: BufferId,
     kind: GeometryBufferKind,
     element_size: usize,
     size_bytes: usize,
@@ -27,9 +30,9 @@ impl Drop for NativeBuffer {
 
 pub struct GeometryBuffer {
     state: *mut PipelineState,
-    vertex_array_object: glow::VertexArray,
+    vertex_array_object: VertexArrayId,
     buffers: Vec<NativeBuffer>,
-    element_buffer_object: glow::Buffer,
+    element_buffer_object: BufferId,
     element_count: Cell<usize>,
     element_kind: ElementKind,
     // Force compiler to not implement Send and Sync, because OpenGL is not thread-safe.
@@ -171,6 +174,7 @@ pub struct GeometryBufferBinding<'a> {
 #[derive(Copy, Clone, Default)]
 pub struct DrawCallStatistics {
     pub triangles: usize,
+    pub instances: usize,
 }
 
 impl<'a> GeometryBufferBinding<'a> {
@@ -230,7 +234,10 @@ impl<'a> GeometryBufferBinding<'a> {
                 self.draw_internal(start_index, index_count);
             }
 
-            Ok(DrawCallStatistics { triangles: count })
+            Ok(DrawCallStatistics {
+                triangles: count,
+                instances: 1,
+            })
         }
     }
 
@@ -252,6 +259,7 @@ impl<'a> GeometryBufferBinding<'a> {
 
         DrawCallStatistics {
             triangles: self.buffer.element_count.get(),
+            instances: 1,
         }
     }
 
@@ -285,6 +293,7 @@ impl<'a> GeometryBufferBinding<'a> {
         }
         DrawCallStatistics {
             triangles: self.buffer.element_count.get() * count,
+            instances: count,
         }
     }
 }
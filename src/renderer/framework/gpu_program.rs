@@ -4,22 +4,26 @@ use crate::{
         algebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4},
         color::Color,
     },
-    renderer::framework::{error::FrameworkError, gpu_texture::GpuTexture, state::PipelineState},
+    renderer::framework::{
+        backend::{ProgramId, ShaderId, UniformLocationId},
+        error::FrameworkError,
+        gpu_texture::GpuTexture,
+        state::PipelineState,
+    },
     utils::log::{Log, MessageKind},
 };
-use glow::HasContext;
 use std::{cell::RefCell, marker::PhantomData, rc::Rc};
 
 pub struct GpuProgram {
     state: *mut PipelineState,
-    id: glow::Program,
+    id: ProgramId,
     // Force compiler to not implement Send and Sync, because OpenGL is not thread-safe.
     thread_mark: PhantomData<*const u8>,
 }
 
 #[derive(Clone, Debug)]
 pub struct UniformLocation {
-    id: glow::UniformLocation,
+    id: UniformLocationId,
     // Force compiler to not implement Send and Sync, because OpenGL is not thread-safe.
     thread_mark: PhantomData<*const u8>,
 }
@@ -29,7 +33,7 @@ unsafe fn create_shader(
     name: String,
     actual_type: u32,
     source: &str,
-) -> Result<glow::Shader, FrameworkError> {
+) -> Result<ShaderId, FrameworkError> {
     let merged_source = prepare_source_code(source);
 
     let shader = state.gl.create_shader(actual_type)?;
@@ -88,7 +92,7 @@ fn prepare_source_code(code: &str) -> String {
 pub struct GpuProgramBinding<'a> {
     pub state: &'a mut PipelineState,
     active_sampler: u32,
-    id: glow::Program,
+    id: ProgramId,
 }
 
 impl<'a> GpuProgramBinding<'a> {
@@ -1,12 +1,12 @@
 use crate::{
     core::color::Color,
-    renderer::framework::{error::FrameworkError, state::PipelineState},
+    renderer::framework::{backend::TextureId, error::FrameworkError, state::PipelineState},
     resource::texture::{
         TextureKind, TextureMagnificationFilter, TextureMinificationFilter, TexturePixelKind,
         TextureWrapMode,
     },
 };
-use glow::{HasContext, COMPRESSED_RED_RGTC1, COMPRESSED_RG_RGTC2};
+use glow::{COMPRESSED_RED_RGTC1, COMPRESSED_RG_RGTC2};
 use std::marker::PhantomData;
 
 #[derive(Copy, Clone)]
@@ -228,7 +228,7 @@ impl PixelKind {
 
 pub struct GpuTexture {
     state: *mut PipelineState,
-    texture: glow::Texture,
+    texture: TextureId,
     kind: GpuTextureKind,
     min_filter: MinificationFilter,
     mag_filter: MagnificationFilter,
@@ -961,7 +961,7 @@ impl GpuTexture {
         self.kind
     }
 
-    pub fn id(&self) -> glow::Texture {
+    pub fn id(&self) -> TextureId {
         self.texture
     }
 
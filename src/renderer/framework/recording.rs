@@ -0,0 +1,806 @@
+//! Graphics backend that does not talk to a GPU at all, but records issued commands instead.
+//!
+//! It is intended for testing - the renderer can be created with [`RecordingBackend`] and
+//! tests can then inspect what a frame would have drawn: draw calls with their programs,
+//! framebuffers, instance counts and uniform values, as well as pipeline state changes.
+//!
+//! Resource creation and uploads always succeed and are not recorded, shaders are "compiled"
+//! instantly and every framebuffer is complete. Uniform locations are resolved only for
+//! uniforms that are declared in the source code of the program, just like a driver does.
+
+use crate::renderer::framework::backend::{
+    BufferId, FramebufferId, GraphicsBackend, ProgramId, ShaderId, TextureId, UniformLocationId,
+    VertexArrayId,
+};
+use std::{
+    cell::{Cell, Ref, RefCell},
+    collections::HashMap,
+    num::NonZeroU32,
+    rc::Rc,
+};
+
+/// Value of a uniform as it was passed to the backend. Matrices are stored as flat arrays
+/// of floats in column-major order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UniformValue {
+    I32(Vec<i32>),
+    U32(Vec<u32>),
+    F32(Vec<f32>),
+}
+
+/// Single recorded draw call.
+#[derive(Clone, Debug)]
+pub struct DrawCall {
+    /// Program that was used to draw.
+    pub program: Option<ProgramId>,
+    /// Framebuffer the draw call was rendering into, `None` means back buffer.
+    pub framebuffer: Option<FramebufferId>,
+    /// Vertex array object with geometry.
+    pub vertex_array: Option<VertexArrayId>,
+    /// Primitive mode (`glow::TRIANGLES`, `glow::LINES`, etc).
+    pub mode: u32,
+    /// Amount of indices drawn per instance.
+    pub index_count: usize,
+    /// Offset in bytes in the index buffer.
+    pub offset: usize,
+    /// Amount of instances, 1 for non-instanced draw calls.
+    pub instance_count: usize,
+    /// Values of all uniforms of the program at the moment of the draw call.
+    pub uniforms: HashMap<String, UniformValue>,
+}
+
+impl DrawCall {
+    /// Returns value of a uniform with given name at the moment of the draw call.
+    pub fn uniform(&self, name: &str) -> Option<&UniformValue> {
+        self.uniforms.get(name)
+    }
+}
+
+/// Command recorded by [`RecordingBackend`].
+#[derive(Clone, Debug)]
+pub enum Command {
+    UseProgram(Option<ProgramId>),
+    SetUniform {
+        program: ProgramId,
+        name: String,
+        value: UniformValue,
+    },
+    BindFramebuffer(Option<FramebufferId>),
+    BindTexture {
+        unit: u32,
+        target: u32,
+        texture: Option<TextureId>,
+    },
+    Enable(u32),
+    Disable(u32),
+    DepthMask(bool),
+    DepthFunc(u32),
+    ColorMask([bool; 4]),
+    CullFace(u32),
+    BlendFunc {
+        src: u32,
+        dst: u32,
+    },
+    StencilFunc {
+        func: u32,
+        reference: i32,
+        mask: u32,
+    },
+    StencilOp {
+        stencil_fail: u32,
+        depth_fail: u32,
+        pass: u32,
+    },
+    StencilMask(u32),
+    Viewport {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    Scissor {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    Clear {
+        framebuffer: Option<FramebufferId>,
+        mask: u32,
+    },
+    ClearBuffer {
+        framebuffer: Option<FramebufferId>,
+        buffer: u32,
+        draw_buffer: u32,
+    },
+    BlitFramebuffer {
+        mask: u32,
+    },
+    Draw(DrawCall),
+}
+
+/// Shared list of recorded commands. Cloning the log is cheap, all clones refer to the
+/// same list, so the log can be inspected after the backend was moved into the renderer.
+#[derive(Clone, Default, Debug)]
+pub struct CommandLog(Rc<RefCell<Vec<Command>>>);
+
+impl CommandLog {
+    /// Returns all commands recorded so far.
+    pub fn commands(&self) -> Ref<'_, Vec<Command>> {
+        self.0.borrow()
+    }
+
+    /// Returns all draw calls recorded so far.
+    pub fn draw_calls(&self) -> Vec<DrawCall> {
+        self.0
+            .borrow()
+            .iter()
+            .filter_map(|command| match command {
+                Command::Draw(draw_call) => Some(draw_call.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Removes every recorded command, it is useful to inspect frames one by one.
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+}
+
+#[derive(Default)]
+struct ProgramInfo {
+    sources: Vec<String>,
+    uniforms: HashMap<String, UniformValue>,
+}
+
+#[derive(Default)]
+struct RecorderState {
+    shaders: HashMap<ShaderId, String>,
+    programs: HashMap<ProgramId, ProgramInfo>,
+    uniform_locations: HashMap<UniformLocationId, (ProgramId, String)>,
+    program: Option<ProgramId>,
+    framebuffer: Option<FramebufferId>,
+    vertex_array: Option<VertexArrayId>,
+    active_texture: u32,
+}
+
+/// See module docs.
+pub struct RecordingBackend {
+    log: CommandLog,
+    state: RefCell<RecorderState>,
+    id_counter: Cell<u32>,
+}
+
+impl Default for RecordingBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordingBackend {
+    /// Creates new recording backend with empty command log.
+    pub fn new() -> Self {
+        Self {
+            log: Default::default(),
+            state: Default::default(),
+            id_counter: Cell::new(0),
+        }
+    }
+
+    /// Returns shared command log of the backend.
+    pub fn log(&self) -> CommandLog {
+        self.log.clone()
+    }
+
+    fn next_id(&self) -> NonZeroU32 {
+        let id = self.id_counter.get() + 1;
+        self.id_counter.set(id);
+        NonZeroU32::new(id).unwrap()
+    }
+
+    fn record(&self, command: Command) {
+        self.log.0.borrow_mut().push(command);
+    }
+
+    fn record_clear_buffer(&self, buffer: u32, draw_buffer: u32) {
+        let framebuffer = self.state.borrow().framebuffer;
+        self.record(Command::ClearBuffer {
+            framebuffer,
+            buffer,
+            draw_buffer,
+        });
+    }
+
+    fn record_draw(&self, mode: u32, count: i32, offset: i32, instance_count: i32) {
+        let state = self.state.borrow();
+        let uniforms = state
+            .program
+            .and_then(|program| state.programs.get(&program))
+            .map(|program| program.uniforms.clone())
+            .unwrap_or_default();
+        self.record(Command::Draw(DrawCall {
+            program: state.program,
+            framebuffer: state.framebuffer,
+            vertex_array: state.vertex_array,
+            mode,
+            index_count: count as usize,
+            offset: offset as usize,
+            instance_count: instance_count as usize,
+            uniforms,
+        }));
+    }
+
+    fn set_uniform(&self, location: Option<&UniformLocationId>, value: UniformValue) {
+        let mut state = self.state.borrow_mut();
+        if let Some((program, name)) = location.and_then(|l| state.uniform_locations.get(l)) {
+            let (program, name) = (*program, name.clone());
+            if let Some(info) = state.programs.get_mut(&program) {
+                info.uniforms.insert(name.clone(), value.clone());
+            }
+            drop(state);
+            self.record(Command::SetUniform {
+                program,
+                name,
+                value,
+            });
+        }
+    }
+}
+
+/// Returns names of uniforms declared in given source code. Arrays are reported by their
+/// name and by the name of their first element (`name` and `name[0]`).
+fn declared_uniforms(source: &str) -> impl Iterator<Item = String> + '_ {
+    source
+        .split(';')
+        .filter_map(|statement| {
+            let mut tokens = statement.split_whitespace().skip_while(|t| *t != "uniform");
+            tokens.next()?;
+            // Skip precision qualifiers.
+            let mut tokens = tokens.skip_while(|t| matches!(*t, "lowp" | "mediump" | "highp"));
+            tokens.next()?;
+            let name = tokens.next()?;
+            Some(match name.find('[') {
+                Some(bracket) => vec![
+                    name[..bracket].to_owned(),
+                    format!("{}[0]", &name[..bracket]),
+                ],
+                None => vec![name.to_owned()],
+            })
+        })
+        .flatten()
+}
+
+impl GraphicsBackend for RecordingBackend {
+    unsafe fn active_texture(&self, unit: u32) {
+        self.state.borrow_mut().active_texture = unit - glow::TEXTURE0;
+    }
+
+    unsafe fn attach_shader(&self, program: ProgramId, shader: ShaderId) {
+        let mut state = self.state.borrow_mut();
+        if let Some(source) = state.shaders.get(&shader).cloned() {
+            if let Some(program) = state.programs.get_mut(&program) {
+                program.sources.push(source);
+            }
+        }
+    }
+
+    unsafe fn bind_framebuffer(&self, _target: u32, framebuffer: Option<FramebufferId>) {
+        self.state.borrow_mut().framebuffer = framebuffer;
+        self.record(Command::BindFramebuffer(framebuffer));
+    }
+
+    unsafe fn bind_texture(&self, target: u32, texture: Option<TextureId>) {
+        let unit = self.state.borrow().active_texture;
+        self.record(Command::BindTexture {
+            unit,
+            target,
+            texture,
+        });
+    }
+
+    unsafe fn bind_vertex_array(&self, vertex_array: Option<VertexArrayId>) {
+        self.state.borrow_mut().vertex_array = vertex_array;
+    }
+
+    unsafe fn blend_func(&self, src: u32, dst: u32) {
+        self.record(Command::BlendFunc { src, dst });
+    }
+
+    unsafe fn blit_framebuffer(
+        &self,
+        _src_x0: i32,
+        _src_y0: i32,
+        _src_x1: i32,
+        _src_y1: i32,
+        _dst_x0: i32,
+        _dst_y0: i32,
+        _dst_x1: i32,
+        _dst_y1: i32,
+        mask: u32,
+        _filter: u32,
+    ) {
+        self.record(Command::BlitFramebuffer { mask });
+    }
+
+    unsafe fn check_framebuffer_status(&self, _target: u32) -> u32 {
+        glow::FRAMEBUFFER_COMPLETE
+    }
+
+    unsafe fn clear(&self, mask: u32) {
+        let framebuffer = self.state.borrow().framebuffer;
+        self.record(Command::Clear { framebuffer, mask });
+    }
+
+    unsafe fn clear_buffer_depth_stencil(
+        &self,
+        target: u32,
+        draw_buffer: u32,
+        _depth: f32,
+        _stencil: i32,
+    ) {
+        self.record_clear_buffer(target, draw_buffer);
+    }
+
+    unsafe fn clear_buffer_f32_slice(&self, target: u32, draw_buffer: u32, _values: &[f32]) {
+        self.record_clear_buffer(target, draw_buffer);
+    }
+
+    unsafe fn clear_buffer_i32_slice(&self, target: u32, draw_buffer: u32, _values: &[i32]) {
+        self.record_clear_buffer(target, draw_buffer);
+    }
+
+    unsafe fn clear_buffer_u32_slice(&self, target: u32, draw_buffer: u32, _values: &[u32]) {
+        self.record_clear_buffer(target, draw_buffer);
+    }
+
+    unsafe fn color_mask(&self, red: bool, green: bool, blue: bool, alpha: bool) {
+        self.record(Command::ColorMask([red, green, blue, alpha]));
+    }
+
+    unsafe fn create_buffer(&self) -> Result<BufferId, String> {
+        Ok(BufferId(self.next_id()))
+    }
+
+    unsafe fn create_framebuffer(&self) -> Result<FramebufferId, String> {
+        Ok(FramebufferId(self.next_id()))
+    }
+
+    unsafe fn create_program(&self) -> Result<ProgramId, String> {
+        let id = ProgramId(self.next_id());
+        self.state
+            .borrow_mut()
+            .programs
+            .insert(id, Default::default());
+        Ok(id)
+    }
+
+    unsafe fn create_shader(&self, _shader_type: u32) -> Result<ShaderId, String> {
+        let id = ShaderId(self.next_id());
+        self.state
+            .borrow_mut()
+            .shaders
+            .insert(id, Default::default());
+        Ok(id)
+    }
+
+    unsafe fn create_texture(&self) -> Result<TextureId, String> {
+        Ok(TextureId(self.next_id()))
+    }
+
+    unsafe fn create_vertex_array(&self) -> Result<VertexArrayId, String> {
+        Ok(VertexArrayId(self.next_id()))
+    }
+
+    unsafe fn cull_face(&self, value: u32) {
+        self.record(Command::CullFace(value));
+    }
+
+    unsafe fn delete_program(&self, program: ProgramId) {
+        let mut state = self.state.borrow_mut();
+        state.programs.remove(&program);
+        state.uniform_locations.retain(|_, (p, _)| *p != program);
+    }
+
+    unsafe fn delete_shader(&self, shader: ShaderId) {
+        self.state.borrow_mut().shaders.remove(&shader);
+    }
+
+    unsafe fn depth_func(&self, func: u32) {
+        self.record(Command::DepthFunc(func));
+    }
+
+    unsafe fn depth_mask(&self, value: bool) {
+        self.record(Command::DepthMask(value));
+    }
+
+    unsafe fn disable(&self, parameter: u32) {
+        self.record(Command::Disable(parameter));
+    }
+
+    unsafe fn draw_elements(&self, mode: u32, count: i32, _element_type: u32, offset: i32) {
+        self.record_draw(mode, count, offset, 1);
+    }
+
+    unsafe fn draw_elements_instanced(
+        &self,
+        mode: u32,
+        count: i32,
+        _element_type: u32,
+        offset: i32,
+        instance_count: i32,
+    ) {
+        self.record_draw(mode, count, offset, instance_count);
+    }
+
+    unsafe fn enable(&self, parameter: u32) {
+        self.record(Command::Enable(parameter));
+    }
+
+    unsafe fn get_debug_message_log(&self, _count: u32) -> Vec<glow::DebugMessageLogEntry> {
+        Vec::new()
+    }
+
+    unsafe fn get_error(&self) -> u32 {
+        glow::NO_ERROR
+    }
+
+    unsafe fn get_parameter_f32(&self, _parameter: u32) -> f32 {
+        1.0
+    }
+
+    unsafe fn get_program_info_log(&self, _program: ProgramId) -> String {
+        String::new()
+    }
+
+    unsafe fn get_program_link_status(&self, _program: ProgramId) -> bool {
+        true
+    }
+
+    unsafe fn get_shader_compile_status(&self, _shader: ShaderId) -> bool {
+        true
+    }
+
+    unsafe fn get_shader_info_log(&self, _shader: ShaderId) -> String {
+        String::new()
+    }
+
+    unsafe fn get_uniform_location(
+        &self,
+        program: ProgramId,
+        name: &str,
+    ) -> Option<UniformLocationId> {
+        let mut state = self.state.borrow_mut();
+        if let Some(location) = state
+            .uniform_locations
+            .iter()
+            .find(|(_, (p, n))| *p == program && n == name)
+            .map(|(location, _)| *location)
+        {
+            return Some(location);
+        }
+        // Mimic the driver - only uniforms declared in the shaders of the program are known.
+        let declared = state
+            .programs
+            .get(&program)?
+            .sources
+            .iter()
+            .any(|source| declared_uniforms(source).any(|uniform| uniform == name));
+        if declared {
+            let location = UniformLocationId(self.next_id());
+            state
+                .uniform_locations
+                .insert(location, (program, name.to_owned()));
+            Some(location)
+        } else {
+            None
+        }
+    }
+
+    unsafe fn scissor(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.record(Command::Scissor {
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    unsafe fn shader_source(&self, shader: ShaderId, source: &str) {
+        if let Some(shader_source) = self.state.borrow_mut().shaders.get_mut(&shader) {
+            *shader_source = source.to_owned();
+        }
+    }
+
+    unsafe fn stencil_func(&self, func: u32, reference: i32, mask: u32) {
+        self.record(Command::StencilFunc {
+            func,
+            reference,
+            mask,
+        });
+    }
+
+    unsafe fn stencil_mask(&self, mask: u32) {
+        self.record(Command::StencilMask(mask));
+    }
+
+    unsafe fn stencil_op(&self, stencil_fail: u32, depth_fail: u32, pass: u32) {
+        self.record(Command::StencilOp {
+            stencil_fail,
+            depth_fail,
+            pass,
+        });
+    }
+
+    unsafe fn uniform_1_f32(&self, location: Option<&UniformLocationId>, x: f32) {
+        self.set_uniform(location, UniformValue::F32(vec![x]));
+    }
+
+    unsafe fn uniform_1_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]) {
+        self.set_uniform(location, UniformValue::F32(v.to_vec()));
+    }
+
+    unsafe fn uniform_1_i32(&self, location: Option<&UniformLocationId>, x: i32) {
+        self.set_uniform(location, UniformValue::I32(vec![x]));
+    }
+
+    unsafe fn uniform_1_i32_slice(&self, location: Option<&UniformLocationId>, v: &[i32]) {
+        self.set_uniform(location, UniformValue::I32(v.to_vec()));
+    }
+
+    unsafe fn uniform_1_u32(&self, location: Option<&UniformLocationId>, x: u32) {
+        self.set_uniform(location, UniformValue::U32(vec![x]));
+    }
+
+    unsafe fn uniform_1_u32_slice(&self, location: Option<&UniformLocationId>, v: &[u32]) {
+        self.set_uniform(location, UniformValue::U32(v.to_vec()));
+    }
+
+    unsafe fn uniform_2_f32(&self, location: Option<&UniformLocationId>, x: f32, y: f32) {
+        self.set_uniform(location, UniformValue::F32(vec![x, y]));
+    }
+
+    unsafe fn uniform_2_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]) {
+        self.set_uniform(location, UniformValue::F32(v.to_vec()));
+    }
+
+    unsafe fn uniform_3_f32(&self, location: Option<&UniformLocationId>, x: f32, y: f32, z: f32) {
+        self.set_uniform(location, UniformValue::F32(vec![x, y, z]));
+    }
+
+    unsafe fn uniform_3_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]) {
+        self.set_uniform(location, UniformValue::F32(v.to_vec()));
+    }
+
+    unsafe fn uniform_4_f32(
+        &self,
+        location: Option<&UniformLocationId>,
+        x: f32,
+        y: f32,
+        z: f32,
+        w: f32,
+    ) {
+        self.set_uniform(location, UniformValue::F32(vec![x, y, z, w]));
+    }
+
+    unsafe fn uniform_4_f32_slice(&self, location: Option<&UniformLocationId>, v: &[f32]) {
+        self.set_uniform(location, UniformValue::F32(v.to_vec()));
+    }
+
+    unsafe fn uniform_matrix_2_f32_slice(
+        &self,
+        location: Option<&UniformLocationId>,
+        _transpose: bool,
+        v: &[f32],
+    ) {
+        self.set_uniform(location, UniformValue::F32(v.to_vec()));
+    }
+
+    unsafe fn uniform_matrix_3_f32_slice(
+        &self,
+        location: Option<&UniformLocationId>,
+        _transpose: bool,
+        v: &[f32],
+    ) {
+        self.set_uniform(location, UniformValue::F32(v.to_vec()));
+    }
+
+    unsafe fn uniform_matrix_4_f32_slice(
+        &self,
+        location: Option<&UniformLocationId>,
+        _transpose: bool,
+        v: &[f32],
+    ) {
+        self.set_uniform(location, UniformValue::F32(v.to_vec()));
+    }
+
+    unsafe fn use_program(&self, program: Option<ProgramId>) {
+        self.state.borrow_mut().program = program;
+        self.record(Command::UseProgram(program));
+    }
+
+    unsafe fn viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.record(Command::Viewport {
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    unsafe fn bind_buffer(&self, _target: u32, _buffer: Option<BufferId>) {}
+
+    unsafe fn buffer_data_u8_slice(&self, _target: u32, _data: &[u8], _usage: u32) {}
+
+    unsafe fn buffer_sub_data_u8_slice(&self, _target: u32, _offset: i32, _src_data: &[u8]) {}
+
+    unsafe fn clear_color(&self, _red: f32, _green: f32, _blue: f32, _alpha: f32) {}
+
+    unsafe fn clear_depth_f32(&self, _depth: f32) {}
+
+    unsafe fn clear_stencil(&self, _stencil: i32) {}
+
+    unsafe fn compile_shader(&self, _shader: ShaderId) {}
+
+    unsafe fn compressed_tex_image_1d(
+        &self,
+        _target: u32,
+        _level: i32,
+        _internal_format: i32,
+        _width: i32,
+        _border: i32,
+        _image_size: i32,
+        _pixels: &[u8],
+    ) {
+    }
+
+    unsafe fn compressed_tex_image_2d(
+        &self,
+        _target: u32,
+        _level: i32,
+        _internal_format: i32,
+        _width: i32,
+        _height: i32,
+        _border: i32,
+        _image_size: i32,
+        _pixels: &[u8],
+    ) {
+    }
+
+    unsafe fn compressed_tex_image_3d(
+        &self,
+        _target: u32,
+        _level: i32,
+        _internal_format: i32,
+        _width: i32,
+        _height: i32,
+        _depth: i32,
+        _border: i32,
+        _image_size: i32,
+        _pixels: &[u8],
+    ) {
+    }
+
+    unsafe fn delete_buffer(&self, _buffer: BufferId) {}
+
+    unsafe fn delete_framebuffer(&self, _framebuffer: FramebufferId) {}
+
+    unsafe fn delete_texture(&self, _texture: TextureId) {}
+
+    unsafe fn delete_vertex_array(&self, _vertex_array: VertexArrayId) {}
+
+    unsafe fn draw_buffer(&self, _buffer: u32) {}
+
+    unsafe fn draw_buffers(&self, _buffers: &[u32]) {}
+
+    unsafe fn enable_vertex_attrib_array(&self, _index: u32) {}
+
+    unsafe fn framebuffer_texture(
+        &self,
+        _target: u32,
+        _attachment: u32,
+        _texture: Option<TextureId>,
+        _level: i32,
+    ) {
+    }
+
+    unsafe fn framebuffer_texture_2d(
+        &self,
+        _target: u32,
+        _attachment: u32,
+        _texture_target: u32,
+        _texture: Option<TextureId>,
+        _level: i32,
+    ) {
+    }
+
+    unsafe fn framebuffer_texture_3d(
+        &self,
+        _target: u32,
+        _attachment: u32,
+        _texture_target: u32,
+        _texture: Option<TextureId>,
+        _level: i32,
+        _layer: i32,
+    ) {
+    }
+
+    unsafe fn generate_mipmap(&self, _target: u32) {}
+
+    unsafe fn link_program(&self, _program: ProgramId) {}
+
+    unsafe fn pixel_store_i32(&self, _parameter: u32, _value: i32) {}
+
+    unsafe fn read_pixels(
+        &self,
+        _x: i32,
+        _y: i32,
+        _width: i32,
+        _height: i32,
+        _format: u32,
+        _ty: u32,
+        _pixels: &mut [u8],
+    ) {
+    }
+
+    unsafe fn tex_image_1d(
+        &self,
+        _target: u32,
+        _level: i32,
+        _internal_format: i32,
+        _width: i32,
+        _border: i32,
+        _format: u32,
+        _ty: u32,
+        _pixels: Option<&[u8]>,
+    ) {
+    }
+
+    unsafe fn tex_image_2d(
+        &self,
+        _target: u32,
+        _level: i32,
+        _internal_format: i32,
+        _width: i32,
+        _height: i32,
+        _border: i32,
+        _format: u32,
+        _ty: u32,
+        _pixels: Option<&[u8]>,
+    ) {
+    }
+
+    unsafe fn tex_image_3d(
+        &self,
+        _target: u32,
+        _level: i32,
+        _internal_format: i32,
+        _width: i32,
+        _height: i32,
+        _depth: i32,
+        _border: i32,
+        _format: u32,
+        _ty: u32,
+        _pixels: Option<&[u8]>,
+    ) {
+    }
+
+    unsafe fn tex_parameter_f32(&self, _target: u32, _parameter: u32, _value: f32) {}
+
+    unsafe fn tex_parameter_f32_slice(&self, _target: u32, _parameter: u32, _values: &[f32]) {}
+
+    unsafe fn tex_parameter_i32(&self, _target: u32, _parameter: u32, _value: i32) {}
+
+    unsafe fn vertex_attrib_divisor(&self, _index: u32, _divisor: u32) {}
+
+    unsafe fn vertex_attrib_pointer_f32(
+        &self,
+        _index: u32,
+        _size: i32,
+        _data_type: u32,
+        _normalized: bool,
+        _stride: i32,
+        _offset: i32,
+    ) {
+    }
+}
@@ -1,9 +1,14 @@
 use crate::{
     core::{color::Color, math::Rect, visitor::prelude::*},
-    renderer::framework::framebuffer::{CullFace, DrawParameters},
+    renderer::framework::{
+        backend::{
+            BufferId, FramebufferId, GlowBackend, GraphicsBackend, ProgramId, TextureId,
+            VertexArrayId,
+        },
+        framebuffer::{CullFace, DrawParameters},
+    },
     utils::log::{Log, MessageKind},
 };
-use glow::{Framebuffer, HasContext};
 use serde::Deserialize;
 use std::fmt::{Display, Formatter};
 
@@ -118,7 +123,7 @@ impl Default for BlendFunc {
 }
 
 pub struct PipelineState {
-    pub gl: glow::Context,
+    pub gl: Box<dyn GraphicsBackend>,
 
     blend: bool,
 
@@ -136,19 +141,19 @@ pub struct PipelineState {
     clear_depth: f32,
     scissor_test: bool,
 
-    framebuffer: Option<glow::Framebuffer>,
+    framebuffer: Option<FramebufferId>,
     viewport: Rect<i32>,
 
     blend_func: BlendFunc,
 
-    program: Option<glow::Program>,
+    program: Option<ProgramId>,
     texture_units: [TextureUnit; 32],
 
     stencil_func: StencilFunc,
     stencil_op: StencilOp,
 
-    vao: Option<glow::VertexArray>,
-    vbo: Option<glow::Buffer>,
+    vao: Option<VertexArrayId>,
+    vbo: Option<BufferId>,
 
     frame_statistics: PipelineStatistics,
 }
@@ -156,7 +161,7 @@ pub struct PipelineState {
 #[derive(Copy, Clone)]
 struct TextureUnit {
     target: u32,
-    texture: Option<glow::Texture>,
+    texture: Option<TextureId>,
 }
 
 impl Default for TextureUnit {
@@ -276,12 +281,17 @@ impl Default for StencilOp {
 
 impl PipelineState {
     pub fn new(context: glow::Context) -> Self {
+        Self::with_backend(Box::new(GlowBackend::new(context)))
+    }
+
+    /// Creates new pipeline state that issues all graphics calls to the given backend.
+    pub fn with_backend(backend: Box<dyn GraphicsBackend>) -> Self {
         unsafe {
-            context.depth_func(CompareFunc::default() as u32);
+            backend.depth_func(CompareFunc::default() as u32);
         }
 
         Self {
-            gl: context,
+            gl: backend,
             blend: false,
             depth_test: false,
             depth_write: true,
@@ -308,7 +318,7 @@ impl PipelineState {
         }
     }
 
-    pub fn set_framebuffer(&mut self, framebuffer: Option<glow::Framebuffer>) {
+    pub fn set_framebuffer(&mut self, framebuffer: Option<FramebufferId>) {
         if self.framebuffer != framebuffer {
             self.framebuffer = framebuffer;
 
@@ -491,7 +501,7 @@ impl PipelineState {
         }
     }
 
-    pub fn set_program(&mut self, program: Option<glow::Program>) {
+    pub fn set_program(&mut self, program: Option<ProgramId>) {
         if self.program != program {
             self.program = program;
 
@@ -503,7 +513,7 @@ impl PipelineState {
         }
     }
 
-    pub fn set_texture(&mut self, sampler_index: u32, target: u32, texture: Option<glow::Texture>) {
+    pub fn set_texture(&mut self, sampler_index: u32, target: u32, texture: Option<TextureId>) {
         let unit = self.texture_units.get_mut(sampler_index as usize).unwrap();
 
         if unit.target != target || unit.texture != texture {
@@ -549,7 +559,7 @@ impl PipelineState {
         }
     }
 
-    pub fn set_vertex_array_object(&mut self, vao: Option<glow::VertexArray>) {
+    pub fn set_vertex_array_object(&mut self, vao: Option<VertexArrayId>) {
         if self.vao != vao {
             self.vao = vao;
 
@@ -561,7 +571,7 @@ impl PipelineState {
         }
     }
 
-    pub fn set_vertex_buffer_object(&mut self, vbo: Option<glow::Buffer>) {
+    pub fn set_vertex_buffer_object(&mut self, vbo: Option<BufferId>) {
         if self.vbo != vbo {
             self.vbo = vbo;
 
@@ -589,8 +599,8 @@ impl PipelineState {
 
     pub fn blit_framebuffer(
         &mut self,
-        source: Option<Framebuffer>,
-        dest: Option<Framebuffer>,
+        source: Option<FramebufferId>,
+        dest: Option<FramebufferId>,
         src_x0: i32,
         src_y0: i32,
         src_x1: i32,
@@ -277,6 +277,8 @@ impl GBuffer {
 
         let initial_view_projection = camera.view_projection_matrix();
 
+        let mut instance_matrices = Vec::new();
+
         for batch in batch_storage
             .batches
             .iter()
@@ -290,7 +292,61 @@ impl GBuffer {
                 .get(state, material.shader())
                 .and_then(|shader_set| shader_set.render_passes.get("GBuffer"))
             {
+                // Instances without depth offset share same view-projection matrix, so they
+                // can be drawn using single instanced draw call.
+                let use_instancing = batch.can_be_instanced() && render_pass.supports_instancing;
+
+                if use_instancing {
+                    instance_matrices.clear();
+                    instance_matrices.extend(
+                        batch
+                            .instances
+                            .iter()
+                            .filter(|i| {
+                                i.depth_offset == 0.0 && camera.visibility_cache.is_visible(i.owner)
+                            })
+                            .map(|i| i.world_transform),
+                    );
+
+                    if !instance_matrices.is_empty() {
+                        GeometryCache::set_instances(state, geometry, &instance_matrices);
+
+                        statistics += self.framebuffer.draw_instances(
+                            instance_matrices.len(),
+                            geometry,
+                            state,
+                            viewport,
+                            &render_pass.program,
+                            &render_pass.draw_params,
+                            |mut program_binding| {
+                                apply_material(MaterialContext {
+                                    material: &material,
+                                    program_binding: &mut program_binding,
+                                    texture_cache,
+                                    world_matrix: &Matrix4::identity(),
+                                    wvp_matrix: &initial_view_projection,
+                                    view_projection_matrix: &initial_view_projection,
+                                    use_instancing: true,
+                                    bone_matrices: &[],
+                                    use_skeletal_animation: false,
+                                    camera_position: &camera.global_position(),
+                                    use_pom: use_parallax_mapping,
+                                    light_position: &Default::default(),
+                                    normal_dummy: normal_dummy.clone(),
+                                    white_dummy: white_dummy.clone(),
+                                    black_dummy: black_dummy.clone(),
+                                });
+                            },
+                        );
+                    }
+                }
+
                 for instance in batch.instances.iter() {
+                    if use_instancing && instance.depth_offset == 0.0 {
+                        // Already rendered.
+                        continue;
+                    }
+
                     if camera.visibility_cache.is_visible(instance.owner) {
                         let apply_uniforms = |mut program_binding: GpuProgramBinding| {
                             let view_projection = if instance.depth_offset != 0.0 {
@@ -302,11 +358,13 @@ impl GBuffer {
                             };
 
                             apply_material(MaterialContext {
-                                material: &*material,
+                                material: &material,
                                 program_binding: &mut program_binding,
                                 texture_cache,
                                 world_matrix: &instance.world_transform,
                                 wvp_matrix: &(view_projection * instance.world_transform),
+                                view_projection_matrix: &view_projection,
+                                use_instancing: false,
                                 bone_matrices: &instance.bone_matrices,
                                 use_skeletal_animation: batch.is_skinned,
                                 camera_position: &camera.global_position(),
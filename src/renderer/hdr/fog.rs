@@ -0,0 +1,52 @@
+use crate::renderer::framework::{
+    error::FrameworkError,
+    gpu_program::{GpuProgram, UniformLocation},
+    state::PipelineState,
+};
+
+pub struct FogShader {
+    pub program: GpuProgram,
+    pub wvp_matrix: UniformLocation,
+    pub depth_sampler: UniformLocation,
+    pub inv_view_proj_matrix: UniformLocation,
+    pub camera_position: UniformLocation,
+    pub fog_color: UniformLocation,
+    pub density: UniformLocation,
+    pub height_falloff: UniformLocation,
+    pub base_height: UniformLocation,
+    pub start_distance: UniformLocation,
+    pub end_distance: UniformLocation,
+    pub max_opacity: UniformLocation,
+    pub sun_enabled: UniformLocation,
+    pub sun_direction: UniformLocation,
+    pub sun_color: UniformLocation,
+    pub sun_exponent: UniformLocation,
+}
+
+impl FogShader {
+    pub fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("../shaders/hdr_fog_fs.glsl");
+        let vertex_source = include_str!("../shaders/flat_vs.glsl");
+
+        let program = GpuProgram::from_source(state, "FogShader", vertex_source, fragment_source)?;
+
+        Ok(Self {
+            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
+            depth_sampler: program.uniform_location(state, "depthSampler")?,
+            inv_view_proj_matrix: program.uniform_location(state, "invViewProj")?,
+            camera_position: program.uniform_location(state, "cameraPosition")?,
+            fog_color: program.uniform_location(state, "fogColor")?,
+            density: program.uniform_location(state, "density")?,
+            height_falloff: program.uniform_location(state, "heightFalloff")?,
+            base_height: program.uniform_location(state, "baseHeight")?,
+            start_distance: program.uniform_location(state, "startDistance")?,
+            end_distance: program.uniform_location(state, "endDistance")?,
+            max_opacity: program.uniform_location(state, "maxOpacity")?,
+            sun_enabled: program.uniform_location(state, "sunEnabled")?,
+            sun_direction: program.uniform_location(state, "sunDirection")?,
+            sun_color: program.uniform_location(state, "sunColor")?,
+            sun_exponent: program.uniform_location(state, "sunExponent")?,
+            program,
+        })
+    }
+}
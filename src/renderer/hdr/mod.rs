@@ -13,22 +13,29 @@ use crate::{
             gpu_texture::{
                 GpuTexture, GpuTextureKind, MagnificationFilter, MinificationFilter, PixelKind,
             },
-            state::PipelineState,
+            state::{BlendFactor, BlendFunc, ColorMask, PipelineState},
         },
         hdr::{
             adaptation::{AdaptationChain, AdaptationShader},
             downscale::DownscaleShader,
+            fog::FogShader,
             luminance::LuminanceShader,
             map::MapShader,
         },
         make_viewport_matrix, RenderPassStatistics,
     },
-    scene::camera::{ColorGradingLut, Exposure},
+    scene::{
+        camera::{Camera, ColorGradingLut, Exposure},
+        graph::Graph,
+        light::Light,
+        node::Node,
+    },
 };
 use std::{cell::RefCell, rc::Rc};
 
 mod adaptation;
 mod downscale;
+mod fog;
 mod luminance;
 mod map;
 
@@ -96,6 +103,7 @@ pub struct HighDynamicRangeRenderer {
     luminance_shader: LuminanceShader,
     downscale_shader: DownscaleShader,
     map_shader: MapShader,
+    fog_shader: FogShader,
     stub_lut: Rc<RefCell<GpuTexture>>,
 }
 
@@ -116,6 +124,7 @@ impl HighDynamicRangeRenderer {
             luminance_shader: LuminanceShader::new(state)?,
             downscale_shader: DownscaleShader::new(state)?,
             map_shader: MapShader::new(state)?,
+            fog_shader: FogShader::new(state)?,
             stub_lut: Rc::new(RefCell::new(GpuTexture::new(
                 state,
                 GpuTextureKind::Volume {
@@ -315,6 +324,88 @@ impl HighDynamicRangeRenderer {
         )
     }
 
+    /// Blends fog of the camera (if any) over the high dynamic range frame. Must be called
+    /// before [`Self::render`], so fog will be tone mapped together with the rest of the frame.
+    pub fn render_fog(
+        &mut self,
+        state: &mut PipelineState,
+        hdr_scene_framebuffer: &mut FrameBuffer,
+        depth_texture: Rc<RefCell<GpuTexture>>,
+        viewport: Rect<i32>,
+        quad: &GeometryBuffer,
+        camera: &Camera,
+        graph: &Graph,
+    ) -> DrawCallStatistics {
+        let fog = match camera.fog() {
+            Some(fog) => fog,
+            None => return Default::default(),
+        };
+
+        let sun = match graph.try_get(fog.sun) {
+            Some(Node::Light(Light::Directional(sun))) if sun.global_visibility() => {
+                let direction = sun
+                    .up_vector()
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(Vector3::z);
+                let color = sun.color().srgb_to_linear_f32().xyz()
+                    * sun.intensity()
+                    * fog.sun_inscattering_intensity;
+                Some((direction, color))
+            }
+            _ => None,
+        };
+
+        let shader = &self.fog_shader;
+        let frame_matrix = make_viewport_matrix(viewport);
+        let inv_view_projection = camera
+            .view_projection_matrix()
+            .try_inverse()
+            .unwrap_or_default();
+        let camera_position = camera.global_position();
+
+        hdr_scene_framebuffer.draw(
+            quad,
+            state,
+            viewport,
+            &shader.program,
+            &DrawParameters {
+                cull_face: None,
+                // Keep alpha of the frame intact.
+                color_write: ColorMask {
+                    alpha: false,
+                    ..Default::default()
+                },
+                depth_write: false,
+                stencil_test: None,
+                depth_test: false,
+                blend: Some(BlendFunc {
+                    sfactor: BlendFactor::SrcAlpha,
+                    dfactor: BlendFactor::OneMinusSrcAlpha,
+                }),
+                stencil_op: Default::default(),
+            },
+            |mut program_binding| {
+                let (sun_direction, sun_color) = sun.unwrap_or_default();
+                program_binding
+                    .set_matrix4(&shader.wvp_matrix, &frame_matrix)
+                    .set_texture(&shader.depth_sampler, &depth_texture)
+                    .set_matrix4(&shader.inv_view_proj_matrix, &inv_view_projection)
+                    .set_vector3(&shader.camera_position, &camera_position)
+                    .set_linear_color(&shader.fog_color, &fog.color)
+                    .set_f32(&shader.density, fog.density)
+                    .set_f32(&shader.height_falloff, fog.height_falloff)
+                    .set_f32(&shader.base_height, fog.base_height)
+                    .set_f32(&shader.start_distance, fog.start_distance)
+                    .set_f32(&shader.end_distance, fog.end_distance)
+                    .set_f32(&shader.max_opacity, fog.max_opacity)
+                    .set_bool(&shader.sun_enabled, sun.is_some())
+                    .set_vector3(&shader.sun_direction, &sun_direction)
+                    .set_vector3(&shader.sun_color, &sun_color)
+                    .set_f32(&shader.sun_exponent, fog.sun_inscattering_exponent);
+            },
+        )
+    }
+
     pub fn render(
         &mut self,
         state: &mut PipelineState,
@@ -12,6 +12,7 @@ use crate::{
         framework::{
             error::FrameworkError,
             framebuffer::{CullFace, DrawParameters, FrameBuffer},
+            gpu_program::GpuProgramBinding,
             gpu_texture::GpuTexture,
             state::{
                 BlendFactor, BlendFunc, ColorMask, CompareFunc, PipelineState, StencilAction,
@@ -21,7 +22,7 @@ use crate::{
         gbuffer::GBuffer,
         light::{
             ambient::AmbientLightShader, directional::DirectionalLightShader,
-            point::PointLightShader, spot::SpotLightShader,
+            point::PointLightShader, reflection::ReflectionShader, spot::SpotLightShader,
         },
         light_volume::LightVolumeRenderer,
         shadow::{
@@ -32,6 +33,7 @@ use crate::{
         ssao::ScreenSpaceAmbientOcclusionRenderer,
         GeometryCache, QualitySettings, RenderPassStatistics, TextureCache,
     },
+    resource::texture::{Texture, TextureKind, TextureState},
     scene::{
         camera::Camera,
         light::Light,
@@ -39,8 +41,10 @@ use crate::{
             buffer::{GeometryBuffer, VertexBuffer},
             surface::SurfaceData,
             vertex::SimpleVertex,
+            RenderPath,
         },
         node::Node,
+        reflection_probe::{select_probes, ProbeSelection, MAX_PROBES_PER_SURFACE},
         Scene,
     },
 };
@@ -54,6 +58,7 @@ use std::{
 pub mod ambient;
 pub mod directional;
 pub mod point;
+pub mod reflection;
 pub mod spot;
 
 #[derive(Copy, Clone, Default)]
@@ -100,6 +105,7 @@ pub struct DeferredLightRenderer {
     point_light_shader: PointLightShader,
     directional_light_shader: DirectionalLightShader,
     ambient_light_shader: AmbientLightShader,
+    reflection_shader: ReflectionShader,
     quad: SurfaceData,
     sphere: SurfaceData,
     skybox: SurfaceData,
@@ -125,6 +131,7 @@ pub(in crate) struct DeferredRendererContext<'a> {
     pub normal_dummy: Rc<RefCell<GpuTexture>>,
     pub white_dummy: Rc<RefCell<GpuTexture>>,
     pub black_dummy: Rc<RefCell<GpuTexture>>,
+    pub environment_dummy: Rc<RefCell<GpuTexture>>,
 }
 
 impl DeferredLightRenderer {
@@ -224,6 +231,7 @@ impl DeferredLightRenderer {
             point_light_shader: PointLightShader::new(state)?,
             directional_light_shader: DirectionalLightShader::new(state)?,
             ambient_light_shader: AmbientLightShader::new(state)?,
+            reflection_shader: ReflectionShader::new(state)?,
             quad: SurfaceData::make_unit_xy_quad(),
             skybox: SurfaceData::new(
                 VertexBuffer::new(vertices.len(), SimpleVertex::layout(), vertices).unwrap(),
@@ -325,6 +333,7 @@ impl DeferredLightRenderer {
             batch_storage,
             frame_buffer,
             black_dummy,
+            environment_dummy,
         } = args;
 
         let viewport = Rect::new(0, 0, gbuffer.width, gbuffer.height);
@@ -443,6 +452,226 @@ impl DeferredLightRenderer {
             },
         );
 
+        // Reflections from environment map of the camera and reflection probes.
+        let environment = camera.environment_ref().and_then(|environment| {
+            let max_lod = cube_map_max_lod(environment)?;
+            textures
+                .get(state, environment)
+                .map(|gpu_texture| (gpu_texture, max_lod))
+        });
+
+        let probes = scene
+            .graph
+            .pair_iter()
+            .filter_map(|(handle, node)| {
+                if let Node::ReflectionProbe(probe) = node {
+                    let max_lod = cube_map_max_lod(probe.cubemap()?)?;
+                    let gpu_texture = textures.get(state, probe.cubemap()?)?;
+                    Some((handle, probe, gpu_texture, max_lod))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if environment.is_some() || !probes.is_empty() {
+            let shader = &self.reflection_shader;
+            let view_matrix = camera.view_matrix();
+            let (environment_texture, environment_max_lod) = environment
+                .clone()
+                .unwrap_or_else(|| (environment_dummy.clone(), 0.0));
+
+            let probe_uniforms = |selection: &ProbeSelection| {
+                let mut uniforms = ProbeUniforms {
+                    textures: [environment_dummy.clone(), environment_dummy.clone()],
+                    position: Default::default(),
+                    box_min: Default::default(),
+                    box_max: Default::default(),
+                    box_projection: Default::default(),
+                    weight: Default::default(),
+                    max_lod: Default::default(),
+                    environment_weight: if environment.is_some() {
+                        selection.environment_weight
+                    } else {
+                        0.0
+                    },
+                };
+                for (i, contribution) in selection.probes.iter().enumerate() {
+                    let (_, probe, gpu_texture, max_lod) = probes
+                        .iter()
+                        .find(|(handle, _, _, _)| *handle == contribution.probe)
+                        .unwrap();
+                    let bounds = probe.projection_bounds();
+                    uniforms.textures[i] = gpu_texture.clone();
+                    uniforms.position[i] = probe.global_position();
+                    uniforms.box_min[i] = bounds.min;
+                    uniforms.box_max[i] = bounds.max;
+                    uniforms.box_projection[i] = probe.is_box_projection() as i32;
+                    uniforms.weight[i] = contribution.weight * probe.intensity();
+                    uniforms.max_lod[i] = *max_lod;
+                }
+                uniforms
+            };
+
+            let apply_uniforms = |program_binding: &mut GpuProgramBinding<'_>,
+                                  uniforms: &ProbeUniforms| {
+                program_binding
+                    .set_texture(&shader.depth_sampler, &gbuffer_depth_map)
+                    .set_texture(&shader.color_sampler, &gbuffer_diffuse_map)
+                    .set_texture(&shader.normal_sampler, &gbuffer_normal_map)
+                    .set_texture(&shader.material_sampler, &gbuffer_material_map)
+                    .set_texture(
+                        &shader.ao_sampler,
+                        if settings.use_ssao {
+                            &ao_map
+                        } else {
+                            &white_dummy
+                        },
+                    )
+                    .set_texture(&shader.environment_texture, &environment_texture)
+                    .set_f32(&shader.environment_weight, uniforms.environment_weight)
+                    .set_f32(&shader.environment_max_lod, environment_max_lod)
+                    .set_texture(&shader.first_probe_texture, &uniforms.textures[0])
+                    .set_texture(&shader.second_probe_texture, &uniforms.textures[1])
+                    .set_vector3_slice(&shader.probe_position, &uniforms.position)
+                    .set_vector3_slice(&shader.probe_box_min, &uniforms.box_min)
+                    .set_vector3_slice(&shader.probe_box_max, &uniforms.box_max)
+                    .set_i32_slice(&shader.probe_box_projection, &uniforms.box_projection)
+                    .set_f32_slice(&shader.probe_weight, &uniforms.weight)
+                    .set_f32_slice(&shader.probe_max_lod, &uniforms.max_lod)
+                    .set_matrix4(&shader.inv_view_proj_matrix, &inv_view_projection)
+                    .set_vector3(&shader.camera_position, &camera_global_position);
+            };
+
+            let draw_params = DrawParameters {
+                cull_face: None,
+                color_write: Default::default(),
+                depth_write: false,
+                stencil_test: None,
+                depth_test: false,
+                blend: Some(BlendFunc {
+                    sfactor: BlendFactor::One,
+                    dfactor: BlendFactor::One,
+                }),
+                stencil_op: Default::default(),
+            };
+
+            let mut grouped_instances = Vec::new();
+            let mut instance_matrices = Vec::new();
+
+            for batch in batch_storage
+                .batches
+                .iter()
+                .filter(|b| b.render_path == RenderPath::Deferred)
+            {
+                let data = batch.data.read().unwrap();
+                let geometry = geometry_cache.get(state, &data);
+
+                grouped_instances.clear();
+
+                for instance in batch.instances.iter() {
+                    if !camera.visibility_cache.is_visible(instance.owner) {
+                        continue;
+                    }
+
+                    let selection = if probes.is_empty() {
+                        ProbeSelection::default()
+                    } else {
+                        select_probes(
+                            probes.iter().map(|(handle, probe, _, _)| (*handle, *probe)),
+                            scene.graph[instance.owner].global_position(),
+                            settings.reflection_probe_blend_mode,
+                        )
+                    };
+
+                    if selection.probes.is_empty() && environment.is_none() {
+                        continue;
+                    }
+
+                    // Instances that share the same selection of probes are drawn using single
+                    // instanced draw call below.
+                    if batch.can_be_instanced() && instance.depth_offset == 0.0 {
+                        grouped_instances.push((
+                            selection_key(&selection),
+                            selection,
+                            instance.world_transform,
+                        ));
+                        continue;
+                    }
+
+                    let view_projection = if instance.depth_offset != 0.0 {
+                        let mut projection = camera.projection_matrix();
+                        projection[14] -= instance.depth_offset;
+                        projection * view_matrix
+                    } else {
+                        view_projection
+                    };
+
+                    let uniforms = probe_uniforms(&selection);
+
+                    pass_stats += frame_buffer.draw(
+                        geometry,
+                        state,
+                        viewport,
+                        &shader.program,
+                        &draw_params,
+                        |mut program_binding| {
+                            apply_uniforms(&mut program_binding, &uniforms);
+                            program_binding
+                                .set_matrix4(
+                                    &shader.wvp_matrix,
+                                    &(view_projection * instance.world_transform),
+                                )
+                                .set_matrix4_array(&shader.bone_matrices, &instance.bone_matrices)
+                                .set_bool(&shader.use_skeletal_animation, batch.is_skinned)
+                                .set_bool(&shader.use_instancing, false);
+                        },
+                    );
+                }
+
+                grouped_instances.sort_by_key(|(key, _, _)| *key);
+
+                let mut start = 0;
+                while start < grouped_instances.len() {
+                    let key = grouped_instances[start].0;
+                    let end = start
+                        + grouped_instances[start..]
+                            .iter()
+                            .take_while(|(other, _, _)| *other == key)
+                            .count();
+
+                    instance_matrices.clear();
+                    instance_matrices.extend(
+                        grouped_instances[start..end]
+                            .iter()
+                            .map(|(_, _, world_transform)| *world_transform),
+                    );
+
+                    let uniforms = probe_uniforms(&grouped_instances[start].1);
+
+                    GeometryCache::set_instances(state, geometry, &instance_matrices);
+
+                    pass_stats += frame_buffer.draw_instances(
+                        instance_matrices.len(),
+                        geometry,
+                        state,
+                        viewport,
+                        &shader.program,
+                        &draw_params,
+                        |mut program_binding| {
+                            apply_uniforms(&mut program_binding, &uniforms);
+                            program_binding
+                                .set_matrix4(&shader.wvp_matrix, &view_projection)
+                                .set_bool(&shader.use_skeletal_animation, false)
+                                .set_bool(&shader.use_instancing, true);
+                        },
+                    );
+
+                    start = end;
+                }
+            }
+        }
+
         for (light_handle, light) in scene.graph.pair_iter().filter_map(|(handle, node)| {
             if let Node::Light(light) = node {
                 Some((handle, light))
@@ -816,3 +1045,37 @@ impl DeferredLightRenderer {
         (pass_stats, light_stats)
     }
 }
+
+// Uniforms of reflection probes that affect a surface.
+struct ProbeUniforms {
+    textures: [Rc<RefCell<GpuTexture>>; MAX_PROBES_PER_SURFACE],
+    position: [Vector3<f32>; MAX_PROBES_PER_SURFACE],
+    box_min: [Vector3<f32>; MAX_PROBES_PER_SURFACE],
+    box_max: [Vector3<f32>; MAX_PROBES_PER_SURFACE],
+    box_projection: [i32; MAX_PROBES_PER_SURFACE],
+    weight: [f32; MAX_PROBES_PER_SURFACE],
+    max_lod: [f32; MAX_PROBES_PER_SURFACE],
+    environment_weight: f32,
+}
+
+// Makes a key that is equal for equal selections of probes, so instances could be grouped by
+// their selections.
+fn selection_key(selection: &ProbeSelection) -> [u32; 2 * MAX_PROBES_PER_SURFACE + 1] {
+    let mut key = [u32::MAX; 2 * MAX_PROBES_PER_SURFACE + 1];
+    for (i, contribution) in selection.probes.iter().enumerate() {
+        key[2 * i] = contribution.probe.index();
+        key[2 * i + 1] = contribution.weight.to_bits();
+    }
+    key[2 * MAX_PROBES_PER_SURFACE] = selection.environment_weight.to_bits();
+    key
+}
+
+/// Returns maximum level of detail of a cube map, or `None` if the texture is not a loaded cube map.
+fn cube_map_max_lod(texture: &Texture) -> Option<f32> {
+    if let TextureState::Ok(data) = &*texture.state() {
+        if let TextureKind::Cube { width, .. } = data.kind() {
+            return Some((width.max(1) as f32).log2());
+        }
+    }
+    None
+}
@@ -0,0 +1,65 @@
+use crate::renderer::framework::{
+    error::FrameworkError,
+    gpu_program::{GpuProgram, UniformLocation},
+    state::PipelineState,
+};
+
+pub struct ReflectionShader {
+    pub program: GpuProgram,
+    pub wvp_matrix: UniformLocation,
+    pub bone_matrices: UniformLocation,
+    pub use_skeletal_animation: UniformLocation,
+    pub use_instancing: UniformLocation,
+    pub depth_sampler: UniformLocation,
+    pub color_sampler: UniformLocation,
+    pub normal_sampler: UniformLocation,
+    pub material_sampler: UniformLocation,
+    pub ao_sampler: UniformLocation,
+    pub environment_texture: UniformLocation,
+    pub environment_weight: UniformLocation,
+    pub environment_max_lod: UniformLocation,
+    pub first_probe_texture: UniformLocation,
+    pub second_probe_texture: UniformLocation,
+    pub probe_position: UniformLocation,
+    pub probe_box_min: UniformLocation,
+    pub probe_box_max: UniformLocation,
+    pub probe_box_projection: UniformLocation,
+    pub probe_weight: UniformLocation,
+    pub probe_max_lod: UniformLocation,
+    pub inv_view_proj_matrix: UniformLocation,
+    pub camera_position: UniformLocation,
+}
+
+impl ReflectionShader {
+    pub fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("../shaders/reflection_fs.glsl");
+        let vertex_source = include_str!("../shaders/reflection_vs.glsl");
+        let program =
+            GpuProgram::from_source(state, "ReflectionShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
+            bone_matrices: program.uniform_location(state, "boneMatrices")?,
+            use_skeletal_animation: program.uniform_location(state, "useSkeletalAnimation")?,
+            use_instancing: program.uniform_location(state, "useInstancing")?,
+            depth_sampler: program.uniform_location(state, "depthTexture")?,
+            color_sampler: program.uniform_location(state, "colorTexture")?,
+            normal_sampler: program.uniform_location(state, "normalTexture")?,
+            material_sampler: program.uniform_location(state, "materialTexture")?,
+            ao_sampler: program.uniform_location(state, "aoSampler")?,
+            environment_texture: program.uniform_location(state, "environmentTexture")?,
+            environment_weight: program.uniform_location(state, "environmentWeight")?,
+            environment_max_lod: program.uniform_location(state, "environmentMaxLod")?,
+            first_probe_texture: program.uniform_location(state, "firstProbeTexture")?,
+            second_probe_texture: program.uniform_location(state, "secondProbeTexture")?,
+            probe_position: program.uniform_location(state, "probePosition")?,
+            probe_box_min: program.uniform_location(state, "probeBoxMin")?,
+            probe_box_max: program.uniform_location(state, "probeBoxMax")?,
+            probe_box_projection: program.uniform_location(state, "probeBoxProjection")?,
+            probe_weight: program.uniform_location(state, "probeWeight")?,
+            probe_max_lod: program.uniform_location(state, "probeMaxLod")?,
+            inv_view_proj_matrix: program.uniform_location(state, "invViewProj")?,
+            camera_position: program.uniform_location(state, "cameraPosition")?,
+            program,
+        })
+    }
+}
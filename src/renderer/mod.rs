@@ -38,7 +38,7 @@ use crate::{
         algebra::{Matrix4, Vector2, Vector3},
         color::Color,
         instant,
-        math::Rect,
+        math::{frustum::Frustum, Rect},
         pool::Handle,
         scope_profile,
     },
@@ -53,6 +53,7 @@ use crate::{
         flat_shader::FlatShader,
         forward_renderer::{ForwardRenderContext, ForwardRenderer},
         framework::{
+            backend::{GlowBackend, GraphicsBackend},
             error::FrameworkError,
             framebuffer::{Attachment, AttachmentKind, DrawParameters, FrameBuffer},
             geometry_buffer::{DrawCallStatistics, GeometryBuffer},
@@ -72,8 +73,15 @@ use crate::{
         sprite_renderer::{SpriteRenderContext, SpriteRenderer},
         ui_renderer::{UiRenderContext, UiRenderer},
     },
-    resource::texture::{Texture, TextureKind},
-    scene::{camera::Camera, mesh::surface::SurfaceData, node::Node, Scene, SceneContainer},
+    resource::texture::{Texture, TextureKind, TexturePixelKind},
+    scene::{
+        base::BaseBuilder,
+        camera::{Camera, CameraBuilder},
+        mesh::surface::SurfaceData,
+        node::Node,
+        reflection_probe::ReflectionProbeBlendMode,
+        Scene, SceneContainer,
+    },
     scene2d::Scene2dContainer,
 };
 use serde::{Deserialize, Serialize};
@@ -138,6 +146,9 @@ pub struct RenderPassStatistics {
     pub draw_calls: usize,
     /// Amount of triangles per frame.
     pub triangles_rendered: usize,
+    /// Amount of instances rendered per frame. Instanced draw calls renders many instances
+    /// at once, so this value can be much bigger than amount of draw calls.
+    pub instances_rendered: usize,
 }
 
 impl Display for RenderPassStatistics {
@@ -145,8 +156,9 @@ impl Display for RenderPassStatistics {
         write!(
             f,
             "Draw Calls: {}\n\
+            Instances Rendered: {}\n\
             Triangles Rendered: {}",
-            self.draw_calls, self.triangles_rendered
+            self.draw_calls, self.instances_rendered, self.triangles_rendered
         )
     }
 }
@@ -156,6 +168,7 @@ impl Default for RenderPassStatistics {
         Self {
             draw_calls: 0,
             triangles_rendered: 0,
+            instances_rendered: 0,
         }
     }
 }
@@ -164,6 +177,7 @@ impl std::ops::AddAssign for RenderPassStatistics {
     fn add_assign(&mut self, rhs: Self) {
         self.draw_calls += rhs.draw_calls;
         self.triangles_rendered += rhs.triangles_rendered;
+        self.instances_rendered += rhs.instances_rendered;
     }
 }
 
@@ -171,6 +185,7 @@ impl std::ops::AddAssign<DrawCallStatistics> for RenderPassStatistics {
     fn add_assign(&mut self, rhs: DrawCallStatistics) {
         self.draw_calls += 1;
         self.triangles_rendered += rhs.triangles;
+        self.instances_rendered += rhs.instances;
     }
 }
 
@@ -239,6 +254,10 @@ pub struct QualitySettings {
 
     /// Whether to use bloom effect.
     pub use_bloom: bool,
+
+    /// Defines how many reflection probes could affect a single surface.
+    #[serde(default)]
+    pub reflection_probe_blend_mode: ReflectionProbeBlendMode,
 }
 
 impl Default for QualitySettings {
@@ -274,6 +293,8 @@ impl QualitySettings {
             use_bloom: true,
 
             use_parallax_mapping: false, // TODO: Enable when it is fixed!
+
+            reflection_probe_blend_mode: ReflectionProbeBlendMode::Blended,
         }
     }
 
@@ -303,6 +324,8 @@ impl QualitySettings {
             use_bloom: true,
 
             use_parallax_mapping: false, // TODO: Enable when it is fixed!
+
+            reflection_probe_blend_mode: ReflectionProbeBlendMode::Blended,
         }
     }
 
@@ -332,6 +355,8 @@ impl QualitySettings {
             use_bloom: true,
 
             use_parallax_mapping: false,
+
+            reflection_probe_blend_mode: ReflectionProbeBlendMode::Blended,
         }
     }
 
@@ -361,6 +386,8 @@ impl QualitySettings {
             use_bloom: false,
 
             use_parallax_mapping: false,
+
+            reflection_probe_blend_mode: ReflectionProbeBlendMode::Nearest,
         }
     }
 }
@@ -835,6 +862,8 @@ pub(in crate) struct MaterialContext<'a, 'b> {
     // Built-in uniforms.
     pub world_matrix: &'a Matrix4<f32>,
     pub wvp_matrix: &'a Matrix4<f32>,
+    pub view_projection_matrix: &'a Matrix4<f32>,
+    pub use_instancing: bool,
     pub bone_matrices: &'a [Matrix4<f32>],
     pub use_skeletal_animation: bool,
     pub camera_position: &'a Vector3<f32>,
@@ -858,6 +887,16 @@ pub(in crate) fn apply_material(ctx: MaterialContext) {
     {
         ctx.program_binding.set_matrix4(&location, ctx.wvp_matrix);
     }
+    if let Some(location) = ctx
+        .program_binding
+        .uniform_location("rg3d_viewProjectionMatrix")
+    {
+        ctx.program_binding
+            .set_matrix4(&location, ctx.view_projection_matrix);
+    }
+    if let Some(location) = ctx.program_binding.uniform_location("rg3d_useInstancing") {
+        ctx.program_binding.set_bool(&location, ctx.use_instancing);
+    }
     if let Some(location) = ctx.program_binding.uniform_location("rg3d_boneMatrices") {
         ctx.program_binding
             .set_matrix4_array(&location, ctx.bone_matrices);
@@ -963,8 +1002,19 @@ pub(in crate) fn apply_material(ctx: MaterialContext) {
 }
 
 impl Renderer {
-    pub(in crate) fn new(
-        context: glow::Context,
+    /// Creates new renderer for the given OpenGL context. The engine creates its renderer
+    /// automatically, but this method can be used to render without a window, for example
+    /// with a headless context such as Mesa's software rasterizer and
+    /// [`Self::render_scene_to_texture`].
+    pub fn new(context: glow::Context, frame_size: (u32, u32)) -> Result<Self, FrameworkError> {
+        Self::with_backend(Box::new(GlowBackend::new(context)), frame_size)
+    }
+
+    /// Creates renderer that issues all graphics calls to the given backend. It is mostly
+    /// useful for testing, for example with [`RecordingBackend`](framework::recording::RecordingBackend)
+    /// that allows to inspect what a frame would draw without a GPU.
+    pub fn with_backend(
+        backend: Box<dyn GraphicsBackend>,
         frame_size: (u32, u32),
     ) -> Result<Self, FrameworkError> {
         let settings = QualitySettings::default();
@@ -973,7 +1023,7 @@ impl Renderer {
 
         // Box pipeline state because we'll store pointers to it inside framework's entities and
         // it must have constant address.
-        let mut state = Box::new(PipelineState::new(context));
+        let mut state = Box::new(PipelineState::with_backend(backend));
 
         Ok(Self {
             backbuffer: FrameBuffer::backbuffer(&mut state),
@@ -1251,6 +1301,427 @@ impl Renderer {
         self.renderer2d.update(dt);
     }
 
+    /// Renders the scene from the point of view of the camera into the LDR frame buffer of
+    /// the associated scene data.
+    fn render_camera(
+        &mut self,
+        scene: &Scene,
+        scene_handle: Handle<Scene>,
+        camera: &Camera,
+        data: &mut AssociatedSceneData,
+        frame_size: Vector2<f32>,
+        dt: f32,
+    ) -> Result<(), FrameworkError> {
+        let state = &mut self.state;
+        let graph = &scene.graph;
+
+        let viewport = camera.viewport_pixels(frame_size);
+
+        self.statistics += data.gbuffer.fill(GBufferRenderContext {
+            state,
+            camera,
+            geom_cache: &mut self.geometry_cache,
+            batch_storage: &self.batch_storage,
+            texture_cache: &mut self.texture_cache,
+            shader_cache: &mut self.shader_cache,
+            environment_dummy: self.environment_dummy.clone(),
+            use_parallax_mapping: self.quality_settings.use_parallax_mapping,
+            normal_dummy: self.normal_dummy.clone(),
+            white_dummy: self.white_dummy.clone(),
+            black_dummy: self.black_dummy.clone(),
+            graph,
+        });
+
+        data.copy_depth_stencil_to_scene_framebuffer(state);
+
+        data.hdr_scene_framebuffer.clear(
+            state,
+            viewport,
+            Some(Color::from_rgba(0, 0, 0, 255)),
+            None, // Keep depth, we've just copied valid data in it.
+            Some(0),
+        );
+
+        let (pass_stats, light_stats) =
+            self.deferred_light_renderer
+                .render(DeferredRendererContext {
+                    state,
+                    scene,
+                    camera,
+                    gbuffer: &mut data.gbuffer,
+                    white_dummy: self.white_dummy.clone(),
+                    ambient_color: scene.ambient_lighting_color,
+                    settings: &self.quality_settings,
+                    textures: &mut self.texture_cache,
+                    geometry_cache: &mut self.geometry_cache,
+                    batch_storage: &self.batch_storage,
+                    frame_buffer: &mut data.hdr_scene_framebuffer,
+                    shader_cache: &mut self.shader_cache,
+                    normal_dummy: self.normal_dummy.clone(),
+                    black_dummy: self.black_dummy.clone(),
+                    environment_dummy: self.environment_dummy.clone(),
+                });
+
+        self.statistics.lighting += light_stats;
+        self.statistics.geometry += pass_stats;
+
+        let depth = data.gbuffer.depth();
+
+        self.statistics += self
+            .particle_system_renderer
+            .render(ParticleSystemRenderContext {
+                state,
+                framebuffer: &mut data.hdr_scene_framebuffer,
+                graph,
+                camera,
+                white_dummy: self.white_dummy.clone(),
+                depth,
+                frame_width: frame_size.x,
+                frame_height: frame_size.y,
+                viewport,
+                texture_cache: &mut self.texture_cache,
+            });
+
+        self.statistics += self.sprite_renderer.render(SpriteRenderContext {
+            state,
+            framebuffer: &mut data.hdr_scene_framebuffer,
+            graph,
+            camera,
+            white_dummy: self.white_dummy.clone(),
+            viewport,
+            textures: &mut self.texture_cache,
+            geom_map: &mut self.geometry_cache,
+        });
+
+        self.statistics += self.forward_renderer.render(ForwardRenderContext {
+            state,
+            camera,
+            geom_cache: &mut self.geometry_cache,
+            texture_cache: &mut self.texture_cache,
+            shader_cache: &mut self.shader_cache,
+            batch_storage: &self.batch_storage,
+            framebuffer: &mut data.hdr_scene_framebuffer,
+            viewport,
+            quality_settings: &self.quality_settings,
+            white_dummy: self.white_dummy.clone(),
+            normal_dummy: self.normal_dummy.clone(),
+            black_dummy: self.black_dummy.clone(),
+        });
+
+        for render_pass in self.scene_render_passes.iter() {
+            self.statistics += render_pass.lock().unwrap().render(SceneRenderPassContext {
+                pipeline_state: state,
+                texture_cache: &mut self.texture_cache,
+                geometry_cache: &mut self.geometry_cache,
+                quality_settings: &self.quality_settings,
+                batch_storage: &self.batch_storage,
+                viewport,
+                scene,
+                camera,
+                scene_handle,
+                white_dummy: self.white_dummy.clone(),
+                normal_dummy: self.normal_dummy.clone(),
+                metallic_dummy: self.metallic_dummy.clone(),
+                environment_dummy: self.environment_dummy.clone(),
+                black_dummy: self.black_dummy.clone(),
+                depth_texture: data.gbuffer.depth(),
+                normal_texture: data.gbuffer.normal_texture(),
+                ambient_texture: data.gbuffer.ambient_texture(),
+                framebuffer: &mut data.hdr_scene_framebuffer,
+            })?;
+        }
+
+        let quad = self.geometry_cache.get(state, &self.quad);
+
+        // Apply fog before bloom, so bright objects hidden in the fog won't glow.
+        self.statistics.geometry += data.hdr_renderer.render_fog(
+            state,
+            &mut data.hdr_scene_framebuffer,
+            data.gbuffer.depth(),
+            viewport,
+            quad,
+            camera,
+            graph,
+        );
+
+        // Prepare glow map.
+        self.statistics.geometry +=
+            data.bloom_renderer
+                .render(state, quad, data.hdr_scene_frame_texture());
+
+        // Convert high dynamic range frame to low dynamic range (sRGB) with tone mapping and gamma correction.
+        self.statistics.geometry += data.hdr_renderer.render(
+            state,
+            data.hdr_scene_frame_texture(),
+            data.bloom_renderer.result(),
+            &mut data.ldr_scene_framebuffer,
+            viewport,
+            quad,
+            dt,
+            camera.exposure(),
+            camera.color_grading_lut_ref(),
+            camera.color_grading_enabled(),
+            &mut self.texture_cache,
+        );
+
+        // Apply FXAA if needed.
+        if self.quality_settings.fxaa {
+            self.statistics.geometry += self.fxaa_renderer.render(
+                state,
+                viewport,
+                data.ldr_scene_frame_texture(),
+                &mut data.ldr_temp_framebuffer,
+                &mut self.geometry_cache,
+            );
+
+            let quad = self.geometry_cache.get(state, &self.quad);
+            let temp_frame_texture = data.ldr_temp_frame_texture();
+            self.statistics.geometry += blit_pixels(
+                state,
+                &mut data.ldr_scene_framebuffer,
+                temp_frame_texture,
+                &self.flat_shader,
+                viewport,
+                quad,
+            );
+        }
+
+        // Render debug geometry in the LDR frame buffer.
+        self.statistics += self.debug_renderer.render(
+            state,
+            viewport,
+            &mut data.ldr_scene_framebuffer,
+            &scene.drawing_context,
+            camera,
+        );
+
+        Ok(())
+    }
+
+    /// Renders the scene from the point of view of the given camera into an offscreen frame
+    /// of arbitrary size and reads the result back into a CPU-side RGBA8 texture. Rows of the
+    /// texture are stored top-to-bottom, so it can be saved to an image file directly. It is
+    /// useful to generate thumbnails of assets or to compare rendered frames with reference
+    /// images (see [`crate::utils::image_diff`]).
+    ///
+    /// The scene must be updated before rendering so the camera has correct matrices and
+    /// visibility cache. Custom scene render passes will receive `Handle::NONE` as the scene
+    /// handle. Exposure adaptation is instant, which makes the result deterministic.
+    ///
+    /// # Performance
+    ///
+    /// Method allocates and then destroys a full set of frame buffers of the requested size
+    /// and stalls the pipeline to read pixels back, do not call it every frame.
+    pub fn render_scene_to_texture(
+        &mut self,
+        scene: &Scene,
+        camera: Handle<Node>,
+        width: u32,
+        height: u32,
+    ) -> Result<Texture, FrameworkError> {
+        scope_profile!();
+
+        let camera = match scene.graph.try_get(camera) {
+            Some(Node::Camera(camera)) => camera,
+            _ => {
+                return Err(FrameworkError::Custom(format!(
+                    "{:?} is not a camera!",
+                    camera
+                )))
+            }
+        };
+
+        let width = width.max(1);
+        let height = height.max(1);
+        let frame_size = Vector2::new(width as f32, height as f32);
+        let frame_rect = Rect::new(0, 0, width as i32, height as i32);
+
+        self.state.invalidate_resource_bindings_cache();
+        self.batch_storage
+            .generate_batches(&scene.graph, camera.global_position());
+
+        let mut data = AssociatedSceneData::new(&mut self.state, width as usize, height as usize)?;
+        data.ldr_scene_framebuffer.clear(
+            &mut self.state,
+            frame_rect,
+            Some(Color::from_rgba(0, 0, 0, 0)),
+            Some(1.0),
+            Some(0),
+        );
+
+        // HDR renderer adapts exposure over time with speed of 0.3 * dt, large time step
+        // makes the adaptation instant.
+        let dt = 10.0;
+        self.render_camera(scene, Handle::NONE, camera, &mut data, frame_size, dt)?;
+
+        // OpenGL stores rows bottom-to-top, flip them.
+        let row_size = width as usize * 4;
+        let pixels = data
+            .ldr_scene_framebuffer
+            .read_pixels(&mut self.state, frame_rect)
+            .chunks_exact(row_size)
+            .rev()
+            .flatten()
+            .cloned()
+            .collect();
+
+        Ok(Texture::from_bytes(
+            TextureKind::Rectangle { width, height },
+            TexturePixelKind::RGBA8,
+            pixels,
+            false,
+        )
+        .unwrap())
+    }
+
+    /// Renders the scene from the position of the given reflection probe into a cube map and
+    /// stores it in the probe. The cube map is also returned, so it could be saved or re-used in
+    /// other probes. Skybox and exposure settings are taken from the first enabled camera of the
+    /// scene, the probe itself is not rendered in its cube map, but other probes affect the
+    /// result, so capturing probes multiple times adds more bounces of reflections.
+    ///
+    /// The scene must be updated at least once before capturing, so global transforms of nodes
+    /// are correct.
+    ///
+    /// # Performance
+    ///
+    /// Capturing renders the scene six times and stalls the pipeline to read pixels back, do it
+    /// at loading time or in the editor, not every frame.
+    pub fn capture_reflection_probe(
+        &mut self,
+        scene: &mut Scene,
+        probe: Handle<Node>,
+    ) -> Result<Texture, FrameworkError> {
+        scope_profile!();
+
+        let (position, resolution, z_near, z_far) = match scene.graph.try_get(probe) {
+            Some(Node::ReflectionProbe(probe)) => (
+                probe.global_position(),
+                probe.resolution(),
+                probe.z_near(),
+                probe.z_far(),
+            ),
+            _ => {
+                return Err(FrameworkError::Custom(format!(
+                    "{:?} is not a reflection probe!",
+                    probe
+                )))
+            }
+        };
+
+        let mut camera = CameraBuilder::new(BaseBuilder::new())
+            .with_fov(std::f32::consts::FRAC_PI_2)
+            .with_z_near(z_near)
+            .with_z_far(z_far)
+            .build_camera();
+        if let Some(scene_camera) = scene.graph.linear_iter().find_map(|node| match node {
+            Node::Camera(camera) if camera.is_enabled() => Some(camera),
+            _ => None,
+        }) {
+            camera.set_skybox(scene_camera.skybox_ref().cloned());
+            camera.set_environment(scene_camera.environment_map());
+            camera.set_exposure(scene_camera.exposure());
+        }
+
+        // The probe should not reflect itself.
+        let probe_visibility = scene.graph[probe].visibility();
+        scene.graph[probe].set_visibility(false);
+        scene.graph.update_hierarchical_data();
+
+        let frame_size = Vector2::new(resolution as f32, resolution as f32);
+        let frame_rect = Rect::new(0, 0, resolution as i32, resolution as i32);
+
+        self.state.invalidate_resource_bindings_cache();
+        self.batch_storage.generate_batches(&scene.graph, position);
+
+        let result =
+            AssociatedSceneData::new(&mut self.state, resolution as usize, resolution as usize)
+                .and_then(|mut data| {
+                    let mut pixels =
+                        Vec::with_capacity(resolution as usize * resolution as usize * 24);
+
+                    // Look and up vectors of faces in the order of OpenGL cube map: +X, -X, +Y, -Y, +Z, -Z.
+                    let faces = [
+                        (Vector3::x(), -Vector3::y()),
+                        (-Vector3::x(), -Vector3::y()),
+                        (Vector3::y(), Vector3::z()),
+                        (-Vector3::y(), -Vector3::z()),
+                        (Vector3::z(), -Vector3::y()),
+                        (-Vector3::z(), -Vector3::y()),
+                    ];
+
+                    for (look, up) in faces.iter() {
+                        let side = up.cross(look);
+                        camera.global_transform.set(Matrix4::new(
+                            side.x, up.x, look.x, position.x, //
+                            side.y, up.y, look.y, position.y, //
+                            side.z, up.z, look.z, position.z, //
+                            0.0, 0.0, 0.0, 1.0,
+                        ));
+                        camera.calculate_matrices(frame_size);
+
+                        let frustum =
+                            Frustum::from(camera.view_projection_matrix()).unwrap_or_default();
+                        let mut visibility_cache = std::mem::take(&mut camera.visibility_cache);
+                        visibility_cache.update(
+                            &scene.graph,
+                            position,
+                            z_near,
+                            z_far,
+                            Some(&[&frustum]),
+                        );
+                        camera.visibility_cache = visibility_cache;
+
+                        data.ldr_scene_framebuffer.clear(
+                            &mut self.state,
+                            frame_rect,
+                            Some(Color::from_rgba(0, 0, 0, 255)),
+                            Some(1.0),
+                            Some(0),
+                        );
+
+                        // Large time step makes exposure adaptation instant, see render_scene_to_texture.
+                        self.render_camera(
+                            scene,
+                            Handle::NONE,
+                            &camera,
+                            &mut data,
+                            frame_size,
+                            10.0,
+                        )?;
+
+                        // Rows are not flipped, cube map faces are stored bottom-to-top just as they
+                        // were rendered.
+                        pixels.extend(
+                            data.ldr_scene_framebuffer
+                                .read_pixels(&mut self.state, frame_rect),
+                        );
+                    }
+
+                    Ok(pixels)
+                });
+
+        scene.graph[probe].set_visibility(probe_visibility);
+        scene.graph.update_hierarchical_data();
+
+        let cubemap = Texture::from_bytes(
+            TextureKind::Cube {
+                width: resolution,
+                height: resolution,
+            },
+            TexturePixelKind::RGBA8,
+            result?,
+            true,
+        )
+        .unwrap();
+
+        scene.graph[probe]
+            .as_reflection_probe_mut()
+            .set_cubemap(Some(cubemap.clone()));
+
+        Ok(cubemap)
+    }
+
     fn render_frame(
         &mut self,
         scenes: &SceneContainer,
@@ -1304,27 +1775,21 @@ impl Renderer {
                 // Clamp to [1.0; infinity] range.
                 .sup(&Vector2::new(1.0, 1.0));
 
-            let state = &mut self.state;
-
-            self.batch_storage.generate_batches(graph);
-
-            let scene_associated_data = self
-                .scene_data_map
-                .entry(scene_handle)
-                .and_modify(|data| {
-                    if data.gbuffer.width != frame_size.x as i32
-                        || data.gbuffer.height != frame_size.y as i32
-                    {
-                        let width = frame_size.x as usize;
-                        let height = frame_size.y as usize;
-                        *data = AssociatedSceneData::new(state, width, height).unwrap();
-                    }
-                })
-                .or_insert_with(|| {
-                    let width = frame_size.x as usize;
-                    let height = frame_size.y as usize;
-                    AssociatedSceneData::new(state, width, height).unwrap()
-                });
+            // Associated data is taken out of the map while cameras are rendered, it will be put
+            // back when the scene is rendered.
+            let mut scene_associated_data = match self.scene_data_map.remove(&scene_handle) {
+                Some(data)
+                    if data.gbuffer.width == frame_size.x as i32
+                        && data.gbuffer.height == frame_size.y as i32 =>
+                {
+                    data
+                }
+                _ => AssociatedSceneData::new(
+                    &mut self.state,
+                    frame_size.x as usize,
+                    frame_size.y as usize,
+                )?,
+            };
 
             // If we specified a texture to draw to, we have to register it in texture cache
             // so it can be used in later on as texture. This is useful in case if you need
@@ -1353,179 +1818,24 @@ impl Renderer {
                     None
                 }
             }) {
-                let viewport = camera.viewport_pixels(frame_size);
-
-                self.statistics += scene_associated_data.gbuffer.fill(GBufferRenderContext {
-                    state,
-                    camera,
-                    geom_cache: &mut self.geometry_cache,
-                    batch_storage: &self.batch_storage,
-                    texture_cache: &mut self.texture_cache,
-                    shader_cache: &mut self.shader_cache,
-                    environment_dummy: self.environment_dummy.clone(),
-                    use_parallax_mapping: self.quality_settings.use_parallax_mapping,
-                    normal_dummy: self.normal_dummy.clone(),
-                    white_dummy: self.white_dummy.clone(),
-                    black_dummy: self.black_dummy.clone(),
-                    graph,
-                });
-
-                scene_associated_data.copy_depth_stencil_to_scene_framebuffer(state);
-
-                scene_associated_data.hdr_scene_framebuffer.clear(
-                    state,
-                    viewport,
-                    Some(Color::from_rgba(0, 0, 0, 255)),
-                    None, // Keep depth, we've just copied valid data in it.
-                    Some(0),
-                );
-
-                let (pass_stats, light_stats) =
-                    self.deferred_light_renderer
-                        .render(DeferredRendererContext {
-                            state,
-                            scene,
-                            camera,
-                            gbuffer: &mut scene_associated_data.gbuffer,
-                            white_dummy: self.white_dummy.clone(),
-                            ambient_color: scene.ambient_lighting_color,
-                            settings: &self.quality_settings,
-                            textures: &mut self.texture_cache,
-                            geometry_cache: &mut self.geometry_cache,
-                            batch_storage: &self.batch_storage,
-                            frame_buffer: &mut scene_associated_data.hdr_scene_framebuffer,
-                            shader_cache: &mut self.shader_cache,
-                            normal_dummy: self.normal_dummy.clone(),
-                            black_dummy: self.black_dummy.clone(),
-                        });
-
-                self.statistics.lighting += light_stats;
-                self.statistics.geometry += pass_stats;
-
-                let depth = scene_associated_data.gbuffer.depth();
-
-                self.statistics +=
-                    self.particle_system_renderer
-                        .render(ParticleSystemRenderContext {
-                            state,
-                            framebuffer: &mut scene_associated_data.hdr_scene_framebuffer,
-                            graph,
-                            camera,
-                            white_dummy: self.white_dummy.clone(),
-                            depth,
-                            frame_width: frame_size.x,
-                            frame_height: frame_size.y,
-                            viewport,
-                            texture_cache: &mut self.texture_cache,
-                        });
-
-                self.statistics += self.sprite_renderer.render(SpriteRenderContext {
-                    state,
-                    framebuffer: &mut scene_associated_data.hdr_scene_framebuffer,
-                    graph,
+                // Batches are generated per camera, because levels of detail depend on the
+                // position of the camera.
+                self.batch_storage
+                    .generate_batches(graph, camera.global_position());
+
+                self.render_camera(
+                    scene,
+                    scene_handle,
                     camera,
-                    white_dummy: self.white_dummy.clone(),
-                    viewport,
-                    textures: &mut self.texture_cache,
-                    geom_map: &mut self.geometry_cache,
-                });
-
-                self.statistics += self.forward_renderer.render(ForwardRenderContext {
-                    state,
-                    camera,
-                    geom_cache: &mut self.geometry_cache,
-                    texture_cache: &mut self.texture_cache,
-                    shader_cache: &mut self.shader_cache,
-                    batch_storage: &self.batch_storage,
-                    framebuffer: &mut scene_associated_data.hdr_scene_framebuffer,
-                    viewport,
-                    quality_settings: &self.quality_settings,
-                    white_dummy: self.white_dummy.clone(),
-                    normal_dummy: self.normal_dummy.clone(),
-                    black_dummy: self.black_dummy.clone(),
-                });
-
-                for render_pass in self.scene_render_passes.iter() {
-                    self.statistics +=
-                        render_pass.lock().unwrap().render(SceneRenderPassContext {
-                            pipeline_state: state,
-                            texture_cache: &mut self.texture_cache,
-                            geometry_cache: &mut self.geometry_cache,
-                            quality_settings: &self.quality_settings,
-                            batch_storage: &self.batch_storage,
-                            viewport,
-                            scene,
-                            camera,
-                            scene_handle,
-                            white_dummy: self.white_dummy.clone(),
-                            normal_dummy: self.normal_dummy.clone(),
-                            metallic_dummy: self.metallic_dummy.clone(),
-                            environment_dummy: self.environment_dummy.clone(),
-                            black_dummy: self.black_dummy.clone(),
-                            depth_texture: scene_associated_data.gbuffer.depth(),
-                            normal_texture: scene_associated_data.gbuffer.normal_texture(),
-                            ambient_texture: scene_associated_data.gbuffer.ambient_texture(),
-                            framebuffer: &mut scene_associated_data.hdr_scene_framebuffer,
-                        })?;
-                }
-
-                let quad = self.geometry_cache.get(state, &self.quad);
-
-                // Prepare glow map.
-                self.statistics.geometry += scene_associated_data.bloom_renderer.render(
-                    state,
-                    quad,
-                    scene_associated_data.hdr_scene_frame_texture(),
-                );
-
-                // Convert high dynamic range frame to low dynamic range (sRGB) with tone mapping and gamma correction.
-                self.statistics.geometry += scene_associated_data.hdr_renderer.render(
-                    state,
-                    scene_associated_data.hdr_scene_frame_texture(),
-                    scene_associated_data.bloom_renderer.result(),
-                    &mut scene_associated_data.ldr_scene_framebuffer,
-                    viewport,
-                    quad,
+                    &mut scene_associated_data,
+                    frame_size,
                     dt,
-                    camera.exposure(),
-                    camera.color_grading_lut_ref(),
-                    camera.color_grading_enabled(),
-                    &mut self.texture_cache,
-                );
-
-                // Apply FXAA if needed.
-                if self.quality_settings.fxaa {
-                    self.statistics.geometry += self.fxaa_renderer.render(
-                        state,
-                        viewport,
-                        scene_associated_data.ldr_scene_frame_texture(),
-                        &mut scene_associated_data.ldr_temp_framebuffer,
-                        &mut self.geometry_cache,
-                    );
-
-                    let quad = self.geometry_cache.get(state, &self.quad);
-                    let temp_frame_texture = scene_associated_data.ldr_temp_frame_texture();
-                    self.statistics.geometry += blit_pixels(
-                        state,
-                        &mut scene_associated_data.ldr_scene_framebuffer,
-                        temp_frame_texture,
-                        &self.flat_shader,
-                        viewport,
-                        quad,
-                    );
-                }
-
-                // Render debug geometry in the LDR frame buffer.
-                self.statistics += self.debug_renderer.render(
-                    state,
-                    viewport,
-                    &mut scene_associated_data.ldr_scene_framebuffer,
-                    &scene.drawing_context,
-                    camera,
-                );
+                )?;
 
                 // Optionally render everything into back buffer.
                 if scene.render_target.is_none() {
+                    let viewport = camera.viewport_pixels(frame_size);
+                    let state = &mut self.state;
                     let quad = self.geometry_cache.get(state, &self.quad);
                     self.statistics.geometry += blit_pixels(
                         state,
@@ -1537,6 +1847,9 @@ impl Renderer {
                     );
                 }
             }
+
+            self.scene_data_map
+                .insert(scene_handle, scene_associated_data);
         }
 
         // TODO: 2D renderer requires its own HDR pipeline.
@@ -1596,3 +1909,253 @@ impl Renderer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Matrix4, Vector2, Vector3},
+            pool::Handle,
+        },
+        gui::draw::DrawingContext,
+        renderer::{
+            framework::recording::{RecordingBackend, UniformValue},
+            Renderer,
+        },
+        resource::texture::{TextureKind, TexturePixelKind},
+        scene::{
+            base::BaseBuilder,
+            camera::{CameraBuilder, Fog},
+            light::{directional::DirectionalLightBuilder, BaseLightBuilder},
+            mesh::{
+                surface::{SurfaceBuilder, SurfaceData},
+                MeshBuilder,
+            },
+            node::Node,
+            reflection_probe::ReflectionProbeBuilder,
+            transform::TransformBuilder,
+            Scene, SceneContainer,
+        },
+        scene2d::Scene2dContainer,
+        sound::engine::SoundEngine,
+    };
+    use std::sync::{Arc, RwLock};
+
+    // Creates a scene with a camera and three cubes that share the same surface.
+    fn make_scene(frame_size: Vector2<f32>) -> (Scene, Handle<Node>) {
+        let mut scene = Scene::new();
+        let camera = CameraBuilder::new(BaseBuilder::new()).build(&mut scene.graph);
+        let surface = SurfaceBuilder::new(Arc::new(RwLock::new(SurfaceData::make_cube(
+            Matrix4::identity(),
+        ))))
+        .build();
+        for i in 0..3 {
+            MeshBuilder::new(
+                BaseBuilder::new().with_local_transform(
+                    TransformBuilder::new()
+                        .with_local_position(Vector3::new(i as f32 * 2.0 - 2.0, 0.0, 5.0))
+                        .build(),
+                ),
+            )
+            .with_surfaces(vec![surface.clone()])
+            .build(&mut scene.graph);
+        }
+        scene.update(frame_size, 0.0);
+        (scene, camera)
+    }
+
+    #[test]
+    fn test_render_frame_with_recording_backend() {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut renderer = Renderer::with_backend(Box::new(backend), (320, 240)).unwrap();
+
+        let (scene, _) = make_scene(Vector2::new(320.0, 240.0));
+
+        let sound_engine = SoundEngine::without_device();
+        let mut scenes = SceneContainer::new(sound_engine.clone());
+        scenes.add(scene);
+
+        log.clear();
+        renderer
+            .render_frame(
+                &scenes,
+                &DrawingContext::new(),
+                &Scene2dContainer::new(sound_engine),
+            )
+            .unwrap();
+
+        let draw_calls = log.draw_calls();
+        assert!(!draw_calls.is_empty());
+
+        // All three cubes share the same surface, so G-Buffer must be filled by one instanced
+        // draw call.
+        let cubes = draw_calls
+            .iter()
+            .filter(|d| d.uniform("rg3d_useInstancing") == Some(&UniformValue::I32(vec![1])))
+            .collect::<Vec<_>>();
+        assert_eq!(cubes.len(), 1);
+        assert_eq!(cubes[0].instance_count, 3);
+        assert_eq!(cubes[0].index_count, 36);
+        assert!(cubes[0].framebuffer.is_some());
+    }
+
+    #[test]
+    fn test_render_scene_to_texture() {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut renderer = Renderer::with_backend(Box::new(backend), (320, 240)).unwrap();
+
+        let (scene, camera) = make_scene(Vector2::new(64.0, 32.0));
+
+        log.clear();
+        let texture = renderer
+            .render_scene_to_texture(&scene, camera, 64, 32)
+            .unwrap();
+
+        let data = texture.data_ref();
+        assert!(matches!(
+            data.kind(),
+            TextureKind::Rectangle {
+                width: 64,
+                height: 32
+            }
+        ));
+        assert_eq!(data.pixel_kind(), TexturePixelKind::RGBA8);
+        assert_eq!(data.data().len(), 64 * 32 * 4);
+
+        // Nothing must be drawn into the back buffer.
+        let draw_calls = log.draw_calls();
+        assert!(draw_calls.iter().any(|d| d.instance_count == 3));
+        assert!(draw_calls.iter().all(|d| d.framebuffer.is_some()));
+
+        assert!(renderer
+            .render_scene_to_texture(&scene, scene.graph.get_root(), 64, 32)
+            .is_err());
+    }
+
+    #[test]
+    fn test_capture_reflection_probe() {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut renderer = Renderer::with_backend(Box::new(backend), (320, 240)).unwrap();
+
+        let frame_size = Vector2::new(64.0, 32.0);
+        let (mut scene, camera) = make_scene(frame_size);
+        let probe = ReflectionProbeBuilder::new(BaseBuilder::new())
+            .with_influence_extents(Vector3::new(10.0, 10.0, 10.0))
+            .with_resolution(8)
+            .build(&mut scene.graph);
+        scene.update(frame_size, 0.0);
+
+        // Probe without cube map has no effect.
+        log.clear();
+        renderer
+            .render_scene_to_texture(&scene, camera, 64, 32)
+            .unwrap();
+        assert!(log
+            .draw_calls()
+            .iter()
+            .all(|d| d.uniform("probeWeight").is_none()));
+
+        log.clear();
+        let cubemap = renderer
+            .capture_reflection_probe(&mut scene, probe)
+            .unwrap();
+        assert!(log.draw_calls().iter().all(|d| d.framebuffer.is_some()));
+
+        {
+            let data = cubemap.data_ref();
+            assert!(matches!(
+                data.kind(),
+                TextureKind::Cube {
+                    width: 8,
+                    height: 8
+                }
+            ));
+            assert_eq!(data.data().len(), 8 * 8 * 4 * 6);
+        }
+        assert!(scene.graph[probe].visibility());
+        assert!(scene.graph[probe].as_reflection_probe().cubemap().is_some());
+
+        // Every cube is deep inside of the probe, so all of them must be drawn once more with
+        // full weight of the probe using single instanced draw call.
+        log.clear();
+        renderer
+            .render_scene_to_texture(&scene, camera, 64, 32)
+            .unwrap();
+        let reflections = log
+            .draw_calls()
+            .into_iter()
+            .filter(|d| d.uniform("probeWeight").is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(reflections.len(), 1);
+        assert_eq!(reflections[0].instance_count, 3);
+        for draw_call in reflections {
+            assert_eq!(
+                draw_call.uniform("probeWeight"),
+                Some(&UniformValue::F32(vec![1.0, 0.0]))
+            );
+            assert_eq!(
+                draw_call.uniform("environmentWeight"),
+                Some(&UniformValue::F32(vec![0.0]))
+            );
+        }
+
+        assert!(renderer
+            .capture_reflection_probe(&mut scene, camera)
+            .is_err());
+    }
+
+    #[test]
+    fn test_fog_pass() {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut renderer = Renderer::with_backend(Box::new(backend), (320, 240)).unwrap();
+
+        let frame_size = Vector2::new(64.0, 32.0);
+        let (mut scene, camera) = make_scene(frame_size);
+
+        let fog_draw_calls = |renderer: &mut Renderer, scene: &Scene| {
+            log.clear();
+            renderer
+                .render_scene_to_texture(scene, camera, 64, 32)
+                .unwrap();
+            log.draw_calls()
+                .into_iter()
+                .filter(|d| d.uniform("heightFalloff").is_some())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(fog_draw_calls(&mut renderer, &scene).is_empty());
+
+        let sun = DirectionalLightBuilder::new(BaseLightBuilder::new(BaseBuilder::new()))
+            .build(&mut scene.graph);
+        scene.graph[camera].as_camera_mut().set_fog(Some(Fog {
+            density: 0.05,
+            sun,
+            ..Default::default()
+        }));
+        scene.update(frame_size, 0.0);
+
+        let draw_calls = fog_draw_calls(&mut renderer, &scene);
+        assert_eq!(draw_calls.len(), 1);
+        assert!(draw_calls[0].framebuffer.is_some());
+        assert_eq!(
+            draw_calls[0].uniform("density"),
+            Some(&UniformValue::F32(vec![0.05]))
+        );
+        assert_eq!(
+            draw_calls[0].uniform("sunEnabled"),
+            Some(&UniformValue::I32(vec![1]))
+        );
+
+        // Fog must ignore anything but directional lights.
+        scene.graph[camera].as_camera_mut().fog_mut().unwrap().sun = camera;
+        let draw_calls = fog_draw_calls(&mut renderer, &scene);
+        assert_eq!(
+            draw_calls[0].uniform("sunEnabled"),
+            Some(&UniformValue::I32(vec![0]))
+        );
+    }
+}
@@ -0,0 +1,52 @@
+uniform sampler2D depthSampler;
+uniform mat4 invViewProj;
+uniform vec3 cameraPosition;
+uniform vec4 fogColor;
+uniform float density;
+uniform float heightFalloff;
+uniform float baseHeight;
+uniform float startDistance;
+uniform float endDistance;
+uniform float maxOpacity;
+uniform bool sunEnabled;
+uniform vec3 sunDirection;
+uniform vec3 sunColor;
+uniform float sunExponent;
+
+in vec2 texCoord;
+
+out vec4 FragColor;
+
+void main() {
+    vec3 position = S_UnProject(vec3(texCoord, texture(depthSampler, texCoord).r), invViewProj);
+
+    vec3 view = position - cameraPosition;
+    float distance = length(view);
+    vec3 direction = view / max(distance, 0.00001);
+
+    float fogDistance = max(distance - startDistance, 0.0);
+
+    // Analytical integral of exponential height fog density along the part of the view ray
+    // that is inside the fog.
+    float startHeight = cameraPosition.y + direction.y * startDistance;
+    float k = heightFalloff * direction.y * fogDistance;
+    float integral = density * exp(-heightFalloff * (startHeight - baseHeight)) * fogDistance;
+    if (abs(k) > 0.0001) {
+        integral *= (1.0 - exp(-k)) / k;
+    }
+    float heightFog = 1.0 - exp(-integral);
+
+    float distanceFog = 0.0;
+    if (endDistance > startDistance) {
+        distanceFog = min(fogDistance / (endDistance - startDistance), 1.0);
+    }
+
+    float opacity = min(1.0 - (1.0 - heightFog) * (1.0 - distanceFog), maxOpacity);
+
+    vec3 color = fogColor.rgb;
+    if (sunEnabled) {
+        color += sunColor * pow(max(dot(direction, sunDirection), 0.0), sunExponent);
+    }
+
+    FragColor = vec4(color, opacity);
+}
@@ -0,0 +1,92 @@
+uniform sampler2D depthTexture;
+uniform sampler2D colorTexture;
+uniform sampler2D normalTexture;
+uniform sampler2D materialTexture;
+uniform sampler2D aoSampler;
+
+uniform samplerCube environmentTexture;
+uniform float environmentWeight;
+uniform float environmentMaxLod;
+
+// Up to two reflection probes per surface, unused probes have zero weight.
+uniform samplerCube firstProbeTexture;
+uniform samplerCube secondProbeTexture;
+uniform vec3 probePosition[2];
+uniform vec3 probeBoxMin[2];
+uniform vec3 probeBoxMax[2];
+uniform bool probeBoxProjection[2];
+uniform float probeWeight[2];
+uniform float probeMaxLod[2];
+
+uniform mat4 invViewProj;
+uniform vec3 cameraPosition;
+
+in vec4 clipSpacePosition;
+out vec4 FragColor;
+
+// Intersects reflection ray with the projection box of a probe and returns direction from
+// the probe to the intersection point. This makes reflections match the geometry around the
+// probe instead of being "infinitely" far.
+vec3 BoxProjection(int probe, vec3 position, vec3 direction)
+{
+    if (!probeBoxProjection[probe]) {
+        return direction;
+    }
+
+    vec3 first = (probeBoxMax[probe] - position) / direction;
+    vec3 second = (probeBoxMin[probe] - position) / direction;
+    vec3 furthest = max(first, second);
+    float distance = min(min(furthest.x, furthest.y), furthest.z);
+
+    return position + direction * distance - probePosition[probe];
+}
+
+vec3 SampleCubemap(samplerCube cubemap, vec3 direction, float lod)
+{
+    // Cube maps are stored in sRGB.
+    return S_SRGBToLinear(textureLod(cubemap, direction, lod)).rgb;
+}
+
+void main()
+{
+    vec2 screenPos = clipSpacePosition.xy / clipSpacePosition.w;
+
+    vec2 texCoord = screenPos * 0.5 + 0.5;
+
+    float sceneDepth = texture(depthTexture, texCoord).r;
+
+    // The surface is drawn the second time, but its depth may slightly differ from the depth in
+    // the G-Buffer, so instead of depth test a small tolerance is used to reject occluded pixels.
+    if (gl_FragCoord.z > sceneDepth + 0.000001) {
+        discard;
+    }
+
+    vec3 material = texture(materialTexture, texCoord).rgb;
+    float metallic = material.x;
+    float roughness = material.y;
+
+    vec3 fragmentPosition = S_UnProject(vec3(texCoord, sceneDepth), invViewProj);
+    vec3 normal = normalize(texture(normalTexture, texCoord).xyz * 2.0 - 1.0);
+    vec3 viewVector = normalize(cameraPosition - fragmentPosition);
+    vec3 reflection = reflect(-viewVector, normal);
+
+    vec3 color = environmentWeight * SampleCubemap(environmentTexture, reflection, roughness * environmentMaxLod);
+    if (probeWeight[0] > 0.0) {
+        vec3 direction = BoxProjection(0, fragmentPosition, reflection);
+        color += probeWeight[0] * SampleCubemap(firstProbeTexture, direction, roughness * probeMaxLod[0]);
+    }
+    if (probeWeight[1] > 0.0) {
+        vec3 direction = BoxProjection(1, fragmentPosition, reflection);
+        color += probeWeight[1] * SampleCubemap(secondProbeTexture, direction, roughness * probeMaxLod[1]);
+    }
+
+    // Roughness-aware Fresnel-Schlick, rough surfaces should not reflect too much at grazing angles.
+    vec3 albedo = texture(colorTexture, texCoord).rgb;
+    vec3 F0 = mix(vec3(0.04), albedo, metallic);
+    float cosTheta = max(dot(normal, viewVector), 0.0);
+    vec3 F = F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(1.0 - cosTheta, 5.0);
+
+    float ambientOcclusion = texture(aoSampler, texCoord).r;
+
+    FragColor = vec4(color * F * ambientOcclusion, 1.0);
+}
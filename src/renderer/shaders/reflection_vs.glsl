@@ -0,0 +1,37 @@
+layout(location = 0) in vec3 vertexPosition;
+layout(location = 4) in vec4 boneWeights;
+layout(location = 5) in vec4 boneIndices;
+layout(location = 7) in mat4 instanceWorldMatrix;
+
+// View-projection matrix when instancing is used.
+uniform mat4 worldViewProjection;
+uniform mat4 boneMatrices[60];
+uniform bool useSkeletalAnimation;
+uniform bool useInstancing;
+
+out vec4 clipSpacePosition;
+
+void main()
+{
+    vec4 localPosition = vec4(vertexPosition, 1.0);
+
+    if (useSkeletalAnimation)
+    {
+        vec4 vertex = localPosition;
+
+        localPosition = boneMatrices[int(boneIndices.x)] * vertex * boneWeights.x;
+        localPosition += boneMatrices[int(boneIndices.y)] * vertex * boneWeights.y;
+        localPosition += boneMatrices[int(boneIndices.z)] * vertex * boneWeights.z;
+        localPosition += boneMatrices[int(boneIndices.w)] * vertex * boneWeights.w;
+    }
+
+    if (useInstancing)
+    {
+        gl_Position = worldViewProjection * instanceWorldMatrix * localPosition;
+    }
+    else
+    {
+        gl_Position = worldViewProjection * localPosition;
+    }
+    clipSpacePosition = gl_Position;
+}
@@ -7,7 +7,7 @@ use crate::{
     },
     renderer::{
         apply_material,
-        batch::BatchStorage,
+        batch::{BatchStorage, SurfaceInstance},
         cache::{shader::ShaderCache, texture::TextureCache},
         framework::{
             error::FrameworkError,
@@ -210,6 +210,8 @@ impl PointShadowMapRenderer {
         let light_projection_matrix =
             Matrix4::new_perspective(1.0, std::f32::consts::FRAC_PI_2, 0.01, light_radius);
 
+        let mut instance_matrices = Vec::new();
+
         for face in self.faces.iter() {
             framebuffer.set_cubemap_face(state, 0, face.face).clear(
                 state,
@@ -229,6 +231,23 @@ impl PointShadowMapRenderer {
 
             let frustum = Frustum::from(light_view_projection_matrix).unwrap_or_default();
 
+            let is_visible = |instance: &SurfaceInstance| {
+                let node = &graph[instance.owner];
+
+                node.global_visibility() && {
+                    match node {
+                        Node::Mesh(mesh) => {
+                            mesh.cast_shadows() && mesh.is_intersect_frustum(graph, &frustum)
+                        }
+                        Node::Terrain(_) => {
+                            // https://github.com/rg3dengine/rg3d/issues/117
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+            };
+
             for batch in batch_storage.batches.iter() {
                 let material = batch.material.lock().unwrap();
                 let geometry = geom_cache.get(state, &batch.data.read().unwrap());
@@ -237,24 +256,49 @@ impl PointShadowMapRenderer {
                     .get(state, material.shader())
                     .and_then(|shader_set| shader_set.render_passes.get("PointShadow"))
                 {
-                    for instance in batch.instances.iter() {
-                        let node = &graph[instance.owner];
+                    if batch.can_be_instanced() && render_pass.supports_instancing {
+                        instance_matrices.clear();
+                        instance_matrices.extend(
+                            batch
+                                .instances
+                                .iter()
+                                .filter(|i| is_visible(i))
+                                .map(|i| i.world_transform),
+                        );
 
-                        let visible = node.global_visibility() && {
-                            match node {
-                                Node::Mesh(mesh) => {
-                                    mesh.cast_shadows()
-                                        && mesh.is_intersect_frustum(graph, &frustum)
-                                }
-                                Node::Terrain(_) => {
-                                    // https://github.com/rg3dengine/rg3d/issues/117
-                                    true
-                                }
-                                _ => false,
-                            }
-                        };
+                        if !instance_matrices.is_empty() {
+                            GeometryCache::set_instances(state, geometry, &instance_matrices);
 
-                        if visible {
+                            statistics += framebuffer.draw_instances(
+                                instance_matrices.len(),
+                                geometry,
+                                state,
+                                viewport,
+                                &render_pass.program,
+                                &render_pass.draw_params,
+                                |mut program_binding| {
+                                    apply_material(MaterialContext {
+                                        material: &material,
+                                        program_binding: &mut program_binding,
+                                        texture_cache,
+                                        world_matrix: &Matrix4::identity(),
+                                        wvp_matrix: &light_view_projection_matrix,
+                                        view_projection_matrix: &light_view_projection_matrix,
+                                        use_instancing: true,
+                                        bone_matrices: &[],
+                                        use_skeletal_animation: false,
+                                        camera_position: &Default::default(),
+                                        use_pom: false,
+                                        light_position: &light_pos,
+                                        normal_dummy: normal_dummy.clone(),
+                                        white_dummy: white_dummy.clone(),
+                                        black_dummy: black_dummy.clone(),
+                                    });
+                                },
+                            );
+                        }
+                    } else {
+                        for instance in batch.instances.iter().filter(|i| is_visible(i)) {
                             statistics += framebuffer.draw(
                                 geometry,
                                 state,
@@ -263,12 +307,14 @@ impl PointShadowMapRenderer {
                                 &render_pass.draw_params,
                                 |mut program_binding| {
                                     apply_material(MaterialContext {
-                                        material: &*material,
+                                        material: &material,
                                         program_binding: &mut program_binding,
                                         texture_cache,
                                         world_matrix: &instance.world_transform,
                                         wvp_matrix: &(light_view_projection_matrix
                                             * instance.world_transform),
+                                        view_projection_matrix: &light_view_projection_matrix,
+                                        use_instancing: false,
                                         bone_matrices: &instance.bone_matrices,
                                         use_skeletal_animation: batch.is_skinned,
                                         camera_position: &Default::default(),
@@ -7,7 +7,7 @@ use crate::{
     },
     renderer::{
         apply_material,
-        batch::BatchStorage,
+        batch::{BatchStorage, SurfaceInstance},
         cache::{shader::ShaderCache, texture::TextureCache},
         framework::{
             error::FrameworkError,
@@ -139,6 +139,35 @@ impl SpotShadowMapRenderer {
         framebuffer.clear(state, viewport, None, Some(1.0), None);
         let frustum = Frustum::from(*light_view_projection).unwrap_or_default();
 
+        let draw_params = DrawParameters {
+            cull_face: Some(CullFace::Back),
+            color_write: ColorMask::all(false),
+            depth_write: true,
+            stencil_test: None,
+            depth_test: true,
+            blend: None,
+            stencil_op: Default::default(),
+        };
+
+        let is_visible = |instance: &SurfaceInstance| {
+            let node = &graph[instance.owner];
+
+            node.global_visibility() && {
+                match node {
+                    Node::Mesh(mesh) => {
+                        mesh.cast_shadows() && mesh.is_intersect_frustum(graph, &frustum)
+                    }
+                    Node::Terrain(_) => {
+                        // https://github.com/rg3dengine/rg3d/issues/117
+                        true
+                    }
+                    _ => false,
+                }
+            }
+        };
+
+        let mut instance_matrices = Vec::new();
+
         for batch in batches.batches.iter() {
             let material = batch.material.lock().unwrap();
             let geometry = geom_cache.get(state, &batch.data.read().unwrap());
@@ -147,44 +176,64 @@ impl SpotShadowMapRenderer {
                 .get(state, material.shader())
                 .and_then(|shader_set| shader_set.render_passes.get("SpotShadow"))
             {
-                for instance in batch.instances.iter() {
-                    let node = &graph[instance.owner];
-
-                    let visible = node.global_visibility() && {
-                        match node {
-                            Node::Mesh(mesh) => {
-                                mesh.cast_shadows() && mesh.is_intersect_frustum(graph, &frustum)
-                            }
-                            Node::Terrain(_) => {
-                                // https://github.com/rg3dengine/rg3d/issues/117
-                                true
-                            }
-                            _ => false,
-                        }
-                    };
-
-                    if visible {
-                        statistics += framebuffer.draw(
+                if batch.can_be_instanced() && render_pass.supports_instancing {
+                    instance_matrices.clear();
+                    instance_matrices.extend(
+                        batch
+                            .instances
+                            .iter()
+                            .filter(|i| is_visible(i))
+                            .map(|i| i.world_transform),
+                    );
+
+                    if !instance_matrices.is_empty() {
+                        GeometryCache::set_instances(state, geometry, &instance_matrices);
+
+                        statistics += framebuffer.draw_instances(
+                            instance_matrices.len(),
                             geometry,
                             state,
                             viewport,
                             &render_pass.program,
-                            &DrawParameters {
-                                cull_face: Some(CullFace::Back),
-                                color_write: ColorMask::all(false),
-                                depth_write: true,
-                                stencil_test: None,
-                                depth_test: true,
-                                blend: None,
-                                stencil_op: Default::default(),
+                            &draw_params,
+                            |mut program_binding| {
+                                apply_material(MaterialContext {
+                                    material: &material,
+                                    program_binding: &mut program_binding,
+                                    texture_cache,
+                                    world_matrix: &Matrix4::identity(),
+                                    wvp_matrix: light_view_projection,
+                                    view_projection_matrix: light_view_projection,
+                                    use_instancing: true,
+                                    bone_matrices: &[],
+                                    use_skeletal_animation: false,
+                                    camera_position: &Default::default(),
+                                    use_pom: false,
+                                    light_position: &Default::default(),
+                                    normal_dummy: normal_dummy.clone(),
+                                    white_dummy: white_dummy.clone(),
+                                    black_dummy: black_dummy.clone(),
+                                });
                             },
+                        );
+                    }
+                } else {
+                    for instance in batch.instances.iter().filter(|i| is_visible(i)) {
+                        statistics += framebuffer.draw(
+                            geometry,
+                            state,
+                            viewport,
+                            &render_pass.program,
+                            &draw_params,
                             |mut program_binding| {
                                 apply_material(MaterialContext {
-                                    material: &*material,
+                                    material: &material,
                                     program_binding: &mut program_binding,
                                     texture_cache,
                                     world_matrix: &instance.world_transform,
                                     wvp_matrix: &(light_view_projection * instance.world_transform),
+                                    view_projection_matrix: light_view_projection,
+                                    use_instancing: false,
                                     bone_matrices: &instance.bone_matrices,
                                     use_skeletal_animation: batch.is_skinned,
                                     camera_position: &Default::default(),
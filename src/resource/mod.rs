@@ -4,4 +4,5 @@
 
 pub mod fbx;
 pub mod model;
+pub mod sound_cue;
 pub mod texture;
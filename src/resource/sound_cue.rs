@@ -0,0 +1,214 @@
+//! Contains everything needed to load sound cues from files.
+//!
+//! Sound cue is a container of sound buffers with rules of how to play them, see
+//! [`crate::sound::cue`] module docs for more info. Cues are loaded by
+//! [`ResourceManager::request_sound_cue`] from RON files with following structure:
+//!
+//! ```ron
+//! (
+//!     // A set of buffers, paths are relative to working directory. Weight defines probability
+//!     // of selection of a buffer in `Random` mode, it is optional and 1.0 by default.
+//!     buffers: [
+//!         (path: "data/sounds/footstep1.wav", weight: 2.0),
+//!         (path: "data/sounds/footstep2.wav"),
+//!     ],
+//!
+//!     // Every field below is optional.
+//!
+//!     // Either Random, Shuffle or Sequential. Random by default.
+//!     selection_mode: Shuffle,
+//!
+//!     // Ranges of random pitch and gain. (1.0, 1.0) by default.
+//!     pitch: (0.9, 1.1),
+//!     gain: (0.8, 1.0),
+//!
+//!     // Maximum amount of instances that are played at the same time. Unlimited by default.
+//!     max_instances: Some(4),
+//!
+//!     // Either Reject, Oldest or Quietest. Oldest by default.
+//!     stealing_policy: Oldest,
+//!
+//!     // Minimal time between two consecutive plays in seconds. 0.0 by default.
+//!     cooldown: 0.05,
+//! )
+//! ```
+
+use crate::{
+    asset::ResourceData,
+    core::{futures::future::join_all, io, numeric_range::NumericRange},
+    engine::resource_manager::ResourceManager,
+    sound::cue::{SelectionMode, SoundCue, SoundCueEntry, SoundCueLoadError, StealingPolicy},
+    utils::log::{Log, MessageKind},
+};
+use serde::Deserialize;
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+fn default_range() -> (f32, f32) {
+    (1.0, 1.0)
+}
+
+/// A buffer definition of sound cue.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct SoundCueEntryDefinition {
+    /// A path to a sound buffer.
+    pub path: PathBuf,
+    /// A weight of the buffer.
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+/// A definition of sound cue. See module docs for more info.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct SoundCueDefinition {
+    /// A set of buffers of the cue.
+    pub buffers: Vec<SoundCueEntryDefinition>,
+    /// Selection mode of the cue.
+    #[serde(default)]
+    pub selection_mode: SelectionMode,
+    /// A range of pitch.
+    #[serde(default = "default_range")]
+    pub pitch: (f32, f32),
+    /// A range of gain.
+    #[serde(default = "default_range")]
+    pub gain: (f32, f32),
+    /// Maximum amount of instances.
+    #[serde(default)]
+    pub max_instances: Option<u32>,
+    /// Stealing policy of the cue.
+    #[serde(default)]
+    pub stealing_policy: StealingPolicy,
+    /// Cooldown in seconds, must be finite.
+    #[serde(default)]
+    pub cooldown: f32,
+}
+
+impl SoundCueDefinition {
+    fn from_buf(buf: Vec<u8>) -> Result<Self, SoundCueLoadError> {
+        let definition: Self = ron::de::from_reader(Cursor::new(buf))
+            .map_err(|e| SoundCueLoadError::Parse(e.to_string()))?;
+        if !definition.cooldown.is_finite() {
+            return Err(SoundCueLoadError::Parse(format!(
+                "Invalid cooldown {} of sound cue!",
+                definition.cooldown
+            )));
+        }
+        Ok(definition)
+    }
+
+    /// Creates sound cue using the definition, sound buffers are requested from given resource
+    /// manager. Buffers that failed to load are skipped.
+    pub async fn instantiate(self, resource_manager: ResourceManager) -> SoundCue {
+        let buffers = self
+            .buffers
+            .iter()
+            .map(|entry| resource_manager.request_sound_buffer(&entry.path, false))
+            .collect::<Vec<_>>();
+
+        let entries = join_all(buffers)
+            .await
+            .into_iter()
+            .zip(self.buffers.iter())
+            .filter_map(|(buffer, entry)| match buffer {
+                Ok(buffer) => Some(SoundCueEntry::new(buffer, entry.weight)),
+                Err(_) => {
+                    Log::writeln(
+                        MessageKind::Warning,
+                        format!(
+                            "Unable to load sound buffer {:?} of a sound cue, it will be skipped!",
+                            entry.path
+                        ),
+                    );
+                    None
+                }
+            })
+            .collect();
+
+        let mut cue = SoundCue::new(entries);
+        cue.set_selection_mode(self.selection_mode)
+            .set_pitch(NumericRange::new(self.pitch.0, self.pitch.1))
+            .set_gain(NumericRange::new(self.gain.0, self.gain.1))
+            .set_max_instances(self.max_instances)
+            .set_stealing_policy(self.stealing_policy)
+            .set_cooldown(Duration::from_secs_f32(self.cooldown.max(0.0)));
+        cue
+    }
+}
+
+pub(in crate) async fn load_sound_cue<P: AsRef<Path>>(
+    path: P,
+    resource_manager: ResourceManager,
+) -> Result<SoundCue, SoundCueLoadError> {
+    let content = io::load_file(path.as_ref())
+        .await
+        .map_err(SoundCueLoadError::Io)?;
+    let mut cue = SoundCueDefinition::from_buf(content)?
+        .instantiate(resource_manager)
+        .await;
+    cue.set_path(path.as_ref().to_owned());
+    Ok(cue)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        resource::sound_cue::{SoundCueDefinition, SoundCueEntryDefinition},
+        sound::cue::{SelectionMode, StealingPolicy},
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_sound_cue_definition() {
+        let definition = SoundCueDefinition::from_buf(
+            br#"(
+                buffers: [
+                    (path: "a.wav", weight: 2.0),
+                    (path: "b.wav"),
+                ],
+                selection_mode: Shuffle,
+                gain: (0.5, 1.0),
+                max_instances: Some(3),
+                stealing_policy: Reject,
+            )"#
+            .to_vec(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            definition,
+            SoundCueDefinition {
+                buffers: vec![
+                    SoundCueEntryDefinition {
+                        path: PathBuf::from("a.wav"),
+                        weight: 2.0
+                    },
+                    SoundCueEntryDefinition {
+                        path: PathBuf::from("b.wav"),
+                        weight: 1.0
+                    },
+                ],
+                selection_mode: SelectionMode::Shuffle,
+                pitch: (1.0, 1.0),
+                gain: (0.5, 1.0),
+                max_instances: Some(3),
+                stealing_policy: StealingPolicy::Reject,
+                cooldown: 0.0,
+            }
+        );
+
+        assert!(SoundCueDefinition::from_buf(b"(selection_mode: Shuffle)".to_vec()).is_err());
+        assert!(
+            SoundCueDefinition::from_buf(b"(buffers: [], cooldown: inf)".to_vec()).is_err()
+        );
+        assert!(
+            SoundCueDefinition::from_buf(b"(buffers: [], cooldown: NaN)".to_vec()).is_err()
+        );
+    }
+}
@@ -17,6 +17,7 @@
 use crate::{
     core::{
         algebra::{Matrix4, Point3, Vector2, Vector3, Vector4},
+        color::Color,
         math::{ray::Ray, Rect},
         pool::Handle,
         visitor::{Visit, VisitResult, Visitor},
@@ -67,6 +68,123 @@ impl Default for Exposure {
     }
 }
 
+/// Global fog settings of a camera. Fog is applied to the high dynamic range frame, right
+/// before tone mapping, so it affects everything that was rendered by the camera including
+/// the skybox.
+///
+/// Fog consists of three parts, each of which can be used alone or in any combination:
+///
+/// - Exponential height fog - fog density decreases exponentially with height, which gives
+///   nice looking thick fog in valleys and thin fog in the mountains. Amount of fog is
+///   computed analytically along view ray, so there is no banding.
+/// - Linear distance fog - fog opacity increases linearly from `start_distance` to
+///   `end_distance`. It is disabled when `end_distance` is less or equal to `start_distance`.
+/// - Sun inscattering - fog becomes brighter and tinted by sun color when you're looking
+///   towards the sun. Sun is a directional light specified by `sun` handle, it is ignored if
+///   the handle does not point to a directional light.
+///
+/// # Example
+///
+/// ```
+/// use rg3d::{
+///     core::{color::Color, pool::Handle},
+///     scene::{camera::Fog, node::Node},
+/// };
+///
+/// fn make_fog(sun: Handle<Node>) -> Fog {
+///     Fog {
+///         color: Color::opaque(180, 190, 200),
+///         density: 0.05,
+///         height_falloff: 0.2,
+///         sun,
+///         ..Default::default()
+///     }
+/// }
+/// ```
+#[derive(Visit, Copy, Clone, PartialEq, Debug)]
+pub struct Fog {
+    /// Color of the fog in sRGB space. Default is light gray.
+    pub color: Color,
+    /// Density of height fog at `base_height`. Zero disables height fog. Default is 0.02.
+    pub density: f32,
+    /// How fast density of height fog decreases with height. The higher the value, the
+    /// thinner the fog layer. Zero makes fog uniform in all directions. Default is 0.2.
+    pub height_falloff: f32,
+    /// Height (in world coordinates) at which height fog has `density`. Default is 0.0.
+    pub base_height: f32,
+    /// Distance from the camera at which fog starts. Default is 0.0.
+    pub start_distance: f32,
+    /// Distance from the camera at which linear distance fog reaches full opacity. Default
+    /// is 0.0 (linear distance fog is disabled).
+    pub end_distance: f32,
+    /// Maximum opacity of the fog, it allows you to keep distant objects (like a skybox)
+    /// slightly visible through the fog. Default is 1.0.
+    pub max_opacity: f32,
+    /// Handle of a directional light that will be used as the sun for inscattering. Default
+    /// is `Handle::NONE` (no inscattering).
+    pub sun: Handle<Node>,
+    /// Intensity multiplier of sun inscattering. Default is 1.0.
+    pub sun_inscattering_intensity: f32,
+    /// Exponent of sun inscattering. The higher the value, the smaller the bright area around
+    /// the sun. Default is 8.0.
+    pub sun_inscattering_exponent: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            color: Color::opaque(190, 190, 190),
+            density: 0.02,
+            height_falloff: 0.2,
+            base_height: 0.0,
+            start_distance: 0.0,
+            end_distance: 0.0,
+            max_opacity: 1.0,
+            sun: Handle::NONE,
+            sun_inscattering_intensity: 1.0,
+            sun_inscattering_exponent: 8.0,
+        }
+    }
+}
+
+impl Fog {
+    /// Calculates opacity of the fog in [0; 1] range between the camera at `camera_position`
+    /// and a point at `position`. This is CPU version of the same computations that are done
+    /// by the renderer, it could be useful for gameplay logic (to check whether an object is
+    /// hidden in the fog, for example).
+    pub fn opacity(&self, camera_position: Vector3<f32>, position: Vector3<f32>) -> f32 {
+        let view = position - camera_position;
+        let distance = view.norm();
+        let fog_distance = (distance - self.start_distance).max(0.0);
+        if fog_distance <= 0.0 {
+            return 0.0;
+        }
+
+        // Integral of density(h) = density * exp(-falloff * (h - base_height)) along the part
+        // of the view ray that is inside the fog.
+        let direction_y = view.y / distance;
+        let start_height = camera_position.y + direction_y * self.start_distance;
+        let k = self.height_falloff * direction_y * fog_distance;
+        let integral = self.density
+            * (-self.height_falloff * (start_height - self.base_height)).exp()
+            * fog_distance
+            * if k.abs() > 0.0001 {
+                (1.0 - (-k).exp()) / k
+            } else {
+                1.0
+            };
+        let height_fog = 1.0 - (-integral).exp();
+
+        let distance_fog = if self.end_distance > self.start_distance {
+            (fog_distance / (self.end_distance - self.start_distance)).min(1.0)
+        } else {
+            0.0
+        };
+
+        (1.0 - (1.0 - height_fog) * (1.0 - distance_fog)).min(self.max_opacity)
+    }
+}
+
 /// See module docs.
 #[derive(Debug, Visit)]
 pub struct Camera {
@@ -88,6 +206,8 @@ pub struct Camera {
     color_grading_lut: Option<ColorGradingLut>,
     #[visit(optional)] // Backward compatibility.
     color_grading_enabled: bool,
+    #[visit(optional)] // Backward compatibility.
+    fog: Option<Fog>,
 
     /// Visibility cache allows you to quickly check if object is visible from the camera or not.
     #[visit(skip)]
@@ -336,6 +456,7 @@ impl Camera {
             exposure: self.exposure,
             color_grading_lut: self.color_grading_lut.clone(),
             color_grading_enabled: self.color_grading_enabled,
+            fog: self.fog,
             // No need to copy cache. It is valid only for one frame.
             visibility_cache: Default::default(),
         }
@@ -375,6 +496,21 @@ impl Camera {
     pub fn exposure(&self) -> Exposure {
         self.exposure
     }
+
+    /// Sets new fog settings, `None` disables fog. See [`Fog`] docs for more info.
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        self.fog = fog;
+    }
+
+    /// Returns current fog settings.
+    pub fn fog(&self) -> Option<Fog> {
+        self.fog
+    }
+
+    /// Returns current fog settings by mutable ref, it allows you to tweak fog in-place.
+    pub fn fog_mut(&mut self) -> Option<&mut Fog> {
+        self.fog.as_mut()
+    }
 }
 
 /// All possible error that may occur during color grading look-up table creation.
@@ -562,6 +698,7 @@ pub struct CameraBuilder {
     exposure: Exposure,
     color_grading_lut: Option<ColorGradingLut>,
     color_grading_enabled: bool,
+    fog: Option<Fog>,
 }
 
 impl CameraBuilder {
@@ -579,6 +716,7 @@ impl CameraBuilder {
             exposure: Default::default(),
             color_grading_lut: None,
             color_grading_enabled: false,
+            fog: None,
         }
     }
 
@@ -636,6 +774,12 @@ impl CameraBuilder {
         self
     }
 
+    /// Sets desired fog settings.
+    pub fn with_fog(mut self, fog: Fog) -> Self {
+        self.fog = Some(fog);
+        self
+    }
+
     /// Creates new instance of camera.
     pub fn build_camera(self) -> Camera {
         Camera {
@@ -655,6 +799,7 @@ impl CameraBuilder {
             exposure: self.exposure,
             color_grading_lut: self.color_grading_lut,
             color_grading_enabled: self.color_grading_enabled,
+            fog: self.fog,
         }
     }
 
@@ -855,3 +1000,50 @@ impl Visit for SkyBox {
         visitor.leave_region()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{core::algebra::Vector3, scene::camera::Fog};
+
+    #[test]
+    fn test_fog_opacity() {
+        let camera = Vector3::new(0.0, 1.0, 0.0);
+        let point = Vector3::new(0.0, 1.0, 10.0);
+
+        // Without falloff height fog is a plain exponential fog.
+        let fog = Fog {
+            density: 0.1,
+            height_falloff: 0.0,
+            ..Default::default()
+        };
+        assert!((fog.opacity(camera, point) - (1.0 - (-1.0f32).exp())).abs() < 0.0001);
+
+        // Fog is thinner above the base height.
+        let height_fog = Fog {
+            height_falloff: 0.5,
+            ..fog
+        };
+        assert!(height_fog.opacity(camera, point) < fog.opacity(camera, point));
+        assert!(
+            height_fog.opacity(camera, Vector3::new(0.0, 5.0, 10.0))
+                < height_fog.opacity(camera, point)
+        );
+
+        // Nothing closer than the start distance is fogged.
+        let fog = Fog {
+            start_distance: 20.0,
+            ..fog
+        };
+        assert_eq!(fog.opacity(camera, point), 0.0);
+
+        let fog = Fog {
+            density: 0.0,
+            start_distance: 5.0,
+            end_distance: 15.0,
+            max_opacity: 0.4,
+            ..Default::default()
+        };
+        assert!((fog.opacity(camera, point) - 0.4).abs() < 0.0001);
+        assert!((fog.opacity(camera, Vector3::new(0.0, 1.0, 7.0)) - 0.2).abs() < 0.0001);
+    }
+}
@@ -91,6 +91,15 @@ fn remap_handles(old_new_mapping: &HashMap<Handle<Node>, Handle<Node>>, dest_gra
             }
         }
 
+        // Fog of a camera could be tied to a directional light.
+        if let Node::Camera(camera) = new_node {
+            if let Some(fog) = camera.fog_mut() {
+                if let Some(entry) = old_new_mapping.get(&fog.sun) {
+                    fog.sun = *entry;
+                }
+            }
+        }
+
         // LODs also have handles that must be remapped too.
         if let Some(lod_group) = new_node.lod_group_mut() {
             for level in lod_group.levels.iter_mut() {
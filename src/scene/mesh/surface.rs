@@ -953,8 +953,22 @@ impl Surface {
         }
     }
 
-    /// Calculates batch id.
+    /// Calculates batch id. Surfaces with same batch id share same data and material, so they
+    /// could be rendered together.
     pub fn batch_id(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(self.material_id());
+        hasher.write_u64(
+            self.data
+                .as_ref()
+                .map_or(0, |data| &**data as *const _ as u64),
+        );
+        hasher.finish()
+    }
+
+    /// Returns unique id of the material of the surface. It could be used to sort surfaces by
+    /// their materials.
+    pub fn material_id(&self) -> u64 {
         &*self.material as *const _ as u64
     }
 
@@ -14,6 +14,7 @@ pub mod mesh;
 pub mod node;
 pub mod particle_system;
 pub mod physics;
+pub mod reflection_probe;
 pub mod sprite;
 pub mod terrain;
 pub mod transform;
@@ -34,7 +35,7 @@ use crate::{
         PhysicsBinder,
     },
     material::{shader::SamplerFallback, PropertyValue},
-    resource::texture::Texture,
+    resource::texture::{Texture, TextureState},
     scene::{
         base::PhysicsBinding,
         debug::SceneDrawingContext,
@@ -353,6 +354,15 @@ impl Scene {
                             .unwrap()
                             .resolve(resource_manager.clone());
                     }
+                    for layer in terrain.foliage_layers() {
+                        for surface in layer.surfaces() {
+                            surface
+                                .material()
+                                .lock()
+                                .unwrap()
+                                .resolve(resource_manager.clone());
+                        }
+                    }
                 }
                 Node::Decal(decal) => {
                     decal.set_diffuse_texture(map_texture(
@@ -364,6 +374,21 @@ impl Scene {
                         resource_manager.clone(),
                     ));
                 }
+                Node::ReflectionProbe(probe) => {
+                    // Captured cube maps are procedural, their content is stored in the scene.
+                    let is_external = match probe.cubemap() {
+                        Some(cubemap) => {
+                            !matches!(&*cubemap.state(), TextureState::Ok(data) if data.is_procedural())
+                        }
+                        None => false,
+                    };
+                    if is_external {
+                        probe.set_cubemap(map_texture(
+                            probe.cubemap_value(),
+                            resource_manager.clone(),
+                        ));
+                    }
+                }
                 _ => (),
             }
         }
@@ -11,7 +11,8 @@ use crate::{
     },
     scene::{
         base::Base, camera::Camera, decal::Decal, light::Light, mesh::Mesh,
-        particle_system::ParticleSystem, sprite::Sprite, terrain::Terrain,
+        particle_system::ParticleSystem, reflection_probe::ReflectionProbe, sprite::Sprite,
+        terrain::Terrain,
     },
 };
 use std::ops::{Deref, DerefMut};
@@ -29,6 +30,7 @@ macro_rules! static_dispatch {
             Node::Sprite(v) => v.$func($($args),*),
             Node::Terrain(v) => v.$func($($args),*),
             Node::Decal(v) => v.$func($($args),*),
+            Node::ReflectionProbe(v) => v.$func($($args),*),
         }
     };
 }
@@ -141,6 +143,12 @@ pub enum Node {
     ///
     /// For more info see Decal node docs.
     Decal(Decal),
+
+    /// A node that captures its surroundings into a cube map, which is then used for local reflections
+    /// on the surfaces around it.
+    ///
+    /// For more info see [`ReflectionProbe`] node docs.
+    ReflectionProbe(ReflectionProbe),
 }
 
 macro_rules! static_dispatch_deref {
@@ -154,6 +162,7 @@ macro_rules! static_dispatch_deref {
             Node::Sprite(v) => v,
             Node::Terrain(v) => v,
             Node::Decal(v) => v,
+            Node::ReflectionProbe(v) => v,
         }
     };
 }
@@ -190,6 +199,7 @@ impl Node {
             5 => Ok(Self::ParticleSystem(Default::default())),
             6 => Ok(Self::Terrain(Default::default())),
             7 => Ok(Self::Decal(Default::default())),
+            8 => Ok(Self::ReflectionProbe(Default::default())),
             _ => Err(format!("Invalid node kind {}", id)),
         }
     }
@@ -205,6 +215,7 @@ impl Node {
             Self::ParticleSystem(_) => 5,
             Self::Terrain(_) => 6,
             Self::Decal(_) => 7,
+            Self::ReflectionProbe(_) => 8,
         }
     }
 
@@ -221,6 +232,7 @@ impl Node {
             Node::ParticleSystem(v) => Node::ParticleSystem(v.raw_copy()),
             Node::Terrain(v) => Node::Terrain(v.raw_copy()),
             Node::Decal(v) => Node::Decal(v.raw_copy()),
+            Node::ReflectionProbe(v) => Node::ReflectionProbe(v.raw_copy()),
         }
     }
 
@@ -231,4 +243,5 @@ impl Node {
     define_is_as!(Node : Sprite -> ref Sprite => fn is_sprite, fn as_sprite, fn as_sprite_mut);
     define_is_as!(Node : Terrain -> ref Terrain => fn is_terrain, fn as_terrain, fn as_terrain_mut);
     define_is_as!(Node : Decal -> ref Decal => fn is_decal, fn as_decal, fn as_decal_mut);
+    define_is_as!(Node : ReflectionProbe -> ref ReflectionProbe => fn is_reflection_probe, fn as_reflection_probe, fn as_reflection_probe_mut);
 }
@@ -38,7 +38,7 @@ use rapier3d::{
     },
     geometry::{BroadPhase, Collider, ColliderBuilder, InteractionGroups, NarrowPhase},
     na::{DMatrix, Dynamic, Isometry3, Point3, Translation, UnitQuaternion, VecStorage, Vector3},
-    parry::shape::{FeatureId, SharedShape, TriMesh},
+    parry::shape::{FeatureId, HeightField, HeightFieldCellStatus, SharedShape, TriMesh},
     pipeline::{EventHandler, PhysicsPipeline, QueryPipeline},
 };
 use std::{
@@ -599,49 +599,63 @@ impl Physics {
         }
     }
 
-    /// Creates height field shape from given terrain.
+    /// Creates height field shape from given terrain. Holes of the terrain are cut in the height
+    /// field too.
     pub fn make_heightfield(terrain: &Terrain) -> SharedShape {
         assert!(!terrain.chunks_ref().is_empty());
 
-        // Count rows and columns.
+        // Count rows and columns, neighbour chunks share points on their edges.
         let first_chunk = terrain.chunks_ref().first().unwrap();
-        let chunk_size = Vector2::new(
-            first_chunk.width_point_count(),
-            first_chunk.length_point_count(),
+        let chunk_cells = Vector2::new(
+            first_chunk.width_point_count() - 1,
+            first_chunk.length_point_count() - 1,
         );
-        let nrows = chunk_size.y * terrain.length_chunk_count() as u32;
-        let ncols = chunk_size.x * terrain.width_chunk_count() as u32;
+        let width_chunks = terrain.width_chunk_count() as u32;
+        let length_chunks = terrain.length_chunk_count() as u32;
+        let nrows = chunk_cells.y * length_chunks + 1;
+        let ncols = chunk_cells.x * width_chunks + 1;
+
+        // Maps a point (or a cell) of combined height map to a chunk and a point in it.
+        let locate = |row: u32, col: u32| {
+            let cx = (col / chunk_cells.x).min(width_chunks - 1);
+            let cz = (row / chunk_cells.y).min(length_chunks - 1);
+            let chunk = &terrain.chunks_ref()[(cz * width_chunks + cx) as usize];
+            (chunk, col - cx * chunk_cells.x, row - cz * chunk_cells.y)
+        };
 
         // Combine height map of each chunk into bigger one.
-        let mut ox = 0;
-        let mut oz = 0;
         let mut data = vec![0.0; (nrows * ncols) as usize];
-        for cz in 0..terrain.length_chunk_count() {
-            for cx in 0..terrain.width_chunk_count() {
-                let chunk = &terrain.chunks_ref()[cz * terrain.width_chunk_count() + cx];
-
-                for z in 0..chunk.length_point_count() {
-                    for x in 0..chunk.width_point_count() {
-                        let value = chunk.heightmap()[(z * chunk.width_point_count() + x) as usize];
-                        data[((ox + x) * nrows + oz + z) as usize] = value;
-                    }
-                }
-
-                ox += chunk_size.x;
+        for row in 0..nrows {
+            for col in 0..ncols {
+                let (chunk, x, z) = locate(row, col);
+                data[(col * nrows + row) as usize] =
+                    chunk.heightmap()[(z * chunk.width_point_count() + x) as usize];
             }
-
-            ox = 0;
-            oz += chunk_size.y;
         }
 
-        SharedShape::heightfield(
+        let mut heightfield = HeightField::new(
             DMatrix::from_data(VecStorage::new(
                 Dynamic::new(nrows as usize),
                 Dynamic::new(ncols as usize),
                 data,
             )),
             Vector3::new(terrain.width(), 1.0, terrain.length()),
-        )
+        );
+
+        for row in 0..nrows - 1 {
+            for col in 0..ncols - 1 {
+                let (chunk, x, z) = locate(row, col);
+                if chunk.is_hole(x, z) {
+                    heightfield.set_cell_status(
+                        row as usize,
+                        col as usize,
+                        HeightFieldCellStatus::CELL_REMOVED,
+                    );
+                }
+            }
+        }
+
+        SharedShape::new(heightfield)
     }
 
     /// Small helper that creates static physics geometry from given mesh.
@@ -0,0 +1,695 @@
+//! Reflection probe is a source of local reflections for the surfaces around it.
+//!
+//! For more info see [`ReflectionProbe`]
+
+use crate::{
+    core::{
+        algebra::Vector3, arrayvec::ArrayVec, math::aabb::AxisAlignedBoundingBox, pool::Handle,
+        visitor::prelude::*,
+    },
+    resource::texture::Texture,
+    scene::{
+        base::{Base, BaseBuilder},
+        graph::Graph,
+        node::Node,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    ops::{Deref, DerefMut},
+};
+
+/// Maximum amount of probes that can affect a single surface.
+pub const MAX_PROBES_PER_SURFACE: usize = 2;
+
+/// Reflection probe captures its surroundings into a cube map which is then used for reflections
+/// on the surfaces that are inside of probe's influence volume. Without probes the only sources of
+/// reflections are camera's environment map and skybox, which means that interiors will reflect
+/// the sky.
+///
+/// # Influence volume
+///
+/// Influence volume is an axis-aligned box centered at the global position of the probe, its size
+/// is defined by half-extents. Rotation and scale of the probe are ignored. A probe has full
+/// influence on every point that lies deeper than `blend distance` inside the box, then influence
+/// gradually fades out to zero at the box boundaries. Surfaces that are not covered by any probe
+/// will use camera's environment map.
+///
+/// # Parallax correction
+///
+/// A cube map is captured from a single point, so reflections will look "infinitely far" if the
+/// cube map is sampled by reflection vector directly. To fix this, probe has another box (projection
+/// box) that should roughly match the geometry of the room around the probe. The reflection vector
+/// is intersected with the box and the cube map is sampled in the direction of the intersection
+/// point. This is called box projection and it can be disabled for outdoor probes.
+///
+/// # Capturing
+///
+/// A probe does nothing until its cube map is captured. Use
+/// [`Renderer::capture_reflection_probe`](crate::renderer::Renderer::capture_reflection_probe)
+/// to render the scene from the position of the probe. Captured cube map is stored in the probe and
+/// will be saved together with the scene, so there is no need to re-capture probes on every load.
+///
+/// # Limitations
+///
+/// Probes are applied to the surfaces that are rendered using deferred render path. Each surface
+/// is affected by no more than [`MAX_PROBES_PER_SURFACE`] probes which are selected using
+/// position of surface owner. Very large surfaces (like terrains) should be split in smaller
+/// pieces to get correct reflections.
+///
+/// # Example
+///
+/// ```
+/// use rg3d::{
+///     core::{algebra::Vector3, pool::Handle},
+///     scene::{
+///         base::BaseBuilder, graph::Graph, node::Node,
+///         reflection_probe::ReflectionProbeBuilder, transform::TransformBuilder,
+///     },
+/// };
+///
+/// fn create_room_probe(graph: &mut Graph) -> Handle<Node> {
+///     ReflectionProbeBuilder::new(
+///         BaseBuilder::new().with_local_transform(
+///             TransformBuilder::new()
+///                 .with_local_position(Vector3::new(0.0, 1.5, 0.0))
+///                 .build(),
+///         ),
+///     )
+///     .with_influence_extents(Vector3::new(4.0, 1.5, 3.0))
+///     .with_blend_distance(0.5)
+///     .build(graph)
+/// }
+/// ```
+#[derive(Debug, Visit)]
+pub struct ReflectionProbe {
+    base: Base,
+    influence_extents: Vector3<f32>,
+    projection_extents: Vector3<f32>,
+    box_projection: bool,
+    blend_distance: f32,
+    intensity: f32,
+    resolution: u32,
+    z_near: f32,
+    z_far: f32,
+    cubemap: Option<Texture>,
+}
+
+impl Default for ReflectionProbe {
+    fn default() -> Self {
+        ReflectionProbeBuilder::new(BaseBuilder::new()).build_reflection_probe()
+    }
+}
+
+impl Deref for ReflectionProbe {
+    type Target = Base;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl DerefMut for ReflectionProbe {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+impl ReflectionProbe {
+    /// Creates a raw copy of the reflection probe node.
+    pub fn raw_copy(&self) -> Self {
+        Self {
+            base: self.base.raw_copy(),
+            influence_extents: self.influence_extents,
+            projection_extents: self.projection_extents,
+            box_projection: self.box_projection,
+            blend_distance: self.blend_distance,
+            intensity: self.intensity,
+            resolution: self.resolution,
+            z_near: self.z_near,
+            z_far: self.z_far,
+            cubemap: self.cubemap.clone(),
+        }
+    }
+
+    /// Sets new half-extents of the influence volume.
+    pub fn set_influence_extents(&mut self, extents: Vector3<f32>) {
+        self.influence_extents = extents;
+    }
+
+    /// Returns current half-extents of the influence volume.
+    pub fn influence_extents(&self) -> Vector3<f32> {
+        self.influence_extents
+    }
+
+    /// Sets new half-extents of the box that is used for parallax correction.
+    pub fn set_projection_extents(&mut self, extents: Vector3<f32>) {
+        self.projection_extents = extents;
+    }
+
+    /// Returns current half-extents of the box that is used for parallax correction.
+    pub fn projection_extents(&self) -> Vector3<f32> {
+        self.projection_extents
+    }
+
+    /// Enables or disables box projection (parallax correction).
+    pub fn set_box_projection(&mut self, enabled: bool) {
+        self.box_projection = enabled;
+    }
+
+    /// Returns true if box projection is enabled.
+    pub fn is_box_projection(&self) -> bool {
+        self.box_projection
+    }
+
+    /// Sets distance from the boundaries of influence volume at which influence starts to fade out.
+    pub fn set_blend_distance(&mut self, distance: f32) {
+        self.blend_distance = distance.max(0.0);
+    }
+
+    /// Returns current blend distance.
+    pub fn blend_distance(&self) -> f32 {
+        self.blend_distance
+    }
+
+    /// Sets new intensity multiplier of the reflections.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity.max(0.0);
+    }
+
+    /// Returns current intensity multiplier of the reflections.
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Sets size of a face of the cube map in pixels, it will be used on next capture.
+    pub fn set_resolution(&mut self, resolution: u32) {
+        self.resolution = resolution.max(1);
+    }
+
+    /// Returns size of a face of the cube map in pixels.
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+
+    /// Sets near clipping plane distance that will be used for capturing.
+    pub fn set_z_near(&mut self, z_near: f32) {
+        self.z_near = z_near;
+    }
+
+    /// Returns near clipping plane distance that will be used for capturing.
+    pub fn z_near(&self) -> f32 {
+        self.z_near
+    }
+
+    /// Sets far clipping plane distance that will be used for capturing.
+    pub fn set_z_far(&mut self, z_far: f32) {
+        self.z_far = z_far;
+    }
+
+    /// Returns far clipping plane distance that will be used for capturing.
+    pub fn z_far(&self) -> f32 {
+        self.z_far
+    }
+
+    /// Sets new cube map. Normally it is set by the renderer when the probe is captured, but
+    /// it could also be a pre-made cube map.
+    pub fn set_cubemap(&mut self, cubemap: Option<Texture>) -> Option<Texture> {
+        std::mem::replace(&mut self.cubemap, cubemap)
+    }
+
+    /// Returns current cube map.
+    pub fn cubemap(&self) -> Option<&Texture> {
+        self.cubemap.as_ref()
+    }
+
+    /// Returns current cube map.
+    pub fn cubemap_value(&self) -> Option<Texture> {
+        self.cubemap.clone()
+    }
+
+    /// Returns influence volume in world coordinates.
+    pub fn influence_bounds(&self) -> AxisAlignedBoundingBox {
+        let position = self.global_position();
+        AxisAlignedBoundingBox::from_min_max(
+            position - self.influence_extents,
+            position + self.influence_extents,
+        )
+    }
+
+    /// Returns box that is used for parallax correction in world coordinates.
+    pub fn projection_bounds(&self) -> AxisAlignedBoundingBox {
+        let position = self.global_position();
+        AxisAlignedBoundingBox::from_min_max(
+            position - self.projection_extents,
+            position + self.projection_extents,
+        )
+    }
+
+    /// Returns influence of the probe on the given point in [0; 1] range. Influence is zero
+    /// outside of the influence volume and it is one deeper than blend distance inside the volume.
+    pub fn influence(&self, point: Vector3<f32>) -> f32 {
+        let offset = point - self.global_position();
+
+        let mut depth = f32::MAX;
+        for i in 0..3 {
+            depth = depth.min(self.influence_extents[i] - offset[i].abs());
+        }
+
+        if depth < 0.0 {
+            0.0
+        } else if self.blend_distance > 0.0 {
+            (depth / self.blend_distance).min(1.0)
+        } else {
+            1.0
+        }
+    }
+
+    fn influence_volume(&self) -> f32 {
+        8.0 * self.influence_extents.x * self.influence_extents.y * self.influence_extents.z
+    }
+}
+
+/// Defines how many probes could affect a single surface.
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ReflectionProbeBlendMode {
+    /// Only one probe with the highest influence will be used, if there are multiple probes with
+    /// the same influence, the smallest one will be selected. It is the fastest mode, but there
+    /// will be noticeable seams between surfaces that use different probes.
+    Nearest,
+    /// Up to [`MAX_PROBES_PER_SURFACE`] probes will be blended together. Smaller probes have
+    /// priority over larger ones, which means that a probe of a room will fully override a probe
+    /// of a building around the room, while still allowing smooth transitions near the boundaries
+    /// of the room.
+    Blended,
+}
+
+impl Default for ReflectionProbeBlendMode {
+    fn default() -> Self {
+        Self::Blended
+    }
+}
+
+/// Contribution of a single probe to the reflections of a surface.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ProbeContribution {
+    /// Handle of a reflection probe.
+    pub probe: Handle<Node>,
+    /// Weight of the probe in [0; 1] range.
+    pub weight: f32,
+}
+
+/// A set of probes that affect a surface. Sum of all weights (including the environment weight)
+/// is always equal to one.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProbeSelection {
+    /// Probes that affect the surface.
+    pub probes: ArrayVec<ProbeContribution, MAX_PROBES_PER_SURFACE>,
+    /// Weight of the camera environment map.
+    pub environment_weight: f32,
+}
+
+impl Default for ProbeSelection {
+    fn default() -> Self {
+        Self {
+            probes: Default::default(),
+            environment_weight: 1.0,
+        }
+    }
+}
+
+/// Selects reflection probes that affect the given point and calculates their weights. Probes that
+/// are hidden or have no cube map are ignored. Remaining weight (if any) goes to the camera
+/// environment map.
+pub fn select_probes<'a, I>(
+    probes: I,
+    point: Vector3<f32>,
+    mode: ReflectionProbeBlendMode,
+) -> ProbeSelection
+where
+    I: IntoIterator<Item = (Handle<Node>, &'a ReflectionProbe)>,
+{
+    let mut candidates = probes
+        .into_iter()
+        .filter(|(_, probe)| probe.global_visibility() && probe.cubemap.is_some())
+        .filter_map(|(handle, probe)| {
+            let weight = probe.influence(point);
+            if weight > 0.0 {
+                Some((handle, weight, probe.influence_volume()))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let by_volume = |a: &(Handle<Node>, f32, f32), b: &(Handle<Node>, f32, f32)| {
+        a.2.partial_cmp(&b.2)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.0.index().cmp(&b.0.index()))
+    };
+
+    let mut selection = ProbeSelection::default();
+
+    match mode {
+        ReflectionProbeBlendMode::Nearest => {
+            candidates.sort_by(|a, b| {
+                b.1.partial_cmp(&a.1)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| by_volume(a, b))
+            });
+
+            if let Some(&(probe, weight, _)) = candidates.first() {
+                selection.probes.push(ProbeContribution { probe, weight });
+                selection.environment_weight = 1.0 - weight;
+            }
+        }
+        ReflectionProbeBlendMode::Blended => {
+            candidates.sort_by(by_volume);
+
+            // Each probe takes as much as it can from the weight that is left from smaller probes.
+            for (probe, weight, _) in candidates {
+                if selection.probes.is_full() || selection.environment_weight <= 0.0 {
+                    break;
+                }
+
+                let weight = weight.min(selection.environment_weight);
+                selection.probes.push(ProbeContribution { probe, weight });
+                selection.environment_weight -= weight;
+            }
+
+            selection.environment_weight = selection.environment_weight.max(0.0);
+        }
+    }
+
+    selection
+}
+
+/// Allows you to create a reflection probe in a declarative manner.
+pub struct ReflectionProbeBuilder {
+    base_builder: BaseBuilder,
+    influence_extents: Vector3<f32>,
+    projection_extents: Option<Vector3<f32>>,
+    box_projection: bool,
+    blend_distance: f32,
+    intensity: f32,
+    resolution: u32,
+    z_near: f32,
+    z_far: f32,
+    cubemap: Option<Texture>,
+}
+
+impl ReflectionProbeBuilder {
+    /// Creates a new instance of the builder.
+    pub fn new(base_builder: BaseBuilder) -> Self {
+        Self {
+            base_builder,
+            influence_extents: Vector3::new(5.0, 5.0, 5.0),
+            projection_extents: None,
+            box_projection: true,
+            blend_distance: 1.0,
+            intensity: 1.0,
+            resolution: 128,
+            z_near: 0.025,
+            z_far: 128.0,
+            cubemap: None,
+        }
+    }
+
+    /// Sets desired half-extents of the influence volume.
+    pub fn with_influence_extents(mut self, extents: Vector3<f32>) -> Self {
+        self.influence_extents = extents;
+        self
+    }
+
+    /// Sets desired half-extents of the projection box. If not set, influence extents will be used.
+    pub fn with_projection_extents(mut self, extents: Vector3<f32>) -> Self {
+        self.projection_extents = Some(extents);
+        self
+    }
+
+    /// Enables or disables box projection.
+    pub fn with_box_projection(mut self, enabled: bool) -> Self {
+        self.box_projection = enabled;
+        self
+    }
+
+    /// Sets desired blend distance.
+    pub fn with_blend_distance(mut self, distance: f32) -> Self {
+        self.blend_distance = distance.max(0.0);
+        self
+    }
+
+    /// Sets desired intensity.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity.max(0.0);
+        self
+    }
+
+    /// Sets desired size of a face of the cube map.
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+
+    /// Sets desired near clipping plane distance for capturing.
+    pub fn with_z_near(mut self, z_near: f32) -> Self {
+        self.z_near = z_near;
+        self
+    }
+
+    /// Sets desired far clipping plane distance for capturing.
+    pub fn with_z_far(mut self, z_far: f32) -> Self {
+        self.z_far = z_far;
+        self
+    }
+
+    /// Sets pre-made cube map.
+    pub fn with_cubemap(mut self, cubemap: Texture) -> Self {
+        self.cubemap = Some(cubemap);
+        self
+    }
+
+    /// Creates new reflection probe.
+    pub fn build_reflection_probe(self) -> ReflectionProbe {
+        ReflectionProbe {
+            base: self.base_builder.build_base(),
+            influence_extents: self.influence_extents,
+            projection_extents: self.projection_extents.unwrap_or(self.influence_extents),
+            box_projection: self.box_projection,
+            blend_distance: self.blend_distance,
+            intensity: self.intensity,
+            resolution: self.resolution,
+            z_near: self.z_near,
+            z_far: self.z_far,
+            cubemap: self.cubemap,
+        }
+    }
+
+    /// Creates new reflection probe node.
+    pub fn build_node(self) -> Node {
+        Node::ReflectionProbe(self.build_reflection_probe())
+    }
+
+    /// Creates new instance of reflection probe node and puts it in the given graph.
+    pub fn build(self, graph: &mut Graph) -> Handle<Node> {
+        graph.add_node(self.build_node())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{algebra::Vector3, pool::Handle},
+        resource::texture::{Texture, TextureKind, TexturePixelKind},
+        scene::{
+            base::BaseBuilder,
+            graph::Graph,
+            node::Node,
+            reflection_probe::{
+                select_probes, ReflectionProbe, ReflectionProbeBlendMode, ReflectionProbeBuilder,
+            },
+            transform::TransformBuilder,
+        },
+    };
+
+    fn make_probe(
+        graph: &mut Graph,
+        position: Vector3<f32>,
+        extents: Vector3<f32>,
+        blend_distance: f32,
+    ) -> Handle<Node> {
+        let cubemap = Texture::from_bytes(
+            TextureKind::Cube {
+                width: 1,
+                height: 1,
+            },
+            TexturePixelKind::RGBA8,
+            vec![0; 24],
+            false,
+        )
+        .unwrap();
+
+        ReflectionProbeBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(position)
+                    .build(),
+            ),
+        )
+        .with_influence_extents(extents)
+        .with_blend_distance(blend_distance)
+        .with_cubemap(cubemap)
+        .build(graph)
+    }
+
+    fn probes(graph: &Graph) -> Vec<(Handle<Node>, &ReflectionProbe)> {
+        graph
+            .pair_iter()
+            .filter_map(|(handle, node)| {
+                if let Node::ReflectionProbe(probe) = node {
+                    Some((handle, probe))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn assert_weight(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1.0e-5,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_influence() {
+        let mut graph = Graph::new();
+        let probe = make_probe(
+            &mut graph,
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(2.0, 1.0, 1.0),
+            0.5,
+        );
+        graph.update_hierarchical_data();
+
+        let probe = graph[probe].as_reflection_probe();
+        assert_weight(probe.influence(Vector3::new(1.0, 0.0, 0.0)), 1.0);
+        assert_weight(probe.influence(Vector3::new(2.0, 0.25, 0.0)), 1.0);
+        assert_weight(probe.influence(Vector3::new(2.75, 0.0, 0.0)), 0.5);
+        assert_weight(probe.influence(Vector3::new(1.0, 0.0, -0.9)), 0.2);
+        assert_weight(probe.influence(Vector3::new(3.5, 0.0, 0.0)), 0.0);
+
+        let bounds = probe.influence_bounds();
+        assert_eq!(bounds.min, Vector3::new(-1.0, -1.0, -1.0));
+        assert_eq!(bounds.max, Vector3::new(3.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_select_nearest() {
+        let mut graph = Graph::new();
+        let a = make_probe(&mut graph, Vector3::default(), Vector3::repeat(2.0), 1.0);
+        let b = make_probe(
+            &mut graph,
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::repeat(2.0),
+            1.0,
+        );
+        graph.update_hierarchical_data();
+
+        let mode = ReflectionProbeBlendMode::Nearest;
+
+        // Deep inside of the first probe.
+        let selection = select_probes(probes(&graph), Vector3::default(), mode);
+        assert_eq!(selection.probes.len(), 1);
+        assert_eq!(selection.probes[0].probe, a);
+        assert_weight(selection.probes[0].weight, 1.0);
+        assert_weight(selection.environment_weight, 0.0);
+
+        // In the overlapping region, but closer to the second probe.
+        let selection = select_probes(probes(&graph), Vector3::new(1.75, 0.0, 0.0), mode);
+        assert_eq!(selection.probes.len(), 1);
+        assert_eq!(selection.probes[0].probe, b);
+        assert_weight(selection.probes[0].weight, 0.75);
+        assert_weight(selection.environment_weight, 0.25);
+
+        // Outside of every probe.
+        let selection = select_probes(probes(&graph), Vector3::new(0.0, 5.0, 0.0), mode);
+        assert!(selection.probes.is_empty());
+        assert_weight(selection.environment_weight, 1.0);
+    }
+
+    #[test]
+    fn test_select_blended() {
+        let mut graph = Graph::new();
+        let building = make_probe(&mut graph, Vector3::default(), Vector3::repeat(10.0), 1.0);
+        let room = make_probe(&mut graph, Vector3::default(), Vector3::repeat(2.0), 1.0);
+        let neighbour = make_probe(
+            &mut graph,
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::repeat(2.0),
+            1.0,
+        );
+        graph.update_hierarchical_data();
+
+        let mode = ReflectionProbeBlendMode::Blended;
+
+        // Room fully overrides the building.
+        let selection = select_probes(probes(&graph), Vector3::new(-0.5, 0.0, 0.0), mode);
+        assert_eq!(selection.probes.len(), 1);
+        assert_eq!(selection.probes[0].probe, room);
+        assert_weight(selection.probes[0].weight, 1.0);
+        assert_weight(selection.environment_weight, 0.0);
+
+        // Between the room and its neighbour, building gets nothing because only two probes
+        // are allowed.
+        let selection = select_probes(probes(&graph), Vector3::new(1.5, 0.0, 0.0), mode);
+        assert_eq!(selection.probes.len(), 2);
+        assert_eq!(selection.probes[0].probe, room);
+        assert_weight(selection.probes[0].weight, 0.5);
+        assert_eq!(selection.probes[1].probe, neighbour);
+        assert_weight(selection.probes[1].weight, 0.5);
+        assert_weight(selection.environment_weight, 0.0);
+
+        // Near the wall of the room, the building fills the rest.
+        let selection = select_probes(probes(&graph), Vector3::new(0.0, -1.75, 0.0), mode);
+        assert_eq!(selection.probes.len(), 2);
+        assert_eq!(selection.probes[0].probe, room);
+        assert_weight(selection.probes[0].weight, 0.25);
+        assert_eq!(selection.probes[1].probe, building);
+        assert_weight(selection.probes[1].weight, 0.75);
+        assert_weight(selection.environment_weight, 0.0);
+
+        // Near the boundary of the building, the rest goes to the environment.
+        let selection = select_probes(probes(&graph), Vector3::new(0.0, 9.5, 0.0), mode);
+        assert_eq!(selection.probes.len(), 1);
+        assert_eq!(selection.probes[0].probe, building);
+        assert_weight(selection.probes[0].weight, 0.5);
+        assert_weight(selection.environment_weight, 0.5);
+    }
+
+    #[test]
+    fn test_select_ignores_uncaptured_and_hidden_probes() {
+        let mut graph = Graph::new();
+        let visible = make_probe(&mut graph, Vector3::default(), Vector3::repeat(10.0), 0.0);
+        let hidden = make_probe(&mut graph, Vector3::default(), Vector3::repeat(1.0), 0.0);
+        graph[hidden].set_visibility(false);
+        let uncaptured = make_probe(&mut graph, Vector3::default(), Vector3::repeat(2.0), 0.0);
+        graph[uncaptured]
+            .as_reflection_probe_mut()
+            .set_cubemap(None);
+        graph.update_hierarchical_data();
+
+        let selection = select_probes(
+            probes(&graph),
+            Vector3::default(),
+            ReflectionProbeBlendMode::Blended,
+        );
+        assert_eq!(selection.probes.len(), 1);
+        assert_eq!(selection.probes[0].probe, visible);
+        assert_weight(selection.probes[0].weight, 1.0);
+    }
+}
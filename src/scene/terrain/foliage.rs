@@ -0,0 +1,607 @@
+//! Foliage is a set of small objects (grass, flowers, small stones, etc.) scattered over a
+//! terrain. There are usually thousands of such objects, so it is too expensive to make a scene
+//! node for each of them. Instead, a foliage layer stores only transforms of its instances,
+//! grouped by chunks of the terrain, and all instances are rendered using instancing.
+//!
+//! Foliage can be painted by a brush (see [`BrushMode::PaintFoliage`]) or scattered procedurally
+//! using masks of terrain layers and slope rules (see [`Terrain::scatter_foliage`]).
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Point3, Vector2, Vector3},
+        math::{aabb::AxisAlignedBoundingBox, lerpf},
+        visitor::prelude::*,
+    },
+    rand::{rngs::StdRng, thread_rng, Rng, SeedableRng},
+    scene::{
+        mesh::surface::Surface,
+        terrain::{Brush, BrushMode, Chunk, Terrain},
+    },
+};
+
+/// Single instance of foliage.
+#[derive(Copy, Clone, Debug, PartialEq, Visit)]
+pub struct FoliageInstance {
+    /// Position of the instance in local coordinates of the terrain.
+    pub position: Vector3<f32>,
+    /// Rotation of the instance around vertical axis in radians.
+    pub rotation: f32,
+    /// Uniform scale of the instance.
+    pub scale: f32,
+}
+
+impl Default for FoliageInstance {
+    fn default() -> Self {
+        Self {
+            position: Default::default(),
+            rotation: 0.0,
+            scale: 1.0,
+        }
+    }
+}
+
+impl FoliageInstance {
+    /// Returns transform of the instance relative to the terrain.
+    pub fn local_transform(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.position)
+            * Matrix4::from_axis_angle(&Vector3::y_axis(), self.rotation)
+            * Matrix4::new_scaling(self.scale)
+    }
+}
+
+/// Foliage layer is a set of instances of the same object, every instance is rendered using all
+/// surfaces of the layer.
+#[derive(Debug, Clone, Visit)]
+pub struct FoliageLayer {
+    surfaces: Vec<Surface>,
+    // Instances of each chunk of the terrain.
+    instances: Vec<Vec<FoliageInstance>>,
+    draw_distance: f32,
+    fade_distance: f32,
+}
+
+impl Default for FoliageLayer {
+    fn default() -> Self {
+        Self {
+            surfaces: Default::default(),
+            instances: Default::default(),
+            draw_distance: 50.0,
+            fade_distance: 10.0,
+        }
+    }
+}
+
+impl FoliageLayer {
+    /// Creates new foliage layer without instances.
+    ///
+    /// # Notes
+    ///
+    /// Only data of procedural surfaces is saved together with the terrain, surfaces with data
+    /// from models must be set again after loading.
+    pub fn new(surfaces: Vec<Surface>) -> Self {
+        Self {
+            surfaces,
+            ..Default::default()
+        }
+    }
+
+    /// Returns surfaces used to render each instance.
+    pub fn surfaces(&self) -> &[Surface] {
+        &self.surfaces
+    }
+
+    /// Sets new surfaces used to render each instance.
+    pub fn set_surfaces(&mut self, surfaces: Vec<Surface>) {
+        self.surfaces = surfaces;
+    }
+
+    /// Returns instances that belong to given chunk of the terrain.
+    pub fn chunk_instances(&self, chunk_index: usize) -> &[FoliageInstance] {
+        self.instances
+            .get(chunk_index)
+            .map(|instances| instances.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns total amount of instances.
+    pub fn instance_count(&self) -> usize {
+        self.instances.iter().map(|instances| instances.len()).sum()
+    }
+
+    /// Removes every instance of the layer.
+    pub fn clear(&mut self) {
+        for instances in self.instances.iter_mut() {
+            instances.clear();
+        }
+    }
+
+    /// Sets distance from an observer at which instances are not rendered anymore.
+    pub fn set_draw_distance(&mut self, distance: f32) {
+        self.draw_distance = distance.max(0.0);
+    }
+
+    /// Returns distance from an observer at which instances are not rendered anymore.
+    pub fn draw_distance(&self) -> f32 {
+        self.draw_distance
+    }
+
+    /// Sets width of a band before draw distance in which instances are gradually shrunk, so
+    /// they do not pop out of existence when an observer moves away.
+    pub fn set_fade_distance(&mut self, distance: f32) {
+        self.fade_distance = distance.max(0.0);
+    }
+
+    /// Returns width of fade band, see [`Self::set_fade_distance`] for more info.
+    pub fn fade_distance(&self) -> f32 {
+        self.fade_distance
+    }
+}
+
+/// Rules of procedural scattering of foliage, see [`Terrain::scatter_foliage`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoliageScatter {
+    /// Index of a terrain layer that defines where foliage grows, amount of instances is
+    /// proportional to weight of the layer (see [`Terrain::layer_weights`]). If not set,
+    /// foliage grows everywhere.
+    pub mask_layer: Option<usize>,
+    /// Amount of instances per square unit where foliage has full density.
+    pub density: f32,
+    /// Minimum slope of terrain in radians where foliage grows.
+    pub min_slope: f32,
+    /// Maximum slope of terrain in radians where foliage grows.
+    pub max_slope: f32,
+    /// Minimum scale of an instance.
+    pub min_scale: f32,
+    /// Maximum scale of an instance.
+    pub max_scale: f32,
+    /// Seed of random number generator, same seed gives the same result.
+    pub seed: u64,
+}
+
+impl Default for FoliageScatter {
+    fn default() -> Self {
+        Self {
+            mask_layer: None,
+            density: 1.0,
+            min_slope: 0.0,
+            max_slope: std::f32::consts::FRAC_PI_4,
+            min_scale: 0.8,
+            max_scale: 1.2,
+            seed: 0,
+        }
+    }
+}
+
+// Returns height and normal of a chunk at given normalized coordinates, or `None` if there is a
+// hole at the point.
+fn sample_surface(chunk: &Chunk, u: f32, v: f32) -> Option<(f32, Vector3<f32>)> {
+    let x = u.clamp(0.0, 1.0) * (chunk.width_point_count - 1) as f32;
+    let z = v.clamp(0.0, 1.0) * (chunk.length_point_count - 1) as f32;
+    let cell_x = (x as u32).min(chunk.width_point_count - 2);
+    let cell_z = (z as u32).min(chunk.length_point_count - 2);
+    if chunk.is_hole(cell_x, cell_z) {
+        return None;
+    }
+
+    let height = |x: u32, z: u32| chunk.heightmap[(z * chunk.width_point_count + x) as usize];
+    let (h00, h10) = (height(cell_x, cell_z), height(cell_x + 1, cell_z));
+    let (h01, h11) = (height(cell_x, cell_z + 1), height(cell_x + 1, cell_z + 1));
+    let (tx, tz) = (x - cell_x as f32, z - cell_z as f32);
+
+    let cell_width = chunk.width / (chunk.width_point_count - 1) as f32;
+    let cell_length = chunk.length / (chunk.length_point_count - 1) as f32;
+    let dx = lerpf(h10 - h00, h11 - h01, tz) / cell_width;
+    let dz = lerpf(h01 - h00, h11 - h10, tx) / cell_length;
+
+    Some((
+        lerpf(lerpf(h00, h10, tx), lerpf(h01, h11, tx), tz),
+        Vector3::new(-dx, 1.0, -dz).normalize(),
+    ))
+}
+
+impl Terrain {
+    /// Adds new foliage layer to the terrain and returns its index.
+    pub fn add_foliage_layer(&mut self, mut layer: FoliageLayer) -> usize {
+        layer
+            .instances
+            .resize_with(self.chunks.len(), Default::default);
+        self.foliage_layers.push(layer);
+        self.foliage_layers.len() - 1
+    }
+
+    /// Removes foliage layer at given index and returns it.
+    pub fn remove_foliage_layer(&mut self, index: usize) -> FoliageLayer {
+        self.foliage_layers.remove(index)
+    }
+
+    /// Returns a reference to foliage layers.
+    pub fn foliage_layers(&self) -> &[FoliageLayer] {
+        &self.foliage_layers
+    }
+
+    /// Returns a reference to foliage layers.
+    pub fn foliage_layers_mut(&mut self) -> &mut [FoliageLayer] {
+        &mut self.foliage_layers
+    }
+
+    /// Replaces all instances of given foliage layer with new ones, scattered procedurally using
+    /// given rules. Instances are never placed in holes. Does nothing if there is no foliage
+    /// layer with given index.
+    pub fn scatter_foliage(&mut self, layer: usize, scatter: &FoliageScatter) {
+        if layer >= self.foliage_layers.len() {
+            return;
+        }
+
+        let mut rng = StdRng::seed_from_u64(scatter.seed);
+        let mut instances = Vec::with_capacity(self.chunks.len());
+
+        for (chunk_index, chunk) in self.chunks.iter().enumerate() {
+            let mut chunk_instances = Vec::new();
+
+            let expected_count = scatter.density.max(0.0) * chunk.width * chunk.length;
+            let mut count = expected_count as usize;
+            if rng.gen::<f32>() < expected_count.fract() {
+                count += 1;
+            }
+
+            for _ in 0..count {
+                // Generate all random values first, so rejected candidates do not affect others.
+                let (u, v) = (rng.gen::<f32>(), rng.gen::<f32>());
+                let chance = rng.gen::<f32>();
+                let rotation = rng.gen_range(0.0..std::f32::consts::TAU);
+                let scale = lerpf(scatter.min_scale, scatter.max_scale, rng.gen::<f32>());
+
+                let weight = match scatter.mask_layer {
+                    Some(mask_layer) => self
+                        .chunk_layer_weights(chunk_index, u, v)
+                        .get(mask_layer)
+                        .cloned()
+                        .unwrap_or_default(),
+                    None => 1.0,
+                };
+                if chance >= weight {
+                    continue;
+                }
+
+                if let Some((height, normal)) = sample_surface(chunk, u, v) {
+                    let slope = normal.y.clamp(-1.0, 1.0).acos();
+                    if slope >= scatter.min_slope && slope <= scatter.max_slope {
+                        chunk_instances.push(FoliageInstance {
+                            position: Vector3::new(
+                                chunk.position.x + u * chunk.width,
+                                height,
+                                chunk.position.z + v * chunk.length,
+                            ),
+                            rotation,
+                            scale,
+                        });
+                    }
+                }
+            }
+
+            instances.push(chunk_instances);
+        }
+
+        self.foliage_layers[layer].instances = instances;
+    }
+
+    pub(super) fn draw_foliage(&mut self, brush: &Brush, center: Vector2<f32>) {
+        let mut rng = thread_rng();
+        let (min, max) = brush.shape.bounds(center);
+
+        match brush.mode {
+            // There is no such foliage layer, nothing to draw.
+            BrushMode::PaintFoliage { layer, .. } | BrushMode::EraseFoliage { layer }
+                if layer >= self.foliage_layers.len() => {}
+            BrushMode::PaintFoliage { layer, density } => {
+                let size = max - min;
+                let expected_count = density.max(0.0) * size.x * size.y;
+                let mut count = expected_count as usize;
+                if rng.gen::<f32>() < expected_count.fract() {
+                    count += 1;
+                }
+
+                for _ in 0..count {
+                    let position =
+                        min + Vector2::new(rng.gen::<f32>() * size.x, rng.gen::<f32>() * size.y);
+                    if !brush.shape.contains(center, position)
+                        || rng.gen::<f32>() >= brush.weight(center, position)
+                    {
+                        continue;
+                    }
+
+                    if let Some((chunk_index, u, v)) = self.locate_chunk(position) {
+                        if let Some((height, _)) = sample_surface(&self.chunks[chunk_index], u, v) {
+                            self.foliage_layers[layer].instances[chunk_index].push(
+                                FoliageInstance {
+                                    position: Vector3::new(position.x, height, position.y),
+                                    rotation: rng.gen_range(0.0..std::f32::consts::TAU),
+                                    scale: 1.0,
+                                },
+                            );
+                        }
+                    }
+                }
+            }
+            BrushMode::EraseFoliage { layer } => {
+                for instances in self.foliage_layers[layer].instances.iter_mut() {
+                    instances.retain(|instance| {
+                        let position = Vector2::new(instance.position.x, instance.position.z);
+                        !brush.shape.contains(center, position)
+                            || rng.gen::<f32>() >= brush.weight(center, position)
+                    });
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Moves every foliage instance to the surface of the terrain, it is used when height map was
+    // changed.
+    pub(super) fn snap_foliage(&mut self) {
+        let chunks = &self.chunks;
+        for layer in self.foliage_layers.iter_mut() {
+            for (chunk, instances) in chunks.iter().zip(layer.instances.iter_mut()) {
+                for instance in instances.iter_mut() {
+                    let u = (instance.position.x - chunk.position.x) / chunk.width;
+                    let v = (instance.position.z - chunk.position.z) / chunk.length;
+                    if let Some((height, _)) = sample_surface(chunk, u, v) {
+                        instance.position.y = height;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Collects world transforms of instances of given foliage layer, which are closer to an
+    /// observer than draw distance of the layer. Instances within fade distance are shrunk, so
+    /// they disappear smoothly.
+    pub fn visible_foliage(
+        &self,
+        layer: usize,
+        observer_position: Vector3<f32>,
+        transforms: &mut Vec<Matrix4<f32>>,
+    ) {
+        let layer = &self.foliage_layers[layer];
+        let global_transform = self.global_transform();
+        let fade_start = (layer.draw_distance - layer.fade_distance).max(0.0);
+
+        for (chunk, instances) in self.chunks.iter().zip(layer.instances.iter()) {
+            if instances.is_empty() {
+                continue;
+            }
+
+            // Skip whole chunk if it is too far. Height bounds are valid only for updated chunks.
+            if !chunk.dirty.get() {
+                let min = Vector3::new(chunk.position.x, chunk.min_height, chunk.position.z);
+                let max = Vector3::new(
+                    chunk.position.x + chunk.width,
+                    chunk.max_height,
+                    chunk.position.z + chunk.length,
+                );
+                let corners = AxisAlignedBoundingBox::from_points(&[min, max])
+                    .corners()
+                    .iter()
+                    .map(|corner| {
+                        global_transform
+                            .transform_point(&Point3::from(*corner))
+                            .coords
+                    })
+                    .collect::<Vec<_>>();
+                if !AxisAlignedBoundingBox::from_points(&corners)
+                    .is_intersects_sphere(observer_position, layer.draw_distance)
+                {
+                    continue;
+                }
+            }
+
+            for instance in instances {
+                let position = global_transform
+                    .transform_point(&Point3::from(instance.position))
+                    .coords;
+                let distance = (position - observer_position).norm();
+                if distance >= layer.draw_distance {
+                    continue;
+                }
+                let fade = if distance > fade_start {
+                    (layer.draw_distance - distance) / (layer.draw_distance - fade_start)
+                } else {
+                    1.0
+                };
+                transforms.push(
+                    global_transform
+                        * FoliageInstance {
+                            scale: instance.scale * fade,
+                            ..*instance
+                        }
+                        .local_transform(),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::{Matrix4, Vector3},
+        scene::{
+            mesh::surface::{SurfaceBuilder, SurfaceData},
+            terrain::{
+                foliage::{FoliageLayer, FoliageScatter},
+                test::make_terrain_with_layers,
+                Brush, BrushMode, BrushShape, HeightMapImage, Terrain,
+            },
+        },
+    };
+    use std::sync::{Arc, RwLock};
+
+    // 32x32 terrain with 2x2 chunks and two layers, second layer covers left half of the terrain.
+    fn make_terrain() -> Terrain {
+        let mut terrain = make_terrain_with_layers(2);
+        terrain.draw(&Brush::new(
+            Vector3::new(8.0, 0.0, 16.0),
+            BrushShape::Rectangle {
+                width: 16.0,
+                length: 32.0,
+            },
+            BrushMode::DrawOnMask {
+                layer: 1,
+                alpha: 1.0,
+            },
+        ));
+        terrain.add_foliage_layer(FoliageLayer::new(vec![SurfaceBuilder::new(Arc::new(
+            RwLock::new(SurfaceData::make_quad(&Matrix4::identity())),
+        ))
+        .build()]));
+        terrain
+    }
+
+    fn all_instances(terrain: &Terrain) -> Vec<Vector3<f32>> {
+        (0..4)
+            .flat_map(|chunk| terrain.foliage_layers()[0].chunk_instances(chunk).to_vec())
+            .map(|instance| instance.position)
+            .collect()
+    }
+
+    #[test]
+    fn test_scatter_foliage() {
+        let mut terrain = make_terrain();
+        let scatter = FoliageScatter {
+            mask_layer: Some(1),
+            density: 0.5,
+            ..Default::default()
+        };
+        terrain.scatter_foliage(0, &scatter);
+
+        // Only left half of the terrain is covered, it is 512 square units.
+        let instances = all_instances(&terrain);
+        assert!(instances.len() > 150 && instances.len() < 350);
+        assert!(instances.iter().all(|p| p.x < 17.1 && p.y == 0.0));
+
+        // Same seed gives same result.
+        let mut other = make_terrain();
+        other.scatter_foliage(0, &scatter);
+        assert_eq!(all_instances(&other), instances);
+
+        // Scattering replaces previous instances.
+        terrain.scatter_foliage(
+            0,
+            &FoliageScatter {
+                density: 0.1,
+                ..Default::default()
+            },
+        );
+        let instances = all_instances(&terrain);
+        assert!(instances.len() > 50 && instances.len() < 160);
+        assert!(instances.iter().any(|p| p.x > 16.0));
+
+        // Slope of 45 degrees is too steep for foliage that grows on slopes up to 30 degrees.
+        terrain.import_height_map(
+            &HeightMapImage::new(2, 1, vec![0, u16::MAX]).unwrap(),
+            32.0,
+            0.0,
+        );
+        terrain.scatter_foliage(
+            0,
+            &FoliageScatter {
+                max_slope: 30.0f32.to_radians(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(terrain.foliage_layers()[0].instance_count(), 0);
+        terrain.scatter_foliage(
+            0,
+            &FoliageScatter {
+                min_slope: 40.0f32.to_radians(),
+                max_slope: 50.0f32.to_radians(),
+                ..Default::default()
+            },
+        );
+        let instances = all_instances(&terrain);
+        assert!(!instances.is_empty());
+        assert!(instances.iter().all(|p| (p.y - p.x).abs() < 1.0e-3));
+    }
+
+    #[test]
+    fn test_paint_foliage() {
+        let mut terrain = make_terrain();
+        let mut brush = Brush::new(
+            Vector3::new(16.0, 0.0, 16.0),
+            BrushShape::Circle { radius: 4.0 },
+            BrushMode::PaintFoliage {
+                layer: 0,
+                density: 4.0,
+            },
+        );
+        assert!(terrain.draw(&brush).is_empty());
+        let instances = all_instances(&terrain);
+        assert!(!instances.is_empty());
+        assert!(instances
+            .iter()
+            .all(|p| (p.x - 16.0).hypot(p.z - 16.0) < 4.0));
+
+        // Instances follow changes of height map.
+        terrain.draw(&Brush::new(
+            Vector3::new(16.0, 0.0, 16.0),
+            BrushShape::Rectangle {
+                width: 32.0,
+                length: 32.0,
+            },
+            BrushMode::SetHeight { height: 3.0 },
+        ));
+        assert!(all_instances(&terrain).iter().all(|p| p.y == 3.0));
+
+        brush.mode = BrushMode::EraseFoliage { layer: 0 };
+        brush.shape = BrushShape::Rectangle {
+            width: 16.0,
+            length: 16.0,
+        };
+        terrain.draw(&brush);
+        assert_eq!(terrain.foliage_layers()[0].instance_count(), 0);
+
+        // Unknown layers are ignored.
+        brush.mode = BrushMode::PaintFoliage {
+            layer: 1,
+            density: 4.0,
+        };
+        terrain.draw(&brush);
+        brush.mode = BrushMode::EraseFoliage { layer: 1 };
+        terrain.draw(&brush);
+        terrain.scatter_foliage(1, &Default::default());
+        assert_eq!(terrain.foliage_layers().len(), 1);
+    }
+
+    #[test]
+    fn test_visible_foliage() {
+        let mut terrain = make_terrain();
+        terrain.scatter_foliage(0, &FoliageScatter::default());
+        terrain.update();
+        let layer = &mut terrain.foliage_layers_mut()[0];
+        layer.set_draw_distance(10.0);
+        layer.set_fade_distance(5.0);
+
+        let observer = Vector3::new(16.0, 0.0, 16.0);
+        let mut transforms = Vec::new();
+        terrain.visible_foliage(0, observer, &mut transforms);
+        assert!(!transforms.is_empty());
+        assert!(transforms.len() < terrain.foliage_layers()[0].instance_count());
+
+        for transform in transforms {
+            let position = Vector3::new(transform[12], transform[13], transform[14]);
+            let scale = Vector3::new(transform[0], transform[1], transform[2]).norm();
+            let distance = (position - observer).norm();
+            assert!(distance < 10.0);
+            if distance > 5.0 {
+                assert!(scale < 1.2 * (10.0 - distance) / 5.0 + 1.0e-4);
+            } else {
+                assert!(scale >= 0.8 - 1.0e-4);
+            }
+        }
+
+        // Nothing is visible from far away.
+        let mut transforms = Vec::new();
+        terrain.visible_foliage(0, Vector3::new(100.0, 0.0, 16.0), &mut transforms);
+        assert!(transforms.is_empty());
+    }
+}
@@ -0,0 +1,2900 @@
+//! Everything related to terrains.
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Point3, Vector2, Vector3},
+        arrayvec::ArrayVec,
+        curve::Curve,
+        math::{
+            aabb::AxisAlignedBoundingBox, lerpf, ray::Ray, ray_rect_intersection, Rect,
+            TriangleDefinition,
+        },
+        pool::Handle,
+        visitor::{prelude::*, PodVecView},
+    },
+    material::Material,
+    resource::texture::{Texture, TextureKind, TexturePixelKind, TextureWrapMode},
+    scene::{
+        base::{Base, BaseBuilder},
+        graph::Graph,
+        mesh::{
+            buffer::{GeometryBuffer, VertexBuffer},
+            surface::SurfaceData,
+            vertex::StaticVertex,
+        },
+        node::Node,
+        terrain::foliage::FoliageLayer,
+    },
+};
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    path::Path,
+    sync::{Arc, Mutex, RwLock},
+};
+
+pub mod foliage;
+
+/// Layers is a set of textures for rendering + mask texture to exclude some pixels from
+/// rendering. Terrain can have as many layers as you want, but each layer slightly decreases
+/// performance, so keep amount of layers on reasonable level (1 - 5 should be enough for most
+/// cases).
+#[derive(Default, Debug, Clone, Visit)]
+pub struct Layer {
+    /// Material of the layer.
+    pub material: Arc<Mutex<Material>>,
+
+    /// Name of the mask sampler in the material.
+    ///
+    /// # Implementation details
+    ///
+    /// It will be used in the renderer to set appropriate chunk mask to the copy of the material.
+    pub mask_property_name: String,
+
+    pub(in crate) chunk_masks: Vec<Texture>,
+}
+
+impl Layer {
+    /// Returns a slice containing a set of masks for every chunk in the layer.
+    pub fn chunk_masks(&self) -> &[Texture] {
+        &self.chunk_masks
+    }
+}
+
+/// Chunk is smaller block of a terrain. Terrain can have as many chunks as you need.
+/// Can't we just use one big chunk? Well, potentially yes. However in practice, it
+/// is very limiting because you need to have very huge mask texture and most of wide-spread
+/// GPUs have 16k texture size limit. Multiple chunks provide different LODs to renderer
+/// so distant chunks can be rendered with low details reducing GPU load.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    heightmap: Vec<f32>,
+    position: Vector3<f32>,
+    width: f32,
+    length: f32,
+    width_point_count: u32,
+    length_point_count: u32,
+    surface_data: Arc<RwLock<SurfaceData>>,
+    dirty: Cell<bool>,
+    min_height: f32,
+    max_height: f32,
+    lods: RefCell<HashMap<ChunkLod, Arc<RwLock<SurfaceData>>>>,
+    // One value per cell of the height map, empty if there are no holes.
+    hole_mask: Vec<u8>,
+}
+
+// Manual implementation of the trait because we need to serialize heightmap differently.
+impl Visit for Chunk {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut view = PodVecView::from_pod_vec(&mut self.heightmap);
+        view.visit("Heightmap", visitor)?;
+
+        self.position.visit("Position", visitor)?;
+        self.width.visit("Width", visitor)?;
+        self.length.visit("Length", visitor)?;
+        self.width_point_count.visit("WidthPointCount", visitor)?;
+        self.length_point_count.visit("LengthPointCount", visitor)?;
+
+        let mut hole_mask = PodVecView::from_pod_vec(&mut self.hole_mask);
+        let _ = hole_mask.visit("HoleMask", visitor); // Backward compatibility.
+
+        // self.surface_data, self.dirty, self.lods and height bounds are not serialized.
+
+        visitor.leave_region()
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self {
+            heightmap: Default::default(),
+            position: Default::default(),
+            width: 0.0,
+            length: 0.0,
+            width_point_count: 0,
+            length_point_count: 0,
+            surface_data: make_surface_data(),
+            dirty: Cell::new(true),
+            min_height: 0.0,
+            max_height: 0.0,
+            lods: Default::default(),
+            hole_mask: Default::default(),
+        }
+    }
+}
+
+impl Chunk {
+    /// Updates vertex and index buffers needed for rendering. In most cases there is no need
+    /// to call this method manually, engine will automatically call it when needed.
+    pub fn update(&mut self) {
+        if self.dirty.get() {
+            let mut surface_data = self.surface_data.write().unwrap();
+            surface_data.clear();
+
+            assert_eq!(self.width_point_count & 1, 0);
+            assert_eq!(self.length_point_count & 1, 0);
+
+            let mut vertex_buffer_mut = surface_data.vertex_buffer.modify();
+            // Form vertex buffer.
+            for z in 0..self.length_point_count {
+                let kz = z as f32 / ((self.length_point_count - 1) as f32);
+                let pz = self.position.z + kz * self.length;
+
+                for x in 0..self.width_point_count {
+                    let index = z * self.width_point_count + x;
+                    let height = self.heightmap[index as usize];
+                    let kx = x as f32 / ((self.width_point_count - 1) as f32);
+
+                    let px = self.position.x + kx * self.width;
+                    let py = self.position.y + height;
+
+                    vertex_buffer_mut
+                        .push_vertex(&StaticVertex {
+                            position: Vector3::new(px, py, pz),
+                            tex_coord: Vector2::new(kx, kz),
+                            // Normals and tangents will be calculated later.
+                            normal: Default::default(),
+                            tangent: Default::default(),
+                        })
+                        .unwrap();
+                }
+            }
+            drop(vertex_buffer_mut);
+
+            // Form index buffer. Full details are used to calculate normals and tangents, lower
+            // levels of detail will be made on demand from this data.
+            surface_data.geometry_buffer.set_triangles(remove_holes(
+                self,
+                make_lod_triangles(
+                    self.width_point_count,
+                    self.length_point_count,
+                    ChunkLod::default(),
+                ),
+            ));
+
+            surface_data.calculate_normals().unwrap();
+            surface_data.calculate_tangents().unwrap();
+
+            let (min_height, max_height) = self
+                .heightmap
+                .iter()
+                .fold((f32::MAX, -f32::MAX), |(min, max), &h| {
+                    (min.min(h), max.max(h))
+                });
+            self.min_height = min_height;
+            self.max_height = max_height;
+
+            self.lods.get_mut().clear();
+
+            self.dirty.set(false);
+        }
+    }
+
+    /// Returns maximum level of detail of the chunk. Every level of detail has at least one
+    /// point of the height map between edges of the chunk.
+    pub fn max_lod(&self) -> u32 {
+        let cells = self
+            .width_point_count
+            .min(self.length_point_count)
+            .saturating_sub(1);
+        let mut level = 0;
+        while (2 << level) < cells {
+            level += 1;
+        }
+        level
+    }
+
+    /// Returns data for rendering of given level of detail. Data for levels of detail is made
+    /// on demand and cached until the height map is changed. Full details (`ChunkLod::default()`)
+    /// are the same as [`Self::data`].
+    pub fn lod_data(&self, lod: ChunkLod) -> Arc<RwLock<SurfaceData>> {
+        if lod == ChunkLod::default() {
+            return self.surface_data.clone();
+        }
+
+        self.lods
+            .borrow_mut()
+            .entry(lod)
+            .or_insert_with(|| {
+                let surface_data = self.surface_data.read().unwrap();
+                let vertices = surface_data
+                    .vertex_buffer
+                    .cast_data_ref::<StaticVertex>()
+                    .unwrap();
+
+                // Keep only vertices that are used by the level of detail.
+                let mut index_map = HashMap::new();
+                let mut lod_vertices = Vec::new();
+                let mut triangles = remove_holes(
+                    self,
+                    make_lod_triangles(self.width_point_count, self.length_point_count, lod),
+                );
+                for triangle in triangles.iter_mut() {
+                    for index in triangle.0.iter_mut() {
+                        *index = *index_map.entry(*index).or_insert_with(|| {
+                            lod_vertices.push(vertices[*index as usize]);
+                            (lod_vertices.len() - 1) as u32
+                        });
+                    }
+                }
+
+                Arc::new(RwLock::new(SurfaceData::new(
+                    VertexBuffer::new(lod_vertices.len(), StaticVertex::layout(), lod_vertices)
+                        .unwrap(),
+                    GeometryBuffer::new(triangles),
+                    false,
+                )))
+            })
+            .clone()
+    }
+
+    fn distance(&self, point: Vector3<f32>) -> f32 {
+        let min = Vector3::new(self.position.x, self.min_height, self.position.z);
+        let max = Vector3::new(
+            self.position.x + self.width,
+            self.max_height,
+            self.position.z + self.length,
+        );
+        (point - point.sup(&min).inf(&max)).norm()
+    }
+
+    /// Returns position of the chunk in local 2D coordinates relative to origin of the
+    /// terrain.
+    pub fn local_position(&self) -> Vector2<f32> {
+        map_to_local(self.position)
+    }
+
+    /// Returns a reference to height map.
+    pub fn heightmap(&self) -> &[f32] {
+        &self.heightmap
+    }
+
+    /// Sets new height map. New height map must be equal with size of current.
+    pub fn set_heightmap(&mut self, heightmap: Vec<f32>) {
+        assert_eq!(self.heightmap.len(), heightmap.len());
+        self.heightmap = heightmap;
+        self.dirty.set(true);
+    }
+
+    /// Returns data for rendering (vertex and index buffers).
+    pub fn data(&self) -> Arc<RwLock<SurfaceData>> {
+        self.surface_data.clone()
+    }
+
+    /// Returns width of height map in dots.
+    pub fn width_point_count(&self) -> u32 {
+        self.width_point_count
+    }
+
+    /// Returns length of height map in dots.
+    pub fn length_point_count(&self) -> u32 {
+        self.length_point_count
+    }
+
+    /// Returns true if given cell of the height map is a hole. Cell `(x, z)` is a quad between
+    /// points `(x, z)` and `(x + 1, z + 1)` of the height map.
+    pub fn is_hole(&self, x: u32, z: u32) -> bool {
+        x + 1 < self.width_point_count
+            && z + 1 < self.length_point_count
+            && matches!(
+                self.hole_mask.get((z * (self.width_point_count - 1) + x) as usize),
+                Some(hole) if *hole != 0
+            )
+    }
+
+    /// Cuts a hole in given cell of the height map or fills it back. Holes are not rendered and
+    /// they are also cut in height field colliders. Returns false if there is no such cell.
+    pub fn set_hole(&mut self, x: u32, z: u32, hole: bool) -> bool {
+        if x + 1 >= self.width_point_count || z + 1 >= self.length_point_count {
+            return false;
+        }
+        if self.hole_mask.is_empty() {
+            if !hole {
+                return true;
+            }
+            self.hole_mask =
+                vec![0; ((self.width_point_count - 1) * (self.length_point_count - 1)) as usize];
+        }
+        self.hole_mask[(z * (self.width_point_count - 1) + x) as usize] = hole as u8;
+        self.dirty.set(true);
+        true
+    }
+
+    /// Returns true if the chunk has at least one hole.
+    pub fn has_holes(&self) -> bool {
+        self.hole_mask.iter().any(|hole| *hole != 0)
+    }
+}
+
+// Removes triangles that lie in holes of a chunk. A triangle belongs to the cell that contains
+// its centroid, so it works for any level of detail.
+fn remove_holes(chunk: &Chunk, triangles: Vec<TriangleDefinition>) -> Vec<TriangleDefinition> {
+    if !chunk.has_holes() {
+        return triangles;
+    }
+    let width = chunk.width_point_count;
+    triangles
+        .into_iter()
+        .filter(|triangle| {
+            let (x, z) = triangle
+                .0
+                .iter()
+                .fold((0, 0), |(x, z), i| (x + i % width, z + i / width));
+            !chunk.is_hole(x / 3, z / 3)
+        })
+        .collect()
+}
+
+/// Level of detail of a chunk. Every next level skips every other row and column of the
+/// previous level, so level `n` uses every `2^n`-th point of the height map. Last row and
+/// column are always used, so the chunk keeps its size at any level.
+///
+/// Chunks with different levels of detail cannot be just put side by side - points on their
+/// shared edge won't match and there will be cracks. To prevent this, an edge of a chunk that
+/// touches a chunk with coarser level of detail is stitched - it uses the points of the
+/// coarser neighbour.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ChunkLod {
+    /// Level of detail of the chunk, zero means full details.
+    pub level: u32,
+    /// Levels of detail of the chunk edges in -X, +X, -Z, +Z order. Edge level must not be
+    /// less than `level`.
+    pub edges: [u32; 4],
+}
+
+// Returns indices of points of a row (or a column) of the height map used by a level of
+// detail.
+fn lod_samples(point_count: u32, level: u32) -> Vec<u32> {
+    let mut samples = (0..point_count - 1).step_by(1 << level).collect::<Vec<_>>();
+    samples.push(point_count - 1);
+    samples
+}
+
+// Returns level of detail for a chunk at given distance. Each next level starts at twice the
+// distance of the previous one.
+fn lod_level(distance: f32, lod_distance: f32) -> u32 {
+    if lod_distance <= 0.0 || distance < lod_distance {
+        0
+    } else {
+        (distance / lod_distance).log2() as u32 + 1
+    }
+}
+
+// Adds a triangle with the same winding as the rest of triangles of a chunk.
+fn push_triangle(
+    triangles: &mut Vec<TriangleDefinition>,
+    a: (u32, u32),
+    mut b: (u32, u32),
+    mut c: (u32, u32),
+    width_point_count: u32,
+) {
+    let area = (b.0 as i64 - a.0 as i64) * (c.1 as i64 - a.1 as i64)
+        - (b.1 as i64 - a.1 as i64) * (c.0 as i64 - a.0 as i64);
+    if area > 0 {
+        std::mem::swap(&mut b, &mut c);
+    }
+    let index = |(x, z): (u32, u32)| z * width_point_count + x;
+    triangles.push(TriangleDefinition([index(a), index(b), index(c)]));
+}
+
+// Connects two parallel polylines with a strip of triangles. Outer one lies on an edge of a
+// chunk, inner one - on the border of the inner part of the chunk. Both polylines are given
+// as sorted coordinates along the edge.
+fn stitch<O, I>(
+    triangles: &mut Vec<TriangleDefinition>,
+    outer: &[u32],
+    inner: &[u32],
+    outer_point: O,
+    inner_point: I,
+    width_point_count: u32,
+) where
+    O: Fn(u32) -> (u32, u32),
+    I: Fn(u32) -> (u32, u32),
+{
+    let (mut i, mut j) = (0, 0);
+    while i + 1 < outer.len() || j + 1 < inner.len() {
+        if j + 1 == inner.len() || (i + 1 < outer.len() && outer[i + 1] <= inner[j + 1]) {
+            push_triangle(
+                triangles,
+                outer_point(outer[i]),
+                outer_point(outer[i + 1]),
+                inner_point(inner[j]),
+                width_point_count,
+            );
+            i += 1;
+        } else {
+            push_triangle(
+                triangles,
+                outer_point(outer[i]),
+                inner_point(inner[j + 1]),
+                inner_point(inner[j]),
+                width_point_count,
+            );
+            j += 1;
+        }
+    }
+}
+
+fn push_grid(
+    triangles: &mut Vec<TriangleDefinition>,
+    xs: &[u32],
+    zs: &[u32],
+    width_point_count: u32,
+) {
+    for z in zs.windows(2) {
+        for x in xs.windows(2) {
+            let i0 = z[0] * width_point_count + x[0];
+            let i1 = z[1] * width_point_count + x[0];
+            let i2 = z[1] * width_point_count + x[1];
+            let i3 = z[0] * width_point_count + x[1];
+
+            triangles.push(TriangleDefinition([i0, i1, i2]));
+            triangles.push(TriangleDefinition([i2, i3, i0]));
+        }
+    }
+}
+
+// Generates triangles of a level of detail of a chunk, indices point to the full height map
+// grid.
+fn make_lod_triangles(
+    width_point_count: u32,
+    length_point_count: u32,
+    lod: ChunkLod,
+) -> Vec<TriangleDefinition> {
+    let xs = lod_samples(width_point_count, lod.level);
+    let zs = lod_samples(length_point_count, lod.level);
+
+    let mut triangles = Vec::new();
+
+    if lod.edges.iter().all(|&edge| edge <= lod.level) {
+        push_grid(&mut triangles, &xs, &zs, width_point_count);
+        return triangles;
+    }
+
+    // Inner part is a regular grid, the border around it consists of four strips which
+    // connect inner part with the edges of the chunk. Every chunk has at least three points
+    // along each axis at any level of detail lower than maximum, so inner part always exists.
+    let inner_xs = &xs[1..xs.len() - 1];
+    let inner_zs = &zs[1..zs.len() - 1];
+    push_grid(&mut triangles, inner_xs, inner_zs, width_point_count);
+
+    let [left, right, bottom, top] = lod.edges;
+    let (x_first, x_last) = (inner_xs[0], inner_xs[inner_xs.len() - 1]);
+    let (z_first, z_last) = (inner_zs[0], inner_zs[inner_zs.len() - 1]);
+    let last_x = width_point_count - 1;
+    let last_z = length_point_count - 1;
+
+    stitch(
+        &mut triangles,
+        &lod_samples(length_point_count, left.max(lod.level)),
+        inner_zs,
+        |z| (0, z),
+        |z| (x_first, z),
+        width_point_count,
+    );
+    stitch(
+        &mut triangles,
+        &lod_samples(length_point_count, right.max(lod.level)),
+        inner_zs,
+        |z| (last_x, z),
+        |z| (x_last, z),
+        width_point_count,
+    );
+    stitch(
+        &mut triangles,
+        &lod_samples(width_point_count, bottom.max(lod.level)),
+        inner_xs,
+        |x| (x, 0),
+        |x| (x, z_first),
+        width_point_count,
+    );
+    stitch(
+        &mut triangles,
+        &lod_samples(width_point_count, top.max(lod.level)),
+        inner_xs,
+        |x| (x, last_z),
+        |x| (x, z_last),
+        width_point_count,
+    );
+
+    triangles
+}
+
+fn map_to_local(v: Vector3<f32>) -> Vector2<f32> {
+    // Terrain is a XZ oriented surface so we can map X -> X, Z -> Y
+    Vector2::new(v.x, v.z)
+}
+
+/// Ray-terrain intersection result.  
+#[derive(Debug)]
+pub struct TerrainRayCastResult {
+    /// World-space position of impact point.
+    pub position: Vector3<f32>,
+    /// World-space normal of triangle at impact point.
+    pub normal: Vector3<f32>,
+    /// Index of a chunk that was hit.
+    pub chunk_index: usize,
+    /// Time of impact. Usually in [0; 1] range where 0 - origin of a ray, 1 - its end.
+    pub toi: f32,
+}
+
+/// See module docs.
+#[derive(Visit, Debug, Default)]
+pub struct Terrain {
+    width: f32,
+    length: f32,
+    mask_resolution: f32,
+    height_map_resolution: f32,
+    base: Base,
+    chunks: Vec<Chunk>,
+    layers: Vec<Layer>,
+    width_chunks: u32,
+    length_chunks: u32,
+    bounding_box_dirty: Cell<bool>,
+    bounding_box: Cell<AxisAlignedBoundingBox>,
+    #[visit(optional)] // Backward compatibility
+    decal_layer_index: u8,
+    #[visit(optional)] // Backward compatibility
+    lod_distance: f32,
+    #[visit(optional)] // Backward compatibility
+    foliage_layers: Vec<FoliageLayer>,
+}
+
+impl Deref for Terrain {
+    type Target = Base;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl DerefMut for Terrain {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+fn project(global_transform: Matrix4<f32>, p: Vector3<f32>) -> Option<Vector2<f32>> {
+    // Transform point in coordinate system of the terrain.
+    if let Some(inv_global_transform) = global_transform.try_inverse() {
+        let local_p = inv_global_transform
+            .transform_point(&Point3::from(p))
+            .coords;
+        Some(map_to_local(local_p))
+    } else {
+        None
+    }
+}
+
+impl Terrain {
+    /// Returns width of the terrain in local coordinates.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Returns amount of chunks along X axis.
+    pub fn width_chunk_count(&self) -> usize {
+        self.width_chunks as usize
+    }
+
+    /// Returns length of the terrain in local coordinates.
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    /// Returns amount of chunks along Z axis
+    pub fn length_chunk_count(&self) -> usize {
+        self.length_chunks as usize
+    }
+
+    /// Returns a reference to chunks of the terrain.
+    pub fn chunks_ref(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    /// Returns a mutable reference to chunks of the terrain.
+    pub fn chunks_mut(&mut self) -> &mut [Chunk] {
+        &mut self.chunks
+    }
+
+    /// Sets new decal layer index. It defines which decals will be applies to the mesh,
+    /// for example iff a decal has index == 0 and a mesh has index == 0, then decals will
+    /// be applied. This allows you to apply decals only on needed surfaces.
+    pub fn set_decal_layer_index(&mut self, index: u8) {
+        self.decal_layer_index = index;
+    }
+
+    /// Returns current decal index.
+    pub fn decal_layer_index(&self) -> u8 {
+        self.decal_layer_index
+    }
+
+    /// Sets new distance at which chunks switch to the first level of detail. Each next level
+    /// starts at twice the distance of the previous one. Zero (default) disables levels of
+    /// detail, every chunk will be rendered with full details.
+    pub fn set_lod_distance(&mut self, distance: f32) {
+        self.lod_distance = distance.max(0.0);
+    }
+
+    /// Returns distance at which chunks switch to the first level of detail.
+    pub fn lod_distance(&self) -> f32 {
+        self.lod_distance
+    }
+
+    /// Selects level of detail for each chunk depending on a distance from the observer to the
+    /// chunk. Edges of chunks are stitched with coarser neighbours, so there will be no cracks
+    /// between chunks. The distance is calculated in the local coordinates of the terrain.
+    ///
+    /// Levels of detail can be used to fetch rendering data using [`Chunk::lod_data`], the
+    /// renderer uses the position of a camera as observer position.
+    pub fn select_lods(&self, observer_position: Vector3<f32>) -> Vec<ChunkLod> {
+        let local_observer_position = self
+            .global_transform()
+            .try_inverse()
+            .map(|inv| inv.transform_point(&Point3::from(observer_position)).coords)
+            .unwrap_or(observer_position);
+
+        let levels = self
+            .chunks
+            .iter()
+            .map(|chunk| {
+                lod_level(chunk.distance(local_observer_position), self.lod_distance)
+                    .min(chunk.max_lod())
+            })
+            .collect::<Vec<_>>();
+
+        let width = self.width_chunks as usize;
+        let length = self.length_chunks as usize;
+        levels
+            .iter()
+            .enumerate()
+            .map(|(i, &level)| {
+                let (x, z) = (i % width, i / width);
+                // Edge always uses the coarsest level of two chunks.
+                let edge = |x: Option<usize>, z: Option<usize>| match (x, z) {
+                    (Some(x), Some(z)) if x < width && z < length => {
+                        levels[z * width + x].max(level)
+                    }
+                    _ => level,
+                };
+                ChunkLod {
+                    level,
+                    edges: [
+                        edge(x.checked_sub(1), Some(z)),
+                        edge(Some(x + 1), Some(z)),
+                        edge(Some(x), z.checked_sub(1)),
+                        edge(Some(x), Some(z + 1)),
+                    ],
+                }
+            })
+            .collect()
+    }
+
+    /// Creates raw copy of the terrain. Do not use this method directly, use
+    /// Graph::copy_node.
+    pub fn raw_copy(&self) -> Self {
+        Self {
+            width: self.width,
+            length: self.length,
+            mask_resolution: self.mask_resolution,
+            height_map_resolution: self.height_map_resolution,
+            base: self.base.raw_copy(),
+            chunks: self.chunks.clone(),
+            width_chunks: self.width_chunks,
+            length_chunks: self.length_chunks,
+            bounding_box_dirty: Cell::new(true),
+            bounding_box: Default::default(),
+            decal_layer_index: self.decal_layer_index,
+            layers: self.layers.clone(),
+            lod_distance: self.lod_distance,
+            foliage_layers: self.foliage_layers.clone(),
+        }
+    }
+
+    /// Returns pre-cached bounding axis-aligned bounding box of the terrain. Keep in mind that
+    /// if you're modified terrain, bounding box will be recalculated and it is not fast.
+    pub fn bounding_box(&self) -> AxisAlignedBoundingBox {
+        if self.bounding_box_dirty.get() {
+            let mut max_height = -f32::MAX;
+            for chunk in self.chunks.iter() {
+                for &height in chunk.heightmap.iter() {
+                    if height > max_height {
+                        max_height = height;
+                    }
+                }
+            }
+
+            let bounding_box = AxisAlignedBoundingBox::from_min_max(
+                self.global_position(),
+                self.global_position() + Vector3::new(self.width, max_height, self.length),
+            );
+            self.bounding_box.set(bounding_box);
+            self.bounding_box_dirty.set(false);
+
+            bounding_box
+        } else {
+            self.bounding_box.get()
+        }
+    }
+
+    /// Projects given 3D point on the surface of terrain and returns 2D vector
+    /// expressed in local 2D coordinate system of terrain.
+    pub fn project(&self, p: Vector3<f32>) -> Option<Vector2<f32>> {
+        project(self.global_transform(), p)
+    }
+
+    /// Multi-functional drawing method. It uses given brush to modify terrain, see Brush docs for
+    /// more info. Returns changes of height maps made by the brush, they can be used to undo or
+    /// redo the operation. Drawing on masks, drawing holes and painting of foliage do not change
+    /// height maps, so the result will be empty in these cases.
+    pub fn draw(&mut self, brush: &Brush) -> HeightMapDelta {
+        let center = project(self.global_transform(), brush.center).unwrap();
+
+        match brush.mode {
+            BrushMode::DrawOnMask { layer, alpha } => {
+                let alpha = alpha.clamp(-1.0, 1.0);
+
+                for (chunk_index, chunk) in self.chunks.iter_mut().enumerate() {
+                    let chunk_position = chunk.local_position();
+                    let layer = &mut self.layers[layer];
+                    let mut texture_data = layer.chunk_masks[chunk_index].data_ref();
+                    let mut texture_data_mut = texture_data.modify();
+
+                    let (texture_width, texture_height) =
+                        if let TextureKind::Rectangle { width, height } = texture_data_mut.kind() {
+                            (width as usize, height as usize)
+                        } else {
+                            unreachable!("Mask must be a 2D greyscale image!")
+                        };
+
+                    for z in 0..texture_height {
+                        let kz = z as f32 / (texture_height - 1) as f32;
+                        for x in 0..texture_width {
+                            let kx = x as f32 / (texture_width - 1) as f32;
+
+                            let pixel_position =
+                                chunk_position + Vector2::new(kx * chunk.width, kz * chunk.length);
+
+                            let k = match (&brush.falloff, brush.shape) {
+                                (Some(falloff), _) => falloff.value_at(
+                                    brush.shape.normalized_distance(center, pixel_position),
+                                ),
+                                (None, BrushShape::Circle { radius }) => {
+                                    1.0 - ((center - pixel_position).norm() / radius).powf(4.0)
+                                }
+                                (None, BrushShape::Rectangle { .. }) => 1.0,
+                            };
+
+                            if brush.shape.contains(center, pixel_position) {
+                                // We can draw on mask directly, without any problems because it has R8 pixel format.
+                                let data = texture_data_mut.data_mut();
+                                let pixel = &mut data[z * texture_width + x];
+                                *pixel =
+                                    (*pixel as f32 + k * alpha * 255.0).clamp(0.0, 255.0) as u8;
+                            }
+                        }
+                    }
+                }
+
+                HeightMapDelta::default()
+            }
+            BrushMode::DrawHoles { erase } => {
+                for chunk in self.chunks.iter_mut() {
+                    let chunk_position = chunk.local_position();
+                    let cell_width = chunk.width / (chunk.width_point_count - 1) as f32;
+                    let cell_length = chunk.length / (chunk.length_point_count - 1) as f32;
+                    for z in 0..chunk.length_point_count - 1 {
+                        for x in 0..chunk.width_point_count - 1 {
+                            let cell_center = chunk_position
+                                + Vector2::new(
+                                    (x as f32 + 0.5) * cell_width,
+                                    (z as f32 + 0.5) * cell_length,
+                                );
+                            if brush.shape.contains(center, cell_center)
+                                && chunk.is_hole(x, z) == erase
+                            {
+                                chunk.set_hole(x, z, !erase);
+                            }
+                        }
+                    }
+                }
+
+                HeightMapDelta::default()
+            }
+            BrushMode::PaintFoliage { .. } | BrushMode::EraseFoliage { .. } => {
+                self.draw_foliage(brush, center);
+
+                HeightMapDelta::default()
+            }
+            _ => self.draw_on_height_map(brush, center),
+        }
+    }
+
+    fn draw_on_height_map(&mut self, brush: &Brush, center: Vector2<f32>) -> HeightMapDelta {
+        let mut delta = HeightMapDelta::default();
+
+        let grid = match HeightGrid::new(self) {
+            Some(grid) => grid,
+            None => return delta,
+        };
+
+        // Find a region of the grid affected by the brush.
+        let (min, max) = brush.shape.bounds(center);
+        if max.x < 0.0
+            || max.y < 0.0
+            || min.x > (grid.width - 1) as f32 * grid.cell_width
+            || min.y > (grid.length - 1) as f32 * grid.cell_length
+        {
+            return delta;
+        }
+        let to_grid = |value: f32, cell_size: f32, point_count: usize| {
+            (value / cell_size).max(0.0).min((point_count - 1) as f32)
+        };
+        let x_begin = to_grid(min.x, grid.cell_width, grid.width).floor() as usize;
+        let x_end = to_grid(max.x, grid.cell_width, grid.width).ceil() as usize;
+        let z_begin = to_grid(min.y, grid.cell_length, grid.length).floor() as usize;
+        let z_end = to_grid(max.y, grid.cell_length, grid.length).ceil() as usize;
+
+        // Region is extended by one point on each side, it is used as read-only border for
+        // operations that use neighbour points.
+        let x_begin = x_begin.saturating_sub(1);
+        let x_end = (x_end + 1).min(grid.width - 1);
+        let z_begin = z_begin.saturating_sub(1);
+        let z_end = (z_end + 1).min(grid.length - 1);
+        let region_width = x_end - x_begin + 1;
+        let region_length = z_end - z_begin + 1;
+
+        let mut heights = Vec::with_capacity(region_width * region_length);
+        let mut weights = Vec::with_capacity(region_width * region_length);
+        for z in z_begin..=z_end {
+            for x in x_begin..=x_end {
+                let (chunk, index) = grid.chunks_at(x, z)[0];
+                heights.push(self.chunks[chunk].heightmap[index]);
+
+                let position = grid.position(x, z);
+                let border = (x == x_begin && x != 0)
+                    || (x == x_end && x != grid.width - 1)
+                    || (z == z_begin && z != 0)
+                    || (z == z_end && z != grid.length - 1);
+                weights.push(if !border && brush.shape.contains(center, position) {
+                    brush.weight(center, position)
+                } else {
+                    0.0
+                });
+            }
+        }
+
+        let region = Region {
+            width: region_width,
+            length: region_length,
+        };
+        let new_heights = match brush.mode {
+            BrushMode::ModifyHeightMap { amount } => heights
+                .iter()
+                .zip(weights.iter())
+                .map(|(h, k)| h + k * amount)
+                .collect::<Vec<_>>(),
+            BrushMode::SetHeight { height } => heights
+                .iter()
+                .zip(weights.iter())
+                .map(|(h, k)| lerpf(*h, height, *k))
+                .collect(),
+            BrushMode::Flatten { height, amount } => heights
+                .iter()
+                .zip(weights.iter())
+                .map(|(h, k)| lerpf(*h, height, k * amount.clamp(0.0, 1.0)))
+                .collect(),
+            BrushMode::Smooth { amount } => {
+                let smoothed = region.smooth(&heights);
+                heights
+                    .iter()
+                    .zip(smoothed.iter())
+                    .zip(weights.iter())
+                    .map(|((h, s), k)| lerpf(*h, *s, k * amount.clamp(0.0, 1.0)))
+                    .collect()
+            }
+            BrushMode::Noise {
+                amount,
+                frequency,
+                seed,
+            } => {
+                let mut i = 0;
+                let mut new_heights = heights.clone();
+                for z in z_begin..=z_end {
+                    for x in x_begin..=x_end {
+                        let position = grid.position(x, z) * frequency;
+                        new_heights[i] += weights[i] * amount * fractal_noise(position, seed);
+                        i += 1;
+                    }
+                }
+                new_heights
+            }
+            BrushMode::ThermalErosion {
+                iterations,
+                talus,
+                amount,
+            } => {
+                let eroded = region.thermal_erosion(
+                    &heights,
+                    iterations,
+                    talus * grid.cell_width.min(grid.cell_length),
+                    amount.clamp(0.0, 1.0),
+                );
+                heights
+                    .iter()
+                    .zip(eroded.iter())
+                    .zip(weights.iter())
+                    .map(|((h, e), k)| lerpf(*h, *e, *k))
+                    .collect()
+            }
+            BrushMode::HydraulicErosion {
+                iterations,
+                rain,
+                solubility,
+                evaporation,
+            } => {
+                let eroded = region.hydraulic_erosion(
+                    &heights,
+                    iterations,
+                    rain,
+                    solubility,
+                    evaporation.clamp(0.0, 1.0),
+                );
+                heights
+                    .iter()
+                    .zip(eroded.iter())
+                    .zip(weights.iter())
+                    .map(|((h, e), k)| lerpf(*h, *e, *k))
+                    .collect()
+            }
+            BrushMode::DrawOnMask { .. }
+            | BrushMode::DrawHoles { .. }
+            | BrushMode::PaintFoliage { .. }
+            | BrushMode::EraseFoliage { .. } => unreachable!(),
+        };
+
+        // Write new heights back to every chunk that contains changed point.
+        let mut i = 0;
+        for z in z_begin..=z_end {
+            for x in x_begin..=x_end {
+                let (old_height, new_height) = (heights[i], new_heights[i]);
+                if weights[i] > 0.0 && old_height != new_height {
+                    for (chunk, index) in grid.chunks_at(x, z) {
+                        delta.changes.push(HeightMapChange {
+                            chunk,
+                            index,
+                            old_height,
+                            new_height,
+                        });
+                        self.set_height(chunk, index, new_height);
+                    }
+                }
+                i += 1;
+            }
+        }
+
+        if !delta.is_empty() {
+            self.snap_foliage();
+        }
+
+        delta
+    }
+
+    fn set_height(&mut self, chunk: usize, index: usize, height: f32) {
+        let chunk = &mut self.chunks[chunk];
+        chunk.heightmap[index] = height;
+        chunk.dirty.set(true);
+        self.bounding_box_dirty.set(true);
+    }
+
+    /// Sets heights of the terrain from given height map. The height map is stretched over the
+    /// whole terrain and resampled to match resolution of chunks. Final height of a point is
+    /// `offset + scale * h`, where `h` is a value of the height map in [0.0; 1.0] range.
+    pub fn import_height_map(&mut self, height_map: &HeightMapImage, scale: f32, offset: f32) {
+        for chunk in self.chunks.iter_mut() {
+            let chunk_position = chunk.local_position();
+            for z in 0..chunk.length_point_count {
+                let kz = z as f32 / (chunk.length_point_count - 1) as f32;
+                for x in 0..chunk.width_point_count {
+                    let kx = x as f32 / (chunk.width_point_count - 1) as f32;
+                    let position =
+                        chunk_position + Vector2::new(kx * chunk.width, kz * chunk.length);
+                    chunk.heightmap[(z * chunk.width_point_count + x) as usize] = offset
+                        + scale
+                            * height_map.sample(position.x / self.width, position.y / self.length);
+                }
+            }
+            chunk.dirty.set(true);
+        }
+        self.bounding_box_dirty.set(true);
+        self.snap_foliage();
+    }
+
+    /// Combines height maps of all chunks into a single height map. It is an inverse of
+    /// [`Self::import_height_map`], heights out of `[offset; offset + scale]` range are clamped.
+    /// Returns [`TerrainError::Empty`] if the terrain has no chunks.
+    pub fn export_height_map(
+        &self,
+        scale: f32,
+        offset: f32,
+    ) -> Result<HeightMapImage, TerrainError> {
+        let grid = HeightGrid::new(self).ok_or(TerrainError::Empty)?;
+        let mut pixels = Vec::with_capacity(grid.width * grid.length);
+        for z in 0..grid.length {
+            for x in 0..grid.width {
+                let (chunk, index) = grid.chunks_at(x, z)[0];
+                let height = self.chunks[chunk].heightmap[index];
+                let value = if scale != 0.0 {
+                    ((height - offset) / scale).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                pixels.push((value * u16::MAX as f32).round() as u16);
+            }
+        }
+        Ok(HeightMapImage {
+            width: grid.width as u32,
+            length: grid.length as u32,
+            pixels,
+        })
+    }
+
+    /// Combines masks of all chunks of given layer into a single greyscale image and writes it
+    /// to given path. Format of the image is defined by extension of the path.
+    pub fn export_layer_mask<P: AsRef<Path>>(
+        &self,
+        layer: usize,
+        path: P,
+    ) -> Result<(), TerrainError> {
+        let layer = self
+            .layers
+            .get(layer)
+            .ok_or(TerrainError::InvalidLayer(layer))?;
+
+        let mut image = image::GrayImage::new(0, 0);
+        for (chunk_index, mask) in layer.chunk_masks.iter().enumerate() {
+            let data = mask.data_ref();
+            let (mask_width, mask_height) = match data.kind() {
+                TextureKind::Rectangle { width, height } => (width, height),
+                _ => return Err(TerrainError::InvalidMask(chunk_index)),
+            };
+            if chunk_index == 0 {
+                // Masks of neighbour chunks share pixels on edges.
+                image = image::GrayImage::new(
+                    self.width_chunks * (mask_width - 1) + 1,
+                    self.length_chunks * (mask_height - 1) + 1,
+                );
+            }
+            let offset_x = (chunk_index as u32 % self.width_chunks) * (mask_width - 1);
+            let offset_y = (chunk_index as u32 / self.width_chunks) * (mask_height - 1);
+            for y in 0..mask_height {
+                for x in 0..mask_width {
+                    let pixel = data.data()[(y * mask_width + x) as usize];
+                    image.put_pixel(offset_x + x, offset_y + y, image::Luma([pixel]));
+                }
+            }
+        }
+
+        Ok(image.save(path)?)
+    }
+
+    /// Returns weights of layers at given point in world coordinates, or `None` if the point is
+    /// outside of the terrain. Weights are sampled from chunk masks, each layer is drawn on top
+    /// of previous ones, so weight of a layer is reduced by opacity of layers above it. It can
+    /// be used to select footstep sounds or friction depending on a surface under a character.
+    pub fn layer_weights(&self, position: Vector3<f32>) -> Option<Vec<f32>> {
+        let (chunk_index, u, v) = self.locate_chunk(self.project(position)?)?;
+        Some(self.chunk_layer_weights(chunk_index, u, v))
+    }
+
+    // Returns index of a chunk that contains given point in local 2D coordinates and normalized
+    // coordinates of the point within the chunk.
+    fn locate_chunk(&self, local: Vector2<f32>) -> Option<(usize, f32, f32)> {
+        if local.x < 0.0 || local.y < 0.0 || local.x > self.width || local.y > self.length {
+            return None;
+        }
+
+        let first_chunk = self.chunks.first()?;
+        let chunk_x = ((local.x / first_chunk.width) as usize).min(self.width_chunks as usize - 1);
+        let chunk_z =
+            ((local.y / first_chunk.length) as usize).min(self.length_chunks as usize - 1);
+        let chunk_index = chunk_z * self.width_chunks as usize + chunk_x;
+        let chunk = &self.chunks[chunk_index];
+        let uv = local - chunk.local_position();
+        Some((chunk_index, uv.x / chunk.width, uv.y / chunk.length))
+    }
+
+    fn chunk_layer_weights(&self, chunk_index: usize, u: f32, v: f32) -> Vec<f32> {
+        let mut weights = self
+            .layers
+            .iter()
+            .map(|layer| sample_mask(&layer.chunk_masks[chunk_index], u, v))
+            .collect::<Vec<_>>();
+        let mut coverage = 1.0;
+        for weight in weights.iter_mut().rev() {
+            let opacity = *weight;
+            *weight *= coverage;
+            coverage *= 1.0 - opacity;
+        }
+        weights
+    }
+
+    /// Returns index of a layer with the largest weight at given point in world coordinates. See
+    /// [`Self::layer_weights`] for more info.
+    pub fn dominant_layer(&self, position: Vector3<f32>) -> Option<usize> {
+        self.layer_weights(position)?
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+
+    /// Casts a ray and looks for intersections with the terrain. This method collects all results in
+    /// given array with optional sorting by time-of-impact.
+    ///
+    /// # Performance
+    ///
+    /// This method isn't well optimized, it could be optimized 2-5x times. This is a TODO for now.
+    pub fn raycast<const DIM: usize>(
+        &self,
+        ray: Ray,
+        results: &mut ArrayVec<TerrainRayCastResult, DIM>,
+        sort_results: bool,
+    ) -> bool {
+        if let Some(inv_transform) = self.global_transform().try_inverse() {
+            // Transform ray into local coordinate system of the terrain.
+            let local_ray = ray.transform(inv_transform);
+
+            // Project ray on the terrain's 2D space.
+            let origin_proj = map_to_local(
+                inv_transform
+                    .transform_point(&Point3::from(ray.origin))
+                    .coords,
+            );
+            let dir_proj = map_to_local(inv_transform.transform_vector(&ray.dir));
+
+            // Check each cell of each chunk for intersection in 2D.
+            'chunk_loop: for (chunk_index, chunk) in self.chunks.iter().enumerate() {
+                let cell_width = chunk.width / (chunk.width_point_count - 1) as f32;
+                let cell_length = chunk.length / (chunk.length_point_count - 1) as f32;
+
+                for z in 0..chunk.length_point_count {
+                    let kz = z as f32 / (chunk.length_point_count - 1) as f32;
+                    let nz = z + 1;
+
+                    for x in 0..chunk.width_point_count {
+                        let kx = x as f32 / (chunk.width_point_count - 1) as f32;
+                        let nx = x + 1;
+
+                        let pixel_position = chunk.local_position()
+                            + Vector2::new(kx * chunk.width, kz * chunk.length);
+
+                        let cell_bounds =
+                            Rect::new(pixel_position.x, pixel_position.y, cell_width, cell_length);
+
+                        if ray_rect_intersection(cell_bounds, origin_proj, dir_proj).is_some() {
+                            // If we have 2D intersection, go back in 3D and do precise intersection
+                            // check.
+                            if nx < chunk.width_point_count
+                                && nz < chunk.length_point_count
+                                && !chunk.is_hole(x, z)
+                            {
+                                let i0 = (z * chunk.width_point_count + x) as usize;
+                                let i1 = ((z + 1) * chunk.width_point_count + x) as usize;
+                                let i2 = ((z + 1) * chunk.width_point_count + x + 1) as usize;
+                                let i3 = (z * chunk.width_point_count + x + 1) as usize;
+
+                                let v0 = Vector3::new(
+                                    pixel_position.x,
+                                    chunk.heightmap[i0],
+                                    pixel_position.y, // Remember Z -> Y mapping!
+                                );
+                                let v1 =
+                                    Vector3::new(v0.x, chunk.heightmap[i1], v0.z + cell_length);
+                                let v2 = Vector3::new(v1.x + cell_width, chunk.heightmap[i2], v1.z);
+                                let v3 = Vector3::new(v0.x + cell_width, chunk.heightmap[i3], v0.z);
+
+                                for vertices in &[[v0, v1, v2], [v2, v3, v0]] {
+                                    if let Some((toi, intersection)) =
+                                        local_ray.triangle_intersection(vertices)
+                                    {
+                                        let normal = (vertices[2] - vertices[0])
+                                            .cross(&(vertices[1] - vertices[0]))
+                                            .try_normalize(f32::EPSILON)
+                                            .unwrap_or_else(Vector3::y);
+
+                                        let result = TerrainRayCastResult {
+                                            position: intersection,
+                                            normal,
+                                            chunk_index,
+                                            toi,
+                                        };
+
+                                        if results.try_push(result).is_err() {
+                                            break 'chunk_loop;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if sort_results {
+            results.sort_unstable_by(|a, b| {
+                if a.toi > b.toi {
+                    Ordering::Greater
+                } else if a.toi < b.toi {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            });
+        }
+
+        !results.is_empty()
+    }
+
+    /// Updates terrain's chunks. There is no need to call this method in normal circumstances,
+    /// engine will automatically call this method when needed.
+    pub fn update(&mut self) {
+        for chunk in self.chunks.iter_mut() {
+            chunk.update();
+        }
+    }
+
+    /// Returns a reference to a slice with layers of the terrain.
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Returns a mutable reference to a slice with layers of the terrain.
+    pub fn layers_mut(&mut self) -> &mut [Layer] {
+        &mut self.layers
+    }
+
+    /// Adds new layer to the chunk. It is possible to have different layer count per chunk
+    /// in the same terrain, however it seems to not have practical usage, so try to keep
+    /// equal layer count per each chunk in your terrains.
+    pub fn add_layer(&mut self, layer: Layer) {
+        self.layers.push(layer);
+    }
+
+    /// Removes given layers from the terrain.
+    pub fn remove_layer(&mut self, layer: usize) -> Layer {
+        self.layers.remove(layer)
+    }
+
+    /// Tries to remove last layer from the terrain.
+    pub fn pop_layer(&mut self) -> Option<Layer> {
+        self.layers.pop()
+    }
+
+    /// Inserts new layer at given position in the terrain.
+    pub fn insert_layer(&mut self, layer: Layer, index: usize) {
+        self.layers.insert(index, layer)
+    }
+
+    /// Creates new layer with given parameters, but does **not** add it to the terrain.
+    pub fn create_layer(
+        &self,
+        value: u8,
+        material: Arc<Mutex<Material>>,
+        mask_property_name: String,
+    ) -> Layer {
+        Layer {
+            material,
+            mask_property_name,
+            chunk_masks: self
+                .chunks
+                .iter()
+                .map(|c| {
+                    create_layer_mask(
+                        (c.width * self.mask_resolution) as u32,
+                        (c.length * self.mask_resolution) as u32,
+                        value,
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Shape of a brush.
+#[derive(Copy, Clone)]
+pub enum BrushShape {
+    /// Circle with given radius.
+    Circle {
+        /// Radius of the circle.
+        radius: f32,
+    },
+    /// Rectangle with given width and height.
+    Rectangle {
+        /// Width of the rectangle.
+        width: f32,
+        /// Length of the rectangle.
+        length: f32,
+    },
+}
+
+impl BrushShape {
+    fn contains(&self, brush_center: Vector2<f32>, pixel_position: Vector2<f32>) -> bool {
+        match *self {
+            BrushShape::Circle { radius } => (brush_center - pixel_position).norm() < radius,
+            BrushShape::Rectangle { width, length } => Rect::new(
+                brush_center.x - width * 0.5,
+                brush_center.y - length * 0.5,
+                width,
+                length,
+            )
+            .contains(pixel_position),
+        }
+    }
+
+    /// Returns distance from the center of the brush to a point, where 0.0 is the center and 1.0 is
+    /// the edge of the brush.
+    fn normalized_distance(&self, brush_center: Vector2<f32>, pixel_position: Vector2<f32>) -> f32 {
+        let d = pixel_position - brush_center;
+        match *self {
+            BrushShape::Circle { radius } => d.norm() / radius,
+            BrushShape::Rectangle { width, length } => {
+                (d.x.abs() / (width * 0.5)).max(d.y.abs() / (length * 0.5))
+            }
+        }
+    }
+
+    fn bounds(&self, brush_center: Vector2<f32>) -> (Vector2<f32>, Vector2<f32>) {
+        let half_size = match *self {
+            BrushShape::Circle { radius } => Vector2::new(radius, radius),
+            BrushShape::Rectangle { width, length } => Vector2::new(width * 0.5, length * 0.5),
+        };
+        (brush_center - half_size, brush_center + half_size)
+    }
+}
+
+/// Paint mode of a brush. It defines operation that will be performed on the terrain.
+#[derive(Clone, PartialEq, PartialOrd)]
+pub enum BrushMode {
+    /// Modifies height map.
+    ModifyHeightMap {
+        /// An offset for height map.
+        amount: f32,
+    },
+    /// Draws on a given layer.
+    DrawOnMask {
+        /// A layer to draw on.
+        layer: usize,
+        /// A value to put on mask. Range is [-1.0; 1.0] where negative values "erase"
+        /// values from mask, and positive - paints.
+        alpha: f32,
+    },
+    /// Cuts holes in the terrain or fills them back. A cell of a height map becomes a hole if its
+    /// center is inside the brush.
+    DrawHoles {
+        /// True to fill holes, false to cut them.
+        erase: bool,
+    },
+    /// Adds instances to a foliage layer at random points within the brush, foliage is never
+    /// placed in holes. Falloff of the brush defines probability of placement.
+    PaintFoliage {
+        /// Index of a foliage layer.
+        layer: usize,
+        /// Amount of new instances per square unit for each application of the brush.
+        density: f32,
+    },
+    /// Removes instances of a foliage layer within the brush. Falloff of the brush defines
+    /// probability of removal.
+    EraseFoliage {
+        /// Index of a foliage layer.
+        layer: usize,
+    },
+    /// Smooths height map by moving every point towards average height of its neighbours.
+    Smooth {
+        /// Strength of smoothing. Range is [0.0; 1.0].
+        amount: f32,
+    },
+    /// Gradually moves height map towards given height.
+    Flatten {
+        /// Target height.
+        height: f32,
+        /// Fraction of distance to target height passed by each application. Range is [0.0; 1.0].
+        amount: f32,
+    },
+    /// Sets height map to given height. Falloff of the brush is used to blend with current heights.
+    SetHeight {
+        /// New height.
+        height: f32,
+    },
+    /// Adds procedural fractal noise to height map.
+    Noise {
+        /// Maximum offset of height map.
+        amount: f32,
+        /// Frequency of the noise, larger values gives more detailed noise.
+        frequency: f32,
+        /// Seed of the noise, different seeds gives different patterns.
+        seed: u32,
+    },
+    /// Simulates collapse of steep slopes - material slides down until slope of terrain is less
+    /// than given one.
+    ThermalErosion {
+        /// Amount of simulation steps.
+        iterations: u32,
+        /// Maximum stable slope as tangent of an angle, e.g. 1.0 is 45 degrees.
+        talus: f32,
+        /// Fraction of unstable material moved on each step. Range is [0.0; 1.0].
+        amount: f32,
+    },
+    /// Simulates rain that dissolves soil, carries it down the slopes and deposits it as water
+    /// evaporates.
+    HydraulicErosion {
+        /// Amount of simulation steps.
+        iterations: u32,
+        /// Amount of water added to each point on each step.
+        rain: f32,
+        /// Amount of soil dissolved by a unit of water, it also defines how much soil can be
+        /// carried by a unit of water.
+        solubility: f32,
+        /// Fraction of water evaporated on each step. Range is [0.0; 1.0].
+        evaporation: f32,
+    },
+}
+
+/// Brush is used to modify terrain. It supports multiple shapes and modes.
+#[derive(Clone)]
+pub struct Brush {
+    /// Center of the brush.
+    pub center: Vector3<f32>,
+    /// Shape of the brush.
+    pub shape: BrushShape,
+    /// Paint mode of the brush.
+    pub mode: BrushMode,
+    /// Optional falloff curve of the brush. It maps distance from the center of the brush (0.0) to
+    /// its edge (1.0) to strength of the brush. If not set, circle brushes are fading to edges and
+    /// rectangle brushes have uniform strength.
+    pub falloff: Option<Curve>,
+}
+
+impl Brush {
+    /// Creates new brush without falloff curve, use [`Brush::with_falloff`] to set one.
+    pub fn new(center: Vector3<f32>, shape: BrushShape, mode: BrushMode) -> Self {
+        Self {
+            center,
+            shape,
+            mode,
+            falloff: None,
+        }
+    }
+
+    /// Sets falloff curve of the brush, see [`Brush::falloff`].
+    pub fn with_falloff(mut self, falloff: Curve) -> Self {
+        self.falloff = Some(falloff);
+        self
+    }
+
+    fn weight(&self, center: Vector2<f32>, position: Vector2<f32>) -> f32 {
+        let distance = self.shape.normalized_distance(center, position);
+        match (&self.falloff, self.shape) {
+            (Some(falloff), _) => falloff.value_at(distance),
+            (None, BrushShape::Circle { .. }) => 1.0 - distance * distance,
+            (None, BrushShape::Rectangle { .. }) => 1.0,
+        }
+    }
+}
+
+/// Change of a single point of a height map of a chunk.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HeightMapChange {
+    /// Index of a chunk.
+    pub chunk: usize,
+    /// Index of a point in the height map of the chunk.
+    pub index: usize,
+    /// Height before the change.
+    pub old_height: f32,
+    /// Height after the change.
+    pub new_height: f32,
+}
+
+/// Set of changes of height maps made by [`Terrain::draw`]. It can be used to undo and redo
+/// drawing operations.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeightMapDelta {
+    changes: Vec<HeightMapChange>,
+}
+
+impl HeightMapDelta {
+    /// Returns a list of changed points.
+    pub fn changes(&self) -> &[HeightMapChange] {
+        &self.changes
+    }
+
+    /// Returns true if nothing was changed.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Restores heights that terrain had before the changes.
+    pub fn revert(&self, terrain: &mut Terrain) {
+        for change in self.changes.iter().rev() {
+            terrain.set_height(change.chunk, change.index, change.old_height);
+        }
+        terrain.snap_foliage();
+    }
+
+    /// Applies the changes again, it is used to redo reverted changes.
+    pub fn apply(&self, terrain: &mut Terrain) {
+        for change in self.changes.iter() {
+            terrain.set_height(change.chunk, change.index, change.new_height);
+        }
+        terrain.snap_foliage();
+    }
+}
+
+// Height maps of all chunks as a single grid. Chunks share points on their edges, so a point of
+// the grid may belong to up to four chunks.
+struct HeightGrid {
+    width_chunks: usize,
+    length_chunks: usize,
+    chunk_width_points: usize,
+    chunk_length_points: usize,
+    width: usize,
+    length: usize,
+    cell_width: f32,
+    cell_length: f32,
+}
+
+impl HeightGrid {
+    fn new(terrain: &Terrain) -> Option<Self> {
+        let chunk = terrain.chunks.first()?;
+        let chunk_width_points = chunk.width_point_count as usize;
+        let chunk_length_points = chunk.length_point_count as usize;
+        if chunk_width_points < 2 || chunk_length_points < 2 {
+            return None;
+        }
+        Some(Self {
+            width_chunks: terrain.width_chunks as usize,
+            length_chunks: terrain.length_chunks as usize,
+            chunk_width_points,
+            chunk_length_points,
+            width: terrain.width_chunks as usize * (chunk_width_points - 1) + 1,
+            length: terrain.length_chunks as usize * (chunk_length_points - 1) + 1,
+            cell_width: chunk.width / (chunk_width_points - 1) as f32,
+            cell_length: chunk.length / (chunk_length_points - 1) as f32,
+        })
+    }
+
+    fn position(&self, x: usize, z: usize) -> Vector2<f32> {
+        Vector2::new(x as f32 * self.cell_width, z as f32 * self.cell_length)
+    }
+
+    // Returns pairs (chunk index, point index) of every chunk that contains given point.
+    fn chunks_at(&self, x: usize, z: usize) -> ArrayVec<(usize, usize), 4> {
+        let mut result = ArrayVec::new();
+        for (chunk_z, local_z) in grid_cells(z, self.chunk_length_points - 1, self.length_chunks) {
+            for (chunk_x, local_x) in grid_cells(x, self.chunk_width_points - 1, self.width_chunks)
+            {
+                result.push((
+                    chunk_z * self.width_chunks + chunk_x,
+                    local_z * self.chunk_width_points + local_x,
+                ));
+            }
+        }
+        result
+    }
+}
+
+// Returns pairs (chunk, local point) for a point on one axis of the height grid.
+fn grid_cells(point: usize, cells: usize, chunks: usize) -> ArrayVec<(usize, usize), 2> {
+    let mut result = ArrayVec::new();
+    let (chunk, local) = (point / cells, point % cells);
+    if local == 0 && chunk > 0 {
+        result.push((chunk - 1, cells));
+    }
+    if chunk < chunks {
+        result.push((chunk, local));
+    }
+    result
+}
+
+// A rectangular part of the height grid affected by a brush.
+struct Region {
+    width: usize,
+    length: usize,
+}
+
+impl Region {
+    fn neighbours(&self, i: usize) -> ArrayVec<usize, 4> {
+        let (x, z) = (i % self.width, i / self.width);
+        let mut result = ArrayVec::new();
+        if x > 0 {
+            result.push(i - 1);
+        }
+        if x + 1 < self.width {
+            result.push(i + 1);
+        }
+        if z > 0 {
+            result.push(i - self.width);
+        }
+        if z + 1 < self.length {
+            result.push(i + self.width);
+        }
+        result
+    }
+
+    fn smooth(&self, heights: &[f32]) -> Vec<f32> {
+        (0..heights.len())
+            .map(|i| {
+                let neighbours = self.neighbours(i);
+                (heights[i] + neighbours.iter().map(|&n| heights[n]).sum::<f32>())
+                    / (neighbours.len() + 1) as f32
+            })
+            .collect()
+    }
+
+    fn thermal_erosion(
+        &self,
+        heights: &[f32],
+        iterations: u32,
+        talus: f32,
+        amount: f32,
+    ) -> Vec<f32> {
+        let mut heights = heights.to_vec();
+        let mut delta = vec![0.0; heights.len()];
+        for _ in 0..iterations {
+            for (i, h) in heights.iter().enumerate() {
+                let neighbours = self.neighbours(i);
+                let mut max_difference = 0.0f32;
+                let mut total_excess = 0.0;
+                for &n in neighbours.iter() {
+                    let difference = h - heights[n];
+                    if difference > talus {
+                        max_difference = max_difference.max(difference);
+                        total_excess += difference - talus;
+                    }
+                }
+                if total_excess > 0.0 {
+                    let moved = amount * (max_difference - talus) * 0.5;
+                    delta[i] -= moved;
+                    for &n in neighbours.iter() {
+                        let difference = h - heights[n];
+                        if difference > talus {
+                            delta[n] += moved * (difference - talus) / total_excess;
+                        }
+                    }
+                }
+            }
+            for (h, d) in heights.iter_mut().zip(delta.iter_mut()) {
+                *h += *d;
+                *d = 0.0;
+            }
+        }
+        heights
+    }
+
+    fn hydraulic_erosion(
+        &self,
+        heights: &[f32],
+        iterations: u32,
+        rain: f32,
+        solubility: f32,
+        evaporation: f32,
+    ) -> Vec<f32> {
+        let mut heights = heights.to_vec();
+        let mut water = vec![0.0; heights.len()];
+        let mut sediment = vec![0.0; heights.len()];
+        for _ in 0..iterations {
+            // Rain dissolves soil.
+            for i in 0..heights.len() {
+                water[i] += rain;
+                let dissolved = solubility * water[i];
+                heights[i] -= dissolved;
+                sediment[i] += dissolved;
+            }
+
+            // Water flows to lower neighbours and carries dissolved soil with it.
+            let mut new_water = water.clone();
+            let mut new_sediment = sediment.clone();
+            for i in 0..heights.len() {
+                if water[i] <= 0.0 {
+                    continue;
+                }
+                let level = heights[i] + water[i];
+                let neighbours = self.neighbours(i);
+                let mut max_difference = 0.0f32;
+                let mut total_difference = 0.0;
+                for &n in neighbours.iter() {
+                    let difference = level - (heights[n] + water[n]);
+                    if difference > 0.0 {
+                        max_difference = max_difference.max(difference);
+                        total_difference += difference;
+                    }
+                }
+                if total_difference > 0.0 {
+                    let moved_water = water[i].min(max_difference * 0.5);
+                    let moved_sediment = sediment[i] * moved_water / water[i];
+                    new_water[i] -= moved_water;
+                    new_sediment[i] -= moved_sediment;
+                    for &n in neighbours.iter() {
+                        let difference = level - (heights[n] + water[n]);
+                        if difference > 0.0 {
+                            let k = difference / total_difference;
+                            new_water[n] += moved_water * k;
+                            new_sediment[n] += moved_sediment * k;
+                        }
+                    }
+                }
+            }
+            water = new_water;
+            sediment = new_sediment;
+
+            // Water evaporates and deposits soil it cannot carry anymore.
+            for i in 0..heights.len() {
+                water[i] *= 1.0 - evaporation;
+                let capacity = solubility * water[i];
+                if sediment[i] > capacity {
+                    heights[i] += sediment[i] - capacity;
+                    sediment[i] = capacity;
+                }
+            }
+        }
+        for (h, s) in heights.iter_mut().zip(sediment.iter()) {
+            *h += s;
+        }
+        heights
+    }
+}
+
+fn noise_hash(x: i32, z: i32, seed: u32) -> f32 {
+    let mut h = (x as u32)
+        .wrapping_mul(0x27d4_eb2d)
+        .wrapping_add((z as u32).wrapping_mul(0x1656_67b1))
+        .wrapping_add(seed.wrapping_mul(0x9e37_79b9));
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    // Map to [-1.0; 1.0] range.
+    (h as f32 / u32::MAX as f32) * 2.0 - 1.0
+}
+
+fn value_noise(p: Vector2<f32>, seed: u32) -> f32 {
+    let (x, z) = (p.x.floor(), p.y.floor());
+    let (ix, iz) = (x as i32, z as i32);
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, tz) = (smooth(p.x - x), smooth(p.y - z));
+    lerpf(
+        lerpf(noise_hash(ix, iz, seed), noise_hash(ix + 1, iz, seed), tx),
+        lerpf(
+            noise_hash(ix, iz + 1, seed),
+            noise_hash(ix + 1, iz + 1, seed),
+            tx,
+        ),
+        tz,
+    )
+}
+
+// Sum of few octaves of value noise, result is in [-1.0; 1.0] range.
+fn fractal_noise(p: Vector2<f32>, seed: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut total_amplitude = 0.0;
+    for octave in 0..4 {
+        sum += amplitude * value_noise(p * frequency, seed.wrapping_add(octave));
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / total_amplitude
+}
+
+/// An error that may occur during import or export of height maps and layer masks.
+#[derive(Debug, thiserror::Error)]
+pub enum TerrainError {
+    /// An io error.
+    #[error("An i/o error has occurred: {0}")]
+    Io(std::io::Error),
+    /// Internal image crate error.
+    #[error("Image error {0}")]
+    Image(image::ImageError),
+    /// Size of RAW data does not match dimensions of a height map.
+    #[error("RAW height map has invalid size of {0} bytes!")]
+    InvalidRawSize(usize),
+    /// There is no layer with given index.
+    #[error("There is no layer with index {0}!")]
+    InvalidLayer(usize),
+    /// Mask of a chunk with given index is not a 2D greyscale image.
+    #[error("Mask of chunk {0} is not a 2D greyscale image!")]
+    InvalidMask(usize),
+    /// Terrain has no chunks.
+    #[error("Terrain has no chunks!")]
+    Empty,
+}
+
+impl From<image::ImageError> for TerrainError {
+    fn from(v: image::ImageError) -> Self {
+        Self::Image(v)
+    }
+}
+
+impl From<std::io::Error> for TerrainError {
+    fn from(v: std::io::Error) -> Self {
+        Self::Io(v)
+    }
+}
+
+fn is_raw_path(path: &Path) -> bool {
+    match path.extension() {
+        Some(ext) => ext.eq_ignore_ascii_case("raw") || ext.eq_ignore_ascii_case("r16"),
+        None => false,
+    }
+}
+
+/// 16-bit greyscale height map that can be imported to or exported from a terrain. Such height
+/// maps are produced by most of terrain generation tools either as 16-bit PNG images or as RAW
+/// files with 16-bit little-endian values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightMapImage {
+    width: u32,
+    length: u32,
+    pixels: Vec<u16>,
+}
+
+impl HeightMapImage {
+    /// Creates new height map from given pixels, amount of pixels must be `width * length`.
+    pub fn new(width: u32, length: u32, pixels: Vec<u16>) -> Option<Self> {
+        if width > 0 && length > 0 && pixels.len() == (width * length) as usize {
+            Some(Self {
+                width,
+                length,
+                pixels,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Loads height map from a file. Files with `raw` or `r16` extensions are treated as RAW
+    /// height maps, which must be square (see [`Self::from_raw`] for non-square ones). Every other
+    /// file is loaded as an image, images with 8-bit channels are expanded to 16 bits.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, TerrainError> {
+        let path = path.as_ref();
+        if is_raw_path(path) {
+            let data = std::fs::read(path)?;
+            let size = (data.len() as f64 / 2.0).sqrt() as u32;
+            Self::from_raw(&data, size, size)
+        } else {
+            Ok(Self::from_dynamic_image(image::open(path)?))
+        }
+    }
+
+    /// Loads height map from an image in memory, format of the image is detected automatically.
+    pub fn from_memory(data: &[u8]) -> Result<Self, TerrainError> {
+        Ok(Self::from_dynamic_image(image::load_from_memory(data)?))
+    }
+
+    fn from_dynamic_image(image: image::DynamicImage) -> Self {
+        let image = image.into_luma16();
+        Self {
+            width: image.width(),
+            length: image.height(),
+            pixels: image.into_raw(),
+        }
+    }
+
+    /// Creates height map from RAW data with 16-bit little-endian values.
+    pub fn from_raw(data: &[u8], width: u32, length: u32) -> Result<Self, TerrainError> {
+        if width == 0 || length == 0 || data.len() != (width * length * 2) as usize {
+            return Err(TerrainError::InvalidRawSize(data.len()));
+        }
+        Ok(Self {
+            width,
+            length,
+            pixels: data
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect(),
+        })
+    }
+
+    /// Returns height map as RAW data with 16-bit little-endian values.
+    pub fn to_raw(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|p| p.to_le_bytes()).collect()
+    }
+
+    /// Saves height map to a file. Files with `raw` or `r16` extensions are saved as RAW data,
+    /// every other file is saved as 16-bit greyscale image in a format defined by extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), TerrainError> {
+        let path = path.as_ref();
+        if is_raw_path(path) {
+            Ok(std::fs::write(path, self.to_raw())?)
+        } else {
+            let bytes = self
+                .pixels
+                .iter()
+                .flat_map(|p| p.to_ne_bytes())
+                .collect::<Vec<_>>();
+            Ok(image::save_buffer(
+                path,
+                &bytes,
+                self.width,
+                self.length,
+                image::ColorType::L16,
+            )?)
+        }
+    }
+
+    /// Returns width of the height map in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns length of the height map in pixels.
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// Returns pixels of the height map.
+    pub fn pixels(&self) -> &[u16] {
+        &self.pixels
+    }
+
+    /// Samples height map at given normalized coordinates using bilinear filtering. Returned
+    /// value is in [0.0; 1.0] range.
+    pub fn sample(&self, u: f32, v: f32) -> f32 {
+        let x = u.clamp(0.0, 1.0) * (self.width - 1) as f32;
+        let z = v.clamp(0.0, 1.0) * (self.length - 1) as f32;
+        let (x0, z0) = (x.floor() as u32, z.floor() as u32);
+        let (x1, z1) = ((x0 + 1).min(self.width - 1), (z0 + 1).min(self.length - 1));
+        let pixel = |x: u32, z: u32| self.pixels[(z * self.width + x) as usize] as f32;
+        let (tx, tz) = (x - x0 as f32, z - z0 as f32);
+        lerpf(
+            lerpf(pixel(x0, z0), pixel(x1, z0), tx),
+            lerpf(pixel(x0, z1), pixel(x1, z1), tx),
+            tz,
+        ) / u16::MAX as f32
+    }
+}
+
+/// Layer definition for a terrain builder.
+pub struct LayerDefinition {
+    /// Material of the layer.
+    pub material: Arc<Mutex<Material>>,
+
+    /// Name of the mask sampler in the material. It should be `maskTexture` if standard material shader
+    /// is used.
+    ///
+    /// # Implementation details
+    ///
+    /// It will be used in the renderer to set appropriate chunk mask to the copy of the material.
+    pub mask_property_name: String,
+}
+
+/// Terrain builder allows you to quickly build a terrain with required features.
+pub struct TerrainBuilder {
+    base_builder: BaseBuilder,
+    width: f32,
+    length: f32,
+    mask_resolution: f32,
+    width_chunks: usize,
+    length_chunks: usize,
+    height_map_resolution: f32,
+    layers: Vec<LayerDefinition>,
+    decal_layer_index: u8,
+    lod_distance: f32,
+    height_map: Option<(HeightMapImage, f32, f32)>,
+}
+
+fn make_divisible_by_2(n: u32) -> u32 {
+    if n & 1 == 1 {
+        n + 1
+    } else {
+        n
+    }
+}
+
+fn create_layer_mask(width: u32, height: u32, value: u8) -> Texture {
+    let mask = Texture::from_bytes(
+        TextureKind::Rectangle { width, height },
+        TexturePixelKind::R8,
+        vec![value; (width * height) as usize],
+        // Content of mask will be explicitly serialized.
+        true,
+    )
+    .unwrap();
+
+    let mut data_ref = mask.data_ref();
+    data_ref.set_s_wrap_mode(TextureWrapMode::ClampToEdge);
+    data_ref.set_t_wrap_mode(TextureWrapMode::ClampToEdge);
+    drop(data_ref);
+
+    mask
+}
+
+// Samples R8 mask with bilinear filtering, returns value in [0.0; 1.0] range.
+fn sample_mask(mask: &Texture, u: f32, v: f32) -> f32 {
+    let data = mask.data_ref();
+    let (width, height) = match data.kind() {
+        TextureKind::Rectangle { width, height } => (width, height),
+        _ => unreachable!("Mask must be a 2D greyscale image!"),
+    };
+    let x = u.clamp(0.0, 1.0) * (width - 1) as f32;
+    let y = v.clamp(0.0, 1.0) * (height - 1) as f32;
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let pixel = |x: u32, y: u32| data.data()[(y * width + x) as usize] as f32;
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+    lerpf(
+        lerpf(pixel(x0, y0), pixel(x1, y0), tx),
+        lerpf(pixel(x0, y1), pixel(x1, y1), tx),
+        ty,
+    ) / 255.0
+}
+
+fn make_surface_data() -> Arc<RwLock<SurfaceData>> {
+    Arc::new(RwLock::new(SurfaceData::new(
+        VertexBuffer::new::<StaticVertex>(0, StaticVertex::layout(), vec![]).unwrap(),
+        GeometryBuffer::default(),
+        false,
+    )))
+}
+
+impl TerrainBuilder {
+    /// Creates new builder instance.
+    pub fn new(base_builder: BaseBuilder) -> Self {
+        Self {
+            base_builder,
+            width: 64.0,
+            length: 64.0,
+            width_chunks: 2,
+            length_chunks: 2,
+            mask_resolution: 16.0,
+            height_map_resolution: 8.0,
+            layers: Default::default(),
+            decal_layer_index: 0,
+            lod_distance: 0.0,
+            height_map: None,
+        }
+    }
+
+    /// Sets desired terrain height in local coordinates.
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets desired terrain length in local coordinates.
+    pub fn with_length(mut self, length: f32) -> Self {
+        self.length = length;
+        self
+    }
+
+    /// Sets desired mask resolution in pixels per unit. For example you have width = height = 16
+    /// and you set resolution to 4 - then mask will have width = height = 4*16 = 64x64 pixels.
+    pub fn with_mask_resolution(mut self, resolution: f32) -> Self {
+        self.mask_resolution = resolution;
+        self
+    }
+
+    /// Sets desired terrain width subdivision. The value passed in should correlate with desired
+    /// width of the terrain. For example if you have small terrain, 2 chunks will be enough, however
+    /// if you have huge terrain, the value should be 8+.
+    pub fn with_width_chunks(mut self, count: usize) -> Self {
+        self.width_chunks = count.max(1);
+        self
+    }
+
+    /// Sets desired terrain length subdivision. The value passed in should correlate with desired
+    /// length of the terrain. For example if you have small terrain, 2 chunks will be enough, however
+    /// if you have huge terrain, the value should be 8+.
+    pub fn with_length_chunks(mut self, count: usize) -> Self {
+        self.length_chunks = count.max(1);
+        self
+    }
+
+    /// Sets desired height map resolution in dots per unit. For example you have width = height = 16
+    /// and you set resolution to 4 - then height map will have width = height = 4*16 = 64x64 dots.
+    pub fn with_height_map_resolution(mut self, resolution: f32) -> Self {
+        self.height_map_resolution = resolution;
+        self
+    }
+
+    /// Sets desired layers that will be used for each chunk in the terrain.
+    pub fn with_layers(mut self, layers: Vec<LayerDefinition>) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Sets desired decal layer index.
+    pub fn with_decal_layer_index(mut self, decal_layer_index: u8) -> Self {
+        self.decal_layer_index = decal_layer_index;
+        self
+    }
+
+    /// Sets desired distance at which chunks switch to the first level of detail. See
+    /// [`Terrain::set_lod_distance`] for more info.
+    pub fn with_lod_distance(mut self, distance: f32) -> Self {
+        self.lod_distance = distance;
+        self
+    }
+
+    /// Sets height map that will be imported to the terrain. See [`Terrain::import_height_map`]
+    /// for more info.
+    pub fn with_height_map(mut self, height_map: HeightMapImage, scale: f32, offset: f32) -> Self {
+        self.height_map = Some((height_map, scale, offset));
+        self
+    }
+
+    /// Build terrain node.
+    pub fn build_node(self) -> Node {
+        let mut chunks = Vec::new();
+        let chunk_length = self.length / self.length_chunks as f32;
+        let chunk_width = self.width / self.width_chunks as f32;
+        let chunk_length_points =
+            make_divisible_by_2((chunk_length * self.height_map_resolution) as u32);
+        let chunk_width_points =
+            make_divisible_by_2((chunk_width * self.height_map_resolution) as u32);
+        for z in 0..self.length_chunks {
+            for x in 0..self.width_chunks {
+                chunks.push(Chunk {
+                    width_point_count: chunk_width_points,
+                    length_point_count: chunk_length_points,
+                    heightmap: vec![0.0; (chunk_length_points * chunk_width_points) as usize],
+                    position: Vector3::new(x as f32 * chunk_width, 0.0, z as f32 * chunk_length),
+                    width: chunk_width,
+                    surface_data: make_surface_data(),
+                    dirty: Cell::new(true),
+                    length: chunk_length,
+                    min_height: 0.0,
+                    max_height: 0.0,
+                    lods: Default::default(),
+                    hole_mask: Default::default(),
+                });
+            }
+        }
+
+        let mask_resolution = self.mask_resolution;
+
+        let mut terrain = Terrain {
+            width: self.width,
+            length: self.length,
+            base: self.base_builder.build_base(),
+            layers: self
+                .layers
+                .into_iter()
+                .enumerate()
+                .map(|(layer_index, definition)| {
+                    Layer {
+                        material: definition.material,
+                        mask_property_name: definition.mask_property_name,
+                        chunk_masks: chunks
+                            .iter()
+                            .map(|c| {
+                                create_layer_mask(
+                                    (c.width * mask_resolution) as u32,
+                                    (c.length * mask_resolution) as u32,
+                                    // Base layer is opaque, every other by default - transparent.
+                                    if layer_index == 0 { 255 } else { 0 },
+                                )
+                            })
+                            .collect(),
+                    }
+                })
+                .collect(),
+            chunks,
+            bounding_box_dirty: Cell::new(true),
+            bounding_box: Default::default(),
+            mask_resolution: self.mask_resolution,
+            height_map_resolution: self.height_map_resolution,
+            width_chunks: self.width_chunks as u32,
+            length_chunks: self.length_chunks as u32,
+            decal_layer_index: self.decal_layer_index,
+            lod_distance: self.lod_distance.max(0.0),
+            foliage_layers: Default::default(),
+        };
+
+        if let Some((height_map, scale, offset)) = self.height_map {
+            terrain.import_height_map(&height_map, scale, offset);
+        }
+
+        Node::Terrain(terrain)
+    }
+
+    /// Builds terrain node and adds it to given graph.
+    pub fn build(self, graph: &mut Graph) -> Handle<Node> {
+        graph.add_node(self.build_node())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::Vector3,
+            arrayvec::ArrayVec,
+            curve::{Curve, CurveKey, CurveKeyKind},
+            math::ray::Ray,
+        },
+        material::Material,
+        physics::parry::shape::HeightFieldCellStatus,
+        scene::{
+            base::BaseBuilder,
+            physics::Physics,
+            terrain::{
+                lod_level, lod_samples, make_lod_triangles, Brush, BrushMode, BrushShape, ChunkLod,
+                HeightMapImage, LayerDefinition, Terrain, TerrainBuilder, TerrainError,
+                TerrainRayCastResult,
+            },
+        },
+    };
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Mutex},
+    };
+
+    const WIDTH: u32 = 12;
+    const LENGTH: u32 = 10;
+
+    fn signed_area(triangle: &[u32; 3]) -> i64 {
+        let p = |i: u32| ((i % WIDTH) as i64, (i / WIDTH) as i64);
+        let (a, b, c) = (p(triangle[0]), p(triangle[1]), p(triangle[2]));
+        (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+    }
+
+    fn edge_points(lod: ChunkLod, on_edge: impl Fn(u32) -> bool) -> Vec<u32> {
+        let mut points = make_lod_triangles(WIDTH, LENGTH, lod)
+            .iter()
+            .flat_map(|t| t.0.to_vec())
+            .filter(|&i| on_edge(i))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        points.sort_unstable();
+        points
+    }
+
+    #[test]
+    fn test_lod_samples() {
+        assert_eq!(lod_samples(6, 0), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(lod_samples(6, 1), vec![0, 2, 4, 5]);
+        assert_eq!(lod_samples(6, 2), vec![0, 4, 5]);
+
+        assert_eq!(lod_level(10.0, 0.0), 0);
+        assert_eq!(lod_level(10.0, 16.0), 0);
+        assert_eq!(lod_level(16.0, 16.0), 1);
+        assert_eq!(lod_level(40.0, 16.0), 2);
+    }
+
+    #[test]
+    fn test_lod_triangles_cover_chunk() {
+        let full = make_lod_triangles(WIDTH, LENGTH, ChunkLod::default());
+        assert_eq!(full.len() as u32, (WIDTH - 1) * (LENGTH - 1) * 2);
+
+        // Every level of detail with any combination of stitched edges must cover whole chunk
+        // without holes and overlaps, and keep the winding.
+        for level in 0..3 {
+            for mask in 0..16 {
+                let mut edges = [level; 4];
+                for (i, edge) in edges.iter_mut().enumerate() {
+                    if mask & (1 << i) != 0 {
+                        *edge = level + 1 + i as u32 % 2;
+                    }
+                }
+                let triangles = make_lod_triangles(WIDTH, LENGTH, ChunkLod { level, edges });
+                assert!(triangles.iter().all(|t| signed_area(&t.0) < 0));
+                let area = -triangles.iter().map(|t| signed_area(&t.0)).sum::<i64>();
+                assert_eq!(area, 2 * ((WIDTH - 1) * (LENGTH - 1)) as i64);
+            }
+        }
+
+        assert!(
+            make_lod_triangles(
+                WIDTH,
+                LENGTH,
+                ChunkLod {
+                    level: 2,
+                    edges: [2; 4]
+                }
+            )
+            .len()
+                < full.len() / 8
+        );
+    }
+
+    #[test]
+    fn test_lod_stitching() {
+        // Full detail chunk on the left, coarse one on the right. Points on the shared edge
+        // must match.
+        let left = ChunkLod {
+            level: 0,
+            edges: [0, 2, 0, 0],
+        };
+        let right = ChunkLod {
+            level: 2,
+            edges: [2; 4],
+        };
+
+        let left_edge = edge_points(left, |i| i % WIDTH == WIDTH - 1)
+            .iter()
+            .map(|i| i / WIDTH)
+            .collect::<Vec<_>>();
+        let right_edge = edge_points(right, |i| i % WIDTH == 0)
+            .iter()
+            .map(|i| i / WIDTH)
+            .collect::<Vec<_>>();
+        assert_eq!(left_edge, right_edge);
+        assert_eq!(left_edge, lod_samples(LENGTH, 2));
+
+        // Other edges of the left chunk keep full details.
+        assert_eq!(edge_points(left, |i| i % WIDTH == 0).len() as u32, LENGTH);
+    }
+
+    #[test]
+    fn test_select_lods() {
+        let node = TerrainBuilder::new(BaseBuilder::new())
+            .with_width(64.0)
+            .with_length(16.0)
+            .with_width_chunks(4)
+            .with_length_chunks(1)
+            .with_height_map_resolution(1.0)
+            .with_lod_distance(8.0)
+            .build_node();
+        let mut terrain: Terrain = match node {
+            crate::scene::node::Node::Terrain(terrain) => terrain,
+            _ => unreachable!(),
+        };
+        terrain.update();
+
+        let chunk = &terrain.chunks_ref()[0];
+        assert_eq!(chunk.width_point_count(), 16);
+        assert_eq!(chunk.max_lod(), 3);
+
+        let lods = terrain.select_lods(Vector3::new(-1.0, 0.0, 8.0));
+        assert_eq!(
+            lods,
+            vec![
+                ChunkLod {
+                    level: 0,
+                    edges: [0, 2, 0, 0]
+                },
+                ChunkLod {
+                    level: 2,
+                    edges: [2, 3, 2, 2]
+                },
+                ChunkLod {
+                    level: 3,
+                    edges: [3; 4]
+                },
+                ChunkLod {
+                    level: 3,
+                    edges: [3; 4]
+                },
+            ]
+        );
+
+        // Full details share data with the chunk, other levels have their own smaller data.
+        assert!(Arc::ptr_eq(
+            &chunk.lod_data(ChunkLod::default()),
+            &chunk.data()
+        ));
+        let lod_data = chunk.lod_data(lods[0]);
+        assert!(Arc::ptr_eq(&lod_data, &chunk.lod_data(lods[0])));
+        assert!(
+            lod_data.read().unwrap().geometry_buffer.len()
+                < chunk.data().read().unwrap().geometry_buffer.len()
+        );
+        let chunk = &terrain.chunks_ref()[2];
+        assert!(
+            chunk
+                .lod_data(lods[2])
+                .read()
+                .unwrap()
+                .vertex_buffer
+                .vertex_count()
+                < chunk.data().read().unwrap().vertex_buffer.vertex_count()
+        );
+
+        terrain.set_lod_distance(0.0);
+        assert!(terrain
+            .select_lods(Vector3::new(-1.0, 0.0, 8.0))
+            .iter()
+            .all(|lod| *lod == ChunkLod::default()));
+    }
+
+    // 2x2 chunks, 16x16 points each, grid has 31x31 points with a step of 16/15.
+    fn make_terrain() -> Terrain {
+        make_terrain_with_layers(0)
+    }
+
+    // Same as `make_terrain`, but with given amount of layers with 32x32 masks.
+    pub(super) fn make_terrain_with_layers(layer_count: usize) -> Terrain {
+        let layers = (0..layer_count)
+            .map(|_| LayerDefinition {
+                material: Arc::new(Mutex::new(Material::standard_terrain())),
+                mask_property_name: "maskTexture".to_string(),
+            })
+            .collect();
+        match TerrainBuilder::new(BaseBuilder::new())
+            .with_width(32.0)
+            .with_length(32.0)
+            .with_width_chunks(2)
+            .with_length_chunks(2)
+            .with_height_map_resolution(1.0)
+            .with_mask_resolution(1.0)
+            .with_layers(layers)
+            .build_node()
+        {
+            crate::scene::node::Node::Terrain(terrain) => terrain,
+            _ => unreachable!(),
+        }
+    }
+
+    // Returns heights of a point of the whole grid in every chunk that contains it.
+    fn heights_at(terrain: &Terrain, x: usize, z: usize) -> Vec<f32> {
+        let mut heights = Vec::new();
+        for (chunk_index, chunk) in terrain.chunks_ref().iter().enumerate() {
+            let (cx, cz) = ((chunk_index % 2) * 15, (chunk_index / 2) * 15);
+            if (cx..=cx + 15).contains(&x) && (cz..=cz + 15).contains(&z) {
+                heights.push(chunk.heightmap()[(z - cz) * 16 + x - cx]);
+            }
+        }
+        heights
+    }
+
+    fn height_at(terrain: &Terrain, x: usize, z: usize) -> f32 {
+        heights_at(terrain, x, z)[0]
+    }
+
+    fn total_height(terrain: &Terrain) -> f32 {
+        (0..31)
+            .flat_map(|z| (0..31).map(move |x| (x, z)))
+            .map(|(x, z)| height_at(terrain, x, z))
+            .sum()
+    }
+
+    fn brush(x: f32, z: f32, shape: BrushShape, mode: BrushMode) -> Brush {
+        Brush::new(Vector3::new(x, 0.0, z), shape, mode)
+    }
+
+    fn make_spike(terrain: &mut Terrain) {
+        terrain.draw(&brush(
+            16.0,
+            16.0,
+            BrushShape::Rectangle {
+                width: 1.0,
+                length: 1.0,
+            },
+            BrushMode::SetHeight { height: 5.0 },
+        ));
+        assert_eq!(height_at(terrain, 15, 15), 5.0);
+    }
+
+    const WHOLE_TERRAIN: BrushShape = BrushShape::Rectangle {
+        width: 32.0,
+        length: 32.0,
+    };
+
+    #[test]
+    fn test_brush_delta() {
+        let mut terrain = make_terrain();
+
+        let delta = terrain.draw(&brush(
+            16.0,
+            16.0,
+            BrushShape::Rectangle {
+                width: 4.0,
+                length: 4.0,
+            },
+            BrushMode::SetHeight { height: 2.0 },
+        ));
+        assert!(!delta.is_empty());
+
+        // Point on the corner of four chunks is changed in each of them.
+        assert_eq!(heights_at(&terrain, 15, 15), vec![2.0; 4]);
+        assert_eq!(heights_at(&terrain, 15, 14), vec![2.0; 2]);
+        assert_eq!(height_at(&terrain, 16, 16), 2.0);
+        assert_eq!(height_at(&terrain, 17, 17), 0.0);
+        assert!(terrain.chunks_ref().iter().all(|c| c.dirty.get()));
+
+        delta.revert(&mut terrain);
+        assert_eq!(total_height(&terrain), 0.0);
+        assert!(terrain
+            .chunks_ref()
+            .iter()
+            .all(|c| c.heightmap().iter().all(|h| *h == 0.0)));
+
+        delta.apply(&mut terrain);
+        assert_eq!(heights_at(&terrain, 15, 15), vec![2.0; 4]);
+
+        // Brush outside of the terrain changes nothing.
+        assert!(terrain
+            .draw(&brush(
+                -10.0,
+                -10.0,
+                BrushShape::Circle { radius: 2.0 },
+                BrushMode::ModifyHeightMap { amount: 1.0 },
+            ))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_brush_falloff() {
+        let mut terrain = make_terrain();
+
+        let circle = brush(
+            16.0,
+            16.0,
+            BrushShape::Circle { radius: 8.0 },
+            BrushMode::ModifyHeightMap { amount: 1.0 },
+        );
+        terrain.draw(&circle);
+        assert_eq!(height_at(&terrain, 15, 15), 1.0);
+        // Default falloff of circle is 1 - d^2, d = 3.2 / 8.0
+        assert!((height_at(&terrain, 18, 15) - 0.84).abs() < 1.0e-5);
+
+        let circle = circle.with_falloff(Curve::from(vec![
+            CurveKey::new(0.0, 1.0, CurveKeyKind::Linear),
+            CurveKey::new(1.0, 0.0, CurveKeyKind::Linear),
+        ]));
+        terrain.draw(&circle);
+        assert_eq!(height_at(&terrain, 15, 15), 2.0);
+        assert!((height_at(&terrain, 18, 15) - 1.44).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn test_brush_smooth_and_flatten() {
+        let mut terrain = make_terrain();
+        make_spike(&mut terrain);
+
+        let total = total_height(&terrain);
+        terrain.draw(&brush(
+            16.0,
+            16.0,
+            WHOLE_TERRAIN,
+            BrushMode::Smooth { amount: 1.0 },
+        ));
+        assert_eq!(height_at(&terrain, 15, 15), 1.0);
+        assert_eq!(height_at(&terrain, 16, 15), 1.0);
+        assert!((total_height(&terrain) - total).abs() < 1.0e-4);
+
+        terrain.draw(&brush(
+            16.0,
+            16.0,
+            WHOLE_TERRAIN,
+            BrushMode::Flatten {
+                height: 3.0,
+                amount: 0.5,
+            },
+        ));
+        assert_eq!(height_at(&terrain, 15, 15), 2.0);
+        assert_eq!(height_at(&terrain, 0, 0), 1.5);
+    }
+
+    #[test]
+    fn test_brush_noise() {
+        let noise = |seed| {
+            let mut terrain = make_terrain();
+            terrain.draw(&brush(
+                16.0,
+                16.0,
+                WHOLE_TERRAIN,
+                BrushMode::Noise {
+                    amount: 2.0,
+                    frequency: 0.3,
+                    seed,
+                },
+            ));
+            terrain.chunks_ref()[0].heightmap().to_vec()
+        };
+
+        let heights = noise(1);
+        assert!(heights.iter().all(|h| h.abs() <= 2.0));
+        assert!(heights.iter().any(|h| *h != heights[0]));
+        assert_eq!(heights, noise(1));
+        assert_ne!(heights, noise(2));
+    }
+
+    #[test]
+    fn test_brush_erosion() {
+        let mut terrain = make_terrain();
+        make_spike(&mut terrain);
+
+        terrain.draw(&brush(
+            16.0,
+            16.0,
+            WHOLE_TERRAIN,
+            BrushMode::ThermalErosion {
+                iterations: 50,
+                talus: 1.0,
+                amount: 0.5,
+            },
+        ));
+        // Material slides down until slopes are stable, but the total amount is kept.
+        let peak = height_at(&terrain, 15, 15);
+        assert!(peak < 5.0);
+        assert!(peak - height_at(&terrain, 16, 15) <= 16.0 / 15.0 + 1.0e-3);
+        assert!((total_height(&terrain) - 5.0).abs() < 1.0e-3);
+
+        let mut terrain = make_terrain();
+        make_spike(&mut terrain);
+        terrain.draw(&brush(
+            16.0,
+            16.0,
+            WHOLE_TERRAIN,
+            BrushMode::HydraulicErosion {
+                iterations: 20,
+                rain: 0.01,
+                solubility: 0.1,
+                evaporation: 0.5,
+            },
+        ));
+        assert!(height_at(&terrain, 15, 15) < 5.0);
+        assert!(height_at(&terrain, 16, 15) > 0.0);
+        assert!((total_height(&terrain) - 5.0).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn test_height_map_import_export() {
+        let source = HeightMapImage::new(3, 2, vec![0, 65535, 0, 65535, 0, 65535]).unwrap();
+        assert_eq!(source.sample(0.25, 0.0), 0.5);
+        assert_eq!(source.sample(1.0, 1.0), 1.0);
+
+        let mut terrain = make_terrain();
+        terrain.import_height_map(&source, 10.0, -2.0);
+        assert_eq!(heights_at(&terrain, 0, 0), vec![-2.0]);
+        assert_eq!(heights_at(&terrain, 15, 0), vec![8.0; 2]);
+        assert_eq!(heights_at(&terrain, 30, 30), vec![8.0]);
+        assert_eq!(height_at(&terrain, 15, 30), -2.0);
+        assert!(terrain.chunks_ref().iter().all(|c| c.dirty.get()));
+
+        let exported = terrain.export_height_map(10.0, -2.0).unwrap();
+        assert_eq!((exported.width(), exported.length()), (31, 31));
+        assert_eq!(exported.pixels()[0], 0);
+        assert_eq!(exported.pixels()[15], 65535);
+        assert_eq!(exported.pixels()[31 * 30 + 30], 65535);
+
+        // Export and import again gives the same heights.
+        let mut other = make_terrain();
+        other.import_height_map(&exported, 10.0, -2.0);
+        for (a, b) in terrain.chunks_ref().iter().zip(other.chunks_ref()) {
+            for (a, b) in a.heightmap().iter().zip(b.heightmap()) {
+                assert!((a - b).abs() < 1.0e-3);
+            }
+        }
+
+        // Builder imports height map too.
+        let built = match TerrainBuilder::new(BaseBuilder::new())
+            .with_width(32.0)
+            .with_length(32.0)
+            .with_height_map_resolution(1.0)
+            .with_height_map(source, 10.0, -2.0)
+            .build_node()
+        {
+            crate::scene::node::Node::Terrain(terrain) => terrain,
+            _ => unreachable!(),
+        };
+        assert_eq!(built.export_height_map(10.0, -2.0).unwrap(), exported);
+
+        // Empty terrain has nothing to export.
+        assert!(matches!(
+            Terrain::default().export_height_map(10.0, -2.0),
+            Err(TerrainError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_height_map_files() {
+        let height_map = HeightMapImage::new(2, 2, vec![0, 1000, 40000, 65535]).unwrap();
+
+        let raw = height_map.to_raw();
+        assert_eq!(raw, vec![0, 0, 232, 3, 64, 156, 255, 255]);
+        assert_eq!(HeightMapImage::from_raw(&raw, 2, 2).unwrap(), height_map);
+        assert!(matches!(
+            HeightMapImage::from_raw(&raw, 3, 2),
+            Err(TerrainError::InvalidRawSize(8))
+        ));
+
+        for extension in ["png", "r16"] {
+            let path = temp_path("height_map", extension);
+            height_map.save(&path).unwrap();
+            assert_eq!(HeightMapImage::from_file(&path).unwrap(), height_map);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_layer_mask_export() {
+        let terrain = match TerrainBuilder::new(BaseBuilder::new())
+            .with_width(4.0)
+            .with_length(2.0)
+            .with_mask_resolution(2.0)
+            .with_layers(vec![LayerDefinition {
+                material: Arc::new(Mutex::new(Material::standard_terrain())),
+                mask_property_name: "maskTexture".to_string(),
+            }])
+            .build_node()
+        {
+            crate::scene::node::Node::Terrain(terrain) => terrain,
+            _ => unreachable!(),
+        };
+
+        let path = temp_path("layer_mask", "png");
+        terrain.export_layer_mask(0, &path).unwrap();
+        let image = image::open(&path).unwrap().into_luma8();
+        std::fs::remove_file(&path).unwrap();
+        // 2x2 chunks with 4x2 pixels masks that share edges.
+        assert_eq!(image.dimensions(), (7, 3));
+        assert!(image.pixels().all(|p| p.0[0] == 255));
+
+        assert!(matches!(
+            terrain.export_layer_mask(1, &path),
+            Err(TerrainError::InvalidLayer(1))
+        ));
+    }
+
+    // Unique path in temp dir, tests are running in parallel in multiple processes.
+    fn temp_path(name: &str, extension: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "rg3d_{}_test_{}.{}",
+            name,
+            std::process::id(),
+            extension
+        ))
+    }
+
+    fn hits(terrain: &Terrain, x: f32, z: f32) -> usize {
+        let mut results = ArrayVec::<TerrainRayCastResult, 8>::new();
+        terrain.raycast(
+            Ray::from_two_points(Vector3::new(x, 10.0, z), Vector3::new(x, -10.0, z)),
+            &mut results,
+            false,
+        );
+        results.len()
+    }
+
+    #[test]
+    fn test_holes() {
+        let mut terrain = make_terrain();
+        let mut full = make_terrain();
+        full.update();
+        let lod = ChunkLod {
+            level: 1,
+            edges: [1; 4],
+        };
+
+        // Cut one cell in each chunk around the shared corner.
+        let mut holes = brush(
+            16.0,
+            16.0,
+            BrushShape::Rectangle {
+                width: 2.0,
+                length: 2.0,
+            },
+            BrushMode::DrawHoles { erase: false },
+        );
+        assert!(terrain.draw(&holes).is_empty());
+        let chunks = terrain.chunks_ref();
+        assert!(chunks[0].is_hole(14, 14));
+        assert!(chunks[1].is_hole(0, 14));
+        assert!(chunks[2].is_hole(14, 0));
+        assert!(chunks[3].is_hole(0, 0));
+        assert!(!chunks[0].is_hole(13, 14));
+        assert!(!chunks[3].is_hole(1, 0));
+
+        // Holes are not rendered at any level of detail.
+        terrain.update();
+        let triangle_count = |terrain: &Terrain, lod| {
+            terrain.chunks_ref()[0]
+                .lod_data(lod)
+                .read()
+                .unwrap()
+                .geometry_buffer
+                .len()
+        };
+        assert_eq!(
+            triangle_count(&terrain, ChunkLod::default()),
+            15 * 15 * 2 - 2
+        );
+        assert!(triangle_count(&terrain, lod) < triangle_count(&full, lod));
+
+        assert_eq!(hits(&terrain, 15.5, 15.5), 0);
+        assert_eq!(hits(&terrain, 16.5, 16.5), 0);
+        assert_ne!(hits(&terrain, 5.0, 5.0), 0);
+
+        // Holes are cut in height field too.
+        let shape = Physics::make_heightfield(&terrain);
+        let heightfield = shape.as_heightfield().unwrap();
+        assert_eq!((heightfield.nrows(), heightfield.ncols()), (30, 30));
+        assert_eq!(
+            heightfield.cell_status(14, 15),
+            HeightFieldCellStatus::CELL_REMOVED
+        );
+        assert_eq!(
+            heightfield.cell_status(15, 14),
+            HeightFieldCellStatus::CELL_REMOVED
+        );
+        assert!(heightfield.cell_status(13, 14).is_empty());
+
+        holes.mode = BrushMode::DrawHoles { erase: true };
+        terrain.draw(&holes);
+        assert!(terrain.chunks_ref().iter().all(|c| !c.has_holes()));
+        terrain.update();
+        assert_eq!(triangle_count(&terrain, ChunkLod::default()), 15 * 15 * 2);
+        assert_ne!(hits(&terrain, 15.5, 15.5), 0);
+
+        // Cells out of a chunk are ignored.
+        let chunk = &mut terrain.chunks_mut()[0];
+        assert!(!chunk.set_hole(15, 0, true));
+        assert!(!chunk.set_hole(0, 100, true));
+        assert!(!chunk.has_holes());
+    }
+
+    #[test]
+    fn test_layer_weights() {
+        let layer = || LayerDefinition {
+            material: Arc::new(Mutex::new(Material::standard_terrain())),
+            mask_property_name: "maskTexture".to_string(),
+        };
+        let mut terrain = match TerrainBuilder::new(BaseBuilder::new())
+            .with_width(4.0)
+            .with_length(4.0)
+            .with_mask_resolution(4.0)
+            .with_layers(vec![layer(), layer()])
+            .build_node()
+        {
+            crate::scene::node::Node::Terrain(terrain) => terrain,
+            _ => unreachable!(),
+        };
+
+        let position = Vector3::new(3.0, 0.0, 1.0);
+        assert_eq!(terrain.layer_weights(position), Some(vec![1.0, 0.0]));
+        assert_eq!(terrain.dominant_layer(position), Some(0));
+        assert_eq!(terrain.layer_weights(Vector3::new(5.0, 0.0, 1.0)), None);
+
+        terrain.draw(&brush(
+            3.0,
+            1.0,
+            BrushShape::Rectangle {
+                width: 1.0,
+                length: 1.0,
+            },
+            BrushMode::DrawOnMask {
+                layer: 1,
+                alpha: 1.0,
+            },
+        ));
+        // Second layer is drawn on top of the first one and hides it completely.
+        assert_eq!(terrain.layer_weights(position), Some(vec![0.0, 1.0]));
+        assert_eq!(terrain.dominant_layer(position), Some(1));
+        assert_eq!(
+            terrain.layer_weights(Vector3::new(1.0, 0.0, 3.0)),
+            Some(vec![1.0, 0.0])
+        );
+    }
+}
@@ -3,6 +3,9 @@
 //! A* is one of fastest graph search algorithms, it is used to construct shortest
 //! possible path from vertex to vertex. In vast majority of games it is used in pair
 //! with navigation meshes (navmesh). Check navmesh module docs for more info.
+//!
+//! Besides path finder for 3D points, there is generic A* search over abstract graphs that
+//! could be used for other kinds of planning, see [`SearchGraph`] and [`find_graph_path`].
 
 #![warn(missing_docs)]
 
@@ -10,47 +13,28 @@ use crate::core::algebra::Vector3;
 use crate::core::math::{self, PositionProvider};
 use crate::core::visitor::Visit;
 use rg3d_core::visitor::{VisitResult, Visitor};
-
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-enum PathVertexState {
-    NonVisited,
-    Open,
-    Closed,
-}
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    convert::Infallible,
+    hash::Hash,
+};
 
 /// Graph vertex that contains position in world and list of indices of neighbour
 /// vertices.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct PathVertex {
     /// Position in world.
     pub position: Vector3<f32>,
-    state: PathVertexState,
-    g_score: f32,
-    f_score: f32,
-    parent: Option<usize>,
     neighbours: Vec<u32>,
 }
 
-impl Default for PathVertex {
-    fn default() -> Self {
-        Self {
-            position: Default::default(),
-            parent: None,
-            g_score: f32::MAX,
-            f_score: f32::MAX,
-            state: PathVertexState::NonVisited,
-            neighbours: Default::default(),
-        }
-    }
-}
-
 impl Visit for PathVertex {
     fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
         visitor.enter_region(name)?;
 
         self.position.visit("Position", visitor)?;
         self.neighbours.visit("Neighbours", visitor)?;
-        // Rest of fields are runtime state and valid only while in a build method of pathfinder.
 
         visitor.leave_region()
     }
@@ -61,10 +45,6 @@ impl PathVertex {
     pub fn new(position: Vector3<f32>) -> Self {
         Self {
             position,
-            parent: None,
-            g_score: f32::MAX,
-            f_score: f32::MAX,
-            state: PathVertexState::NonVisited,
             neighbours: Default::default(),
         }
     }
@@ -73,13 +53,6 @@ impl PathVertex {
     pub fn neighbours(&self) -> &[u32] {
         &self.neighbours
     }
-
-    fn clear(&mut self) {
-        self.g_score = f32::MAX;
-        self.f_score = f32::MAX;
-        self.state = PathVertexState::NonVisited;
-        self.parent = None;
-    }
 }
 
 /// See module docs.
@@ -116,6 +89,25 @@ fn heuristic(a: Vector3<f32>, b: Vector3<f32>) -> f32 {
     (a - b).norm_squared()
 }
 
+/// Defines costs of traversal between vertices for [`PathFinder::build_with_costs`].
+pub trait PathCostProvider {
+    /// Returns cost of moving by a link from vertex `from` to its neighbour vertex `to`, or `None`
+    /// if the link can't be used.
+    fn link_cost(
+        &self,
+        from: usize,
+        to: usize,
+        from_position: &Vector3<f32>,
+        to_position: &Vector3<f32>,
+    ) -> Option<f32>;
+
+    /// Returns a factor for straight-line distance heuristic. It must not be greater than the
+    /// smallest cost per unit of distance, otherwise found paths may be not the shortest ones.
+    fn heuristic_scale(&self) -> f32 {
+        1.0
+    }
+}
+
 impl Default for PathFinder {
     fn default() -> Self {
         Self::new()
@@ -196,6 +188,20 @@ impl PathFinder {
         &self.vertices
     }
 
+    /// Adds new vertex and returns its index.
+    pub fn add_vertex(&mut self, vertex: PathVertex) -> usize {
+        self.vertices.push(vertex);
+        self.vertices.len() - 1
+    }
+
+    /// Removes every vertex starting from given index together with all links to them.
+    pub fn truncate(&mut self, count: usize) {
+        self.vertices.truncate(count);
+        for vertex in self.vertices.iter_mut() {
+            vertex.neighbours.retain(|n| (*n as usize) < count);
+        }
+    }
+
     /// Tries to build path from begin point to end point. Returns path kind:
     /// - Full: there are direct path from begin to end.
     /// - Partial: there are not direct path from begin to end, but it is closest.
@@ -213,102 +219,109 @@ impl PathFinder {
         to: usize,
         path: &mut Vec<Vector3<f32>>,
     ) -> Result<PathKind, PathError> {
+        self.search(
+            from,
+            to,
+            path,
+            |_, _, a, b| Some((a - b).norm_squared()),
+            heuristic,
+        )
+    }
+
+    /// Same as [`Self::build`], but uses distances between vertices multiplied by costs from
+    /// given provider. Links for which the provider returns `None` are never used.
+    pub fn build_with_costs(
+        &mut self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<Vector3<f32>>,
+        costs: &dyn PathCostProvider,
+    ) -> Result<PathKind, PathError> {
+        let scale = costs.heuristic_scale();
+        self.search(
+            from,
+            to,
+            path,
+            |from, to, a, b| costs.link_cost(from, to, a, b),
+            |a, b| (a - b).norm() * scale,
+        )
+    }
+
+    fn search<C, H>(
+        &mut self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<Vector3<f32>>,
+        link_cost: C,
+        heuristic: H,
+    ) -> Result<PathKind, PathError>
+    where
+        C: Fn(usize, usize, &Vector3<f32>, &Vector3<f32>) -> Option<f32>,
+        H: Fn(Vector3<f32>, Vector3<f32>) -> f32,
+    {
         if self.vertices.is_empty() {
             return Ok(PathKind::Empty);
         }
 
         path.clear();
 
-        for vertex in self.vertices.iter_mut() {
-            vertex.clear();
-        }
-
-        let end_pos = self
-            .vertices
+        let vertices = &self.vertices;
+        let end_pos = vertices
             .get(to)
             .ok_or(PathError::InvalidIndex(to))?
             .position;
+        if from >= vertices.len() {
+            return Err(PathError::InvalidIndex(from));
+        }
 
-        // Put start vertex in open set.
-        let start = self
-            .vertices
-            .get_mut(from)
-            .ok_or(PathError::InvalidIndex(from))?;
-        start.state = PathVertexState::Open;
-        start.g_score = 0.0;
-        start.f_score = heuristic(start.position, end_pos);
-
-        let mut open_set_size = 1;
-        while open_set_size > 0 {
-            let mut current_index = 0;
-            let mut lowest_f_score = f32::MAX;
-            for (i, vertex) in self.vertices.iter().enumerate() {
-                if vertex.state == PathVertexState::Open && vertex.f_score < lowest_f_score {
-                    current_index = i;
-                    lowest_f_score = vertex.f_score;
-                }
-            }
-
-            if current_index == to {
-                self.reconstruct_path(current_index, path);
-                return Ok(PathKind::Full);
-            }
-
-            open_set_size -= 1;
-
-            // Take second mutable reference to vertices array, we'll enforce borrowing rules
-            // at runtime. It will *never* give two mutable references to same path vertex.
-            let unsafe_vertices: &mut Vec<PathVertex> =
-                unsafe { &mut *(&mut self.vertices as *mut _) };
-
-            let current_vertex = self
-                .vertices
-                .get_mut(current_index)
-                .ok_or(PathError::InvalidIndex(current_index))?;
-
-            current_vertex.state = PathVertexState::Closed;
-
-            for neighbour_index in current_vertex.neighbours.iter() {
-                // Make sure that borrowing rules are not violated.
-                if *neighbour_index as usize == current_index {
-                    return Err(PathError::CyclicReferenceFound(current_index));
-                }
-
-                // Safely get mutable reference to neighbour
-                let neighbour = unsafe_vertices
-                    .get_mut(*neighbour_index as usize)
-                    .ok_or(PathError::InvalidIndex(*neighbour_index as usize))?;
-
-                let g_score = current_vertex.g_score
-                    + (current_vertex.position - neighbour.position).norm_squared();
-                if g_score < neighbour.g_score {
-                    neighbour.parent = Some(current_index);
-                    neighbour.g_score = g_score;
-                    neighbour.f_score = g_score + heuristic(neighbour.position, end_pos);
-
-                    if neighbour.state != PathVertexState::Open {
-                        neighbour.state = PathVertexState::Open;
-                        open_set_size += 1;
+        let vertex_heuristic = |index: &usize| heuristic(vertices[*index].position, end_pos);
+        let (records, goal) = search_graph(
+            from,
+            usize::MAX,
+            vertex_heuristic,
+            |index| *index == to,
+            |&current, edges| {
+                let current_vertex = &vertices[current];
+                for &neighbour_index in current_vertex.neighbours.iter() {
+                    let neighbour_index = neighbour_index as usize;
+                    if neighbour_index == current {
+                        return Err(PathError::CyclicReferenceFound(current));
+                    }
+                    let neighbour = vertices
+                        .get(neighbour_index)
+                        .ok_or(PathError::InvalidIndex(neighbour_index))?;
+                    if let Some(cost) = link_cost(
+                        current,
+                        neighbour_index,
+                        &current_vertex.position,
+                        &neighbour.position,
+                    ) {
+                        edges.push(((), neighbour_index, cost));
                     }
                 }
-            }
+                Ok(())
+            },
+        )?;
+
+        if let Some(goal) = goal {
+            self.reconstruct_path(&records, goal, path);
+            return Ok(PathKind::Full);
         }
 
         // No direct path found, then there is probably partial path exists.
         // Look for vertex with least f_score and use it as starting point to
         // reconstruct partial path.
-        let mut closest_index = 0;
-        for (i, vertex) in self.vertices.iter().enumerate() {
-            let closest_vertex = self
-                .vertices
-                .get(closest_index)
-                .ok_or(PathError::InvalidIndex(closest_index))?;
-            if vertex.f_score < closest_vertex.f_score {
-                closest_index = i;
+        let mut closest = 0;
+        let mut closest_f_score = f32::MAX;
+        for (i, record) in records.iter().enumerate() {
+            let f_score = record.g_score + vertex_heuristic(&record.node);
+            if f_score < closest_f_score {
+                closest = i;
+                closest_f_score = f_score;
             }
         }
 
-        self.reconstruct_path(closest_index, path);
+        self.reconstruct_path(&records, closest, path);
 
         if path.is_empty() {
             Ok(PathKind::Empty)
@@ -317,26 +330,288 @@ impl PathFinder {
         }
     }
 
-    fn reconstruct_path(&self, mut current: usize, path: &mut Vec<Vector3<f32>>) {
-        while let Some(vertex) = self.vertices.get(current) {
-            path.push(vertex.position);
-            if let Some(parent) = vertex.parent {
-                current = parent;
-            } else {
-                break;
+    // Path is written in reverse order, from given record to the start vertex.
+    fn reconstruct_path(
+        &self,
+        records: &[GraphRecord<usize, ()>],
+        mut current: usize,
+        path: &mut Vec<Vector3<f32>>,
+    ) {
+        loop {
+            let record = &records[current];
+            path.push(self.vertices[record.node].position);
+            match record.parent {
+                Some((parent, _)) => current = parent,
+                None => break,
             }
         }
     }
 }
 
+/// Abstract graph for generic A* search, see [`find_graph_path`]. Nodes of the graph are
+/// generated on demand, so the graph could be huge or even infinite - for example a set of
+/// all possible world states.
+pub trait SearchGraph {
+    /// Node of the graph.
+    type Node: Clone + Eq + Hash;
+    /// Edge of the graph, for example an action that changes one state to another.
+    type Edge: Clone;
+
+    /// Collects every edge going out of given node. The edge, the node it leads to and the
+    /// non-negative cost of the edge must be pushed to the `edges` array.
+    fn edges(&self, node: &Self::Node, edges: &mut Vec<(Self::Edge, Self::Node, f32)>);
+
+    /// Estimates cost from given node to the goal. It must not be greater than the real cost,
+    /// otherwise found paths may be not the cheapest ones.
+    fn heuristic(&self, node: &Self::Node) -> f32;
+
+    /// Returns true if given node satisfies the goal of the search.
+    fn is_goal(&self, node: &Self::Node) -> bool;
+}
+
+/// Result of generic A* search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphPath<N, E> {
+    /// Nodes of the path, including start and goal nodes.
+    pub nodes: Vec<N>,
+    /// Edges of the path, `edges[i]` leads from `nodes[i]` to `nodes[i + 1]`.
+    pub edges: Vec<E>,
+    /// Total cost of the path.
+    pub cost: f32,
+}
+
+struct GraphRecord<N, E> {
+    node: N,
+    parent: Option<(usize, E)>,
+    g_score: f32,
+    closed: bool,
+}
+
+struct OpenEntry {
+    f_score: f32,
+    g_score: f32,
+    index: usize,
+}
+
+impl PartialEq for OpenEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenEntry {}
+
+impl PartialOrd for OpenEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Binary heap is a max-heap, so the order is reversed to pop the lowest f-score first.
+        // Ties are resolved in favor of deeper nodes and then in order of discovery to make
+        // the search deterministic.
+        other
+            .f_score
+            .partial_cmp(&self.f_score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| {
+                self.g_score
+                    .partial_cmp(&other.g_score)
+                    .unwrap_or(Ordering::Equal)
+            })
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+/// Searches the cheapest path from given node to any goal node of the graph using A*
+/// algorithm. At most `max_expanded_nodes` nodes are expanded, it prevents endless search
+/// in huge graphs. Returns `None` if there is no path or the limit was reached.
+pub fn find_graph_path<G: SearchGraph>(
+    graph: &G,
+    start: G::Node,
+    max_expanded_nodes: usize,
+) -> Option<GraphPath<G::Node, G::Edge>> {
+    let (records, goal) = search_graph(
+        start,
+        max_expanded_nodes,
+        |node| graph.heuristic(node),
+        |node| graph.is_goal(node),
+        |node, edges| {
+            graph.edges(node, edges);
+            Ok::<_, Infallible>(())
+        },
+    )
+    .ok()?;
+    Some(reconstruct_graph_path(records, goal?))
+}
+
+// A* search shared by the path finder and generic graph search. Returns every discovered node
+// together with index of a goal node, if it was reached. Closed nodes are reopened if a cheaper
+// way to them is found, so inconsistent heuristics still give the cheapest paths.
+fn search_graph<N, E, H, G, Ed, Er>(
+    start: N,
+    max_expanded_nodes: usize,
+    heuristic: H,
+    is_goal: G,
+    mut edges_of: Ed,
+) -> Result<(Vec<GraphRecord<N, E>>, Option<usize>), Er>
+where
+    N: Clone + Eq + Hash,
+    H: Fn(&N) -> f32,
+    G: Fn(&N) -> bool,
+    Ed: FnMut(&N, &mut Vec<(E, N, f32)>) -> Result<(), Er>,
+{
+    let mut records = vec![GraphRecord {
+        node: start.clone(),
+        parent: None,
+        g_score: 0.0,
+        closed: false,
+    }];
+    let mut indices = HashMap::new();
+    let mut open_set = BinaryHeap::new();
+    open_set.push(OpenEntry {
+        f_score: heuristic(&start),
+        g_score: 0.0,
+        index: 0,
+    });
+    indices.insert(start, 0);
+
+    let mut edges = Vec::new();
+    let mut expanded_nodes = 0;
+    while let Some(entry) = open_set.pop() {
+        let current = &mut records[entry.index];
+        if current.closed || entry.g_score > current.g_score {
+            // Outdated entry.
+            continue;
+        }
+
+        if is_goal(&current.node) {
+            return Ok((records, Some(entry.index)));
+        }
+
+        if expanded_nodes >= max_expanded_nodes {
+            break;
+        }
+        expanded_nodes += 1;
+        current.closed = true;
+        let g_score = current.g_score;
+
+        edges.clear();
+        edges_of(&current.node, &mut edges)?;
+        for (edge, node, cost) in edges.drain(..) {
+            let neighbour_g_score = g_score + cost;
+            let index = match indices.get(&node) {
+                Some(&index) => {
+                    let neighbour = &mut records[index];
+                    if neighbour_g_score >= neighbour.g_score {
+                        continue;
+                    }
+                    neighbour.g_score = neighbour_g_score;
+                    neighbour.parent = Some((entry.index, edge));
+                    neighbour.closed = false;
+                    index
+                }
+                None => {
+                    let index = records.len();
+                    indices.insert(node.clone(), index);
+                    records.push(GraphRecord {
+                        node,
+                        parent: Some((entry.index, edge)),
+                        g_score: neighbour_g_score,
+                        closed: false,
+                    });
+                    index
+                }
+            };
+            open_set.push(OpenEntry {
+                f_score: neighbour_g_score + heuristic(&records[index].node),
+                g_score: neighbour_g_score,
+                index,
+            });
+        }
+    }
+
+    Ok((records, None))
+}
+
+fn reconstruct_graph_path<N: Clone, E: Clone>(
+    records: Vec<GraphRecord<N, E>>,
+    goal: usize,
+) -> GraphPath<N, E> {
+    let cost = records[goal].g_score;
+    let mut nodes = vec![records[goal].node.clone()];
+    let mut edges = Vec::new();
+    let mut current = goal;
+    while let Some((parent, edge)) = records[current].parent.clone() {
+        nodes.push(records[parent].node.clone());
+        edges.push(edge);
+        current = parent;
+    }
+    nodes.reverse();
+    edges.reverse();
+    GraphPath { nodes, edges, cost }
+}
+
 #[cfg(test)]
 mod test {
     use crate::rand::Rng;
     use crate::{
         core::{algebra::Vector3, rand},
-        utils::astar::{PathFinder, PathVertex},
+        utils::astar::{find_graph_path, PathFinder, PathVertex, SearchGraph},
     };
 
+    // 8x8 grid with a wall at x = 4 that has a single gap at y = 7.
+    struct GridGraph;
+
+    impl GridGraph {
+        fn is_blocked(&self, (x, y): (i32, i32)) -> bool {
+            !(0..8).contains(&x) || !(0..8).contains(&y) || (x == 4 && y != 7)
+        }
+    }
+
+    impl SearchGraph for GridGraph {
+        type Node = (i32, i32);
+        type Edge = char;
+
+        fn edges(&self, &(x, y): &(i32, i32), edges: &mut Vec<(char, (i32, i32), f32)>) {
+            for &(dx, dy, direction) in &[(1, 0, 'R'), (-1, 0, 'L'), (0, 1, 'U'), (0, -1, 'D')] {
+                let node = (x + dx, y + dy);
+                if !self.is_blocked(node) {
+                    edges.push((direction, node, 1.0));
+                }
+            }
+        }
+
+        fn heuristic(&self, &(x, y): &(i32, i32)) -> f32 {
+            ((7 - x).abs() + y.abs()) as f32
+        }
+
+        fn is_goal(&self, node: &(i32, i32)) -> bool {
+            *node == (7, 0)
+        }
+    }
+
+    #[test]
+    fn astar_generic_graph() {
+        let path = find_graph_path(&GridGraph, (0, 0), 1000).unwrap();
+        assert_eq!(path.cost, 21.0);
+        assert_eq!(path.nodes.len(), 22);
+        assert_eq!(path.edges.len(), 21);
+        assert_eq!(*path.nodes.first().unwrap(), (0, 0));
+        assert_eq!(*path.nodes.last().unwrap(), (7, 0));
+        assert!(path.nodes.contains(&(4, 7)));
+
+        // Start is the goal.
+        let path = find_graph_path(&GridGraph, (7, 0), 0).unwrap();
+        assert_eq!(path.nodes, vec![(7, 0)]);
+        assert!(path.edges.is_empty());
+
+        // Not enough expansions to reach the goal behind the wall.
+        assert!(find_graph_path(&GridGraph, (0, 0), 10).is_none());
+    }
+
     #[test]
     fn astar_random_points() {
         let mut pathfinder = PathFinder::new();
@@ -0,0 +1,239 @@
+//! Blackboard is a shared storage of named values of a behavior tree. Leaf nodes could write
+//! values to the blackboard and conditions of decorator nodes could observe them. Values are
+//! accessed by typed keys, so it is impossible to read a value of one type as a value of
+//! another type.
+
+use crate::core::{algebra::Vector3, visitor::prelude::*};
+use std::{cmp::Ordering, collections::HashMap, marker::PhantomData};
+
+/// A value that could be stored in a blackboard.
+#[derive(Clone, Debug, PartialEq, Visit)]
+pub enum BlackboardValue {
+    /// Boolean value.
+    Bool(bool),
+    /// Integer value.
+    Integer(i64),
+    /// Floating point value.
+    Number(f32),
+    /// Vector value, for example a position.
+    Vector3(Vector3<f32>),
+    /// String value.
+    String(String),
+}
+
+impl Default for BlackboardValue {
+    fn default() -> Self {
+        Self::Bool(false)
+    }
+}
+
+impl BlackboardValue {
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Integer(a), Self::Integer(b)) => a.partial_cmp(b),
+            (Self::Number(a), Self::Number(b)) => a.partial_cmp(b),
+            (Self::String(a), Self::String(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+/// A type that could be stored in a blackboard.
+pub trait BlackboardType: Sized {
+    /// Converts self into blackboard value.
+    fn into_value(self) -> BlackboardValue;
+
+    /// Tries to convert blackboard value to self, returns `None` if the value has other type.
+    fn from_value(value: &BlackboardValue) -> Option<Self>;
+}
+
+macro_rules! impl_blackboard_type {
+    ($type:ty, $variant:ident) => {
+        impl BlackboardType for $type {
+            fn into_value(self) -> BlackboardValue {
+                BlackboardValue::$variant(self)
+            }
+
+            fn from_value(value: &BlackboardValue) -> Option<Self> {
+                if let BlackboardValue::$variant(value) = value {
+                    Some(value.clone())
+                } else {
+                    None
+                }
+            }
+        }
+    };
+}
+
+impl_blackboard_type!(bool, Bool);
+impl_blackboard_type!(i64, Integer);
+impl_blackboard_type!(f32, Number);
+impl_blackboard_type!(Vector3<f32>, Vector3);
+impl_blackboard_type!(String, String);
+
+/// Typed name of a value in a blackboard.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct BlackboardKey<T> {
+    name: String,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Clone for BlackboardKey<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: BlackboardType> BlackboardKey<T> {
+    /// Creates new key with given name.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns name of the key.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// See module docs.
+#[derive(Clone, Debug, Default, PartialEq, Visit)]
+pub struct Blackboard {
+    values: HashMap<String, BlackboardValue>,
+}
+
+impl Blackboard {
+    /// Creates new empty blackboard.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets new value for given key.
+    pub fn set<T: BlackboardType>(&mut self, key: &BlackboardKey<T>, value: T) {
+        self.values.insert(key.name.clone(), value.into_value());
+    }
+
+    /// Returns value of given key, or `None` if there is no value or it has other type.
+    pub fn get<T: BlackboardType>(&self, key: &BlackboardKey<T>) -> Option<T> {
+        self.values.get(&key.name).and_then(T::from_value)
+    }
+
+    /// Removes value of given key and returns it.
+    pub fn remove<T: BlackboardType>(&mut self, key: &BlackboardKey<T>) -> Option<T> {
+        self.values
+            .remove(&key.name)
+            .and_then(|value| T::from_value(&value))
+    }
+
+    /// Returns untyped value by its name.
+    pub fn value(&self, name: &str) -> Option<&BlackboardValue> {
+        self.values.get(name)
+    }
+
+    /// Returns true if there is a value with given name.
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    /// Removes every value.
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+/// Defines how a blackboard value is checked by a condition.
+#[derive(Clone, Debug, PartialEq, Visit)]
+pub enum Comparison {
+    /// Value must exist.
+    IsSet,
+    /// Value must not exist.
+    IsNotSet,
+    /// Value must be equal to given one.
+    Equal(BlackboardValue),
+    /// Value must exist and must not be equal to given one.
+    NotEqual(BlackboardValue),
+    /// Value must be less than given one. Only integers, numbers and strings could be compared.
+    Less(BlackboardValue),
+    /// Value must be greater than given one. Only integers, numbers and strings could be
+    /// compared.
+    Greater(BlackboardValue),
+}
+
+impl Default for Comparison {
+    fn default() -> Self {
+        Self::IsSet
+    }
+}
+
+/// A condition on a blackboard value. Conditions are stored as plain data, so they could be
+/// saved and loaded as a part of a tree, but they are created using typed keys.
+#[derive(Clone, Debug, Default, PartialEq, Visit)]
+pub struct BlackboardCondition {
+    /// Name of the observed value.
+    pub key: String,
+    /// Check of the observed value.
+    pub comparison: Comparison,
+}
+
+impl BlackboardCondition {
+    fn new<T: BlackboardType>(key: &BlackboardKey<T>, comparison: Comparison) -> Self {
+        Self {
+            key: key.name.clone(),
+            comparison,
+        }
+    }
+
+    /// Creates a condition that is true when there is a value of given key.
+    pub fn is_set<T: BlackboardType>(key: &BlackboardKey<T>) -> Self {
+        Self::new(key, Comparison::IsSet)
+    }
+
+    /// Creates a condition that is true when there is no value of given key.
+    pub fn is_not_set<T: BlackboardType>(key: &BlackboardKey<T>) -> Self {
+        Self::new(key, Comparison::IsNotSet)
+    }
+
+    /// Creates a condition that is true when value of given key is equal to given one.
+    pub fn equal<T: BlackboardType>(key: &BlackboardKey<T>, value: T) -> Self {
+        Self::new(key, Comparison::Equal(value.into_value()))
+    }
+
+    /// Creates a condition that is true when value of given key is not equal to given one.
+    pub fn not_equal<T: BlackboardType>(key: &BlackboardKey<T>, value: T) -> Self {
+        Self::new(key, Comparison::NotEqual(value.into_value()))
+    }
+
+    /// Creates a condition that is true when value of given key is less than given one.
+    pub fn less<T: BlackboardType>(key: &BlackboardKey<T>, value: T) -> Self {
+        Self::new(key, Comparison::Less(value.into_value()))
+    }
+
+    /// Creates a condition that is true when value of given key is greater than given one.
+    pub fn greater<T: BlackboardType>(key: &BlackboardKey<T>, value: T) -> Self {
+        Self::new(key, Comparison::Greater(value.into_value()))
+    }
+
+    /// Checks the condition using given blackboard.
+    pub fn check(&self, blackboard: &Blackboard) -> bool {
+        let value = blackboard.value(&self.key);
+        match (&self.comparison, value) {
+            (Comparison::IsSet, value) => value.is_some(),
+            (Comparison::IsNotSet, value) => value.is_none(),
+            (Comparison::Equal(expected), Some(value)) => value == expected,
+            (Comparison::NotEqual(expected), Some(value)) => value != expected,
+            (Comparison::Less(expected), Some(value)) => {
+                value.compare(expected) == Some(Ordering::Less)
+            }
+            (Comparison::Greater(expected), Some(value)) => {
+                value.compare(expected) == Some(Ordering::Greater)
+            }
+            (_, None) => false,
+        }
+    }
+}
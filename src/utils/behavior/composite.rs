@@ -3,7 +3,8 @@
 //! until `Status::Failure` is returned from any descendant node. In other words `Sequence`
 //! implement AND logical function. `Selector` node will execute children until `Status::Success`
 //! is returned from any descendant node. In other worlds `Selector` implement OR logical
-//! function.
+//! function. `Parallel` node executes every child on each tick and combines their results
+//! using success and failure policies.
 
 use crate::{
     core::{pool::Handle, visitor::prelude::*},
@@ -21,6 +22,39 @@ pub enum CompositeNodeKind {
     /// is returned from any descendant node. In other worlds `Selector` implement OR logical
     /// function.
     Selector,
+    /// `Parallel` node will execute every child on each tick. It fails when failure policy
+    /// is satisfied, otherwise it succeeds when success policy is satisfied. If every child
+    /// is finished, but none of the policies is satisfied, the node fails.
+    Parallel {
+        /// Defines how many children must succeed.
+        success: ParallelPolicy,
+        /// Defines how many children must fail.
+        failure: ParallelPolicy,
+    },
+}
+
+/// Defines how many children of `Parallel` node must return specific status.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Visit)]
+pub enum ParallelPolicy {
+    /// At least one child.
+    RequireOne,
+    /// Every child.
+    RequireAll,
+}
+
+impl Default for ParallelPolicy {
+    fn default() -> Self {
+        Self::RequireAll
+    }
+}
+
+impl ParallelPolicy {
+    pub(in crate::utils::behavior) fn is_satisfied(self, count: usize, total: usize) -> bool {
+        match self {
+            ParallelPolicy::RequireOne => count > 0,
+            ParallelPolicy::RequireAll => count == total,
+        }
+    }
 }
 
 impl Default for CompositeNodeKind {
@@ -69,6 +103,18 @@ impl<B> CompositeNode<B> {
         }
     }
 
+    /// Creates new parallel composite node with given policies and a set of children nodes.
+    pub fn new_parallel(
+        success: ParallelPolicy,
+        failure: ParallelPolicy,
+        children: Vec<Handle<BehaviorNode<B>>>,
+    ) -> Self {
+        Self {
+            children,
+            kind: CompositeNodeKind::Parallel { success, failure },
+        }
+    }
+
     /// Adds self to the tree and return handle to self.
     pub fn add_to(self, tree: &mut BehaviorTree<B>) -> Handle<BehaviorNode<B>> {
         tree.add_node(BehaviorNode::Composite(self))
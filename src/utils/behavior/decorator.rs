@@ -0,0 +1,147 @@
+//! Decorator is a node with single child, it changes result of its child or decides whether
+//! the child should be executed at all. Decorators could invert result of the child, repeat
+//! the child few times, prevent the child from running too often or too long and check
+//! conditions on the blackboard of the tree.
+
+use crate::{
+    core::{pool::Handle, visitor::prelude::*},
+    utils::behavior::{blackboard::BlackboardCondition, BehaviorNode, BehaviorTree},
+};
+use std::cell::Cell;
+
+/// Defines when condition of a decorator is checked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Visit)]
+pub enum AbortMode {
+    /// Condition is checked only when decorated node starts, changes of observed value do not
+    /// interrupt running node.
+    Never,
+    /// Condition is checked on every tick, running node is aborted as soon as the condition
+    /// becomes false.
+    Immediate,
+}
+
+impl Default for AbortMode {
+    fn default() -> Self {
+        Self::Never
+    }
+}
+
+/// Defines exact behavior of the decorator node. Time is measured in seconds of the tree,
+/// see `BehaviorTree::update`.
+#[derive(Clone, Debug, PartialEq, Visit)]
+pub enum DecoratorKind {
+    /// Turns `Success` into `Failure` and vice versa.
+    Inverter,
+    /// Returns `Success` when the child is finished, regardless of its result.
+    Succeeder,
+    /// Runs the child again until it succeeds given amount of times, zero means forever.
+    /// Failure of the child stops repetition.
+    Repeater {
+        /// Amount of repetitions.
+        count: u32,
+    },
+    /// Fails without running the child for given amount of time after the child has finished.
+    Cooldown {
+        /// Duration of the cooldown.
+        duration: f32,
+    },
+    /// Aborts the child and fails if the child is running longer than given amount of time.
+    TimeLimit {
+        /// Maximum duration of the child execution.
+        duration: f32,
+    },
+    /// Runs the child only if the condition is true, fails otherwise.
+    Condition {
+        /// Condition on a blackboard value.
+        condition: BlackboardCondition,
+        /// Defines whether running child is aborted when the condition becomes false.
+        abort: AbortMode,
+    },
+}
+
+impl Default for DecoratorKind {
+    fn default() -> Self {
+        Self::Inverter
+    }
+}
+
+// Runtime state of decorator, it is not serialized.
+#[derive(Debug, Default, PartialEq)]
+pub(in crate::utils::behavior) struct DecoratorState {
+    pub counter: Cell<u32>,
+    pub time: Cell<Option<f32>>,
+    pub active: Cell<bool>,
+}
+
+/// See module docs.
+#[derive(Debug, PartialEq, Visit)]
+pub struct DecoratorNode<B> {
+    /// Decorated node.
+    pub child: Handle<BehaviorNode<B>>,
+    /// Current kind of the node.
+    pub kind: DecoratorKind,
+    #[visit(skip)]
+    pub(in crate::utils::behavior) state: DecoratorState,
+}
+
+impl<B> Default for DecoratorNode<B> {
+    fn default() -> Self {
+        Self {
+            child: Default::default(),
+            kind: Default::default(),
+            state: Default::default(),
+        }
+    }
+}
+
+impl<B> DecoratorNode<B> {
+    /// Creates new decorator node of given kind.
+    pub fn new(kind: DecoratorKind, child: Handle<BehaviorNode<B>>) -> Self {
+        Self {
+            child,
+            kind,
+            state: Default::default(),
+        }
+    }
+
+    /// Creates new decorator that inverts result of given node.
+    pub fn new_inverter(child: Handle<BehaviorNode<B>>) -> Self {
+        Self::new(DecoratorKind::Inverter, child)
+    }
+
+    /// Creates new decorator that always succeeds when given node is finished.
+    pub fn new_succeeder(child: Handle<BehaviorNode<B>>) -> Self {
+        Self::new(DecoratorKind::Succeeder, child)
+    }
+
+    /// Creates new decorator that repeats given node `count` times, zero means forever.
+    pub fn new_repeater(count: u32, child: Handle<BehaviorNode<B>>) -> Self {
+        Self::new(DecoratorKind::Repeater { count }, child)
+    }
+
+    /// Creates new decorator that prevents given node from running more often than once per
+    /// `duration` seconds.
+    pub fn new_cooldown(duration: f32, child: Handle<BehaviorNode<B>>) -> Self {
+        Self::new(DecoratorKind::Cooldown { duration }, child)
+    }
+
+    /// Creates new decorator that aborts given node if it is running longer than `duration`
+    /// seconds.
+    pub fn new_time_limit(duration: f32, child: Handle<BehaviorNode<B>>) -> Self {
+        Self::new(DecoratorKind::TimeLimit { duration }, child)
+    }
+
+    /// Creates new decorator that runs given node only if the condition is true.
+    pub fn new_condition(
+        condition: BlackboardCondition,
+        abort: AbortMode,
+        child: Handle<BehaviorNode<B>>,
+    ) -> Self {
+        Self::new(DecoratorKind::Condition { condition, abort }, child)
+    }
+
+    /// Adds self to the tree and return handle to self.
+    pub fn add_to(self, tree: &mut BehaviorTree<B>) -> Handle<BehaviorNode<B>> {
+        tree.add_node(BehaviorNode::Decorator(self))
+    }
+}
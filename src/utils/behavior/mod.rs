@@ -6,8 +6,16 @@
 //! games. The main concept is in its name. Tree is a set of connected nodes, where each node could
 //! have single parent and zero or more children nodes. Execution path of the tree is defined by the
 //! actions of the nodes. Behavior tree has a set of hard coded nodes as well as leaf nodes with
-//! user-defined logic. Hard coded nodes are: Sequence, Selector, Leaf. Leaf is special - it has
-//! custom method `tick` that can contain any logic you want.
+//! user-defined logic. Hard coded nodes are: Sequence, Selector, Parallel, Decorator, Leaf. Leaf
+//! is special - it has custom method `tick` that can contain any logic you want.
+//!
+//! The tree is evaluated from the root on every tick, so composite nodes always re-check their
+//! children from the first one. When a node that was running on previous tick is not running
+//! anymore because some other branch took over (or a decorator stopped it), the node is aborted:
+//! state of decorators is reset and `Behavior::abort` is called for leaves.
+//!
+//! Every tree has a blackboard - a storage of typed values shared between nodes of the tree.
+//! Leaves could write to the blackboard, decorators could check conditions on its values.
 //!
 //! For more info see:
 //! - [Wikipedia article](https://en.wikipedia.org/wiki/Behavior_tree_(artificial_intelligence,_robotics_and_control))
@@ -19,17 +27,25 @@ use crate::{
         visitor::prelude::*,
     },
     utils::behavior::{
+        blackboard::Blackboard,
         composite::{CompositeNode, CompositeNodeKind},
+        decorator::{AbortMode, DecoratorKind, DecoratorNode},
         leaf::LeafNode,
     },
 };
-use std::fmt::Debug;
-use std::ops::{Index, IndexMut};
+use std::{
+    cell::{Cell, Ref, RefCell, RefMut},
+    fmt::Debug,
+    ops::{Index, IndexMut},
+};
 
+pub mod blackboard;
 pub mod composite;
+pub mod decorator;
 pub mod leaf;
 
 /// Status of execution of behavior tree node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Status {
     /// Action was successful.
     Success,
@@ -48,6 +64,20 @@ pub trait Behavior<'a>: Visit + Default + PartialEq + Debug {
     /// the current execution path of the behavior tree it belongs
     /// to.
     fn tick(&mut self, context: &mut Self::Context) -> Status;
+
+    /// Same as `tick`, but also gives access to the blackboard of the tree. Default
+    /// implementation just calls `tick`.
+    fn tick_with_blackboard(
+        &mut self,
+        context: &mut Self::Context,
+        _blackboard: &mut Blackboard,
+    ) -> Status {
+        self.tick(context)
+    }
+
+    /// A function that will be called when the behavior was running on previous tick, but it
+    /// was interrupted by other nodes of the tree. Default implementation does nothing.
+    fn abort(&mut self, _context: &mut Self::Context) {}
 }
 
 /// Root node of the tree.
@@ -75,6 +105,8 @@ pub enum BehaviorNode<B> {
     Composite(CompositeNode<B>),
     /// A node with custom logic.
     Leaf(LeafNode<B>),
+    /// A node that changes result of its child.
+    Decorator(DecoratorNode<B>),
 }
 
 impl<B> Default for BehaviorNode<B> {
@@ -88,6 +120,16 @@ impl<B> Default for BehaviorNode<B> {
 pub struct BehaviorTree<B> {
     nodes: Pool<BehaviorNode<B>>,
     root: Handle<BehaviorNode<B>>,
+    #[visit(optional)] // Backward compatibility.
+    blackboard: RefCell<Blackboard>,
+    #[visit(skip)]
+    time: Cell<f32>,
+    // Nodes that returned `Running` on current (or last) tick.
+    #[visit(skip)]
+    running: RefCell<Vec<Handle<BehaviorNode<B>>>>,
+    // Nodes that was ticked on current (or last) tick.
+    #[visit(skip)]
+    visited: RefCell<Vec<Handle<BehaviorNode<B>>>>,
 }
 
 impl<B> Default for BehaviorTree<B> {
@@ -95,6 +137,10 @@ impl<B> Default for BehaviorTree<B> {
         Self {
             nodes: Default::default(),
             root: Default::default(),
+            blackboard: Default::default(),
+            time: Default::default(),
+            running: Default::default(),
+            visited: Default::default(),
         }
     }
 }
@@ -106,7 +152,11 @@ impl<B> BehaviorTree<B> {
         let root = nodes.spawn(BehaviorNode::Root(RootNode {
             child: Default::default(),
         }));
-        Self { nodes, root }
+        Self {
+            nodes,
+            root,
+            ..Default::default()
+        }
     }
 
     /// Adds a node to the tree, returns its handle.
@@ -124,6 +174,28 @@ impl<B> BehaviorTree<B> {
     }
 
     fn tick_recursive<'a, Ctx>(&self, handle: Handle<BehaviorNode<B>>, context: &mut Ctx) -> Status
+    where
+        B: Behavior<'a, Context = Ctx>,
+    {
+        self.visited.borrow_mut().push(handle);
+        let first_running = self.running.borrow().len();
+
+        let status = self.tick_node(handle, context);
+
+        if status == Status::Running {
+            self.running.borrow_mut().push(handle);
+        } else {
+            // Node is finished, so its running descendants must be stopped.
+            let interrupted = self.running.borrow_mut().split_off(first_running);
+            for descendant in interrupted {
+                self.abort_node(descendant, context);
+            }
+        }
+
+        status
+    }
+
+    fn tick_node<'a, Ctx>(&self, handle: Handle<BehaviorNode<B>>, context: &mut Ctx) -> Status
     where
         B: Behavior<'a, Context = Ctx>,
     {
@@ -166,16 +238,144 @@ impl<B> BehaviorTree<B> {
                     }
                     Status::Failure
                 }
+                CompositeNodeKind::Parallel { success, failure } => {
+                    let total = composite.children.len();
+                    let (mut succeeded, mut failed) = (0, 0);
+                    for child in composite.children.iter() {
+                        match self.tick_recursive(*child, context) {
+                            Status::Success => succeeded += 1,
+                            Status::Failure => failed += 1,
+                            Status::Running => (),
+                        }
+                    }
+                    if total == 0 {
+                        Status::Success
+                    } else if failure.is_satisfied(failed, total) {
+                        Status::Failure
+                    } else if success.is_satisfied(succeeded, total) {
+                        Status::Success
+                    } else if succeeded + failed == total {
+                        Status::Failure
+                    } else {
+                        Status::Running
+                    }
+                }
             },
-            BehaviorNode::Leaf(ref leaf) => {
-                leaf.behavior.as_ref().unwrap().borrow_mut().tick(context)
-            }
+            BehaviorNode::Leaf(ref leaf) => leaf
+                .behavior
+                .as_ref()
+                .unwrap()
+                .borrow_mut()
+                .tick_with_blackboard(context, &mut self.blackboard.borrow_mut()),
+            BehaviorNode::Decorator(ref decorator) => self.tick_decorator(decorator, context),
             BehaviorNode::Unknown => {
                 unreachable!()
             }
         }
     }
 
+    fn tick_decorator<'a, Ctx>(&self, decorator: &DecoratorNode<B>, context: &mut Ctx) -> Status
+    where
+        B: Behavior<'a, Context = Ctx>,
+    {
+        let state = &decorator.state;
+        let now = self.time.get();
+        match decorator.kind {
+            DecoratorKind::Inverter => match self.tick_recursive(decorator.child, context) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                Status::Running => Status::Running,
+            },
+            DecoratorKind::Succeeder => match self.tick_recursive(decorator.child, context) {
+                Status::Running => Status::Running,
+                _ => Status::Success,
+            },
+            DecoratorKind::Repeater { count } => {
+                match self.tick_recursive(decorator.child, context) {
+                    Status::Success => {
+                        let repetitions = state.counter.get() + 1;
+                        if count != 0 && repetitions >= count {
+                            state.counter.set(0);
+                            Status::Success
+                        } else {
+                            state.counter.set(repetitions);
+                            Status::Running
+                        }
+                    }
+                    Status::Failure => {
+                        state.counter.set(0);
+                        Status::Failure
+                    }
+                    Status::Running => Status::Running,
+                }
+            }
+            DecoratorKind::Cooldown { duration } => {
+                if let Some(ready_time) = state.time.get() {
+                    if now < ready_time {
+                        return Status::Failure;
+                    }
+                }
+                let status = self.tick_recursive(decorator.child, context);
+                if status != Status::Running {
+                    state.time.set(Some(now + duration));
+                }
+                status
+            }
+            DecoratorKind::TimeLimit { duration } => {
+                let start_time = state.time.get().unwrap_or(now);
+                if now - start_time > duration {
+                    // Child is not ticked and will be aborted.
+                    state.time.set(None);
+                    return Status::Failure;
+                }
+                let status = self.tick_recursive(decorator.child, context);
+                if status == Status::Running {
+                    state.time.set(Some(start_time));
+                } else {
+                    state.time.set(None);
+                }
+                status
+            }
+            DecoratorKind::Condition {
+                ref condition,
+                abort,
+            } => {
+                let must_check = abort == AbortMode::Immediate || !state.active.get();
+                if must_check && !condition.check(&self.blackboard.borrow()) {
+                    state.active.set(false);
+                    return Status::Failure;
+                }
+                let status = self.tick_recursive(decorator.child, context);
+                state.active.set(status == Status::Running);
+                status
+            }
+        }
+    }
+
+    fn abort_node<'a, Ctx>(&self, handle: Handle<BehaviorNode<B>>, context: &mut Ctx)
+    where
+        B: Behavior<'a, Context = Ctx>,
+    {
+        match self.nodes[handle] {
+            BehaviorNode::Leaf(ref leaf) => {
+                if let Some(behavior) = leaf.behavior.as_ref() {
+                    behavior.borrow_mut().abort(context);
+                }
+            }
+            BehaviorNode::Decorator(ref decorator) => {
+                let state = &decorator.state;
+                state.counter.set(0);
+                state.active.set(false);
+                state.time.set(match decorator.kind {
+                    // Cooldown starts when the child is interrupted too.
+                    DecoratorKind::Cooldown { duration } => Some(self.time.get() + duration),
+                    _ => None,
+                });
+            }
+            _ => (),
+        }
+    }
+
     /// Tries to get a shared reference to a node by given handle.
     pub fn node(&self, handle: Handle<BehaviorNode<B>>) -> Option<&BehaviorNode<B>> {
         self.nodes.try_borrow(handle)
@@ -186,12 +386,50 @@ impl<B> BehaviorTree<B> {
         self.nodes.try_borrow_mut(handle)
     }
 
-    /// Performs a single update tick with given context.
+    /// Performs a single update tick with given context. Time of the tree is not changed, use
+    /// `update` if the tree has time-dependent decorators.
     pub fn tick<'a, Ctx>(&self, context: &mut Ctx) -> Status
     where
         B: Behavior<'a, Context = Ctx>,
     {
-        self.tick_recursive(self.root, context)
+        let previously_running = self.running.replace(Vec::new());
+        self.visited.borrow_mut().clear();
+
+        let status = self.tick_recursive(self.root, context);
+
+        // Abort nodes that were running on previous tick, but were not ticked this time.
+        for handle in previously_running {
+            if !self.visited.borrow().contains(&handle) {
+                self.abort_node(handle, context);
+            }
+        }
+
+        status
+    }
+
+    /// Advances time of the tree by `dt` seconds and performs a single update tick with given
+    /// context.
+    pub fn update<'a, Ctx>(&self, dt: f32, context: &mut Ctx) -> Status
+    where
+        B: Behavior<'a, Context = Ctx>,
+    {
+        self.time.set(self.time.get() + dt);
+        self.tick(context)
+    }
+
+    /// Returns current time of the tree in seconds.
+    pub fn time(&self) -> f32 {
+        self.time.get()
+    }
+
+    /// Returns shared reference to the blackboard of the tree.
+    pub fn blackboard(&self) -> Ref<'_, Blackboard> {
+        self.blackboard.borrow()
+    }
+
+    /// Returns mutable reference to the blackboard of the tree.
+    pub fn blackboard_mut(&self) -> RefMut<'_, Blackboard> {
+        self.blackboard.borrow_mut()
     }
 }
 
@@ -212,14 +450,16 @@ impl<B> IndexMut<Handle<BehaviorNode<B>>> for BehaviorTree<B> {
 #[cfg(test)]
 mod test {
     use crate::{
-        core::{futures::executor::block_on, visitor::prelude::*},
+        core::{futures::executor::block_on, pool::Handle, visitor::prelude::*},
         utils::behavior::{
-            composite::{CompositeNode, CompositeNodeKind},
+            blackboard::{Blackboard, BlackboardCondition, BlackboardKey},
+            composite::{CompositeNode, CompositeNodeKind, ParallelPolicy},
+            decorator::{AbortMode, DecoratorKind, DecoratorNode},
             leaf::LeafNode,
-            Behavior, BehaviorTree, Status,
+            Behavior, BehaviorNode, BehaviorTree, Status,
         },
     };
-    use std::{env, fs::File, io::Write, path::PathBuf};
+    use std::{collections::HashMap, env, fs::File, io::Write, path::PathBuf};
 
     #[derive(Debug, PartialEq, Default, Visit)]
     struct WalkAction;
@@ -393,4 +633,294 @@ mod test {
 
         assert_eq!(saved_tree, loaded_tree);
     }
+
+    const ALARM_ACTION: u32 = 100;
+
+    fn alarm() -> BlackboardKey<bool> {
+        BlackboardKey::new("Alarm")
+    }
+
+    // Action with scripted result, it records every tick and abort.
+    #[derive(Debug, PartialEq, Default, Visit)]
+    struct ScriptedAction {
+        id: u32,
+    }
+
+    #[derive(Default)]
+    struct Script {
+        results: HashMap<u32, Status>,
+        ticks: Vec<u32>,
+        aborts: Vec<u32>,
+    }
+
+    impl<'a> Behavior<'a> for ScriptedAction {
+        type Context = Script;
+
+        fn tick(&mut self, context: &mut Self::Context) -> Status {
+            context.ticks.push(self.id);
+            context
+                .results
+                .get(&self.id)
+                .cloned()
+                .unwrap_or(Status::Success)
+        }
+
+        fn tick_with_blackboard(
+            &mut self,
+            context: &mut Self::Context,
+            blackboard: &mut Blackboard,
+        ) -> Status {
+            if self.id == ALARM_ACTION {
+                blackboard.set(&alarm(), true);
+            }
+            self.tick(context)
+        }
+
+        fn abort(&mut self, context: &mut Self::Context) {
+            context.aborts.push(self.id);
+        }
+    }
+
+    fn action(
+        tree: &mut BehaviorTree<ScriptedAction>,
+        id: u32,
+    ) -> Handle<BehaviorNode<ScriptedAction>> {
+        LeafNode::new(ScriptedAction { id }).add_to(tree)
+    }
+
+    fn decorate(kind: DecoratorKind, id: u32) -> BehaviorTree<ScriptedAction> {
+        let mut tree = BehaviorTree::new();
+        let child = action(&mut tree, id);
+        let entry = DecoratorNode::new(kind, child).add_to(&mut tree);
+        tree.set_entry_node(entry);
+        tree
+    }
+
+    #[test]
+    fn test_decorators() {
+        let mut script = Script::default();
+
+        let inverter = decorate(DecoratorKind::Inverter, 1);
+        assert_eq!(inverter.tick(&mut script), Status::Failure);
+        script.results.insert(1, Status::Failure);
+        assert_eq!(inverter.tick(&mut script), Status::Success);
+        script.results.insert(1, Status::Running);
+        assert_eq!(inverter.tick(&mut script), Status::Running);
+
+        let succeeder = decorate(DecoratorKind::Succeeder, 2);
+        script.results.insert(2, Status::Failure);
+        assert_eq!(succeeder.tick(&mut script), Status::Success);
+
+        let repeater = decorate(DecoratorKind::Repeater { count: 3 }, 3);
+        script.ticks.clear();
+        assert_eq!(repeater.tick(&mut script), Status::Running);
+        assert_eq!(repeater.tick(&mut script), Status::Running);
+        assert_eq!(repeater.tick(&mut script), Status::Success);
+        assert_eq!(script.ticks, vec![3, 3, 3]);
+        // Failure resets the counter.
+        assert_eq!(repeater.tick(&mut script), Status::Running);
+        script.results.insert(3, Status::Failure);
+        assert_eq!(repeater.tick(&mut script), Status::Failure);
+        script.results.remove(&3);
+        assert_eq!(repeater.tick(&mut script), Status::Running);
+        assert_eq!(repeater.tick(&mut script), Status::Running);
+        assert_eq!(repeater.tick(&mut script), Status::Success);
+    }
+
+    #[test]
+    fn test_cooldown_and_time_limit() {
+        let mut script = Script::default();
+
+        let cooldown = decorate(DecoratorKind::Cooldown { duration: 1.0 }, 1);
+        assert_eq!(cooldown.update(0.1, &mut script), Status::Success);
+        assert_eq!(cooldown.update(0.5, &mut script), Status::Failure);
+        assert_eq!(script.ticks, vec![1]);
+        assert_eq!(cooldown.update(0.6, &mut script), Status::Success);
+        assert_eq!(script.ticks, vec![1, 1]);
+
+        let time_limit = decorate(DecoratorKind::TimeLimit { duration: 1.0 }, 2);
+        script.results.insert(2, Status::Running);
+        assert_eq!(time_limit.update(0.5, &mut script), Status::Running);
+        assert_eq!(time_limit.update(0.4, &mut script), Status::Running);
+        assert!(script.aborts.is_empty());
+        assert_eq!(time_limit.update(0.7, &mut script), Status::Failure);
+        assert_eq!(script.aborts, vec![2]);
+        // Next tick starts the child again.
+        assert_eq!(time_limit.update(0.5, &mut script), Status::Running);
+    }
+
+    #[test]
+    fn test_parallel() {
+        let mut script = Script::default();
+        script.results.insert(1, Status::Running);
+        script.results.insert(3, Status::Failure);
+
+        let mut tree = BehaviorTree::new();
+        let children = vec![action(&mut tree, 1), action(&mut tree, 2)];
+        let entry = CompositeNode::new_parallel(
+            ParallelPolicy::RequireOne,
+            ParallelPolicy::RequireAll,
+            children,
+        )
+        .add_to(&mut tree);
+        tree.set_entry_node(entry);
+        assert_eq!(tree.tick(&mut script), Status::Success);
+        assert_eq!(script.ticks, vec![1, 2]);
+        // Running sibling is interrupted when the node is finished.
+        assert_eq!(script.aborts, vec![1]);
+
+        let mut tree = BehaviorTree::new();
+        let children = vec![action(&mut tree, 1), action(&mut tree, 2)];
+        let entry = CompositeNode::new_parallel(
+            ParallelPolicy::RequireAll,
+            ParallelPolicy::RequireOne,
+            children,
+        )
+        .add_to(&mut tree);
+        tree.set_entry_node(entry);
+        assert_eq!(tree.tick(&mut script), Status::Running);
+        script.results.insert(1, Status::Success);
+        assert_eq!(tree.tick(&mut script), Status::Success);
+        script.results.insert(2, Status::Failure);
+        assert_eq!(tree.tick(&mut script), Status::Failure);
+    }
+
+    #[test]
+    fn test_conditional_abort() {
+        let mut script = Script::default();
+        script.results.insert(1, Status::Running);
+        script.results.insert(2, Status::Running);
+
+        // Flee when alarm is raised, patrol otherwise.
+        let mut tree = BehaviorTree::new();
+        let flee = action(&mut tree, 1);
+        let patrol = action(&mut tree, 2);
+        let children = vec![
+            DecoratorNode::new_condition(
+                BlackboardCondition::equal(&alarm(), true),
+                AbortMode::Never,
+                flee,
+            )
+            .add_to(&mut tree),
+            patrol,
+        ];
+        let entry = CompositeNode::new_selector(children).add_to(&mut tree);
+        tree.set_entry_node(entry);
+
+        assert_eq!(tree.tick(&mut script), Status::Running);
+        assert_eq!(script.ticks, vec![2]);
+        tree.blackboard_mut().set(&alarm(), true);
+        assert_eq!(tree.tick(&mut script), Status::Running);
+        assert_eq!(script.ticks, vec![2, 1]);
+        assert_eq!(script.aborts, vec![2]);
+
+        // Running node is not interrupted by changes of the condition.
+        tree.blackboard_mut().set(&alarm(), false);
+        assert_eq!(tree.tick(&mut script), Status::Running);
+        assert_eq!(script.ticks, vec![2, 1, 1]);
+
+        // But it is interrupted when the condition is observed.
+        if let Some(BehaviorNode::Decorator(decorator)) =
+            tree.node_mut(children_of(&tree, entry)[0])
+        {
+            if let DecoratorKind::Condition { abort, .. } = &mut decorator.kind {
+                *abort = AbortMode::Immediate;
+            }
+        }
+        assert_eq!(tree.tick(&mut script), Status::Running);
+        assert_eq!(script.ticks, vec![2, 1, 1, 2]);
+        assert_eq!(script.aborts, vec![2, 1]);
+
+        // Leaves could write to the blackboard.
+        let mut tree = BehaviorTree::new();
+        let entry = action(&mut tree, ALARM_ACTION);
+        tree.set_entry_node(entry);
+        assert_eq!(tree.blackboard().get(&alarm()), None);
+        tree.tick(&mut script);
+        assert_eq!(tree.blackboard().get(&alarm()), Some(true));
+    }
+
+    fn children_of(
+        tree: &BehaviorTree<ScriptedAction>,
+        handle: Handle<BehaviorNode<ScriptedAction>>,
+    ) -> Vec<Handle<BehaviorNode<ScriptedAction>>> {
+        match &tree[handle] {
+            BehaviorNode::Composite(composite) => composite.children.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_blackboard() {
+        let health = BlackboardKey::<f32>::new("Health");
+        let name = BlackboardKey::<String>::new("Name");
+        let health_as_int = BlackboardKey::<i64>::new("Health");
+
+        let mut blackboard = Blackboard::new();
+        assert!(!BlackboardCondition::is_set(&health).check(&blackboard));
+        assert!(BlackboardCondition::is_not_set(&health).check(&blackboard));
+        assert!(!BlackboardCondition::less(&health, 10.0).check(&blackboard));
+
+        blackboard.set(&health, 5.0);
+        blackboard.set(&name, "Bot".to_owned());
+        assert_eq!(blackboard.get(&health), Some(5.0));
+        assert_eq!(blackboard.get(&health_as_int), None);
+        assert!(BlackboardCondition::less(&health, 10.0).check(&blackboard));
+        assert!(!BlackboardCondition::greater(&health, 10.0).check(&blackboard));
+        assert!(!BlackboardCondition::less(&health_as_int, 10).check(&blackboard));
+        assert!(BlackboardCondition::equal(&name, "Bot".to_owned()).check(&blackboard));
+        assert!(BlackboardCondition::not_equal(&name, "Player".to_owned()).check(&blackboard));
+
+        assert_eq!(blackboard.remove(&name), Some("Bot".to_owned()));
+        assert!(!blackboard.contains("Name"));
+    }
+
+    #[test]
+    fn test_decorated_tree_save_load() {
+        let path = {
+            let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+            let root = PathBuf::from(manifest_dir).join("test_output");
+            if !root.exists() {
+                std::fs::create_dir(&root).unwrap();
+            }
+            root.join("decorated_behavior_save_load.bin")
+        };
+
+        let mut saved_tree = BehaviorTree::new();
+        let children = vec![
+            action(&mut saved_tree, 1),
+            DecoratorNode::new_repeater(2, action(&mut saved_tree, 2)).add_to(&mut saved_tree),
+            DecoratorNode::new_time_limit(3.0, action(&mut saved_tree, 3)).add_to(&mut saved_tree),
+        ];
+        let parallel = CompositeNode::new_parallel(
+            ParallelPolicy::RequireOne,
+            ParallelPolicy::RequireAll,
+            children,
+        )
+        .add_to(&mut saved_tree);
+        let entry = DecoratorNode::new_condition(
+            BlackboardCondition::greater(&BlackboardKey::<i64>::new("Ammo"), 0),
+            AbortMode::Immediate,
+            parallel,
+        )
+        .add_to(&mut saved_tree);
+        saved_tree.set_entry_node(entry);
+        saved_tree
+            .blackboard_mut()
+            .set(&BlackboardKey::<i64>::new("Ammo"), 10);
+
+        let mut visitor = Visitor::new();
+        saved_tree.visit("Tree", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+
+        let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+        let mut loaded_tree = BehaviorTree::<ScriptedAction>::default();
+        loaded_tree.visit("Tree", &mut visitor).unwrap();
+
+        assert_eq!(saved_tree, loaded_tree);
+
+        let mut script = Script::default();
+        assert_eq!(loaded_tree.tick(&mut script), Status::Success);
+        assert_eq!(script.ticks, vec![1, 2, 3]);
+    }
 }
@@ -0,0 +1,478 @@
+//! Goal-oriented action planning (GOAP).
+//!
+//! GOAP planner searches a sequence of actions that changes current world state to a state
+//! that satisfies a goal. World state is a set of named boolean facts, every action has
+//! preconditions (facts that must have certain values to perform the action), effects
+//! (facts that are changed by the action) and cost. The planner uses generic A* search
+//! (see [`crate::utils::astar::find_graph_path`]) over world states, so found plan is the
+//! cheapest one.
+//!
+//! The planner only builds plans, it does not execute them - it is up to the game to run
+//! actions of a plan one by one and to plan again when something goes wrong. Planning could
+//! also be combined with behavior trees: a leaf of the tree could request new plan and run
+//! its actions.
+
+#![warn(missing_docs)]
+
+use crate::{
+    core::visitor::prelude::*,
+    utils::astar::{find_graph_path, SearchGraph},
+};
+
+/// Named boolean value of world state.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Visit)]
+pub struct Fact {
+    /// Name of the fact.
+    pub name: String,
+    /// Value of the fact.
+    pub value: bool,
+}
+
+/// World state is a set of facts. Facts that are not in the set are considered false.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Visit)]
+pub struct WorldState {
+    // True facts sorted by name.
+    facts: Vec<Fact>,
+}
+
+impl WorldState {
+    /// Creates new empty world state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets value of given fact.
+    pub fn set(&mut self, name: &str, value: bool) {
+        match (
+            self.facts
+                .binary_search_by(|fact| fact.name.as_str().cmp(name)),
+            value,
+        ) {
+            (Ok(_), true) | (Err(_), false) => (),
+            // False facts are not stored, so equal states have equal representation.
+            (Ok(index), false) => {
+                self.facts.remove(index);
+            }
+            (Err(index), true) => self.facts.insert(
+                index,
+                Fact {
+                    name: name.to_owned(),
+                    value,
+                },
+            ),
+        }
+    }
+
+    /// Sets value of given fact and returns self, useful for chained initialization.
+    pub fn with(mut self, name: &str, value: bool) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Returns value of given fact, facts that are not set are false.
+    pub fn get(&self, name: &str) -> bool {
+        self.facts
+            .binary_search_by(|fact| fact.name.as_str().cmp(name))
+            .map(|index| self.facts[index].value)
+            .unwrap_or_default()
+    }
+
+    /// Returns true facts of the state sorted by name.
+    pub fn facts(&self) -> &[Fact] {
+        &self.facts
+    }
+
+    /// Returns true if every given condition is true in the state.
+    pub fn satisfies(&self, conditions: &[Fact]) -> bool {
+        self.unsatisfied_count(conditions) == 0
+    }
+
+    /// Returns amount of given conditions that are false in the state.
+    pub fn unsatisfied_count(&self, conditions: &[Fact]) -> usize {
+        conditions
+            .iter()
+            .filter(|condition| self.get(&condition.name) != condition.value)
+            .count()
+    }
+
+    /// Applies given effects to the state.
+    pub fn apply(&mut self, effects: &[Fact]) {
+        for effect in effects {
+            self.set(&effect.name, effect.value);
+        }
+    }
+}
+
+/// Action that could be performed by an agent.
+#[derive(Clone, Debug, PartialEq, Visit)]
+pub struct GoapAction {
+    /// Name of the action.
+    pub name: String,
+    /// Cost of the action, must be positive.
+    pub cost: f32,
+    /// Facts that must have given values to perform the action.
+    pub preconditions: Vec<Fact>,
+    /// Facts that are changed by the action.
+    pub effects: Vec<Fact>,
+}
+
+impl Default for GoapAction {
+    fn default() -> Self {
+        Self {
+            name: Default::default(),
+            cost: 1.0,
+            preconditions: Default::default(),
+            effects: Default::default(),
+        }
+    }
+}
+
+impl GoapAction {
+    /// Creates new action without preconditions and effects.
+    pub fn new(name: &str, cost: f32) -> Self {
+        Self {
+            name: name.to_owned(),
+            cost,
+            ..Default::default()
+        }
+    }
+
+    /// Adds new precondition to the action.
+    pub fn with_precondition(mut self, name: &str, value: bool) -> Self {
+        self.preconditions.push(Fact {
+            name: name.to_owned(),
+            value,
+        });
+        self
+    }
+
+    /// Adds new effect to the action.
+    pub fn with_effect(mut self, name: &str, value: bool) -> Self {
+        self.effects.push(Fact {
+            name: name.to_owned(),
+            value,
+        });
+        self
+    }
+
+    /// Returns true if the action could be performed in given state.
+    pub fn is_applicable(&self, state: &WorldState) -> bool {
+        state.satisfies(&self.preconditions)
+    }
+}
+
+/// Goal of planning - a set of facts that must have given values.
+#[derive(Clone, Debug, Default, PartialEq, Visit)]
+pub struct GoapGoal {
+    /// Name of the goal.
+    pub name: String,
+    /// Conditions of the goal.
+    pub conditions: Vec<Fact>,
+}
+
+impl GoapGoal {
+    /// Creates new goal without conditions.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            conditions: Default::default(),
+        }
+    }
+
+    /// Adds new condition to the goal.
+    pub fn with_condition(mut self, name: &str, value: bool) -> Self {
+        self.conditions.push(Fact {
+            name: name.to_owned(),
+            value,
+        });
+        self
+    }
+
+    /// Returns true if given state satisfies the goal.
+    pub fn is_satisfied(&self, state: &WorldState) -> bool {
+        state.satisfies(&self.conditions)
+    }
+}
+
+/// Result of planning.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoapPlan {
+    /// Indices of actions of the planner in order of execution. Empty if the goal is already
+    /// satisfied.
+    pub actions: Vec<usize>,
+    /// Total cost of the plan.
+    pub cost: f32,
+}
+
+/// See module docs.
+#[derive(Clone, Debug, PartialEq, Visit)]
+pub struct GoapPlanner {
+    actions: Vec<GoapAction>,
+    max_expanded_nodes: u32,
+}
+
+impl Default for GoapPlanner {
+    fn default() -> Self {
+        Self {
+            actions: Default::default(),
+            max_expanded_nodes: 4096,
+        }
+    }
+}
+
+struct PlanningGraph<'a> {
+    planner: &'a GoapPlanner,
+    goal: &'a GoapGoal,
+    // Lower bound of cost per satisfied condition, used to make the heuristic admissible.
+    min_cost_per_fact: f32,
+}
+
+impl<'a> SearchGraph for PlanningGraph<'a> {
+    type Node = WorldState;
+    type Edge = usize;
+
+    fn edges(&self, node: &WorldState, edges: &mut Vec<(usize, WorldState, f32)>) {
+        for (index, action) in self.planner.actions.iter().enumerate() {
+            if action.is_applicable(node) {
+                let mut state = node.clone();
+                state.apply(&action.effects);
+                if state != *node {
+                    edges.push((index, state, action.cost));
+                }
+            }
+        }
+    }
+
+    fn heuristic(&self, node: &WorldState) -> f32 {
+        node.unsatisfied_count(&self.goal.conditions) as f32 * self.min_cost_per_fact
+    }
+
+    fn is_goal(&self, node: &WorldState) -> bool {
+        self.goal.is_satisfied(node)
+    }
+}
+
+impl GoapPlanner {
+    /// Creates new planner without actions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds new action and returns its index.
+    pub fn add_action(&mut self, action: GoapAction) -> usize {
+        self.actions.push(action);
+        self.actions.len() - 1
+    }
+
+    /// Adds new action and returns self, useful for chained initialization.
+    pub fn with_action(mut self, action: GoapAction) -> Self {
+        self.add_action(action);
+        self
+    }
+
+    /// Returns shared reference to action with given index.
+    pub fn action(&self, index: usize) -> &GoapAction {
+        &self.actions[index]
+    }
+
+    /// Returns shared reference to actions of the planner.
+    pub fn actions(&self) -> &[GoapAction] {
+        &self.actions
+    }
+
+    /// Returns mutable reference to actions of the planner.
+    pub fn actions_mut(&mut self) -> &mut Vec<GoapAction> {
+        &mut self.actions
+    }
+
+    /// Sets maximum amount of world states that could be examined during single planning,
+    /// planning fails if the limit is reached.
+    pub fn set_max_expanded_nodes(&mut self, max_expanded_nodes: u32) {
+        self.max_expanded_nodes = max_expanded_nodes;
+    }
+
+    /// Returns maximum amount of world states that could be examined during single planning.
+    pub fn max_expanded_nodes(&self) -> u32 {
+        self.max_expanded_nodes
+    }
+
+    /// Searches the cheapest sequence of actions that changes given state to a state that
+    /// satisfies the goal. Returns `None` if there is no such sequence.
+    pub fn plan(&self, state: &WorldState, goal: &GoapGoal) -> Option<GoapPlan> {
+        // Single action could satisfy at most as many conditions as it has effects.
+        let min_cost_per_fact = self
+            .actions
+            .iter()
+            .filter(|action| !action.effects.is_empty())
+            .map(|action| action.cost / action.effects.len() as f32)
+            .fold(f32::MAX, f32::min);
+        let graph = PlanningGraph {
+            planner: self,
+            goal,
+            min_cost_per_fact: if min_cost_per_fact == f32::MAX {
+                0.0
+            } else {
+                min_cost_per_fact.max(0.0)
+            },
+        };
+
+        find_graph_path(&graph, state.clone(), self.max_expanded_nodes as usize).map(|path| {
+            GoapPlan {
+                actions: path.edges,
+                cost: path.cost,
+            }
+        })
+    }
+
+    /// Tries to plan for given goals in order (most important first) and returns index of
+    /// first goal that could be achieved together with its plan.
+    pub fn plan_best<'a, I>(&self, state: &WorldState, goals: I) -> Option<(usize, GoapPlan)>
+    where
+        I: IntoIterator<Item = &'a GoapGoal>,
+    {
+        goals
+            .into_iter()
+            .enumerate()
+            .find_map(|(index, goal)| self.plan(state, goal).map(|plan| (index, plan)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            futures::executor::block_on,
+            visitor::{Visit, Visitor},
+        },
+        utils::goap::{GoapAction, GoapGoal, GoapPlanner, WorldState},
+    };
+    use std::{env, path::PathBuf};
+
+    fn make_planner() -> GoapPlanner {
+        GoapPlanner::new()
+            .with_action(
+                GoapAction::new("GetAxe", 2.0)
+                    .with_precondition("HasAxe", false)
+                    .with_effect("HasAxe", true),
+            )
+            .with_action(
+                GoapAction::new("ChopWood", 4.0)
+                    .with_precondition("HasAxe", true)
+                    .with_effect("HasWood", true),
+            )
+            .with_action(
+                GoapAction::new("GatherBranches", 8.0)
+                    .with_precondition("HasWood", false)
+                    .with_effect("HasWood", true),
+            )
+            .with_action(
+                GoapAction::new("MakeFire", 1.0)
+                    .with_precondition("HasWood", true)
+                    .with_effect("HasWood", false)
+                    .with_effect("IsWarm", true),
+            )
+    }
+
+    fn names(planner: &GoapPlanner, actions: &[usize]) -> Vec<String> {
+        actions
+            .iter()
+            .map(|&index| planner.action(index).name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_world_state() {
+        let mut state = WorldState::new().with("b", true).with("a", false);
+        assert!(state.get("b"));
+        assert!(!state.get("a"));
+        assert!(!state.get("c"));
+        assert_eq!(state.facts().len(), 1);
+
+        state.set("a", true);
+        state.set("b", false);
+        assert_eq!(state, WorldState::new().with("a", true));
+        assert_eq!(state.facts()[0].name, "a");
+        assert_eq!(
+            WorldState::new().with("x", true).with("y", true),
+            WorldState::new().with("y", true).with("x", true)
+        );
+    }
+
+    #[test]
+    fn test_cheapest_plan() {
+        let planner = make_planner();
+        let goal = GoapGoal::new("KeepWarm").with_condition("IsWarm", true);
+
+        let plan = planner.plan(&WorldState::new(), &goal).unwrap();
+        assert_eq!(
+            names(&planner, &plan.actions),
+            ["GetAxe", "ChopWood", "MakeFire"]
+        );
+        assert_eq!(plan.cost, 7.0);
+
+        // Already has wood.
+        let plan = planner
+            .plan(&WorldState::new().with("HasWood", true), &goal)
+            .unwrap();
+        assert_eq!(names(&planner, &plan.actions), ["MakeFire"]);
+
+        // Goal is already satisfied.
+        let plan = planner
+            .plan(&WorldState::new().with("IsWarm", true), &goal)
+            .unwrap();
+        assert!(plan.actions.is_empty());
+        assert_eq!(plan.cost, 0.0);
+
+        // Axe is too expensive now.
+        let mut planner = make_planner();
+        planner.actions_mut()[0].cost = 10.0;
+        let plan = planner.plan(&WorldState::new(), &goal).unwrap();
+        assert_eq!(
+            names(&planner, &plan.actions),
+            ["GatherBranches", "MakeFire"]
+        );
+    }
+
+    #[test]
+    fn test_plan_best() {
+        let planner = make_planner();
+        let goals = [
+            GoapGoal::new("Fly").with_condition("CanFly", true),
+            GoapGoal::new("KeepWarm").with_condition("IsWarm", true),
+        ];
+
+        assert!(planner.plan(&WorldState::new(), &goals[0]).is_none());
+
+        let (index, plan) = planner.plan_best(&WorldState::new(), &goals).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(plan.actions.len(), 3);
+
+        let mut planner = make_planner();
+        planner.set_max_expanded_nodes(1);
+        assert!(planner.plan_best(&WorldState::new(), &goals).is_none());
+    }
+
+    #[test]
+    fn test_planner_save_load() {
+        let mut planner = make_planner();
+        planner.set_max_expanded_nodes(123);
+
+        let path = {
+            let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+            let root = PathBuf::from(manifest_dir).join("test_output");
+            // Other tests could create the directory concurrently.
+            std::fs::create_dir_all(&root).unwrap();
+            root.join("goap_save_load.bin")
+        };
+
+        let mut visitor = Visitor::new();
+        planner.visit("Planner", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+
+        let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+        let mut loaded = GoapPlanner::default();
+        loaded.visit("Planner", &mut visitor).unwrap();
+
+        assert_eq!(loaded, planner);
+    }
+}
@@ -0,0 +1,221 @@
+//! Image comparison utilities. Main use case is golden tests - an image produced by the
+//! renderer (see [`Renderer::render_scene_to_texture`](crate::renderer::Renderer::render_scene_to_texture))
+//! is compared with a reference image stored on disk.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rg3d::{
+//!     resource::texture::{CompressionOptions, Texture},
+//!     utils::image_diff,
+//! };
+//!
+//! fn check(actual: Texture) {
+//!     let expected = Texture::load_from_memory(
+//!         &std::fs::read("tests/golden/cube.png").unwrap(),
+//!         CompressionOptions::NoCompression,
+//!     )
+//!     .unwrap();
+//!
+//!     let diff = image_diff::compare(&actual.data_ref(), &expected.data_ref(), 2).unwrap();
+//!     assert!(diff.mismatch_ratio() < 0.001, "{} pixels differ", diff.mismatched_pixels);
+//! }
+//! ```
+
+use crate::resource::texture::{TextureData, TextureKind, TexturePixelKind};
+
+/// An error that may occur during image comparison.
+#[derive(Debug, thiserror::Error)]
+pub enum ImageDiffError {
+    /// Images must be 2D textures of the same size.
+    #[error("Size mismatch: {0:?} vs {1:?}")]
+    SizeMismatch(TextureKind, TextureKind),
+    /// Pixel format is compressed or uses 16 bits per channel.
+    #[error("Unsupported pixel format: {0:?}")]
+    UnsupportedFormat(TexturePixelKind),
+}
+
+/// Result of comparison of two images.
+#[derive(Debug, Clone)]
+pub struct ImageDiff {
+    /// Width of compared images in pixels.
+    pub width: u32,
+    /// Height of compared images in pixels.
+    pub height: u32,
+    /// Amount of pixels that have at least one channel which differs more than the tolerance.
+    pub mismatched_pixels: usize,
+    /// Maximum difference of a single channel over the whole image.
+    pub max_difference: u8,
+    /// Per-pixel maximum channel difference, row by row.
+    pub differences: Vec<u8>,
+}
+
+impl ImageDiff {
+    /// Returns ratio of mismatched pixels to total amount of pixels in [0; 1] range.
+    pub fn mismatch_ratio(&self) -> f32 {
+        self.mismatched_pixels as f32 / (self.width * self.height).max(1) as f32
+    }
+
+    /// Returns true if there are no mismatched pixels.
+    pub fn is_match(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+
+    /// Creates grayscale texture from per-pixel differences, brighter pixels differ more.
+    /// It can be saved next to the failed test output to see what exactly has changed.
+    pub fn make_texture_data(&self) -> TextureData {
+        TextureData::from_bytes(
+            TextureKind::Rectangle {
+                width: self.width,
+                height: self.height,
+            },
+            TexturePixelKind::R8,
+            self.differences.clone(),
+            false,
+        )
+        .unwrap()
+    }
+}
+
+/// Returns RGBA8 color of a pixel at given index.
+fn fetch(data: &[u8], kind: TexturePixelKind, index: usize) -> [u8; 4] {
+    match kind {
+        TexturePixelKind::R8 => [data[index], data[index], data[index], 255],
+        TexturePixelKind::RG8 => {
+            let i = index * 2;
+            [data[i], data[i], data[i], data[i + 1]]
+        }
+        TexturePixelKind::RGB8 => {
+            let i = index * 3;
+            [data[i], data[i + 1], data[i + 2], 255]
+        }
+        TexturePixelKind::BGR8 => {
+            let i = index * 3;
+            [data[i + 2], data[i + 1], data[i], 255]
+        }
+        TexturePixelKind::RGBA8 => {
+            let i = index * 4;
+            [data[i], data[i + 1], data[i + 2], data[i + 3]]
+        }
+        TexturePixelKind::BGRA8 => {
+            let i = index * 4;
+            [data[i + 2], data[i + 1], data[i], data[i + 3]]
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn is_supported(kind: TexturePixelKind) -> bool {
+    matches!(
+        kind,
+        TexturePixelKind::R8
+            | TexturePixelKind::RG8
+            | TexturePixelKind::RGB8
+            | TexturePixelKind::BGR8
+            | TexturePixelKind::RGBA8
+            | TexturePixelKind::BGRA8
+    )
+}
+
+/// Compares two 2D images with 8-bit channels pixel by pixel. Images may have different pixel
+/// formats, every pixel is converted to RGBA8 first (grayscale is expanded to RGB). Pixel is
+/// considered mismatched if any of its channels differs more than `tolerance`.
+pub fn compare(
+    a: &TextureData,
+    b: &TextureData,
+    tolerance: u8,
+) -> Result<ImageDiff, ImageDiffError> {
+    let (width, height) = match (a.kind(), b.kind()) {
+        (
+            TextureKind::Rectangle { width, height },
+            TextureKind::Rectangle {
+                width: other_width,
+                height: other_height,
+            },
+        ) if width == other_width && height == other_height => (width, height),
+        (a, b) => return Err(ImageDiffError::SizeMismatch(a, b)),
+    };
+
+    for kind in [a.pixel_kind(), b.pixel_kind()].iter() {
+        if !is_supported(*kind) {
+            return Err(ImageDiffError::UnsupportedFormat(*kind));
+        }
+    }
+
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    let differences = (0..(width * height) as usize)
+        .map(|i| {
+            let pa = fetch(a.data(), a.pixel_kind(), i);
+            let pb = fetch(b.data(), b.pixel_kind(), i);
+            let difference = pa
+                .iter()
+                .zip(pb.iter())
+                .map(|(ca, cb)| (*ca as i16 - *cb as i16).unsigned_abs() as u8)
+                .max()
+                .unwrap_or_default();
+            if difference > tolerance {
+                mismatched_pixels += 1;
+            }
+            max_difference = max_difference.max(difference);
+            difference
+        })
+        .collect();
+
+    Ok(ImageDiff {
+        width,
+        height,
+        mismatched_pixels,
+        max_difference,
+        differences,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        resource::texture::{TextureData, TextureKind, TexturePixelKind},
+        utils::image_diff::{compare, ImageDiffError},
+    };
+
+    fn image(pixel_kind: TexturePixelKind, bytes: Vec<u8>) -> TextureData {
+        TextureData::from_bytes(
+            TextureKind::Rectangle {
+                width: 2,
+                height: 1,
+            },
+            pixel_kind,
+            bytes,
+            false,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_compare() {
+        let a = image(TexturePixelKind::RGBA8, vec![10, 20, 30, 255, 0, 0, 0, 255]);
+        let b = image(TexturePixelKind::BGR8, vec![30, 20, 12, 0, 100, 0]);
+
+        let diff = compare(&a, &b, 2).unwrap();
+        assert_eq!(diff.mismatched_pixels, 1);
+        assert_eq!(diff.max_difference, 100);
+        assert_eq!(diff.differences, vec![2, 100]);
+        assert!((diff.mismatch_ratio() - 0.5).abs() < f32::EPSILON);
+        assert!(compare(&a, &a, 0).unwrap().is_match());
+
+        let c = TextureData::from_bytes(
+            TextureKind::Rectangle {
+                width: 1,
+                height: 2,
+            },
+            TexturePixelKind::R8,
+            vec![0, 0],
+            false,
+        )
+        .unwrap();
+        assert!(matches!(
+            compare(&a, &c, 0),
+            Err(ImageDiffError::SizeMismatch(..))
+        ));
+    }
+}
@@ -4,10 +4,13 @@
 
 pub mod astar;
 pub mod behavior;
+pub mod goap;
+pub mod image_diff;
 pub mod lightmap;
 pub mod log;
 pub mod navmesh;
 pub mod raw_mesh;
+pub mod utility_ai;
 pub mod uvgen;
 
 use crate::core::algebra::Vector2;
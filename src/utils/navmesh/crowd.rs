@@ -0,0 +1,699 @@
+//! Local collision avoidance for groups of navmesh agents.
+//!
+//! Every agent of a crowd follows its own path, but its velocity is adjusted on every update
+//! using optimal reciprocal collision avoidance (ORCA): each neighbour forbids a half-plane of
+//! velocities that would lead to a collision within a time horizon, and the velocity closest
+//! to the preferred one is picked from the remaining set. Every agent takes half of the
+//! responsibility for avoiding a collision, which gives smooth and oscillation-free motion.
+//! Agents are kept on the navmesh - moves that leave the navmesh slide along its border.
+
+use crate::{
+    core::{
+        algebra::{Vector2, Vector3},
+        math::ray::Ray,
+        pool::{Handle, Pool},
+        visitor::prelude::*,
+    },
+    utils::{
+        astar::PathError,
+        navmesh::{Navmesh, NavmeshAgent},
+    },
+};
+use std::ops::{Index, IndexMut};
+
+const EPSILON: f32 = 1.0e-5;
+
+/// A set of navmesh agents that avoid collisions with each other.
+#[derive(Visit)]
+pub struct Crowd {
+    agents: Pool<NavmeshAgent>,
+    time_horizon: f32,
+    neighbour_distance: f32,
+}
+
+impl Default for Crowd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Index<Handle<NavmeshAgent>> for Crowd {
+    type Output = NavmeshAgent;
+
+    fn index(&self, index: Handle<NavmeshAgent>) -> &Self::Output {
+        &self.agents[index]
+    }
+}
+
+impl IndexMut<Handle<NavmeshAgent>> for Crowd {
+    fn index_mut(&mut self, index: Handle<NavmeshAgent>) -> &mut Self::Output {
+        &mut self.agents[index]
+    }
+}
+
+// A snapshot of agent's state in XZ plane.
+struct AgentState {
+    handle: Handle<NavmeshAgent>,
+    position: Vector2<f32>,
+    velocity: Vector2<f32>,
+    preferred_velocity: Vector2<f32>,
+    radius: f32,
+    max_speed: f32,
+}
+
+// Directed line, velocities to the left of it are allowed.
+#[derive(Copy, Clone)]
+struct Line {
+    point: Vector2<f32>,
+    direction: Vector2<f32>,
+}
+
+fn det(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn to_2d(v: Vector3<f32>) -> Vector2<f32> {
+    Vector2::new(v.x, v.z)
+}
+
+impl Crowd {
+    /// Creates new empty crowd.
+    pub fn new() -> Self {
+        Self {
+            agents: Default::default(),
+            time_horizon: 2.0,
+            neighbour_distance: 5.0,
+        }
+    }
+
+    /// Adds new agent to the crowd and returns its handle.
+    pub fn add_agent(&mut self, agent: NavmeshAgent) -> Handle<NavmeshAgent> {
+        self.agents.spawn(agent)
+    }
+
+    /// Removes agent from the crowd and returns it.
+    pub fn remove_agent(&mut self, handle: Handle<NavmeshAgent>) -> NavmeshAgent {
+        self.agents.free(handle)
+    }
+
+    /// Returns an iterator over every agent of the crowd.
+    pub fn agents(&self) -> impl Iterator<Item = &NavmeshAgent> {
+        self.agents.iter()
+    }
+
+    /// Returns an iterator over every agent of the crowd with their handles.
+    pub fn pair_iter(&self) -> impl Iterator<Item = (Handle<NavmeshAgent>, &NavmeshAgent)> {
+        self.agents.pair_iter()
+    }
+
+    /// Sets new time horizon (in seconds). Agents avoid collisions that could happen within
+    /// this time, larger values make agents to react earlier but make them more "shy".
+    pub fn set_time_horizon(&mut self, time_horizon: f32) {
+        self.time_horizon = time_horizon.max(EPSILON);
+    }
+
+    /// Returns current time horizon.
+    pub fn time_horizon(&self) -> f32 {
+        self.time_horizon
+    }
+
+    /// Sets new distance (in meters) at which other agents are taken into account.
+    pub fn set_neighbour_distance(&mut self, distance: f32) {
+        self.neighbour_distance = distance;
+    }
+
+    /// Returns current neighbour distance.
+    pub fn neighbour_distance(&self) -> f32 {
+        self.neighbour_distance
+    }
+
+    /// Performs single update tick of every agent of the crowd: paths are recalculated if
+    /// needed, then agents are moved along their paths avoiding each other. An agent whose path
+    /// could not be built stands still (its path is cleared) while others continue moving, the
+    /// first of such errors is returned after the update.
+    pub fn update(&mut self, dt: f32, navmesh: &mut Navmesh) -> Result<(), PathError> {
+        let mut result = Ok(());
+        for agent in self.agents.iter_mut() {
+            if let Err(error) = agent.update_path(navmesh) {
+                agent.path.clear();
+                agent.current = 0;
+                if result.is_ok() {
+                    result = Err(error);
+                }
+            }
+        }
+
+        if dt <= 0.0 {
+            return result;
+        }
+
+        let states = self
+            .agents
+            .pair_iter()
+            .map(|(handle, agent)| AgentState {
+                handle,
+                position: to_2d(agent.position),
+                velocity: to_2d(agent.velocity),
+                preferred_velocity: preferred_velocity(agent, dt),
+                radius: agent.radius,
+                max_speed: agent.speed,
+            })
+            .collect::<Vec<_>>();
+
+        let mut lines = Vec::new();
+        let velocities = states
+            .iter()
+            .map(|state| {
+                lines.clear();
+                for other in states.iter() {
+                    if other.handle != state.handle
+                        && (other.position - state.position).norm() < self.neighbour_distance
+                    {
+                        lines.push(orca_line(state, other, self.time_horizon, dt));
+                    }
+                }
+
+                let mut velocity = Vector2::default();
+                let line_fail = linear_program2(
+                    &lines,
+                    state.max_speed,
+                    state.preferred_velocity,
+                    false,
+                    &mut velocity,
+                );
+                if line_fail < lines.len() {
+                    linear_program3(&lines, line_fail, state.max_speed, &mut velocity);
+                }
+                velocity
+            })
+            .collect::<Vec<_>>();
+
+        for (state, velocity) in states.iter().zip(velocities) {
+            move_agent(&mut self.agents[state.handle], velocity, dt, navmesh);
+        }
+
+        result
+    }
+}
+
+fn preferred_velocity(agent: &NavmeshAgent, dt: f32) -> Vector2<f32> {
+    match agent.steering_target() {
+        Some(target) => {
+            let delta = to_2d(target - agent.position);
+            let distance = delta.norm();
+            if distance > EPSILON {
+                // Slow down near the target to not overshoot it.
+                delta.scale(agent.speed.min(distance / dt) / distance)
+            } else {
+                Vector2::default()
+            }
+        }
+        None => Vector2::default(),
+    }
+}
+
+// Finds a point on the navmesh below or above given one that is the closest to its height.
+// Surfaces that are farther than climb height are ignored, so agents of a multi-level navmesh
+// never fall to another level when they are blocked at an edge.
+fn project_on_navmesh(
+    navmesh: &Navmesh,
+    point: Vector3<f32>,
+    max_climb: f32,
+) -> Option<Vector3<f32>> {
+    let max_climb = max_climb.max(EPSILON);
+    let ray = Ray::new(
+        point + Vector3::new(0.0, max_climb, 0.0),
+        Vector3::new(0.0, -2.0 * max_climb, 0.0),
+    );
+    let mut closest = None;
+    let mut closest_distance = f32::MAX;
+    navmesh.visit_ray_hits(&ray, |intersection, _, _| {
+        let distance = (intersection.y - point.y).abs();
+        if distance < closest_distance {
+            closest_distance = distance;
+            closest = Some(intersection);
+        }
+        true
+    });
+    closest
+}
+
+fn move_agent(agent: &mut NavmeshAgent, velocity: Vector2<f32>, dt: f32, navmesh: &Navmesh) {
+    // Limit change of velocity by acceleration of the agent.
+    let current = to_2d(agent.velocity);
+    let mut change = velocity - current;
+    let max_change = agent.acceleration * dt;
+    if change.norm() > max_change {
+        change = change.normalize().scale(max_change);
+    }
+    let velocity = current + change;
+
+    // Try to move the agent, if it leaves navmesh then it slides along border of the navmesh.
+    let candidates = [
+        velocity,
+        Vector2::new(velocity.x, 0.0),
+        Vector2::new(0.0, velocity.y),
+    ];
+    let mut moved = false;
+    for candidate in candidates.iter() {
+        let position = agent.position + Vector3::new(candidate.x, 0.0, candidate.y).scale(dt);
+        if let Some(position) = project_on_navmesh(navmesh, position, agent.max_climb) {
+            agent.position = position;
+            agent.velocity = Vector3::new(candidate.x, 0.0, candidate.y);
+            moved = true;
+            break;
+        }
+    }
+    if !moved {
+        if project_on_navmesh(navmesh, agent.position, agent.max_climb).is_none() {
+            // Agent is already outside of navmesh, let it move freely to get back.
+            agent.position += Vector3::new(velocity.x, 0.0, velocity.y).scale(dt);
+            agent.velocity = Vector3::new(velocity.x, 0.0, velocity.y);
+        } else {
+            agent.velocity = Vector3::default();
+        }
+    }
+
+    // Switch to next point of the path if current one is reached or passed.
+    while let (Some(source), Some(destination)) = (
+        agent.path.get(agent.current as usize),
+        agent.path.get(agent.current as usize + 1),
+    ) {
+        let passed = Ray::from_two_points(*source, *destination).project_point(&agent.position);
+        let distance = to_2d(destination - agent.position).norm();
+        if passed >= 1.0 || distance <= agent.radius {
+            agent.current += 1;
+        } else {
+            break;
+        }
+    }
+}
+
+// Builds a half-plane of velocities that allows an agent to avoid collision with other agent
+// within given time horizon. The agent takes half of the responsibility for avoiding the collision.
+fn orca_line(agent: &AgentState, other: &AgentState, time_horizon: f32, dt: f32) -> Line {
+    let relative_position = other.position - agent.position;
+    let relative_velocity = agent.velocity - other.velocity;
+    let distance_sq = relative_position.norm_squared();
+    let combined_radius = agent.radius + other.radius;
+    let combined_radius_sq = combined_radius * combined_radius;
+
+    let direction;
+    let u;
+    if distance_sq > combined_radius_sq {
+        // No collision yet.
+        let w = relative_velocity - relative_position.scale(1.0 / time_horizon);
+        let w_length_sq = w.norm_squared();
+        let dot_product = w.dot(&relative_position);
+
+        if dot_product < 0.0 && dot_product * dot_product > combined_radius_sq * w_length_sq {
+            // Project on cut-off circle.
+            let w_length = w_length_sq.sqrt();
+            let unit_w = w.scale(1.0 / w_length);
+            direction = Vector2::new(unit_w.y, -unit_w.x);
+            u = unit_w.scale(combined_radius / time_horizon - w_length);
+        } else {
+            // Project on legs.
+            let leg = (distance_sq - combined_radius_sq).sqrt();
+            if det(relative_position, w) > 0.0 {
+                // Left leg.
+                direction = Vector2::new(
+                    relative_position.x * leg - relative_position.y * combined_radius,
+                    relative_position.x * combined_radius + relative_position.y * leg,
+                )
+                .scale(1.0 / distance_sq);
+            } else {
+                // Right leg.
+                direction = -Vector2::new(
+                    relative_position.x * leg + relative_position.y * combined_radius,
+                    -relative_position.x * combined_radius + relative_position.y * leg,
+                )
+                .scale(1.0 / distance_sq);
+            }
+            u = direction.scale(relative_velocity.dot(&direction)) - relative_velocity;
+        }
+    } else {
+        // Agents already collide, resolve the collision within one time step.
+        let w = relative_velocity - relative_position.scale(1.0 / dt);
+        let w_length = w.norm();
+        let unit_w = if w_length > EPSILON {
+            w.scale(1.0 / w_length)
+        } else {
+            Vector2::new(1.0, 0.0)
+        };
+        direction = Vector2::new(unit_w.y, -unit_w.x);
+        u = unit_w.scale(combined_radius / dt - w_length);
+    }
+
+    Line {
+        point: agent.velocity + u.scale(0.5),
+        direction,
+    }
+}
+
+// Finds velocity on a line that satisfies constraints of previous lines and closest to
+// optimal velocity.
+fn linear_program1(
+    lines: &[Line],
+    line_no: usize,
+    radius: f32,
+    opt_velocity: Vector2<f32>,
+    direction_opt: bool,
+    result: &mut Vector2<f32>,
+) -> bool {
+    let line = lines[line_no];
+    let dot_product = line.point.dot(&line.direction);
+    let discriminant = dot_product * dot_product + radius * radius - line.point.norm_squared();
+
+    if discriminant < 0.0 {
+        // Max speed circle fully invalidates the line.
+        return false;
+    }
+
+    let sqrt_discriminant = discriminant.sqrt();
+    let mut t_left = -dot_product - sqrt_discriminant;
+    let mut t_right = -dot_product + sqrt_discriminant;
+
+    for other in lines[..line_no].iter() {
+        let denominator = det(line.direction, other.direction);
+        let numerator = det(other.direction, line.point - other.point);
+
+        if denominator.abs() <= EPSILON {
+            // Lines are almost parallel.
+            if numerator < 0.0 {
+                return false;
+            }
+            continue;
+        }
+
+        let t = numerator / denominator;
+        if denominator >= 0.0 {
+            t_right = t_right.min(t);
+        } else {
+            t_left = t_left.max(t);
+        }
+
+        if t_left > t_right {
+            return false;
+        }
+    }
+
+    let t = if direction_opt {
+        if opt_velocity.dot(&line.direction) > 0.0 {
+            t_right
+        } else {
+            t_left
+        }
+    } else {
+        line.direction
+            .dot(&(opt_velocity - line.point))
+            .max(t_left)
+            .min(t_right)
+    };
+    *result = line.point + line.direction.scale(t);
+
+    true
+}
+
+// Finds velocity that satisfies every constraint and closest to optimal velocity, returns
+// number of processed lines - it is less than number of lines if there is no solution.
+fn linear_program2(
+    lines: &[Line],
+    radius: f32,
+    opt_velocity: Vector2<f32>,
+    direction_opt: bool,
+    result: &mut Vector2<f32>,
+) -> usize {
+    *result = if direction_opt {
+        // Optimal velocity is a unit direction in this case.
+        opt_velocity.scale(radius)
+    } else if opt_velocity.norm_squared() > radius * radius {
+        opt_velocity.normalize().scale(radius)
+    } else {
+        opt_velocity
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        if det(line.direction, line.point - *result) > 0.0 {
+            // Result does not satisfy the constraint.
+            let previous = *result;
+            if !linear_program1(lines, i, radius, opt_velocity, direction_opt, result) {
+                *result = previous;
+                return i;
+            }
+        }
+    }
+
+    lines.len()
+}
+
+// Finds velocity that minimizes maximum penetration into forbidden half-planes when there
+// is no velocity that satisfies every constraint.
+fn linear_program3(lines: &[Line], begin_line: usize, radius: f32, result: &mut Vector2<f32>) {
+    let mut distance = 0.0;
+    let mut projected_lines = Vec::new();
+
+    for (i, line) in lines.iter().enumerate().skip(begin_line) {
+        if det(line.direction, line.point - *result) > distance {
+            projected_lines.clear();
+            for other in lines[..i].iter() {
+                let determinant = det(line.direction, other.direction);
+                let point = if determinant.abs() <= EPSILON {
+                    if line.direction.dot(&other.direction) > 0.0 {
+                        // Lines are parallel and point in the same direction.
+                        continue;
+                    }
+                    (line.point + other.point).scale(0.5)
+                } else {
+                    line.point
+                        + line
+                            .direction
+                            .scale(det(other.direction, line.point - other.point) / determinant)
+                };
+                let direction = (other.direction - line.direction)
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_default();
+                projected_lines.push(Line { point, direction });
+            }
+
+            let previous = *result;
+            if linear_program2(
+                &projected_lines,
+                radius,
+                Vector2::new(-line.direction.y, line.direction.x),
+                true,
+                result,
+            ) < projected_lines.len()
+            {
+                // Should not happen in theory, keep previous result in case of numeric errors.
+                *result = previous;
+            }
+
+            distance = det(line.direction, line.point - *result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::Vector3,
+            math::{ray::Ray, TriangleDefinition},
+        },
+        utils::navmesh::{
+            crowd::{project_on_navmesh, Crowd},
+            Navmesh, NavmeshAgentBuilder,
+        },
+    };
+
+    fn make_grid(width: u32, length: u32) -> Navmesh {
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        add_grid(&mut vertices, &mut triangles, width, length, 0.0);
+        Navmesh::new(&triangles, &vertices)
+    }
+
+    fn add_grid(
+        vertices: &mut Vec<Vector3<f32>>,
+        triangles: &mut Vec<TriangleDefinition>,
+        width: u32,
+        length: u32,
+        height: f32,
+    ) {
+        let base = vertices.len() as u32;
+        for z in 0..=length {
+            for x in 0..=width {
+                vertices.push(Vector3::new(x as f32, height, z as f32));
+            }
+        }
+        let index = |x: u32, z: u32| base + z * (width + 1) + x;
+        for z in 0..length {
+            for x in 0..width {
+                triangles.push(TriangleDefinition([
+                    index(x, z),
+                    index(x, z + 1),
+                    index(x + 1, z + 1),
+                ]));
+                triangles.push(TriangleDefinition([
+                    index(x, z),
+                    index(x + 1, z + 1),
+                    index(x + 1, z),
+                ]));
+            }
+        }
+    }
+
+    fn horizontal_distance(a: Vector3<f32>, b: Vector3<f32>) -> f32 {
+        ((a.x - b.x).powi(2) + (a.z - b.z).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn test_head_on_avoidance() {
+        let mut navmesh = make_grid(10, 4);
+        let mut crowd = Crowd::new();
+        let (a, b) = (Vector3::new(1.0, 0.0, 2.0), Vector3::new(9.0, 0.0, 2.0));
+        let first = crowd.add_agent(
+            NavmeshAgentBuilder::new()
+                .with_position(a)
+                .with_target(b)
+                .with_radius(0.4)
+                .build(),
+        );
+        let second = crowd.add_agent(
+            NavmeshAgentBuilder::new()
+                .with_position(b)
+                .with_target(a)
+                .with_radius(0.4)
+                .build(),
+        );
+
+        let dt = 1.0 / 30.0;
+        let mut min_distance = f32::MAX;
+        for _ in 0..300 {
+            crowd.update(dt, &mut navmesh).unwrap();
+            min_distance = min_distance.min(horizontal_distance(
+                crowd[first].position(),
+                crowd[second].position(),
+            ));
+            for agent in crowd.agents() {
+                assert!(navmesh
+                    .ray_cast(Ray::new(
+                        agent.position() + Vector3::new(0.0, 1.0, 0.0),
+                        Vector3::new(0.0, -10.0, 0.0),
+                    ))
+                    .is_some());
+            }
+        }
+
+        assert!(min_distance >= 0.8 * 0.95, "{}", min_distance);
+        assert!(horizontal_distance(crowd[first].position(), b) < 0.05);
+        assert!(horizontal_distance(crowd[second].position(), a) < 0.05);
+    }
+
+    #[test]
+    fn test_multi_level_projection() {
+        // A balcony at the height of 3 meters above a floor.
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        add_grid(&mut vertices, &mut triangles, 10, 4, 0.0);
+        add_grid(&mut vertices, &mut triangles, 4, 4, 3.0);
+        let navmesh = Navmesh::new(&triangles, &vertices);
+
+        let on_balcony = project_on_navmesh(&navmesh, Vector3::new(2.0, 3.1, 2.0), 0.4).unwrap();
+        assert!((on_balcony.y - 3.0).abs() < 1.0e-4);
+        let on_floor = project_on_navmesh(&navmesh, Vector3::new(2.0, 0.1, 2.0), 0.4).unwrap();
+        assert!(on_floor.y.abs() < 1.0e-4);
+        // Past the edge of the balcony there is only the floor below, it must not be picked.
+        assert!(project_on_navmesh(&navmesh, Vector3::new(6.0, 3.0, 2.0), 0.4).is_none());
+    }
+
+    #[test]
+    fn test_agent_stays_on_its_level() {
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        add_grid(&mut vertices, &mut triangles, 10, 4, 0.0);
+        add_grid(&mut vertices, &mut triangles, 4, 4, 3.0);
+        let mut navmesh = Navmesh::new(&triangles, &vertices);
+
+        let mut crowd = Crowd::new();
+        let handle = crowd.add_agent(
+            NavmeshAgentBuilder::new()
+                .with_position(Vector3::new(1.0, 3.0, 2.0))
+                .with_acceleration(0.0)
+                .build(),
+        );
+        // Push the agent towards the edge of the balcony.
+        crowd[handle].velocity = Vector3::new(1.5, 0.0, 0.0);
+
+        for _ in 0..100 {
+            let _ = crowd.update(0.1, &mut navmesh);
+            let position = crowd[handle].position();
+            assert!((position.y - 3.0).abs() < 1.0e-4);
+            assert!(position.x <= 4.0 + 1.0e-4);
+        }
+    }
+
+    #[test]
+    fn test_acceleration_and_speed_limits() {
+        let mut navmesh = make_grid(10, 4);
+        let mut crowd = Crowd::new();
+        let handle = crowd.add_agent(
+            NavmeshAgentBuilder::new()
+                .with_position(Vector3::new(1.0, 0.0, 2.0))
+                .with_target(Vector3::new(9.0, 0.0, 2.0))
+                .with_speed(2.0)
+                .with_acceleration(4.0)
+                .build(),
+        );
+
+        let dt = 0.1;
+        let mut previous = crowd[handle].velocity();
+        for _ in 0..20 {
+            crowd.update(dt, &mut navmesh).unwrap();
+            let velocity = crowd[handle].velocity();
+            assert!((velocity - previous).norm() <= 4.0 * dt + 1.0e-4);
+            assert!(velocity.norm() <= 2.0 + 1.0e-4);
+            previous = velocity;
+        }
+        assert!((previous.norm() - 2.0).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn test_crossing_crowd() {
+        let mut navmesh = make_grid(20, 20);
+        let mut crowd = Crowd::new();
+        let center = Vector3::new(10.0, 0.0, 10.0);
+        let count = 32;
+        for i in 0..count {
+            let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+            let offset = Vector3::new(angle.cos(), 0.0, angle.sin()).scale(8.0);
+            crowd.add_agent(
+                NavmeshAgentBuilder::new()
+                    .with_position(center + offset)
+                    .with_target(center - offset)
+                    .with_radius(0.3)
+                    .build(),
+            );
+        }
+
+        let dt = 1.0 / 30.0;
+        let mut min_distance = f32::MAX;
+        for _ in 0..900 {
+            crowd.update(dt, &mut navmesh).unwrap();
+            let positions = crowd.agents().map(|a| a.position()).collect::<Vec<_>>();
+            for (i, a) in positions.iter().enumerate() {
+                assert!(a.x >= 0.0 && a.x <= 20.0 && a.z >= 0.0 && a.z <= 20.0);
+                for b in positions[i + 1..].iter() {
+                    min_distance = min_distance.min(horizontal_distance(*a, *b));
+                }
+            }
+        }
+
+        // Small overlaps are possible because of discrete time steps.
+        assert!(min_distance > 0.6 * 0.8, "{}", min_distance);
+        for agent in crowd.agents() {
+            assert!(horizontal_distance(agent.position(), agent.target()) < 0.5);
+        }
+    }
+}
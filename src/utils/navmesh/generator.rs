@@ -0,0 +1,1289 @@
+//! Automatic generation of navigation meshes from scene geometry.
+//!
+//! Generator follows the approach of Recast: input triangles are voxelized into a height field
+//! of solid spans, then walkable surfaces are filtered by slope, step height and clearance,
+//! walkable area is eroded by agent radius and remaining cells are merged into planar
+//! rectangles which are triangulated into a [`Navmesh`].
+//!
+//! Output is fully deterministic - same input geometry and settings always produce the same
+//! set of triangles and vertices.
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Point3, Vector3},
+        math::TriangleDefinition,
+        pool::Handle,
+    },
+    physics::geometry::Collider,
+    scene::{
+        graph::Graph,
+        mesh::{
+            buffer::{VertexAttributeUsage, VertexReadTrait},
+            Mesh,
+        },
+        node::Node,
+        physics::Physics,
+        terrain::Terrain,
+    },
+    utils::navmesh::Navmesh,
+};
+
+/// A set of parameters for navmesh generation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NavmeshSettings {
+    /// Size of a voxel on XZ plane. Smaller values give more precise navmesh, but make
+    /// generation slower. Must be positive, otherwise generated navmesh is empty.
+    pub cell_size: f32,
+    /// Height of a voxel. Must be positive, otherwise generated navmesh is empty.
+    pub cell_height: f32,
+    /// Radius of agents, walkable area is shrunk by this value near walls and ledges.
+    pub agent_radius: f32,
+    /// Minimal height of free space above walkable surface.
+    pub agent_height: f32,
+    /// Maximal height of a step that agents are able to climb.
+    pub max_climb: f32,
+    /// Maximal angle (in radians) between walkable surface and horizontal plane.
+    pub max_slope: f32,
+    /// Maximal length of an edge of generated polygons.
+    pub max_edge_length: f32,
+    /// Minimal area of an isolated walkable region, smaller regions are removed. Closed meshes
+    /// are hollow for the generator, so this also removes walkable areas inside of them.
+    pub min_region_area: f32,
+}
+
+impl Default for NavmeshSettings {
+    fn default() -> Self {
+        Self {
+            cell_size: 0.25,
+            cell_height: 0.1,
+            agent_radius: 0.4,
+            agent_height: 1.8,
+            max_climb: 0.4,
+            max_slope: 45.0f32.to_radians(),
+            max_edge_length: 4.0,
+            min_region_area: 4.0,
+        }
+    }
+}
+
+/// Navmesh generator collects world-space triangles of scene geometry and builds navigation
+/// mesh from them.
+///
+/// # Example
+///
+/// ```
+/// use rg3d::{
+///     core::pool::Handle,
+///     scene::{node::Node, Scene},
+///     utils::navmesh::generator::{NavmeshGenerator, NavmeshSettings},
+/// };
+///
+/// fn add_navmesh(scene: &mut Scene, level: &[Handle<Node>]) {
+///     let mut generator = NavmeshGenerator::new(NavmeshSettings {
+///         agent_radius: 0.3,
+///         ..Default::default()
+///     });
+///     for &node in level {
+///         generator.add_node(&scene.graph, node);
+///     }
+///     generator.add_static_colliders(&scene.physics);
+///     scene.navmeshes.add(generator.generate());
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NavmeshGenerator {
+    settings: NavmeshSettings,
+    triangles: Vec<[Vector3<f32>; 3]>,
+}
+
+impl NavmeshGenerator {
+    /// Creates new generator without any input geometry.
+    pub fn new(settings: NavmeshSettings) -> Self {
+        Self {
+            settings,
+            triangles: Default::default(),
+        }
+    }
+
+    /// Returns current generation settings.
+    pub fn settings(&self) -> &NavmeshSettings {
+        &self.settings
+    }
+
+    /// Sets new generation settings.
+    pub fn set_settings(&mut self, settings: NavmeshSettings) {
+        self.settings = settings;
+    }
+
+    /// Returns collected input triangles in world coordinates.
+    pub fn triangles(&self) -> &[[Vector3<f32>; 3]] {
+        &self.triangles
+    }
+
+    /// Removes all collected input geometry.
+    pub fn clear(&mut self) {
+        self.triangles.clear();
+    }
+
+    /// Adds a single triangle in world coordinates. Only triangles that have counter-clockwise
+    /// winding (looking from above) may become walkable.
+    pub fn add_triangle(&mut self, triangle: [Vector3<f32>; 3]) {
+        self.triangles.push(triangle);
+    }
+
+    /// Adds a set of indexed triangles transformed by given matrix.
+    pub fn add_triangles(
+        &mut self,
+        vertices: &[Vector3<f32>],
+        triangles: &[TriangleDefinition],
+        transform: &Matrix4<f32>,
+    ) {
+        let transform = |i: u32| transform.transform_point(&Point3::from(vertices[i as usize]));
+        for triangle in triangles {
+            self.triangles.push([
+                transform(triangle[0]).coords,
+                transform(triangle[1]).coords,
+                transform(triangle[2]).coords,
+            ]);
+        }
+    }
+
+    /// Adds every surface of given mesh. Global transform of the mesh must be up-to-date.
+    pub fn add_mesh(&mut self, mesh: &Mesh) {
+        let global_transform = mesh.global_transform();
+        for surface in mesh.surfaces() {
+            let shared_data = surface.data();
+            let shared_data = shared_data.read().unwrap();
+
+            let vertex_buffer = &shared_data.vertex_buffer;
+            let position = |i: u32| {
+                global_transform
+                    .transform_point(&Point3::from(
+                        vertex_buffer
+                            .get(i as usize)
+                            .unwrap()
+                            .read_3_f32(VertexAttributeUsage::Position)
+                            .unwrap(),
+                    ))
+                    .coords
+            };
+            for triangle in shared_data.geometry_buffer.iter() {
+                self.triangles.push([
+                    position(triangle[0]),
+                    position(triangle[1]),
+                    position(triangle[2]),
+                ]);
+            }
+        }
+    }
+
+    /// Adds height map of every chunk of given terrain, holes are skipped. Global transform of
+    /// the terrain must be up-to-date.
+    pub fn add_terrain(&mut self, terrain: &Terrain) {
+        let global_transform = terrain.global_transform();
+        let chunk_width = terrain.width() / terrain.width_chunk_count() as f32;
+        let chunk_length = terrain.length() / terrain.length_chunk_count() as f32;
+        for chunk in terrain.chunks_ref() {
+            let width_points = chunk.width_point_count();
+            let length_points = chunk.length_point_count();
+            if width_points < 2 || length_points < 2 {
+                continue;
+            }
+            let cell_width = chunk_width / (width_points - 1) as f32;
+            let cell_length = chunk_length / (length_points - 1) as f32;
+            let origin = chunk.local_position();
+            let position = |x: u32, z: u32| {
+                global_transform
+                    .transform_point(&Point3::new(
+                        origin.x + x as f32 * cell_width,
+                        chunk.heightmap()[(z * width_points + x) as usize],
+                        origin.y + z as f32 * cell_length,
+                    ))
+                    .coords
+            };
+            for z in 0..length_points - 1 {
+                for x in 0..width_points - 1 {
+                    if chunk.is_hole(x, z) {
+                        continue;
+                    }
+                    let (p00, p10) = (position(x, z), position(x + 1, z));
+                    let (p01, p11) = (position(x, z + 1), position(x + 1, z + 1));
+                    self.triangles.push([p00, p01, p11]);
+                    self.triangles.push([p00, p11, p10]);
+                }
+            }
+        }
+    }
+
+    /// Adds a graph node. Only meshes and terrains are supported, other kinds of nodes are
+    /// ignored.
+    pub fn add_node(&mut self, graph: &Graph, handle: Handle<Node>) {
+        match &graph[handle] {
+            Node::Mesh(mesh) => self.add_mesh(mesh),
+            Node::Terrain(terrain) => self.add_terrain(terrain),
+            _ => (),
+        }
+    }
+
+    /// Adds shape of given collider at its current world position. Round shapes are approximated,
+    /// compound shapes and 2D shapes are ignored.
+    pub fn add_collider(&mut self, collider: &Collider) {
+        let shape = collider.shape();
+        let (vertices, indices) = if let Some(cuboid) = shape.as_cuboid() {
+            cuboid.to_trimesh()
+        } else if let Some(ball) = shape.as_ball() {
+            ball.to_trimesh(16, 8)
+        } else if let Some(capsule) = shape.as_capsule() {
+            capsule.to_trimesh(16, 8)
+        } else if let Some(cylinder) = shape.as_cylinder() {
+            cylinder.to_trimesh(16)
+        } else if let Some(cone) = shape.as_cone() {
+            cone.to_trimesh(16)
+        } else if let Some(polyhedron) = shape.as_convex_polyhedron() {
+            polyhedron.to_trimesh()
+        } else if let Some(trimesh) = shape.as_trimesh() {
+            (trimesh.vertices().to_vec(), trimesh.indices().to_vec())
+        } else if let Some(heightfield) = shape.as_heightfield() {
+            // Height field is an open surface, so it is always walkable from above.
+            let position = collider.position();
+            for triangle in heightfield.triangles() {
+                let a = position.transform_point(&triangle.a).coords;
+                let b = position.transform_point(&triangle.b).coords;
+                let c = position.transform_point(&triangle.c).coords;
+                if (b - a).cross(&(c - a)).y < 0.0 {
+                    self.triangles.push([a, c, b]);
+                } else {
+                    self.triangles.push([a, b, c]);
+                }
+            }
+            return;
+        } else {
+            return;
+        };
+
+        let position = collider.position();
+        for [a, b, c] in indices {
+            self.triangles.push([
+                position.transform_point(&vertices[a as usize]).coords,
+                position.transform_point(&vertices[b as usize]).coords,
+                position.transform_point(&vertices[c as usize]).coords,
+            ]);
+        }
+    }
+
+    /// Adds every collider attached to a static rigid body.
+    pub fn add_static_colliders(&mut self, physics: &Physics) {
+        for collider in physics.colliders.iter() {
+            let is_static = collider
+                .parent()
+                .and_then(|body| physics.bodies.native_ref(body))
+                .map(|body| body.is_static())
+                .unwrap_or_default();
+            if is_static {
+                self.add_collider(collider);
+            }
+        }
+    }
+
+    /// Builds navigation mesh from collected geometry.
+    pub fn generate(&self) -> Navmesh {
+        let (triangles, vertices) = self.generate_raw();
+        Navmesh::new(&triangles, &vertices)
+    }
+
+    /// Builds triangles and vertices of navigation mesh from collected geometry without
+    /// creating [`Navmesh`].
+    pub fn generate_raw(&self) -> (Vec<TriangleDefinition>, Vec<Vector3<f32>>) {
+        let settings = &self.settings;
+        // Written this way to reject NaN too.
+        if !(settings.cell_size > 0.0 && settings.cell_height > 0.0) {
+            return Default::default();
+        }
+
+        let mut heightfield = match Heightfield::new(settings, &self.triangles) {
+            Some(heightfield) => heightfield,
+            None => return Default::default(),
+        };
+
+        let walkable_normal_y = settings.max_slope.cos();
+        for triangle in self.triangles.iter() {
+            let normal = (triangle[1] - triangle[0]).cross(&(triangle[2] - triangle[0]));
+            let walkable = match normal.try_normalize(f32::EPSILON) {
+                Some(normal) => normal.y >= walkable_normal_y,
+                None => false,
+            };
+            heightfield.rasterize(triangle, walkable);
+        }
+
+        heightfield.filter_low_hanging_obstacles();
+        heightfield.filter_ledges();
+        heightfield.filter_low_height_spans();
+
+        let mut open = OpenHeightfield::new(&heightfield);
+        open.erode(heightfield.radius);
+        open.remove_small_regions(heightfield.min_region);
+        open.triangulate(&heightfield)
+    }
+}
+
+// A solid part of a column of the height field, `min` and `max` are indices of the lowest and
+// the highest voxels of the span.
+#[derive(Copy, Clone, Debug)]
+struct Span {
+    min: i32,
+    max: i32,
+    walkable: bool,
+}
+
+struct Heightfield {
+    origin: Vector3<f32>,
+    width: usize,
+    length: usize,
+    cell_size: f32,
+    cell_height: f32,
+    max_edge: usize,
+    min_region: usize,
+    // Agent parameters in voxels.
+    climb: i32,
+    height: i32,
+    radius: u32,
+    columns: Vec<Vec<Span>>,
+}
+
+// Directions of neighbour columns: -X, +Z, +X, -Z.
+const OFFSETS: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+impl Heightfield {
+    fn new(settings: &NavmeshSettings, triangles: &[[Vector3<f32>; 3]]) -> Option<Self> {
+        let mut points = triangles.iter().flat_map(|t| t.iter());
+        let first = *points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), p| (min.inf(p), max.sup(p)));
+
+        let width = ((max.x - min.x) / settings.cell_size).ceil().max(1.0) as usize;
+        let length = ((max.z - min.z) / settings.cell_size).ceil().max(1.0) as usize;
+
+        Some(Self {
+            origin: min,
+            width,
+            length,
+            cell_size: settings.cell_size,
+            cell_height: settings.cell_height,
+            max_edge: ((settings.max_edge_length / settings.cell_size).round() as usize).max(1),
+            min_region: (settings.min_region_area / (settings.cell_size * settings.cell_size))
+                .ceil() as usize,
+            climb: (settings.max_climb / settings.cell_height).floor() as i32,
+            height: (settings.agent_height / settings.cell_height).ceil() as i32,
+            radius: (settings.agent_radius / settings.cell_size).ceil() as u32,
+            columns: vec![Default::default(); width * length],
+        })
+    }
+
+    fn neighbour(&self, x: usize, z: usize, dir: usize) -> Option<usize> {
+        let (dx, dz) = OFFSETS[dir];
+        let nx = x as isize + dx;
+        let nz = z as isize + dz;
+        if nx >= 0 && nz >= 0 && (nx as usize) < self.width && (nz as usize) < self.length {
+            Some(nz as usize * self.width + nx as usize)
+        } else {
+            None
+        }
+    }
+
+    fn voxel(&self, y: f32) -> i32 {
+        // Small bias keeps surfaces that lie exactly on voxel boundaries in the right voxel.
+        ((y - self.origin.y) / self.cell_height + 1.0e-3).floor() as i32
+    }
+
+    fn cell_range(&self, min: f32, max: f32, origin: f32, count: usize) -> (usize, usize) {
+        let first = (((min - origin) / self.cell_size).floor().max(0.0) as usize).min(count - 1);
+        // Cells are half-open, geometry that only touches far side of a cell belongs to the next
+        // one.
+        let last = (((max - origin) / self.cell_size - 1.0e-4).ceil() - 1.0).max(0.0) as usize;
+        (first, last.min(count - 1).max(first))
+    }
+
+    fn rasterize(&mut self, triangle: &[Vector3<f32>; 3], walkable: bool) {
+        let min = triangle[0].inf(&triangle[1]).inf(&triangle[2]);
+        let max = triangle[0].sup(&triangle[1]).sup(&triangle[2]);
+        let (x0, x1) = self.cell_range(min.x, max.x, self.origin.x, self.width);
+        let (z0, z1) = self.cell_range(min.z, max.z, self.origin.z, self.length);
+
+        for z in z0..=z1 {
+            let row_min = self.origin.z + z as f32 * self.cell_size;
+            let row = clip_polygon(triangle, 2, row_min, row_min + self.cell_size);
+            if row.is_empty() {
+                continue;
+            }
+            for x in x0..=x1 {
+                let cell_min = self.origin.x + x as f32 * self.cell_size;
+                let cell = clip_polygon(&row, 0, cell_min, cell_min + self.cell_size);
+                if cell.is_empty() {
+                    continue;
+                }
+                let (y_min, y_max) = cell.iter().fold((f32::MAX, f32::MIN), |(min, max), p| {
+                    (min.min(p.y), max.max(p.y))
+                });
+                let span = Span {
+                    min: self.voxel(y_min),
+                    max: self.voxel(y_max),
+                    walkable,
+                };
+                let climb = self.climb;
+                add_span(&mut self.columns[z * self.width + x], span, climb);
+            }
+        }
+    }
+
+    // Allows agents to step over small obstacles like curbs or stairs.
+    fn filter_low_hanging_obstacles(&mut self) {
+        for column in self.columns.iter_mut() {
+            let mut previous: Option<Span> = None;
+            for span in column.iter_mut() {
+                let original = *span;
+                if let Some(previous) = previous {
+                    if !span.walkable && previous.walkable && span.max - previous.max <= self.climb
+                    {
+                        span.walkable = true;
+                    }
+                }
+                previous = Some(original);
+            }
+        }
+    }
+
+    // Removes walkable flag from spans at ledges or on too steep slopes.
+    fn filter_ledges(&mut self) {
+        for z in 0..self.length {
+            for x in 0..self.width {
+                let index = z * self.width + x;
+                for i in 0..self.columns[index].len() {
+                    let span = self.columns[index][i];
+                    if !span.walkable {
+                        continue;
+                    }
+                    let bottom = span.max;
+                    let top = ceiling(&self.columns[index], i);
+
+                    let mut min_drop = i32::MAX;
+                    let (mut accessible_min, mut accessible_max) = (bottom, bottom);
+                    for dir in 0..4 {
+                        let neighbour = match self.neighbour(x, z, dir) {
+                            Some(neighbour) => &self.columns[neighbour],
+                            None => {
+                                min_drop = min_drop.min(-self.climb - bottom);
+                                continue;
+                            }
+                        };
+
+                        // Free space below the lowest span of neighbour column.
+                        let n_bottom = -self.climb;
+                        let n_top = neighbour.first().map_or(i32::MAX, |s| s.min);
+                        if top.min(n_top) - bottom.max(n_bottom) > self.height {
+                            min_drop = min_drop.min(n_bottom - bottom);
+                        }
+
+                        for j in 0..neighbour.len() {
+                            let n_bottom = neighbour[j].max;
+                            let n_top = ceiling(neighbour, j);
+                            if top.min(n_top) - bottom.max(n_bottom) > self.height {
+                                min_drop = min_drop.min(n_bottom - bottom);
+                                if (n_bottom - bottom).abs() <= self.climb {
+                                    accessible_min = accessible_min.min(n_bottom);
+                                    accessible_max = accessible_max.max(n_bottom);
+                                }
+                            }
+                        }
+                    }
+
+                    if min_drop < -self.climb || accessible_max - accessible_min > self.climb {
+                        self.columns[index][i].walkable = false;
+                    }
+                }
+            }
+        }
+    }
+
+    // Removes walkable flag from spans that do not have enough free space above them.
+    fn filter_low_height_spans(&mut self) {
+        for column in self.columns.iter_mut() {
+            for i in 0..column.len() {
+                if ceiling(column, i) - column[i].max < self.height {
+                    column[i].walkable = false;
+                }
+            }
+        }
+    }
+}
+
+fn ceiling(column: &[Span], i: usize) -> i32 {
+    column.get(i + 1).map_or(i32::MAX, |s| s.min)
+}
+
+// Inserts a span into sorted column merging it with overlapping spans. Merged span is walkable
+// if top of a walkable source span is close enough to the top of the merged span.
+fn add_span(column: &mut Vec<Span>, mut span: Span, merge_threshold: i32) {
+    let mut i = 0;
+    while i < column.len() {
+        let current = column[i];
+        if current.min > span.max {
+            break;
+        }
+        if current.max < span.min {
+            i += 1;
+            continue;
+        }
+        let max = span.max.max(current.max);
+        span.walkable = (span.walkable && max - span.max <= merge_threshold)
+            || (current.walkable && max - current.max <= merge_threshold);
+        span.min = span.min.min(current.min);
+        span.max = max;
+        column.remove(i);
+    }
+    column.insert(i, span);
+}
+
+// Clips convex polygon by two parallel planes perpendicular to given axis (Sutherland-Hodgman).
+fn clip_polygon(polygon: &[Vector3<f32>], axis: usize, min: f32, max: f32) -> Vec<Vector3<f32>> {
+    let clip = |polygon: &[Vector3<f32>], distance: &dyn Fn(&Vector3<f32>) -> f32| {
+        let mut result = Vec::with_capacity(polygon.len() + 2);
+        for (i, a) in polygon.iter().enumerate() {
+            let b = &polygon[(i + 1) % polygon.len()];
+            let (da, db) = (distance(a), distance(b));
+            if da >= 0.0 {
+                result.push(*a);
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                result.push(a + (b - a) * (da / (da - db)));
+            }
+        }
+        result
+    };
+    let lower = clip(polygon, &|p| p[axis] - min);
+    if lower.is_empty() {
+        return lower;
+    }
+    clip(&lower, &|p| max - p[axis])
+}
+
+// A walkable surface with enough free space above it.
+struct OpenCell {
+    x: usize,
+    z: usize,
+    floor: i32,
+    ceiling: i32,
+    // Indices of connected cells in directions defined by `OFFSETS`.
+    connections: [Option<usize>; 4],
+}
+
+struct OpenHeightfield {
+    cells: Vec<OpenCell>,
+}
+
+impl OpenHeightfield {
+    fn new(heightfield: &Heightfield) -> Self {
+        let mut cells = Vec::new();
+        // Range of cells of each column.
+        let mut columns = Vec::with_capacity(heightfield.columns.len());
+        for z in 0..heightfield.length {
+            for x in 0..heightfield.width {
+                let column = &heightfield.columns[z * heightfield.width + x];
+                let start = cells.len();
+                for (i, span) in column.iter().enumerate() {
+                    if span.walkable {
+                        cells.push(OpenCell {
+                            x,
+                            z,
+                            floor: span.max,
+                            ceiling: ceiling(column, i),
+                            connections: Default::default(),
+                        });
+                    }
+                }
+                columns.push(start..cells.len());
+            }
+        }
+
+        for i in 0..cells.len() {
+            for dir in 0..4 {
+                let neighbour = match heightfield.neighbour(cells[i].x, cells[i].z, dir) {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+                let cell = &cells[i];
+                let connection = columns[neighbour].clone().find(|&n| {
+                    let other = &cells[n];
+                    (other.floor - cell.floor).abs() <= heightfield.climb
+                        && cell.ceiling.min(other.ceiling) - cell.floor.max(other.floor)
+                            >= heightfield.height
+                });
+                cells[i].connections[dir] = connection;
+            }
+        }
+
+        Self { cells }
+    }
+
+    // Removes cells that are closer than given radius (in cells) to borders of walkable area.
+    fn erode(&mut self, radius: u32) {
+        if radius == 0 {
+            return;
+        }
+
+        // Chamfer distance transform, orthogonal step costs 2 and diagonal step costs 3.
+        let mut distance = self
+            .cells
+            .iter()
+            .map(|c| {
+                if c.connections.iter().all(|c| c.is_some()) {
+                    u32::MAX
+                } else {
+                    0
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let relax = |distance: &mut Vec<u32>, i: usize, dir: usize, diagonal_dir: usize| {
+            if let Some(n) = self.cells[i].connections[dir] {
+                distance[i] = distance[i].min(distance[n].saturating_add(2));
+                if let Some(d) = self.cells[n].connections[diagonal_dir] {
+                    distance[i] = distance[i].min(distance[d].saturating_add(3));
+                }
+            }
+        };
+        for i in 0..self.cells.len() {
+            relax(&mut distance, i, 0, 3);
+            relax(&mut distance, i, 3, 2);
+        }
+        for i in (0..self.cells.len()).rev() {
+            relax(&mut distance, i, 2, 1);
+            relax(&mut distance, i, 1, 0);
+        }
+
+        let threshold = radius * 2;
+        self.retain(&distance.iter().map(|d| *d >= threshold).collect::<Vec<_>>());
+    }
+
+    // Removes isolated regions that have less cells than given amount. Interiors of closed
+    // meshes are treated as hollow by voxelization, this filter removes them in most cases.
+    fn remove_small_regions(&mut self, min_cells: usize) {
+        let mut keep = vec![false; self.cells.len()];
+        let mut visited = vec![false; self.cells.len()];
+        let mut region = Vec::new();
+        for start in 0..self.cells.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            region.clear();
+            region.push(start);
+            let mut i = 0;
+            while i < region.len() {
+                for connection in self.cells[region[i]].connections.iter() {
+                    if let Some(n) = *connection {
+                        if !visited[n] {
+                            visited[n] = true;
+                            region.push(n);
+                        }
+                    }
+                }
+                i += 1;
+            }
+            if region.len() >= min_cells {
+                for &cell in region.iter() {
+                    keep[cell] = true;
+                }
+            }
+        }
+        self.retain(&keep);
+    }
+
+    fn retain(&mut self, keep: &[bool]) {
+        let mut remap = vec![None; self.cells.len()];
+        let mut count = 0;
+        for (i, keep) in keep.iter().enumerate() {
+            if *keep {
+                remap[i] = Some(count);
+                count += 1;
+            }
+        }
+
+        let cells = std::mem::take(&mut self.cells);
+        self.cells = cells
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| remap[*i].is_some())
+            .map(|(_, mut cell)| {
+                for connection in cell.connections.iter_mut() {
+                    *connection = connection.and_then(|c| remap[c]);
+                }
+                cell
+            })
+            .collect();
+    }
+
+    // Checks whether floor of a cell at given offset from the origin of a rectangle lies on a
+    // plane defined by origin floor and slopes.
+    fn fits_plane(&self, origin: usize, cell: usize, slope_x: f32, slope_z: f32) -> bool {
+        let (o, c) = (&self.cells[origin], &self.cells[cell]);
+        let expected = o.floor as f32 + slope_x * (c.x - o.x) as f32 + slope_z * (c.z - o.z) as f32;
+        (c.floor as f32 - expected).abs() <= 1.0
+    }
+
+    // Greedily merges cells into rectangles with planar floor. Returns cells of each rectangle
+    // in rows.
+    fn build_rectangles(&self, max_edge: usize) -> Vec<Vec<Vec<usize>>> {
+        let mut assigned = vec![false; self.cells.len()];
+        let mut rectangles = Vec::new();
+        for start in 0..self.cells.len() {
+            if assigned[start] {
+                continue;
+            }
+
+            // Grow first row along +X.
+            let mut first_row = vec![start];
+            while first_row.len() < max_edge {
+                let next = match self.cells[*first_row.last().unwrap()].connections[2] {
+                    Some(next) if !assigned[next] => next,
+                    _ => break,
+                };
+                let slope_x = (self.cells[next].floor - self.cells[start].floor) as f32
+                    / first_row.len() as f32;
+                if !first_row
+                    .iter()
+                    .all(|&c| self.fits_plane(start, c, slope_x, 0.0))
+                {
+                    break;
+                }
+                first_row.push(next);
+            }
+            let slope_x = if first_row.len() > 1 {
+                (self.cells[*first_row.last().unwrap()].floor - self.cells[start].floor) as f32
+                    / (first_row.len() - 1) as f32
+            } else {
+                0.0
+            };
+
+            // Then add rows along +Z while they are complete and lie on the same plane.
+            let mut rows = vec![first_row];
+            while rows.len() < max_edge {
+                let last = rows.last().unwrap();
+                let mut row: Vec<usize> = Vec::with_capacity(last.len());
+                for &cell in last.iter() {
+                    match self.cells[cell].connections[1] {
+                        Some(next) if !assigned[next] => {
+                            // Row must stay contiguous.
+                            if let Some(&prev) = row.last() {
+                                if self.cells[prev].connections[2] != Some(next) {
+                                    break;
+                                }
+                            }
+                            row.push(next)
+                        }
+                        _ => break,
+                    }
+                }
+                if row.len() != last.len() {
+                    break;
+                }
+                let slope_z =
+                    (self.cells[row[0]].floor - self.cells[start].floor) as f32 / rows.len() as f32;
+                if !rows
+                    .iter()
+                    .chain(std::iter::once(&row))
+                    .flatten()
+                    .all(|&c| self.fits_plane(start, c, slope_x, slope_z))
+                {
+                    break;
+                }
+                rows.push(row);
+            }
+
+            for &cell in rows.iter().flatten() {
+                assigned[cell] = true;
+            }
+            rectangles.push(rows);
+        }
+        rectangles
+    }
+
+    fn triangulate(
+        &self,
+        heightfield: &Heightfield,
+    ) -> (Vec<TriangleDefinition>, Vec<Vector3<f32>>) {
+        // Every cell has four corner slots: (x, z), (x + 1, z), (x + 1, z + 1), (x, z + 1).
+        // Slots of connected cells that lie in the same point are merged into one vertex.
+        let mut slots = DisjointSet::new(self.cells.len() * 4);
+        for (i, cell) in self.cells.iter().enumerate() {
+            if let Some(n) = cell.connections[2] {
+                slots.union(i * 4 + 1, n * 4);
+                slots.union(i * 4 + 2, n * 4 + 3);
+            }
+            if let Some(n) = cell.connections[1] {
+                slots.union(i * 4 + 3, n * 4);
+                slots.union(i * 4 + 2, n * 4 + 1);
+            }
+        }
+
+        let rectangles = self.build_rectangles(heightfield.max_edge);
+
+        // Only corners of rectangles become vertices, including corners of neighbour
+        // rectangles that lie on edges of a rectangle, this way there are no T-junctions.
+        let mut is_corner = vec![false; slots.len()];
+        for rows in rectangles.iter() {
+            for slot in rectangle_corners(rows).iter() {
+                is_corner[slots.find(*slot)] = true;
+            }
+        }
+
+        // Height of a vertex is an average height of floors of all cells around it.
+        let mut heights = vec![(0.0, 0u32); slots.len()];
+        for slot in 0..slots.len() {
+            let root = slots.find(slot);
+            heights[root].0 += self.cells[slot / 4].floor as f32;
+            heights[root].1 += 1;
+        }
+
+        let mut vertex_indices = vec![None; slots.len()];
+        let mut vertices = Vec::new();
+        let mut vertex = |slot: usize, vertices: &mut Vec<Vector3<f32>>| {
+            let root = slots.find(slot);
+            *vertex_indices[root].get_or_insert_with(|| {
+                let cell = &self.cells[slot / 4];
+                let (dx, dz) = [(0, 0), (1, 0), (1, 1), (0, 1)][slot % 4];
+                let (sum, count) = heights[root];
+                vertices.push(Vector3::new(
+                    heightfield.origin.x + (cell.x + dx) as f32 * heightfield.cell_size,
+                    heightfield.origin.y + sum / count as f32 * heightfield.cell_height,
+                    heightfield.origin.z + (cell.z + dz) as f32 * heightfield.cell_size,
+                ));
+                vertices.len() as u32 - 1
+            })
+        };
+
+        let mut triangles = Vec::new();
+        for rows in rectangles.iter() {
+            let corners = rectangle_corners(rows);
+            let mut polygon = Vec::new();
+            for slot in rectangle_perimeter(rows) {
+                if corners.contains(&slot) || is_corner[slots.find(slot)] {
+                    let index = vertex(slot, &mut vertices);
+                    if polygon.last() != Some(&index) {
+                        polygon.push(index);
+                    }
+                }
+            }
+
+            // Perimeter goes clockwise when looking from above, so triangles are emitted in
+            // reverse order to make them face up.
+            if polygon.len() == 4 {
+                triangles.push(TriangleDefinition([polygon[0], polygon[2], polygon[1]]));
+                triangles.push(TriangleDefinition([polygon[0], polygon[3], polygon[2]]));
+            } else {
+                let center = polygon
+                    .iter()
+                    .map(|i| vertices[*i as usize])
+                    .sum::<Vector3<f32>>()
+                    .scale(1.0 / polygon.len() as f32);
+                let center_index = vertices.len() as u32;
+                vertices.push(center);
+                for i in 0..polygon.len() {
+                    let next = polygon[(i + 1) % polygon.len()];
+                    triangles.push(TriangleDefinition([center_index, next, polygon[i]]));
+                }
+            }
+        }
+
+        (triangles, vertices)
+    }
+}
+
+fn rectangle_corners(rows: &[Vec<usize>]) -> [usize; 4] {
+    let (first, last) = (&rows[0], rows.last().unwrap());
+    [
+        first[0] * 4,
+        *first.last().unwrap() * 4 + 1,
+        *last.last().unwrap() * 4 + 2,
+        last[0] * 4 + 3,
+    ]
+}
+
+// Returns corner slots of every grid point on perimeter of a rectangle.
+fn rectangle_perimeter(rows: &[Vec<usize>]) -> Vec<usize> {
+    let (first, last) = (&rows[0], rows.last().unwrap());
+    let mut perimeter = Vec::with_capacity(2 * (first.len() + rows.len()));
+    perimeter.extend(first.iter().map(|c| c * 4));
+    perimeter.extend(rows.iter().map(|row| *row.last().unwrap() * 4 + 1));
+    perimeter.push(*last.last().unwrap() * 4 + 2);
+    perimeter.extend(last.iter().rev().map(|c| c * 4 + 3));
+    perimeter.extend(rows[1..].iter().rev().map(|row| row[0] * 4));
+    perimeter
+}
+
+struct DisjointSet {
+    parents: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        Self {
+            parents: (0..size).collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    fn find(&self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            i = self.parents[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        // Smaller index always becomes the root to keep output independent of merge order.
+        if a < b {
+            self.parents[b] = a;
+        } else {
+            self.parents[a] = b;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{algebra::Vector3, math::TriangleDefinition},
+        physics::{dynamics::RigidBodyBuilder, geometry::ColliderBuilder, parry::shape::Cuboid},
+        scene::{base::BaseBuilder, node::Node, physics::Physics, terrain::TerrainBuilder},
+        utils::{
+            astar::PathKind,
+            navmesh::generator::{NavmeshGenerator, NavmeshSettings},
+        },
+    };
+
+    fn settings() -> NavmeshSettings {
+        NavmeshSettings {
+            cell_size: 0.25,
+            cell_height: 0.1,
+            agent_radius: 0.5,
+            agent_height: 1.8,
+            max_climb: 0.4,
+            max_slope: 45.0f32.to_radians(),
+            max_edge_length: 4.0,
+            min_region_area: 2.0,
+        }
+    }
+
+    // Adds rectangle facing up, its height changes from `min.y` to `max.y` along Z axis.
+    fn add_quad(generator: &mut NavmeshGenerator, min: Vector3<f32>, max: Vector3<f32>) {
+        let p00 = Vector3::new(min.x, min.y, min.z);
+        let p01 = Vector3::new(min.x, max.y, max.z);
+        let p11 = Vector3::new(max.x, max.y, max.z);
+        let p10 = Vector3::new(max.x, min.y, min.z);
+        generator.add_triangle([p00, p01, p11]);
+        generator.add_triangle([p00, p11, p10]);
+    }
+
+    fn add_box(generator: &mut NavmeshGenerator, min: Vector3<f32>, max: Vector3<f32>) {
+        let (vertices, indices) = Cuboid::new((max - min).scale(0.5)).to_trimesh();
+        let center = (min + max).scale(0.5);
+        for [a, b, c] in indices {
+            generator.add_triangle([
+                vertices[a as usize].coords + center,
+                vertices[b as usize].coords + center,
+                vertices[c as usize].coords + center,
+            ]);
+        }
+    }
+
+    fn area(triangles: &[TriangleDefinition], vertices: &[Vector3<f32>]) -> f32 {
+        triangles
+            .iter()
+            .map(|t| {
+                let (a, b, c) = (
+                    vertices[t[0] as usize],
+                    vertices[t[1] as usize],
+                    vertices[t[2] as usize],
+                );
+                (b - a).cross(&(c - a)).norm() * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_flat_plane() {
+        let mut generator = NavmeshGenerator::new(settings());
+        add_quad(
+            &mut generator,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(10.0, 0.0, 10.0),
+        );
+
+        let (triangles, vertices) = generator.generate_raw();
+        assert!(!triangles.is_empty());
+        // Walkable area is shrunk by agent radius.
+        for v in vertices.iter() {
+            assert!(v.y.abs() < 1.0e-4);
+            assert!((0.5 - 1.0e-4..=9.5 + 1.0e-4).contains(&v.x));
+            assert!((0.5 - 1.0e-4..=9.5 + 1.0e-4).contains(&v.z));
+        }
+        assert!((area(&triangles, &vertices) - 81.0).abs() < 1.0e-2);
+        // Every triangle faces up.
+        for t in triangles.iter() {
+            let (a, b, c) = (
+                vertices[t[0] as usize],
+                vertices[t[1] as usize],
+                vertices[t[2] as usize],
+            );
+            assert!((b - a).cross(&(c - a)).y > 0.0);
+        }
+        // Edges of polygons are limited.
+        assert!(triangles.len() >= 2 * 9);
+
+        assert!(NavmeshGenerator::new(settings())
+            .generate_raw()
+            .0
+            .is_empty());
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        for (cell_size, cell_height) in [(0.0, 0.1), (0.25, -1.0), (f32::NAN, 0.1)] {
+            let mut generator = NavmeshGenerator::new(NavmeshSettings {
+                cell_size,
+                cell_height,
+                ..settings()
+            });
+            add_quad(
+                &mut generator,
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(10.0, 0.0, 10.0),
+            );
+            let (triangles, vertices) = generator.generate_raw();
+            assert!(triangles.is_empty() && vertices.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_obstacles() {
+        let mut generator = NavmeshGenerator::new(settings());
+        add_quad(
+            &mut generator,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(20.0, 0.0, 10.0),
+        );
+        // Small tall box, its top and its hollow interior are too small to be walkable.
+        add_box(
+            &mut generator,
+            Vector3::new(4.0, 0.0, 4.0),
+            Vector3::new(6.0, 2.0, 6.0),
+        );
+        // Wide low box, agents can't fit inside, but its top is walkable.
+        add_box(
+            &mut generator,
+            Vector3::new(12.0, 0.0, 2.0),
+            Vector3::new(18.0, 1.0, 8.0),
+        );
+
+        let (triangles, vertices) = generator.generate_raw();
+        // Corners of eroded area are rounded, the distance is approximated by cells.
+        let distance = |v: f32, min: f32, max: f32| (min - v).max(v - max).max(0.0);
+        for v in vertices.iter() {
+            if v.y < 0.5 {
+                assert!(v.y.abs() < 1.0e-4);
+                assert!(distance(v.x, 4.0, 6.0).hypot(distance(v.z, 4.0, 6.0)) > 0.25);
+                assert!(distance(v.x, 12.0, 18.0).hypot(distance(v.z, 2.0, 8.0)) > 0.25);
+            } else {
+                assert!((v.y - 1.0).abs() < 0.1);
+                assert!((12.5 - 1.0e-3..=17.75 + 1.0e-3).contains(&v.x));
+                assert!((2.5 - 1.0e-3..=7.75 + 1.0e-3).contains(&v.z));
+            }
+        }
+        // Ground area without footprints of boxes expanded by agent radius and top of the
+        // second box shrunk by agent radius, walls may shift the borders by one cell.
+        let area = area(&triangles, &vertices);
+        assert!(area > 171.0 - 3.25 * 3.25 - 7.25 * 7.25 + 4.5 * 4.5 - 1.0e-2);
+        assert!(area < 171.0 - 2.0 * 2.0 - 6.0 * 6.0 + 5.0 * 5.0);
+
+        let mut navmesh = generator.generate();
+        let from = navmesh.query_closest(Vector3::new(1.0, 0.0, 1.0)).unwrap();
+        let to = navmesh.query_closest(Vector3::new(19.0, 0.0, 9.0)).unwrap();
+        let mut path = Vec::new();
+        assert!(matches!(
+            navmesh.build_path(from, to, &mut path),
+            Ok(PathKind::Full)
+        ));
+        let on_box = navmesh.query_closest(Vector3::new(15.0, 1.0, 5.0)).unwrap();
+        assert!(matches!(
+            navmesh.build_path(from, on_box, &mut path),
+            Ok(PathKind::Partial)
+        ));
+    }
+
+    #[test]
+    fn test_slopes_and_steps() {
+        let mut generator = NavmeshGenerator::new(settings());
+        let gentle = 20.0f32.to_radians().tan() * 10.0;
+        let steep = 60.0f32.to_radians().tan() * 10.0;
+        add_quad(
+            &mut generator,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(4.0, gentle, 10.0),
+        );
+        add_quad(
+            &mut generator,
+            Vector3::new(6.0, 0.0, 0.0),
+            Vector3::new(10.0, steep, 10.0),
+        );
+        // Stairs with small and big steps.
+        for i in 0..5 {
+            let x = 12.0 + i as f32;
+            let y = 0.2 * i as f32;
+            add_quad(
+                &mut generator,
+                Vector3::new(x, y, 0.0),
+                Vector3::new(x + 1.0, y, 10.0),
+            );
+        }
+        for i in 0..3 {
+            let x = 12.0 + 3.0 * i as f32;
+            let y = 0.6 * i as f32;
+            add_quad(
+                &mut generator,
+                Vector3::new(x, y, 20.0),
+                Vector3::new(x + 3.0, y, 30.0),
+            );
+        }
+
+        let mut navmesh = generator.generate();
+        let vertices = navmesh
+            .vertices()
+            .iter()
+            .map(|v| v.position)
+            .collect::<Vec<_>>();
+
+        // Gentle slope is walkable and follows the surface, steep one is not.
+        assert!(vertices.iter().any(|v| v.x < 4.0 && v.y > gentle - 1.0));
+        for v in vertices.iter().filter(|v| v.x < 5.0) {
+            assert!((v.y - v.z / 10.0 * gentle).abs() < 0.25);
+        }
+        assert!(!vertices.iter().any(|v| v.x > 5.0 && v.x < 11.0));
+
+        // Small steps can be climbed, big steps are separate islands.
+        let mut path = Vec::new();
+        let from = navmesh.query_closest(Vector3::new(12.5, 0.0, 5.0)).unwrap();
+        let to = navmesh.query_closest(Vector3::new(16.5, 0.8, 5.0)).unwrap();
+        assert!(matches!(
+            navmesh.build_path(from, to, &mut path),
+            Ok(PathKind::Full)
+        ));
+        let from = navmesh
+            .query_closest(Vector3::new(12.5, 0.0, 25.0))
+            .unwrap();
+        let to = navmesh
+            .query_closest(Vector3::new(16.5, 2.4, 25.0))
+            .unwrap();
+        assert!(matches!(
+            navmesh.build_path(from, to, &mut path),
+            Ok(PathKind::Partial)
+        ));
+    }
+
+    #[test]
+    fn test_low_ceiling() {
+        let mut generator = NavmeshGenerator::new(settings());
+        add_quad(
+            &mut generator,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(10.0, 0.0, 10.0),
+        );
+        // Agents can't fit under the slab, but can walk on top of it.
+        add_box(
+            &mut generator,
+            Vector3::new(-1.0, 1.0, -1.0),
+            Vector3::new(5.0, 1.2, 11.0),
+        );
+
+        let (_, vertices) = generator.generate_raw();
+        assert!(!vertices.iter().any(|v| v.y < 0.5 && v.x < 5.5 - 1.0e-3));
+        assert!(vertices.iter().any(|v| v.y < 0.5));
+        assert!(vertices.iter().any(|v| (v.y - 1.2).abs() < 0.1));
+    }
+
+    #[test]
+    fn test_determinism() {
+        let mut generator = NavmeshGenerator::new(settings());
+        add_quad(
+            &mut generator,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(20.0, 0.0, 20.0),
+        );
+        add_box(
+            &mut generator,
+            Vector3::new(3.0, 0.0, 7.0),
+            Vector3::new(8.0, 1.0, 9.0),
+        );
+        add_quad(
+            &mut generator,
+            Vector3::new(10.0, 0.0, 10.0),
+            Vector3::new(20.0, 3.0, 20.0),
+        );
+
+        let first = generator.generate_raw();
+        let second = generator.clone().generate_raw();
+        assert!(!first.0.is_empty());
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_terrain_and_colliders() {
+        let mut terrain = match TerrainBuilder::new(BaseBuilder::new())
+            .with_width(15.0)
+            .with_length(15.0)
+            .with_width_chunks(1)
+            .with_length_chunks(1)
+            .with_height_map_resolution(1.0)
+            .build_node()
+        {
+            Node::Terrain(terrain) => terrain,
+            _ => unreachable!(),
+        };
+        for z in 7..9 {
+            for x in 7..9 {
+                terrain.chunks_mut()[0].set_hole(x, z, true);
+            }
+        }
+
+        let mut generator = NavmeshGenerator::new(settings());
+        generator.add_terrain(&terrain);
+        let (triangles, vertices) = generator.generate_raw();
+        assert!(!triangles.is_empty());
+        let distance = |v: f32| (7.0 - v).max(v - 9.0).max(0.0);
+        assert!(vertices
+            .iter()
+            .all(|v| distance(v.x).hypot(distance(v.z)) > 0.25));
+
+        // Only colliders of static bodies are used.
+        let mut physics = Physics::default();
+        let ground = physics.add_body(RigidBodyBuilder::new_static().build());
+        physics.add_collider(ColliderBuilder::cuboid(5.0, 0.5, 5.0).build(), &ground);
+        let dynamic = physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .translation(Vector3::new(50.0, 0.0, 0.0))
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::cuboid(5.0, 0.5, 5.0).build(), &dynamic);
+
+        let mut generator = NavmeshGenerator::new(settings());
+        generator.add_static_colliders(&physics);
+        let (triangles, vertices) = generator.generate_raw();
+        assert!(!triangles.is_empty());
+        for v in vertices.iter() {
+            assert!((v.y - 0.5).abs() < 0.1);
+            assert!((-4.5 - 1.0e-3..=4.5 + 1.0e-3).contains(&v.x));
+        }
+    }
+}
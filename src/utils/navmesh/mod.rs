@@ -0,0 +1,1794 @@
+//! Contains all structures and methods to create and manage navigation meshes (navmesh).
+//!
+//! Navigation mesh is a set of convex polygons which is used for path finding in complex
+//! environment.
+
+#![warn(missing_docs)]
+
+use crate::scene::mesh::buffer::{VertexAttributeUsage, VertexReadTrait};
+use crate::{
+    core::{
+        algebra::{Point3, Vector3},
+        arrayvec::ArrayVec,
+        math::{self, aabb::AxisAlignedBoundingBox, ray::Ray, TriangleDefinition},
+        octree::{Octree, OctreeNode},
+        pool::{Handle, Pool},
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::mesh::Mesh,
+    utils::{
+        astar::{PathCostProvider, PathError, PathFinder, PathKind, PathVertex},
+        raw_mesh::{RawMeshBuilder, RawVertex},
+    },
+};
+use std::{
+    collections::HashSet,
+    hash::{Hash, Hasher},
+};
+
+pub mod crowd;
+pub mod generator;
+
+/// Area type of triangles that was not changed explicitly.
+pub const DEFAULT_AREA: u8 = 0;
+
+/// Minimal traversal cost multiplier of an area. Costs must be positive, otherwise path finder
+/// could not estimate remaining distance correctly.
+pub const MIN_AREA_COST: f32 = 0.01;
+
+/// A set of traversal cost multipliers for area types. Agents with different costs may prefer
+/// different routes, for example a car prefers roads while a soldier walks straight through
+/// grass. Cost of every area that was not set explicitly is `1.0`.
+#[derive(Clone, Debug, Default, PartialEq, Visit)]
+pub struct AreaCosts {
+    costs: Vec<f32>,
+}
+
+impl AreaCosts {
+    /// Creates new set of costs where every area has cost of `1.0`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets cost multiplier of given area. Cost is clamped to [`MIN_AREA_COST`], use
+    /// [`Self::exclude`] to make area impassable.
+    pub fn set_cost(&mut self, area: u8, cost: f32) {
+        let cost = cost.max(MIN_AREA_COST);
+        let area = area as usize;
+        if self.costs.len() <= area {
+            self.costs.resize(area + 1, 1.0);
+        }
+        self.costs[area] = cost;
+    }
+
+    /// Sets cost multiplier of given area and returns self.
+    pub fn with_cost(mut self, area: u8, cost: f32) -> Self {
+        self.set_cost(area, cost);
+        self
+    }
+
+    /// Makes given area impassable.
+    pub fn exclude(&mut self, area: u8) {
+        self.set_cost(area, f32::INFINITY);
+    }
+
+    /// Returns cost multiplier of given area, impassable areas have infinite cost.
+    pub fn cost(&self, area: u8) -> f32 {
+        self.costs.get(area as usize).cloned().unwrap_or(1.0)
+    }
+
+    /// Returns true if given area can be traversed.
+    pub fn is_passable(&self, area: u8) -> bool {
+        self.cost(area).is_finite()
+    }
+
+    /// Returns the lowest cost of all areas.
+    pub fn min_cost(&self) -> f32 {
+        self.costs.iter().fold(1.0, |min, cost| min.min(*cost))
+    }
+}
+
+/// Off-mesh link is an explicit connection between two points of navmesh, that can be used to
+/// describe jumps, ladders, teleports, etc. Both points are connected to closest triangles of
+/// navmesh, so links could connect separate islands.
+#[derive(Clone, Debug, Default, PartialEq, Visit)]
+pub struct OffMeshLink {
+    /// Start point of the link.
+    pub start: Vector3<f32>,
+    /// End point of the link.
+    pub end: Vector3<f32>,
+    /// If true, the link could be traversed from end to start as well.
+    pub bidirectional: bool,
+    /// Area type of the link, agents use their area costs to decide whether the link could be
+    /// used.
+    pub area: u8,
+}
+
+/// A region of navmesh that is temporarily blocked, paths never go through blocked regions.
+/// Blocked regions could be changed at any time without rebuilding navmesh, for example when a
+/// door is closed or a vehicle stops on a road.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct BlockedRegion {
+    bounds: AxisAlignedBoundingBox,
+}
+
+impl BlockedRegion {
+    /// Returns bounds of the region.
+    pub fn bounds(&self) -> &AxisAlignedBoundingBox {
+        &self.bounds
+    }
+
+    fn is_segment_blocked(&self, begin: Vector3<f32>, end: Vector3<f32>) -> bool {
+        self.bounds.is_contains_point(begin)
+            || self.bounds.is_contains_point(end)
+            || Ray::from_two_points(begin, end)
+                .aabb_intersection(&self.bounds)
+                .is_some()
+    }
+}
+
+/// See module docs.
+#[derive(Clone, Debug)]
+pub struct Navmesh {
+    octree: Octree,
+    triangles: Vec<TriangleDefinition>,
+    areas: Vec<u8>,
+    pathfinder: PathFinder,
+    query_buffer: Vec<u32>,
+    links: Vec<OffMeshLink>,
+    blocked_regions: Pool<BlockedRegion>,
+    base_vertex_count: usize,
+    // Runtime data, restored on load.
+    vertex_triangles: Vec<Vec<u32>>,
+    link_triangles: Vec<[Option<u32>; 2]>,
+    revision: u64,
+}
+
+impl Visit for Navmesh {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.pathfinder.visit("PathFinder", visitor)?;
+        self.triangles.visit("Triangles", visitor)?;
+        // Backward compatibility.
+        let _ = self.areas.visit("Areas", visitor);
+        let _ = self.links.visit("Links", visitor);
+        let _ = self.blocked_regions.visit("BlockedRegions", visitor);
+        let mut base_vertex_count = self.base_vertex_count as u32;
+        let has_base_vertex_count = base_vertex_count.visit("BaseVertexCount", visitor).is_ok();
+
+        // No need to save octree, we can restore it on load.
+        if visitor.is_reading() {
+            self.areas.resize(self.triangles.len(), DEFAULT_AREA);
+            self.base_vertex_count = if has_base_vertex_count {
+                base_vertex_count as usize
+            } else {
+                // Backward compatibility, every link adds two vertices after navmesh vertices.
+                self.pathfinder
+                    .vertices()
+                    .len()
+                    .saturating_sub(2 * self.links.len())
+            };
+            self.vertex_triangles = vertex_triangles(&self.triangles, self.base_vertex_count);
+
+            let vertices = self.pathfinder.vertices();
+            let raw_triangles = self
+                .triangles
+                .iter()
+                .map(|t| {
+                    [
+                        vertices[t[0] as usize].position,
+                        vertices[t[1] as usize].position,
+                        vertices[t[2] as usize].position,
+                    ]
+                })
+                .collect::<Vec<[Vector3<f32>; 3]>>();
+
+            self.octree = Octree::new(&raw_triangles, 32);
+            self.rebuild_links();
+        }
+
+        visitor.leave_region()
+    }
+}
+
+fn vertex_triangles(triangles: &[TriangleDefinition], vertex_count: usize) -> Vec<Vec<u32>> {
+    let mut vertex_triangles = vec![Vec::new(); vertex_count];
+    for (index, triangle) in triangles.iter().enumerate() {
+        for &vertex in triangle.0.iter() {
+            vertex_triangles[vertex as usize].push(index as u32);
+        }
+    }
+    vertex_triangles
+}
+
+// Twice signed area of a triangle projected on XZ plane, it is negative if `c` lies to the left
+// of `a -> b` direction.
+fn triangle_area_2d(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> f32 {
+    let ab = b - a;
+    let ac = c - a;
+    ac.x * ab.z - ab.x * ac.z
+}
+
+fn is_same_point_2d(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+    let d = a - b;
+    d.x * d.x + d.z * d.z < 1.0e-6
+}
+
+// "Simple stupid funnel algorithm" - funnel is narrowed by every next portal (left and right
+// points of an edge between triangles of a corridor) until its sides cross each other, then
+// the crossed side becomes new apex of the funnel. First and last portals must be degenerated
+// to begin and end points of the path.
+fn string_pull(portals: &[(Vector3<f32>, Vector3<f32>)]) -> Vec<Vector3<f32>> {
+    let mut points = vec![portals[0].0];
+    let (mut apex, mut left, mut right) = (portals[0].0, portals[0].0, portals[0].1);
+    let (mut left_index, mut right_index) = (0, 0);
+
+    let mut i = 1;
+    while i < portals.len() {
+        let (portal_left, portal_right) = portals[i];
+
+        // Narrow right side of the funnel.
+        if triangle_area_2d(apex, right, portal_right) <= 0.0 {
+            if is_same_point_2d(apex, right) || triangle_area_2d(apex, left, portal_right) > 0.0 {
+                right = portal_right;
+                right_index = i;
+            } else {
+                // Right side crosses left side, left point becomes new apex.
+                apex = left;
+                points.push(apex);
+                right = apex;
+                right_index = left_index;
+                i = left_index + 1;
+                continue;
+            }
+        }
+
+        // Narrow left side of the funnel.
+        if triangle_area_2d(apex, left, portal_left) >= 0.0 {
+            if is_same_point_2d(apex, left) || triangle_area_2d(apex, right, portal_left) < 0.0 {
+                left = portal_left;
+                left_index = i;
+            } else {
+                // Left side crosses right side, right point becomes new apex.
+                apex = right;
+                points.push(apex);
+                left = apex;
+                left_index = right_index;
+                i = right_index + 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    let end = portals[portals.len() - 1].0;
+    if points.last() != Some(&end) {
+        points.push(end);
+    }
+    points
+}
+
+// Calculates costs of links between vertices of navmesh for specific agent.
+struct NavmeshCosts<'a> {
+    navmesh: &'a Navmesh,
+    costs: &'a AreaCosts,
+}
+
+impl<'a> PathCostProvider for NavmeshCosts<'a> {
+    fn link_cost(
+        &self,
+        from: usize,
+        to: usize,
+        from_position: &Vector3<f32>,
+        to_position: &Vector3<f32>,
+    ) -> Option<f32> {
+        let navmesh = self.navmesh;
+        if navmesh.is_segment_blocked(*from_position, *to_position) {
+            return None;
+        }
+
+        let base = navmesh.base_vertex_count;
+        let multiplier = match (from.checked_sub(base), to.checked_sub(base)) {
+            // Off-mesh link itself.
+            (Some(link_vertex), Some(_)) => self.costs.cost(navmesh.links[link_vertex / 2].area),
+            // Connection between an end of off-mesh link and vertex of its triangle.
+            (Some(link_vertex), None) | (None, Some(link_vertex)) => {
+                match navmesh.link_triangles[link_vertex / 2][link_vertex % 2] {
+                    Some(triangle) => self.costs.cost(navmesh.areas[triangle as usize]),
+                    None => self.costs.cost(DEFAULT_AREA),
+                }
+            }
+            // Edge of one or two triangles, the cheapest one is used.
+            (None, None) => navmesh.vertex_triangles[from]
+                .iter()
+                .filter(|t| navmesh.vertex_triangles[to].contains(t))
+                .map(|t| self.costs.cost(navmesh.areas[*t as usize]))
+                .fold(f32::INFINITY, f32::min),
+        };
+
+        if multiplier.is_finite() {
+            Some(from_position.metric_distance(to_position) * multiplier)
+        } else {
+            None
+        }
+    }
+
+    fn heuristic_scale(&self) -> f32 {
+        self.costs.min_cost()
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Edge {
+    a: u32,
+    b: u32,
+}
+
+impl PartialEq for Edge {
+    fn eq(&self, other: &Self) -> bool {
+        // Direction-agnostic compare.
+        (self.a == other.a && self.b == other.b) || (self.a == other.b && self.b == other.a)
+    }
+}
+
+impl Eq for Edge {}
+
+impl Hash for Edge {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Direction-agnostic hash.
+        (self.a as u64 + self.b as u64).hash(state)
+    }
+}
+
+impl Default for Navmesh {
+    fn default() -> Self {
+        Self {
+            octree: Default::default(),
+            triangles: Default::default(),
+            areas: Default::default(),
+            pathfinder: Default::default(),
+            query_buffer: Default::default(),
+            links: Default::default(),
+            blocked_regions: Default::default(),
+            base_vertex_count: 0,
+            vertex_triangles: Default::default(),
+            link_triangles: Default::default(),
+            revision: 0,
+        }
+    }
+}
+
+impl Navmesh {
+    /// Creates new navigation mesh from given set of triangles and vertices. This is
+    /// low level method that allows to specify triangles and vertices directly. In
+    /// most cases you should use `from_mesh` method.
+    pub fn new(triangles: &[TriangleDefinition], vertices: &[Vector3<f32>]) -> Self {
+        // Build triangles for octree.
+        let raw_triangles = triangles
+            .iter()
+            .map(|t| {
+                [
+                    vertices[t[0] as usize],
+                    vertices[t[1] as usize],
+                    vertices[t[2] as usize],
+                ]
+            })
+            .collect::<Vec<[Vector3<f32>; 3]>>();
+
+        // Fill in pathfinder.
+        let mut pathfinder = PathFinder::new();
+        pathfinder.set_vertices(vertices.iter().map(|v| PathVertex::new(*v)).collect());
+
+        let mut edges = HashSet::new();
+        for triangle in triangles {
+            edges.insert(Edge {
+                a: triangle[0],
+                b: triangle[1],
+            });
+            edges.insert(Edge {
+                a: triangle[1],
+                b: triangle[2],
+            });
+            edges.insert(Edge {
+                a: triangle[2],
+                b: triangle[0],
+            });
+        }
+
+        for edge in edges {
+            pathfinder.link_bidirect(edge.a as usize, edge.b as usize);
+        }
+
+        Self {
+            triangles: triangles.to_vec(),
+            areas: vec![DEFAULT_AREA; triangles.len()],
+            octree: Octree::new(&raw_triangles, 32),
+            pathfinder,
+            query_buffer: Default::default(),
+            links: Default::default(),
+            blocked_regions: Default::default(),
+            base_vertex_count: vertices.len(),
+            vertex_triangles: vertex_triangles(triangles, vertices.len()),
+            link_triangles: Default::default(),
+            revision: 0,
+        }
+    }
+
+    /// Creates new navigation mesh (navmesh) from given mesh. It is most simple way to create complex
+    /// navigation mesh, it should be used in pair with model loading functionality - you can
+    /// load model from file and turn it into navigation mesh, or even build navigation mesh
+    /// from a model in existing scene. This method "eats" any kind of meshes with any amount
+    /// of surfaces - it joins all surfaces into single mesh and creates navmesh from it.
+    ///
+    /// Example:
+    /// ```
+    /// use rg3d::scene::Scene;
+    /// use rg3d::utils::navmesh::Navmesh;
+    ///
+    /// fn make_navmesh(scene: &Scene, navmesh_name: &str) -> Navmesh {
+    ///     // Find mesh node in existing scene and create navigation mesh from it.
+    ///     let navmesh_node_handle = scene.graph.find_by_name_from_root(navmesh_name);
+    ///     Navmesh::from_mesh(scene.graph[navmesh_node_handle].as_mesh())
+    /// }
+    /// ```
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        // Join surfaces into one simple mesh.
+        let mut builder = RawMeshBuilder::<RawVertex>::default();
+        let global_transform = mesh.global_transform();
+        for surface in mesh.surfaces() {
+            let shared_data = surface.data();
+            let shared_data = shared_data.read().unwrap();
+
+            let vertex_buffer = &shared_data.vertex_buffer;
+            for triangle in shared_data.geometry_buffer.iter() {
+                builder.insert(RawVertex::from(
+                    global_transform
+                        .transform_point(&Point3::from(
+                            vertex_buffer
+                                .get(triangle[0] as usize)
+                                .unwrap()
+                                .read_3_f32(VertexAttributeUsage::Position)
+                                .unwrap(),
+                        ))
+                        .coords,
+                ));
+                builder.insert(RawVertex::from(
+                    global_transform
+                        .transform_point(&Point3::from(
+                            vertex_buffer
+                                .get(triangle[1] as usize)
+                                .unwrap()
+                                .read_3_f32(VertexAttributeUsage::Position)
+                                .unwrap(),
+                        ))
+                        .coords,
+                ));
+                builder.insert(RawVertex::from(
+                    global_transform
+                        .transform_point(&Point3::from(
+                            vertex_buffer
+                                .get(triangle[2] as usize)
+                                .unwrap()
+                                .read_3_f32(VertexAttributeUsage::Position)
+                                .unwrap(),
+                        ))
+                        .coords,
+                ));
+            }
+        }
+
+        let mesh = builder.build();
+
+        Navmesh::new(
+            &mesh.triangles,
+            &mesh
+                .vertices
+                .into_iter()
+                .map(|v| Vector3::new(v.x, v.y, v.z))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Searches closest graph vertex to given point. Returns Some(index), or None
+    /// if navmesh was empty.
+    pub fn query_closest(&mut self, point: Vector3<f32>) -> Option<usize> {
+        self.octree.point_query(point, &mut self.query_buffer);
+        if self.query_buffer.is_empty() {
+            // TODO: This is not optimal. It is better to trace ray down from given point
+            //  and pick closest triangle.
+            math::get_closest_point(self.pathfinder.vertices(), point)
+        } else {
+            math::get_closest_point_triangles(
+                self.pathfinder.vertices(),
+                &self.triangles,
+                &self.query_buffer,
+                point,
+            )
+        }
+    }
+
+    /// Returns reference to array of triangles.
+    pub fn triangles(&self) -> &[TriangleDefinition] {
+        &self.triangles
+    }
+
+    /// Returns reference to array of vertices.
+    pub fn vertices(&self) -> &[PathVertex] {
+        self.pathfinder.vertices()
+    }
+
+    /// Returns shared reference to inner octree.
+    pub fn octree(&self) -> &Octree {
+        &self.octree
+    }
+
+    /// Tries to build path using indices of begin and end points. Every area has the same cost,
+    /// off-mesh links are used and blocked regions are avoided.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use rg3d::utils::navmesh::Navmesh;
+    /// use rg3d::core::algebra::Vector3;
+    /// use rg3d::utils::astar::{PathKind, PathError};
+    ///
+    /// fn find_path(navmesh: &mut Navmesh, begin: Vector3<f32>, end: Vector3<f32>, path: &mut Vec<Vector3<f32>>) -> Result<PathKind, PathError> {
+    ///     if let Some(begin_index) = navmesh.query_closest(begin) {
+    ///         if let Some(end_index) = navmesh.query_closest(end) {
+    ///             return navmesh.build_path(begin_index, end_index, path);
+    ///         }
+    ///     }
+    ///     Ok(PathKind::Empty)
+    /// }
+    /// ```
+    pub fn build_path(
+        &mut self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<Vector3<f32>>,
+    ) -> Result<PathKind, PathError> {
+        self.build_path_with_costs(from, to, path, &AreaCosts::default())
+    }
+
+    /// Tries to build path using indices of begin and end points and given area costs. Path goes
+    /// through areas with the lowest total cost, impassable areas and blocked regions are
+    /// avoided.
+    pub fn build_path_with_costs(
+        &mut self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<Vector3<f32>>,
+        costs: &AreaCosts,
+    ) -> Result<PathKind, PathError> {
+        let mut pathfinder = std::mem::take(&mut self.pathfinder);
+        let result = pathfinder.build_with_costs(
+            from,
+            to,
+            path,
+            &NavmeshCosts {
+                navmesh: self,
+                costs,
+            },
+        );
+        self.pathfinder = pathfinder;
+        result
+    }
+
+    /// Straightens given path using funnel algorithm (also known as "string pulling"). Path
+    /// must be built by `build_path` or `build_path_with_costs`, it could be in any direction.
+    /// Triangles around the path form a corridor and the shortest path inside of the corridor
+    /// is found, so corners of the result are lying on vertices of the navmesh. Off-mesh links
+    /// are kept as is, parts of the path that cannot be straightened without crossing a blocked
+    /// region are left unchanged too.
+    pub fn smooth_path(&self, path: &mut Vec<Vector3<f32>>, costs: &AreaCosts) {
+        if self.triangles.is_empty() || path.len() < 3 {
+            return;
+        }
+
+        let mut result = Vec::with_capacity(path.len());
+        let mut piece_start = 0;
+        for i in 0..path.len() {
+            let is_last = i + 1 == path.len();
+            if is_last || self.is_link(path[i], path[i + 1]) {
+                for point in self.smooth_piece(&path[piece_start..=i], costs) {
+                    if result.last() != Some(&point) {
+                        result.push(point);
+                    }
+                }
+                piece_start = i + 1;
+            }
+        }
+
+        *path = result;
+    }
+
+    fn smooth_piece(&self, piece: &[Vector3<f32>], costs: &AreaCosts) -> Vec<Vector3<f32>> {
+        if piece.len() < 3 {
+            return piece.to_vec();
+        }
+
+        let (begin, end) = (piece[0], piece[piece.len() - 1]);
+        let (begin_triangle, end_triangle) = match (self.triangle_at(begin), self.triangle_at(end))
+        {
+            (Some(begin_triangle), Some(end_triangle)) => (begin_triangle, end_triangle),
+            _ => return piece.to_vec(),
+        };
+
+        // Corridor can contain only triangles around the path. Path goes along edges of
+        // triangles, so one more ring of triangles is added to let the corridor to go
+        // straight through the triangles next to the path.
+        let mut around_path = vec![begin_triangle as u32, end_triangle as u32];
+        for point in piece[1..piece.len() - 1].iter() {
+            if let Some(vertex) = self.vertex_at(*point) {
+                around_path.extend_from_slice(&self.vertex_triangles[vertex]);
+            }
+        }
+        let mut allowed = around_path.clone();
+        for triangle in around_path {
+            for &vertex in self.triangles[triangle as usize].0.iter() {
+                allowed.extend_from_slice(&self.vertex_triangles[vertex as usize]);
+            }
+        }
+        allowed.sort_unstable();
+        allowed.dedup();
+        allowed.retain(|t| costs.is_passable(self.areas[*t as usize]));
+
+        let corridor =
+            match self.find_corridor(&allowed, (begin_triangle, begin), (end_triangle, end)) {
+                Some(corridor) => corridor,
+                None => return piece.to_vec(),
+            };
+
+        let mut portals = Vec::with_capacity(corridor.len() + 1);
+        portals.push((begin, begin));
+        let vertices = self.pathfinder.vertices();
+        for pair in corridor.windows(2) {
+            let (current, next) = (&self.triangles[pair[0]], &self.triangles[pair[1]]);
+            let mut shared = ArrayVec::<Vector3<f32>, 3>::new();
+            let mut opposite = None;
+            for &vertex in current.0.iter() {
+                let position = vertices[vertex as usize].position;
+                if next.0.contains(&vertex) {
+                    shared.push(position);
+                } else {
+                    opposite = Some(position);
+                }
+            }
+            if let (Some(opposite), [a, b]) = (opposite, shared.as_slice()) {
+                // Portal is (left, right) pair as seen from the current triangle.
+                if triangle_area_2d(opposite, *a, *b) < 0.0 {
+                    portals.push((*b, *a));
+                } else {
+                    portals.push((*a, *b));
+                }
+            } else {
+                return piece.to_vec();
+            }
+        }
+        portals.push((end, end));
+
+        let points = string_pull(&portals);
+
+        // Corridor is not always the straightest one, so remove corners that could be skipped.
+        let mut anchor = 0;
+        let mut straightened = vec![points[0]];
+        while anchor + 1 < points.len() {
+            let next = (anchor + 2..points.len())
+                .rev()
+                .find(|j| self.is_visible(points[anchor], points[*j], costs))
+                .unwrap_or(anchor + 1);
+            straightened.push(points[next]);
+            anchor = next;
+        }
+        let points = straightened;
+
+        if points
+            .windows(2)
+            .any(|pair| self.is_segment_blocked(pair[0], pair[1]))
+        {
+            piece.to_vec()
+        } else {
+            points
+        }
+    }
+
+    // Searches the shortest chain of adjacent triangles between triangles of given points
+    // using only allowed (sorted) triangles. Triangles are entered through middle points of
+    // their edges.
+    fn find_corridor(
+        &self,
+        allowed: &[u32],
+        (from, begin): (usize, Vector3<f32>),
+        (to, end): (usize, Vector3<f32>),
+    ) -> Option<Vec<usize>> {
+        let index_of = |triangle: usize| allowed.binary_search(&(triangle as u32)).ok();
+        let start = index_of(from)?;
+        let goal = index_of(to)?;
+        let vertices = self.pathfinder.vertices();
+
+        let mut entries = vec![begin; allowed.len()];
+        let mut distances = vec![f32::INFINITY; allowed.len()];
+        let mut parents = vec![None; allowed.len()];
+        let mut closed = vec![false; allowed.len()];
+        distances[start] = 0.0;
+
+        loop {
+            let mut current = None;
+            let mut lowest = f32::INFINITY;
+            for (i, distance) in distances.iter().enumerate() {
+                if !closed[i] && *distance < lowest {
+                    current = Some(i);
+                    lowest = *distance;
+                }
+            }
+            let current = current?;
+            if current == goal {
+                break;
+            }
+            closed[current] = true;
+
+            let triangle = &self.triangles[allowed[current] as usize];
+            for k in 0..3 {
+                let (a, b) = (triangle[k] as usize, triangle[(k + 1) % 3] as usize);
+                for neighbour in self.vertex_triangles[a].iter() {
+                    if *neighbour == allowed[current]
+                        || !self.vertex_triangles[b].contains(neighbour)
+                    {
+                        continue;
+                    }
+                    if let Some(next) = index_of(*neighbour as usize) {
+                        let entry = (vertices[a].position + vertices[b].position).scale(0.5);
+                        let mut distance = lowest + entries[current].metric_distance(&entry);
+                        if next == goal {
+                            distance += entry.metric_distance(&end);
+                        }
+                        if !closed[next] && distance < distances[next] {
+                            distances[next] = distance;
+                            parents[next] = Some(current);
+                            entries[next] = entry;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut corridor = vec![allowed[goal] as usize];
+        let mut current = goal;
+        while let Some(parent) = parents[current] {
+            corridor.push(allowed[parent] as usize);
+            current = parent;
+        }
+        corridor.reverse();
+        Some(corridor)
+    }
+
+    // Checks if straight segment between given points lies on passable triangles of the navmesh.
+    fn is_visible(&self, begin: Vector3<f32>, end: Vector3<f32>, costs: &AreaCosts) -> bool {
+        let start_triangles = match self.vertex_at(begin) {
+            Some(vertex) => self.vertex_triangles[vertex].clone(),
+            None => self
+                .triangle_at(begin)
+                .map(|t| t as u32)
+                .into_iter()
+                .collect(),
+        };
+        start_triangles
+            .into_iter()
+            .any(|triangle| self.walk_segment(triangle as usize, begin, end, costs))
+    }
+
+    // Walks from given triangle along a segment through edges of triangles until a triangle
+    // with the end of the segment is found.
+    fn walk_segment(
+        &self,
+        mut triangle: usize,
+        begin: Vector3<f32>,
+        end: Vector3<f32>,
+        costs: &AreaCosts,
+    ) -> bool {
+        const EPSILON: f32 = 1.0e-5;
+
+        let vertices = self.pathfinder.vertices();
+        let mut previous = None;
+        for _ in 0..self.triangles.len() {
+            if !costs.is_passable(self.areas[triangle]) {
+                return false;
+            }
+
+            let definition = &self.triangles[triangle];
+            let positions = [
+                vertices[definition[0] as usize].position,
+                vertices[definition[1] as usize].position,
+                vertices[definition[2] as usize].position,
+            ];
+
+            // Check if end point is inside of the triangle on XZ plane.
+            let signs = [
+                triangle_area_2d(positions[0], positions[1], end),
+                triangle_area_2d(positions[1], positions[2], end),
+                triangle_area_2d(positions[2], positions[0], end),
+            ];
+            if !(signs.iter().any(|s| *s < -EPSILON) && signs.iter().any(|s| *s > EPSILON)) {
+                return true;
+            }
+
+            // Find the farthest edge crossed by the segment, it is the exit edge.
+            let mut exit = None;
+            let mut farthest = -EPSILON;
+            for k in 0..3 {
+                let (a, b) = (definition[k] as usize, definition[(k + 1) % 3] as usize);
+                let (pa, pb) = (vertices[a].position, vertices[b].position);
+                if triangle_area_2d(begin, end, pa) * triangle_area_2d(begin, end, pb) > EPSILON {
+                    continue;
+                }
+                let (from_begin, from_end) = (
+                    triangle_area_2d(pa, pb, begin),
+                    triangle_area_2d(pa, pb, end),
+                );
+                if (from_begin - from_end).abs() <= EPSILON {
+                    continue;
+                }
+                let t = from_begin / (from_begin - from_end);
+                if t > farthest {
+                    let neighbour = self.vertex_triangles[a].iter().find(|n| {
+                        **n as usize != triangle
+                            && Some(**n as usize) != previous
+                            && self.vertex_triangles[b].contains(n)
+                    });
+                    if let Some(neighbour) = neighbour {
+                        farthest = t;
+                        exit = Some(*neighbour as usize);
+                    }
+                }
+            }
+
+            match exit {
+                Some(next) => {
+                    previous = Some(triangle);
+                    triangle = next;
+                }
+                None => return false,
+            }
+        }
+
+        false
+    }
+
+    // Returns index of a triangle right below given point.
+    fn triangle_at(&self, point: Vector3<f32>) -> Option<usize> {
+        self.ray_cast(Ray::new(
+            point + Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, -10.0, 0.0),
+        ))
+        .map(|(_, index, _)| index)
+    }
+
+    // Returns index of a vertex of triangles at given position.
+    fn vertex_at(&self, point: Vector3<f32>) -> Option<usize> {
+        let vertices = &self.pathfinder.vertices()[..self.base_vertex_count];
+        let mut buffer = Vec::new();
+        self.octree.point_query(point, &mut buffer);
+        buffer
+            .iter()
+            .flat_map(|t| self.triangles[*t as usize].0.iter())
+            .map(|v| *v as usize)
+            .find(|v| vertices[*v].position == point)
+            .or_else(|| vertices.iter().position(|v| v.position == point))
+    }
+
+    /// Returns area type of triangle at given index.
+    pub fn triangle_area(&self, index: usize) -> u8 {
+        self.areas[index]
+    }
+
+    /// Sets area type of triangle at given index.
+    pub fn set_triangle_area(&mut self, index: usize, area: u8) {
+        self.areas[index] = area;
+        self.revision += 1;
+    }
+
+    /// Sets area type of every triangle which center lies inside of given bounds.
+    pub fn set_area_in_bounds(&mut self, bounds: &AxisAlignedBoundingBox, area: u8) {
+        let vertices = self.pathfinder.vertices();
+        for (triangle, triangle_area) in self.triangles.iter().zip(self.areas.iter_mut()) {
+            let center = (vertices[triangle[0] as usize].position
+                + vertices[triangle[1] as usize].position
+                + vertices[triangle[2] as usize].position)
+                .scale(1.0 / 3.0);
+            if bounds.is_contains_point(center) {
+                *triangle_area = area;
+            }
+        }
+        self.revision += 1;
+    }
+
+    /// Returns area types of every triangle.
+    pub fn areas(&self) -> &[u8] {
+        &self.areas
+    }
+
+    /// Adds new off-mesh link and returns its index.
+    pub fn add_off_mesh_link(&mut self, link: OffMeshLink) -> usize {
+        self.links.push(link);
+        self.rebuild_links();
+        self.links.len() - 1
+    }
+
+    /// Removes off-mesh link at given index, indices of next links are shifted.
+    pub fn remove_off_mesh_link(&mut self, index: usize) -> OffMeshLink {
+        let link = self.links.remove(index);
+        self.rebuild_links();
+        link
+    }
+
+    /// Returns reference to array of off-mesh links.
+    pub fn off_mesh_links(&self) -> &[OffMeshLink] {
+        &self.links
+    }
+
+    /// Blocks given region until it is removed and returns its handle.
+    pub fn add_blocked_region(&mut self, bounds: AxisAlignedBoundingBox) -> Handle<BlockedRegion> {
+        self.revision += 1;
+        self.blocked_regions.spawn(BlockedRegion { bounds })
+    }
+
+    /// Moves blocked region to new bounds.
+    pub fn set_blocked_region_bounds(
+        &mut self,
+        handle: Handle<BlockedRegion>,
+        bounds: AxisAlignedBoundingBox,
+    ) {
+        self.blocked_regions[handle].bounds = bounds;
+        self.revision += 1;
+    }
+
+    /// Unblocks region with given handle.
+    pub fn remove_blocked_region(&mut self, handle: Handle<BlockedRegion>) {
+        self.blocked_regions.free(handle);
+        self.revision += 1;
+    }
+
+    /// Returns an iterator over every blocked region.
+    pub fn blocked_regions(&self) -> impl Iterator<Item = &BlockedRegion> {
+        self.blocked_regions.iter()
+    }
+
+    /// Returns true if a segment between given points intersects any blocked region.
+    pub fn is_segment_blocked(&self, begin: Vector3<f32>, end: Vector3<f32>) -> bool {
+        self.blocked_regions
+            .iter()
+            .any(|region| region.is_segment_blocked(begin, end))
+    }
+
+    fn is_link(&self, a: Vector3<f32>, b: Vector3<f32>) -> bool {
+        self.links
+            .iter()
+            .any(|link| (link.start == a && link.end == b) || (link.start == b && link.end == a))
+    }
+
+    /// Returns a number that is changed every time when areas, off-mesh links or blocked regions
+    /// are changed. It could be used to find out if paths should be rebuilt.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    // Off-mesh links are stored as pairs of extra vertices after vertices of triangles, every
+    // extra vertex is linked with vertices of closest triangle.
+    fn rebuild_links(&mut self) {
+        self.pathfinder.truncate(self.base_vertex_count);
+        self.link_triangles.clear();
+        for i in 0..self.links.len() {
+            let (start, end) = (self.links[i].start, self.links[i].end);
+            let mut ends = [0; 2];
+            let mut triangles = [None; 2];
+            for (k, point) in [start, end].iter().enumerate() {
+                let vertex = self.pathfinder.add_vertex(PathVertex::new(*point));
+                ends[k] = vertex;
+                if let Some((_, index, triangle)) = self.ray_cast(Ray::new(
+                    point + Vector3::new(0.0, 1.0, 0.0),
+                    Vector3::new(0.0, -10.0, 0.0),
+                )) {
+                    triangles[k] = Some(index as u32);
+                    for &neighbour in triangle.0.iter() {
+                        self.pathfinder.link_bidirect(vertex, neighbour as usize);
+                    }
+                } else if let Some(closest) = math::get_closest_point(
+                    &self.pathfinder.vertices()[..self.base_vertex_count],
+                    *point,
+                ) {
+                    self.pathfinder.link_bidirect(vertex, closest);
+                }
+            }
+            self.pathfinder.link_unidirect(ends[0], ends[1]);
+            if self.links[i].bidirectional {
+                self.pathfinder.link_unidirect(ends[1], ends[0]);
+            }
+            self.link_triangles.push(triangles);
+        }
+        self.revision += 1;
+    }
+
+    /// Tries to pick a triangle by given ray.
+    pub fn ray_cast(&self, ray: Ray) -> Option<(Vector3<f32>, usize, TriangleDefinition)> {
+        let mut result = None;
+        self.visit_ray_hits(&ray, |intersection, index, triangle| {
+            result = Some((intersection, index, triangle));
+            false
+        });
+        result
+    }
+
+    // Calls given closure for every triangle intersected by the ray (in octree order, not in
+    // order of distance) until the closure returns `false`.
+    fn visit_ray_hits<F>(&self, ray: &Ray, mut func: F)
+    where
+        F: FnMut(Vector3<f32>, usize, TriangleDefinition) -> bool,
+    {
+        let mut buffer = ArrayVec::<Handle<OctreeNode>, 128>::new();
+
+        self.octree.ray_query_static(ray, &mut buffer);
+
+        for node in buffer.into_iter() {
+            if let OctreeNode::Leaf { indices, .. } = self.octree.node(node) {
+                for &index in indices {
+                    let triangle = self.triangles[index as usize].clone();
+                    let a = self.pathfinder.vertices()[triangle[0] as usize].position;
+                    let b = self.pathfinder.vertices()[triangle[1] as usize].position;
+                    let c = self.pathfinder.vertices()[triangle[2] as usize].position;
+
+                    if let Some(intersection) = ray.triangle_intersection_point(&[a, b, c]) {
+                        if !func(intersection, index as usize, triangle) {
+                            return;
+                        }
+                    }
+                }
+            } else {
+                unreachable!()
+            }
+        }
+    }
+}
+
+/// Navmesh agent is a "pathfinding unit" that performs navigation on a mesh. It is designed to
+/// cover most of simple use cases when you need to build and follow some path from point A to point B.
+pub struct NavmeshAgent {
+    path: Vec<Vector3<f32>>,
+    current: u32,
+    position: Vector3<f32>,
+    last_warp_position: Vector3<f32>,
+    target: Vector3<f32>,
+    last_target_position: Vector3<f32>,
+    recalculation_threshold: f32,
+    speed: f32,
+    path_dirty: bool,
+    area_costs: AreaCosts,
+    navmesh_revision: u64,
+    radius: f32,
+    acceleration: f32,
+    velocity: Vector3<f32>,
+    max_climb: f32,
+}
+
+impl Visit for NavmeshAgent {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.path.visit("Path", visitor)?;
+        self.current.visit("Current", visitor)?;
+        self.position.visit("Position", visitor)?;
+        self.last_warp_position.visit("LastWarpPosition", visitor)?;
+        self.target.visit("Target", visitor)?;
+        self.last_target_position
+            .visit("LastTargetPosition", visitor)?;
+        self.recalculation_threshold
+            .visit("RecalculationThreshold", visitor)?;
+        self.speed.visit("Speed", visitor)?;
+        self.path_dirty.visit("PathDirty", visitor)?;
+        let _ = self.area_costs.visit("AreaCosts", visitor); // Backward compatibility.
+        let _ = self.radius.visit("Radius", visitor); // Backward compatibility.
+        let _ = self.acceleration.visit("Acceleration", visitor); // Backward compatibility.
+        let _ = self.velocity.visit("Velocity", visitor); // Backward compatibility.
+        let _ = self.max_climb.visit("MaxClimb", visitor); // Backward compatibility.
+
+        visitor.leave_region()
+    }
+}
+
+impl Default for NavmeshAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NavmeshAgent {
+    /// Creates new navigation mesh agent.
+    pub fn new() -> Self {
+        Self {
+            path: vec![],
+            current: 0,
+            position: Default::default(),
+            last_warp_position: Default::default(),
+            target: Default::default(),
+            last_target_position: Default::default(),
+            recalculation_threshold: 0.25,
+            speed: 1.5,
+            path_dirty: true,
+            area_costs: Default::default(),
+            navmesh_revision: 0,
+            radius: 0.4,
+            acceleration: 8.0,
+            velocity: Default::default(),
+            max_climb: 0.4,
+        }
+    }
+
+    /// Returns agent's position.
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    /// Returns agent's path that will be followed.
+    pub fn path(&self) -> &[Vector3<f32>] {
+        &self.path
+    }
+
+    /// Sets new speed of agent's movement.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// Returns current agent's movement speed.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Sets new area costs of the agent, path will be recalculated on next update.
+    pub fn set_area_costs(&mut self, area_costs: AreaCosts) {
+        self.area_costs = area_costs;
+        self.path_dirty = true;
+    }
+
+    /// Returns current area costs of the agent.
+    pub fn area_costs(&self) -> &AreaCosts {
+        &self.area_costs
+    }
+
+    /// Sets new radius of the agent. It is used by crowds to avoid collisions between agents.
+    pub fn set_radius(&mut self, radius: f32) {
+        self.radius = radius;
+    }
+
+    /// Returns current radius of the agent.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Sets new acceleration of the agent. It is used by crowds to limit changes of velocity.
+    pub fn set_acceleration(&mut self, acceleration: f32) {
+        self.acceleration = acceleration;
+    }
+
+    /// Returns current acceleration of the agent.
+    pub fn acceleration(&self) -> f32 {
+        self.acceleration
+    }
+
+    /// Returns current velocity of the agent. It is changed only when the agent is a part of
+    /// a crowd.
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.velocity
+    }
+
+    /// Sets maximal height (in meters) of a step that the agent is able to climb or descend.
+    /// It is used by crowds to keep agents on their level of a multi-level navmesh.
+    pub fn set_max_climb(&mut self, max_climb: f32) {
+        self.max_climb = max_climb;
+    }
+
+    /// Returns current maximal climb height of the agent.
+    pub fn max_climb(&self) -> f32 {
+        self.max_climb
+    }
+}
+
+fn closest_point_index_in_triangle_and_adjacent(
+    triangle: TriangleDefinition,
+    navmesh: &Navmesh,
+    to: Vector3<f32>,
+) -> Option<usize> {
+    let mut triangles = ArrayVec::<TriangleDefinition, 4>::new();
+    triangles.push(triangle);
+    math::get_closest_point_triangle_set(navmesh.pathfinder.vertices(), &triangles, to)
+}
+
+impl NavmeshAgent {
+    /// Calculates path from point A to point B. In most cases there is no need to use this method
+    /// directly, because `update` will call it anyway if target position has moved.
+    pub fn calculate_path(
+        &mut self,
+        navmesh: &mut Navmesh,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+    ) -> Result<PathKind, PathError> {
+        self.path.clear();
+
+        self.current = 0;
+
+        let (n_from, begin, from_triangle) = if let Some((point, index, triangle)) = navmesh
+            .ray_cast(Ray::new(
+                from + Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(0.0, -10.0, 0.0),
+            )) {
+            (
+                closest_point_index_in_triangle_and_adjacent(triangle, navmesh, to),
+                Some(point),
+                Some(index),
+            )
+        } else {
+            (navmesh.query_closest(from), None, None)
+        };
+
+        let (n_to, end, to_triangle) = if let Some((point, index, triangle)) =
+            navmesh.ray_cast(Ray::new(
+                to + Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(0.0, -10.0, 0.0),
+            )) {
+            (
+                closest_point_index_in_triangle_and_adjacent(triangle, navmesh, from),
+                Some(point),
+                Some(index),
+            )
+        } else {
+            (navmesh.query_closest(to), None, None)
+        };
+
+        if let (Some(from_triangle), Some(to_triangle)) = (from_triangle, to_triangle) {
+            if from_triangle == to_triangle
+                && self
+                    .area_costs
+                    .is_passable(navmesh.triangle_area(from_triangle))
+                && !navmesh.is_segment_blocked(from, to)
+            {
+                self.path.push(from);
+                self.path.push(to);
+
+                return Ok(PathKind::Full);
+            }
+        }
+
+        if let (Some(n_from), Some(n_to)) = (n_from, n_to) {
+            let result =
+                navmesh.build_path_with_costs(n_from, n_to, &mut self.path, &self.area_costs);
+
+            if let Some(end) = end {
+                if self.path.is_empty() {
+                    self.path.push(end);
+                } else {
+                    self.path.insert(0, end)
+                }
+            }
+
+            if let Some(begin) = begin {
+                self.path.push(begin);
+            }
+
+            self.path.reverse();
+
+            navmesh.smooth_path(&mut self.path, &self.area_costs);
+
+            result
+        } else {
+            Err(PathError::Custom("Empty navmesh!".to_owned()))
+        }
+    }
+
+    /// Performs single update tick that moves agent to the target along the path (which is automatically
+    /// recalculated if target's position has changed).
+    pub fn update(&mut self, dt: f32, navmesh: &mut Navmesh) -> Result<PathKind, PathError> {
+        self.update_path(navmesh)?;
+
+        if let Some(source) = self.path.get(self.current as usize) {
+            if let Some(destination) = self.path.get((self.current + 1) as usize) {
+                let ray = Ray::from_two_points(*source, *destination);
+                let d = ray.dir.try_normalize(f32::EPSILON).unwrap_or_default();
+                self.position += d.scale(self.speed * dt);
+                if ray.project_point(&self.position) >= 1.0 {
+                    self.current += 1;
+                }
+            }
+        }
+
+        Ok(PathKind::Full)
+    }
+
+    fn update_path(&mut self, navmesh: &mut Navmesh) -> Result<(), PathError> {
+        if self.path_dirty || self.navmesh_revision != navmesh.revision() {
+            self.calculate_path(navmesh, self.position, self.target)?;
+            self.path_dirty = false;
+            self.navmesh_revision = navmesh.revision();
+        }
+        Ok(())
+    }
+
+    /// Returns current steering target which in most cases next path point from which
+    /// agent is close to.
+    pub fn steering_target(&self) -> Option<Vector3<f32>> {
+        self.path
+            .get(self.current as usize + 1)
+            .or_else(|| self.path.last())
+            .cloned()
+    }
+
+    /// Sets new target for the agent.
+    pub fn set_target(&mut self, new_target: Vector3<f32>) {
+        if new_target.metric_distance(&self.last_target_position) >= self.recalculation_threshold {
+            self.path_dirty = true;
+            self.last_target_position = new_target;
+        }
+
+        self.target = new_target;
+    }
+
+    /// Returns current target of the agent.
+    pub fn target(&self) -> Vector3<f32> {
+        self.target
+    }
+
+    /// Sets new position of the agent.
+    pub fn set_position(&mut self, new_position: Vector3<f32>) {
+        if new_position.metric_distance(&self.last_warp_position) >= self.recalculation_threshold {
+            self.path_dirty = true;
+            self.last_warp_position = new_position;
+        }
+
+        self.position = new_position;
+    }
+}
+
+/// Allows you to build agent in declarative manner.
+pub struct NavmeshAgentBuilder {
+    position: Vector3<f32>,
+    target: Vector3<f32>,
+    recalculation_threshold: f32,
+    speed: f32,
+    area_costs: AreaCosts,
+    radius: f32,
+    acceleration: f32,
+    max_climb: f32,
+}
+
+impl Default for NavmeshAgentBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NavmeshAgentBuilder {
+    /// Creates new builder instance.
+    pub fn new() -> Self {
+        Self {
+            position: Default::default(),
+            target: Default::default(),
+            recalculation_threshold: 0.25,
+            speed: 1.5,
+            area_costs: Default::default(),
+            radius: 0.4,
+            acceleration: 8.0,
+            max_climb: 0.4,
+        }
+    }
+
+    /// Sets new desired position of the agent being built.
+    pub fn with_position(mut self, position: Vector3<f32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Sets new desired target of the agent being built.
+    pub fn with_target(mut self, position: Vector3<f32>) -> Self {
+        self.target = position;
+        self
+    }
+
+    /// Sets new desired recalculation threshold (in meters) of the agent being built.
+    pub fn with_recalculation_threshold(mut self, threshold: f32) -> Self {
+        self.recalculation_threshold = threshold;
+        self
+    }
+
+    /// Sets new desired movement speed of the agent being built.
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Sets new desired area costs of the agent being built.
+    pub fn with_area_costs(mut self, area_costs: AreaCosts) -> Self {
+        self.area_costs = area_costs;
+        self
+    }
+
+    /// Sets new desired radius of the agent being built.
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Sets new desired acceleration of the agent being built.
+    pub fn with_acceleration(mut self, acceleration: f32) -> Self {
+        self.acceleration = acceleration;
+        self
+    }
+
+    /// Sets new desired maximal climb height of the agent being built.
+    pub fn with_max_climb(mut self, max_climb: f32) -> Self {
+        self.max_climb = max_climb;
+        self
+    }
+
+    /// Build the agent.
+    pub fn build(self) -> NavmeshAgent {
+        NavmeshAgent {
+            position: self.position,
+            last_warp_position: self.position,
+            target: self.target,
+            last_target_position: self.target,
+            recalculation_threshold: self.recalculation_threshold,
+            speed: self.speed,
+            area_costs: self.area_costs,
+            radius: self.radius,
+            acceleration: self.acceleration,
+            max_climb: self.max_climb,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::Vector3,
+            futures::executor::block_on,
+            math::{aabb::AxisAlignedBoundingBox, TriangleDefinition},
+            visitor::{Visit, Visitor},
+        },
+        utils::{
+            astar::PathKind,
+            navmesh::{AreaCosts, Navmesh, NavmeshAgentBuilder, OffMeshLink, MIN_AREA_COST},
+        },
+    };
+    use std::{env, path::PathBuf};
+
+    // Makes a grid of square cells of unit size in XZ plane.
+    fn add_grid(
+        vertices: &mut Vec<Vector3<f32>>,
+        triangles: &mut Vec<TriangleDefinition>,
+        origin: Vector3<f32>,
+        width: u32,
+        length: u32,
+    ) {
+        let base = vertices.len() as u32;
+        for z in 0..=length {
+            for x in 0..=width {
+                vertices.push(origin + Vector3::new(x as f32, 0.0, z as f32));
+            }
+        }
+        let index = |x: u32, z: u32| base + z * (width + 1) + x;
+        for z in 0..length {
+            for x in 0..width {
+                triangles.push(TriangleDefinition([
+                    index(x, z),
+                    index(x, z + 1),
+                    index(x + 1, z + 1),
+                ]));
+                triangles.push(TriangleDefinition([
+                    index(x, z),
+                    index(x + 1, z + 1),
+                    index(x + 1, z),
+                ]));
+            }
+        }
+    }
+
+    fn make_grid(width: u32, length: u32) -> Navmesh {
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        add_grid(
+            &mut vertices,
+            &mut triangles,
+            Vector3::default(),
+            width,
+            length,
+        );
+        Navmesh::new(&triangles, &vertices)
+    }
+
+    fn find_path(
+        navmesh: &mut Navmesh,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+        costs: &AreaCosts,
+    ) -> (PathKind, Vec<Vector3<f32>>) {
+        let from = navmesh.query_closest(from).unwrap();
+        let to = navmesh.query_closest(to).unwrap();
+        let mut path = Vec::new();
+        let kind = navmesh
+            .build_path_with_costs(from, to, &mut path, costs)
+            .unwrap();
+        (kind, path)
+    }
+
+    const MUD: u8 = 1;
+    const JUMP: u8 = 2;
+
+    #[test]
+    fn test_area_costs() {
+        let mut navmesh = make_grid(10, 3);
+        navmesh.set_area_in_bounds(
+            &AxisAlignedBoundingBox::from_points(&[
+                Vector3::new(-1.0, -1.0, -1.0),
+                Vector3::new(11.0, 1.0, 1.0),
+            ]),
+            MUD,
+        );
+        assert_eq!(navmesh.areas().iter().filter(|a| **a == MUD).count(), 20);
+
+        let (from, to) = (Vector3::new(0.0, 0.0, 0.0), Vector3::new(10.0, 0.0, 0.0));
+
+        // Shortest path goes straight through mud.
+        let (kind, path) = find_path(&mut navmesh, from, to, &AreaCosts::new());
+        assert_eq!(kind, PathKind::Full);
+        assert!(path.iter().all(|p| p.z == 0.0));
+        assert_eq!(path.len(), 11);
+
+        // Expensive mud is avoided, but borders of mud are fine.
+        let costs = AreaCosts::new().with_cost(MUD, 5.0);
+        let (kind, path) = find_path(&mut navmesh, from, to, &costs);
+        assert_eq!(kind, PathKind::Full);
+        assert!(path[1..path.len() - 1].iter().all(|p| p.z >= 1.0));
+
+        // Roads are preferred even if they are longer.
+        let (a, b) = (Vector3::new(0.0, 0.0, 2.0), Vector3::new(10.0, 0.0, 2.0));
+        let (_, path) = find_path(&mut navmesh, a, b, &AreaCosts::new());
+        assert!(path.iter().all(|p| p.z == 2.0));
+        let costs = AreaCosts::new().with_cost(MUD, 0.5);
+        let (_, path) = find_path(&mut navmesh, a, b, &costs);
+        assert!(path.iter().filter(|p| p.z <= 1.0).count() >= 9);
+
+        // Non-positive costs are clamped.
+        for cost in [0.0, -1.0, f32::NAN] {
+            assert_eq!(
+                AreaCosts::new().with_cost(MUD, cost).cost(MUD),
+                MIN_AREA_COST
+            );
+        }
+
+        let mut costs = AreaCosts::new();
+        costs.exclude(MUD);
+        assert!(!costs.is_passable(MUD));
+        let (kind, _) = find_path(&mut navmesh, from, Vector3::new(5.0, 0.0, 0.0), &costs);
+        assert_eq!(kind, PathKind::Partial);
+    }
+
+    #[test]
+    fn test_off_mesh_links() {
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        add_grid(&mut vertices, &mut triangles, Vector3::default(), 4, 3);
+        add_grid(
+            &mut vertices,
+            &mut triangles,
+            Vector3::new(6.0, 0.0, 0.0),
+            4,
+            3,
+        );
+        let mut navmesh = Navmesh::new(&triangles, &vertices);
+
+        let (a, b) = (Vector3::new(0.0, 0.0, 0.0), Vector3::new(10.0, 0.0, 3.0));
+        let costs = AreaCosts::new();
+        assert_eq!(find_path(&mut navmesh, a, b, &costs).0, PathKind::Partial);
+
+        let link = OffMeshLink {
+            start: Vector3::new(3.5, 0.0, 1.5),
+            end: Vector3::new(6.5, 0.0, 1.5),
+            bidirectional: false,
+            area: JUMP,
+        };
+        let revision = navmesh.revision();
+        let index = navmesh.add_off_mesh_link(link.clone());
+        assert_ne!(navmesh.revision(), revision);
+        assert_eq!(navmesh.vertices().len(), vertices.len() + 2);
+
+        let (kind, path) = find_path(&mut navmesh, a, b, &costs);
+        assert_eq!(kind, PathKind::Full);
+        assert!(path.contains(&link.start) && path.contains(&link.end));
+        // The link is one-way.
+        assert_eq!(find_path(&mut navmesh, b, a, &costs).0, PathKind::Partial);
+
+        // Agents may be unable to use some links.
+        let mut no_jumps = AreaCosts::new();
+        no_jumps.exclude(JUMP);
+        assert_eq!(
+            find_path(&mut navmesh, a, b, &no_jumps).0,
+            PathKind::Partial
+        );
+
+        assert_eq!(navmesh.remove_off_mesh_link(index), link);
+        assert_eq!(navmesh.vertices().len(), vertices.len());
+        assert_eq!(find_path(&mut navmesh, a, b, &costs).0, PathKind::Partial);
+    }
+
+    #[test]
+    fn test_blocked_regions() {
+        let mut navmesh = make_grid(10, 5);
+        let (from, to) = (Vector3::new(0.0, 0.0, 0.0), Vector3::new(10.0, 0.0, 0.0));
+
+        let mut agent = NavmeshAgentBuilder::new()
+            .with_position(from)
+            .with_target(to)
+            .build();
+        agent.update(0.0, &mut navmesh).unwrap();
+        let initial_path = agent.path().to_vec();
+        assert!(initial_path.iter().all(|p| p.z < 2.0));
+
+        // Close the "door", paths go around it without rebuilding navmesh.
+        let door = AxisAlignedBoundingBox::from_points(&[
+            Vector3::new(4.5, -1.0, -1.0),
+            Vector3::new(5.5, 1.0, 3.5),
+        ]);
+        let handle = navmesh.add_blocked_region(door);
+        assert_eq!(navmesh.blocked_regions().count(), 1);
+        assert!(navmesh.is_segment_blocked(from, to));
+
+        let (kind, path) = find_path(&mut navmesh, from, to, &AreaCosts::new());
+        assert_eq!(kind, PathKind::Full);
+        assert!(path.iter().any(|p| p.z >= 4.0));
+        for pair in path.windows(2) {
+            assert!(!navmesh.is_segment_blocked(pair[0], pair[1]));
+        }
+
+        // Agents notice changes of the navmesh.
+        agent.update(0.0, &mut navmesh).unwrap();
+        assert_ne!(agent.path(), initial_path.as_slice());
+        assert!(agent.path().iter().any(|p| p.z > 3.5));
+        for pair in agent.path().windows(2) {
+            assert!(!navmesh.is_segment_blocked(pair[0], pair[1]));
+        }
+
+        // Block whole width of the navmesh.
+        let mut wall = door;
+        wall.max.z = 6.0;
+        navmesh.set_blocked_region_bounds(handle, wall);
+        assert_eq!(
+            find_path(&mut navmesh, from, to, &AreaCosts::new()).0,
+            PathKind::Partial
+        );
+
+        navmesh.remove_blocked_region(handle);
+        let (_, path) = find_path(&mut navmesh, from, to, &AreaCosts::new());
+        assert!(path.iter().all(|p| p.z == 0.0));
+    }
+
+    #[test]
+    fn test_path_smoothing() {
+        // L-shaped navmesh, 10x10 grid without cells at x < 8, z >= 2.
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        add_grid(&mut vertices, &mut triangles, Vector3::default(), 10, 10);
+        let triangles = triangles
+            .into_iter()
+            .filter(|t| {
+                let min_x =
+                    t.0.iter()
+                        .map(|v| vertices[*v as usize].x)
+                        .fold(10.0, f32::min);
+                let min_z =
+                    t.0.iter()
+                        .map(|v| vertices[*v as usize].z)
+                        .fold(10.0, f32::min);
+                !(min_x < 8.0 && min_z >= 2.0)
+            })
+            .collect::<Vec<_>>();
+        let mut navmesh = Navmesh::new(&triangles, &vertices);
+
+        // Straight line when nothing is in the way.
+        let (from, to) = (Vector3::new(0.2, 0.0, 0.3), Vector3::new(9.7, 0.0, 1.4));
+        let mut agent = NavmeshAgentBuilder::new()
+            .with_position(from)
+            .with_target(to)
+            .build();
+        agent.update(0.0, &mut navmesh).unwrap();
+        assert_eq!(agent.path(), &[from, to]);
+
+        // Path goes around the inner corner of the L.
+        let to = Vector3::new(9.1, 0.0, 9.3);
+        agent.set_target(to);
+        agent.update(0.0, &mut navmesh).unwrap();
+        assert_eq!(agent.path(), &[from, Vector3::new(8.0, 0.0, 2.0), to]);
+
+        // Raw vertex paths could be straightened too.
+        let (_, raw_path) = find_path(&mut navmesh, from, to, &AreaCosts::new());
+        assert!(raw_path.len() > 3);
+        let mut path = raw_path.clone();
+        navmesh.smooth_path(&mut path, &AreaCosts::new());
+        assert_eq!(
+            path,
+            vec![
+                raw_path[0],
+                Vector3::new(8.0, 0.0, 2.0),
+                raw_path[raw_path.len() - 1]
+            ]
+        );
+
+        // Ends of off-mesh links stay in place.
+        let link = OffMeshLink {
+            start: Vector3::new(3.0, 0.0, 1.5),
+            end: Vector3::new(8.5, 0.0, 8.0),
+            bidirectional: false,
+            area: JUMP,
+        };
+        navmesh.add_off_mesh_link(link.clone());
+        agent.update(0.0, &mut navmesh).unwrap();
+        assert_eq!(agent.path(), &[from, link.start, link.end, to]);
+    }
+
+    #[test]
+    fn test_navmesh_save_load() {
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        add_grid(&mut vertices, &mut triangles, Vector3::default(), 4, 3);
+        add_grid(
+            &mut vertices,
+            &mut triangles,
+            Vector3::new(6.0, 0.0, 0.0),
+            4,
+            3,
+        );
+        let mut navmesh = Navmesh::new(&triangles, &vertices);
+        navmesh.set_triangle_area(3, MUD);
+        navmesh.add_off_mesh_link(OffMeshLink {
+            start: Vector3::new(3.5, 0.0, 1.5),
+            end: Vector3::new(6.5, 0.0, 1.5),
+            bidirectional: true,
+            area: JUMP,
+        });
+        navmesh.add_blocked_region(AxisAlignedBoundingBox::from_points(&[
+            Vector3::new(1.5, -1.0, -1.0),
+            Vector3::new(2.5, 1.0, 1.5),
+        ]));
+
+        let path = {
+            let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+            let root = PathBuf::from(manifest_dir).join("test_output");
+            if !root.exists() {
+                std::fs::create_dir(&root).unwrap();
+            }
+            root.join("navmesh_save_load.bin")
+        };
+
+        let mut visitor = Visitor::new();
+        navmesh.visit("Navmesh", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+
+        let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+        let mut loaded = Navmesh::default();
+        loaded.visit("Navmesh", &mut visitor).unwrap();
+
+        assert_eq!(loaded.areas(), navmesh.areas());
+        assert_eq!(loaded.off_mesh_links(), navmesh.off_mesh_links());
+        assert_eq!(loaded.blocked_regions().count(), 1);
+        assert_eq!(loaded.vertices().len(), navmesh.vertices().len());
+        assert_eq!(loaded.base_vertex_count, navmesh.base_vertex_count);
+
+        let (a, b) = (Vector3::new(0.0, 0.0, 3.0), Vector3::new(10.0, 0.0, 0.0));
+        let costs = AreaCosts::new();
+        assert_eq!(
+            find_path(&mut loaded, a, b, &costs),
+            find_path(&mut navmesh, a, b, &costs)
+        );
+        assert_eq!(find_path(&mut loaded, b, a, &costs).0, PathKind::Full);
+    }
+}
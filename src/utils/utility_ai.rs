@@ -0,0 +1,426 @@
+//! Utility AI - decision making by scoring.
+//!
+//! Utility selector picks one of options (actions, goals, targets, etc.) by scoring each of
+//! them in current context. Score of an option is a product of its considerations, every
+//! consideration takes some input from the context (health, distance to enemy, amount of
+//! ammo, etc.) normalized to `[0; 1]` range and maps it through a response curve. Options
+//! with many considerations would have lower scores just because of multiplication of
+//! values less than one, so the product is compensated depending on amount of
+//! considerations.
+//!
+//! The selector works nicely together with other decision making tools, for example it
+//! could pick a goal for the GOAP planner (see [`crate::utils::goap`]) or a value that is
+//! written to a blackboard of a behavior tree.
+
+#![warn(missing_docs)]
+
+use crate::core::visitor::prelude::*;
+use std::fmt::{Debug, Formatter};
+
+/// Response curve maps normalized input value to a score. Every curve clamps both input and
+/// output values to `[0; 1]` range.
+#[derive(Copy, Clone, Debug, PartialEq, Visit)]
+pub enum ResponseCurve {
+    /// `slope * x + offset`
+    Linear {
+        /// Slope of the line.
+        slope: f32,
+        /// Offset of the line.
+        offset: f32,
+    },
+    /// `slope * (x - shift) ^ exponent + offset`
+    Polynomial {
+        /// Slope of the curve.
+        slope: f32,
+        /// Exponent of the curve.
+        exponent: f32,
+        /// Horizontal shift of the curve.
+        shift: f32,
+        /// Vertical offset of the curve.
+        offset: f32,
+    },
+    /// S-shaped curve `1 / (1 + e ^ (-steepness * (x - midpoint)))`
+    Logistic {
+        /// Steepness of the curve, negative values flip the curve.
+        steepness: f32,
+        /// Input value at which the curve is 0.5.
+        midpoint: f32,
+    },
+    /// Returns 1 if the input is greater or equal to the threshold, 0 otherwise.
+    Step {
+        /// Threshold of the step.
+        threshold: f32,
+    },
+}
+
+impl Default for ResponseCurve {
+    fn default() -> Self {
+        Self::Linear {
+            slope: 1.0,
+            offset: 0.0,
+        }
+    }
+}
+
+impl ResponseCurve {
+    /// Maps given input value to a score.
+    pub fn evaluate(&self, input: f32) -> f32 {
+        let x = input.clamp(0.0, 1.0);
+        let y = match *self {
+            ResponseCurve::Linear { slope, offset } => slope * x + offset,
+            ResponseCurve::Polynomial {
+                slope,
+                exponent,
+                shift,
+                offset,
+            } => slope * (x - shift).powf(exponent) + offset,
+            ResponseCurve::Logistic {
+                steepness,
+                midpoint,
+            } => 1.0 / (1.0 + (-steepness * (x - midpoint)).exp()),
+            ResponseCurve::Step { threshold } => {
+                if x >= threshold {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        };
+        if y.is_nan() {
+            0.0
+        } else {
+            y.clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Single factor of an option score, see module docs.
+pub struct Consideration<C> {
+    name: String,
+    input: Box<dyn Fn(&C) -> f32>,
+    curve: ResponseCurve,
+}
+
+impl<C> Debug for Consideration<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Consideration")
+            .field("name", &self.name)
+            .field("curve", &self.curve)
+            .finish()
+    }
+}
+
+impl<C> Consideration<C> {
+    /// Creates new consideration with given input function and response curve. Input
+    /// function must return values in `[0; 1]` range, other values are clamped.
+    pub fn new<F>(name: &str, curve: ResponseCurve, input: F) -> Self
+    where
+        F: Fn(&C) -> f32 + 'static,
+    {
+        Self {
+            name: name.to_owned(),
+            input: Box::new(input),
+            curve,
+        }
+    }
+
+    /// Returns name of the consideration.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns response curve of the consideration.
+    pub fn curve(&self) -> ResponseCurve {
+        self.curve
+    }
+
+    /// Sets new response curve of the consideration.
+    pub fn set_curve(&mut self, curve: ResponseCurve) {
+        self.curve = curve;
+    }
+
+    /// Calculates score of the consideration in given context.
+    pub fn score(&self, context: &C) -> f32 {
+        self.curve.evaluate((self.input)(context))
+    }
+}
+
+/// An option that could be selected by the utility selector.
+#[derive(Debug)]
+pub struct UtilityOption<T, C> {
+    value: T,
+    weight: f32,
+    considerations: Vec<Consideration<C>>,
+}
+
+impl<T, C> UtilityOption<T, C> {
+    /// Creates new option without considerations, such option always has score equal to
+    /// its weight.
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            weight: 1.0,
+            considerations: Default::default(),
+        }
+    }
+
+    /// Sets weight of the option, final score is multiplied by the weight.
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Adds new consideration to the option.
+    pub fn with_consideration(mut self, consideration: Consideration<C>) -> Self {
+        self.considerations.push(consideration);
+        self
+    }
+
+    /// Returns value of the option.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns weight of the option.
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    /// Returns considerations of the option.
+    pub fn considerations(&self) -> &[Consideration<C>] {
+        &self.considerations
+    }
+
+    /// Calculates score of the option in given context.
+    pub fn score(&self, context: &C) -> f32 {
+        let mut score = self.weight;
+        if self.considerations.is_empty() {
+            return score;
+        }
+
+        let modification_factor = 1.0 - 1.0 / self.considerations.len() as f32;
+        for consideration in self.considerations.iter() {
+            let value = consideration.score(context);
+            // Compensate multiplication of many values less than one.
+            let make_up = (1.0 - value) * modification_factor;
+            score *= value + make_up * value;
+            if score == 0.0 {
+                break;
+            }
+        }
+        score
+    }
+}
+
+/// See module docs.
+#[derive(Debug)]
+pub struct UtilitySelector<T, C> {
+    options: Vec<UtilityOption<T, C>>,
+}
+
+impl<T, C> Default for UtilitySelector<T, C> {
+    fn default() -> Self {
+        Self {
+            options: Default::default(),
+        }
+    }
+}
+
+impl<T, C> UtilitySelector<T, C> {
+    /// Creates new selector without options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds new option to the selector.
+    pub fn add_option(&mut self, option: UtilityOption<T, C>) {
+        self.options.push(option);
+    }
+
+    /// Adds new option and returns self, useful for chained initialization.
+    pub fn with_option(mut self, option: UtilityOption<T, C>) -> Self {
+        self.add_option(option);
+        self
+    }
+
+    /// Returns options of the selector.
+    pub fn options(&self) -> &[UtilityOption<T, C>] {
+        &self.options
+    }
+
+    /// Removes every option.
+    pub fn clear(&mut self) {
+        self.options.clear();
+    }
+
+    /// Calculates scores of every option in given context and returns pairs (option value,
+    /// score) sorted by score in descending order.
+    pub fn rank(&self, context: &C) -> Vec<(&T, f32)> {
+        let mut ranks = self
+            .options
+            .iter()
+            .map(|option| (&option.value, option.score(context)))
+            .collect::<Vec<_>>();
+        // Sort is stable, so options with equal scores keep order of addition.
+        ranks.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranks
+    }
+
+    /// Returns value of the option with highest score in given context, or `None` if there
+    /// are no options or every option has zero score.
+    pub fn select(&self, context: &C) -> Option<&T> {
+        self.rank(context)
+            .into_iter()
+            .next()
+            .filter(|(_, score)| *score > 0.0)
+            .map(|(value, _)| value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::utility_ai::{Consideration, ResponseCurve, UtilityOption, UtilitySelector};
+
+    struct Npc {
+        health: f32,
+        ammo: f32,
+        enemy_distance: f32,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Action {
+        Attack,
+        Heal,
+        Reload,
+        Idle,
+    }
+
+    fn make_selector() -> UtilitySelector<Action, Npc> {
+        UtilitySelector::new()
+            .with_option(
+                UtilityOption::new(Action::Attack)
+                    .with_consideration(Consideration::new(
+                        "HasAmmo",
+                        ResponseCurve::Step { threshold: 0.01 },
+                        |npc: &Npc| npc.ammo,
+                    ))
+                    .with_consideration(Consideration::new(
+                        "EnemyIsClose",
+                        ResponseCurve::Linear {
+                            slope: -1.0,
+                            offset: 1.0,
+                        },
+                        |npc: &Npc| npc.enemy_distance,
+                    )),
+            )
+            .with_option(
+                UtilityOption::new(Action::Heal).with_consideration(Consideration::new(
+                    "LowHealth",
+                    ResponseCurve::Logistic {
+                        steepness: -20.0,
+                        midpoint: 0.3,
+                    },
+                    |npc: &Npc| npc.health,
+                )),
+            )
+            .with_option(
+                UtilityOption::new(Action::Reload)
+                    .with_weight(0.8)
+                    .with_consideration(Consideration::new(
+                        "LowAmmo",
+                        ResponseCurve::Polynomial {
+                            slope: 1.0,
+                            exponent: 2.0,
+                            shift: 1.0,
+                            offset: 0.0,
+                        },
+                        |npc: &Npc| npc.ammo,
+                    )),
+            )
+            .with_option(UtilityOption::new(Action::Idle).with_weight(0.1))
+    }
+
+    #[test]
+    fn test_response_curves() {
+        let linear = ResponseCurve::default();
+        assert_eq!(linear.evaluate(0.25), 0.25);
+        assert_eq!(linear.evaluate(2.0), 1.0);
+        assert_eq!(linear.evaluate(-1.0), 0.0);
+
+        let logistic = ResponseCurve::Logistic {
+            steepness: 10.0,
+            midpoint: 0.5,
+        };
+        assert!((logistic.evaluate(0.5) - 0.5).abs() < 1.0e-6);
+        assert!(logistic.evaluate(0.9) > 0.95);
+        assert!(logistic.evaluate(0.1) < 0.05);
+
+        let step = ResponseCurve::Step { threshold: 0.5 };
+        assert_eq!(step.evaluate(0.49), 0.0);
+        assert_eq!(step.evaluate(0.5), 1.0);
+
+        let quadratic = ResponseCurve::Polynomial {
+            slope: 1.0,
+            exponent: 2.0,
+            shift: 0.0,
+            offset: 0.0,
+        };
+        assert_eq!(quadratic.evaluate(0.5), 0.25);
+    }
+
+    #[test]
+    fn test_compensation() {
+        let consideration = || Consideration::new("Half", ResponseCurve::default(), |x: &f32| *x);
+
+        let single = UtilityOption::<(), f32>::new(()).with_consideration(consideration());
+        let triple = UtilityOption::<(), f32>::new(())
+            .with_consideration(consideration())
+            .with_consideration(consideration())
+            .with_consideration(consideration());
+
+        assert_eq!(single.score(&0.5), 0.5);
+        // Without compensation it would be 0.125.
+        assert!(triple.score(&0.5) > 0.25);
+        assert!(triple.score(&0.5) < 0.5);
+        assert_eq!(triple.score(&1.0), 1.0);
+        assert_eq!(triple.score(&0.0), 0.0);
+    }
+
+    #[test]
+    fn test_selection() {
+        let selector = make_selector();
+
+        let npc = Npc {
+            health: 1.0,
+            ammo: 1.0,
+            enemy_distance: 0.1,
+        };
+        assert_eq!(selector.select(&npc), Some(&Action::Attack));
+
+        let npc = Npc {
+            health: 0.1,
+            ammo: 1.0,
+            enemy_distance: 0.5,
+        };
+        assert_eq!(selector.select(&npc), Some(&Action::Heal));
+
+        let npc = Npc {
+            health: 1.0,
+            ammo: 0.0,
+            enemy_distance: 0.1,
+        };
+        assert_eq!(selector.select(&npc), Some(&Action::Reload));
+
+        let npc = Npc {
+            health: 1.0,
+            ammo: 1.0,
+            enemy_distance: 1.0,
+        };
+        let ranks = selector.rank(&npc);
+        assert_eq!(ranks.len(), 4);
+        assert_eq!(*ranks[0].0, Action::Idle);
+        assert!(ranks.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        assert!(UtilitySelector::<Action, Npc>::new().select(&npc).is_none());
+    }
+}